@@ -6,11 +6,21 @@
 
 use std::borrow::Cow;
 
-use directory::Directory;
+use directory::{
+    backend::internal::{lookup::DirectoryStore, manage::ManageDirectory},
+    Directory, QueryBy,
+};
+use store::{
+    write::{Bincode, DirectoryClass, ValueClass},
+    ValueKey,
+};
 use utils::config::{utils::AsKey, Config};
 
 use crate::{
-    config::smtp::session::AddressMapping,
+    config::smtp::{
+        list::{ListCommand, ListSettings, MailingList},
+        session::AddressMapping,
+    },
     expr::{
         functions::ResolveVariable, if_block::IfBlock, tokenizer::TokenMap, Variable, V_RECIPIENT,
     },
@@ -53,7 +63,70 @@ impl Core {
         Ok(vec![])
     }
 
+    pub async fn mailing_list(
+        &self,
+        address: &str,
+    ) -> directory::Result<Option<(MailingList, ListCommand)>> {
+        if let Some(list) = self.mailing_list_by_address(address).await? {
+            return Ok(Some((list, ListCommand::Post)));
+        }
+
+        // Command addresses use the list's local part followed by '+command'
+        if let Some((list_address, command)) =
+            address.rsplit_once('@').and_then(|(local_part, domain)| {
+                let (local_part, command) = local_part.split_once('+')?;
+                Some((
+                    format!("{local_part}@{domain}"),
+                    ListCommand::parse(command)?,
+                ))
+            })
+        {
+            if let Some(list) = self.mailing_list_by_address(&list_address).await? {
+                return Ok(Some((list, command)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub async fn mailing_list_by_id(&self, list_id: u32) -> directory::Result<Option<MailingList>> {
+        // Only list principals with stored settings are managed by the list manager
+        if let Some(settings) = self
+            .storage
+            .data
+            .get_value::<Bincode<ListSettings>>(ValueKey::from(ValueClass::Directory(
+                DirectoryClass::ListSettings(list_id),
+            )))
+            .await?
+        {
+            Ok(self
+                .storage
+                .data
+                .query(QueryBy::Id(list_id), false)
+                .await?
+                .and_then(|principal| MailingList::new(principal, settings.inner)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn mailing_list_by_address(
+        &self,
+        address: &str,
+    ) -> directory::Result<Option<MailingList>> {
+        if let Some(list_id) = self.storage.data.get_list_id(address).await? {
+            self.mailing_list_by_id(list_id).await
+        } else {
+            Ok(None)
+        }
+    }
+
     pub async fn rcpt(&self, directory: &Directory, email: &str) -> directory::Result<bool> {
+        // Mailing list and list command addresses are always accepted
+        if self.mailing_list(email).await?.is_some() {
+            return Ok(true);
+        }
+
         // Expand subaddress
         let mut address = self
             .smtp
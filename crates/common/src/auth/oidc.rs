@@ -0,0 +1,723 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use directory::{
+    backend::internal::manage::ManageDirectory, Directory, DirectoryError, DirectoryInner,
+    ManagementError, Principal, QueryBy, Type,
+};
+use mail_send::Credentials;
+use parking_lot::Mutex;
+use ring::signature::{self, RsaParameters, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde_json::{Map, Value};
+use utils::config::Config;
+
+use crate::Core;
+
+// Minimum time between forced JWKS refreshes caused by unknown key ids
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(30);
+
+pub type Claims = Map<String, Value>;
+
+#[derive(Debug)]
+pub struct OidcValidator {
+    pub method: ValidationMethod,
+    pub issuer: Option<String>,
+    pub audiences: Vec<String>,
+    pub leeway: u64,
+    pub timeout: Duration,
+    pub tls_allow_invalid_certs: bool,
+    pub claim_username: String,
+    pub claim_email: String,
+    pub claim_name: String,
+    pub trust_unverified_email: bool,
+    pub auto_provision: bool,
+}
+
+#[derive(Debug)]
+pub enum ValidationMethod {
+    Jwks {
+        source: JwksSource,
+        ttl: Duration,
+        cache: Mutex<Option<(Instant, Arc<Vec<Jwk>>)>>,
+    },
+    Introspect {
+        url: String,
+        authorization: Option<String>,
+    },
+}
+
+#[derive(Debug)]
+pub enum JwksSource {
+    Url(String),
+    File(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+pub struct Jwk {
+    pub kid: Option<String>,
+    pub key: JwkKey,
+}
+
+#[derive(Debug, Clone)]
+pub enum JwkKey {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    P256(Vec<u8>),
+    P384(Vec<u8>),
+    Ed25519(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Rs256,
+    Rs384,
+    Rs512,
+    Ps256,
+    Ps384,
+    Ps512,
+    Es256,
+    Es384,
+    EdDsa,
+}
+
+impl OidcValidator {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        let method = if let Some(url) = config.value("authentication.oidc.jwks.url") {
+            let url = url.to_string();
+            ValidationMethod::Jwks {
+                source: JwksSource::Url(url),
+                ttl: config
+                    .property_or_default("authentication.oidc.jwks.cache", "1h")
+                    .unwrap_or_else(|| Duration::from_secs(3600)),
+                cache: Mutex::new(None),
+            }
+        } else if let Some(path) = config.value("authentication.oidc.jwks.path") {
+            let path = PathBuf::from(path);
+            ValidationMethod::Jwks {
+                source: JwksSource::File(path),
+                ttl: config
+                    .property_or_default("authentication.oidc.jwks.cache", "1h")
+                    .unwrap_or_else(|| Duration::from_secs(3600)),
+                cache: Mutex::new(None),
+            }
+        } else if let Some(url) = config.value("authentication.oidc.introspect.url") {
+            let url = url.to_string();
+            let authorization = if let (Some(client_id), Some(secret)) = (
+                config.value("authentication.oidc.introspect.client-id"),
+                config.value("authentication.oidc.introspect.client-secret"),
+            ) {
+                format!(
+                    "Basic {}",
+                    STANDARD.encode(format!("{}:{}", client_id, secret))
+                )
+                .into()
+            } else {
+                config
+                    .value("authentication.oidc.introspect.token")
+                    .map(|token| format!("Bearer {token}"))
+            };
+
+            ValidationMethod::Introspect { url, authorization }
+        } else {
+            return None;
+        };
+
+        let issuer = config
+            .value("authentication.oidc.issuer")
+            .map(|issuer| issuer.to_string());
+        let audiences = config
+            .values("authentication.oidc.audience")
+            .map(|(_, v)| v.to_string())
+            .collect::<Vec<_>>();
+
+        // Any provider could otherwise sign tokens accepted by this server
+        if matches!(method, ValidationMethod::Jwks { .. }) {
+            if issuer.is_none() {
+                config.new_build_error(
+                    "authentication.oidc.issuer",
+                    "An issuer is required when validating tokens with JWKS",
+                );
+                return None;
+            } else if audiences.is_empty() {
+                config.new_build_error(
+                    "authentication.oidc.audience",
+                    "An audience is required when validating tokens with JWKS",
+                );
+                return None;
+            }
+        }
+
+        Some(OidcValidator {
+            method,
+            issuer,
+            audiences,
+            leeway: config
+                .property_or_default::<Duration>("authentication.oidc.leeway", "1m")
+                .unwrap_or_else(|| Duration::from_secs(60))
+                .as_secs(),
+            timeout: config
+                .property_or_default("authentication.oidc.timeout", "10s")
+                .unwrap_or_else(|| Duration::from_secs(10)),
+            tls_allow_invalid_certs: config
+                .property_or_default("authentication.oidc.allow-invalid-certs", "false")
+                .unwrap_or_default(),
+            claim_username: config
+                .value("authentication.oidc.claim.username")
+                .unwrap_or("sub")
+                .to_string(),
+            claim_email: config
+                .value("authentication.oidc.claim.email")
+                .unwrap_or("email")
+                .to_string(),
+            claim_name: config
+                .value("authentication.oidc.claim.name")
+                .unwrap_or("name")
+                .to_string(),
+            trust_unverified_email: config
+                .property_or_default("authentication.oidc.trust-unverified-email", "false")
+                .unwrap_or_default(),
+            auto_provision: config
+                .property_or_default("authentication.oidc.auto-provision", "false")
+                .unwrap_or_default(),
+        })
+    }
+
+    pub async fn validate(&self, token: &str) -> Result<Claims, String> {
+        let (claims, require_exp) = match &self.method {
+            ValidationMethod::Jwks { .. } => (self.validate_jwt(token).await?, true),
+            ValidationMethod::Introspect { url, authorization } => (
+                self.introspect(url, authorization.as_deref(), token)
+                    .await?,
+                false,
+            ),
+        };
+
+        self.validate_claims(&claims, require_exp)?;
+
+        Ok(claims)
+    }
+
+    async fn validate_jwt(&self, token: &str) -> Result<Claims, String> {
+        let mut parts = token.split('.');
+        let (header, payload, signature) =
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(header), Some(payload), Some(signature), None) => {
+                    (header, payload, signature)
+                }
+                _ => return Err("Token is not a JWT.".to_string()),
+            };
+        let message = &token.as_bytes()[..header.len() + payload.len() + 1];
+        let header = decode_json(header)?;
+        let alg = header
+            .get("alg")
+            .and_then(Value::as_str)
+            .ok_or("Missing signing algorithm.")?;
+        let alg = Algorithm::parse(alg).ok_or_else(|| format!("Unsupported algorithm {alg:?}."))?;
+        let kid = header.get("kid").and_then(Value::as_str);
+        let signature = decode_base64(signature)?;
+
+        // Refresh the key set if the key id is unknown, the IdP might have rotated its keys
+        let mut keys = self.keys(false).await?;
+        if kid.map_or(false, |kid| {
+            !keys.iter().any(|key| key.kid.as_deref() == Some(kid))
+        }) {
+            keys = self.keys(true).await?;
+        }
+
+        if keys
+            .iter()
+            .filter(|key| kid.is_none() || key.kid.as_deref() == kid)
+            .any(|key| key.verify(alg, message, &signature))
+        {
+            decode_json(payload)
+        } else {
+            Err("Invalid token signature.".to_string())
+        }
+    }
+
+    async fn keys(&self, force_refresh: bool) -> Result<Arc<Vec<Jwk>>, String> {
+        let (source, ttl, cache) = match &self.method {
+            ValidationMethod::Jwks { source, ttl, cache } => (source, ttl, cache),
+            ValidationMethod::Introspect { .. } => return Ok(Arc::new(Vec::new())),
+        };
+
+        let cached = cache.lock().clone();
+        if let Some((fetched, keys)) = cached {
+            let age = fetched.elapsed();
+            if age < *ttl && (!force_refresh || age < JWKS_MIN_REFRESH) {
+                return Ok(keys);
+            }
+        }
+
+        let jwks = match source {
+            JwksSource::Url(url) => {
+                let response = reqwest::Client::builder()
+                    .timeout(self.timeout)
+                    .danger_accept_invalid_certs(self.tls_allow_invalid_certs)
+                    .build()
+                    .map_err(|err| format!("Failed to create HTTP client: {err}"))?
+                    .get(url)
+                    .send()
+                    .await
+                    .map_err(|err| format!("Failed to fetch JWKS from {url}: {err}"))?;
+                if !response.status().is_success() {
+                    return Err(format!(
+                        "Failed to fetch JWKS from {url}: HTTP status {}",
+                        response.status()
+                    ));
+                }
+                response
+                    .bytes()
+                    .await
+                    .map_err(|err| format!("Failed to fetch JWKS from {url}: {err}"))?
+                    .to_vec()
+            }
+            JwksSource::File(path) => tokio::fs::read(path)
+                .await
+                .map_err(|err| format!("Failed to read JWKS from {}: {err}", path.display()))?,
+        };
+        let keys = Arc::new(
+            serde_json::from_slice::<Value>(&jwks)
+                .map_err(|err| format!("Failed to parse JWKS: {err}"))?
+                .get("keys")
+                .and_then(Value::as_array)
+                .ok_or("JWKS does not contain any keys.")?
+                .iter()
+                .filter_map(Jwk::parse)
+                .collect::<Vec<_>>(),
+        );
+
+        tracing::debug!(
+            context = "oidc",
+            event = "jwks-refresh",
+            keys = keys.len(),
+            "Refreshed OpenID Connect signing keys."
+        );
+
+        *cache.lock() = Some((Instant::now(), keys.clone()));
+
+        Ok(keys)
+    }
+
+    async fn introspect(
+        &self,
+        url: &str,
+        authorization: Option<&str>,
+        token: &str,
+    ) -> Result<Claims, String> {
+        let mut request = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .danger_accept_invalid_certs(self.tls_allow_invalid_certs)
+            .build()
+            .map_err(|err| format!("Failed to create HTTP client: {err}"))?
+            .post(url)
+            .form(&[("token", token), ("token_type_hint", "access_token")]);
+        if let Some(authorization) = authorization {
+            request = request.header(reqwest::header::AUTHORIZATION, authorization);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|err| format!("Token introspection request to {url} failed: {err}"))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Token introspection request to {url} failed: HTTP status {}",
+                response.status()
+            ));
+        }
+        let claims = serde_json::from_slice::<Claims>(
+            &response
+                .bytes()
+                .await
+                .map_err(|err| format!("Token introspection request to {url} failed: {err}"))?,
+        )
+        .map_err(|err| format!("Failed to parse introspection response: {err}"))?;
+
+        if claims
+            .get("active")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+        {
+            Ok(claims)
+        } else {
+            Err("Token is not active.".to_string())
+        }
+    }
+
+    fn validate_claims(&self, claims: &Claims, require_exp: bool) -> Result<(), String> {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        match claims.get("exp").and_then(Value::as_u64) {
+            Some(exp) if exp + self.leeway <= now => {
+                return Err("Token expired.".to_string());
+            }
+            None if require_exp => {
+                return Err("Token does not have an expiration time.".to_string());
+            }
+            _ => (),
+        }
+        if claims
+            .get("nbf")
+            .and_then(Value::as_u64)
+            .map_or(false, |nbf| nbf > now + self.leeway)
+        {
+            return Err("Token is not valid yet.".to_string());
+        }
+
+        if let Some(issuer) = &self.issuer {
+            if claims.get("iss").and_then(Value::as_str) != Some(issuer.as_str()) {
+                return Err("Token was issued by an untrusted issuer.".to_string());
+            }
+        }
+
+        if !self.audiences.is_empty() {
+            let is_valid = match claims.get("aud") {
+                Some(Value::String(aud)) => self.audiences.contains(aud),
+                Some(Value::Array(auds)) => auds
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .any(|aud| self.audiences.iter().any(|a| a == aud)),
+                _ => false,
+            };
+            if !is_valid {
+                return Err("Token is not intended for this server.".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn map_principal(
+        &self,
+        directory: &Directory,
+        claims: &Claims,
+        login: Option<&str>,
+        return_member_of: bool,
+    ) -> directory::Result<Option<Principal<u32>>> {
+        // Addresses are only trusted once the provider has verified them
+        let email = claim_str(claims, &self.claim_email)
+            .filter(|_| self.trust_unverified_email || is_email_verified(claims))
+            .map(|email| email.to_lowercase());
+        let name = if let Some(name) =
+            claim_str(claims, &self.claim_username).map(|name| name.to_lowercase())
+        {
+            name
+        } else {
+            tracing::debug!(
+                context = "oidc",
+                event = "error",
+                claim = self.claim_username,
+                "Token does not contain a username claim."
+            );
+            return Ok(None);
+        };
+
+        // Principals are only matched by the configured claim, never by other names or addresses
+        let mut principal = directory
+            .query(QueryBy::Name(&name), return_member_of)
+            .await?;
+
+        // Create the account on first login
+        if principal.is_none() && self.auto_provision {
+            principal = self
+                .provision(directory, name, email, claims, return_member_of)
+                .await?;
+        }
+
+        // The authorization identity, when provided, has to match the token's subject
+        if let (Some(principal_), Some(login)) = (&principal, login) {
+            if !login.is_empty()
+                && !principal_.name.eq_ignore_ascii_case(login)
+                && !principal_
+                    .emails
+                    .iter()
+                    .any(|email| email.eq_ignore_ascii_case(login))
+            {
+                tracing::debug!(
+                    context = "oidc",
+                    event = "error",
+                    login = login,
+                    name = principal_.name,
+                    "Authorization identity does not match the token subject."
+                );
+                return Ok(None);
+            }
+        }
+
+        Ok(principal)
+    }
+
+    async fn provision(
+        &self,
+        directory: &Directory,
+        name: String,
+        email: Option<String>,
+        claims: &Claims,
+        return_member_of: bool,
+    ) -> directory::Result<Option<Principal<u32>>> {
+        let store = if let DirectoryInner::Internal(store) = &directory.store {
+            store
+        } else {
+            tracing::debug!(
+                context = "oidc",
+                event = "error",
+                name = name,
+                "Auto-provisioning is only supported by the internal directory."
+            );
+            return Ok(None);
+        };
+
+        // Only add addresses belonging to local domains
+        let mut emails = Vec::new();
+        if let Some(email) = email {
+            if let Some((_, domain)) = email.rsplit_once('@') {
+                if directory.is_local_domain(domain).await? {
+                    emails.push(email);
+                }
+            }
+        }
+
+        match store
+            .create_account(
+                Principal {
+                    typ: Type::Individual,
+                    name: name.clone(),
+                    description: claim_str(claims, &self.claim_name).map(|name| name.to_string()),
+                    emails,
+                    ..Default::default()
+                },
+                vec![],
+            )
+            .await
+        {
+            Ok(account_id) => {
+                tracing::info!(
+                    context = "oidc",
+                    event = "provision",
+                    name = name,
+                    account_id = account_id,
+                    "Provisioned account from OpenID Connect claims."
+                );
+            }
+            Err(DirectoryError::Management(ManagementError::AlreadyExists { .. })) => {}
+            Err(err) => return Err(err),
+        }
+
+        directory
+            .query(QueryBy::Name(&name), return_member_of)
+            .await
+    }
+}
+
+impl Core {
+    pub(crate) async fn query_credentials(
+        &self,
+        directory: &Directory,
+        credentials: &Credentials<String>,
+        return_member_of: bool,
+    ) -> directory::Result<Option<Principal<u32>>> {
+        // Validate tokens issued by an external OpenID Connect provider
+        if let (Some(oidc), Some((login, token))) =
+            (&self.jmap.oidc, bearer_credentials(credentials))
+        {
+            match oidc.validate(token).await {
+                Ok(claims) => {
+                    return oidc
+                        .map_principal(directory, &claims, login, return_member_of)
+                        .await;
+                }
+                Err(reason) => {
+                    tracing::debug!(
+                        context = "oidc",
+                        event = "invalid",
+                        reason = reason,
+                        "Failed to validate bearer token."
+                    );
+                }
+            }
+        }
+
+        directory
+            .query(QueryBy::Credentials(credentials), return_member_of)
+            .await
+    }
+}
+
+impl Jwk {
+    fn parse(value: &Value) -> Option<Self> {
+        if value
+            .get("use")
+            .and_then(Value::as_str)
+            .map_or(false, |usage| usage != "sig")
+        {
+            return None;
+        }
+        let field = |name: &str| {
+            value
+                .get(name)
+                .and_then(Value::as_str)
+                .and_then(|v| decode_base64(v).ok())
+        };
+
+        let key = match (
+            value.get("kty").and_then(Value::as_str)?,
+            value.get("crv").and_then(Value::as_str),
+        ) {
+            ("RSA", _) => JwkKey::Rsa {
+                n: field("n")?,
+                e: field("e")?,
+            },
+            ("EC", Some(crv @ ("P-256" | "P-384"))) => {
+                let mut point = vec![0x04];
+                point.extend(field("x")?);
+                point.extend(field("y")?);
+                if crv == "P-256" {
+                    JwkKey::P256(point)
+                } else {
+                    JwkKey::P384(point)
+                }
+            }
+            ("OKP", Some("Ed25519")) => JwkKey::Ed25519(field("x")?),
+            _ => return None,
+        };
+
+        Some(Jwk {
+            kid: value.get("kid").and_then(Value::as_str).map(String::from),
+            key,
+        })
+    }
+
+    fn verify(&self, alg: Algorithm, message: &[u8], signature: &[u8]) -> bool {
+        match (&self.key, alg) {
+            (JwkKey::Rsa { n, e }, alg) => {
+                if let Some(params) = alg.rsa_parameters() {
+                    RsaPublicKeyComponents { n, e }
+                        .verify(params, message, signature)
+                        .is_ok()
+                } else {
+                    false
+                }
+            }
+            (JwkKey::P256(point), Algorithm::Es256) => {
+                UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                    .verify(message, signature)
+                    .is_ok()
+            }
+            (JwkKey::P384(point), Algorithm::Es384) => {
+                UnparsedPublicKey::new(&signature::ECDSA_P384_SHA384_FIXED, point)
+                    .verify(message, signature)
+                    .is_ok()
+            }
+            (JwkKey::Ed25519(key), Algorithm::EdDsa) => {
+                UnparsedPublicKey::new(&signature::ED25519, key)
+                    .verify(message, signature)
+                    .is_ok()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Algorithm {
+    fn parse(value: &str) -> Option<Self> {
+        // Symmetric and unsigned tokens are never accepted
+        match value {
+            "RS256" => Some(Algorithm::Rs256),
+            "RS384" => Some(Algorithm::Rs384),
+            "RS512" => Some(Algorithm::Rs512),
+            "PS256" => Some(Algorithm::Ps256),
+            "PS384" => Some(Algorithm::Ps384),
+            "PS512" => Some(Algorithm::Ps512),
+            "ES256" => Some(Algorithm::Es256),
+            "ES384" => Some(Algorithm::Es384),
+            "EdDSA" => Some(Algorithm::EdDsa),
+            _ => None,
+        }
+    }
+
+    fn rsa_parameters(&self) -> Option<&'static RsaParameters> {
+        match self {
+            Algorithm::Rs256 => Some(&signature::RSA_PKCS1_2048_8192_SHA256),
+            Algorithm::Rs384 => Some(&signature::RSA_PKCS1_2048_8192_SHA384),
+            Algorithm::Rs512 => Some(&signature::RSA_PKCS1_2048_8192_SHA512),
+            Algorithm::Ps256 => Some(&signature::RSA_PSS_2048_8192_SHA256),
+            Algorithm::Ps384 => Some(&signature::RSA_PSS_2048_8192_SHA384),
+            Algorithm::Ps512 => Some(&signature::RSA_PSS_2048_8192_SHA512),
+            _ => None,
+        }
+    }
+}
+
+// Returns the authorization identity (if any) and the bearer token
+fn bearer_credentials(credentials: &Credentials<String>) -> Option<(Option<&str>, &str)> {
+    match credentials {
+        Credentials::OAuthBearer { token } if token.contains("auth=") => {
+            // RFC 7628 GS2 header followed by key/value pairs
+            let mut parts = token.split('\x01');
+            let login = parts.next().and_then(|header| {
+                header
+                    .split(',')
+                    .find_map(|part| part.strip_prefix("a="))
+                    .filter(|login| !login.is_empty())
+            });
+            let token = parts.find_map(|part| part.strip_prefix("auth="))?;
+
+            Some((login, strip_bearer(token)))
+        }
+        Credentials::OAuthBearer { token } => Some((None, token.as_str())),
+        Credentials::XOauth2 { username, secret } => {
+            Some((Some(username.as_str()), strip_bearer(secret)))
+        }
+        Credentials::Plain { .. } => None,
+    }
+}
+
+fn strip_bearer(token: &str) -> &str {
+    let token = token.trim();
+    token
+        .get(..7)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("bearer "))
+        .map_or(token, |_| token[7..].trim_start())
+}
+
+fn claim_str<'x>(claims: &'x Claims, name: &str) -> Option<&'x str> {
+    claims
+        .get(name)
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+}
+
+// Some providers send booleans as strings
+fn is_email_verified(claims: &Claims) -> bool {
+    match claims.get("email_verified") {
+        Some(Value::Bool(verified)) => *verified,
+        Some(Value::String(verified)) => verified.eq_ignore_ascii_case("true"),
+        _ => false,
+    }
+}
+
+fn decode_base64(value: &str) -> Result<Vec<u8>, String> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|err| format!("Failed to decode base64: {err}"))
+}
+
+fn decode_json(value: &str) -> Result<Claims, String> {
+    serde_json::from_slice(&decode_base64(value)?)
+        .map_err(|err| format!("Failed to parse JSON: {err}"))
+}
@@ -0,0 +1,747 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::net::IpAddr;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use directory::{
+    core::{
+        policy::PasswordStatus,
+        secret::{ScramAlgorithm, ScramCredentials, SCRAM_DEFAULT_ITERATIONS},
+    },
+    Directory, Principal, QueryBy,
+};
+use ring::{
+    digest,
+    rand::{SecureRandom, SystemRandom},
+};
+use utils::constant_time_eq;
+use x509_parser::{
+    certificate::X509Certificate,
+    oid_registry::{
+        OID_HASH_SHA1, OID_NIST_HASH_SHA256, OID_NIST_HASH_SHA384, OID_NIST_HASH_SHA512,
+        OID_PKCS1_MD5WITHRSAENC, OID_PKCS1_SHA1WITHRSA, OID_PKCS1_SHA256WITHRSA,
+        OID_PKCS1_SHA384WITHRSA, OID_PKCS1_SHA512WITHRSA, OID_SIG_ECDSA_WITH_SHA256,
+        OID_SIG_ECDSA_WITH_SHA384, OID_SIG_ECDSA_WITH_SHA512,
+    },
+    prelude::FromDer,
+    signature_algorithm::SignatureAlgorithm,
+};
+
+use crate::{
+    config::server::ServerProtocol,
+    listener::SessionStream,
+    webhooks::{WebhookPayload, WebhookType},
+    AuthFailureReason, Core, Ipc,
+};
+
+const NONCE_LEN: usize = 24;
+const SALT_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScramMechanism {
+    pub algorithm: ScramAlgorithm,
+    pub channel_binding: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChannelBinding {
+    pub tls_exporter: Option<Vec<u8>>,
+    pub tls_server_end_point: Option<Vec<u8>>,
+}
+
+pub struct ScramSession {
+    mechanism: ScramMechanism,
+    channel_binding: ChannelBinding,
+    plus_offered: bool,
+    state: ScramState,
+}
+
+enum ScramState {
+    ClientFirst,
+    ClientFinal {
+        username: String,
+        principal: Option<Principal<u32>>,
+        credentials: ScramCredentials,
+        client_first_bare: String,
+        server_first: String,
+        nonce: String,
+        cbind_input: Vec<u8>,
+    },
+    ClientAck {
+        username: String,
+        principal: Principal<u32>,
+    },
+    Done,
+}
+
+pub enum SaslStep<T> {
+    // Base64 encoded server challenge
+    Challenge(String),
+    Success(T),
+    Failure(AuthFailureReason),
+}
+
+impl ScramMechanism {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (value, channel_binding) = if let Some(value) = value
+            .strip_suffix("-PLUS")
+            .or_else(|| value.strip_suffix("-plus"))
+        {
+            (value, true)
+        } else {
+            (value, false)
+        };
+
+        ScramAlgorithm::parse(value).map(|algorithm| ScramMechanism {
+            algorithm,
+            channel_binding,
+        })
+    }
+}
+
+impl ChannelBinding {
+    pub fn is_available(&self) -> bool {
+        self.tls_exporter.is_some() || self.tls_server_end_point.is_some()
+    }
+
+    fn get(&self, name: &str) -> Option<&[u8]> {
+        match name {
+            "tls-exporter" => self.tls_exporter.as_deref(),
+            "tls-server-end-point" => self.tls_server_end_point.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl ScramSession {
+    pub fn new(
+        mechanism: ScramMechanism,
+        channel_binding: ChannelBinding,
+        plus_offered: bool,
+    ) -> Self {
+        ScramSession {
+            mechanism,
+            channel_binding,
+            plus_offered,
+            state: ScramState::ClientFirst,
+        }
+    }
+
+    pub fn mechanism(&self) -> ScramMechanism {
+        self.mechanism
+    }
+
+    // Returns true when the last challenge was the server-final-message,
+    // which some protocols send as additional data with the success response
+    pub fn is_server_final(&self) -> bool {
+        matches!(self.state, ScramState::ClientAck { .. })
+    }
+
+    pub fn username(&self) -> Option<&str> {
+        match &self.state {
+            ScramState::ClientFinal { username, .. } | ScramState::ClientAck { username, .. } => {
+                Some(username)
+            }
+            ScramState::ClientFirst | ScramState::Done => None,
+        }
+    }
+
+    fn handle_client_first(&mut self, message: &[u8]) -> Result<ClientFirst, &'static str> {
+        let message = std::str::from_utf8(message).map_err(|_| "Invalid UTF-8 message.")?;
+
+        // Parse GS2 header
+        let (cbind_flag, authzid, client_first_bare) = message
+            .split_once(',')
+            .and_then(|(cbind_flag, message)| {
+                message
+                    .split_once(',')
+                    .map(|(authzid, client_first_bare)| (cbind_flag, authzid, client_first_bare))
+            })
+            .ok_or("Missing GS2 header.")?;
+        let gs2_header = format!("{cbind_flag},{authzid},");
+        let cbind_data = match cbind_flag {
+            "n" | "y" if self.mechanism.channel_binding => {
+                return Err("Channel binding is required by this mechanism.");
+            }
+            "n" => None,
+            "y" if self.plus_offered => {
+                // The client believes the server does not support channel binding
+                return Err("Channel binding is supported by the server.");
+            }
+            "y" => None,
+            _ => match cbind_flag.strip_prefix("p=") {
+                Some(name) if self.mechanism.channel_binding => Some(
+                    self.channel_binding
+                        .get(name)
+                        .ok_or("Unsupported channel binding type.")?,
+                ),
+                _ => return Err("Invalid channel binding flag."),
+            },
+        };
+        let mut cbind_input = gs2_header.into_bytes();
+        if let Some(cbind_data) = cbind_data {
+            cbind_input.extend_from_slice(cbind_data);
+        }
+
+        // Parse client-first-message-bare
+        let mut username = None;
+        let mut nonce = None;
+        for (pos, attribute) in client_first_bare.split(',').enumerate() {
+            match attribute.split_once('=') {
+                Some(("m", _)) if pos == 0 => {
+                    return Err("Mandatory extensions are not supported.");
+                }
+                Some(("n", value)) if pos == 0 => {
+                    username = Some(decode_sasl_name(value)?);
+                }
+                Some(("r", value)) if pos == 1 && !value.is_empty() => {
+                    nonce = Some(value);
+                }
+                Some(_) if pos > 1 => {}
+                _ => return Err("Invalid client-first-message."),
+            }
+        }
+        let username = username.ok_or("Missing username.")?;
+        let client_nonce = nonce.ok_or("Missing nonce.")?;
+
+        // Authorization identities other than the authenticated user are not supported
+        let authzid = match authzid.strip_prefix("a=") {
+            Some(authzid) => Some(decode_sasl_name(authzid)?),
+            None if authzid.is_empty() => None,
+            None => return Err("Invalid authorization identity."),
+        };
+        if authzid.map_or(false, |authzid| authzid != username) {
+            return Err("Authorization identity does not match username.");
+        }
+
+        Ok(ClientFirst {
+            client_nonce: client_nonce.to_string(),
+            client_first_bare: client_first_bare.to_string(),
+            cbind_input,
+            username,
+        })
+    }
+}
+
+struct ClientFirst {
+    username: String,
+    client_nonce: String,
+    client_first_bare: String,
+    cbind_input: Vec<u8>,
+}
+
+impl Core {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn authenticate_scram(
+        &self,
+        directory: &Directory,
+        ipc: &Ipc,
+        session: &mut ScramSession,
+        message: &[u8],
+        remote_ip: IpAddr,
+        protocol: ServerProtocol,
+        return_member_of: bool,
+    ) -> SaslStep<Principal<u32>> {
+        match std::mem::replace(&mut session.state, ScramState::Done) {
+            ScramState::ClientFirst => {
+                let client_first = match session.handle_client_first(message) {
+                    Ok(client_first) => client_first,
+                    Err(err) => {
+                        tracing::debug!(
+                            context = "sasl",
+                            event = "error",
+                            mechanism = session.mechanism.algorithm.as_str(),
+                            reason = err,
+                            "Invalid SCRAM client-first-message."
+                        );
+                        return SaslStep::Failure(AuthFailureReason::InvalidCredentials);
+                    }
+                };
+
+                // Locked accounts are rejected before verifying their credentials
+                match self
+                    .is_account_locked(&client_first.username, &remote_ip)
+                    .await
+                {
+                    Ok(false) => (),
+                    Ok(true) => return SaslStep::Failure(AuthFailureReason::AccountLocked),
+                    Err(err) => {
+                        return SaslStep::Failure(AuthFailureReason::InternalError(err.into()))
+                    }
+                }
+
+                // Obtain the credentials of the principal
+                let rng = SystemRandom::new();
+                let mut salt = [0u8; SALT_LEN];
+                let mut server_nonce = [0u8; NONCE_LEN];
+                if rng.fill(&mut salt).is_err() || rng.fill(&mut server_nonce).is_err() {
+                    return SaslStep::Failure(AuthFailureReason::InvalidCredentials);
+                }
+                let principal = match &self.jmap.fallback_admin {
+                    Some((fallback_admin, fallback_pass))
+                        if fallback_admin == &client_first.username =>
+                    {
+                        Some(Principal::fallback_admin(fallback_pass))
+                    }
+                    _ => match directory
+                        .query(QueryBy::Name(&client_first.username), return_member_of)
+                        .await
+                    {
+                        Ok(principal) => principal,
+                        Err(err) => {
+                            return SaslStep::Failure(AuthFailureReason::InternalError(err))
+                        }
+                    },
+                };
+                let (principal, credentials) = match principal.and_then(|principal| {
+                    principal
+                        .scram_credentials(session.mechanism.algorithm, &salt)
+                        .map(|credentials| (principal, credentials))
+                }) {
+                    Some((principal, credentials)) => (Some(principal), credentials),
+                    None => {
+                        // Continue the exchange with random credentials to avoid
+                        // disclosing whether the account exists
+                        (
+                            None,
+                            ScramCredentials::derive(
+                                session.mechanism.algorithm,
+                                &STANDARD.encode(server_nonce),
+                                salt.to_vec(),
+                                SCRAM_DEFAULT_ITERATIONS,
+                            ),
+                        )
+                    }
+                };
+
+                // Build server-first-message
+                let nonce = format!(
+                    "{}{}",
+                    client_first.client_nonce,
+                    STANDARD.encode(server_nonce)
+                );
+                let server_first = format!(
+                    "r={nonce},s={},i={}",
+                    STANDARD.encode(&credentials.salt),
+                    credentials.iterations
+                );
+                let challenge = STANDARD.encode(&server_first);
+                session.state = ScramState::ClientFinal {
+                    username: client_first.username,
+                    principal,
+                    credentials,
+                    client_first_bare: client_first.client_first_bare,
+                    server_first,
+                    nonce,
+                    cbind_input: client_first.cbind_input,
+                };
+
+                SaslStep::Challenge(challenge)
+            }
+            ScramState::ClientFinal {
+                username,
+                principal,
+                credentials,
+                client_first_bare,
+                server_first,
+                nonce,
+                cbind_input,
+            } => {
+                match verify_client_final(
+                    message,
+                    &credentials,
+                    &client_first_bare,
+                    &server_first,
+                    &nonce,
+                    &cbind_input,
+                ) {
+                    Ok(Some(server_final)) if principal.is_some() => {
+                        let principal = principal.unwrap();
+
+                        // Passwords past their grace period can no longer be used
+                        if self
+                            .jmap
+                            .password_policy
+                            .status(principal.password_changed_at())
+                            == PasswordStatus::Expired
+                        {
+                            return SaslStep::Failure(AuthFailureReason::PasswordExpired);
+                        }
+
+                        // Send webhook event
+                        if self.has_webhook_subscribers(WebhookType::AuthSuccess) {
+                            ipc.send_webhook(
+                                WebhookType::AuthSuccess,
+                                WebhookPayload::Authentication {
+                                    login: username.clone(),
+                                    protocol,
+                                    remote_ip,
+                                    typ: principal.typ.into(),
+                                    as_master: None,
+                                },
+                            )
+                            .await;
+                        }
+
+                        self.record_login(ipc, &username, &principal, remote_ip, protocol)
+                            .await;
+
+                        session.state = ScramState::ClientAck {
+                            username,
+                            principal,
+                        };
+                        SaslStep::Challenge(STANDARD.encode(server_final))
+                    }
+                    Ok(_) => match self.auth_failure(ipc, &username, remote_ip, protocol).await {
+                        Ok(reason) => SaslStep::Failure(reason),
+                        Err(err) => SaslStep::Failure(AuthFailureReason::InternalError(err)),
+                    },
+                    Err(err) => {
+                        tracing::debug!(
+                            context = "sasl",
+                            event = "error",
+                            mechanism = session.mechanism.algorithm.as_str(),
+                            reason = err,
+                            "Invalid SCRAM client-final-message."
+                        );
+                        SaslStep::Failure(AuthFailureReason::InvalidCredentials)
+                    }
+                }
+            }
+            ScramState::ClientAck { principal, .. } => {
+                // The client acknowledges the server signature with an empty response
+                if message.is_empty() {
+                    SaslStep::Success(principal)
+                } else {
+                    SaslStep::Failure(AuthFailureReason::InvalidCredentials)
+                }
+            }
+            ScramState::Done => SaslStep::Failure(AuthFailureReason::InvalidCredentials),
+        }
+    }
+
+    pub fn tls_channel_binding<T: SessionStream>(&self, stream: &T) -> ChannelBinding {
+        if !stream.is_tls() {
+            return ChannelBinding::default();
+        }
+
+        ChannelBinding {
+            tls_exporter: stream.tls_exporter(b"EXPORTER-Channel-Binding", 32),
+            tls_server_end_point: stream
+                .tls_certificate(self)
+                .and_then(|key| key.cert.first().and_then(|cert| server_end_point(cert))),
+        }
+    }
+}
+
+fn verify_client_final(
+    message: &[u8],
+    credentials: &ScramCredentials,
+    client_first_bare: &str,
+    server_first: &str,
+    nonce: &str,
+    cbind_input: &[u8],
+) -> Result<Option<String>, &'static str> {
+    let message = std::str::from_utf8(message).map_err(|_| "Invalid UTF-8 message.")?;
+    let (client_final_without_proof, proof) =
+        message.rsplit_once(",p=").ok_or("Missing client proof.")?;
+
+    let mut has_cbind = false;
+    let mut has_nonce = false;
+    for (pos, attribute) in client_final_without_proof.split(',').enumerate() {
+        match attribute.split_once('=') {
+            Some(("c", value)) if pos == 0 => {
+                if STANDARD.decode(value).ok().as_deref() != Some(cbind_input) {
+                    return Err("Channel binding mismatch.");
+                }
+                has_cbind = true;
+            }
+            Some(("r", value)) if pos == 1 => {
+                if value != nonce {
+                    return Err("Nonce mismatch.");
+                }
+                has_nonce = true;
+            }
+            Some(_) if pos > 1 => {}
+            _ => return Err("Invalid client-final-message."),
+        }
+    }
+    if !has_cbind || !has_nonce {
+        return Err("Invalid client-final-message.");
+    }
+    let proof = STANDARD
+        .decode(proof)
+        .ok()
+        .filter(|proof| proof.len() == credentials.algorithm.digest_len())
+        .ok_or("Invalid client proof.")?;
+
+    // Verify proof
+    let auth_message = format!("{client_first_bare},{server_first},{client_final_without_proof}");
+    let algorithm = credentials.algorithm;
+    let client_signature = algorithm.hmac(&credentials.stored_key, auth_message.as_bytes());
+    let client_key = proof
+        .iter()
+        .zip(client_signature.iter())
+        .map(|(a, b)| a ^ b)
+        .collect::<Vec<_>>();
+
+    if constant_time_eq(&algorithm.hash(&client_key), &credentials.stored_key) {
+        let server_signature = algorithm.hmac(&credentials.server_key, auth_message.as_bytes());
+        Ok(Some(format!("v={}", STANDARD.encode(server_signature))))
+    } else {
+        Ok(None)
+    }
+}
+
+// RFC 5929: hash of the server certificate using the hash function of its signature
+// algorithm, with MD5 and SHA-1 replaced by SHA-256
+fn server_end_point(der: &[u8]) -> Option<Vec<u8>> {
+    let (_, cert) = X509Certificate::from_der(der).ok()?;
+    let signature = &cert.signature_algorithm;
+    let hash = match SignatureAlgorithm::try_from(signature).ok()? {
+        SignatureAlgorithm::RSASSA_PSS(params) => params.hash_algorithm_oid().to_owned(),
+        _ => signature.algorithm.to_owned(),
+    };
+    let algorithm = if [
+        OID_PKCS1_SHA384WITHRSA,
+        OID_SIG_ECDSA_WITH_SHA384,
+        OID_NIST_HASH_SHA384,
+    ]
+    .contains(&hash)
+    {
+        &digest::SHA384
+    } else if [
+        OID_PKCS1_SHA512WITHRSA,
+        OID_SIG_ECDSA_WITH_SHA512,
+        OID_NIST_HASH_SHA512,
+    ]
+    .contains(&hash)
+    {
+        &digest::SHA512
+    } else if [
+        OID_PKCS1_MD5WITHRSAENC,
+        OID_PKCS1_SHA1WITHRSA,
+        OID_PKCS1_SHA256WITHRSA,
+        OID_SIG_ECDSA_WITH_SHA256,
+        OID_HASH_SHA1,
+        OID_NIST_HASH_SHA256,
+    ]
+    .contains(&hash)
+    {
+        &digest::SHA256
+    } else {
+        // The binding is undefined for algorithms without a single hash function, such as EdDSA
+        return None;
+    };
+
+    Some(digest::digest(algorithm, der).as_ref().to_vec())
+}
+
+fn decode_sasl_name(value: &str) -> Result<String, &'static str> {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.split('=');
+    result.push_str(chars.next().unwrap_or_default());
+    for part in chars {
+        if let Some(part) = part.strip_prefix("2C") {
+            result.push(',');
+            result.push_str(part);
+        } else if let Some(part) = part.strip_prefix("3D") {
+            result.push('=');
+            result.push_str(part);
+        } else {
+            return Err("Invalid SASL name encoding.");
+        }
+    }
+
+    if !result.is_empty() {
+        Ok(result)
+    } else {
+        Err("Empty username.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use directory::core::secret::{ScramAlgorithm, ScramCredentials};
+
+    use ring::digest;
+
+    use super::{
+        server_end_point, verify_client_final, ChannelBinding, ScramMechanism, ScramSession,
+    };
+
+    #[test]
+    fn scram_sha_256() {
+        // Test vectors from RFC 7677
+        let credentials = ScramCredentials::derive(
+            ScramAlgorithm::Sha256,
+            "pencil",
+            STANDARD.decode("W22ZaJ0SNY7soEsUEjb6gQ==").unwrap(),
+            4096,
+        );
+        assert!(credentials.verify("pencil"));
+        assert!(!credentials.verify("pencil2"));
+        assert_eq!(
+            ScramCredentials::parse(&credentials.to_string()),
+            Some(credentials.clone())
+        );
+
+        let mut session = ScramSession::new(
+            ScramMechanism::parse("SCRAM-SHA-256").unwrap(),
+            ChannelBinding::default(),
+            false,
+        );
+        let client_first = session
+            .handle_client_first(b"n,,n=user,r=rOprNGfwEbeRWgbNEkqO")
+            .unwrap();
+        assert_eq!(client_first.username, "user");
+        assert_eq!(client_first.client_nonce, "rOprNGfwEbeRWgbNEkqO");
+        assert_eq!(client_first.cbind_input, b"n,,");
+
+        let nonce = "rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0";
+        let server_first = format!("r={nonce},s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096");
+        assert_eq!(
+            verify_client_final(
+                format!("c=biws,r={nonce},p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ=")
+                    .as_bytes(),
+                &credentials,
+                &client_first.client_first_bare,
+                &server_first,
+                nonce,
+                &client_first.cbind_input,
+            ),
+            Ok(Some(
+                "v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=".to_string()
+            ))
+        );
+        assert_eq!(
+            verify_client_final(
+                format!("c=biws,r={nonce},p=AHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ=")
+                    .as_bytes(),
+                &credentials,
+                &client_first.client_first_bare,
+                &server_first,
+                nonce,
+                &client_first.cbind_input,
+            ),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn scram_channel_binding() {
+        let channel_binding = ChannelBinding {
+            tls_exporter: Some(vec![1, 2, 3]),
+            tls_server_end_point: None,
+        };
+
+        // Channel binding is required by -PLUS mechanisms
+        let mut session = ScramSession::new(
+            ScramMechanism::parse("SCRAM-SHA-256-PLUS").unwrap(),
+            channel_binding.clone(),
+            true,
+        );
+        assert!(session.handle_client_first(b"n,,n=user,r=abc").is_err());
+        assert!(session
+            .handle_client_first(b"p=tls-server-end-point,,n=user,r=abc")
+            .is_err());
+        assert_eq!(
+            session
+                .handle_client_first(b"p=tls-exporter,,n=user,r=abc")
+                .unwrap()
+                .cbind_input,
+            b"p=tls-exporter,,\x01\x02\x03"
+        );
+
+        let mut session = ScramSession::new(
+            ScramMechanism::parse("SCRAM-SHA-256-PLUS").unwrap(),
+            ChannelBinding {
+                tls_exporter: None,
+                tls_server_end_point: Some(vec![4, 5, 6]),
+            },
+            true,
+        );
+        assert!(session
+            .handle_client_first(b"p=tls-exporter,,n=user,r=abc")
+            .is_err());
+        assert_eq!(
+            session
+                .handle_client_first(b"p=tls-server-end-point,,n=user,r=abc")
+                .unwrap()
+                .cbind_input,
+            b"p=tls-server-end-point,,\x04\x05\x06"
+        );
+
+        // Downgrade attempts are rejected when -PLUS was offered
+        let mut session = ScramSession::new(
+            ScramMechanism::parse("SCRAM-SHA-1").unwrap(),
+            channel_binding.clone(),
+            true,
+        );
+        assert!(session.handle_client_first(b"y,,n=user,r=abc").is_err());
+        assert!(session
+            .handle_client_first(b"p=tls-exporter,,n=user,r=abc")
+            .is_err());
+        assert!(session.handle_client_first(b"n,,n=user,r=abc").is_ok());
+
+        // Clients may signal channel binding support when -PLUS was not offered
+        let mut session = ScramSession::new(
+            ScramMechanism::parse("SCRAM-SHA-1").unwrap(),
+            channel_binding,
+            false,
+        );
+        assert!(session.handle_client_first(b"y,,n=user,r=abc").is_ok());
+
+        // Authorization identities must match the username
+        assert!(session
+            .handle_client_first(b"n,a=admin,n=user,r=abc")
+            .is_err());
+        assert_eq!(
+            session
+                .handle_client_first(b"n,a=us=2Cer,n=us=2Cer,r=abc")
+                .unwrap()
+                .username,
+            "us,er"
+        );
+    }
+
+    #[test]
+    fn tls_server_end_point() {
+        // ECDSA with SHA-256 certificates are hashed with SHA-256
+        let cert = rcgen::generate_simple_self_signed(vec!["mail.example.org".to_string()])
+            .unwrap()
+            .serialize_der()
+            .unwrap();
+        assert_eq!(
+            server_end_point(&cert).unwrap(),
+            digest::digest(&digest::SHA256, &cert).as_ref()
+        );
+
+        // ECDSA with SHA-384 certificates are hashed with SHA-384
+        let mut params = rcgen::CertificateParams::new(vec!["mail.example.org".to_string()]);
+        params.alg = &rcgen::PKCS_ECDSA_P384_SHA384;
+        let cert = rcgen::Certificate::from_params(params)
+            .unwrap()
+            .serialize_der()
+            .unwrap();
+        assert_eq!(
+            server_end_point(&cert).unwrap(),
+            digest::digest(&digest::SHA384, &cert).as_ref()
+        );
+
+        // EdDSA does not define a hash function
+        let mut params = rcgen::CertificateParams::new(vec!["mail.example.org".to_string()]);
+        params.alg = &rcgen::PKCS_ED25519;
+        let cert = rcgen::Certificate::from_params(params)
+            .unwrap()
+            .serialize_der()
+            .unwrap();
+        assert_eq!(server_end_point(&cert), None);
+        assert_eq!(server_end_point(b"garbage"), None);
+    }
+}
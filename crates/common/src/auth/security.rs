@@ -0,0 +1,583 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{net::IpAddr, time::Duration};
+
+use directory::{Principal, QueryBy};
+use mail_parser::DateTime;
+use serde::{Deserialize, Serialize};
+use store::{
+    write::{now, BatchBuilder, Bincode, BlobOp},
+    Serialize as _,
+};
+use tokio::sync::oneshot;
+use utils::{
+    config::{Config, Rate},
+    BlobHash,
+};
+
+use crate::{
+    config::server::ServerProtocol,
+    webhooks::{WebhookPayload, WebhookType},
+    Core, DeliveryEvent, IngestMessage, Ipc,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct AccountSecurity {
+    pub lockout_rate: Option<Rate>,
+    pub lockout_duration: Duration,
+    pub notify_lockout: bool,
+    pub history_size: usize,
+    pub notify_new_location: bool,
+    pub from_name: String,
+    pub from_address: String,
+    pub geoip: GeoIpDatabase,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GeoIpDatabase {
+    countries: Vec<(u128, u128, String)>,
+    asns: Vec<(u128, u128, u32, String)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeoIpLocation {
+    pub country: Option<String>,
+    pub asn: Option<u32>,
+    #[serde(rename = "asOrganization")]
+    pub as_org: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoginEntry {
+    pub timestamp: u64,
+    #[serde(rename = "remoteIp")]
+    pub remote_ip: IpAddr,
+    pub protocol: ServerProtocol,
+    pub location: GeoIpLocation,
+}
+
+// Logins from the same address and protocol are recorded at most once per hour
+const HISTORY_MIN_INTERVAL: u64 = 3600;
+
+impl AccountSecurity {
+    pub fn parse(config: &mut Config) -> Self {
+        let hostname = config
+            .value("lookup.default.hostname")
+            .unwrap_or("localhost")
+            .to_string();
+
+        AccountSecurity {
+            lockout_rate: config
+                .property_or_default::<Option<Rate>>("authentication.lockout.rate", "false")
+                .unwrap_or_default(),
+            lockout_duration: config
+                .property_or_default("authentication.lockout.duration", "15m")
+                .unwrap_or_else(|| Duration::from_secs(15 * 60)),
+            notify_lockout: config
+                .property_or_default("authentication.lockout.notify", "true")
+                .unwrap_or(true),
+            history_size: config
+                .property_or_default("authentication.login-history.size", "0")
+                .unwrap_or(0),
+            notify_new_location: config
+                .property_or_default("authentication.login-history.notify", "true")
+                .unwrap_or(true),
+            from_name: config
+                .value("authentication.notify.from-name")
+                .unwrap_or("Security Notification")
+                .to_string(),
+            from_address: config
+                .value("authentication.notify.from-address")
+                .map(|address| address.to_string())
+                .unwrap_or_else(|| format!("postmaster@{hostname}")),
+            geoip: GeoIpDatabase::parse(config),
+        }
+    }
+}
+
+impl GeoIpDatabase {
+    // Both databases are CSV files where the first column is either a network in
+    // CIDR notation or the first and last addresses of a range. The country database
+    // contains the ISO country code, the ASN database the AS number and organization.
+    pub fn parse(config: &mut Config) -> Self {
+        let mut db = GeoIpDatabase::default();
+
+        for key in ["authentication.geoip.country", "authentication.geoip.asn"] {
+            let path = if let Some(path) = config.value(key) {
+                path.to_string()
+            } else {
+                continue;
+            };
+            let contents = match std::fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(err) => {
+                    config.new_build_error(
+                        key,
+                        format!("Failed to read GeoIP database {path:?}: {err}"),
+                    );
+                    continue;
+                }
+            };
+
+            let is_country = key.ends_with("country");
+            let mut invalid_lines = 0;
+            for line in contents.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let Some((from, to, fields)) = parse_range(line) else {
+                    invalid_lines += 1;
+                    continue;
+                };
+                let mut fields = fields.splitn(2, ',');
+                let first = fields.next().unwrap_or_default().trim().trim_matches('"');
+                if is_country {
+                    if !first.is_empty() {
+                        db.countries.push((from, to, first.to_ascii_uppercase()));
+                    }
+                } else if let Ok(asn) = first.trim_start_matches("AS").parse::<u32>() {
+                    let org = fields.next().unwrap_or_default().trim().trim_matches('"');
+                    db.asns.push((from, to, asn, org.to_string()));
+                } else {
+                    invalid_lines += 1;
+                }
+            }
+
+            // Header lines are not counted as errors
+            if invalid_lines > 1 {
+                config.new_build_warning(
+                    key,
+                    format!("Ignored {invalid_lines} invalid lines in GeoIP database {path:?}"),
+                );
+            }
+        }
+
+        db.countries.sort_unstable_by_key(|(from, _, _)| *from);
+        db.asns.sort_unstable_by_key(|(from, _, _, _)| *from);
+        db
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.countries.is_empty() && self.asns.is_empty()
+    }
+
+    pub fn lookup(&self, ip: &IpAddr) -> GeoIpLocation {
+        let ip = ip_to_u128(ip);
+        let mut location = GeoIpLocation::default();
+
+        let pos = self.countries.partition_point(|(from, _, _)| *from <= ip);
+        if let Some((_, to, country)) = pos.checked_sub(1).map(|pos| &self.countries[pos]) {
+            if ip <= *to {
+                location.country = Some(country.clone());
+            }
+        }
+
+        let pos = self.asns.partition_point(|(from, _, _, _)| *from <= ip);
+        if let Some((_, to, asn, org)) = pos.checked_sub(1).map(|pos| &self.asns[pos]) {
+            if ip <= *to {
+                location.asn = Some(*asn);
+                if !org.is_empty() {
+                    location.as_org = Some(org.clone());
+                }
+            }
+        }
+
+        location
+    }
+}
+
+impl Core {
+    pub async fn is_account_locked(&self, login: &str, remote_ip: &IpAddr) -> store::Result<bool> {
+        if self.network.security.lockout_rate.is_some() && !self.is_ip_allowed(remote_ip) {
+            self.storage
+                .lookup
+                .key_exists(format!("lk:{}", login.to_lowercase()).into_bytes())
+                .await
+        } else {
+            Ok(false)
+        }
+    }
+
+    // Returns true if the account was locked as a result of this failure
+    pub async fn account_lockout(
+        &self,
+        ipc: &Ipc,
+        login: &str,
+        remote_ip: IpAddr,
+        protocol: ServerProtocol,
+    ) -> store::Result<bool> {
+        let security = &self.network.security;
+        let rate = match &security.lockout_rate {
+            Some(rate) if !self.is_ip_allowed(&remote_ip) => rate,
+            _ => return Ok(false),
+        };
+        let login = login.to_lowercase();
+
+        if self
+            .storage
+            .lookup
+            .is_rate_allowed(format!("lf:{login}").as_bytes(), rate, false)
+            .await?
+            .is_none()
+        {
+            return Ok(false);
+        }
+
+        let key = format!("lk:{login}").into_bytes();
+        if self.storage.lookup.key_exists(key.clone()).await? {
+            return Ok(true);
+        }
+        self.storage
+            .lookup
+            .key_set(
+                key,
+                now().to_be_bytes().to_vec(),
+                security.lockout_duration.as_secs().into(),
+            )
+            .await?;
+
+        tracing::info!(
+            context = "directory",
+            event = "account-locked",
+            remote_ip = ?remote_ip,
+            login = ?login,
+            duration = security.lockout_duration.as_secs(),
+            "Account locked after too many failed login attempts",
+        );
+
+        // Send webhook event
+        if self.has_webhook_subscribers(WebhookType::AuthLocked) {
+            ipc.send_webhook(
+                WebhookType::AuthLocked,
+                WebhookPayload::Authentication {
+                    login: login.clone(),
+                    protocol,
+                    remote_ip,
+                    typ: None,
+                    as_master: None,
+                },
+            )
+            .await;
+        }
+
+        // Notify the account owner
+        if security.notify_lockout {
+            if let Ok(Some(principal)) = self
+                .storage
+                .directory
+                .query(QueryBy::Name(&login), false)
+                .await
+            {
+                self.send_security_notice(
+                    ipc,
+                    &principal,
+                    "Your account has been temporarily locked",
+                    format!(
+                        concat!(
+                            "Your account <{}> has been locked for {} minute(s) after too many ",
+                            "failed login attempts.\r\n\r\n",
+                            "Last attempt: {}\r\nRemote IP: {}\r\nProtocol: {}\r\n\r\n",
+                            "If these attempts were not made by you, please consider changing ",
+                            "your password and contact your administrator.\r\n"
+                        ),
+                        principal.name,
+                        security.lockout_duration.as_secs().div_ceil(60),
+                        DateTime::from_timestamp(now() as i64).to_rfc822(),
+                        remote_ip,
+                        protocol,
+                    ),
+                )
+                .await;
+            }
+        }
+
+        Ok(true)
+    }
+
+    pub async fn record_login(
+        &self,
+        ipc: &Ipc,
+        login: &str,
+        principal: &Principal<u32>,
+        remote_ip: IpAddr,
+        protocol: ServerProtocol,
+    ) {
+        let security = &self.network.security;
+
+        // Successful logins reset the failed attempts counter
+        if let Some(rate) = &security.lockout_rate {
+            if let Err(err) = self
+                .storage
+                .lookup
+                .reset_rate(format!("lf:{}", login.to_lowercase()).as_bytes(), rate)
+                .await
+            {
+                tracing::error!(
+                    context = "directory",
+                    event = "error",
+                    login = ?login,
+                    "Failed to reset failed login counter: {}",
+                    err
+                );
+            }
+        }
+
+        if security.history_size == 0 || principal.id == u32::MAX {
+            return;
+        }
+
+        let mut history = match self.login_history(principal.id).await {
+            Ok(history) => history,
+            Err(err) => {
+                tracing::error!(
+                    context = "directory",
+                    event = "error",
+                    account_id = principal.id,
+                    "Failed to read login history: {}",
+                    err
+                );
+                return;
+            }
+        };
+        let entry = LoginEntry {
+            timestamp: now(),
+            remote_ip,
+            protocol,
+            location: security.geoip.lookup(&remote_ip),
+        };
+        if history.first().map_or(false, |last| {
+            last.remote_ip == entry.remote_ip
+                && last.protocol == entry.protocol
+                && last.timestamp + HISTORY_MIN_INTERVAL > entry.timestamp
+        }) {
+            return;
+        }
+
+        // Logins from a country or network not seen before are considered suspicious,
+        // unless this is the first login recorded for the account
+        let is_new_country = entry.location.country.as_ref().map_or(false, |country| {
+            !history
+                .iter()
+                .any(|e| e.location.country.as_ref() == Some(country))
+        });
+        let is_new_asn = entry.location.asn.map_or(false, |asn| {
+            !history.iter().any(|e| e.location.asn == Some(asn))
+        });
+        let is_suspicious = !history.is_empty() && (is_new_country || is_new_asn);
+
+        history.insert(0, entry.clone());
+        history.truncate(security.history_size);
+        if let Err(err) = self
+            .storage
+            .lookup
+            .key_set(
+                format!("lh:{}", principal.id).into_bytes(),
+                Bincode::new(history).serialize(),
+                None,
+            )
+            .await
+        {
+            tracing::error!(
+                context = "directory",
+                event = "error",
+                account_id = principal.id,
+                "Failed to write login history: {}",
+                err
+            );
+        }
+
+        if is_suspicious {
+            tracing::info!(
+                context = "directory",
+                event = "suspicious-login",
+                account_id = principal.id,
+                login = ?principal.name,
+                remote_ip = ?remote_ip,
+                country = ?entry.location.country,
+                asn = ?entry.location.asn,
+                "Login from a new location",
+            );
+
+            // Send webhook event
+            if self.has_webhook_subscribers(WebhookType::AuthSuspicious) {
+                ipc.send_webhook(
+                    WebhookType::AuthSuspicious,
+                    WebhookPayload::SuspiciousLogin {
+                        login: principal.name.clone(),
+                        protocol,
+                        remote_ip,
+                        country: entry.location.country.clone(),
+                        asn: entry.location.asn,
+                    },
+                )
+                .await;
+            }
+
+            // Notify the account owner
+            if security.notify_new_location {
+                self.send_security_notice(
+                    ipc,
+                    principal,
+                    "New sign-in to your account",
+                    format!(
+                        concat!(
+                            "Your account <{}> was accessed from a new location.\r\n\r\n",
+                            "Date: {}\r\nRemote IP: {}\r\nCountry: {}\r\nNetwork: {}\r\n",
+                            "Protocol: {}\r\n\r\n",
+                            "If this was not you, please change your password immediately ",
+                            "and contact your administrator.\r\n"
+                        ),
+                        principal.name,
+                        DateTime::from_timestamp(entry.timestamp as i64).to_rfc822(),
+                        remote_ip,
+                        entry.location.country.as_deref().unwrap_or("Unknown"),
+                        match (entry.location.asn, &entry.location.as_org) {
+                            (Some(asn), Some(org)) => format!("AS{asn} {org}"),
+                            (Some(asn), None) => format!("AS{asn}"),
+                            _ => "Unknown".to_string(),
+                        },
+                        protocol,
+                    ),
+                )
+                .await;
+            }
+        }
+    }
+
+    pub async fn login_history(&self, account_id: u32) -> store::Result<Vec<LoginEntry>> {
+        self.storage
+            .lookup
+            .key_get::<Bincode<Vec<LoginEntry>>>(format!("lh:{account_id}").into_bytes())
+            .await
+            .map(|history| history.map(|history| history.inner).unwrap_or_default())
+    }
+
+    async fn send_security_notice(
+        &self,
+        ipc: &Ipc,
+        principal: &Principal<u32>,
+        subject: &str,
+        body: String,
+    ) {
+        let security = &self.network.security;
+        let rcpt = if let Some(rcpt) = principal.emails.first() {
+            rcpt.to_lowercase()
+        } else {
+            return;
+        };
+        let domain = security
+            .from_address
+            .rsplit_once('@')
+            .map_or("localhost", |(_, domain)| domain);
+        let message = format!(
+            concat!(
+                "From: \"{}\" <{}>\r\nTo: <{}>\r\nSubject: {}\r\nDate: {}\r\n",
+                "Message-ID: <{}.{}@{}>\r\nAuto-Submitted: auto-generated\r\n",
+                "MIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\n",
+                "Content-Transfer-Encoding: 8bit\r\n\r\n{}"
+            ),
+            security.from_name,
+            security.from_address,
+            rcpt,
+            subject,
+            DateTime::from_timestamp(now() as i64).to_rfc822(),
+            now(),
+            store::rand::random::<u64>(),
+            domain,
+            body
+        )
+        .into_bytes();
+
+        // Reserve and write blob
+        let message_blob = BlobHash::from(message.as_slice());
+        let mut batch = BatchBuilder::new();
+        batch.set(
+            BlobOp::Reserve {
+                hash: message_blob.clone(),
+                until: now() + 120,
+            },
+            0u32.serialize(),
+        );
+        if let Err(err) = self.storage.data.write(batch.build()).await {
+            tracing::error!(
+                context = "directory",
+                event = "error",
+                "Failed to write to data store: {}",
+                err
+            );
+            return;
+        }
+        if let Err(err) = self
+            .storage
+            .blob
+            .put_blob(message_blob.as_slice(), &message)
+            .await
+        {
+            tracing::error!(
+                context = "directory",
+                event = "error",
+                "Failed to write to blob store: {}",
+                err
+            );
+            return;
+        }
+
+        let (result_tx, _) = oneshot::channel();
+        if ipc
+            .delivery_tx
+            .send(DeliveryEvent::Ingest {
+                message: IngestMessage {
+                    sender_address: security.from_address.to_lowercase(),
+                    sender_verified: false,
+                    recipients: vec![rcpt],
+                    message_blob,
+                    message_size: message.len(),
+                },
+                result_tx,
+            })
+            .await
+            .is_err()
+        {
+            tracing::warn!(
+                context = "directory",
+                event = "error",
+                reason = "tx channel closed",
+                "Failed to deliver security notification",
+            );
+        }
+    }
+}
+
+fn parse_range(line: &str) -> Option<(u128, u128, &str)> {
+    let (first, rest) = line.split_once(',')?;
+    let first = first.trim().trim_matches('"');
+
+    if let Some((addr, mask)) = first.split_once('/') {
+        let addr = addr.parse::<IpAddr>().ok()?;
+        let mask = mask.parse::<u32>().ok()?;
+        let mask = if addr.is_ipv4() { mask + 96 } else { mask };
+        if mask > 128 {
+            return None;
+        }
+        let host_bits = u128::MAX.checked_shr(mask).unwrap_or(0);
+        let from = ip_to_u128(&addr) & !host_bits;
+        Some((from, from | host_bits, rest))
+    } else {
+        let (last, rest) = rest.split_once(',')?;
+        let from = ip_to_u128(&first.parse::<IpAddr>().ok()?);
+        let to = ip_to_u128(&last.trim().trim_matches('"').parse::<IpAddr>().ok()?);
+        (from <= to).then_some((from, to, rest))
+    }
+}
+
+fn ip_to_u128(ip: &IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => u128::from(ip.to_ipv6_mapped()),
+        IpAddr::V6(ip) => u128::from(*ip),
+    }
+}
@@ -0,0 +1,577 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use directory::{backend::internal::SpecialSecrets, Principal};
+use ring::{
+    rand::{SecureRandom, SystemRandom},
+    signature::{self, RsaPublicKeyComponents, UnparsedPublicKey},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use utils::config::Config;
+
+use crate::Core;
+
+const CHALLENGE_LEN: usize = 32;
+const MAX_CBOR_DEPTH: usize = 16;
+
+// Authenticator data flags
+const FLAG_UP: u8 = 0x01;
+const FLAG_UV: u8 = 0x04;
+const FLAG_AT: u8 = 0x40;
+
+// COSE algorithms
+const COSE_ES256: i64 = -7;
+const COSE_EDDSA: i64 = -8;
+const COSE_RS256: i64 = -257;
+
+#[derive(Debug, Clone, Default)]
+pub struct WebAuthnConfig {
+    pub rp_id: String,
+    pub rp_name: String,
+    pub origins: Vec<String>,
+    pub timeout: Duration,
+    pub user_verification: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebAuthnCredential {
+    pub name: String,
+    pub id: Vec<u8>,
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistrationCredential {
+    pub id: String,
+    pub response: RegistrationResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistrationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssertionCredential {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    typ: String,
+    challenge: String,
+    origin: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Cbor {
+    Int(i128),
+    Bytes(Vec<u8>),
+    Text(String),
+    Map(Vec<(Cbor, Cbor)>),
+    Simple,
+}
+
+pub type Result<T> = std::result::Result<T, &'static str>;
+
+impl WebAuthnConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        let rp_id = config
+            .value("authentication.webauthn.rp-id")
+            .or_else(|| config.value("lookup.default.hostname"))
+            .unwrap_or("localhost")
+            .to_lowercase();
+
+        WebAuthnConfig {
+            rp_name: config
+                .value("authentication.webauthn.rp-name")
+                .unwrap_or("Stalwart Mail Server")
+                .to_string(),
+            origins: config
+                .values("authentication.webauthn.origins")
+                .map(|(_, v)| v.trim_end_matches('/').to_lowercase())
+                .collect(),
+            timeout: config
+                .property_or_default("authentication.webauthn.timeout", "5m")
+                .unwrap_or_else(|| Duration::from_secs(300)),
+            user_verification: config
+                .property_or_default("authentication.webauthn.user-verification", "false")
+                .unwrap_or(false),
+            rp_id,
+        }
+    }
+
+    pub fn creation_options(
+        &self,
+        challenge: &[u8],
+        principal: &Principal<u32>,
+        credentials: &[WebAuthnCredential],
+    ) -> Value {
+        let algorithms = [COSE_ES256, COSE_EDDSA, COSE_RS256]
+            .into_iter()
+            .map(|alg| json!({"type": "public-key", "alg": alg}))
+            .collect::<Vec<_>>();
+
+        json!({
+            "publicKey": {
+                "rp": {
+                    "id": self.rp_id,
+                    "name": self.rp_name,
+                },
+                "user": {
+                    "id": URL_SAFE_NO_PAD.encode(principal.id.to_be_bytes()),
+                    "name": principal.name,
+                    "displayName": principal.description.as_deref().unwrap_or(&principal.name),
+                },
+                "challenge": URL_SAFE_NO_PAD.encode(challenge),
+                "pubKeyCredParams": algorithms,
+                "timeout": self.timeout.as_millis() as u64,
+                "excludeCredentials": credential_descriptors(credentials),
+                "authenticatorSelection": {
+                    "userVerification": self.user_verification_requirement(),
+                },
+                "attestation": "none",
+            }
+        })
+    }
+
+    pub fn request_options(&self, challenge: &[u8], credentials: &[WebAuthnCredential]) -> Value {
+        json!({
+            "publicKey": {
+                "rpId": self.rp_id,
+                "challenge": URL_SAFE_NO_PAD.encode(challenge),
+                "timeout": self.timeout.as_millis() as u64,
+                "allowCredentials": credential_descriptors(credentials),
+                "userVerification": self.user_verification_requirement(),
+            }
+        })
+    }
+
+    // Verifies a registration ceremony. Attestation statements are not
+    // verified, the authenticator is trusted on first use.
+    pub fn verify_registration(
+        &self,
+        challenge: &[u8],
+        name: String,
+        credential: &RegistrationCredential,
+        credentials: &[WebAuthnCredential],
+    ) -> Result<WebAuthnCredential> {
+        if name.is_empty() || name.contains('$') {
+            return Err("Invalid credential name");
+        } else if credentials.iter().any(|c| c.name == name) {
+            return Err("A credential with this name already exists");
+        }
+
+        self.verify_client_data(
+            &decode_base64(&credential.response.client_data_json)?,
+            "webauthn.create",
+            challenge,
+        )?;
+
+        // Decode attestation object
+        let attestation = decode_base64(&credential.response.attestation_object)?;
+        let auth_data = match Cbor::decode(&attestation) {
+            Some((Cbor::Map(map), _)) => map
+                .into_iter()
+                .find_map(|(key, value)| match (key, value) {
+                    (Cbor::Text(key), Cbor::Bytes(value)) if key == "authData" => Some(value),
+                    _ => None,
+                })
+                .ok_or("Missing authenticator data")?,
+            _ => return Err("Invalid attestation object"),
+        };
+        let (flags, sign_count) = self.verify_authenticator_data(&auth_data)?;
+        if flags & FLAG_AT == 0 {
+            return Err("Missing attested credential data");
+        }
+
+        // Parse attested credential data
+        let id_len = auth_data
+            .get(53..55)
+            .map(|len| u16::from_be_bytes([len[0], len[1]]) as usize)
+            .ok_or("Invalid attested credential data")?;
+        let id = auth_data
+            .get(55..55 + id_len)
+            .ok_or("Invalid attested credential data")?
+            .to_vec();
+        let key_bytes = &auth_data[55 + id_len..];
+        let public_key = match Cbor::decode(key_bytes) {
+            Some((cbor @ Cbor::Map(_), len)) if is_supported_key(&cbor) => {
+                key_bytes[..len].to_vec()
+            }
+            Some(_) => return Err("Unsupported public key algorithm"),
+            None => return Err("Invalid credential public key"),
+        };
+        if decode_base64(&credential.id)? != id {
+            return Err("Credential ID mismatch");
+        } else if credentials.iter().any(|c| c.id == id) {
+            return Err("Credential is already registered");
+        }
+
+        Ok(WebAuthnCredential {
+            name,
+            id,
+            public_key,
+            sign_count,
+        })
+    }
+
+    // Verifies an assertion ceremony, returning the credential that was used
+    // with its updated signature counter
+    pub fn verify_assertion(
+        &self,
+        challenge: &[u8],
+        assertion: &AssertionCredential,
+        credentials: &[WebAuthnCredential],
+    ) -> Result<WebAuthnCredential> {
+        let id = decode_base64(&assertion.id)?;
+        let mut credential = credentials
+            .iter()
+            .find(|c| c.id == id)
+            .ok_or("Unknown credential")?
+            .clone();
+
+        let client_data = decode_base64(&assertion.response.client_data_json)?;
+        self.verify_client_data(&client_data, "webauthn.get", challenge)?;
+        let auth_data = decode_base64(&assertion.response.authenticator_data)?;
+        let (_, sign_count) = self.verify_authenticator_data(&auth_data)?;
+
+        // Verify signature over the authenticator data and client data hash
+        let mut message = auth_data;
+        message.extend_from_slice(&Sha256::digest(&client_data));
+        verify_signature(
+            &credential.public_key,
+            &message,
+            &decode_base64(&assertion.response.signature)?,
+        )?;
+
+        // A counter that does not increase could indicate a cloned authenticator
+        if sign_count != 0 || credential.sign_count != 0 {
+            if sign_count <= credential.sign_count {
+                return Err("Invalid signature counter");
+            }
+            credential.sign_count = sign_count;
+        }
+
+        Ok(credential)
+    }
+
+    fn verify_client_data(&self, client_data: &[u8], typ: &str, challenge: &[u8]) -> Result<()> {
+        let client_data =
+            serde_json::from_slice::<ClientData>(client_data).map_err(|_| "Invalid client data")?;
+
+        if client_data.typ != typ {
+            Err("Invalid ceremony type")
+        } else if decode_base64(&client_data.challenge)? != challenge {
+            Err("Invalid challenge")
+        } else if !self.is_valid_origin(&client_data.origin) {
+            Err("Invalid origin")
+        } else {
+            Ok(())
+        }
+    }
+
+    fn verify_authenticator_data(&self, auth_data: &[u8]) -> Result<(u8, u32)> {
+        if auth_data.len() < 37 {
+            return Err("Invalid authenticator data");
+        } else if auth_data[..32] != Sha256::digest(self.rp_id.as_bytes())[..] {
+            return Err("Invalid relying party");
+        }
+
+        let flags = auth_data[32];
+        if flags & FLAG_UP == 0 {
+            Err("User presence is required")
+        } else if self.user_verification && flags & FLAG_UV == 0 {
+            Err("User verification is required")
+        } else {
+            Ok((
+                flags,
+                u32::from_be_bytes([auth_data[33], auth_data[34], auth_data[35], auth_data[36]]),
+            ))
+        }
+    }
+
+    fn is_valid_origin(&self, origin: &str) -> bool {
+        let origin = origin.trim_end_matches('/').to_lowercase();
+        if !self.origins.is_empty() {
+            return self.origins.contains(&origin);
+        }
+
+        // The RP ID has to be equal to or a registrable suffix of the origin's host
+        let (host, is_https) = if let Some(host) = origin.strip_prefix("https://") {
+            (host, true)
+        } else if let Some(host) = origin.strip_prefix("http://") {
+            (host, false)
+        } else {
+            return false;
+        };
+        let host = host.rsplit_once(':').map_or(host, |(host, _)| host);
+        (is_https || host == "localhost")
+            && (host == self.rp_id
+                || host
+                    .strip_suffix(&self.rp_id)
+                    .map_or(false, |prefix| prefix.ends_with('.')))
+    }
+
+    fn user_verification_requirement(&self) -> &'static str {
+        if self.user_verification {
+            "required"
+        } else {
+            "preferred"
+        }
+    }
+}
+
+impl WebAuthnCredential {
+    // Credentials are stored as $webauthn$<name>$<id>$<public-key>$<counter>
+    pub fn parse(secret: &str) -> Option<Self> {
+        let mut parts = secret.strip_prefix("$webauthn$")?.split('$');
+        let name = parts.next()?.to_string();
+        let id = URL_SAFE_NO_PAD.decode(parts.next()?).ok()?;
+        let public_key = URL_SAFE_NO_PAD.decode(parts.next()?).ok()?;
+        let sign_count = parts.next()?.parse().ok()?;
+
+        Some(WebAuthnCredential {
+            name,
+            id,
+            public_key,
+            sign_count,
+        })
+    }
+
+    pub fn from_principal(principal: &Principal<u32>) -> Vec<Self> {
+        principal
+            .secrets
+            .iter()
+            .filter(|secret| secret.is_webauthn())
+            .filter_map(|secret| Self::parse(secret))
+            .collect()
+    }
+
+    pub fn to_secret(&self) -> String {
+        format!(
+            "$webauthn${}${}${}${}",
+            self.name,
+            URL_SAFE_NO_PAD.encode(&self.id),
+            URL_SAFE_NO_PAD.encode(&self.public_key),
+            self.sign_count
+        )
+    }
+}
+
+impl Core {
+    pub async fn webauthn_challenge(
+        &self,
+        account_id: u32,
+        ceremony: &str,
+    ) -> store::Result<Vec<u8>> {
+        let mut challenge = vec![0u8; CHALLENGE_LEN];
+        SystemRandom::new()
+            .fill(&mut challenge)
+            .map_err(|_| store::Error::InternalError("Failed to generate challenge".into()))?;
+        self.storage
+            .lookup
+            .key_set(
+                format!("webauthn:{ceremony}:{account_id}").into_bytes(),
+                URL_SAFE_NO_PAD.encode(&challenge).into_bytes(),
+                self.jmap.webauthn.timeout.as_secs().into(),
+            )
+            .await?;
+
+        Ok(challenge)
+    }
+
+    // Challenges can only be used once
+    pub async fn webauthn_take_challenge(
+        &self,
+        account_id: u32,
+        ceremony: &str,
+    ) -> store::Result<Option<Vec<u8>>> {
+        let key = format!("webauthn:{ceremony}:{account_id}").into_bytes();
+        let challenge = self.storage.lookup.key_get::<String>(key.clone()).await?;
+        if challenge.is_some() {
+            self.storage.lookup.key_delete(key).await?;
+        }
+
+        Ok(challenge.and_then(|challenge| URL_SAFE_NO_PAD.decode(challenge).ok()))
+    }
+}
+
+fn credential_descriptors(credentials: &[WebAuthnCredential]) -> Vec<Value> {
+    credentials
+        .iter()
+        .map(|c| json!({"type": "public-key", "id": URL_SAFE_NO_PAD.encode(&c.id)}))
+        .collect()
+}
+
+fn decode_base64(value: &str) -> Result<Vec<u8>> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| "Invalid base64url encoding")
+}
+
+fn is_supported_key(key: &Cbor) -> bool {
+    matches!(
+        (key.get_int(1), key.get_int(3)),
+        (Some(2), Some(COSE_ES256)) | (Some(1), Some(COSE_EDDSA)) | (Some(3), Some(COSE_RS256))
+    )
+}
+
+fn verify_signature(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<()> {
+    let key = match Cbor::decode(public_key) {
+        Some((key @ Cbor::Map(_), _)) => key,
+        _ => return Err("Invalid credential public key"),
+    };
+
+    let result = match (key.get_int(1), key.get_int(3)) {
+        (Some(2), Some(COSE_ES256)) => {
+            let (x, y) = key
+                .get_bytes(-2)
+                .zip(key.get_bytes(-3))
+                .filter(|(x, y)| x.len() == 32 && y.len() == 32 && key.get_int(-1) == Some(1))
+                .ok_or("Invalid credential public key")?;
+            let mut point = Vec::with_capacity(65);
+            point.push(0x04);
+            point.extend_from_slice(x);
+            point.extend_from_slice(y);
+            UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, point)
+                .verify(message, signature)
+        }
+        (Some(1), Some(COSE_EDDSA)) => {
+            let x = key
+                .get_bytes(-2)
+                .filter(|x| x.len() == 32 && key.get_int(-1) == Some(6))
+                .ok_or("Invalid credential public key")?;
+            UnparsedPublicKey::new(&signature::ED25519, x).verify(message, signature)
+        }
+        (Some(3), Some(COSE_RS256)) => {
+            let (n, e) = key
+                .get_bytes(-1)
+                .zip(key.get_bytes(-2))
+                .ok_or("Invalid credential public key")?;
+            RsaPublicKeyComponents { n, e }.verify(
+                &signature::RSA_PKCS1_2048_8192_SHA256,
+                message,
+                signature,
+            )
+        }
+        _ => return Err("Unsupported public key algorithm"),
+    };
+
+    result.map_err(|_| "Invalid signature")
+}
+
+impl Cbor {
+    // Decodes a single CBOR item, returning it along with the number of bytes read.
+    // Only definite lengths are supported, as required by CTAP2 canonical encoding.
+    fn decode(bytes: &[u8]) -> Option<(Cbor, usize)> {
+        let mut pos = 0;
+        Cbor::decode_item(bytes, &mut pos, 0).map(|item| (item, pos))
+    }
+
+    fn decode_item(bytes: &[u8], pos: &mut usize, depth: usize) -> Option<Cbor> {
+        if depth > MAX_CBOR_DEPTH {
+            return None;
+        }
+        let initial = *bytes.get(*pos)?;
+        *pos += 1;
+        let major = initial >> 5;
+        let info = initial & 0x1f;
+        let value = match info {
+            0..=23 => info as u64,
+            24..=27 => {
+                let len = 1 << (info - 24);
+                let value = bytes
+                    .get(*pos..*pos + len)?
+                    .iter()
+                    .fold(0u64, |acc, b| (acc << 8) | *b as u64);
+                *pos += len;
+                value
+            }
+            _ => return None,
+        };
+
+        match major {
+            0 => Some(Cbor::Int(value as i128)),
+            1 => Some(Cbor::Int(-1 - value as i128)),
+            2 | 3 => {
+                let len = usize::try_from(value).ok()?;
+                let data = bytes.get(*pos..pos.checked_add(len)?)?.to_vec();
+                *pos += len;
+                if major == 2 {
+                    Some(Cbor::Bytes(data))
+                } else {
+                    String::from_utf8(data).ok().map(Cbor::Text)
+                }
+            }
+            4 => {
+                // Arrays are only found in attestation statements, which are not verified
+                for _ in 0..value {
+                    Cbor::decode_item(bytes, pos, depth + 1)?;
+                }
+                Some(Cbor::Simple)
+            }
+            5 => {
+                let mut items = Vec::new();
+                for _ in 0..value {
+                    let key = Cbor::decode_item(bytes, pos, depth + 1)?;
+                    let value = Cbor::decode_item(bytes, pos, depth + 1)?;
+                    items.push((key, value));
+                }
+                Some(Cbor::Map(items))
+            }
+            6 => Cbor::decode_item(bytes, pos, depth + 1),
+            _ => Some(Cbor::Simple),
+        }
+    }
+
+    fn get(&self, key: i64) -> Option<&Cbor> {
+        match self {
+            Cbor::Map(items) => items.iter().find_map(|(k, v)| {
+                if *k == Cbor::Int(key as i128) {
+                    Some(v)
+                } else {
+                    None
+                }
+            }),
+            _ => None,
+        }
+    }
+
+    fn get_int(&self, key: i64) -> Option<i64> {
+        match self.get(key)? {
+            Cbor::Int(value) => i64::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+
+    fn get_bytes(&self, key: i64) -> Option<&[u8]> {
+        match self.get(key)? {
+            Cbor::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+}
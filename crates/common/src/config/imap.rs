@@ -20,6 +20,8 @@ pub struct ImapConfig {
 
     pub rate_requests: Option<Rate>,
     pub rate_concurrent: Option<u64>,
+
+    pub recently_deleted_folder: String,
 }
 
 impl ImapConfig {
@@ -49,6 +51,10 @@ impl ImapConfig {
             allow_plain_auth: config
                 .property_or_default("imap.auth.allow-plain-text", "false")
                 .unwrap_or(false),
+            recently_deleted_folder: config
+                .value("imap.folders.recently-deleted.name")
+                .unwrap_or("Recently Deleted")
+                .to_string(),
         }
     }
 }
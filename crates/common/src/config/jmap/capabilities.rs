@@ -7,9 +7,9 @@
 use ahash::AHashSet;
 use jmap_proto::{
     request::capability::{
-        BlobCapabilities, Capabilities, Capability, CoreCapabilities, EmptyCapabilities,
-        MailCapabilities, SieveAccountCapabilities, SieveSessionCapabilities,
-        SubmissionCapabilities,
+        BlobCapabilities, CalendarsAccountCapabilities, Capabilities, Capability, CoreCapabilities,
+        EmptyCapabilities, MailCapabilities, PrincipalsAccountCapabilities,
+        SieveAccountCapabilities, SieveSessionCapabilities, SubmissionCapabilities,
     },
     types::type_state::DataType,
 };
@@ -98,6 +98,42 @@ impl JmapConfig {
             Capabilities::Empty(EmptyCapabilities::default()),
         );
 
+        // Add MDN capabilities
+        self.capabilities.session.append(
+            Capability::Mdn,
+            Capabilities::Empty(EmptyCapabilities::default()),
+        );
+        self.capabilities.account.append(
+            Capability::Mdn,
+            Capabilities::Empty(EmptyCapabilities::default()),
+        );
+
+        // Add Principals capabilities
+        self.capabilities.session.append(
+            Capability::Principals,
+            Capabilities::Empty(EmptyCapabilities::default()),
+        );
+        self.capabilities.account.append(
+            Capability::Principals,
+            Capabilities::PrincipalsAccount(PrincipalsAccountCapabilities::default()),
+        );
+
+        // Add Calendars capabilities
+        self.capabilities.session.append(
+            Capability::Calendars,
+            Capabilities::Empty(EmptyCapabilities::default()),
+        );
+        self.capabilities.account.append(
+            Capability::Calendars,
+            Capabilities::CalendarsAccount(CalendarsAccountCapabilities {
+                max_calendars_per_event: None,
+                min_date_time: "1970-01-01T00:00:00".to_string(),
+                max_date_time: "2100-01-01T00:00:00".to_string(),
+                max_participants_per_event: Some(self.calendar_max_participants),
+                may_create_calendar: true,
+            }),
+        );
+
         // Add Sieve capabilities
         let mut notification_methods = Vec::new();
 
@@ -5,4 +5,5 @@
  */
 
 pub mod capabilities;
+pub mod oauth;
 pub mod settings;
@@ -0,0 +1,277 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::io::Cursor;
+
+use ahash::AHashMap;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::{
+    rand::SystemRandom,
+    signature::{
+        EcdsaKeyPair, EcdsaSigningAlgorithm, Ed25519KeyPair, KeyPair, RsaEncoding, RsaKeyPair,
+        RsaPublicKeyComponents, ECDSA_P256_SHA256_FIXED_SIGNING, ECDSA_P384_SHA384_FIXED_SIGNING,
+        RSA_PKCS1_SHA256, RSA_PKCS1_SHA384, RSA_PKCS1_SHA512, RSA_PSS_SHA256, RSA_PSS_SHA384,
+        RSA_PSS_SHA512,
+    },
+};
+use rustls_pemfile::{read_one, Item};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use utils::config::Config;
+
+// Client ids are embedded in access tokens and have a maximum length
+const CLIENT_ID_MAX_LEN: usize = 20;
+
+#[derive(Debug, Clone, Default)]
+pub struct OAuthClient {
+    pub name: Option<String>,
+    pub redirect_uris: Vec<String>,
+    pub secret: Option<String>,
+}
+
+pub struct OidcSigner {
+    pub algorithm: &'static str,
+    pub kid: String,
+    pub jwk: Value,
+    key: SigningKey,
+}
+
+enum SigningKey {
+    Rsa(RsaKeyPair, &'static dyn RsaEncoding),
+    Ecdsa(EcdsaKeyPair),
+    Ed25519(Ed25519KeyPair),
+}
+
+impl OAuthClient {
+    pub fn parse_all(config: &mut Config) -> AHashMap<String, OAuthClient> {
+        let mut clients = AHashMap::new();
+
+        for id in config
+            .sub_keys("oauth.client", "")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+        {
+            if id.len() > CLIENT_ID_MAX_LEN {
+                config.new_parse_error(
+                    ("oauth.client", id.as_str()),
+                    format!("Client ids cannot exceed {CLIENT_ID_MAX_LEN} characters"),
+                );
+                continue;
+            }
+
+            let client = OAuthClient {
+                name: config
+                    .value(("oauth.client", id.as_str(), "name"))
+                    .map(|v| v.to_string()),
+                redirect_uris: config
+                    .values(("oauth.client", id.as_str(), "redirect-uri"))
+                    .map(|(_, v)| v.to_string())
+                    .collect(),
+                secret: config
+                    .value(("oauth.client", id.as_str(), "secret"))
+                    .map(|v| v.to_string()),
+            };
+            if client.redirect_uris.is_empty() {
+                config.new_parse_error(
+                    ("oauth.client", id.as_str(), "redirect-uri"),
+                    "At least one redirect URI is required",
+                );
+                continue;
+            }
+
+            clients.insert(id, client);
+        }
+
+        clients
+    }
+
+    pub fn is_valid_redirect_uri(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.iter().any(|uri| uri == redirect_uri)
+    }
+}
+
+impl OidcSigner {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        let pem = config.value("oauth.oidc.signature-key")?.to_string();
+        let algorithm = config
+            .value("oauth.oidc.signature-algorithm")
+            .unwrap_or("RS256")
+            .to_string();
+
+        match OidcSigner::from_pem(&algorithm, pem.as_bytes()) {
+            Ok(signer) => Some(signer),
+            Err(err) => {
+                config.new_build_error("oauth.oidc.signature-key", err);
+                None
+            }
+        }
+    }
+
+    pub fn from_pem(algorithm: &str, pem: &[u8]) -> Result<Self, String> {
+        let (pkcs1, pkcs8) = match read_one(&mut Cursor::new(pem))
+            .map_err(|err| format!("Failed to read private key: {err}"))?
+        {
+            Some(Item::Pkcs8Key(key)) => (None, Some(key)),
+            Some(Item::Pkcs1Key(key)) => (Some(key), None),
+            Some(_) => return Err("Unsupported private key type.".to_string()),
+            None => return Err("No private keys found.".to_string()),
+        };
+
+        let (algorithm, key, jwk) = match algorithm {
+            "RS256" | "RS384" | "RS512" | "PS256" | "PS384" | "PS512" => {
+                let key = if let Some(pkcs8) = &pkcs8 {
+                    RsaKeyPair::from_pkcs8(pkcs8.secret_pkcs8_der())
+                } else {
+                    RsaKeyPair::from_der(pkcs1.as_ref().unwrap().secret_pkcs1_der())
+                }
+                .map_err(|err| format!("Invalid RSA private key: {err}"))?;
+                let (algorithm, encoding): (_, &'static dyn RsaEncoding) = match algorithm {
+                    "RS256" => ("RS256", &RSA_PKCS1_SHA256),
+                    "RS384" => ("RS384", &RSA_PKCS1_SHA384),
+                    "RS512" => ("RS512", &RSA_PKCS1_SHA512),
+                    "PS256" => ("PS256", &RSA_PSS_SHA256),
+                    "PS384" => ("PS384", &RSA_PSS_SHA384),
+                    _ => ("PS512", &RSA_PSS_SHA512),
+                };
+                let public = RsaPublicKeyComponents::<Vec<u8>>::from(key.public());
+                let jwk = json!({
+                    "kty": "RSA",
+                    "e": URL_SAFE_NO_PAD.encode(&public.e),
+                    "n": URL_SAFE_NO_PAD.encode(&public.n),
+                });
+
+                (algorithm, SigningKey::Rsa(key, encoding), jwk)
+            }
+            "ES256" | "ES384" => {
+                let (algorithm, signing, crv): (_, &'static EcdsaSigningAlgorithm, _) =
+                    if algorithm == "ES256" {
+                        ("ES256", &ECDSA_P256_SHA256_FIXED_SIGNING, "P-256")
+                    } else {
+                        ("ES384", &ECDSA_P384_SHA384_FIXED_SIGNING, "P-384")
+                    };
+                let key = EcdsaKeyPair::from_pkcs8(
+                    signing,
+                    pkcs8
+                        .as_ref()
+                        .ok_or("ECDSA keys must be in PKCS#8 format.")?
+                        .secret_pkcs8_der(),
+                    &SystemRandom::new(),
+                )
+                .map_err(|err| format!("Invalid ECDSA private key: {err}"))?;
+
+                // Uncompressed point: 0x04 || x || y
+                let point = &key.public_key().as_ref()[1..];
+                let (x, y) = point.split_at(point.len() / 2);
+                let jwk = json!({
+                    "kty": "EC",
+                    "crv": crv,
+                    "x": URL_SAFE_NO_PAD.encode(x),
+                    "y": URL_SAFE_NO_PAD.encode(y),
+                });
+
+                (algorithm, SigningKey::Ecdsa(key), jwk)
+            }
+            "EdDSA" => {
+                let key = Ed25519KeyPair::from_pkcs8_maybe_unchecked(
+                    pkcs8
+                        .as_ref()
+                        .ok_or("Ed25519 keys must be in PKCS#8 format.")?
+                        .secret_pkcs8_der(),
+                )
+                .map_err(|err| format!("Invalid Ed25519 private key: {err}"))?;
+                let jwk = json!({
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "x": URL_SAFE_NO_PAD.encode(key.public_key().as_ref()),
+                });
+
+                ("EdDSA", SigningKey::Ed25519(key), jwk)
+            }
+            _ => return Err(format!("Unsupported signature algorithm {algorithm:?}.")),
+        };
+
+        // Key id is the RFC 7638 thumbprint of the public key
+        let kid = URL_SAFE_NO_PAD.encode(Sha256::digest(thumbprint_input(&jwk).as_bytes()));
+        let mut jwk = jwk;
+        jwk["kid"] = Value::String(kid.clone());
+        jwk["use"] = Value::String("sig".to_string());
+        jwk["alg"] = Value::String(algorithm.to_string());
+
+        Ok(OidcSigner {
+            algorithm,
+            kid,
+            jwk,
+            key,
+        })
+    }
+
+    pub fn sign(&self, claims: &Value) -> Result<String, &'static str> {
+        let header = json!({
+            "alg": self.algorithm,
+            "typ": "JWT",
+            "kid": self.kid,
+        });
+        let message = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let rng = SystemRandom::new();
+        let signature = match &self.key {
+            SigningKey::Rsa(key, encoding) => {
+                let mut signature = vec![0; key.public().modulus_len()];
+                key.sign(*encoding, &rng, message.as_bytes(), &mut signature)
+                    .map_err(|_| "Failed to sign token.")?;
+                signature
+            }
+            SigningKey::Ecdsa(key) => key
+                .sign(&rng, message.as_bytes())
+                .map_err(|_| "Failed to sign token.")?
+                .as_ref()
+                .to_vec(),
+            SigningKey::Ed25519(key) => key.sign(message.as_bytes()).as_ref().to_vec(),
+        };
+
+        Ok(format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    pub fn jwks(&self) -> Value {
+        json!({
+            "keys": [self.jwk],
+        })
+    }
+}
+
+fn thumbprint_input(jwk: &Value) -> String {
+    // Required members in lexicographic order
+    let members: &[&str] = match jwk["kty"].as_str() {
+        Some("RSA") => &["e", "kty", "n"],
+        Some("EC") => &["crv", "kty", "x", "y"],
+        _ => &["crv", "kty", "x"],
+    };
+
+    let mut input = String::from("{");
+    for (pos, member) in members.iter().enumerate() {
+        if pos > 0 {
+            input.push(',');
+        }
+        input.push_str(&format!(
+            "\"{member}\":\"{}\"",
+            jwk[member].as_str().unwrap_or_default()
+        ));
+    }
+    input.push('}');
+    input
+}
+
+impl std::fmt::Debug for OidcSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OidcSigner")
+            .field("algorithm", &self.algorithm)
+            .field("kid", &self.kid)
+            .finish()
+    }
+}
@@ -4,14 +4,20 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{str::FromStr, time::Duration};
+use std::{str::FromStr, sync::Arc, time::Duration};
 
+use ahash::AHashMap;
+use directory::core::policy::PasswordPolicy;
 use jmap_proto::request::capability::BaseCapabilities;
 use mail_parser::HeaderName;
 use nlp::language::Language;
 use store::rand::{distributions::Alphanumeric, thread_rng, Rng};
 use utils::config::{cron::SimpleCron, utils::ParseValue, Config, Rate};
 
+use crate::auth::{oidc::OidcValidator, webauthn::WebAuthnConfig};
+
+use super::oauth::{OAuthClient, OidcSigner};
+
 #[derive(Default, Clone)]
 pub struct JmapConfig {
     pub default_language: Language,
@@ -41,10 +47,22 @@ pub struct JmapConfig {
     pub mail_parse_max_items: usize,
     pub mail_max_size: usize,
     pub mail_autoexpunge_after: Option<Duration>,
+    pub mail_snooze_frequency: Duration,
+    pub mail_thread_strategy: ThreadStrategy,
+    pub mail_thread_window: Option<Duration>,
+    pub mail_undelete_retention: Option<Duration>,
 
     pub sieve_max_script_name: usize,
     pub sieve_max_scripts: usize,
 
+    pub calendar_max_participants: usize,
+    pub calendar_max_occurrences: usize,
+    pub calendar_default_name: String,
+
+    pub template_max_size: usize,
+    pub template_name_max_len: usize,
+    pub template_max_templates: usize,
+
     pub session_cache_ttl: Duration,
     pub rate_authenticated: Option<Rate>,
     pub rate_authenticate_req: Option<Rate>,
@@ -70,8 +88,15 @@ pub struct JmapConfig {
     pub oauth_expiry_refresh_token: u64,
     pub oauth_expiry_refresh_token_renew: u64,
     pub oauth_max_auth_attempts: u32,
+    pub oauth_clients: AHashMap<String, OAuthClient>,
+    pub oauth_require_registration: bool,
+    pub oidc_signer: Option<Arc<OidcSigner>>,
     pub fallback_admin: Option<(String, String)>,
     pub master_user: Option<(String, String)>,
+    pub password_policy: Arc<PasswordPolicy>,
+    pub scim_tokens: Vec<String>,
+    pub oidc: Option<Arc<OidcValidator>>,
+    pub webauthn: WebAuthnConfig,
 
     pub spam_header: Option<(HeaderName<'static>, String)>,
     pub default_folders: Vec<DefaultFolder>,
@@ -111,6 +136,13 @@ pub enum SpecialUse {
     None,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ThreadStrategy {
+    References,
+    #[default]
+    SubjectReferences,
+}
+
 impl JmapConfig {
     pub fn parse(config: &mut Config) -> Self {
         // Parse HTTP headers
@@ -304,12 +336,44 @@ impl JmapConfig {
             mail_autoexpunge_after: config
                 .property_or_default::<Option<Duration>>("jmap.email.auto-expunge", "30d")
                 .unwrap_or_default(),
+            mail_snooze_frequency: config
+                .property_or_default::<Duration>("jmap.email.snooze.frequency", "1m")
+                .unwrap_or_else(|| Duration::from_secs(60)),
+            mail_thread_strategy: config
+                .property_or_default::<ThreadStrategy>(
+                    "jmap.email.threading.strategy",
+                    "subject-references",
+                )
+                .unwrap_or(ThreadStrategy::SubjectReferences),
+            mail_thread_window: config
+                .property_or_default::<Option<Duration>>("jmap.email.threading.window", "false")
+                .unwrap_or_default(),
+            mail_undelete_retention: config
+                .property_or_default::<Option<Duration>>("jmap.email.undelete.retention", "false")
+                .unwrap_or_default(),
             sieve_max_script_name: config
                 .property("sieve.untrusted.limits.name-length")
                 .unwrap_or(512),
             sieve_max_scripts: config
                 .property("sieve.untrusted.limits.max-scripts")
                 .unwrap_or(256),
+            calendar_max_participants: config
+                .property("jmap.calendar.max-participants")
+                .unwrap_or(100),
+            calendar_max_occurrences: config
+                .property("jmap.calendar.max-occurrences")
+                .unwrap_or(1000),
+            calendar_default_name: config
+                .value("jmap.calendar.default-name")
+                .unwrap_or("Calendar")
+                .to_string(),
+            template_max_size: config.property("jmap.template.max-size").unwrap_or(524288),
+            template_name_max_len: config
+                .property("jmap.template.max-name-length")
+                .unwrap_or(255),
+            template_max_templates: config
+                .property("jmap.template.max-templates")
+                .unwrap_or(256),
             capabilities: BaseCapabilities::default(),
             session_cache_ttl: config
                 .property("cache.session.ttl")
@@ -356,6 +420,11 @@ impl JmapConfig {
             oauth_max_auth_attempts: config
                 .property_or_default("oauth.auth.max-attempts", "3")
                 .unwrap_or(10),
+            oauth_clients: OAuthClient::parse_all(config),
+            oauth_require_registration: config
+                .property_or_default("oauth.client-registration.require", "false")
+                .unwrap_or(false),
+            oidc_signer: OidcSigner::parse(config).map(Arc::new),
             event_source_throttle: config
                 .property_or_default("jmap.event-source.throttle", "1s")
                 .unwrap_or_else(|| Duration::from_secs(1)),
@@ -431,6 +500,14 @@ impl JmapConfig {
                     .value("authentication.master.secret")
                     .map(|p| (u.to_string(), p.to_string()))
             }),
+            password_policy: Arc::new(PasswordPolicy::parse(config)),
+            scim_tokens: config
+                .values("scim.token")
+                .map(|(_, v)| v.to_string())
+                .filter(|v| !v.is_empty())
+                .collect(),
+            oidc: OidcValidator::parse(config).map(Arc::new),
+            webauthn: WebAuthnConfig::parse(config),
             default_folders,
             shared_folder,
         };
@@ -456,3 +533,13 @@ impl ParseValue for SpecialUse {
         }
     }
 }
+
+impl ParseValue for ThreadStrategy {
+    fn parse_value(value: &str) -> utils::config::Result<Self> {
+        match value {
+            "references" => Ok(ThreadStrategy::References),
+            "subject-references" => Ok(ThreadStrategy::SubjectReferences),
+            other => Err(format!("Unknown threading strategy {other:?}")),
+        }
+    }
+}
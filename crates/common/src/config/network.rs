@@ -4,9 +4,10 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{str::FromStr, time::Duration};
+use std::{str::FromStr, sync::Arc, time::Duration};
 
 use crate::{
+    auth::security::AccountSecurity,
     expr::{if_block::IfBlock, tokenizer::TokenMap},
     listener::blocked::{AllowedIps, BlockedIps},
     webhooks::{Webhook, WebhookType, Webhooks},
@@ -32,6 +33,7 @@ impl Default for Network {
                 [],
                 "protocol + '://' + key_get('default', 'hostname') + ':' + local_port",
             ),
+            security: Default::default(),
         }
     }
 }
@@ -41,6 +43,7 @@ impl Network {
         let mut network = Network {
             blocked_ips: BlockedIps::parse(config),
             allowed_ips: AllowedIps::parse(config),
+            security: Arc::new(AccountSecurity::parse(config)),
             ..Default::default()
         };
         let token_map = &TokenMap::default().with_variables(CONNECTION_VARS);
@@ -168,6 +171,8 @@ impl FromStr for WebhookType {
             "auth.success" => Ok(Self::AuthSuccess),
             "auth.failure" => Ok(Self::AuthFailure),
             "auth.banned" => Ok(Self::AuthBanned),
+            "auth.locked" => Ok(Self::AuthLocked),
+            "auth.suspicious" => Ok(Self::AuthSuspicious),
             "auth.error" => Ok(Self::AuthError),
             "message.accepted" => Ok(Self::MessageAccepted),
             "message.rejected" => Ok(Self::MessageRejected),
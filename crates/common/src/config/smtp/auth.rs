@@ -6,13 +6,14 @@
 
 use std::{sync::Arc, time::Duration};
 
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
 use mail_auth::{
     common::crypto::{Algorithm, Ed25519Key, HashAlgorithm, RsaKey, Sha256, SigningKey},
     dkim::{Canonicalization, Done},
 };
 use mail_parser::decoders::base64::base64_decode;
 use utils::config::{
+    cron::SimpleCron,
     utils::{AsKey, ParseValue},
     Config,
 };
@@ -41,12 +42,22 @@ pub struct DkimAuthConfig {
     pub verify: IfBlock,
     pub sign: IfBlock,
     pub strict: bool,
+    pub rotation: Option<DkimRotation>,
+}
+
+#[derive(Clone)]
+pub struct DkimRotation {
+    pub frequency: SimpleCron,
+    pub interval: Duration,
+    pub grace_period: Duration,
+    pub retire_after: Duration,
 }
 
 #[derive(Clone)]
 pub struct ArcAuthConfig {
     pub verify: IfBlock,
     pub seal: IfBlock,
+    pub trusted_sealers: AHashSet<String>,
 }
 
 #[derive(Clone)]
@@ -103,6 +114,7 @@ impl Default for MailAuthConfig {
                     "false",
                 ),
                 strict: true,
+                rotation: None,
             },
             arc: ArcAuthConfig {
                 verify: IfBlock::new::<VerifyStrategy>("auth.arc.verify", [], "relaxed"),
@@ -111,6 +123,7 @@ impl Default for MailAuthConfig {
                     [],
                     "'rsa-' + key_get('default', 'domain')",
                 ),
+                trusted_sealers: AHashSet::new(),
             },
             spf: SpfAuthConfig {
                 verify_ehlo: IfBlock::new::<VerifyStrategy>(
@@ -191,6 +204,31 @@ impl MailAuthConfig {
         mail_auth.dkim.strict = config
             .property_or_default("auth.dkim.strict", "true")
             .unwrap_or(true);
+        mail_auth.arc.trusted_sealers = config
+            .values("auth.arc.trusted-sealers")
+            .map(|(_, domain)| domain.trim().to_lowercase())
+            .filter(|domain| !domain.is_empty())
+            .collect();
+        if config
+            .property_or_default("auth.dkim.rotation.enable", "false")
+            .unwrap_or(false)
+        {
+            mail_auth.dkim.rotation = DkimRotation {
+                frequency: config
+                    .property_or_default::<SimpleCron>("auth.dkim.rotation.frequency", "0 3 *")
+                    .unwrap_or_else(|| SimpleCron::parse_value("0 3 *").unwrap()),
+                interval: config
+                    .property_or_default("auth.dkim.rotation.interval", "90d")
+                    .unwrap_or(Duration::from_secs(90 * 86400)),
+                grace_period: config
+                    .property_or_default("auth.dkim.rotation.grace-period", "7d")
+                    .unwrap_or(Duration::from_secs(7 * 86400)),
+                retire_after: config
+                    .property_or_default("auth.dkim.rotation.retire-after", "7d")
+                    .unwrap_or(Duration::from_secs(7 * 86400)),
+            }
+            .into();
+        }
 
         // Parse signatures
         for id in config
@@ -0,0 +1,228 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use directory::{Principal, Type};
+use store::rand::{distributions::Alphanumeric, thread_rng, Rng};
+use utils::config::Config;
+
+use crate::expr::{if_block::IfBlock, tokenizer::TokenMap};
+
+use super::*;
+
+#[derive(Clone)]
+pub struct MailingListConfig {
+    pub secret: String,
+    pub moderation_expire: Duration,
+    pub bounce_threshold: u32,
+    pub unsubscribe_url: IfBlock,
+    pub sign: IfBlock,
+}
+
+#[derive(Debug, Clone)]
+pub struct MailingList {
+    pub id: u32,
+    pub address: String,
+    pub name: String,
+    pub policy: ListPolicy,
+    pub moderators: Vec<String>,
+    pub allow_subscribe: bool,
+    pub subject_prefix: Option<String>,
+    pub rewrite_from: FromRewrite,
+    pub archive: Option<String>,
+    pub one_click: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ListSettings {
+    pub policy: ListPolicy,
+    pub moderators: Vec<String>,
+    #[serde(rename = "allowSubscribe")]
+    pub allow_subscribe: bool,
+    #[serde(rename = "subjectPrefix")]
+    pub subject_prefix: Option<String>,
+    #[serde(rename = "rewriteFrom")]
+    pub rewrite_from: FromRewrite,
+    pub archive: Option<String>,
+    #[serde(rename = "oneClick")]
+    pub one_click: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ListPolicy {
+    #[serde(rename = "open")]
+    Open,
+    #[serde(rename = "members-only")]
+    MembersOnly,
+    #[serde(rename = "moderated")]
+    Moderated,
+    #[serde(rename = "announce-only")]
+    AnnounceOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum FromRewrite {
+    #[serde(rename = "never")]
+    Never,
+    #[serde(rename = "dmarc")]
+    Dmarc,
+    #[serde(rename = "always")]
+    Always,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListCommand {
+    Post,
+    Subscribe,
+    Confirm { token: String },
+    Unsubscribe,
+    Approve { id: u64, token: String },
+    Reject { id: u64, token: String },
+    Bounces,
+}
+
+impl MailingListConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        let rcpt_domain_vars = TokenMap::default().with_variables(RCPT_DOMAIN_VARS);
+        let mut lists = MailingListConfig {
+            secret: config
+                .value("list.secret")
+                .filter(|v| !v.is_empty())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| {
+                    thread_rng()
+                        .sample_iter(Alphanumeric)
+                        .take(64)
+                        .map(char::from)
+                        .collect::<String>()
+                }),
+            moderation_expire: config
+                .property_or_default::<Duration>("list.moderation.expire", "7d")
+                .unwrap_or_else(|| Duration::from_secs(7 * 86400)),
+            bounce_threshold: config
+                .property_or_default("list.bounce.threshold", "5")
+                .unwrap_or(5),
+            unsubscribe_url: IfBlock::new::<()>(
+                "list.unsubscribe.url",
+                [],
+                "'https://' + key_get('default', 'hostname')",
+            ),
+            sign: IfBlock::new::<()>(
+                "list.sign",
+                [],
+                "['rsa-' + key_get('default', 'domain'), 'ed25519-' + key_get('default', 'domain')]",
+            ),
+        };
+        for (value, key) in [
+            (&mut lists.unsubscribe_url, "list.unsubscribe.url"),
+            (&mut lists.sign, "list.sign"),
+        ] {
+            if let Some(if_block) = IfBlock::try_parse(config, key, &rcpt_domain_vars) {
+                *value = if_block;
+            }
+        }
+
+        lists
+    }
+}
+
+impl MailingList {
+    pub fn new(principal: Principal<u32>, settings: ListSettings) -> Option<Self> {
+        if principal.typ != Type::List {
+            return None;
+        }
+
+        Some(MailingList {
+            id: principal.id,
+            address: principal.emails.into_iter().next()?,
+            name: principal.description.unwrap_or(principal.name),
+            policy: settings.policy,
+            moderators: settings.moderators,
+            allow_subscribe: settings.allow_subscribe,
+            subject_prefix: settings.subject_prefix.filter(|v| !v.is_empty()),
+            rewrite_from: settings.rewrite_from,
+            archive: settings.archive.filter(|v| !v.is_empty()),
+            one_click: settings.one_click,
+        })
+    }
+
+    pub fn is_moderator(&self, address: &str) -> bool {
+        self.moderators.iter().any(|m| m == address)
+    }
+
+    pub fn command_address(&self, command: &str) -> String {
+        let (local_part, domain) = self
+            .address
+            .rsplit_once('@')
+            .unwrap_or((self.address.as_str(), ""));
+        format!("{local_part}+{command}@{domain}")
+    }
+
+    pub fn list_id(&self) -> String {
+        self.address.replace('@', ".")
+    }
+}
+
+impl ListSettings {
+    pub fn normalize(mut self) -> Self {
+        for moderator in &mut self.moderators {
+            *moderator = moderator.trim().to_lowercase();
+        }
+        self.moderators.retain(|m| m.contains('@'));
+        self.archive = self
+            .archive
+            .map(|archive| archive.trim().to_lowercase())
+            .filter(|archive| archive.contains('@'));
+        self
+    }
+}
+
+impl ListCommand {
+    pub fn parse(command: &str) -> Option<Self> {
+        let (name, args) = command.split_once('-').unwrap_or((command, ""));
+        match name {
+            "subscribe" if args.is_empty() => Some(ListCommand::Subscribe),
+            "unsubscribe" if args.is_empty() => Some(ListCommand::Unsubscribe),
+            "bounces" if args.is_empty() => Some(ListCommand::Bounces),
+            "confirm" if !args.is_empty() => Some(ListCommand::Confirm {
+                token: args.to_string(),
+            }),
+            "approve" | "reject" => {
+                let (id, token) = args.split_once('-')?;
+                let id = id.parse().ok()?;
+                let token = token.to_string();
+                if name == "approve" {
+                    Some(ListCommand::Approve { id, token })
+                } else {
+                    Some(ListCommand::Reject { id, token })
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Default for ListSettings {
+    fn default() -> Self {
+        ListSettings {
+            policy: ListPolicy::MembersOnly,
+            moderators: Vec::new(),
+            allow_subscribe: true,
+            subject_prefix: None,
+            rewrite_from: FromRewrite::Dmarc,
+            archive: None,
+            one_click: true,
+        }
+    }
+}
+
+impl Default for MailingListConfig {
+    fn default() -> Self {
+        Self::parse(&mut Config::default())
+    }
+}
@@ -7,6 +7,8 @@
 use utils::config::{Config, Rate};
 
 pub mod auth;
+pub mod list;
+pub mod quarantine;
 pub mod queue;
 pub mod report;
 pub mod resolver;
@@ -16,8 +18,8 @@ pub mod throttle;
 use crate::expr::{tokenizer::TokenMap, Expression};
 
 use self::{
-    auth::MailAuthConfig, queue::QueueConfig, report::ReportConfig, resolver::Resolvers,
-    session::SessionConfig,
+    auth::MailAuthConfig, list::MailingListConfig, quarantine::QuarantineConfig,
+    queue::QueueConfig, report::ReportConfig, resolver::Resolvers, session::SessionConfig,
 };
 
 use super::*;
@@ -29,6 +31,8 @@ pub struct SmtpConfig {
     pub resolvers: Resolvers,
     pub mail_auth: MailAuthConfig,
     pub report: ReportConfig,
+    pub quarantine: QuarantineConfig,
+    pub list: MailingListConfig,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -152,6 +156,8 @@ impl SmtpConfig {
             resolvers: Resolvers::parse(config).await,
             mail_auth: MailAuthConfig::parse(config),
             report: ReportConfig::parse(config),
+            quarantine: QuarantineConfig::parse(config),
+            list: MailingListConfig::parse(config),
         }
     }
 }
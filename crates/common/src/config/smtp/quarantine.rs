@@ -0,0 +1,93 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use utils::config::{cron::SimpleCron, utils::ParseValue, Config};
+
+use crate::expr::{if_block::IfBlock, tokenizer::TokenMap};
+
+use super::*;
+
+#[derive(Clone)]
+pub struct QuarantineConfig {
+    pub expire: IfBlock,
+    pub digest: Option<QuarantineDigest>,
+}
+
+#[derive(Clone)]
+pub struct QuarantineDigest {
+    pub frequency: SimpleCron,
+    pub name: IfBlock,
+    pub address: IfBlock,
+    pub subject: IfBlock,
+    pub sign: IfBlock,
+}
+
+impl QuarantineConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        let rcpt_vars = TokenMap::default().with_variables(SMTP_RCPT_TO_VARS);
+        let rcpt_domain_vars = TokenMap::default().with_variables(RCPT_DOMAIN_VARS);
+
+        let mut quarantine = Self {
+            expire: IfBlock::new::<()>("quarantine.expire", [], "30d"),
+            digest: None,
+        };
+        if let Some(if_block) = IfBlock::try_parse(config, "quarantine.expire", &rcpt_vars) {
+            quarantine.expire = if_block;
+        }
+
+        if config
+            .property_or_default("quarantine.digest.enable", "false")
+            .unwrap_or(false)
+        {
+            let mut digest = QuarantineDigest {
+                frequency: config
+                    .property_or_default::<SimpleCron>("quarantine.digest.frequency", "0 8 *")
+                    .unwrap_or_else(|| SimpleCron::parse_value("0 8 *").unwrap()),
+                name: IfBlock::new::<()>(
+                    "quarantine.digest.from-name",
+                    [],
+                    "'Quarantine Notification'",
+                ),
+                address: IfBlock::new::<()>(
+                    "quarantine.digest.from-address",
+                    [],
+                    "'postmaster@' + key_get('default', 'domain')",
+                ),
+                subject: IfBlock::new::<()>(
+                    "quarantine.digest.subject",
+                    [],
+                    "'Quarantined messages summary'",
+                ),
+                sign: IfBlock::new::<()>(
+                    "quarantine.digest.sign",
+                    [],
+                    "['rsa-' + key_get('default', 'domain'), 'ed25519-' + key_get('default', 'domain')]",
+                ),
+            };
+            for (value, key) in [
+                (&mut digest.name, "from-name"),
+                (&mut digest.address, "from-address"),
+                (&mut digest.subject, "subject"),
+                (&mut digest.sign, "sign"),
+            ] {
+                if let Some(if_block) =
+                    IfBlock::try_parse(config, ("quarantine.digest", key), &rcpt_domain_vars)
+                {
+                    *value = if_block;
+                }
+            }
+            quarantine.digest = digest.into();
+        }
+
+        quarantine
+    }
+}
+
+impl Default for QuarantineConfig {
+    fn default() -> Self {
+        Self::parse(&mut Config::default())
+    }
+}
@@ -8,6 +8,7 @@ use ahash::AHashMap;
 use mail_auth::IpLookupStrategy;
 use mail_send::Credentials;
 use utils::config::{
+    cron::SimpleCron,
     utils::{AsKey, ParseValue},
     Config,
 };
@@ -60,6 +61,36 @@ pub struct Dsn {
     pub name: IfBlock,
     pub address: IfBlock,
     pub sign: IfBlock,
+    pub language: IfBlock,
+    pub language_header: Option<String>,
+    pub templates: AHashMap<String, DsnTemplate>,
+    pub domain_templates: AHashMap<String, String>,
+    pub digest: Option<DsnDigest>,
+}
+
+#[derive(Clone)]
+pub struct DsnDigest {
+    pub frequency: SimpleCron,
+    pub delay: IfBlock,
+    pub failure: IfBlock,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DsnTemplate {
+    pub subject: DsnTemplateText,
+    pub intro: DsnTemplateText,
+    pub heading: DsnTemplateText,
+    pub recipient: DsnTemplateText,
+    pub footer: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DsnTemplateText {
+    pub success: Option<String>,
+    pub delay: Option<String>,
+    pub failure: Option<String>,
+    pub partial: Option<String>,
+    pub mixed: Option<String>,
 }
 
 #[derive(Clone)]
@@ -192,6 +223,11 @@ impl Default for QueueConfig {
                     [],
                     "['rsa-' + key_get('default', 'domain'), 'ed25519-' + key_get('default', 'domain')]",
                 ),
+                language: IfBlock::new::<()>("report.dsn.language", [], "'en'"),
+                language_header: None,
+                templates: Default::default(),
+                domain_templates: Default::default(),
+                digest: None,
             },
             timeout: QueueOutboundTimeout {
                 connect: IfBlock::new::<()>("queue.outbound.timeouts.connect", [], "5m"),
@@ -319,6 +355,7 @@ impl QueueConfig {
                 &sender_vars,
             ),
             (&mut queue.dsn.sign, "report.dsn.sign", &sender_vars),
+            (&mut queue.dsn.language, "report.dsn.language", &sender_vars),
         ] {
             if let Some(if_block) = IfBlock::try_parse(config, key, token_map) {
                 *value = if_block;
@@ -329,6 +366,58 @@ impl QueueConfig {
         queue.throttle = parse_queue_throttle(config);
         queue.quota = parse_queue_quota(config);
 
+        // Parse DSN templates
+        queue.dsn.language_header = config
+            .value("report.dsn.language-header")
+            .map(|h| h.trim().to_string())
+            .filter(|h| !h.is_empty());
+        queue.dsn.templates = config
+            .sub_keys("report.dsn.template", "")
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|id| {
+                let template = parse_dsn_template(config, &id);
+                (id.to_ascii_lowercase(), template)
+            })
+            .collect();
+        queue.dsn.domain_templates = config
+            .sub_keys("report.dsn.domain", ".template")
+            .map(|domain| domain.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|domain| {
+                let id = config
+                    .value(("report.dsn.domain", domain.as_str(), "template"))?
+                    .trim()
+                    .to_ascii_lowercase();
+                Some((domain.to_ascii_lowercase(), id))
+            })
+            .collect();
+
+        // Parse DSN digests
+        if config
+            .property_or_default("report.dsn.digest.enable", "false")
+            .unwrap_or(false)
+        {
+            let mut digest = DsnDigest {
+                frequency: config
+                    .property_or_default::<SimpleCron>("report.dsn.digest.frequency", "0 * *")
+                    .unwrap_or_else(|| SimpleCron::parse_value("0 * *").unwrap()),
+                delay: IfBlock::new::<()>("report.dsn.digest.delay", [], "true"),
+                failure: IfBlock::new::<()>("report.dsn.digest.failure", [], "true"),
+            };
+            for (value, key) in [
+                (&mut digest.delay, "report.dsn.digest.delay"),
+                (&mut digest.failure, "report.dsn.digest.failure"),
+            ] {
+                if let Some(if_block) = IfBlock::try_parse(config, key, &sender_vars) {
+                    *value = if_block;
+                }
+            }
+            queue.dsn.digest = digest.into();
+        }
+
         // Parse relay hosts
         queue.relay_hosts = config
             .sub_keys("remote", ".address")
@@ -355,6 +444,37 @@ impl QueueConfig {
     }
 }
 
+fn parse_dsn_template(config: &mut Config, id: &str) -> DsnTemplate {
+    let prefix = ("report.dsn.template", id).as_key();
+    let mut template = DsnTemplate {
+        footer: config
+            .value((prefix.as_str(), "footer"))
+            .map(|v| v.to_string()),
+        ..Default::default()
+    };
+
+    for (text, section) in [
+        (&mut template.subject, "subject"),
+        (&mut template.intro, "intro"),
+        (&mut template.heading, "heading"),
+        (&mut template.recipient, "recipient"),
+    ] {
+        for (value, kind) in [
+            (&mut text.success, "success"),
+            (&mut text.delay, "delay"),
+            (&mut text.failure, "failure"),
+            (&mut text.partial, "partial"),
+            (&mut text.mixed, "mixed"),
+        ] {
+            *value = config
+                .value((prefix.as_str(), section, kind))
+                .map(|v| v.to_string());
+        }
+    }
+
+    template
+}
+
 fn parse_relay_host(config: &mut Config, id: &str) -> Option<RelayHost> {
     Some(RelayHost {
         address: config.property_require(("remote", id, "address"))?,
@@ -7,6 +7,7 @@
 use std::{borrow::Cow, net::IpAddr, sync::Arc};
 
 use arc_swap::ArcSwap;
+use auth::security::AccountSecurity;
 use config::{
     imap::ImapConfig,
     jmap::settings::JmapConfig,
@@ -21,7 +22,9 @@ use config::{
     tracers::{OtelTracer, Tracer, Tracers},
 };
 use directory::{
-    core::secret::verify_secret_hash, Directory, DirectoryError, Principal, QueryBy, Type,
+    backend::internal::{manage::ManageDirectory, PrincipalField, PrincipalUpdate, PrincipalValue},
+    core::{policy::PasswordStatus, secret::verify_secret_hash},
+    Directory, DirectoryError, DirectoryInner, Principal, QueryBy, Type,
 };
 use expr::if_block::IfBlock;
 use listener::{
@@ -36,7 +39,7 @@ use opentelemetry_sdk::{
 };
 use opentelemetry_semantic_conventions::resource::{SERVICE_NAME, SERVICE_VERSION};
 use sieve::Sieve;
-use store::LookupStore;
+use store::{write::now, LookupStore};
 use tokio::sync::{mpsc, oneshot};
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::{
@@ -46,6 +49,7 @@ use utils::{config::Config, BlobHash};
 use webhooks::{manager::WebhookEvent, WebhookPayload, WebhookType, Webhooks};
 
 pub mod addresses;
+pub mod auth;
 pub mod config;
 #[cfg(feature = "enterprise")]
 pub mod enterprise;
@@ -81,6 +85,7 @@ pub struct Network {
     pub blocked_ips: BlockedIps,
     pub allowed_ips: AllowedIps,
     pub url: IfBlock,
+    pub security: Arc<AccountSecurity>,
 }
 
 pub enum AuthResult<T> {
@@ -91,6 +96,8 @@ pub enum AuthResult<T> {
 pub enum AuthFailureReason {
     InvalidCredentials,
     MissingTotp,
+    PasswordExpired,
+    AccountLocked,
     Banned,
     InternalError(DirectoryError),
 }
@@ -112,6 +119,7 @@ pub struct Ipc {
 #[derive(Debug)]
 pub struct IngestMessage {
     pub sender_address: String,
+    pub sender_verified: bool,
     pub recipients: Vec<String>,
     pub message_blob: BlobHash,
     pub message_size: usize,
@@ -242,12 +250,36 @@ impl Core {
         protocol: ServerProtocol,
         return_member_of: bool,
     ) -> directory::Result<AuthResult<Principal<u32>>> {
+        // Locked accounts are rejected before verifying their credentials
+        if let Credentials::Plain { username, .. } = credentials {
+            if self.is_account_locked(username, &remote_ip).await? {
+                return Ok(AuthResult::Failure(AuthFailureReason::AccountLocked));
+            }
+        }
+
         // First try to authenticate the user against the default directory
-        let result = match directory
-            .query(QueryBy::Credentials(credentials), return_member_of)
+        let result = match self
+            .query_credentials(directory, credentials, return_member_of)
             .await
         {
             Ok(Some(principal)) => {
+                if matches!(credentials, Credentials::Plain { .. }) {
+                    let policy = &self.jmap.password_policy;
+                    match principal.password_changed_at() {
+                        // Passwords past their grace period can no longer be used
+                        Some(changed_at)
+                            if policy.status(Some(changed_at)) == PasswordStatus::Expired =>
+                        {
+                            return Ok(AuthResult::Failure(AuthFailureReason::PasswordExpired));
+                        }
+                        // Passwords set before expiry was enforced age from their next login
+                        None if policy.expiry.is_some() => {
+                            self.track_password_age(directory, principal.id).await;
+                        }
+                        _ => (),
+                    }
+                }
+
                 // Send webhook event
                 if self.has_webhook_subscribers(WebhookType::AuthSuccess) {
                     ipc.send_webhook(
@@ -263,6 +295,9 @@ impl Core {
                     .await;
                 }
 
+                self.record_login(ipc, credentials.login(), &principal, remote_ip, protocol)
+                    .await;
+
                 return Ok(AuthResult::Success(principal));
             }
             Ok(None) => Ok(()),
@@ -364,8 +399,54 @@ impl Core {
             }
 
             Err(err)
-        } else if self.has_fail2ban() {
-            let login = credentials.login();
+        } else {
+            self.auth_failure(ipc, credentials.login(), remote_ip, protocol)
+                .await
+                .map(AuthResult::Failure)
+        }
+    }
+
+    async fn track_password_age(&self, directory: &Directory, account_id: u32) {
+        // External directories are responsible for ageing their own passwords
+        if let DirectoryInner::Internal(store) = &directory.store {
+            if let Err(err) = store
+                .update_account(
+                    QueryBy::Id(account_id),
+                    vec![PrincipalUpdate::add_item(
+                        PrincipalField::Secrets,
+                        PrincipalValue::String(format!("$changed${}", now())),
+                    )],
+                )
+                .await
+            {
+                tracing::warn!(
+                    context = "directory",
+                    event = "error",
+                    account_id = account_id,
+                    reason = %err,
+                    "Failed to record password change timestamp",
+                );
+            }
+        }
+    }
+
+    pub async fn auth_failure(
+        &self,
+        ipc: &Ipc,
+        login: &str,
+        remote_ip: IpAddr,
+        protocol: ServerProtocol,
+    ) -> directory::Result<AuthFailureReason> {
+        let reason = if self
+            .account_lockout(ipc, login, remote_ip, protocol)
+            .await?
+        {
+            AuthFailureReason::AccountLocked
+        } else {
+            AuthFailureReason::InvalidCredentials
+        };
+
+        if self.has_fail2ban() {
             if self.is_fail2banned(remote_ip, login.to_string()).await? {
                 tracing::info!(
                     context = "directory",
@@ -380,7 +461,7 @@ impl Core {
                     ipc.send_webhook(
                         WebhookType::AuthBanned,
                         WebhookPayload::Authentication {
-                            login: credentials.login().to_string(),
+                            login: login.to_string(),
                             protocol,
                             remote_ip,
                             typ: None,
@@ -390,14 +471,14 @@ impl Core {
                     .await;
                 }
 
-                Ok(AuthResult::Failure(AuthFailureReason::Banned))
+                Ok(AuthFailureReason::Banned)
             } else {
                 // Send webhook event
                 if self.has_webhook_subscribers(WebhookType::AuthFailure) {
                     ipc.send_webhook(
                         WebhookType::AuthFailure,
                         WebhookPayload::Authentication {
-                            login: credentials.login().to_string(),
+                            login: login.to_string(),
                             protocol,
                             remote_ip,
                             typ: None,
@@ -407,7 +488,7 @@ impl Core {
                     .await;
                 }
 
-                Ok(AuthResult::Failure(AuthFailureReason::InvalidCredentials))
+                Ok(reason)
             }
         } else {
             // Send webhook event
@@ -415,7 +496,7 @@ impl Core {
                 ipc.send_webhook(
                     WebhookType::AuthFailure,
                     WebhookPayload::Authentication {
-                        login: credentials.login().to_string(),
+                        login: login.to_string(),
                         protocol,
                         remote_ip,
                         typ: None,
@@ -424,7 +505,7 @@ impl Core {
                 )
                 .await;
             }
-            Ok(AuthResult::Failure(AuthFailureReason::InvalidCredentials))
+            Ok(reason)
         }
     }
 }
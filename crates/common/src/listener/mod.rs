@@ -6,7 +6,7 @@
 
 use std::{borrow::Cow, net::IpAddr, sync::Arc};
 
-use rustls::ServerConfig;
+use rustls::{sign::CertifiedKey, ServerConfig};
 use std::fmt::Debug;
 use tokio::{
     io::{AsyncRead, AsyncWrite},
@@ -75,6 +75,14 @@ pub struct SessionData<T: SessionStream> {
 pub trait SessionStream: AsyncRead + AsyncWrite + Unpin + 'static + Sync + Send {
     fn is_tls(&self) -> bool;
     fn tls_version_and_cipher(&self) -> (Cow<'static, str>, Cow<'static, str>);
+
+    fn tls_exporter(&self, _label: &[u8], _len: usize) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn tls_certificate(&self, _core: &Core) -> Option<Arc<CertifiedKey>> {
+        None
+    }
 }
 
 pub trait SessionManager: Sync + Send + 'static + Clone {
@@ -4,15 +4,18 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc};
 
 use proxy_header::io::ProxiedStream;
+use rustls::sign::CertifiedKey;
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     net::TcpStream,
 };
 use tokio_rustls::server::TlsStream;
 
+use crate::Core;
+
 use super::SessionStream;
 
 impl SessionStream for TcpStream {
@@ -62,6 +65,18 @@ impl<T: SessionStream> SessionStream for TlsStream<T> {
             .into(),
         )
     }
+
+    fn tls_exporter(&self, label: &[u8], len: usize) -> Option<Vec<u8>> {
+        self.get_ref()
+            .1
+            .export_keying_material(vec![0u8; len], label, None)
+            .ok()
+    }
+
+    fn tls_certificate(&self, core: &Core) -> Option<Arc<CertifiedKey>> {
+        // Same lookup performed by the certificate resolver during the handshake
+        core.resolve_certificate(self.get_ref().1.server_name())
+    }
 }
 
 impl SessionStream for ProxiedStream<TcpStream> {
@@ -621,10 +621,9 @@ impl Core {
                                 account_id: u32::MAX,
                                 collection: u8::MAX,
                                 document_id: u32::MAX,
-                                class: ValueClass::Directory(DirectoryClass::Members {
-                                    principal_id: u32::MAX,
-                                    has_member: u32::MAX,
-                                }),
+                                class: ValueClass::Directory(DirectoryClass::ListSettings(
+                                    u32::MAX,
+                                )),
                             },
                         ),
                         |key, value| {
@@ -204,6 +204,22 @@ impl BootManager {
                     )));
                 }
 
+                // Generate a mailing list token key if missing
+                if config
+                    .value("list.secret")
+                    .filter(|v| !v.is_empty())
+                    .is_none()
+                {
+                    insert_keys.push(ConfigKey::from((
+                        "list.secret",
+                        thread_rng()
+                            .sample_iter(Alphanumeric)
+                            .take(64)
+                            .map(char::from)
+                            .collect::<String>(),
+                    )));
+                }
+
                 // Generate a Cluster encryption key if missing
                 if config
                     .value("cluster.key")
@@ -234,6 +234,20 @@ async fn restore_file(store: Store, blob_store: BlobStore, path: &Path) {
                                             .expect("Failed to read principal id"),
                                     ),
                                 },
+                                7 => {
+                                    let list_id =
+                                        key.deserialize_be_u32(1).expect("Failed to read list id");
+                                    let address = key
+                                        .get(1 + U32_LEN..)
+                                        .expect("Failed to read list subscriber");
+                                    DirectoryClass::ListSubscriber {
+                                        list_id,
+                                        address: address.to_vec(),
+                                    }
+                                }
+                                8 => DirectoryClass::ListSettings(
+                                    key.deserialize_be_u32(1).expect("Failed to read list id"),
+                                ),
 
                                 _ => failed("Invalid directory key"),
                             };
@@ -26,6 +26,9 @@ pub enum ScriptModification {
         name: Arc<String>,
         value: Arc<String>,
     },
+    Quarantine {
+        reason: String,
+    },
 }
 
 pub fn into_sieve_value(value: Value) -> Variable {
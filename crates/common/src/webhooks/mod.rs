@@ -61,6 +61,10 @@ pub enum WebhookType {
     AuthFailure,
     #[serde(rename = "auth.banned")]
     AuthBanned,
+    #[serde(rename = "auth.locked")]
+    AuthLocked,
+    #[serde(rename = "auth.suspicious")]
+    AuthSuspicious,
     #[serde(rename = "auth.error")]
     AuthError,
     #[serde(rename = "message.accepted")]
@@ -100,6 +104,16 @@ pub enum WebhookPayload {
         #[serde(skip_serializing_if = "Option::is_none")]
         as_master: Option<bool>,
     },
+    SuspiciousLogin {
+        login: String,
+        protocol: ServerProtocol,
+        #[serde(rename = "remoteIp")]
+        remote_ip: IpAddr,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        country: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        asn: Option<u32>,
+    },
     Error {
         message: String,
     },
@@ -308,6 +322,7 @@ pub enum WebhookMessageFailure {
     SieveReject,
     QuotaExceeded,
     ServerFailure,
+    Quarantined,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
@@ -0,0 +1,136 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use utils::config::{
+    utils::{AsKey, ParseValue},
+    Config,
+};
+
+use crate::Directory;
+
+use super::{CompositeDirectory, MergeRule, PrincipalAttribute};
+
+impl CompositeDirectory {
+    pub fn from_config(
+        config: &mut Config,
+        prefix: impl AsKey,
+        directories: &AHashMap<String, Arc<Directory>>,
+    ) -> Option<Self> {
+        let prefix = prefix.as_key();
+
+        // Obtain member directories, in order of precedence
+        let names = config
+            .values((&prefix, "directories"))
+            .map(|(_, v)| v.to_string())
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            config.new_parse_error(
+                (&prefix, "directories"),
+                "At least one directory is required",
+            );
+            return None;
+        }
+        let mut members = Vec::with_capacity(names.len());
+        for name in &names {
+            if let Some(directory) = directories.get(name) {
+                members.push(directory.clone());
+            } else {
+                config.new_parse_error(
+                    (&prefix, "directories"),
+                    format!("Directory {name:?} does not exist or is a composite directory"),
+                );
+                return None;
+            }
+        }
+        let position = |name: &str| names.iter().position(|n| n == name);
+
+        // Directories used for authentication
+        let mut auth = Vec::new();
+        for name in config
+            .values((&prefix, "authentication"))
+            .map(|(_, v)| v.to_string())
+            .collect::<Vec<_>>()
+        {
+            if let Some(pos) = position(&name) {
+                auth.push(pos);
+            } else {
+                config.new_parse_error(
+                    (&prefix, "authentication"),
+                    format!("Directory {name:?} is not part of this composite directory"),
+                );
+                return None;
+            }
+        }
+        if auth.is_empty() {
+            auth = (0..members.len()).collect();
+        }
+
+        // Directories that are authoritative for specific attributes
+        let mut attributes = Vec::new();
+        for (attribute, name) in config
+            .iterate_prefix((&prefix, "attributes"))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>()
+        {
+            let key = (prefix.as_str(), "attributes", attribute.as_str());
+            match (PrincipalAttribute::parse_value(&attribute), position(&name)) {
+                (Ok(attribute), Some(pos)) => {
+                    attributes.push((attribute, pos));
+                }
+                (Err(err), _) => {
+                    config.new_parse_error(key, err);
+                }
+                (_, None) => {
+                    config.new_parse_error(
+                        key,
+                        format!("Directory {name:?} is not part of this composite directory"),
+                    );
+                }
+            }
+        }
+
+        Some(CompositeDirectory {
+            directories: members,
+            auth,
+            lookup: config
+                .property_or_default((&prefix, "merge.lookup"), "first")
+                .unwrap_or(MergeRule::First),
+            vrfy: config
+                .property_or_default((&prefix, "merge.vrfy"), "first")
+                .unwrap_or(MergeRule::First),
+            expn: config
+                .property_or_default((&prefix, "merge.expn"), "first")
+                .unwrap_or(MergeRule::First),
+            attributes,
+        })
+    }
+}
+
+impl ParseValue for MergeRule {
+    fn parse_value(value: &str) -> utils::config::Result<Self> {
+        match value {
+            "first" => Ok(MergeRule::First),
+            "merge" => Ok(MergeRule::Merge),
+            other => Err(format!("Unknown merge rule {other:?}")),
+        }
+    }
+}
+
+impl ParseValue for PrincipalAttribute {
+    fn parse_value(value: &str) -> utils::config::Result<Self> {
+        match value {
+            "type" => Ok(PrincipalAttribute::Type),
+            "quota" => Ok(PrincipalAttribute::Quota),
+            "description" => Ok(PrincipalAttribute::Description),
+            "emails" => Ok(PrincipalAttribute::Emails),
+            "member-of" => Ok(PrincipalAttribute::MemberOf),
+            other => Err(format!("Unknown principal attribute {other:?}")),
+        }
+    }
+}
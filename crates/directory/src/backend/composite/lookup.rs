@@ -0,0 +1,187 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::{Principal, QueryBy};
+
+use super::{CompositeDirectory, MergeRule, PrincipalAttribute};
+
+impl CompositeDirectory {
+    pub async fn query(
+        &self,
+        by: QueryBy<'_>,
+        return_member_of: bool,
+    ) -> crate::Result<Option<Principal<u32>>> {
+        // Obtain the principal from the directory with the highest precedence
+        let (base_idx, mut principal) = if let QueryBy::Credentials(_) = by {
+            let mut last_err = None;
+            let mut result = None;
+
+            for &idx in &self.auth {
+                match Box::pin(self.directories[idx].query(by, return_member_of)).await {
+                    Ok(Some(principal)) => {
+                        result = Some((idx, principal));
+                        break;
+                    }
+                    Ok(None) => (),
+                    Err(err) => {
+                        last_err = Some(err);
+                    }
+                }
+            }
+
+            match (result, last_err) {
+                (Some(result), _) => result,
+                (None, Some(err)) => return Err(err),
+                (None, None) => return Ok(None),
+            }
+        } else {
+            let mut result = None;
+
+            for (idx, directory) in self.directories.iter().enumerate() {
+                if let Some(principal) = Box::pin(directory.query(by, return_member_of)).await? {
+                    result = Some((idx, principal));
+                    break;
+                }
+            }
+
+            if let Some(result) = result {
+                result
+            } else {
+                return Ok(None);
+            }
+        };
+
+        // Obtain the same principal from the remaining directories
+        if self.lookup == MergeRule::Merge || !self.attributes.is_empty() {
+            let mut principals = vec![None; self.directories.len()];
+            let name = principal.name.clone();
+
+            for (idx, directory) in self.directories.iter().enumerate() {
+                if idx != base_idx
+                    && (self.lookup == MergeRule::Merge
+                        || self.attributes.iter().any(|(_, pos)| *pos == idx))
+                {
+                    principals[idx] =
+                        Box::pin(directory.query(QueryBy::Name(&name), return_member_of)).await?;
+                }
+            }
+
+            // Fill in missing attributes following the order of precedence
+            if self.lookup == MergeRule::Merge {
+                for other in principals.iter().flatten() {
+                    principal.merge(other);
+                }
+            }
+
+            // Apply attributes from authoritative directories
+            principals[base_idx] = Some(principal.clone());
+            for (attribute, idx) in &self.attributes {
+                if let Some(source) = &principals[*idx] {
+                    match attribute {
+                        PrincipalAttribute::Type => principal.typ = source.typ,
+                        PrincipalAttribute::Quota => principal.quota = source.quota,
+                        PrincipalAttribute::Description => {
+                            principal.description.clone_from(&source.description)
+                        }
+                        PrincipalAttribute::Emails => principal.emails.clone_from(&source.emails),
+                        PrincipalAttribute::MemberOf => {
+                            principal.member_of.clone_from(&source.member_of)
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(Some(principal))
+    }
+
+    pub async fn email_to_ids(&self, address: &str) -> crate::Result<Vec<u32>> {
+        let mut ids = Vec::new();
+
+        for directory in &self.directories {
+            for id in Box::pin(directory.email_to_ids(address)).await? {
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+
+            if !ids.is_empty() && self.lookup == MergeRule::First {
+                break;
+            }
+        }
+
+        Ok(ids)
+    }
+
+    pub async fn is_local_domain(&self, domain: &str) -> crate::Result<bool> {
+        for directory in &self.directories {
+            if Box::pin(directory.is_local_domain(domain)).await? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    pub async fn rcpt(&self, address: &str) -> crate::Result<bool> {
+        for directory in &self.directories {
+            if Box::pin(directory.rcpt(address)).await? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    pub async fn vrfy(&self, address: &str) -> crate::Result<Vec<String>> {
+        let mut results = Vec::new();
+
+        for directory in &self.directories {
+            merge_list(&mut results, Box::pin(directory.vrfy(address)).await?);
+
+            if !results.is_empty() && self.vrfy == MergeRule::First {
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    pub async fn expn(&self, address: &str) -> crate::Result<Vec<String>> {
+        let mut results = Vec::new();
+
+        for directory in &self.directories {
+            merge_list(&mut results, Box::pin(directory.expn(address)).await?);
+
+            if !results.is_empty() && self.expn == MergeRule::First {
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+impl Principal<u32> {
+    fn merge(&mut self, other: &Principal<u32>) {
+        if self.quota == 0 {
+            self.quota = other.quota;
+        }
+        if self.description.is_none() {
+            self.description.clone_from(&other.description);
+        }
+        merge_list(&mut self.emails, other.emails.iter().cloned());
+        merge_list(&mut self.member_of, other.member_of.iter().copied());
+    }
+}
+
+fn merge_list<T: PartialEq>(list: &mut Vec<T>, items: impl IntoIterator<Item = T>) {
+    for item in items {
+        if !list.contains(&item) {
+            list.push(item);
+        }
+    }
+}
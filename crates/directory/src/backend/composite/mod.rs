@@ -0,0 +1,36 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use crate::Directory;
+
+pub mod config;
+pub mod lookup;
+
+pub struct CompositeDirectory {
+    directories: Vec<Arc<Directory>>,
+    auth: Vec<usize>,
+    lookup: MergeRule,
+    vrfy: MergeRule,
+    expn: MergeRule,
+    attributes: Vec<(PrincipalAttribute, usize)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeRule {
+    First,
+    Merge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrincipalAttribute {
+    Type,
+    Quota,
+    Description,
+    Emails,
+    MemberOf,
+}
@@ -13,7 +13,13 @@ use store::{
     Deserialize, IterateParams, Serialize, Store, ValueKey, U32_LEN,
 };
 
-use crate::{DirectoryError, ManagementError, Principal, QueryBy, Type};
+use crate::{
+    core::{
+        policy::PasswordPolicy,
+        secret::{derive_scram_credentials, hash_passwords},
+    },
+    DirectoryError, ManagementError, Principal, QueryBy, Type,
+};
 
 use super::{
     lookup::DirectoryStore, PrincipalAction, PrincipalField, PrincipalIdType, PrincipalUpdate,
@@ -23,6 +29,7 @@ use super::{
 #[allow(async_fn_in_trait)]
 pub trait ManageDirectory: Sized {
     async fn get_account_id(&self, name: &str) -> crate::Result<Option<u32>>;
+    async fn get_list_id(&self, email: &str) -> crate::Result<Option<u32>>;
     async fn get_or_create_account_id(&self, name: &str) -> crate::Result<u32>;
     async fn get_account_name(&self, account_id: u32) -> crate::Result<Option<String>>;
     async fn get_member_of(&self, account_id: u32) -> crate::Result<Vec<u32>>;
@@ -37,12 +44,30 @@ pub trait ManageDirectory: Sized {
         by: QueryBy<'_>,
         changes: Vec<PrincipalUpdate>,
     ) -> crate::Result<()>;
+    async fn create_account_with_policy(
+        &self,
+        principal: Principal<String>,
+        members: Vec<String>,
+        policy: Option<&PasswordPolicy>,
+    ) -> crate::Result<u32>;
+    async fn update_account_with_policy(
+        &self,
+        by: QueryBy<'_>,
+        changes: Vec<PrincipalUpdate>,
+        policy: Option<&PasswordPolicy>,
+    ) -> crate::Result<()>;
     async fn delete_account(&self, by: QueryBy<'_>) -> crate::Result<()>;
     async fn list_accounts(
         &self,
         filter: Option<&str>,
         typ: Option<Type>,
     ) -> crate::Result<Vec<String>>;
+    async fn list_principals(
+        &self,
+        filter: impl Fn(&Principal<u32>) -> bool + Sync + Send,
+        offset: usize,
+        limit: usize,
+    ) -> crate::Result<(usize, Vec<Principal<u32>>)>;
     async fn map_group_ids(&self, principal: Principal<u32>) -> crate::Result<Principal<String>>;
     async fn map_principal(
         &self,
@@ -91,6 +116,15 @@ impl ManageDirectory for Store {
         .map_err(Into::into)
     }
 
+    async fn get_list_id(&self, email: &str) -> crate::Result<Option<u32>> {
+        self.get_value::<PrincipalIdType>(ValueKey::from(ValueClass::Directory(
+            DirectoryClass::EmailToId(email.as_bytes().to_vec()),
+        )))
+        .await
+        .map(|v| v.filter(|v| v.typ == Type::List).map(|v| v.account_id))
+        .map_err(Into::into)
+    }
+
     // Used by all directories except internal
     async fn get_or_create_account_id(&self, name: &str) -> crate::Result<u32> {
         let mut try_count = 0;
@@ -148,6 +182,16 @@ impl ManageDirectory for Store {
         &self,
         principal: Principal<String>,
         members: Vec<String>,
+    ) -> crate::Result<u32> {
+        self.create_account_with_policy(principal, members, None)
+            .await
+    }
+
+    async fn create_account_with_policy(
+        &self,
+        mut principal: Principal<String>,
+        members: Vec<String>,
+        policy: Option<&PasswordPolicy>,
     ) -> crate::Result<u32> {
         // Make sure the principal has a name
         if principal.name.is_empty() {
@@ -156,6 +200,13 @@ impl ManageDirectory for Store {
             )));
         }
 
+        // Enforce password policy
+        if let Some(policy) = policy {
+            policy.apply(&[], &mut principal.secrets).await?;
+            derive_scram_credentials(&mut principal.secrets);
+            hash_passwords(&mut principal.secrets);
+        }
+
         // Map group names
         let mut principal = self.map_principal(principal, false).await?;
         let members = self.map_group_names(members, false).await?;
@@ -298,6 +349,22 @@ impl ManageDirectory for Store {
             batch.clear(DirectoryClass::EmailToId(email.into_bytes()));
         }
 
+        // Delete mailing list settings and subscribers
+        if principal.typ == Type::List {
+            batch.clear(DirectoryClass::ListSettings(account_id));
+            self.delete_range(
+                ValueKey::from(ValueClass::Directory(DirectoryClass::ListSubscriber {
+                    list_id: account_id,
+                    address: vec![],
+                })),
+                ValueKey::from(ValueClass::Directory(DirectoryClass::ListSubscriber {
+                    list_id: account_id,
+                    address: vec![u8::MAX; 10],
+                })),
+            )
+            .await?;
+        }
+
         for member_id in self.get_member_of(account_id).await? {
             batch.clear(DirectoryClass::MemberOf {
                 principal_id: MaybeDynamicId::Static(account_id),
@@ -329,6 +396,15 @@ impl ManageDirectory for Store {
         &self,
         by: QueryBy<'_>,
         changes: Vec<PrincipalUpdate>,
+    ) -> crate::Result<()> {
+        self.update_account_with_policy(by, changes, None).await
+    }
+
+    async fn update_account_with_policy(
+        &self,
+        by: QueryBy<'_>,
+        changes: Vec<PrincipalUpdate>,
+        policy: Option<&PasswordPolicy>,
     ) -> crate::Result<()> {
         let account_id = match by {
             QueryBy::Name(name) => self.get_account_id(name).await?.ok_or_else(|| {
@@ -351,6 +427,10 @@ impl ManageDirectory for Store {
         // Obtain members and memberOf
         let mut member_of = self.get_member_of(account_id).await?;
         let mut members = self.get_members(account_id).await?;
+        let current_secrets = changes
+            .iter()
+            .any(|c| c.field == PrincipalField::Secrets)
+            .then(|| principal.inner.secrets.clone());
 
         // Apply changes
         let mut batch = BatchBuilder::new();
@@ -433,7 +513,7 @@ impl ManageDirectory for Store {
                     PrincipalField::Secrets,
                     PrincipalValue::String(secret),
                 ) => {
-                    if secret.is_app_password() || secret.is_otp_auth() {
+                    if secret.is_app_password() || secret.is_otp_auth() || secret.is_webauthn() {
                         principal
                             .inner
                             .secrets
@@ -455,9 +535,27 @@ impl ManageDirectory for Store {
                         principal.inner.description = None;
                     }
                 }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::TimeZone,
+                    PrincipalValue::String(time_zone),
+                ) => {
+                    if !time_zone.is_empty() {
+                        principal.inner.time_zone = Some(time_zone);
+                    } else {
+                        principal.inner.time_zone = None;
+                    }
+                }
                 (PrincipalAction::Set, PrincipalField::Quota, PrincipalValue::Integer(quota)) => {
                     principal.inner.quota = quota;
                 }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::UndeleteRetention,
+                    PrincipalValue::Integer(retention),
+                ) => {
+                    principal.inner.undelete_retention = retention;
+                }
 
                 // Emails
                 (
@@ -740,6 +838,15 @@ impl ManageDirectory for Store {
             }
         }
 
+        // Enforce password policy
+        if let (Some(policy), Some(current_secrets)) = (policy, current_secrets) {
+            policy
+                .apply(&current_secrets, &mut principal.inner.secrets)
+                .await?;
+            derive_scram_credentials(&mut principal.inner.secrets);
+            hash_passwords(&mut principal.inner.secrets);
+        }
+
         if update_principal {
             batch.set(
                 ValueClass::Directory(DirectoryClass::Principal(MaybeDynamicId::Static(
@@ -797,6 +904,8 @@ impl ManageDirectory for Store {
             emails: principal.emails,
             member_of: Vec::with_capacity(principal.member_of.len()),
             description: principal.description,
+            time_zone: principal.time_zone,
+            undelete_retention: principal.undelete_retention,
         };
 
         for account_id in principal.member_of {
@@ -824,6 +933,8 @@ impl ManageDirectory for Store {
                 .map_group_names(principal.member_of, create_if_missing)
                 .await?,
             description: principal.description,
+            time_zone: principal.time_zone,
+            undelete_retention: principal.undelete_retention,
         })
     }
 
@@ -916,6 +1027,37 @@ impl ManageDirectory for Store {
         }
     }
 
+    async fn list_principals(
+        &self,
+        filter: impl Fn(&Principal<u32>) -> bool + Sync + Send,
+        offset: usize,
+        limit: usize,
+    ) -> crate::Result<(usize, Vec<Principal<u32>>)> {
+        let from_key = ValueKey::from(ValueClass::Directory(DirectoryClass::Principal(0)));
+        let to_key = ValueKey::from(ValueClass::Directory(DirectoryClass::Principal(u32::MAX)));
+
+        let mut total = 0;
+        let mut results = Vec::new();
+        self.iterate(
+            IterateParams::new(from_key, to_key).ascending(),
+            |_, value| {
+                let principal = Principal::<u32>::deserialize(value)?;
+
+                if filter(&principal) {
+                    if total >= offset && results.len() < limit {
+                        results.push(principal);
+                    }
+                    total += 1;
+                }
+
+                Ok(true)
+            },
+        )
+        .await?;
+
+        Ok((total, results))
+    }
+
     async fn list_domains(&self, filter: Option<&str>) -> crate::Result<Vec<String>> {
         let from_key = ValueKey::from(ValueClass::Directory(DirectoryClass::Domain(vec![])));
         let to_key = ValueKey::from(ValueClass::Directory(DirectoryClass::Domain(vec![
@@ -1023,6 +1165,8 @@ impl From<Principal<String>> for Principal<u32> {
             emails: principal.emails,
             member_of: Vec::with_capacity(0),
             description: principal.description,
+            time_zone: principal.time_zone,
+            undelete_retention: principal.undelete_retention,
         }
     }
 }
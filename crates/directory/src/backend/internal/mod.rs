@@ -28,12 +28,13 @@ impl Serialize for Principal<u32> {
 impl Serialize for &Principal<u32> {
     fn serialize(self) -> Vec<u8> {
         let mut serializer = KeySerializer::new(
-            U32_LEN * 3
+            U32_LEN * 4
                 + 2
                 + self.name.len()
                 + self.emails.iter().map(|s| s.len()).sum::<usize>()
                 + self.secrets.iter().map(|s| s.len()).sum::<usize>()
-                + self.description.as_ref().map(|s| s.len()).unwrap_or(0),
+                + self.description.as_ref().map(|s| s.len()).unwrap_or(0)
+                + self.time_zone.as_ref().map(|s| s.len()).unwrap_or(0),
         )
         .write(1u8)
         .write_leb128(self.id)
@@ -51,7 +52,11 @@ impl Serialize for &Principal<u32> {
             }
         }
 
-        serializer.finalize()
+        serializer
+            .write_leb128(self.time_zone.as_ref().map_or(0, |s| s.len()))
+            .write(self.time_zone.as_deref().unwrap_or_default().as_bytes())
+            .write_leb128(self.undelete_retention)
+            .finalize()
     }
 }
 
@@ -112,6 +117,9 @@ fn deserialize(bytes: &[u8]) -> Option<Principal<u32>> {
         secrets: deserialize_string_list(&mut bytes)?,
         emails: deserialize_string_list(&mut bytes)?,
         member_of: Vec::new(),
+        // Principals written before time zones were supported end here
+        time_zone: deserialize_string(&mut bytes).filter(|v| !v.is_empty()),
+        undelete_retention: bytes.next_leb128().unwrap_or(0),
     }
     .into()
 }
@@ -134,6 +142,10 @@ pub enum PrincipalField {
     MemberOf,
     #[serde(rename = "members")]
     Members,
+    #[serde(rename = "timeZone")]
+    TimeZone,
+    #[serde(rename = "undeleteRetention")]
+    UndeleteRetention,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -198,6 +210,8 @@ impl Display for PrincipalField {
             PrincipalField::Emails => write!(f, "emails"),
             PrincipalField::MemberOf => write!(f, "memberOf"),
             PrincipalField::Members => write!(f, "members"),
+            PrincipalField::TimeZone => write!(f, "timeZone"),
+            PrincipalField::UndeleteRetention => write!(f, "undeleteRetention"),
         }
     }
 }
@@ -266,6 +280,9 @@ pub trait SpecialSecrets {
     fn is_otp_auth(&self) -> bool;
     fn is_app_password(&self) -> bool;
     fn is_password(&self) -> bool;
+    fn is_password_history(&self) -> bool;
+    fn is_password_timestamp(&self) -> bool;
+    fn is_webauthn(&self) -> bool;
 }
 
 impl<T> SpecialSecrets for T
@@ -285,6 +302,23 @@ where
     }
 
     fn is_password(&self) -> bool {
-        !self.is_disabled() && !self.is_otp_auth() && !self.is_app_password()
+        !self.is_disabled()
+            && !self.is_otp_auth()
+            && !self.is_app_password()
+            && !self.is_password_history()
+            && !self.is_password_timestamp()
+            && !self.is_webauthn()
+    }
+
+    fn is_password_history(&self) -> bool {
+        self.as_ref().starts_with("$history$")
+    }
+
+    fn is_password_timestamp(&self) -> bool {
+        self.as_ref().starts_with("$changed$")
+    }
+
+    fn is_webauthn(&self) -> bool {
+        self.as_ref().starts_with("$webauthn$")
     }
 }
@@ -12,7 +12,9 @@ use utils::config::{utils::AsKey, Config};
 
 use crate::core::config::build_pool;
 
-use super::{Bind, LdapConnectionManager, LdapDirectory, LdapFilter, LdapMappings};
+use super::{
+    Bind, LdapConnectionManager, LdapDirectory, LdapFilter, LdapMappings, LdapWriteSettings,
+};
 
 impl LdapDirectory {
     pub fn from_config(config: &mut Config, prefix: impl AsKey, data_store: Store) -> Option<Self> {
@@ -83,6 +85,14 @@ impl LdapDirectory {
                 .values((&prefix, "attributes.quota"))
                 .map(|(_, v)| v.to_string())
                 .collect(),
+            attr_time_zone: config
+                .values((&prefix, "attributes.time-zone"))
+                .map(|(_, v)| v.to_string())
+                .collect(),
+            attr_undelete_retention: config
+                .values((&prefix, "attributes.undelete-retention"))
+                .map(|(_, v)| v.to_string())
+                .collect(),
             attr_email_alias: config
                 .values((&prefix, "attributes.email-alias"))
                 .map(|(_, v)| v.to_string())
@@ -96,6 +106,8 @@ impl LdapDirectory {
             &mappings.attr_description,
             &mappings.attr_secret,
             &mappings.attr_quota,
+            &mappings.attr_time_zone,
+            &mappings.attr_undelete_retention,
             &mappings.attr_groups,
             &mappings.attr_email_address,
             &mappings.attr_email_alias,
@@ -112,8 +124,46 @@ impl LdapDirectory {
             None
         };
 
+        let write = if config
+            .property_or_default::<bool>((&prefix, "write.enable"), "false")
+            .unwrap_or_default()
+        {
+            LdapWriteSettings {
+                base_dn: config
+                    .value((&prefix, "write.base-dn"))
+                    .unwrap_or(mappings.base_dn.as_str())
+                    .to_string(),
+                attr_rdn: config
+                    .value((&prefix, "write.attributes.rdn"))
+                    .or_else(|| mappings.attr_name.first().map(|v| v.as_str()))
+                    .unwrap_or("uid")
+                    .to_string(),
+                attr_member: config
+                    .value((&prefix, "write.attributes.member"))
+                    .unwrap_or("member")
+                    .to_string(),
+                object_class_individual: object_classes(
+                    config,
+                    (&prefix, "write.object-class.individual"),
+                    &["inetOrgPerson"],
+                ),
+                object_class_group: object_classes(
+                    config,
+                    (&prefix, "write.object-class.group"),
+                    &["groupOfNames"],
+                ),
+                password_modify: config
+                    .property_or_default((&prefix, "write.password-modify"), "true")
+                    .unwrap_or(true),
+            }
+            .into()
+        } else {
+            None
+        };
+
         Some(LdapDirectory {
             mappings,
+            write,
             pool: build_pool(config, &prefix, manager)
                 .map_err(|e| {
                     config.new_parse_error(prefix, format!("Failed to build LDAP pool: {e:?}"))
@@ -125,6 +175,18 @@ impl LdapDirectory {
     }
 }
 
+fn object_classes(config: &mut Config, key: impl AsKey, default: &[&str]) -> Vec<String> {
+    let classes = config
+        .values(key)
+        .map(|(_, v)| v.to_string())
+        .collect::<Vec<_>>();
+    if !classes.is_empty() {
+        classes
+    } else {
+        default.iter().map(|v| v.to_string()).collect()
+    }
+}
+
 impl LdapFilter {
     fn from_config(config: &mut Config, key: impl AsKey) -> Self {
         if let Some(value) = config.value(key.clone()) {
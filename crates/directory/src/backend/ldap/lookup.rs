@@ -307,7 +307,7 @@ impl LdapDirectory {
 }
 
 impl LdapMappings {
-    fn entry_to_principal(&self, entry: SearchEntry) -> Principal<String> {
+    pub(crate) fn entry_to_principal(&self, entry: SearchEntry) -> Principal<String> {
         let mut principal = Principal::default();
 
         tracing::debug!(
@@ -342,6 +342,12 @@ impl LdapMappings {
                 if let Ok(quota) = value.into_iter().next().unwrap_or_default().parse() {
                     principal.quota = quota;
                 }
+            } else if self.attr_time_zone.contains(&attr) {
+                principal.time_zone = value.into_iter().next();
+            } else if self.attr_undelete_retention.contains(&attr) {
+                if let Ok(retention) = value.into_iter().next().unwrap_or_default().parse() {
+                    principal.undelete_retention = retention;
+                }
             } else if self.attr_type.contains(&attr) {
                 for value in value {
                     match value.to_ascii_lowercase().as_str() {
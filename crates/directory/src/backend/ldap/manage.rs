@@ -0,0 +1,839 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::collections::HashSet;
+
+use ldap3::{dn_escape, exop::PasswordModify, Ldap, LdapResult, Mod, Scope, SearchEntry};
+
+use crate::{
+    backend::internal::{
+        manage::ManageDirectory, PrincipalAction, PrincipalField, PrincipalUpdate, PrincipalValue,
+        SpecialSecrets,
+    },
+    core::{
+        policy::{PasswordPolicy, PasswordViolation},
+        secret::plain_text_secret,
+    },
+    DirectoryError, ManagementError, Principal, QueryBy, Type,
+};
+
+use super::{LdapDirectory, LdapWriteSettings};
+
+// LDAP result codes
+const RC_NO_SUCH_ATTRIBUTE: u32 = 16;
+const RC_ATTRIBUTE_OR_VALUE_EXISTS: u32 = 20;
+const RC_NO_SUCH_OBJECT: u32 = 32;
+const RC_ENTRY_ALREADY_EXISTS: u32 = 68;
+
+type Attributes = Vec<(String, HashSet<String>)>;
+
+impl LdapDirectory {
+    pub fn is_writable(&self) -> bool {
+        self.write.is_some()
+    }
+
+    pub async fn create_account(
+        &self,
+        principal: Principal<String>,
+        members: Vec<String>,
+        policy: Option<&PasswordPolicy>,
+    ) -> crate::Result<u32> {
+        let write = self.write_settings()?;
+        validate_passwords(policy, &principal.secrets)?;
+        let mut conn = self.pool.get().await?;
+
+        // Make sure the name is not taken
+        let name = principal.name.trim().to_lowercase();
+        if name.is_empty() {
+            return Err(DirectoryError::Management(ManagementError::MissingField(
+                PrincipalField::Name,
+            )));
+        } else if self.find_entry(&mut conn, &name).await?.is_some() {
+            return Err(DirectoryError::Management(ManagementError::AlreadyExists {
+                field: PrincipalField::Name,
+                value: name,
+            }));
+        }
+
+        // Validate emails
+        let emails = principal
+            .emails
+            .into_iter()
+            .map(|email| email.to_lowercase())
+            .collect::<Vec<_>>();
+        for email in &emails {
+            self.validate_email(email).await?;
+        }
+
+        // Build entry
+        let dn = format!("{}={},{}", write.attr_rdn, dn_escape(&name), write.base_dn);
+        let is_group = matches!(principal.typ, Type::Group | Type::List);
+        let object_classes = if is_group {
+            &write.object_class_group
+        } else {
+            &write.object_class_individual
+        };
+        let mut attrs = Attributes::new();
+        push_attr(&mut attrs, "objectClass", object_classes.iter().cloned());
+        push_attr(&mut attrs, &write.attr_rdn, [name.clone()]);
+        push_attr(
+            &mut attrs,
+            self.attr_required(&self.mappings.attr_name)?,
+            [name.clone()],
+        );
+        if let Some(description) = principal.description.filter(|d| !d.is_empty()) {
+            push_attr(
+                &mut attrs,
+                self.attr_required(&self.mappings.attr_description)?,
+                [description],
+            );
+        }
+
+        // Add attributes required by the standard object classes
+        let has_class = |class: &str| object_classes.iter().any(|c| c.eq_ignore_ascii_case(class));
+        let is_person = !is_group && (has_class("person") || has_class("inetOrgPerson"));
+        for (attr, required) in [("cn", is_group || is_person), ("sn", is_person)] {
+            if required && !attrs.iter().any(|(a, _)| a.eq_ignore_ascii_case(attr)) {
+                push_attr(&mut attrs, attr, [name.clone()]);
+            }
+        }
+        if principal.quota > 0 {
+            push_attr(
+                &mut attrs,
+                self.attr_required(&self.mappings.attr_quota)?,
+                [principal.quota.to_string()],
+            );
+        }
+        if let Some(time_zone) = principal.time_zone.filter(|tz| !tz.is_empty()) {
+            push_attr(
+                &mut attrs,
+                self.attr_required(&self.mappings.attr_time_zone)?,
+                [time_zone],
+            );
+        }
+        if principal.undelete_retention > 0 {
+            push_attr(
+                &mut attrs,
+                self.attr_required(&self.mappings.attr_undelete_retention)?,
+                [principal.undelete_retention.to_string()],
+            );
+        }
+        for (pos, email) in emails.into_iter().enumerate() {
+            push_attr(&mut attrs, self.attr_email(pos == 0)?, [email]);
+        }
+
+        // Passwords are set using the Password Modify extended operation when available
+        let mut password = None;
+        for secret in principal.secrets {
+            if write.password_modify
+                && password.is_none()
+                && !secret.is_otp_auth()
+                && !secret.is_app_password()
+            {
+                password = Some(secret);
+            } else {
+                push_attr(
+                    &mut attrs,
+                    self.attr_required(&self.mappings.attr_secret)?,
+                    [secret],
+                );
+            }
+        }
+        if !members.is_empty() {
+            let mut member_dns = Vec::with_capacity(members.len());
+            for member in members {
+                member_dns.push(self.principal_dn(&mut conn, &member).await?);
+            }
+            push_attr(&mut attrs, &write.attr_member, member_dns);
+        }
+
+        // Create entry
+        check_result(conn.add(&dn, attrs).await?, &name, PrincipalField::Name)?;
+        tracing::debug!(
+            context = "ldap",
+            event = "create_principal",
+            dn = dn,
+            "Created LDAP entry"
+        );
+
+        if let Some(password) = password {
+            self.set_password(&mut conn, &dn, &password).await?;
+        }
+
+        // Add principal to groups
+        for group in principal.member_of {
+            let group_dn = self.principal_dn(&mut conn, &group).await?;
+            check_result(
+                conn.modify(
+                    &group_dn,
+                    vec![Mod::Add(
+                        write.attr_member.clone(),
+                        HashSet::from([dn.clone()]),
+                    )],
+                )
+                .await?,
+                &group,
+                PrincipalField::MemberOf,
+            )?;
+        }
+
+        self.data_store.get_or_create_account_id(&name).await
+    }
+
+    pub async fn update_account(
+        &self,
+        by: QueryBy<'_>,
+        changes: Vec<PrincipalUpdate>,
+        policy: Option<&PasswordPolicy>,
+    ) -> crate::Result<()> {
+        let write = self.write_settings()?;
+        validate_passwords(
+            policy,
+            changes
+                .iter()
+                .filter(|change| {
+                    change.field == PrincipalField::Secrets
+                        && change.action != PrincipalAction::RemoveItem
+                })
+                .flat_map(|change| match &change.value {
+                    PrincipalValue::String(secret) => std::slice::from_ref(secret),
+                    PrincipalValue::StringList(secrets) => secrets.as_slice(),
+                    PrincipalValue::Integer(_) => &[],
+                }),
+        )?;
+        let mut conn = self.pool.get().await?;
+        let name = self.account_name(by).await?;
+        let entry = self
+            .find_entry(&mut conn, &name)
+            .await?
+            .ok_or_else(|| DirectoryError::Management(ManagementError::NotFound(name.clone())))?;
+        let dn = entry.dn.clone();
+        let principal = self.mappings.entry_to_principal(entry.clone());
+
+        let mut mods = Vec::new();
+        let mut group_mods = Vec::new();
+        let mut new_password = None;
+        let mut new_name = None;
+
+        for change in changes {
+            match (change.action, change.field, change.value) {
+                (PrincipalAction::Set, PrincipalField::Name, PrincipalValue::String(value)) => {
+                    let value = value.trim().to_lowercase();
+                    if value != name {
+                        if self.find_entry(&mut conn, &value).await?.is_some() {
+                            return Err(DirectoryError::Management(
+                                ManagementError::AlreadyExists {
+                                    field: PrincipalField::Name,
+                                    value,
+                                },
+                            ));
+                        }
+                        new_name = Some(value);
+                    }
+                }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::Description,
+                    PrincipalValue::String(value),
+                ) => {
+                    mods.push(Mod::Replace(
+                        self.attr_required(&self.mappings.attr_description)?
+                            .to_string(),
+                        HashSet::from_iter((!value.is_empty()).then_some(value)),
+                    ));
+                }
+                (PrincipalAction::Set, PrincipalField::Quota, PrincipalValue::Integer(quota)) => {
+                    mods.push(Mod::Replace(
+                        self.attr_required(&self.mappings.attr_quota)?.to_string(),
+                        HashSet::from_iter((quota > 0).then(|| quota.to_string())),
+                    ));
+                }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::UndeleteRetention,
+                    PrincipalValue::Integer(retention),
+                ) => {
+                    mods.push(Mod::Replace(
+                        self.attr_required(&self.mappings.attr_undelete_retention)?
+                            .to_string(),
+                        HashSet::from_iter((retention > 0).then(|| retention.to_string())),
+                    ));
+                }
+                (PrincipalAction::Set, PrincipalField::TimeZone, PrincipalValue::String(value)) => {
+                    mods.push(Mod::Replace(
+                        self.attr_required(&self.mappings.attr_time_zone)?
+                            .to_string(),
+                        HashSet::from_iter((!value.is_empty()).then_some(value)),
+                    ));
+                }
+
+                // Secrets
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::Secrets,
+                    PrincipalValue::StringList(secrets),
+                ) => {
+                    mods.push(Mod::Replace(
+                        self.attr_required(&self.mappings.attr_secret)?.to_string(),
+                        HashSet::from_iter(secrets),
+                    ));
+                }
+                (
+                    PrincipalAction::AddItem,
+                    PrincipalField::Secrets,
+                    PrincipalValue::String(secret),
+                ) => {
+                    if write.password_modify && !secret.is_otp_auth() && !secret.is_app_password() {
+                        new_password = Some(secret);
+                    } else if !principal.secrets.contains(&secret) {
+                        mods.push(Mod::Add(
+                            self.attr_required(&self.mappings.attr_secret)?.to_string(),
+                            HashSet::from([secret]),
+                        ));
+                    }
+                }
+                (
+                    PrincipalAction::RemoveItem,
+                    PrincipalField::Secrets,
+                    PrincipalValue::String(secret),
+                ) => {
+                    let remove = principal
+                        .secrets
+                        .iter()
+                        .filter(|v| {
+                            if secret.is_app_password() || secret.is_otp_auth() {
+                                **v == secret || v.starts_with(&secret)
+                            } else if !secret.is_empty() {
+                                **v == secret
+                            } else {
+                                v.is_password()
+                            }
+                        })
+                        .cloned()
+                        .collect::<HashSet<_>>();
+                    if !remove.is_empty() {
+                        mods.push(Mod::Delete(
+                            self.attr_required(&self.mappings.attr_secret)?.to_string(),
+                            remove,
+                        ));
+                    }
+                }
+
+                // Emails
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::Emails,
+                    PrincipalValue::StringList(emails),
+                ) => {
+                    let emails = emails
+                        .into_iter()
+                        .map(|email| email.to_lowercase())
+                        .collect::<Vec<_>>();
+                    for email in &emails {
+                        if !principal
+                            .emails
+                            .iter()
+                            .any(|e| e.eq_ignore_ascii_case(email))
+                        {
+                            self.validate_email(email).await?;
+                        }
+                    }
+
+                    let mut emails = emails.into_iter();
+                    let primary = self.attr_email(true)?;
+                    let alias = self.attr_email(false)?;
+                    if primary != alias {
+                        mods.push(Mod::Replace(
+                            primary.to_string(),
+                            HashSet::from_iter(emails.next()),
+                        ));
+                    }
+                    mods.push(Mod::Replace(alias.to_string(), HashSet::from_iter(emails)));
+                }
+                (
+                    PrincipalAction::AddItem,
+                    PrincipalField::Emails,
+                    PrincipalValue::String(email),
+                ) => {
+                    let email = email.to_lowercase();
+                    if !principal
+                        .emails
+                        .iter()
+                        .any(|e| e.eq_ignore_ascii_case(&email))
+                    {
+                        self.validate_email(&email).await?;
+                        mods.push(Mod::Add(
+                            self.attr_email(principal.emails.is_empty())?.to_string(),
+                            HashSet::from([email]),
+                        ));
+                    }
+                }
+                (
+                    PrincipalAction::RemoveItem,
+                    PrincipalField::Emails,
+                    PrincipalValue::String(email),
+                ) => {
+                    for attr in self
+                        .mappings
+                        .attr_email_address
+                        .iter()
+                        .chain(self.mappings.attr_email_alias.iter())
+                    {
+                        if let Some(value) = entry.attrs.get(attr).and_then(|values| {
+                            values.iter().find(|v| v.eq_ignore_ascii_case(&email))
+                        }) {
+                            mods.push(Mod::Delete(attr.clone(), HashSet::from([value.clone()])));
+                            break;
+                        }
+                    }
+                }
+
+                // Group memberships are stored in the group entry
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::MemberOf,
+                    PrincipalValue::StringList(groups),
+                ) => {
+                    let mut current = Vec::with_capacity(principal.member_of.len());
+                    for group in &principal.member_of {
+                        current.push(self.principal_dn(&mut conn, group).await?);
+                    }
+                    let mut new = Vec::with_capacity(groups.len());
+                    for group in &groups {
+                        new.push(self.principal_dn(&mut conn, group).await?);
+                    }
+
+                    for group_dn in &current {
+                        if !new.iter().any(|dn| dn.eq_ignore_ascii_case(group_dn)) {
+                            group_mods.push((group_dn.clone(), false));
+                        }
+                    }
+                    for group_dn in new {
+                        if !current.iter().any(|dn| dn.eq_ignore_ascii_case(&group_dn)) {
+                            group_mods.push((group_dn, true));
+                        }
+                    }
+                }
+                (
+                    action @ (PrincipalAction::AddItem | PrincipalAction::RemoveItem),
+                    PrincipalField::MemberOf,
+                    PrincipalValue::String(group),
+                ) => {
+                    group_mods.push((
+                        self.principal_dn(&mut conn, &group).await?,
+                        matches!(action, PrincipalAction::AddItem),
+                    ));
+                }
+
+                // Members
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::Members,
+                    PrincipalValue::StringList(members),
+                ) => {
+                    let mut member_dns = HashSet::with_capacity(members.len());
+                    for member in &members {
+                        member_dns.insert(self.principal_dn(&mut conn, member).await?);
+                    }
+                    mods.push(Mod::Replace(write.attr_member.clone(), member_dns));
+                }
+                (
+                    PrincipalAction::AddItem,
+                    PrincipalField::Members,
+                    PrincipalValue::String(member),
+                ) => {
+                    mods.push(Mod::Add(
+                        write.attr_member.clone(),
+                        HashSet::from([self.principal_dn(&mut conn, &member).await?]),
+                    ));
+                }
+                (
+                    PrincipalAction::RemoveItem,
+                    PrincipalField::Members,
+                    PrincipalValue::String(member),
+                ) => {
+                    mods.push(Mod::Delete(
+                        write.attr_member.clone(),
+                        HashSet::from([self.principal_dn(&mut conn, &member).await?]),
+                    ));
+                }
+
+                _ => {
+                    return Err(DirectoryError::Unsupported);
+                }
+            }
+        }
+
+        // Update entry
+        if !mods.is_empty() {
+            check_result(conn.modify(&dn, mods).await?, &name, PrincipalField::Name)?;
+        }
+
+        // Update group memberships
+        for (group_dn, is_add) in group_mods {
+            let values = HashSet::from([dn.clone()]);
+            check_result(
+                conn.modify(
+                    &group_dn,
+                    vec![if is_add {
+                        Mod::Add(write.attr_member.clone(), values)
+                    } else {
+                        Mod::Delete(write.attr_member.clone(), values)
+                    }],
+                )
+                .await?,
+                &group_dn,
+                PrincipalField::MemberOf,
+            )?;
+        }
+
+        // Change password
+        if let Some(password) = new_password {
+            self.set_password(&mut conn, &dn, &password).await?;
+        }
+
+        // Rename entry
+        if let Some(new_name) = new_name {
+            let name_attr = self.attr_required(&self.mappings.attr_name)?;
+            if name_attr.eq_ignore_ascii_case(&write.attr_rdn) {
+                check_result(
+                    conn.modifydn(
+                        &dn,
+                        &format!("{}={}", write.attr_rdn, dn_escape(&new_name)),
+                        true,
+                        None,
+                    )
+                    .await?,
+                    &new_name,
+                    PrincipalField::Name,
+                )?;
+            } else {
+                check_result(
+                    conn.modify(
+                        &dn,
+                        vec![Mod::Replace(
+                            name_attr.to_string(),
+                            HashSet::from([new_name.clone()]),
+                        )],
+                    )
+                    .await?,
+                    &new_name,
+                    PrincipalField::Name,
+                )?;
+            }
+
+            // Keep the account id assigned to the principal
+            if self.data_store.get_account_id(&name).await?.is_some() {
+                self.data_store
+                    .update_account(
+                        QueryBy::Name(&name),
+                        vec![PrincipalUpdate::set(
+                            PrincipalField::Name,
+                            PrincipalValue::String(new_name),
+                        )],
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn delete_account(&self, by: QueryBy<'_>) -> crate::Result<()> {
+        let write = self.write_settings()?;
+        let mut conn = self.pool.get().await?;
+        let name = self.account_name(by).await?;
+        let entry = self
+            .find_entry(&mut conn, &name)
+            .await?
+            .ok_or_else(|| DirectoryError::Management(ManagementError::NotFound(name.clone())))?;
+        let principal = self.mappings.entry_to_principal(entry.clone());
+
+        // Remove principal from its groups
+        for group in &principal.member_of {
+            let group_dn = match self.principal_dn(&mut conn, group).await {
+                Ok(group_dn) => group_dn,
+                Err(DirectoryError::Management(ManagementError::NotFound(_))) => continue,
+                Err(err) => return Err(err),
+            };
+            check_result(
+                conn.modify(
+                    &group_dn,
+                    vec![Mod::Delete(
+                        write.attr_member.clone(),
+                        HashSet::from([entry.dn.clone()]),
+                    )],
+                )
+                .await?,
+                group,
+                PrincipalField::MemberOf,
+            )
+            .or_else(|err| match err {
+                DirectoryError::Management(ManagementError::NotFound(_)) => Ok(()),
+                err => Err(err),
+            })?;
+        }
+
+        check_result(conn.delete(&entry.dn).await?, &name, PrincipalField::Name)?;
+        tracing::debug!(
+            context = "ldap",
+            event = "delete_principal",
+            dn = entry.dn,
+            "Deleted LDAP entry"
+        );
+
+        Ok(())
+    }
+
+    pub async fn get_members(&self, name: &str) -> crate::Result<Vec<String>> {
+        let write = self.write_settings()?;
+        let mut conn = self.pool.get().await?;
+        let mut members = Vec::new();
+
+        if let Some(entry) = self.find_entry(&mut conn, name).await? {
+            for member_dn in entry.attrs.get(&write.attr_member).into_iter().flatten() {
+                if let Some(member) = self.dn_to_name(&mut conn, member_dn).await? {
+                    members.push(member);
+                }
+            }
+        }
+
+        Ok(members)
+    }
+
+    pub async fn list_accounts(
+        &self,
+        filter: Option<&str>,
+        typ: Option<Type>,
+    ) -> crate::Result<Vec<String>> {
+        let mut conn = self.pool.get().await?;
+        let filters = filter
+            .map(|filter| {
+                filter
+                    .split_whitespace()
+                    .map(|term| term.to_lowercase())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        // Match all principals by replacing the name placeholder with a wildcard
+        let (rs, _) = conn
+            .search(
+                &self.mappings.base_dn,
+                Scope::Subtree,
+                &self.mappings.filter_name.filter.join("*"),
+                &self.mappings.attrs_principal,
+            )
+            .await?
+            .success()?;
+
+        let mut names = Vec::new();
+        for entry in rs {
+            let principal = self
+                .mappings
+                .entry_to_principal(SearchEntry::construct(entry));
+            if principal.name.is_empty() || typ.map_or(false, |typ| principal.typ != typ) {
+                continue;
+            }
+
+            if filters.iter().all(|term| {
+                principal.name.to_lowercase().contains(term)
+                    || principal
+                        .description
+                        .as_ref()
+                        .map_or(false, |d| d.to_lowercase().contains(term))
+                    || principal
+                        .emails
+                        .iter()
+                        .any(|email| email.to_lowercase().contains(term))
+            }) {
+                names.push(principal.name);
+            }
+        }
+        names.sort_unstable();
+        names.dedup();
+
+        Ok(names)
+    }
+
+    fn write_settings(&self) -> crate::Result<&LdapWriteSettings> {
+        self.write.as_ref().ok_or(DirectoryError::Unsupported)
+    }
+
+    fn attr_required<'x>(&self, attrs: &'x [String]) -> crate::Result<&'x str> {
+        attrs
+            .first()
+            .map(|attr| attr.as_str())
+            .ok_or(DirectoryError::Unsupported)
+    }
+
+    fn attr_email(&self, is_primary: bool) -> crate::Result<&str> {
+        if !is_primary {
+            if let Some(attr) = self.mappings.attr_email_alias.first() {
+                return Ok(attr);
+            }
+        }
+        self.attr_required(&self.mappings.attr_email_address)
+    }
+
+    async fn account_name(&self, by: QueryBy<'_>) -> crate::Result<String> {
+        match by {
+            QueryBy::Name(name) => Ok(name.to_string()),
+            QueryBy::Id(account_id) => self
+                .data_store
+                .get_account_name(account_id)
+                .await?
+                .ok_or_else(|| {
+                    DirectoryError::Management(ManagementError::NotFound(account_id.to_string()))
+                }),
+            QueryBy::Credentials(_) => Err(DirectoryError::Unsupported),
+        }
+    }
+
+    async fn validate_email(&self, email: &str) -> crate::Result<()> {
+        if self.rcpt(email).await? {
+            Err(DirectoryError::Management(ManagementError::AlreadyExists {
+                field: PrincipalField::Emails,
+                value: email.to_string(),
+            }))
+        } else if let Some(domain) = email
+            .rsplit_once('@')
+            .map(|(_, domain)| domain)
+            .filter(|domain| !domain.is_empty())
+        {
+            if self.is_local_domain(domain).await? {
+                Ok(())
+            } else {
+                Err(DirectoryError::Management(ManagementError::NotFound(
+                    domain.to_string(),
+                )))
+            }
+        } else {
+            Err(DirectoryError::Management(ManagementError::NotFound(
+                email.to_string(),
+            )))
+        }
+    }
+
+    async fn find_entry(&self, conn: &mut Ldap, name: &str) -> crate::Result<Option<SearchEntry>> {
+        let mut attrs = self.mappings.attrs_principal.clone();
+        if let Some(write) = &self.write {
+            attrs.push(write.attr_member.clone());
+        }
+
+        conn.search(
+            &self.mappings.base_dn,
+            Scope::Subtree,
+            &self.mappings.filter_name.build(name),
+            attrs,
+        )
+        .await?
+        .success()
+        .map(|(rs, _)| rs.into_iter().next().map(SearchEntry::construct))
+        .map_err(Into::into)
+    }
+
+    async fn principal_dn(&self, conn: &mut Ldap, name: &str) -> crate::Result<String> {
+        // Group memberships might already be stored as DNs
+        if name.contains('=') {
+            return Ok(name.to_string());
+        }
+
+        self.find_entry(conn, name)
+            .await?
+            .map(|entry| entry.dn)
+            .ok_or_else(|| DirectoryError::Management(ManagementError::NotFound(name.to_string())))
+    }
+
+    async fn dn_to_name(&self, conn: &mut Ldap, dn: &str) -> crate::Result<Option<String>> {
+        let (rs, _) = match conn
+            .search(dn, Scope::Base, "objectClass=*", &self.mappings.attr_name)
+            .await?
+            .success()
+        {
+            Ok(result) => result,
+            Err(ldap3::LdapError::LdapResult { result }) if result.rc == RC_NO_SUCH_OBJECT => {
+                return Ok(None)
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(rs.into_iter().find_map(|entry| {
+            let entry = SearchEntry::construct(entry);
+            self.mappings.attr_name.iter().find_map(|attr| {
+                entry
+                    .attrs
+                    .get(attr)
+                    .and_then(|v| v.first())
+                    .filter(|v| !v.is_empty())
+                    .cloned()
+            })
+        }))
+    }
+
+    async fn set_password(&self, conn: &mut Ldap, dn: &str, password: &str) -> crate::Result<()> {
+        conn.extended(PasswordModify {
+            user_id: Some(dn),
+            old_pass: None,
+            new_pass: Some(password),
+        })
+        .await?
+        .success()
+        .map(|_| ())
+        .map_err(Into::into)
+    }
+}
+
+// LDAP servers keep their own password history and expiry, only the
+// strength rules of the policy can be enforced on new passwords
+fn validate_passwords<'x>(
+    policy: Option<&PasswordPolicy>,
+    secrets: impl IntoIterator<Item = &'x String>,
+) -> crate::Result<()> {
+    if let Some(policy) = policy {
+        for secret in secrets.into_iter().filter(|secret| secret.is_password()) {
+            if let Some(password) = plain_text_secret(secret) {
+                policy
+                    .validate(password)
+                    .map_err(PasswordViolation::into_error)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn push_attr(attrs: &mut Attributes, attr: &str, values: impl IntoIterator<Item = String>) {
+    let mut values = values.into_iter().peekable();
+    if values.peek().is_none() {
+        return;
+    }
+
+    if let Some((_, existing)) = attrs
+        .iter_mut()
+        .find(|(name, _)| name.eq_ignore_ascii_case(attr))
+    {
+        existing.extend(values);
+    } else {
+        attrs.push((attr.to_string(), values.collect()));
+    }
+}
+
+fn check_result(result: LdapResult, value: &str, field: PrincipalField) -> crate::Result<()> {
+    match result.rc {
+        0 | RC_NO_SUCH_ATTRIBUTE | RC_ATTRIBUTE_OR_VALUE_EXISTS => Ok(()),
+        RC_ENTRY_ALREADY_EXISTS => {
+            Err(DirectoryError::Management(ManagementError::AlreadyExists {
+                field,
+                value: value.to_string(),
+            }))
+        }
+        RC_NO_SUCH_OBJECT => Err(DirectoryError::Management(ManagementError::NotFound(
+            value.to_string(),
+        ))),
+        _ => result.success().map(|_| ()).map_err(Into::into),
+    }
+}
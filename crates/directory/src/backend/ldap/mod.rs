@@ -10,15 +10,27 @@ use store::Store;
 
 pub mod config;
 pub mod lookup;
+pub mod manage;
 pub mod pool;
 
 pub struct LdapDirectory {
     pool: Pool<LdapConnectionManager>,
     mappings: LdapMappings,
     auth_bind: Option<LdapFilter>,
+    write: Option<LdapWriteSettings>,
     pub(crate) data_store: Store,
 }
 
+#[derive(Debug, Default)]
+pub struct LdapWriteSettings {
+    base_dn: String,
+    attr_rdn: String,
+    attr_member: String,
+    object_class_individual: Vec<String>,
+    object_class_group: Vec<String>,
+    password_modify: bool,
+}
+
 #[derive(Debug, Default)]
 pub struct LdapMappings {
     base_dn: String,
@@ -35,6 +47,8 @@ pub struct LdapMappings {
     attr_email_address: Vec<String>,
     attr_email_alias: Vec<String>,
     attr_quota: Vec<String>,
+    attr_time_zone: Vec<String>,
+    attr_undelete_retention: Vec<String>,
     attrs_principal: Vec<String>,
 }
 
@@ -4,6 +4,8 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::time::Duration;
+
 use store::Store;
 use utils::config::{utils::AsKey, Config};
 
@@ -132,6 +134,18 @@ impl MemoryDirectory {
                 quota: config
                     .property((prefix.as_str(), "principals", lookup_id, "quota"))
                     .unwrap_or(0),
+                time_zone: config
+                    .value((prefix.as_str(), "principals", lookup_id, "time-zone"))
+                    .map(|v| v.to_string()),
+                undelete_retention: config
+                    .property::<Duration>((
+                        prefix.as_str(),
+                        "principals",
+                        lookup_id,
+                        "undelete-retention",
+                    ))
+                    .map(|retention| retention.as_secs())
+                    .unwrap_or(0),
                 member_of,
                 id,
                 emails,
@@ -4,6 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod composite;
 pub mod imap;
 pub mod internal;
 pub mod ldap;
@@ -43,6 +43,14 @@ impl SqlDirectory {
                 .value((&prefix, "columns.class"))
                 .unwrap_or_default()
                 .to_string(),
+            column_time_zone: config
+                .value((&prefix, "columns.time-zone"))
+                .unwrap_or_default()
+                .to_string(),
+            column_undelete_retention: config
+                .value((&prefix, "columns.undelete-retention"))
+                .unwrap_or_default()
+                .to_string(),
             ..Default::default()
         };
 
@@ -207,6 +207,14 @@ impl SqlMappings {
                     if let Value::Integer(quota) = value {
                         principal.quota = quota as u64;
                     }
+                } else if name.eq_ignore_ascii_case(&self.column_time_zone) {
+                    if let Value::Text(text) = value {
+                        principal.time_zone = text.into_owned().into();
+                    }
+                } else if name.eq_ignore_ascii_case(&self.column_undelete_retention) {
+                    if let Value::Integer(retention) = value {
+                        principal.undelete_retention = retention as u64;
+                    }
                 }
             }
         }
@@ -28,4 +28,6 @@ pub(crate) struct SqlMappings {
     column_secret: String,
     column_quota: String,
     column_type: String,
+    column_time_zone: String,
+    column_undelete_retention: String,
 }
@@ -16,8 +16,8 @@ use ahash::AHashMap;
 
 use crate::{
     backend::{
-        imap::ImapDirectory, ldap::LdapDirectory, memory::MemoryDirectory, smtp::SmtpDirectory,
-        sql::SqlDirectory,
+        composite::CompositeDirectory, imap::ImapDirectory, ldap::LdapDirectory,
+        memory::MemoryDirectory, smtp::SmtpDirectory, sql::SqlDirectory,
     },
     Directories, Directory, DirectoryInner,
 };
@@ -27,6 +27,7 @@ use super::cache::CachedDirectory;
 impl Directories {
     pub async fn parse(config: &mut Config, stores: &Stores, data_store: Store) -> Self {
         let mut directories = AHashMap::new();
+        let mut composites = Vec::new();
 
         for id in config
             .sub_keys("directory", ".type")
@@ -77,6 +78,11 @@ impl Directories {
                 "memory" => MemoryDirectory::from_config(config, prefix, data_store.clone())
                     .await
                     .map(DirectoryInner::Memory),
+                "composite" => {
+                    // Composite directories are built once all other directories are available
+                    composites.push(id.to_string());
+                    continue;
+                }
                 unknown => {
                     let err = format!("Unknown directory type: {unknown:?}");
                     config.new_parse_error(("directory", id, "type"), err);
@@ -96,6 +102,22 @@ impl Directories {
             }
         }
 
+        // Build composite directories, members are resolved before any composite
+        // is added so they cannot be nested regardless of their order
+        let mut composite_directories = Vec::with_capacity(composites.len());
+        for id in composites {
+            if let Some(store) =
+                CompositeDirectory::from_config(config, ("directory", id.as_str()), &directories)
+            {
+                let directory = Arc::new(Directory {
+                    store: DirectoryInner::Composite(store),
+                    cache: CachedDirectory::try_from_config(config, ("directory", id.as_str())),
+                });
+                composite_directories.push((id, directory));
+            }
+        }
+        directories.extend(composite_directories);
+
         Directories { directories }
     }
 }
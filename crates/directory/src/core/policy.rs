@@ -0,0 +1,325 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{fmt::Display, time::Duration};
+
+use ahash::AHashSet;
+use pwhash::sha512_crypt;
+use sha1::{Digest, Sha1};
+use utils::config::Config;
+
+use crate::{backend::internal::SpecialSecrets, DirectoryError, ManagementError};
+
+use super::secret::{plain_text_secret, verify_secret_hash, ScramCredentials};
+
+#[derive(Debug, Clone, Default)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_special: bool,
+    pub breached: AHashSet<[u8; 20]>,
+    pub history: usize,
+    pub expiry: Option<Duration>,
+    pub grace_period: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordViolation {
+    TooShort(usize),
+    MissingLowercase,
+    MissingUppercase,
+    MissingDigit,
+    MissingSpecial,
+    Breached,
+    Reused,
+    Hashed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordStatus {
+    Valid,
+    Grace { expires_at: u64 },
+    Expired,
+}
+
+impl PasswordPolicy {
+    pub fn parse(config: &mut Config) -> Self {
+        let mut policy = PasswordPolicy {
+            min_length: config
+                .property_or_default("authentication.password.min-length", "0")
+                .unwrap_or(0),
+            require_lowercase: config
+                .property_or_default("authentication.password.require.lowercase", "false")
+                .unwrap_or(false),
+            require_uppercase: config
+                .property_or_default("authentication.password.require.uppercase", "false")
+                .unwrap_or(false),
+            require_digit: config
+                .property_or_default("authentication.password.require.number", "false")
+                .unwrap_or(false),
+            require_special: config
+                .property_or_default("authentication.password.require.special", "false")
+                .unwrap_or(false),
+            breached: AHashSet::new(),
+            history: config
+                .property_or_default("authentication.password.history", "0")
+                .unwrap_or(0),
+            expiry: config
+                .property_or_default::<Option<Duration>>("authentication.password.expiry", "false")
+                .unwrap_or_default(),
+            grace_period: config
+                .property_or_default("authentication.password.grace-period", "7d")
+                .unwrap_or(Duration::from_secs(7 * 86400)),
+        };
+
+        // Breached passwords are read from a local file containing either one
+        // password per line or SHA-1 hashes in the 'HASH[:count]' format
+        if let Some(path) = config
+            .value("authentication.password.breached-list")
+            .map(|path| path.to_string())
+        {
+            match std::fs::read_to_string(&path) {
+                Ok(contents) => {
+                    for line in contents.lines() {
+                        let line = line.trim_end_matches('\r');
+                        if !line.is_empty() {
+                            policy.breached.insert(breached_entry(line));
+                        }
+                    }
+                }
+                Err(err) => {
+                    config.new_build_error(
+                        "authentication.password.breached-list",
+                        format!("Failed to read breached password list {path:?}: {err}"),
+                    );
+                }
+            }
+        }
+
+        policy
+    }
+
+    pub fn validate(&self, password: &str) -> Result<(), PasswordViolation> {
+        if password.chars().count() < self.min_length {
+            Err(PasswordViolation::TooShort(self.min_length))
+        } else if self.require_lowercase && !password.chars().any(|ch| ch.is_lowercase()) {
+            Err(PasswordViolation::MissingLowercase)
+        } else if self.require_uppercase && !password.chars().any(|ch| ch.is_uppercase()) {
+            Err(PasswordViolation::MissingUppercase)
+        } else if self.require_digit && !password.chars().any(|ch| ch.is_numeric()) {
+            Err(PasswordViolation::MissingDigit)
+        } else if self.require_special && password.chars().all(|ch| ch.is_alphanumeric()) {
+            Err(PasswordViolation::MissingSpecial)
+        } else if !self.breached.is_empty()
+            && self
+                .breached
+                .contains(&<[u8; 20]>::from(Sha1::digest(password.as_bytes())))
+        {
+            Err(PasswordViolation::Breached)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.min_length > 0
+            || self.require_lowercase
+            || self.require_uppercase
+            || self.require_digit
+            || self.require_special
+            || !self.breached.is_empty()
+            || self.history > 0
+            || self.expiry.is_some()
+    }
+
+    // Hashed passwords cannot be checked against the policy, only administrators
+    // are allowed to set them
+    pub fn validate_plain_text(&self, password: &str) -> Result<(), PasswordViolation> {
+        if self.is_enabled() && plain_text_secret(password).is_none() {
+            Err(PasswordViolation::Hashed)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Enforces the policy on a list of secrets that is about to replace the
+    // current one. Password history and the time of the last password change
+    // are maintained as special secrets that cannot be set by clients.
+    // Secrets that are already hashed cannot be checked for complexity.
+    pub async fn apply(&self, current: &[String], secrets: &mut Vec<String>) -> crate::Result<()> {
+        secrets.retain(|secret| !secret.is_password_history() && !secret.is_password_timestamp());
+
+        let previous = current
+            .iter()
+            .filter(|secret| is_policy_password(secret))
+            .collect::<Vec<_>>();
+        let mut history = current
+            .iter()
+            .filter_map(|secret| secret.strip_prefix("$history$"))
+            .map(|secret| secret.to_string())
+            .collect::<Vec<_>>();
+
+        // Setting the current password again is not a change
+        for secret in secrets.iter_mut() {
+            if let Some(password) = plain_text_secret(secret).filter(|_| secret.is_password()) {
+                for old in &previous {
+                    if verify_secret_hash(old, password).await {
+                        *secret = old.to_string();
+                        break;
+                    }
+                }
+            }
+        }
+
+        let changed = secrets
+            .iter()
+            .filter(|secret| is_policy_password(secret) && !previous.contains(secret))
+            .collect::<Vec<_>>();
+
+        if changed.is_empty() {
+            // Passwords did not change, keep the existing metadata
+            secrets.extend(
+                current
+                    .iter()
+                    .filter(|secret| secret.is_password_history() || secret.is_password_timestamp())
+                    .cloned(),
+            );
+            return Ok(());
+        }
+
+        for secret in changed {
+            let password = plain_text_secret(secret);
+            if let Some(password) = password {
+                self.validate(password)
+                    .map_err(PasswordViolation::into_error)?;
+            }
+
+            // The current password counts towards the history
+            if self.history > 0 {
+                for old in previous
+                    .iter()
+                    .map(|secret| secret.as_str())
+                    .chain(history.iter().map(|secret| secret.as_str()))
+                    .take(self.history.max(previous.len()))
+                {
+                    if old == secret {
+                        return Err(PasswordViolation::Reused.into_error());
+                    } else if let Some(password) = password {
+                        if verify_secret_hash(old, password).await {
+                            return Err(PasswordViolation::Reused.into_error());
+                        }
+                    }
+                }
+            }
+        }
+
+        // Archive previous passwords
+        if self.history > 1 {
+            let mut archived = previous
+                .iter()
+                .map(|secret| match plain_text_secret(secret) {
+                    Some(password) => {
+                        sha512_crypt::hash(password).unwrap_or_else(|_| secret.to_string())
+                    }
+                    None => secret.to_string(),
+                })
+                .collect::<Vec<_>>();
+            archived.append(&mut history);
+            archived.truncate(self.history - 1);
+            secrets.extend(
+                archived
+                    .into_iter()
+                    .map(|secret| format!("$history${secret}")),
+            );
+        }
+
+        if self.expiry.is_some() {
+            secrets.push(format!("$changed${}", now()));
+        }
+
+        Ok(())
+    }
+
+    // Passwords without a change timestamp are not aged here, the internal
+    // directory records one on their first login after expiry is enabled
+    pub fn status(&self, changed_at: Option<u64>) -> PasswordStatus {
+        if let (Some(expiry), Some(changed_at)) = (self.expiry, changed_at) {
+            let expires_at = changed_at + expiry.as_secs();
+            let now = now();
+            if now < expires_at {
+                PasswordStatus::Valid
+            } else if now < expires_at + self.grace_period.as_secs() {
+                PasswordStatus::Grace {
+                    expires_at: expires_at + self.grace_period.as_secs(),
+                }
+            } else {
+                PasswordStatus::Expired
+            }
+        } else {
+            PasswordStatus::Valid
+        }
+    }
+}
+
+impl PasswordViolation {
+    pub fn into_error(self) -> DirectoryError {
+        DirectoryError::Management(ManagementError::PasswordPolicy(self))
+    }
+}
+
+impl Display for PasswordViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PasswordViolation::TooShort(min) => {
+                write!(f, "Password must be at least {min} characters long")
+            }
+            PasswordViolation::MissingLowercase => {
+                write!(f, "Password must contain a lowercase letter")
+            }
+            PasswordViolation::MissingUppercase => {
+                write!(f, "Password must contain an uppercase letter")
+            }
+            PasswordViolation::MissingDigit => write!(f, "Password must contain a number"),
+            PasswordViolation::MissingSpecial => {
+                write!(f, "Password must contain a special character")
+            }
+            PasswordViolation::Breached => {
+                write!(f, "Password appears in a list of breached passwords")
+            }
+            PasswordViolation::Reused => write!(f, "Password was used recently"),
+            PasswordViolation::Hashed => write!(f, "Password must be provided in plain text"),
+        }
+    }
+}
+
+// SCRAM credentials are derived from the password they accompany
+fn is_policy_password(secret: &str) -> bool {
+    secret.is_password() && ScramCredentials::parse(secret).is_none()
+}
+
+fn breached_entry(line: &str) -> [u8; 20] {
+    let hash = line.split_once(':').map_or(line, |(hash, _)| hash);
+    if hash.len() == 40 && hash.bytes().all(|ch| ch.is_ascii_hexdigit()) {
+        let mut entry = [0u8; 20];
+        for (pos, hex) in hash.as_bytes().chunks(2).enumerate() {
+            entry[pos] = std::str::from_utf8(hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .unwrap_or_default();
+        }
+        entry
+    } else {
+        Sha1::digest(line.as_bytes()).into()
+    }
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
@@ -8,20 +8,43 @@ use argon2::Argon2;
 use mail_builder::encoders::base64::base64_encode;
 use mail_parser::decoders::base64::base64_decode;
 use password_hash::PasswordHash;
-use pbkdf2::Pbkdf2;
+use pbkdf2::hmac::{Hmac, Mac};
+use pbkdf2::{pbkdf2_hmac, Pbkdf2};
 use pwhash::{bcrypt, bsdi_crypt, md5_crypt, sha1_crypt, sha256_crypt, sha512_crypt, unix_crypt};
+use rand::RngCore;
 use scrypt::Scrypt;
+use sha1::digest::KeyInit;
 use sha1::Digest;
 use sha1::Sha1;
 use sha2::Sha256;
 use sha2::Sha512;
 use tokio::sync::oneshot;
 use totp_rs::TOTP;
+use utils::constant_time_eq;
 
 use crate::backend::internal::SpecialSecrets;
 use crate::DirectoryError;
 use crate::Principal;
 
+// Iteration count used when SCRAM credentials are derived from a plain text secret
+pub const SCRAM_DEFAULT_ITERATIONS: u32 = 4096;
+const SCRAM_SALT_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScramAlgorithm {
+    Sha1,
+    Sha256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScramCredentials {
+    pub algorithm: ScramAlgorithm,
+    pub iterations: u32,
+    pub salt: Vec<u8>,
+    pub stored_key: Vec<u8>,
+    pub server_key: Vec<u8>,
+}
+
 impl<T: serde::Serialize + serde::de::DeserializeOwned> Principal<T> {
     pub async fn verify_secret(&self, mut code: &str) -> crate::Result<bool> {
         let mut totp_token = None;
@@ -36,6 +59,11 @@ impl<T: serde::Serialize + serde::de::DeserializeOwned> Principal<T> {
                 // Account is disabled, no need to check further
 
                 return Ok(false);
+            } else if secret.is_password_history()
+                || secret.is_password_timestamp()
+                || secret.is_webauthn()
+            {
+                continue;
             } else if secret.is_otp_auth() {
                 if !is_totp_verified && !is_totp_token_missing {
                     is_totp_required = true;
@@ -108,6 +136,242 @@ impl<T: serde::Serialize + serde::de::DeserializeOwned> Principal<T> {
     }
 }
 
+impl<T> Principal<T> {
+    // Returns the SCRAM credentials of the principal for the requested algorithm.
+    // Stored credentials are preferred, otherwise they are derived using the
+    // provided salt when only a plain text secret is available. Accounts protected with TOTP can only use SCRAM
+    // through app passwords, as the token cannot be included in the exchange.
+    pub fn scram_credentials(
+        &self,
+        algorithm: ScramAlgorithm,
+        salt: &[u8],
+    ) -> Option<ScramCredentials> {
+        let mut is_totp_required = false;
+        let mut credentials = None;
+        let mut stored_credentials = None;
+        let mut app_credentials = None;
+
+        for secret in &self.secrets {
+            if secret.is_disabled() {
+                return None;
+            } else if secret.is_password_history()
+                || secret.is_password_timestamp()
+                || secret.is_webauthn()
+            {
+                continue;
+            } else if secret.is_otp_auth() {
+                is_totp_required = true;
+            } else if let Some((_, app_secret)) =
+                secret.strip_prefix("$app$").and_then(|s| s.split_once('$'))
+            {
+                if app_credentials.is_none() {
+                    app_credentials = ScramCredentials::from_secret(app_secret, algorithm, salt);
+                }
+            } else if let Some(stored) = ScramCredentials::parse(secret) {
+                if stored.algorithm == algorithm && stored_credentials.is_none() {
+                    stored_credentials = Some(stored);
+                }
+            } else if credentials.is_none() {
+                credentials = ScramCredentials::from_secret(secret, algorithm, salt);
+            }
+        }
+
+        if !is_totp_required {
+            stored_credentials.or(credentials).or(app_credentials)
+        } else {
+            app_credentials
+        }
+    }
+
+    // Returns the time of the last password change, if it was tracked
+    // by the password policy
+    pub fn password_changed_at(&self) -> Option<u64> {
+        self.secrets
+            .iter()
+            .find_map(|secret| secret.strip_prefix("$changed$"))
+            .and_then(|timestamp| timestamp.parse().ok())
+    }
+}
+
+impl ScramCredentials {
+    pub fn derive(algorithm: ScramAlgorithm, secret: &str, salt: Vec<u8>, iterations: u32) -> Self {
+        let salted_password = algorithm.salted_password(secret.as_bytes(), &salt, iterations);
+        let client_key = algorithm.hmac(&salted_password, b"Client Key");
+
+        ScramCredentials {
+            algorithm,
+            iterations,
+            stored_key: algorithm.hash(&client_key),
+            server_key: algorithm.hmac(&salted_password, b"Server Key"),
+            salt,
+        }
+    }
+
+    // Parses secrets in the RFC 5803 format, for example
+    // {SCRAM-SHA-256}4096:<salt>$<stored-key>:<server-key>
+    pub fn parse(secret: &str) -> Option<Self> {
+        let (algorithm, secret) = secret.strip_prefix('{')?.split_once('}')?;
+        Self::parse_params(ScramAlgorithm::parse(algorithm)?, secret)
+    }
+
+    fn parse_params(algorithm: ScramAlgorithm, secret: &str) -> Option<Self> {
+        let (params, keys) = secret.split_once('$')?;
+        let (iterations, salt) = params.split_once(':')?;
+        let (stored_key, server_key) = keys.split_once(':')?;
+
+        Some(ScramCredentials {
+            algorithm,
+            iterations: iterations.parse().ok().filter(|&i| i > 0)?,
+            salt: base64_decode(salt.as_bytes())?,
+            stored_key: base64_decode(stored_key.as_bytes())
+                .filter(|key| key.len() == algorithm.digest_len())?,
+            server_key: base64_decode(server_key.as_bytes())
+                .filter(|key| key.len() == algorithm.digest_len())?,
+        })
+    }
+
+    fn from_secret(secret: &str, algorithm: ScramAlgorithm, salt: &[u8]) -> Option<Self> {
+        if let Some(credentials) = Self::parse(secret) {
+            Some(credentials).filter(|credentials| credentials.algorithm == algorithm)
+        } else {
+            plain_text_secret(secret).map(|secret| {
+                Self::derive(algorithm, secret, salt.to_vec(), SCRAM_DEFAULT_ITERATIONS)
+            })
+        }
+    }
+
+    pub fn verify(&self, secret: &str) -> bool {
+        let salted_password =
+            self.algorithm
+                .salted_password(secret.as_bytes(), &self.salt, self.iterations);
+        let client_key = self.algorithm.hmac(&salted_password, b"Client Key");
+
+        constant_time_eq(&self.algorithm.hash(&client_key), &self.stored_key)
+    }
+}
+
+// Stores the SCRAM credentials of plain text passwords, replacing any
+// credentials derived from a previous password
+pub fn derive_scram_credentials(secrets: &mut Vec<String>) {
+    let passwords = secrets
+        .iter()
+        .filter(|secret| secret.is_password())
+        .filter_map(|secret| plain_text_secret(secret))
+        .map(|secret| secret.to_string())
+        .collect::<Vec<_>>();
+    if passwords.is_empty() {
+        return;
+    }
+
+    secrets.retain(|secret| ScramCredentials::parse(secret).is_none());
+    let mut rng = rand::thread_rng();
+    for password in passwords {
+        for algorithm in [ScramAlgorithm::Sha256, ScramAlgorithm::Sha1] {
+            let mut salt = vec![0u8; SCRAM_SALT_LEN];
+            rng.fill_bytes(&mut salt);
+            secrets.push(
+                ScramCredentials::derive(algorithm, &password, salt, SCRAM_DEFAULT_ITERATIONS)
+                    .to_string(),
+            );
+        }
+    }
+}
+
+// Plain text passwords are only kept until the SCRAM credentials are derived
+pub fn hash_passwords(secrets: &mut [String]) {
+    for secret in secrets.iter_mut() {
+        if secret.is_password() {
+            if let Some(hash) =
+                plain_text_secret(secret).and_then(|password| sha512_crypt::hash(password).ok())
+            {
+                *secret = hash;
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ScramCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{{{}}}{}:{}${}:{}",
+            self.algorithm.as_str(),
+            self.iterations,
+            String::from_utf8_lossy(&base64_encode(&self.salt).unwrap_or_default()),
+            String::from_utf8_lossy(&base64_encode(&self.stored_key).unwrap_or_default()),
+            String::from_utf8_lossy(&base64_encode(&self.server_key).unwrap_or_default())
+        )
+    }
+}
+
+impl ScramAlgorithm {
+    pub fn parse(value: &str) -> Option<Self> {
+        if value.eq_ignore_ascii_case("SCRAM-SHA-256") {
+            Some(ScramAlgorithm::Sha256)
+        } else if value.eq_ignore_ascii_case("SCRAM-SHA-1") {
+            Some(ScramAlgorithm::Sha1)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScramAlgorithm::Sha1 => "SCRAM-SHA-1",
+            ScramAlgorithm::Sha256 => "SCRAM-SHA-256",
+        }
+    }
+
+    pub fn digest_len(&self) -> usize {
+        match self {
+            ScramAlgorithm::Sha1 => 20,
+            ScramAlgorithm::Sha256 => 32,
+        }
+    }
+
+    pub fn hash(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            ScramAlgorithm::Sha1 => Sha1::digest(data).to_vec(),
+            ScramAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+        }
+    }
+
+    pub fn hmac(&self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        match self {
+            ScramAlgorithm::Sha1 => {
+                let mut mac = <Hmac<Sha1> as KeyInit>::new_from_slice(key).unwrap();
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            ScramAlgorithm::Sha256 => {
+                let mut mac = <Hmac<Sha256> as KeyInit>::new_from_slice(key).unwrap();
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+
+    pub fn salted_password(&self, secret: &[u8], salt: &[u8], iterations: u32) -> Vec<u8> {
+        let mut result = vec![0u8; self.digest_len()];
+        match self {
+            ScramAlgorithm::Sha1 => pbkdf2_hmac::<Sha1>(secret, salt, iterations, &mut result),
+            ScramAlgorithm::Sha256 => pbkdf2_hmac::<Sha256>(secret, salt, iterations, &mut result),
+        }
+        result
+    }
+}
+
+// Returns the password contained in a plain text secret, or None if the
+// secret is hashed
+pub fn plain_text_secret(secret: &str) -> Option<&str> {
+    secret
+        .strip_prefix("{PLAIN}")
+        .or_else(|| secret.strip_prefix("{plain}"))
+        .or_else(|| secret.strip_prefix("{CLEAR}"))
+        .or_else(|| secret.strip_prefix("{clear}"))
+        .or_else(|| (!secret.starts_with(['$', '_', '{'])).then_some(secret))
+}
+
 async fn verify_hash_prefix(hashed_secret: &str, secret: &str) -> bool {
     if hashed_secret.starts_with("$argon2")
         || hashed_secret.starts_with("$pbkdf2")
@@ -251,6 +515,12 @@ pub async fn verify_secret_hash(hashed_secret: &str, secret: &str) -> bool {
                     }
                 }
                 "PLAIN" | "plain" | "CLEAR" | "clear" => hashed_secret == secret,
+                "SCRAM-SHA-1" | "SCRAM-SHA-256" => {
+                    // Salted SCRAM credentials (RFC 5803)
+                    ScramAlgorithm::parse(algo)
+                        .and_then(|algo| ScramCredentials::parse_params(algo, hashed_secret))
+                        .map_or(false, |credentials| credentials.verify(secret))
+                }
                 _ => {
                     tracing::warn!(
                         context = "directory",
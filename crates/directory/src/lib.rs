@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use core::cache::CachedDirectory;
+use core::{cache::CachedDirectory, policy::PasswordViolation};
 use std::{
     fmt::{Debug, Display},
     sync::Arc,
@@ -12,6 +12,7 @@ use std::{
 
 use ahash::AHashMap;
 use backend::{
+    composite::CompositeDirectory,
     imap::{ImapDirectory, ImapError},
     internal::PrincipalField,
     ldap::LdapDirectory,
@@ -51,6 +52,12 @@ pub struct Principal<T> {
     pub member_of: Vec<T>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(default)]
+    #[serde(rename = "timeZone", skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+    #[serde(default)]
+    #[serde(rename = "undeleteRetention")]
+    pub undelete_retention: u64,
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -94,6 +101,7 @@ pub enum ManagementError {
         value: String,
     },
     NotFound(String),
+    PasswordPolicy(PasswordViolation),
 }
 
 pub enum DirectoryInner {
@@ -103,8 +111,10 @@ pub enum DirectoryInner {
     Imap(ImapDirectory),
     Smtp(SmtpDirectory),
     Memory(MemoryDirectory),
+    Composite(CompositeDirectory),
 }
 
+#[derive(Clone, Copy)]
 pub enum QueryBy<'x> {
     Name(&'x str),
     Id(u32),
@@ -311,6 +311,14 @@ pub fn parse_filters(
                             .ok_or_else(|| Cow::from("Expected an THREADID value."))?
                             .unwrap_string()?,
                     ));
+                } else if value.eq_ignore_ascii_case(b"X-ATTACHMENT-NAME") {
+                    filters.push(Filter::AttachmentName(decode_argument(tokens, decoder)?));
+                } else if value.eq_ignore_ascii_case(b"X-ATTACHMENT-TYPE") {
+                    filters.push(Filter::AttachmentType(decode_argument(tokens, decoder)?));
+                } else if value.eq_ignore_ascii_case(b"X-ENCRYPTED") {
+                    filters.push(Filter::Encrypted);
+                } else if value.eq_ignore_ascii_case(b"X-SIGNED") {
+                    filters.push(Filter::Signed);
                 } else if value.eq_ignore_ascii_case(b"OR") {
                     if filters_stack.len() > 10 {
                         return Err(Cow::from("Too many nested filters"));
@@ -740,6 +748,23 @@ mod tests {
                     sort: None,
                 },
             ),
+            (
+                b"6 SEARCH X-ATTACHMENT-NAME \"invoice\" X-ATTACHMENT-TYPE application/pdf NOT X-ENCRYPTED X-SIGNED\r\n".to_vec(),
+                search::Arguments {
+                    tag: "6".to_string(),
+                    result_options: vec![],
+                    filter: vec![
+                        Filter::AttachmentName("invoice".to_string()),
+                        Filter::AttachmentType("application/pdf".to_string()),
+                        Filter::Not,
+                        Filter::Encrypted,
+                        Filter::End,
+                        Filter::Signed,
+                    ],
+                    is_esearch: true,
+                    sort: None,
+                },
+            ),
         ] {
             let command_str = String::from_utf8_lossy(&command).into_owned();
             assert_eq!(
@@ -135,7 +135,15 @@ impl Capability {
                 Capability::Preview,
             ]);
         } else {
+            if is_tls {
+                capabilities.extend([
+                    Capability::Auth(Mechanism::ScramSha256Plus),
+                    Capability::Auth(Mechanism::ScramSha1Plus),
+                ]);
+            }
             capabilities.extend([
+                Capability::Auth(Mechanism::ScramSha256),
+                Capability::Auth(Mechanism::ScramSha1),
                 Capability::Auth(Mechanism::OAuthBearer),
                 Capability::Auth(Mechanism::Plain),
             ]);
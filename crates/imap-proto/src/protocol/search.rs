@@ -112,6 +112,12 @@ pub enum Filter {
     // RFC 8474 - ObjectID
     EmailId(String),
     ThreadId(String),
+
+    // Non-standard extensions
+    AttachmentName(String),
+    AttachmentType(String),
+    Encrypted,
+    Signed,
 }
 
 impl FilterItem for Filter {
@@ -124,7 +130,11 @@ impl FilterItem for Filter {
             | Filter::Subject(_)
             | Filter::Body(_)
             | Filter::Text(_)
-            | Filter::Header(_, _) => FilterType::Fts,
+            | Filter::Header(_, _)
+            | Filter::AttachmentName(_)
+            | Filter::AttachmentType(_)
+            | Filter::Encrypted
+            | Filter::Signed => FilterType::Fts,
             Filter::And => FilterType::And,
             Filter::Or => FilterType::Or,
             Filter::Not => FilterType::Not,
@@ -307,6 +307,8 @@ impl<T: SessionStream> Session<T> {
             | Command::Thread(_) => match state {
                 State::Selected { mailbox, .. } => {
                     if mailbox.is_select
+                        || (mailbox.id.is_recently_deleted()
+                            && matches!(request.command, Command::Move(_)))
                         || !matches!(
                             request.command,
                             Command::Store(_) | Command::Expunge(_) | Command::Move(_),
@@ -0,0 +1,159 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::AHashMap;
+use common::listener::SessionStream;
+use imap_proto::{ResponseCode, StatusResponse};
+use jmap::{email::undelete::HeldEmail, mailbox::RECENTLY_DELETED_ID};
+use jmap_proto::types::{collection::Collection, keyword::Keyword, property::Property};
+use store::{write::ValueClass, ValueKey};
+use utils::BlobHash;
+
+use super::{ImapId, Mailbox, MailboxId, MailboxState, SessionData};
+
+// Held emails never change once listed, so the UID validity of the
+// Recently Deleted mailbox is fixed.
+pub const RECENTLY_DELETED_UID_VALIDITY: u32 = 1;
+
+pub struct HeldMessage {
+    pub blob_hash: BlobHash,
+    pub expires: u64,
+    pub email: HeldEmail,
+}
+
+impl MailboxId {
+    pub fn is_recently_deleted(&self) -> bool {
+        self.mailbox_id == RECENTLY_DELETED_ID
+    }
+}
+
+impl<T: SessionStream> SessionData<T> {
+    pub async fn fetch_held_messages(
+        &self,
+        account_id: u32,
+    ) -> crate::op::Result<AHashMap<u32, HeldMessage>> {
+        Ok(self
+            .jmap
+            .list_held_emails(account_id)
+            .await?
+            .into_iter()
+            .map(|(blob_hash, expires, email)| {
+                (
+                    email.uid,
+                    HeldMessage {
+                        blob_hash,
+                        expires,
+                        email,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    pub async fn fetch_recently_deleted(
+        &self,
+        mailbox: &MailboxId,
+    ) -> crate::op::Result<MailboxState> {
+        // Obtain current state
+        let modseq = self.get_modseq(mailbox.account_id).await?;
+
+        // Held emails are identified by their UID
+        let mut uids = self
+            .fetch_held_messages(mailbox.account_id)
+            .await?
+            .into_keys()
+            .collect::<Vec<_>>();
+        uids.sort_unstable();
+
+        let uid_max = uids.last().copied().unwrap_or(0);
+        let uid_next = self.recently_deleted_uid_next(mailbox.account_id).await?;
+        let mut id_to_imap = AHashMap::with_capacity(uids.len());
+        let mut uid_to_id = AHashMap::with_capacity(uids.len());
+        for (seqnum, uid) in uids.into_iter().enumerate() {
+            id_to_imap.insert(
+                uid,
+                ImapId {
+                    uid,
+                    seqnum: seqnum as u32 + 1,
+                },
+            );
+            uid_to_id.insert(uid, uid);
+        }
+
+        Ok(MailboxState {
+            uid_next: std::cmp::max(uid_next, uid_max + 1),
+            uid_validity: RECENTLY_DELETED_UID_VALIDITY,
+            total_messages: id_to_imap.len(),
+            id_to_imap,
+            uid_to_id,
+            uid_max,
+            modseq,
+            next_state: None,
+        })
+    }
+
+    pub async fn recently_deleted_mailbox(
+        &self,
+        account_id: u32,
+    ) -> crate::op::Result<Option<Mailbox>> {
+        // The mailbox is only listed when deleted emails are held for the account
+        if self.jmap.undelete_retention(account_id).await?.is_none() {
+            return Ok(None);
+        }
+
+        let held_messages = self.fetch_held_messages(account_id).await?;
+        let seen = Keyword::Seen.to_string();
+        Ok(Some(Mailbox {
+            total_messages: Some(held_messages.len() as u32),
+            total_unseen: Some(
+                held_messages
+                    .values()
+                    .filter(|held| !held.email.metadata.keywords.contains(&seen))
+                    .count() as u32,
+            ),
+            total_deleted: Some(0),
+            uid_validity: Some(RECENTLY_DELETED_UID_VALIDITY),
+            uid_next: Some(self.recently_deleted_uid_next(account_id).await?),
+            is_subscribed: true,
+            size: Some(
+                held_messages
+                    .values()
+                    .map(|held| held.email.size)
+                    .sum::<u32>(),
+            ),
+            ..Default::default()
+        }))
+    }
+
+    async fn recently_deleted_uid_next(&self, account_id: u32) -> crate::op::Result<u32> {
+        self.jmap
+            .core
+            .storage
+            .data
+            .get_counter(ValueKey {
+                account_id,
+                collection: Collection::Mailbox.into(),
+                document_id: RECENTLY_DELETED_ID,
+                class: ValueClass::Property(Property::EmailIds.into()),
+            })
+            .await
+            .map(|uid| uid as u32 + 1)
+            .map_err(|err| {
+                tracing::error!(parent: &self.span,
+                    event = "error",
+                    context = "store",
+                    account_id = account_id,
+                    reason = ?err,
+                    "Failed to obtain uid next");
+                StatusResponse::database_failure()
+            })
+    }
+}
+
+pub fn recently_deleted_read_only() -> StatusResponse {
+    StatusResponse::no("Recently deleted messages can only be copied or moved.")
+        .with_code(ResponseCode::Cannot)
+}
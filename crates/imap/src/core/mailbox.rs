@@ -12,7 +12,7 @@ use directory::QueryBy;
 use imap_proto::{protocol::list::Attribute, StatusResponse};
 use jmap::{
     auth::{acl::EffectiveAcl, AccessToken},
-    mailbox::INBOX_ID,
+    mailbox::{INBOX_ID, RECENTLY_DELETED_ID},
 };
 use jmap_proto::{
     object::Object,
@@ -125,7 +125,10 @@ impl<T: SessionStream> SessionData<T> {
                     }
                 })
         {
-            return Ok(cached_account.as_ref().clone());
+            let mut account = cached_account.as_ref().clone();
+            self.add_recently_deleted_mailbox(&mut account, access_token)
+                .await?;
+            return Ok(account);
         }
 
         let mailbox_ids = if access_token.is_primary_id(account_id)
@@ -321,9 +324,36 @@ impl<T: SessionStream> SessionData<T> {
             .cache_account
             .insert(cached_account_id, Arc::new(account.clone()));
 
+        self.add_recently_deleted_mailbox(&mut account, access_token)
+            .await?;
+
         Ok(account)
     }
 
+    async fn add_recently_deleted_mailbox(
+        &self,
+        account: &mut Account,
+        access_token: &AccessToken,
+    ) -> crate::Result<()> {
+        // Retention is a directory setting, so the mailbox is not cached
+        if account.prefix.is_none() && access_token.is_primary_id(account.account_id) {
+            let mailbox_name = &self.jmap.core.imap.recently_deleted_folder;
+            if let Some(mailbox) = self
+                .recently_deleted_mailbox(account.account_id)
+                .await
+                .map_err(|_| {})?
+                .filter(|_| !account.mailbox_names.contains_key(mailbox_name))
+            {
+                account
+                    .mailbox_names
+                    .insert(mailbox_name.to_string(), RECENTLY_DELETED_ID);
+                account.mailbox_state.insert(RECENTLY_DELETED_ID, mailbox);
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn synchronize_mailboxes(
         &self,
         return_changes: bool,
@@ -20,7 +20,7 @@ use jmap_proto::{
 use store::write::assert::HashedValue;
 use utils::lru_cache::LruCached;
 
-use crate::core::ImapId;
+use crate::core::{deleted::RECENTLY_DELETED_UID_VALIDITY, ImapId};
 
 use super::{ImapUidToId, MailboxId, MailboxState, NextMailboxState, SelectedMailbox, SessionData};
 
@@ -28,6 +28,10 @@ pub(crate) const MAX_RETRIES: usize = 10;
 
 impl<T: SessionStream> SessionData<T> {
     pub async fn fetch_messages(&self, mailbox: &MailboxId) -> crate::op::Result<MailboxState> {
+        if mailbox.is_recently_deleted() {
+            return self.fetch_recently_deleted(mailbox).await;
+        }
+
         // Obtain message ids
         let message_ids = self
             .jmap
@@ -238,6 +242,10 @@ impl<T: SessionStream> SessionData<T> {
     }
 
     pub async fn get_uid_validity(&self, mailbox: &MailboxId) -> crate::op::Result<u32> {
+        if mailbox.is_recently_deleted() {
+            return Ok(RECENTLY_DELETED_UID_VALIDITY);
+        }
+
         self.jmap
             .get_property::<Object<Value>>(
                 mailbox.account_id,
@@ -11,7 +11,10 @@ use std::{
 };
 
 use ahash::AHashMap;
-use common::listener::{limiter::InFlight, ServerInstance, SessionStream};
+use common::{
+    auth::sasl::{ChannelBinding, ScramSession},
+    listener::{limiter::InFlight, ServerInstance, SessionStream},
+};
 use dashmap::DashMap;
 use imap_proto::{
     protocol::{list::Attribute, ProtocolVersion},
@@ -29,6 +32,7 @@ use tokio::{
 use utils::lru_cache::LruCache;
 
 pub mod client;
+pub mod deleted;
 pub mod mailbox;
 pub mod message;
 pub mod session;
@@ -70,6 +74,8 @@ pub struct Session<T: SessionStream> {
     pub version: ProtocolVersion,
     pub state: State<T>,
     pub is_tls: bool,
+    pub channel_binding: ChannelBinding,
+    pub sasl: Option<ScramSession>,
     pub is_condstore: bool,
     pub is_qresync: bool,
     pub stream_rx: ReadHalf<T>,
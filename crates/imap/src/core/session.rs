@@ -108,14 +108,17 @@ impl<T: SessionStream> Session<T> {
         let _ = session.stream.flush().await;
 
         // Split stream into read and write halves
-        let (stream_rx, stream_tx) = tokio::io::split(session.stream);
         let jmap = JMAP::from(manager.imap.jmap_instance);
+        let channel_binding = jmap.core.tls_channel_binding(&session.stream);
+        let (stream_rx, stream_tx) = tokio::io::split(session.stream);
 
         Ok(Session {
             receiver: Receiver::with_max_request_size(jmap.core.imap.max_request_size),
             version: ProtocolVersion::Rev1,
             state: State::NotAuthenticated { auth_failures: 0 },
             is_tls,
+            channel_binding,
+            sasl: None,
             is_condstore: false,
             is_qresync: false,
             jmap,
@@ -153,8 +156,9 @@ impl<T: SessionStream> Session<T> {
         };
 
         // Upgrade to TLS
-        let (stream_rx, stream_tx) =
-            tokio::io::split(self.instance.tls_accept(stream, &self.span).await?);
+        let stream = self.instance.tls_accept(stream, &self.span).await?;
+        let channel_binding = self.jmap.core.tls_channel_binding(&stream);
+        let (stream_rx, stream_tx) = tokio::io::split(stream);
         let stream_tx = Arc::new(tokio::sync::Mutex::new(stream_tx));
 
         Ok(Session {
@@ -165,6 +169,8 @@ impl<T: SessionStream> Session<T> {
             version: self.version,
             state: state.try_replace_stream_tx(stream_tx.clone()).unwrap(),
             is_tls: true,
+            channel_binding,
+            sasl: None,
             is_condstore: self.is_condstore,
             is_qresync: self.is_qresync,
             span: self.span,
@@ -35,7 +35,7 @@ use jmap_proto::{
 use store::write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder};
 use utils::map::bitmap::Bitmap;
 
-use crate::core::{MailboxId, Session, SessionData};
+use crate::core::{deleted::recently_deleted_read_only, MailboxId, Session, SessionData};
 
 impl<T: SessionStream> Session<T> {
     pub async fn handle_get_acl(&mut self, request: Request<Command>) -> crate::OpResult {
@@ -218,14 +218,15 @@ impl<T: SessionStream> Session<T> {
 
                 tokio::spawn(async move {
                     // Validate mailbox
-                    let (mailbox, values, _) = match data.get_acl_mailbox(&arguments, true).await {
-                        Ok(result) => result,
-                        Err(response) => {
-                            data.write_bytes(response.with_tag(arguments.tag).into_bytes())
-                                .await;
-                            return;
-                        }
-                    };
+                    let (mailbox, values, access_token) =
+                        match data.get_acl_mailbox(&arguments, true).await {
+                            Ok(result) => result,
+                            Err(response) => {
+                                data.write_bytes(response.with_tag(arguments.tag).into_bytes())
+                                    .await;
+                                return;
+                            }
+                        };
 
                     // Obtain principal id
                     let acl_account_id = match data
@@ -326,6 +327,22 @@ impl<T: SessionStream> Session<T> {
                         }
                     }
 
+                    // Keep a copy of the ACLs for share notifications
+                    let acl_changes = acl.clone();
+                    let acl_current = values
+                        .inner
+                        .properties
+                        .get(&Property::Acl)
+                        .and_then(|acl| acl.as_acl())
+                        .cloned()
+                        .unwrap_or_default();
+                    let mailbox_name = values
+                        .inner
+                        .properties
+                        .get(&Property::Name)
+                        .and_then(|name| name.as_string())
+                        .map(|name| name.to_string());
+
                     // Write changes
                     let mailbox_id = mailbox.mailbox_id;
                     let mut batch = BatchBuilder::new();
@@ -341,6 +358,17 @@ impl<T: SessionStream> Session<T> {
                     if !batch.is_empty() {
                         match data.jmap.write_batch(batch).await {
                             Ok(_) => {
+                                data.jmap
+                                    .notify_acl_changes(
+                                        access_token.primary_id(),
+                                        mailbox.account_id,
+                                        mailbox_id,
+                                        mailbox_name.as_deref(),
+                                        &acl_current,
+                                        &acl_changes,
+                                    )
+                                    .await;
+
                                 let mut changes = ChangeLogBuilder::new();
                                 changes.log_update(Collection::Mailbox, mailbox_id);
                                 match data.jmap.commit_changes(mailbox.account_id, changes).await {
@@ -441,6 +469,10 @@ impl<T: SessionStream> SessionData<T> {
         validate: bool,
     ) -> crate::op::Result<(MailboxId, HashedValue<Object<Value>>, Arc<AccessToken>)> {
         if let Some(mailbox) = self.get_mailbox_by_name(&arguments.mailbox_name) {
+            if mailbox.is_recently_deleted() {
+                return Err(recently_deleted_read_only());
+            }
+
             match (
                 self.jmap
                     .get_property::<HashedValue<Object<Value>>>(
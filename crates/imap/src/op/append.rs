@@ -12,7 +12,10 @@ use imap_proto::{
     Command, ResponseCode, StatusResponse,
 };
 
-use crate::core::{ImapUidToId, MailboxId, SelectedMailbox, Session, SessionData};
+use crate::core::{
+    deleted::recently_deleted_read_only, ImapUidToId, MailboxId, SelectedMailbox, Session,
+    SessionData,
+};
 use common::listener::SessionStream;
 use jmap::email::ingest::{IngestEmail, IngestSource};
 use jmap_proto::types::{acl::Acl, keyword::Keyword, state::StateChange, type_state::DataType};
@@ -36,6 +39,15 @@ impl<T: SessionStream> Session<T> {
                 // Obtain mailbox
                 let mailbox =
                     if let Some(mailbox) = data.get_mailbox_by_name(&arguments.mailbox_name) {
+                        if mailbox.is_recently_deleted() {
+                            return self
+                                .write_bytes(
+                                    recently_deleted_read_only()
+                                        .with_tag(arguments.tag)
+                                        .into_bytes(),
+                                )
+                                .await;
+                        }
                         mailbox
                     } else {
                         return self
@@ -5,13 +5,21 @@
  */
 
 use common::{
-    config::server::ServerProtocol, listener::SessionStream, AuthFailureReason, AuthResult,
+    auth::sasl::{SaslStep, ScramMechanism, ScramSession},
+    config::server::ServerProtocol,
+    listener::SessionStream,
+    AuthFailureReason, AuthResult,
 };
+use directory::core::policy::PasswordStatus;
 use imap_proto::{
-    protocol::{authenticate::Mechanism, capability::Capability},
+    protocol::{
+        authenticate::{Arguments, Mechanism},
+        capability::Capability,
+    },
     receiver::{self, Request},
     Command, ResponseCode, StatusResponse,
 };
+use jmap::auth::AccessToken;
 use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
 use std::sync::Arc;
@@ -64,6 +72,10 @@ impl<T: SessionStream> Session<T> {
                         self.write_bytes(b"+ \"\"\r\n".to_vec()).await
                     }
                 }
+                Mechanism::ScramSha1
+                | Mechanism::ScramSha1Plus
+                | Mechanism::ScramSha256
+                | Mechanism::ScramSha256Plus => self.handle_scram(args).await,
                 _ => {
                     self.write_bytes(
                         StatusResponse::no("Authentication mechanism not supported.")
@@ -78,11 +90,94 @@ impl<T: SessionStream> Session<T> {
         }
     }
 
-    pub async fn authenticate(
-        &mut self,
-        credentials: Credentials<String>,
-        tag: String,
-    ) -> crate::Result<()> {
+    async fn handle_scram(&mut self, mut args: Arguments) -> crate::OpResult {
+        let mut sasl = if let Some(sasl) = self.sasl.take() {
+            sasl
+        } else {
+            let mechanism = match args.mechanism {
+                Mechanism::ScramSha1 => ScramMechanism::parse("SCRAM-SHA-1"),
+                Mechanism::ScramSha1Plus => ScramMechanism::parse("SCRAM-SHA-1-PLUS"),
+                Mechanism::ScramSha256 => ScramMechanism::parse("SCRAM-SHA-256"),
+                _ => ScramMechanism::parse("SCRAM-SHA-256-PLUS"),
+            }
+            .filter(|mechanism| !mechanism.channel_binding || self.channel_binding.is_available());
+            let mechanism = if let Some(mechanism) = mechanism {
+                mechanism
+            } else {
+                return self
+                    .write_bytes(
+                        StatusResponse::no("Channel binding is not available.")
+                            .with_tag(args.tag)
+                            .with_code(ResponseCode::Cannot)
+                            .into_bytes(),
+                    )
+                    .await;
+            };
+            self.is_auth_allowed().await?;
+
+            let sasl = ScramSession::new(mechanism, self.channel_binding.clone(), self.is_tls);
+            if args.params.is_empty() {
+                // Wait for the client-first-message
+                self.sasl = Some(sasl);
+                return self.sasl_challenge(args, "").await;
+            }
+            sasl
+        };
+
+        let message = match args.params.pop() {
+            Some(param) => match base64_decode(param.as_bytes()) {
+                Some(message) => message,
+                None => {
+                    return self
+                        .write_bytes(
+                            StatusResponse::no("Failed to decode challenge.")
+                                .with_tag(args.tag)
+                                .with_code(ResponseCode::Parse)
+                                .into_bytes(),
+                        )
+                        .await;
+                }
+            },
+            None => vec![],
+        };
+
+        match self
+            .jmap
+            .authenticate_scram(&mut sasl, &message, self.remote_addr, ServerProtocol::Imap)
+            .await
+        {
+            SaslStep::Challenge(challenge) => {
+                self.sasl = Some(sasl);
+                self.sasl_challenge(args, &challenge).await
+            }
+            SaslStep::Success(access_token) => {
+                self.complete_authentication(Some(access_token), false, args.tag)
+                    .await
+            }
+            SaslStep::Failure(AuthFailureReason::Banned) => Err(()),
+            SaslStep::Failure(AuthFailureReason::PasswordExpired) => {
+                self.password_expired(args.tag).await
+            }
+            SaslStep::Failure(AuthFailureReason::AccountLocked) => {
+                self.account_locked(args.tag).await
+            }
+            SaslStep::Failure(_) => self.complete_authentication(None, false, args.tag).await,
+        }
+    }
+
+    async fn sasl_challenge(&mut self, args: Arguments, challenge: &str) -> crate::OpResult {
+        let response = format!("+ {challenge}\r\n").into_bytes();
+
+        self.receiver.request = receiver::Request {
+            tag: args.tag,
+            command: Command::Authenticate,
+            tokens: vec![receiver::Token::Argument(args.mechanism.into_bytes())],
+        };
+        self.receiver.state = receiver::State::Argument { last_ch: b' ' };
+        self.write_bytes(response).await
+    }
+
+    async fn is_auth_allowed(&self) -> crate::Result<()> {
         // Throttle authentication requests
         if self
             .jmap
@@ -102,6 +197,17 @@ impl<T: SessionStream> Session<T> {
             return Err(());
         }
 
+        Ok(())
+    }
+
+    pub async fn authenticate(
+        &mut self,
+        credentials: Credentials<String>,
+        tag: String,
+    ) -> crate::Result<()> {
+        // Throttle authentication requests
+        self.is_auth_allowed().await?;
+
         // Authenticate
         let mut is_totp_error = false;
         let access_token = match credentials {
@@ -119,29 +225,64 @@ impl<T: SessionStream> Session<T> {
                         is_totp_error = true;
                         None
                     }
+                    AuthResult::Failure(AuthFailureReason::PasswordExpired) => {
+                        return self.password_expired(tag).await;
+                    }
+                    AuthResult::Failure(AuthFailureReason::AccountLocked) => {
+                        return self.account_locked(tag).await;
+                    }
                     AuthResult::Failure(AuthFailureReason::Banned) => return Err(()),
                 }
             }
             Credentials::OAuthBearer { token } => {
                 match self
                     .jmap
-                    .validate_access_token("access_token", &token)
+                    .authenticate_bearer(&token, self.remote_addr, ServerProtocol::Imap)
                     .await
                 {
-                    Ok((account_id, _, _)) => self.jmap.get_access_token(account_id).await,
-                    Err(err) => {
-                        tracing::debug!(
-                            parent: &self.span,
-                            context = "authenticate",
-                            err = err,
-                            "Failed to validate access token."
-                        );
-                        None
-                    }
+                    AuthResult::Success(token) => Some(token),
+                    AuthResult::Failure(
+                        AuthFailureReason::InvalidCredentials
+                        | AuthFailureReason::MissingTotp
+                        | AuthFailureReason::PasswordExpired
+                        | AuthFailureReason::AccountLocked
+                        | AuthFailureReason::InternalError(_),
+                    ) => None,
+                    AuthResult::Failure(AuthFailureReason::Banned) => return Err(()),
                 }
             }
         };
 
+        self.complete_authentication(access_token, is_totp_error, tag)
+            .await
+    }
+
+    async fn password_expired(&mut self, tag: String) -> crate::Result<()> {
+        self.write_bytes(
+            StatusResponse::no("Password expired, please contact your administrator.")
+                .with_tag(tag)
+                .with_code(ResponseCode::Expired)
+                .into_bytes(),
+        )
+        .await
+    }
+
+    async fn account_locked(&mut self, tag: String) -> crate::Result<()> {
+        self.write_bytes(
+            StatusResponse::no("Account temporarily locked, try again later.")
+                .with_tag(tag)
+                .with_code(ResponseCode::Unavailable)
+                .into_bytes(),
+        )
+        .await
+    }
+
+    async fn complete_authentication(
+        &mut self,
+        access_token: Option<AccessToken>,
+        is_totp_error: bool,
+        tag: String,
+    ) -> crate::Result<()> {
         if let Some(access_token) = access_token {
             // Enforce concurrency limits
             let in_flight = match self
@@ -171,6 +312,22 @@ impl<T: SessionStream> Session<T> {
             self.state = State::Authenticated {
                 data: Arc::new(SessionData::new(self, &access_token, in_flight).await?),
             };
+
+            // Warn users whose password expired and is within the grace period
+            if let PasswordStatus::Grace { .. } = self
+                .jmap
+                .core
+                .jmap
+                .password_policy
+                .status(access_token.password_changed_at)
+            {
+                self.write_bytes(
+                    StatusResponse::ok("Your password has expired and must be changed.")
+                        .with_code(ResponseCode::Alert)
+                        .into_bytes(),
+                )
+                .await?;
+            }
             self.write_bytes(
                 StatusResponse::ok("Authentication successful")
                     .with_code(ResponseCode::Capability {
@@ -11,19 +11,24 @@ use imap_proto::{
     StatusResponse,
 };
 
-use crate::core::{MailboxId, SelectedMailbox, Session, SessionData};
+use crate::core::{
+    deleted::recently_deleted_read_only, ImapId, MailboxId, SelectedMailbox, Session, SessionData,
+};
+use ahash::AHashMap;
 use common::listener::SessionStream;
 use jmap::{email::set::TagManager, mailbox::UidMailbox};
 use jmap_proto::{
     error::{method::MethodError, set::SetErrorType},
+    method::undelete::UndeleteEmailRequest,
     types::{
-        acl::Acl, collection::Collection, id::Id, property::Property, state::StateChange,
-        type_state::DataType,
+        acl::Acl, blob::BlobId, collection::Collection, id::Id, property::Property,
+        state::StateChange, type_state::DataType,
     },
 };
 use store::{
     roaring::RoaringBitmap,
     write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder, F_VALUE},
+    BlobClass,
 };
 
 impl<T: SessionStream> Session<T> {
@@ -77,6 +82,17 @@ impl<T: SessionStream> Session<T> {
                             .await;
                     }
 
+                    // Messages can only be restored from the Recently Deleted mailbox
+                    if dest_mailbox.is_recently_deleted() {
+                        return data
+                            .write_bytes(
+                                recently_deleted_read_only()
+                                    .with_tag(arguments.tag)
+                                    .into_bytes(),
+                            )
+                            .await;
+                    }
+
                     if let Err(err) = data
                         .copy_move(
                             arguments,
@@ -128,6 +144,20 @@ impl<T: SessionStream> SessionData<T> {
             }
         };
 
+        // Recently deleted messages are restored rather than copied
+        if src_mailbox.id.is_recently_deleted() {
+            return self
+                .restore_held(
+                    arguments,
+                    src_mailbox,
+                    dest_mailbox,
+                    ids,
+                    is_move,
+                    is_qresync,
+                )
+                .await;
+        }
+
         // Verify that the user can delete messages from the source mailbox.
         if is_move
             && !self
@@ -418,6 +448,154 @@ impl<T: SessionStream> SessionData<T> {
         Ok(())
     }
 
+    async fn restore_held(
+        &self,
+        arguments: Arguments,
+        src_mailbox: Arc<SelectedMailbox>,
+        dest_mailbox: MailboxId,
+        ids: AHashMap<u32, ImapId>,
+        is_move: bool,
+        is_qresync: bool,
+    ) -> Result<(), StatusResponse> {
+        // Held messages can only be restored to the same account
+        let account_id = src_mailbox.id.account_id;
+        if dest_mailbox.account_id != account_id {
+            return Err(StatusResponse::no(
+                "Deleted messages can only be restored to a mailbox in the same account.",
+            )
+            .with_tag(arguments.tag)
+            .with_code(ResponseCode::Cannot));
+        }
+
+        // Obtain the held messages to restore
+        let held_messages = self
+            .fetch_held_messages(account_id)
+            .await
+            .map_err(|r| r.with_tag(&arguments.tag))?;
+        let mut blob_ids = Vec::with_capacity(ids.len());
+        for (uid, held) in held_messages {
+            if ids.contains_key(&uid) {
+                blob_ids.push((
+                    BlobId::new(
+                        held.blob_hash,
+                        BlobClass::Reserved {
+                            account_id,
+                            expires: held.expires,
+                        },
+                    ),
+                    uid,
+                ));
+            }
+        }
+        if blob_ids.is_empty() {
+            return Err(StatusResponse::no("No messages were found.").with_tag(arguments.tag));
+        }
+
+        // Restore messages
+        let access_token = self
+            .get_access_token()
+            .await
+            .map_err(|r| r.with_tag(&arguments.tag))?;
+        let undelete = self
+            .jmap
+            .email_undelete(
+                UndeleteEmailRequest {
+                    account_id: Id::from(account_id),
+                    blob_ids: blob_ids
+                        .iter()
+                        .map(|(blob_id, _)| blob_id.clone())
+                        .collect(),
+                    mailbox_ids: vec![Id::from(dest_mailbox.mailbox_id)],
+                },
+                &access_token,
+            )
+            .await
+            .map_err(|err| StatusResponse::from(err).with_tag(&arguments.tag))?;
+        if let Some(state_change) = undelete.state_change {
+            self.jmap.broadcast_state_change(state_change).await;
+        }
+
+        // Map restored messages to their UIDs in the destination folder
+        let mut src_uids = Vec::with_capacity(undelete.created.len());
+        let mut dest_uids = Vec::with_capacity(undelete.created.len());
+        for (blob_id, email) in undelete.created.iter() {
+            let (src_uid, document_id) = match (
+                blob_ids.iter().find(|(id, _)| id == blob_id),
+                email.get(&Property::Id).as_id(),
+            ) {
+                (Some((_, src_uid)), Some(id)) => (*src_uid, id.document_id()),
+                _ => continue,
+            };
+            if let Some(dest_uid) = self
+                .jmap
+                .get_property::<Vec<UidMailbox>>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::MailboxIds,
+                )
+                .await
+                .map_err(|_| StatusResponse::database_failure().with_tag(&arguments.tag))?
+                .and_then(|mailboxes| {
+                    mailboxes
+                        .into_iter()
+                        .find(|m| m.mailbox_id == dest_mailbox.mailbox_id)
+                })
+            {
+                src_uids.push(src_uid);
+                dest_uids.push(dest_uid.uid);
+            }
+        }
+        if src_uids.is_empty() {
+            return Err(StatusResponse::no("No messages were restored.").with_tag(arguments.tag));
+        }
+        src_uids.sort_unstable();
+        dest_uids.sort_unstable();
+
+        // Prepare response
+        let uid_validity = self
+            .get_uid_validity(&dest_mailbox)
+            .await
+            .map_err(|r| r.with_tag(&arguments.tag))?;
+        let response = StatusResponse::completed(if is_move {
+            Command::Move(true)
+        } else {
+            Command::Copy(true)
+        })
+        .with_tag(arguments.tag);
+        let response = if is_move {
+            self.write_bytes(
+                StatusResponse::ok("Copied UIDs")
+                    .with_code(ResponseCode::CopyUid {
+                        uid_validity,
+                        src_uids,
+                        dest_uids,
+                    })
+                    .into_bytes(),
+            )
+            .await;
+
+            // Restored messages are no longer held
+            self.write_mailbox_changes(&src_mailbox, is_qresync)
+                .await
+                .map_err(|r| r.with_tag(response.tag.as_ref().unwrap()))?;
+
+            response.into_bytes()
+        } else {
+            response
+                .with_code(ResponseCode::CopyUid {
+                    uid_validity,
+                    src_uids,
+                    dest_uids,
+                })
+                .into_bytes()
+        };
+
+        self.write_bytes(response).await;
+
+        Ok(())
+    }
+
     pub async fn get_mailbox_tags(
         &self,
         account_id: u32,
@@ -4,14 +4,14 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use crate::core::{Account, Mailbox, Session, SessionData};
+use crate::core::{deleted::recently_deleted_read_only, Account, Mailbox, Session, SessionData};
 use common::listener::SessionStream;
 use imap_proto::{
     protocol::{create::Arguments, list::Attribute},
     receiver::Request,
     Command, ResponseCode, StatusResponse,
 };
-use jmap::mailbox::set::SCHEMA;
+use jmap::mailbox::{set::SCHEMA, RECENTLY_DELETED_ID};
 use jmap_proto::{
     object::{index::ObjectIndexBuilder, Object},
     types::{
@@ -332,7 +332,9 @@ impl<T: SessionStream> SessionData<T> {
 
         // Validate ACLs
         if let Some(parent_mailbox_id) = parent_mailbox_id {
-            if !self
+            if parent_mailbox_id == RECENTLY_DELETED_ID {
+                return Err(recently_deleted_read_only());
+            } else if !self
                 .check_mailbox_acl(account_id, parent_mailbox_id, Acl::CreateChild)
                 .await?
             {
@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use crate::core::{Session, SessionData};
+use crate::core::{deleted::recently_deleted_read_only, Session, SessionData};
 use common::listener::SessionStream;
 use imap_proto::{protocol::delete::Arguments, receiver::Request, Command, StatusResponse};
 use jmap_proto::types::{state::StateChange, type_state::DataType};
@@ -46,6 +46,9 @@ impl<T: SessionStream> SessionData<T> {
         // Validate mailbox
         let (account_id, mailbox_id) =
             if let Some(mailbox) = self.get_mailbox_by_name(&arguments.mailbox_name) {
+                if mailbox.is_recently_deleted() {
+                    return recently_deleted_read_only().with_tag(arguments.tag);
+                }
                 (mailbox.account_id, mailbox.mailbox_id)
             } else {
                 return StatusResponse::no("Mailbox does not exist.").with_tag(arguments.tag);
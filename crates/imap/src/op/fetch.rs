@@ -22,7 +22,7 @@ use imap_proto::{
     receiver::Request,
     Command, ResponseCode, StatusResponse,
 };
-use jmap::email::metadata::MessageMetadata;
+use jmap::email::{index::PREVIEW_LENGTH, metadata::MessageMetadata};
 use jmap_proto::{
     error::method::MethodError,
     types::{
@@ -30,7 +30,7 @@ use jmap_proto::{
         state::StateChange, type_state::DataType,
     },
 };
-use mail_parser::{Address, GetHeader, HeaderName, Message, PartType};
+use mail_parser::{Address, GetHeader, HeaderName, Message, MessageParser, PartType};
 use store::{
     query::log::{Change, Query},
     write::{assert::HashedValue, BatchBuilder, Bincode, F_BITMAP, F_VALUE},
@@ -120,8 +120,18 @@ impl<T: SessionStream> SessionData<T> {
             }
         };
 
+        // Recently deleted messages are not part of the changelog
+        let held_messages = if mailbox.id.is_recently_deleted() {
+            match self.fetch_held_messages(account_id).await {
+                Ok(held_messages) => Some(held_messages),
+                Err(response) => return response.with_tag(arguments.tag),
+            }
+        } else {
+            None
+        };
+
         // Convert state to modseq
-        if let Some(changed_since) = arguments.changed_since {
+        if let Some(changed_since) = arguments.changed_since.filter(|_| held_messages.is_none()) {
             // Obtain changes since the modseq.
             let changelog = match self
                 .jmap
@@ -263,7 +273,61 @@ impl<T: SessionStream> SessionData<T> {
         ids.sort_unstable_by_key(|(seqnum, _, _)| *seqnum);
         for (seqnum, uid, id) in ids {
             // Obtain attributes and keywords
-            let (email, keywords) = if let (Ok(Some(email)), Ok(Some(keywords))) = (
+            let mut held_raw_message = None;
+            let (email, keywords) = if let Some(held_messages) = &held_messages {
+                let held = if let Some(held) = held_messages.get(&id) {
+                    held
+                } else {
+                    continue;
+                };
+                let raw_message = match self.jmap.get_blob(&held.blob_hash, 0..usize::MAX).await {
+                    Ok(Some(raw_message)) => raw_message,
+                    Ok(None) => {
+                        tracing::warn!(event = "not-found",
+                        account_id = account_id,
+                        uid = uid,
+                        blob_id = ?held.blob_hash,
+                        "Held blob not found");
+                        continue;
+                    }
+                    Err(_) => {
+                        return StatusResponse::database_failure().with_tag(arguments.tag);
+                    }
+                };
+                let message = if let Some(message) = MessageParser::new().parse(&raw_message) {
+                    message.into_owned()
+                } else {
+                    continue;
+                };
+                let root_part = message.root_part();
+                let email = MessageMetadata {
+                    preview: message
+                        .body_preview(PREVIEW_LENGTH)
+                        .unwrap_or_default()
+                        .into_owned(),
+                    raw_headers: raw_message
+                        .get(root_part.offset_header..root_part.offset_body)
+                        .unwrap_or_default()
+                        .to_vec(),
+                    size: held.email.size as usize,
+                    received_at: held.email.received_at,
+                    has_attachments: false,
+                    blob_hash: held.blob_hash.clone(),
+                    contents: message.into(),
+                };
+                let keywords = HashedValue {
+                    hash: 0,
+                    inner: held
+                        .email
+                        .metadata
+                        .keywords
+                        .iter()
+                        .map(|keyword| Keyword::from(keyword.clone()))
+                        .collect::<Vec<_>>(),
+                };
+                held_raw_message = Some(raw_message);
+                (email, keywords)
+            } else if let (Ok(Some(email)), Ok(Some(keywords))) = (
                 self.jmap
                     .get_property::<Bincode<MessageMetadata>>(
                         account_id,
@@ -293,7 +357,9 @@ impl<T: SessionStream> SessionData<T> {
             };
 
             // Fetch and parse blob
-            let raw_message = if needs_blobs {
+            let raw_message = if let Some(raw_message) = held_raw_message {
+                raw_message
+            } else if needs_blobs {
                 // Retrieve raw message if needed
                 match self.jmap.get_blob(&email.blob_hash, 0..usize::MAX).await {
                     Ok(Some(raw_message)) => raw_message,
@@ -319,7 +385,7 @@ impl<T: SessionStream> SessionData<T> {
             let mut items = Vec::with_capacity(arguments.attributes.len());
             let set_seen_flag =
                 set_seen_flags && !keywords.inner.iter().any(|k| k == &Keyword::Seen);
-            let thread_id = if needs_thread_id || set_seen_flag {
+            let thread_id = if held_messages.is_none() && (needs_thread_id || set_seen_flag) {
                 if let Ok(Some(thread_id)) = self
                     .jmap
                     .get_property::<u32>(account_id, Collection::Email, id, Property::ThreadId)
@@ -449,6 +515,8 @@ impl<T: SessionStream> SessionData<T> {
                             });
                         }
                     }
+                    Attribute::ModSeq | Attribute::EmailId | Attribute::ThreadId
+                        if held_messages.is_some() => {}
                     Attribute::ModSeq => {
                         if let Ok(Some(modseq)) = self
                             .jmap
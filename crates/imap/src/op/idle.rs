@@ -205,6 +205,11 @@ impl<T: SessionStream> SessionData<T> {
                     }
                 }
 
+                // Held messages never change
+                if mailbox.id.is_recently_deleted() {
+                    return;
+                }
+
                 // Obtain changed messages
                 let changed_ids = match self
                     .jmap
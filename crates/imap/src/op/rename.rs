@@ -6,12 +6,15 @@
 
 use std::collections::BTreeMap;
 
-use crate::core::{Session, SessionData};
+use crate::core::{deleted::recently_deleted_read_only, Session, SessionData};
 use common::listener::SessionStream;
 use imap_proto::{
     protocol::rename::Arguments, receiver::Request, Command, ResponseCode, StatusResponse,
 };
-use jmap::{auth::acl::EffectiveAcl, mailbox::set::SCHEMA};
+use jmap::{
+    auth::acl::EffectiveAcl,
+    mailbox::{set::SCHEMA, RECENTLY_DELETED_ID},
+};
 use jmap_proto::{
     error::method::MethodError,
     object::{index::ObjectIndexBuilder, Object},
@@ -75,6 +78,9 @@ impl<T: SessionStream> SessionData<T> {
                 }
             }
             if let Some(mailbox_id) = mailbox_id {
+                if mailbox_id == RECENTLY_DELETED_ID {
+                    return recently_deleted_read_only().with_tag(arguments.tag);
+                }
                 mailbox_id
             } else {
                 return StatusResponse::no(format!(
@@ -13,8 +13,9 @@ use imap_proto::{
         Sequence,
     },
     receiver::Request,
-    Command, StatusResponse,
+    Command, ResponseCode, StatusResponse,
 };
+use jmap::email::index::{KEYWORD_ENCRYPTED, KEYWORD_SIGNED};
 use jmap_proto::types::{collection::Collection, id::Id, keyword::Keyword, property::Property};
 use mail_parser::HeaderName;
 use nlp::language::Language;
@@ -236,6 +237,14 @@ impl<T: SessionStream> SessionData<T> {
         mailbox: &SelectedMailbox,
         prev_saved_search: &Option<Option<Arc<Vec<ImapId>>>>,
     ) -> Result<(ResultSet, bool), StatusResponse> {
+        // Held messages are not indexed
+        if mailbox.id.is_recently_deleted() {
+            return Err(StatusResponse::no(
+                "Searching is not supported in the Recently Deleted mailbox.",
+            )
+            .with_code(ResponseCode::Cannot));
+        }
+
         // Obtain message ids
         let mut filters = Vec::with_capacity(imap_filter.len() + 1);
         let message_ids = self
@@ -376,6 +385,29 @@ impl<T: SessionStream> SessionData<T> {
                                     Language::None,
                                 ));
                             }
+                            search::Filter::AttachmentName(text) => {
+                                fts_filters.push(FtsFilter::has_text(
+                                    Field::AttachmentName,
+                                    text,
+                                    Language::None,
+                                ));
+                            }
+                            search::Filter::AttachmentType(content_type) => {
+                                fts_filters.push(FtsFilter::has_keyword(
+                                    Field::AttachmentType,
+                                    content_type.to_lowercase(),
+                                ));
+                            }
+                            search::Filter::Encrypted => {
+                                fts_filters.push(FtsFilter::has_keyword(
+                                    Field::Keyword,
+                                    KEYWORD_ENCRYPTED,
+                                ));
+                            }
+                            search::Filter::Signed => {
+                                fts_filters
+                                    .push(FtsFilter::has_keyword(Field::Keyword, KEYWORD_SIGNED));
+                            }
                             search::Filter::And => {
                                 fts_filters.push(FtsFilter::And);
                             }
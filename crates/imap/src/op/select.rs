@@ -26,7 +26,7 @@ use super::ToModSeq;
 
 impl<T: SessionStream> Session<T> {
     pub async fn handle_select(&mut self, request: Request<Command>) -> crate::OpResult {
-        let is_select = request.command == Command::Select;
+        let mut is_select = request.command == Command::Select;
         let command = request.command;
         match request.parse_select(self.version) {
             Ok(arguments) => {
@@ -40,6 +40,10 @@ impl<T: SessionStream> Session<T> {
                 }
 
                 if let Some(mailbox) = data.get_mailbox_by_name(&arguments.mailbox_name) {
+                    // Recently deleted messages are always read-only
+                    let is_recently_deleted = mailbox.is_recently_deleted();
+                    is_select &= !is_recently_deleted;
+
                     // Try obtaining the mailbox from the cache
                     let state = {
                         let modseq = match data.get_modseq(mailbox.account_id).await {
@@ -55,7 +59,9 @@ impl<T: SessionStream> Session<T> {
                                 .cache_mailbox
                                 .get(&mailbox)
                                 .and_then(|cached_state| {
-                                    if cached_state.modseq.unwrap_or(0) >= modseq.unwrap_or(0) {
+                                    if !is_recently_deleted
+                                        && cached_state.modseq.unwrap_or(0) >= modseq.unwrap_or(0)
+                                    {
                                         Some(cached_state)
                                     } else {
                                         None
@@ -66,9 +72,12 @@ impl<T: SessionStream> Session<T> {
                         } else {
                             match data.fetch_messages(&mailbox).await {
                                 Ok(new_state) => {
-                                    let new_state = Arc::new(new_state);
-                                    self.imap.cache_mailbox.insert(mailbox, new_state.clone());
-                                    new_state.as_ref().clone()
+                                    if !is_recently_deleted {
+                                        self.imap
+                                            .cache_mailbox
+                                            .insert(mailbox, Arc::new(new_state.clone()));
+                                    }
+                                    new_state
                                 }
                                 Err(mut response) => {
                                     response.tag = arguments.tag.into();
@@ -196,7 +196,28 @@ impl<T: SessionStream> SessionData<T> {
             }
         }
 
-        if !items_update.is_empty() {
+        if !items_update.is_empty() && mailbox.is_recently_deleted() {
+            // Recently deleted messages are obtained from the held emails
+            let held = self
+                .recently_deleted_mailbox(mailbox.account_id)
+                .await?
+                .unwrap_or_default();
+            for item in items_update {
+                let result = match item {
+                    Status::Messages => held.total_messages,
+                    Status::UidNext => held.uid_next,
+                    Status::UidValidity => held.uid_validity,
+                    Status::Unseen => held.total_unseen,
+                    Status::Deleted => held.total_deleted,
+                    Status::Size => held.size,
+                    Status::Recent => None,
+                    Status::HighestModSeq | Status::MailboxId => {
+                        unreachable!()
+                    }
+                };
+                items_response.push((item, StatusItemType::Number(result.unwrap_or(0) as u64)));
+            }
+        } else if !items_update.is_empty() {
             // Retrieve latest values
             let mut values_update = Vec::with_capacity(items_update.len());
             let mailbox_message_ids = self
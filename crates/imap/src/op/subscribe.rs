@@ -4,10 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use crate::core::{Session, SessionData};
+use crate::core::{deleted::recently_deleted_read_only, Session, SessionData};
 use common::listener::SessionStream;
 use imap_proto::{receiver::Request, Command, ResponseCode, StatusResponse};
-use jmap::mailbox::set::{MailboxSubscribe, SCHEMA};
+use jmap::mailbox::{
+    set::{MailboxSubscribe, SCHEMA},
+    RECENTLY_DELETED_ID,
+};
 use jmap_proto::{
     error::method::MethodError,
     object::{index::ObjectIndexBuilder, Object},
@@ -80,6 +83,9 @@ impl<T: SessionStream> SessionData<T> {
                 break;
             }
         }
+        if mailbox_id == RECENTLY_DELETED_ID {
+            return recently_deleted_read_only().with_tag(tag);
+        }
 
         // Obtain mailbox
         let mailbox = if let Ok(Some(mailbox)) = self
@@ -54,6 +54,8 @@ pub enum SetErrorType {
     MailboxHasChild,
     #[serde(rename = "mailboxHasEmail")]
     MailboxHasEmail,
+    #[serde(rename = "calendarHasEvent")]
+    CalendarHasEvent,
     #[serde(rename = "blobNotFound")]
     BlobNotFound,
     #[serde(rename = "tooManyKeywords")]
@@ -82,6 +84,8 @@ pub enum SetErrorType {
     InvalidScript,
     #[serde(rename = "scriptIsActive")]
     ScriptIsActive,
+    #[serde(rename = "mdnAlreadySent")]
+    MdnAlreadySent,
 }
 
 impl SetErrorType {
@@ -99,6 +103,7 @@ impl SetErrorType {
             SetErrorType::BlobNotFound => "blobNotFound",
             SetErrorType::MailboxHasChild => "mailboxHasChild",
             SetErrorType::MailboxHasEmail => "mailboxHasEmail",
+            SetErrorType::CalendarHasEvent => "calendarHasEvent",
             SetErrorType::TooManyKeywords => "tooManyKeywords",
             SetErrorType::TooManyMailboxes => "tooManyMailboxes",
             SetErrorType::ForbiddenFrom => "forbiddenFrom",
@@ -112,6 +117,7 @@ impl SetErrorType {
             SetErrorType::AlreadyExists => "alreadyExists",
             SetErrorType::InvalidScript => "invalidScript",
             SetErrorType::ScriptIsActive => "scriptIsActive",
+            SetErrorType::MdnAlreadySent => "mdnAlreadySent",
         }
     }
 }
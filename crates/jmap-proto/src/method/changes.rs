@@ -52,6 +52,11 @@ pub enum RequestArguments {
     Identity,
     EmailSubmission,
     Quota,
+    ShareNotification,
+    Calendar,
+    CalendarEvent,
+    CalendarEventNotification,
+    Template,
 }
 
 impl JsonObjectParser for ChangesRequest {
@@ -67,6 +72,13 @@ impl JsonObjectParser for ChangesRequest {
                 MethodObject::Identity => RequestArguments::Identity,
                 MethodObject::EmailSubmission => RequestArguments::EmailSubmission,
                 MethodObject::Quota => RequestArguments::Quota,
+                MethodObject::ShareNotification => RequestArguments::ShareNotification,
+                MethodObject::Calendar => RequestArguments::Calendar,
+                MethodObject::CalendarEvent => RequestArguments::CalendarEvent,
+                MethodObject::CalendarEventNotification => {
+                    RequestArguments::CalendarEventNotification
+                }
+                MethodObject::Template => RequestArguments::Template,
                 _ => {
                     return Err(Error::Method(MethodError::UnknownMethod(format!(
                         "{}/changes",
@@ -37,6 +37,11 @@ pub enum RequestArguments {
     Principal,
     Quota,
     Blob(blob::GetArguments),
+    ShareNotification,
+    Calendar,
+    CalendarEvent,
+    CalendarEventNotification,
+    Template,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -72,6 +77,13 @@ impl JsonObjectParser for GetRequest<RequestArguments> {
                 MethodObject::Principal => RequestArguments::Principal,
                 MethodObject::Blob => RequestArguments::Blob(Default::default()),
                 MethodObject::Quota => RequestArguments::Quota,
+                MethodObject::ShareNotification => RequestArguments::ShareNotification,
+                MethodObject::Calendar => RequestArguments::Calendar,
+                MethodObject::CalendarEvent => RequestArguments::CalendarEvent,
+                MethodObject::CalendarEventNotification => {
+                    RequestArguments::CalendarEventNotification
+                }
+                MethodObject::Template => RequestArguments::Template,
                 _ => {
                     return Err(Error::Method(MethodError::UnknownMethod(format!(
                         "{}/get",
@@ -11,11 +11,14 @@ pub mod copy;
 pub mod get;
 pub mod import;
 pub mod lookup;
+pub mod mdn;
 pub mod parse;
 pub mod query;
 pub mod query_changes;
 pub mod search_snippet;
 pub mod set;
+pub mod thread;
+pub mod undelete;
 pub mod upload;
 pub mod validate;
 
@@ -82,6 +82,10 @@ pub enum Filter {
     HasKeyword(Keyword),
     NotKeyword(Keyword),
     HasAttachment(bool),
+    AttachmentName(String),
+    AttachmentType(String),
+    IsEncrypted(bool),
+    IsSigned(bool),
     From(String),
     To(String),
     Cc(String),
@@ -100,6 +104,12 @@ pub enum Filter {
     IsActive(bool),
     Scope(String),
     ResourceType(String),
+    ObjectType(String),
+    ObjectAccountId(Id),
+    InCalendar(Id),
+    Uid(String),
+    Title(String),
+    CalendarEventIds(Vec<Id>),
     _T(String),
 
     And,
@@ -137,6 +147,8 @@ pub enum SortProperty {
     AllInThreadHaveKeyword,
     SomeInThreadHaveKeyword,
     Used,
+    Created,
+    Start,
     _T(String),
 }
 
@@ -148,6 +160,11 @@ pub enum RequestArguments {
     SieveScript,
     Principal,
     Quota,
+    ShareNotification,
+    Calendar,
+    CalendarEvent,
+    CalendarEventNotification,
+    Template,
 }
 
 impl JsonObjectParser for QueryRequest<RequestArguments> {
@@ -163,6 +180,13 @@ impl JsonObjectParser for QueryRequest<RequestArguments> {
                 MethodObject::SieveScript => RequestArguments::SieveScript,
                 MethodObject::Principal => RequestArguments::Principal,
                 MethodObject::Quota => RequestArguments::Quota,
+                MethodObject::ShareNotification => RequestArguments::ShareNotification,
+                MethodObject::Calendar => RequestArguments::Calendar,
+                MethodObject::CalendarEvent => RequestArguments::CalendarEvent,
+                MethodObject::CalendarEventNotification => {
+                    RequestArguments::CalendarEventNotification
+                }
+                MethodObject::Template => RequestArguments::Template,
                 _ => {
                     return Err(Error::Method(MethodError::UnknownMethod(format!(
                         "{}/query",
@@ -368,6 +392,22 @@ pub fn parse_filter(parser: &mut Parser) -> crate::parser::Result<Vec<Filter>> {
                                 .next_token::<String>()?
                                 .unwrap_bool("hasAttachment")?,
                         ),
+                        (0x656d_614e_746e_656d_6863_6174_7461, _) => Filter::AttachmentName(
+                            parser
+                                .next_token::<String>()?
+                                .unwrap_string("attachmentName")?,
+                        ),
+                        (0x6570_7954_746e_656d_6863_6174_7461, _) => Filter::AttachmentType(
+                            parser
+                                .next_token::<String>()?
+                                .unwrap_string("attachmentType")?,
+                        ),
+                        (0x0064_6574_7079_7263_6e45_7369, _) => Filter::IsEncrypted(
+                            parser.next_token::<String>()?.unwrap_bool("isEncrypted")?,
+                        ),
+                        (0x6465_6e67_6953_7369, _) => Filter::IsSigned(
+                            parser.next_token::<String>()?.unwrap_bool("isSigned")?,
+                        ),
                         (0x6d6f_7266, _) => {
                             Filter::From(parser.next_token::<String>()?.unwrap_string("from")?)
                         }
@@ -426,6 +466,26 @@ pub fn parse_filter(parser: &mut Parser) -> crate::parser::Result<Vec<Filter>> {
                                 .next_token::<String>()?
                                 .unwrap_string("resourceType")?,
                         ),
+                        (0x6570_7954_7463_656a_626f, _) => Filter::ObjectType(
+                            parser.next_token::<String>()?.unwrap_string("objectType")?,
+                        ),
+                        (0x0064_4974_6e75_6f63_6341_7463_656a_626f, _) => Filter::ObjectAccountId(
+                            parser
+                                .next_token::<Id>()?
+                                .unwrap_string("objectAccountId")?,
+                        ),
+                        (0x7261_646e_656c_6143_6e69, _) => Filter::InCalendar(
+                            parser.next_token::<Id>()?.unwrap_string("inCalendar")?,
+                        ),
+                        (0x0064_6975, _) => {
+                            Filter::Uid(parser.next_token::<String>()?.unwrap_string("uid")?)
+                        }
+                        (0x0065_6c74_6974, _) => {
+                            Filter::Title(parser.next_token::<String>()?.unwrap_string("title")?)
+                        }
+                        (0x7364_4974_6e65_7645_7261_646e_656c_6163, _) => {
+                            Filter::CalendarEventIds(<Vec<Id>>::parse(parser)?)
+                        }
                         _ => {
                             if parser.is_eof || parser.skip_string() {
                                 let filter = Filter::_T(
@@ -568,6 +628,8 @@ impl JsonObjectParser for SortProperty {
             0x4b65_7661_4864_6165_7268_546e_496c_6c61 => Ok(SortProperty::AllInThreadHaveKeyword),
             0x6576_6148_6461_6572_6854_6e49_656d_6f73 => Ok(SortProperty::SomeInThreadHaveKeyword),
             0x6465_7375 => Ok(SortProperty::Used),
+            0x0064_6574_6165_7263 => Ok(SortProperty::Created),
+            0x0074_7261_7473 => Ok(SortProperty::Start),
             _ => {
                 if parser.is_eof || parser.skip_string() {
                     Ok(SortProperty::_T(
@@ -612,6 +674,10 @@ impl Display for Filter {
             Filter::HasKeyword(_) => "hasKeyword",
             Filter::NotKeyword(_) => "notKeyword",
             Filter::HasAttachment(_) => "hasAttachment",
+            Filter::AttachmentName(_) => "attachmentName",
+            Filter::AttachmentType(_) => "attachmentType",
+            Filter::IsEncrypted(_) => "isEncrypted",
+            Filter::IsSigned(_) => "isSigned",
             Filter::From(_) => "from",
             Filter::To(_) => "to",
             Filter::Cc(_) => "cc",
@@ -630,6 +696,12 @@ impl Display for Filter {
             Filter::IsActive(_) => "isActive",
             Filter::ResourceType(_) => "resourceType",
             Filter::Scope(_) => "scope",
+            Filter::ObjectType(_) => "objectType",
+            Filter::ObjectAccountId(_) => "objectAccountId",
+            Filter::InCalendar(_) => "inCalendar",
+            Filter::Uid(_) => "uid",
+            Filter::Title(_) => "title",
+            Filter::CalendarEventIds(_) => "calendarEventIds",
             Filter::_T(v) => v.as_str(),
             Filter::And => "and",
             Filter::Or => "or",
@@ -661,6 +733,8 @@ impl Display for SortProperty {
             SortProperty::AllInThreadHaveKeyword => "allInThreadHaveKeyword",
             SortProperty::SomeInThreadHaveKeyword => "someInThreadHaveKeyword",
             SortProperty::Used => "used",
+            SortProperty::Created => "created",
+            SortProperty::Start => "start",
             SortProperty::_T(s) => s,
         })
     }
@@ -690,6 +764,10 @@ impl Filter {
                 | Filter::MaxSize(_)
                 | Filter::Text(_)
                 | Filter::HasAttachment(_)
+                | Filter::AttachmentName(_)
+                | Filter::AttachmentType(_)
+                | Filter::IsEncrypted(_)
+                | Filter::IsSigned(_)
                 | Filter::From(_)
                 | Filter::To(_)
                 | Filter::Cc(_)
@@ -793,7 +871,11 @@ impl FilterItem for Filter {
             | Filter::Bcc(_)
             | Filter::Subject(_)
             | Filter::Body(_)
-            | Filter::Header(_) => FilterType::Fts,
+            | Filter::Header(_)
+            | Filter::AttachmentName(_)
+            | Filter::AttachmentType(_)
+            | Filter::IsEncrypted(_)
+            | Filter::IsSigned(_) => FilterType::Fts,
             Filter::And => FilterType::And,
             Filter::Or => FilterType::Or,
             Filter::Not => FilterType::Not,
@@ -12,7 +12,7 @@ use crate::{
         method::MethodError,
         set::{InvalidProperty, SetError},
     },
-    object::{email_submission, mailbox, sieve, Object},
+    object::{calendar, calendar_event, email_submission, mailbox, sieve, Object},
     parser::{json::Parser, Error, JsonObjectParser, Token},
     request::{
         method::MethodObject,
@@ -54,6 +54,11 @@ pub enum RequestArguments {
     PushSubscription,
     SieveScript(sieve::SetArguments),
     VacationResponse,
+    ShareNotification,
+    Calendar(calendar::SetArguments),
+    CalendarEvent(calendar_event::SetArguments),
+    CalendarEventNotification,
+    Template,
 }
 
 #[derive(Debug, Clone, Default, serde::Serialize)]
@@ -114,6 +119,13 @@ impl JsonObjectParser for SetRequest<RequestArguments> {
                 MethodObject::PushSubscription => RequestArguments::PushSubscription,
                 MethodObject::VacationResponse => RequestArguments::VacationResponse,
                 MethodObject::SieveScript => RequestArguments::SieveScript(Default::default()),
+                MethodObject::ShareNotification => RequestArguments::ShareNotification,
+                MethodObject::Calendar => RequestArguments::Calendar(Default::default()),
+                MethodObject::CalendarEvent => RequestArguments::CalendarEvent(Default::default()),
+                MethodObject::CalendarEventNotification => {
+                    RequestArguments::CalendarEventNotification
+                }
+                MethodObject::Template => RequestArguments::Template,
                 _ => {
                     return Err(Error::Method(MethodError::UnknownMethod(format!(
                         "{}/set",
@@ -195,6 +207,7 @@ impl JsonObjectParser for Object<SetValue> {
                         .unwrap_or(SetValue::Value(Value::Null)),
                     Property::SentAt
                     | Property::ReceivedAt
+                    | Property::SendAt
                     | Property::Expires
                     | Property::FromDate
                     | Property::ToDate => parser
@@ -216,12 +229,24 @@ impl JsonObjectParser for Object<SetValue> {
                     | Property::TextSignature
                     | Property::Type
                     | Property::Charset
-                    | Property::Disposition
                     | Property::Language
                     | Property::Location
                     | Property::Cid
                     | Property::Role
-                    | Property::PartId => parser
+                    | Property::PartId
+                    | Property::ReportingUA
+                    | Property::MdnGateway
+                    | Property::OriginalRecipient
+                    | Property::FinalRecipient
+                    | Property::OriginalMessageId
+                    | Property::Uid
+                    | Property::Title
+                    | Property::Start
+                    | Property::Duration
+                    | Property::Status
+                    | Property::Color
+                    | Property::Scope
+                    | Property::Domain => parser
                         .next_token::<String>()?
                         .unwrap_string_or_null("")?
                         .map(|text| SetValue::Value(Value::Text(text)))
@@ -240,25 +265,49 @@ impl JsonObjectParser for Object<SetValue> {
                                 .unwrap_or(SetValue::Value(Value::Null))
                         }
                     }
+                    Property::Disposition => {
+                        if let MethodObject::Mdn = &parser.ctx {
+                            SetValue::Value(Value::parse::<ObjectProperty, String>(
+                                parser.next_token()?,
+                                parser,
+                            )?)
+                        } else {
+                            parser
+                                .next_token::<String>()?
+                                .unwrap_string_or_null("")?
+                                .map(|text| SetValue::Value(Value::Text(text)))
+                                .unwrap_or(SetValue::Value(Value::Null))
+                        }
+                    }
                     Property::HasAttachment
+                    | Property::IncludeOriginalMessage
                     | Property::IsSubscribed
                     | Property::IsEnabled
-                    | Property::IsActive => parser
+                    | Property::IsActive
+                    | Property::ShowWithoutTime
+                    | Property::IsVisible
+                    | Property::IsDefault => parser
                         .next_token::<String>()?
                         .unwrap_bool_or_null("")?
                         .map(|bool| SetValue::Value(Value::Bool(bool)))
                         .unwrap_or(SetValue::Value(Value::Null)),
-                    Property::Size | Property::SortOrder | Property::Quota => parser
-                        .next_token::<String>()?
-                        .unwrap_uint_or_null("")?
-                        .map(|uint| SetValue::Value(Value::UnsignedInt(uint)))
-                        .unwrap_or(SetValue::Value(Value::Null)),
-                    Property::ParentId | Property::EmailId | Property::IdentityId => parser
+                    Property::Size | Property::SortOrder | Property::Quota | Property::Sequence => {
+                        parser
+                            .next_token::<String>()?
+                            .unwrap_uint_or_null("")?
+                            .map(|uint| SetValue::Value(Value::UnsignedInt(uint)))
+                            .unwrap_or(SetValue::Value(Value::Null))
+                    }
+                    Property::ParentId
+                    | Property::EmailId
+                    | Property::IdentityId
+                    | Property::ForEmailId
+                    | Property::FromTemplate => parser
                         .next_token::<MaybeReference<Id, String>>()?
                         .unwrap_string_or_null("")?
                         .map(SetValue::from)
                         .unwrap_or(SetValue::Value(Value::Null)),
-                    Property::MailboxIds => {
+                    Property::MailboxIds | Property::CalendarIds => {
                         if key.patch.is_empty() {
                             SetValue::from(
                                 <SetValueMap<MaybeReference<Id, String>>>::parse(parser)?.values,
@@ -325,14 +374,18 @@ impl JsonObjectParser for Object<SetValue> {
                     | Property::SubParts
                     | Property::To
                     | Property::UndoStatus
-                    | Property::Types => SetValue::Value(Value::parse::<ObjectProperty, String>(
-                        parser.next_token()?,
-                        parser,
-                    )?),
-                    Property::Parameters => SetValue::Value(Value::parse::<String, String>(
+                    | Property::Error
+                    | Property::Types
+                    | Property::RecurrenceRules
+                    | Property::Participants
+                    | Property::Locations
+                    | Property::Snoozed => SetValue::Value(Value::parse::<ObjectProperty, String>(
                         parser.next_token()?,
                         parser,
                     )?),
+                    Property::Parameters | Property::ExtensionFields => SetValue::Value(
+                        Value::parse::<String, String>(parser.next_token()?, parser)?,
+                    ),
                     Property::Members => SetValue::Value(Value::parse::<ObjectProperty, Id>(
                         parser.next_token()?,
                         parser,
@@ -394,6 +447,8 @@ impl RequestPropertyParser for RequestArguments {
             RequestArguments::Mailbox(args) => args.parse(parser, property),
             RequestArguments::EmailSubmission(args) => args.parse(parser, property),
             RequestArguments::SieveScript(args) => args.parse(parser, property),
+            RequestArguments::Calendar(args) => args.parse(parser, property),
+            RequestArguments::CalendarEvent(args) => args.parse(parser, property),
             _ => Ok(false),
         }
     }
@@ -0,0 +1,228 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use utils::map::vec_map::VecMap;
+
+use crate::{
+    error::set::SetError,
+    object::Object,
+    parser::{json::Parser, JsonObjectParser, Token},
+    request::RequestProperty,
+    types::{
+        blob::BlobId,
+        id::Id,
+        state::{State, StateChange},
+        value::Value,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct SplitThreadRequest {
+    pub account_id: Id,
+    pub email_ids: Vec<Id>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SplitThreadResponse {
+    #[serde(rename = "accountId")]
+    pub account_id: Id,
+
+    #[serde(rename = "oldState")]
+    pub old_state: State,
+
+    #[serde(rename = "newState")]
+    pub new_state: State,
+
+    #[serde(rename = "split")]
+    #[serde(skip_serializing_if = "VecMap::is_empty")]
+    pub split: VecMap<Id, Object<Value>>,
+
+    #[serde(rename = "notSplit")]
+    #[serde(skip_serializing_if = "VecMap::is_empty")]
+    pub not_split: VecMap<Id, SetError>,
+
+    #[serde(skip)]
+    pub state_change: Option<StateChange>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MergeThreadRequest {
+    pub account_id: Id,
+    pub thread_ids: Vec<Id>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MergeThreadResponse {
+    #[serde(rename = "accountId")]
+    pub account_id: Id,
+
+    #[serde(rename = "oldState")]
+    pub old_state: State,
+
+    #[serde(rename = "newState")]
+    pub new_state: State,
+
+    #[serde(rename = "threadId")]
+    pub thread_id: Option<Id>,
+
+    #[serde(rename = "notFound")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub not_found: Vec<Id>,
+
+    #[serde(skip)]
+    pub state_change: Option<StateChange>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportThreadRequest {
+    pub account_id: Id,
+    pub thread_ids: Vec<Id>,
+    pub format: ExportFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Mbox,
+    Zip,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ExportThreadResponse {
+    #[serde(rename = "accountId")]
+    pub account_id: Id,
+
+    #[serde(rename = "exported")]
+    #[serde(skip_serializing_if = "VecMap::is_empty")]
+    pub exported: VecMap<Id, ExportThreadObject>,
+
+    #[serde(rename = "notExported")]
+    #[serde(skip_serializing_if = "VecMap::is_empty")]
+    pub not_exported: VecMap<Id, SetError>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ExportThreadObject {
+    #[serde(rename = "blobId")]
+    pub blob_id: BlobId,
+    #[serde(rename = "type")]
+    pub type_: &'static str,
+    pub size: usize,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Mbox => "application/mbox",
+            ExportFormat::Zip => "application/zip",
+        }
+    }
+}
+
+impl JsonObjectParser for SplitThreadRequest {
+    fn parse(parser: &mut Parser<'_>) -> crate::parser::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut request = SplitThreadRequest {
+            account_id: Id::default(),
+            email_ids: vec![],
+        };
+
+        parser
+            .next_token::<String>()?
+            .assert_jmap(Token::DictStart)?;
+
+        while let Some(key) = parser.next_dict_key::<RequestProperty>()? {
+            match &key.hash[0] {
+                0x0064_4974_6e75_6f63_6361 if !key.is_ref => {
+                    request.account_id = parser.next_token::<Id>()?.unwrap_string("accountId")?;
+                }
+                0x7364_496c_6961_6d65 if !key.is_ref => {
+                    request.email_ids = <Vec<Id>>::parse(parser)?;
+                }
+                _ => {
+                    parser.skip_token(parser.depth_array, parser.depth_dict)?;
+                }
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+impl JsonObjectParser for MergeThreadRequest {
+    fn parse(parser: &mut Parser<'_>) -> crate::parser::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut request = MergeThreadRequest {
+            account_id: Id::default(),
+            thread_ids: vec![],
+        };
+
+        parser
+            .next_token::<String>()?
+            .assert_jmap(Token::DictStart)?;
+
+        while let Some(key) = parser.next_dict_key::<RequestProperty>()? {
+            match &key.hash[0] {
+                0x0064_4974_6e75_6f63_6361 if !key.is_ref => {
+                    request.account_id = parser.next_token::<Id>()?.unwrap_string("accountId")?;
+                }
+                0x0073_6449_6461_6572_6874 if !key.is_ref => {
+                    request.thread_ids = <Vec<Id>>::parse(parser)?;
+                }
+                _ => {
+                    parser.skip_token(parser.depth_array, parser.depth_dict)?;
+                }
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+impl JsonObjectParser for ExportThreadRequest {
+    fn parse(parser: &mut Parser<'_>) -> crate::parser::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut request = ExportThreadRequest {
+            account_id: Id::default(),
+            thread_ids: vec![],
+            format: ExportFormat::default(),
+        };
+
+        parser
+            .next_token::<String>()?
+            .assert_jmap(Token::DictStart)?;
+
+        while let Some(key) = parser.next_dict_key::<RequestProperty>()? {
+            match &key.hash[0] {
+                0x0064_4974_6e75_6f63_6361 if !key.is_ref => {
+                    request.account_id = parser.next_token::<Id>()?.unwrap_string("accountId")?;
+                }
+                0x0073_6449_6461_6572_6874 if !key.is_ref => {
+                    request.thread_ids = <Vec<Id>>::parse(parser)?;
+                }
+                0x7461_6d72_6f66 if !key.is_ref => {
+                    request.format = match parser.next_token::<String>()? {
+                        Token::String(format) if format == "mbox" => ExportFormat::Mbox,
+                        Token::String(format) if format == "zip" => ExportFormat::Zip,
+                        Token::Null => ExportFormat::default(),
+                        token => return Err(token.error("format", "\"mbox\" or \"zip\"")),
+                    };
+                }
+                _ => {
+                    parser.skip_token(parser.depth_array, parser.depth_dict)?;
+                }
+            }
+        }
+
+        Ok(request)
+    }
+}
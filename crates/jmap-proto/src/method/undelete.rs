@@ -0,0 +1,157 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use utils::map::vec_map::VecMap;
+
+use crate::{
+    error::set::SetError,
+    object::Object,
+    parser::{json::Parser, JsonObjectParser, Token},
+    request::RequestProperty,
+    types::{
+        blob::BlobId,
+        date::UTCDate,
+        id::Id,
+        keyword::Keyword,
+        state::{State, StateChange},
+        value::{SetValueMap, Value},
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct ListDeletedEmailRequest {
+    pub account_id: Id,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ListDeletedEmailResponse {
+    #[serde(rename = "accountId")]
+    pub account_id: Id,
+
+    #[serde(rename = "list")]
+    pub list: Vec<DeletedEmail>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DeletedEmail {
+    #[serde(rename = "blobId")]
+    pub blob_id: BlobId,
+
+    #[serde(rename = "size")]
+    pub size: usize,
+
+    #[serde(rename = "subject")]
+    pub subject: String,
+
+    #[serde(rename = "mailboxIds")]
+    pub mailbox_ids: VecMap<Id, bool>,
+
+    #[serde(rename = "keywords")]
+    pub keywords: VecMap<Keyword, bool>,
+
+    #[serde(rename = "receivedAt")]
+    pub received_at: UTCDate,
+
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: UTCDate,
+
+    #[serde(rename = "expiresAt")]
+    pub expires_at: UTCDate,
+}
+
+#[derive(Debug, Clone)]
+pub struct UndeleteEmailRequest {
+    pub account_id: Id,
+    pub blob_ids: Vec<BlobId>,
+    pub mailbox_ids: Vec<Id>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct UndeleteEmailResponse {
+    #[serde(rename = "accountId")]
+    pub account_id: Id,
+
+    #[serde(rename = "oldState")]
+    pub old_state: State,
+
+    #[serde(rename = "newState")]
+    pub new_state: State,
+
+    #[serde(rename = "created")]
+    #[serde(skip_serializing_if = "VecMap::is_empty")]
+    pub created: VecMap<BlobId, Object<Value>>,
+
+    #[serde(rename = "notCreated")]
+    #[serde(skip_serializing_if = "VecMap::is_empty")]
+    pub not_created: VecMap<BlobId, SetError>,
+
+    #[serde(skip)]
+    pub state_change: Option<StateChange>,
+}
+
+impl JsonObjectParser for ListDeletedEmailRequest {
+    fn parse(parser: &mut Parser<'_>) -> crate::parser::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut request = ListDeletedEmailRequest {
+            account_id: Id::default(),
+        };
+
+        parser
+            .next_token::<String>()?
+            .assert_jmap(Token::DictStart)?;
+
+        while let Some(key) = parser.next_dict_key::<RequestProperty>()? {
+            match &key.hash[0] {
+                0x0064_4974_6e75_6f63_6361 if !key.is_ref => {
+                    request.account_id = parser.next_token::<Id>()?.unwrap_string("accountId")?;
+                }
+                _ => {
+                    parser.skip_token(parser.depth_array, parser.depth_dict)?;
+                }
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+impl JsonObjectParser for UndeleteEmailRequest {
+    fn parse(parser: &mut Parser<'_>) -> crate::parser::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut request = UndeleteEmailRequest {
+            account_id: Id::default(),
+            blob_ids: vec![],
+            mailbox_ids: vec![],
+        };
+
+        parser
+            .next_token::<String>()?
+            .assert_jmap(Token::DictStart)?;
+
+        while let Some(key) = parser.next_dict_key::<RequestProperty>()? {
+            match &key.hash[0] {
+                0x0064_4974_6e75_6f63_6361 if !key.is_ref => {
+                    request.account_id = parser.next_token::<Id>()?.unwrap_string("accountId")?;
+                }
+                0x0073_6449_626f_6c62 if !key.is_ref => {
+                    request.blob_ids = <Vec<BlobId>>::parse(parser)?;
+                }
+                0x7364_4978_6f62_6c69_616d if !key.is_ref => {
+                    request.mailbox_ids = <SetValueMap<Id>>::parse(parser)?.values;
+                }
+                _ => {
+                    parser.skip_token(parser.depth_array, parser.depth_dict)?;
+                }
+            }
+        }
+
+        Ok(request)
+    }
+}
@@ -0,0 +1,34 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::{
+    parser::{json::Parser, Ignore},
+    request::{RequestProperty, RequestPropertyParser},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct SetArguments {
+    pub send_scheduling_messages: Option<bool>,
+}
+
+impl RequestPropertyParser for SetArguments {
+    fn parse(
+        &mut self,
+        parser: &mut Parser,
+        property: RequestProperty,
+    ) -> crate::parser::Result<bool> {
+        if property.hash[0] == 0x654d_676e_696c_7564_6568_6353_646e_6573
+            && property.hash[1] == 0x7365_6761_7373
+        {
+            self.send_scheduling_messages = parser
+                .next_token::<Ignore>()?
+                .unwrap_bool_or_null("sendSchedulingMessages")?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
@@ -5,6 +5,8 @@
  */
 
 pub mod blob;
+pub mod calendar;
+pub mod calendar_event;
 pub mod email;
 pub mod email_submission;
 pub mod index;
@@ -72,6 +72,12 @@ pub enum Capability {
     Blob = 1 << 8,
     #[serde(rename(serialize = "urn:ietf:params:jmap:quota"))]
     Quota = 1 << 9,
+    #[serde(rename(serialize = "urn:ietf:params:jmap:mdn"))]
+    Mdn = 1 << 10,
+    #[serde(rename(serialize = "urn:ietf:params:jmap:principals"))]
+    Principals = 1 << 11,
+    #[serde(rename(serialize = "urn:ietf:params:jmap:principals:owner"))]
+    PrincipalsOwner = 1 << 12,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -85,6 +91,9 @@ pub enum Capabilities {
     SieveAccount(SieveAccountCapabilities),
     SieveSession(SieveSessionCapabilities),
     Blob(BlobCapabilities),
+    PrincipalsAccount(PrincipalsAccountCapabilities),
+    PrincipalsOwner(PrincipalsOwnerCapabilities),
+    CalendarsAccount(CalendarsAccountCapabilities),
     Empty(EmptyCapabilities),
 }
 
@@ -176,6 +185,34 @@ pub struct BlobCapabilities {
     pub supported_digest_algorithms: Vec<&'static str>,
 }
 
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct PrincipalsAccountCapabilities {
+    #[serde(rename(serialize = "currentUserPrincipalId"))]
+    pub current_user_principal_id: Option<Id>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PrincipalsOwnerCapabilities {
+    #[serde(rename(serialize = "accountIdForPrincipal"))]
+    pub account_id_for_principal: Id,
+    #[serde(rename(serialize = "principalId"))]
+    pub principal_id: Id,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CalendarsAccountCapabilities {
+    #[serde(rename(serialize = "maxCalendarsPerEvent"))]
+    pub max_calendars_per_event: Option<usize>,
+    #[serde(rename(serialize = "minDateTime"))]
+    pub min_date_time: String,
+    #[serde(rename(serialize = "maxDateTime"))]
+    pub max_date_time: String,
+    #[serde(rename(serialize = "maxParticipantsPerEvent"))]
+    pub max_participants_per_event: Option<usize>,
+    #[serde(rename(serialize = "mayCreateCalendar"))]
+    pub may_create_calendar: bool,
+}
+
 #[derive(Debug, Clone, Default, serde::Serialize)]
 pub struct EmptyCapabilities {}
 
@@ -256,6 +293,17 @@ impl Session {
         );
     }
 
+    pub fn set_account_capability(
+        &mut self,
+        account_id: Id,
+        capability: Capability,
+        value: Capabilities,
+    ) {
+        if let Some(account) = self.accounts.get_mut(&account_id) {
+            account.account_capabilities.set(capability, value);
+        }
+    }
+
     pub fn set_state(&mut self, state: u32) {
         self.state = state;
     }
@@ -341,6 +389,9 @@ impl JsonObjectParser for Capability {
                 0x0065_7665_6973 => Ok(Capability::Sieve),
                 0x626f_6c62 => Ok(Capability::Blob),
                 0x0061_746f_7571 => Ok(Capability::Quota),
+                0x006e_646d => Ok(Capability::Mdn),
+                0x736c_6170_6963_6e69_7270 => Ok(Capability::Principals),
+                0x7265_6e77_6f3a_736c_6170_6963_6e69_7270 => Ok(Capability::PrincipalsOwner),
                 _ => Err(parser.error_capability()),
             },
             Err(Error::Method(_)) => Err(parser.error_capability()),
@@ -29,6 +29,12 @@ pub enum MethodObject {
     SieveScript,
     Principal,
     Quota,
+    Mdn,
+    ShareNotification,
+    Calendar,
+    CalendarEvent,
+    CalendarEventNotification,
+    Template,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -44,6 +50,12 @@ pub enum MethodFunction {
     Validate,
     Lookup,
     Upload,
+    Send,
+    Split,
+    Merge,
+    Export,
+    ListDeleted,
+    Undelete,
     Echo,
 }
 
@@ -54,6 +66,7 @@ impl JsonObjectParser for MethodName {
     {
         let mut shift = 0;
         let mut obj_hash: u128 = 0;
+        let mut obj_hash_ext: u128 = 0;
         let mut fnc_hash: u128 = 0;
 
         loop {
@@ -63,10 +76,12 @@ impl JsonObjectParser for MethodName {
             if ch != b'/' {
                 if shift < 128 {
                     obj_hash |= (ch as u128) << shift;
-                    shift += 8;
+                } else if shift < 256 {
+                    obj_hash_ext |= (ch as u128) << (shift - 128);
                 } else {
                     return Err(parser.error_value());
                 }
+                shift += 8;
             } else {
                 break;
             }
@@ -84,6 +99,15 @@ impl JsonObjectParser for MethodName {
 
         Ok(MethodName {
             obj: match obj_hash {
+                0x6f69_7461_6369_6669_746f_4e65_7261_6853 if obj_hash_ext == 0x006e => {
+                    MethodObject::ShareNotification
+                }
+                0x746f_4e74_6e65_7645_7261_646e_656c_6143
+                    if obj_hash_ext == 0x006e_6f69_7461_6369_6669 =>
+                {
+                    MethodObject::CalendarEventNotification
+                }
+                _ if obj_hash_ext != 0 => return Err(parser.error_value()),
                 0x006c_6961_6d45 => MethodObject::Email,
                 0x0078_6f62_6c69_614d => MethodObject::Mailbox,
                 0x6461_6572_6854 => MethodObject::Thread,
@@ -96,6 +120,10 @@ impl JsonObjectParser for MethodName {
                 0x0074_7069_7263_5365_7665_6953 => MethodObject::SieveScript,
                 0x006c_6170_6963_6e69_7250 => MethodObject::Principal,
                 0x0061_746f_7551 => MethodObject::Quota,
+                0x004e_444d => MethodObject::Mdn,
+                0x7261_646e_656c_6143 => MethodObject::Calendar,
+                0x0074_6e65_7645_7261_646e_656c_6143 => MethodObject::CalendarEvent,
+                0x6574_616c_706d_6554 => MethodObject::Template,
                 0x6572_6f43 => MethodObject::Core,
                 _ => return Err(parser.error_value()),
             },
@@ -111,6 +139,12 @@ impl JsonObjectParser for MethodName {
                 0x6574_6164_696c_6176 => MethodFunction::Validate,
                 0x7075_6b6f_6f6c => MethodFunction::Lookup,
                 0x6461_6f6c_7075 => MethodFunction::Upload,
+                0x646e_6573 => MethodFunction::Send,
+                0x0074_696c_7073 => MethodFunction::Split,
+                0x0065_6772_656d => MethodFunction::Merge,
+                0x7472_6f70_7865 => MethodFunction::Export,
+                0x0064_6574_656c_6544_7473_696c => MethodFunction::ListDeleted,
+                0x6574_656c_6564_6e75 => MethodFunction::Undelete,
                 0x6f68_6365 => MethodFunction::Echo,
                 _ => return Err(parser.error_value()),
             },
@@ -149,6 +183,9 @@ impl MethodName {
 
             (MethodFunction::Get, MethodObject::Thread) => "Thread/get",
             (MethodFunction::Changes, MethodObject::Thread) => "Thread/changes",
+            (MethodFunction::Split, MethodObject::Thread) => "Thread/split",
+            (MethodFunction::Merge, MethodObject::Thread) => "Thread/merge",
+            (MethodFunction::Export, MethodObject::Thread) => "Thread/export",
 
             (MethodFunction::Get, MethodObject::Email) => "Email/get",
             (MethodFunction::Changes, MethodObject::Email) => "Email/changes",
@@ -157,6 +194,8 @@ impl MethodName {
             (MethodFunction::Set, MethodObject::Email) => "Email/set",
             (MethodFunction::Copy, MethodObject::Email) => "Email/copy",
             (MethodFunction::Import, MethodObject::Email) => "Email/import",
+            (MethodFunction::ListDeleted, MethodObject::Email) => "Email/listDeleted",
+            (MethodFunction::Undelete, MethodObject::Email) => "Email/undelete",
             (MethodFunction::Parse, MethodObject::Email) => "Email/parse",
 
             (MethodFunction::Get, MethodObject::SearchSnippet) => "SearchSnippet/get",
@@ -195,6 +234,55 @@ impl MethodName {
             (MethodFunction::Lookup, MethodObject::Blob) => "Blob/lookup",
             (MethodFunction::Upload, MethodObject::Blob) => "Blob/upload",
 
+            (MethodFunction::Get, MethodObject::ShareNotification) => "ShareNotification/get",
+            (MethodFunction::Changes, MethodObject::ShareNotification) => {
+                "ShareNotification/changes"
+            }
+            (MethodFunction::Query, MethodObject::ShareNotification) => "ShareNotification/query",
+            (MethodFunction::QueryChanges, MethodObject::ShareNotification) => {
+                "ShareNotification/queryChanges"
+            }
+            (MethodFunction::Set, MethodObject::ShareNotification) => "ShareNotification/set",
+
+            (MethodFunction::Get, MethodObject::Calendar) => "Calendar/get",
+            (MethodFunction::Changes, MethodObject::Calendar) => "Calendar/changes",
+            (MethodFunction::Query, MethodObject::Calendar) => "Calendar/query",
+            (MethodFunction::QueryChanges, MethodObject::Calendar) => "Calendar/queryChanges",
+            (MethodFunction::Set, MethodObject::Calendar) => "Calendar/set",
+
+            (MethodFunction::Get, MethodObject::CalendarEvent) => "CalendarEvent/get",
+            (MethodFunction::Changes, MethodObject::CalendarEvent) => "CalendarEvent/changes",
+            (MethodFunction::Query, MethodObject::CalendarEvent) => "CalendarEvent/query",
+            (MethodFunction::QueryChanges, MethodObject::CalendarEvent) => {
+                "CalendarEvent/queryChanges"
+            }
+            (MethodFunction::Set, MethodObject::CalendarEvent) => "CalendarEvent/set",
+            (MethodFunction::Parse, MethodObject::CalendarEvent) => "CalendarEvent/parse",
+
+            (MethodFunction::Get, MethodObject::CalendarEventNotification) => {
+                "CalendarEventNotification/get"
+            }
+            (MethodFunction::Changes, MethodObject::CalendarEventNotification) => {
+                "CalendarEventNotification/changes"
+            }
+            (MethodFunction::Query, MethodObject::CalendarEventNotification) => {
+                "CalendarEventNotification/query"
+            }
+            (MethodFunction::QueryChanges, MethodObject::CalendarEventNotification) => {
+                "CalendarEventNotification/queryChanges"
+            }
+            (MethodFunction::Set, MethodObject::CalendarEventNotification) => {
+                "CalendarEventNotification/set"
+            }
+
+            (MethodFunction::Get, MethodObject::Template) => "Template/get",
+            (MethodFunction::Changes, MethodObject::Template) => "Template/changes",
+            (MethodFunction::Query, MethodObject::Template) => "Template/query",
+            (MethodFunction::Set, MethodObject::Template) => "Template/set",
+
+            (MethodFunction::Send, MethodObject::Mdn) => "MDN/send",
+            (MethodFunction::Parse, MethodObject::Mdn) => "MDN/parse",
+
             (MethodFunction::Echo, MethodObject::Core) => "Core/echo",
             _ => "error",
         }
@@ -217,6 +305,12 @@ impl Display for MethodObject {
             MethodObject::Thread => "Thread",
             MethodObject::Email => "Email",
             MethodObject::Quota => "Quota",
+            MethodObject::Mdn => "MDN",
+            MethodObject::ShareNotification => "ShareNotification",
+            MethodObject::Calendar => "Calendar",
+            MethodObject::CalendarEvent => "CalendarEvent",
+            MethodObject::CalendarEventNotification => "CalendarEventNotification",
+            MethodObject::Template => "Template",
         })
     }
 }
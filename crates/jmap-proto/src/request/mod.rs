@@ -24,11 +24,14 @@ use crate::{
         get::{self, GetRequest},
         import::ImportEmailRequest,
         lookup::BlobLookupRequest,
-        parse::ParseEmailRequest,
+        mdn::{ParseMdnRequest, SendMdnRequest},
+        parse::{ParseCalendarEventRequest, ParseEmailRequest},
         query::{self, QueryRequest},
         query_changes::QueryChangesRequest,
         search_snippet::GetSearchSnippetRequest,
         set::{self, SetRequest},
+        thread::{ExportThreadRequest, MergeThreadRequest, SplitThreadRequest},
+        undelete::{ListDeletedEmailRequest, UndeleteEmailRequest},
         upload::BlobUploadRequest,
         validate::ValidateSieveScriptRequest,
     },
@@ -73,6 +76,14 @@ pub enum RequestMethod {
     ValidateScript(ValidateSieveScriptRequest),
     LookupBlob(BlobLookupRequest),
     UploadBlob(BlobUploadRequest),
+    SendMdn(SendMdnRequest),
+    ParseMdn(ParseMdnRequest),
+    ParseCalendarEvent(ParseCalendarEventRequest),
+    SplitThread(SplitThreadRequest),
+    MergeThread(MergeThreadRequest),
+    ExportThread(ExportThreadRequest),
+    ListDeletedEmail(ListDeletedEmailRequest),
+    UndeleteEmail(UndeleteEmailRequest),
     Echo(Echo),
     Error(MethodError),
 }
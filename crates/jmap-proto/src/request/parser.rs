@@ -17,11 +17,14 @@ use crate::{
         get::GetRequest,
         import::ImportEmailRequest,
         lookup::BlobLookupRequest,
-        parse::ParseEmailRequest,
+        mdn::{ParseMdnRequest, SendMdnRequest},
+        parse::{ParseCalendarEventRequest, ParseEmailRequest},
         query::QueryRequest,
         query_changes::QueryChangesRequest,
         search_snippet::GetSearchSnippetRequest,
         set::SetRequest,
+        thread::{ExportThreadRequest, MergeThreadRequest, SplitThreadRequest},
+        undelete::{ListDeletedEmailRequest, UndeleteEmailRequest},
         upload::BlobUploadRequest,
         validate::ValidateSieveScriptRequest,
     },
@@ -126,7 +129,12 @@ impl Request {
                                 | MethodObject::SieveScript
                                 | MethodObject::Principal
                                 | MethodObject::Quota
-                                | MethodObject::Blob,
+                                | MethodObject::Blob
+                                | MethodObject::ShareNotification
+                                | MethodObject::Calendar
+                                | MethodObject::CalendarEvent
+                                | MethodObject::CalendarEventNotification
+                                | MethodObject::Template,
                             ) => GetRequest::parse(parser).map(RequestMethod::Get),
                             (MethodFunction::Get, MethodObject::SearchSnippet) => {
                                 GetSearchSnippetRequest::parse(parser)
@@ -162,10 +170,37 @@ impl Request {
                             (MethodFunction::Parse, MethodObject::Email) => {
                                 ParseEmailRequest::parse(parser).map(RequestMethod::ParseEmail)
                             }
+                            (MethodFunction::ListDeleted, MethodObject::Email) => {
+                                ListDeletedEmailRequest::parse(parser)
+                                    .map(RequestMethod::ListDeletedEmail)
+                            }
+                            (MethodFunction::Undelete, MethodObject::Email) => {
+                                UndeleteEmailRequest::parse(parser)
+                                    .map(RequestMethod::UndeleteEmail)
+                            }
                             (MethodFunction::Validate, MethodObject::SieveScript) => {
                                 ValidateSieveScriptRequest::parse(parser)
                                     .map(RequestMethod::ValidateScript)
                             }
+                            (MethodFunction::Send, MethodObject::Mdn) => {
+                                SendMdnRequest::parse(parser).map(RequestMethod::SendMdn)
+                            }
+                            (MethodFunction::Parse, MethodObject::Mdn) => {
+                                ParseMdnRequest::parse(parser).map(RequestMethod::ParseMdn)
+                            }
+                            (MethodFunction::Parse, MethodObject::CalendarEvent) => {
+                                ParseCalendarEventRequest::parse(parser)
+                                    .map(RequestMethod::ParseCalendarEvent)
+                            }
+                            (MethodFunction::Split, MethodObject::Thread) => {
+                                SplitThreadRequest::parse(parser).map(RequestMethod::SplitThread)
+                            }
+                            (MethodFunction::Merge, MethodObject::Thread) => {
+                                MergeThreadRequest::parse(parser).map(RequestMethod::MergeThread)
+                            }
+                            (MethodFunction::Export, MethodObject::Thread) => {
+                                ExportThreadRequest::parse(parser).map(RequestMethod::ExportThread)
+                            }
                             (MethodFunction::Echo, MethodObject::Core) => {
                                 Echo::parse(parser).map(RequestMethod::Echo)
                             }
@@ -53,6 +53,8 @@ pub enum WebSocketResponseType {
 pub struct WebSocketPushEnable {
     pub data_types: Vec<DataType>,
     pub push_state: Option<String>,
+    pub close_after_state: bool,
+    pub ping: u32,
 }
 
 #[derive(Debug)]
@@ -77,6 +79,18 @@ pub struct WebSocketStateChange {
     push_state: Option<String>,
 }
 
+#[derive(serde::Serialize, Debug)]
+pub enum WebSocketPingType {
+    Ping,
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct WebSocketPing {
+    #[serde(rename = "@type")]
+    pub type_: WebSocketPingType,
+    pub interval: u64,
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct WebSocketRequestError {
     #[serde(rename = "@type")]
@@ -154,6 +168,26 @@ impl WebSocketMessage {
                             .unwrap_string_or_null("pushState")?;
                         found_push_keys = true;
                     }
+                    0x7265_7466_4165_736f_6c63 => {
+                        push_enable.close_after_state = match parser
+                            .next_token::<String>()?
+                            .unwrap_string_or_null("closeAfter")?
+                            .as_deref()
+                        {
+                            Some("state") => true,
+                            Some("no") | None => false,
+                            Some(_) => return Err(RequestError::invalid_parameters().into()),
+                        };
+                        found_push_keys = true;
+                    }
+                    0x676e_6970 => {
+                        push_enable.ping = parser
+                            .next_token::<String>()?
+                            .unwrap_uint_or_null("ping")?
+                            .map(|ping| ping.min(u32::MAX as u64) as u32)
+                            .unwrap_or_default();
+                        found_push_keys = true;
+                    }
                     0x6469 => {
                         request.id = parser.next_token::<String>()?.unwrap_string_or_null("id")?;
                     }
@@ -240,3 +274,16 @@ impl WebSocketStateChange {
         serde_json::to_string(self).unwrap()
     }
 }
+
+impl WebSocketPing {
+    pub fn new(interval: u64) -> Self {
+        WebSocketPing {
+            type_: WebSocketPingType::Ping,
+            interval,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
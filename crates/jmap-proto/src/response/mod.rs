@@ -17,11 +17,14 @@ use crate::{
         get::GetResponse,
         import::ImportEmailResponse,
         lookup::BlobLookupResponse,
-        parse::ParseEmailResponse,
+        mdn::{ParseMdnResponse, SendMdnResponse},
+        parse::{ParseCalendarEventResponse, ParseEmailResponse},
         query::QueryResponse,
         query_changes::QueryChangesResponse,
         search_snippet::GetSearchSnippetResponse,
         set::SetResponse,
+        thread::{ExportThreadResponse, MergeThreadResponse, SplitThreadResponse},
+        undelete::{ListDeletedEmailResponse, UndeleteEmailResponse},
         upload::BlobUploadResponse,
         validate::ValidateSieveScriptResponse,
     },
@@ -47,6 +50,14 @@ pub enum ResponseMethod {
     ValidateScript(ValidateSieveScriptResponse),
     LookupBlob(BlobLookupResponse),
     UploadBlob(BlobUploadResponse),
+    SendMdn(SendMdnResponse),
+    ParseMdn(ParseMdnResponse),
+    ParseCalendarEvent(ParseCalendarEventResponse),
+    SplitThread(SplitThreadResponse),
+    MergeThread(MergeThreadResponse),
+    ExportThread(ExportThreadResponse),
+    ListDeletedEmail(ListDeletedEmailResponse),
+    UndeleteEmail(UndeleteEmailResponse),
     Echo(Echo),
     Error(MethodError),
 }
@@ -154,6 +165,54 @@ impl From<ParseEmailResponse> for ResponseMethod {
     }
 }
 
+impl From<SendMdnResponse> for ResponseMethod {
+    fn from(send_mdn: SendMdnResponse) -> Self {
+        ResponseMethod::SendMdn(send_mdn)
+    }
+}
+
+impl From<ParseMdnResponse> for ResponseMethod {
+    fn from(parse_mdn: ParseMdnResponse) -> Self {
+        ResponseMethod::ParseMdn(parse_mdn)
+    }
+}
+
+impl From<ParseCalendarEventResponse> for ResponseMethod {
+    fn from(parse_calendar_event: ParseCalendarEventResponse) -> Self {
+        ResponseMethod::ParseCalendarEvent(parse_calendar_event)
+    }
+}
+
+impl From<SplitThreadResponse> for ResponseMethod {
+    fn from(split_thread: SplitThreadResponse) -> Self {
+        ResponseMethod::SplitThread(split_thread)
+    }
+}
+
+impl From<MergeThreadResponse> for ResponseMethod {
+    fn from(merge_thread: MergeThreadResponse) -> Self {
+        ResponseMethod::MergeThread(merge_thread)
+    }
+}
+
+impl From<ExportThreadResponse> for ResponseMethod {
+    fn from(export_thread: ExportThreadResponse) -> Self {
+        ResponseMethod::ExportThread(export_thread)
+    }
+}
+
+impl From<ListDeletedEmailResponse> for ResponseMethod {
+    fn from(list_deleted: ListDeletedEmailResponse) -> Self {
+        ResponseMethod::ListDeletedEmail(list_deleted)
+    }
+}
+
+impl From<UndeleteEmailResponse> for ResponseMethod {
+    fn from(undelete: UndeleteEmailResponse) -> Self {
+        ResponseMethod::UndeleteEmail(undelete)
+    }
+}
+
 impl From<QueryChangesResponse> for ResponseMethod {
     fn from(query_changes: QueryChangesResponse) -> Self {
         ResponseMethod::QueryChanges(query_changes)
@@ -129,6 +129,12 @@ impl Response {
                     }
                 }
             }
+            RequestMethod::SendMdn(request) => {
+                // Resolve forEmailId references
+                for obj in request.send.values_mut() {
+                    self.eval_object_references(obj, None)?;
+                }
+            }
             RequestMethod::SearchSnippet(request) => {
                 // Resolve emailIds references
                 if let MaybeReference::Reference(reference) = &request.email_ids {
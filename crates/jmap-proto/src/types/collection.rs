@@ -24,7 +24,12 @@ pub enum Collection {
     SieveScript = 5,
     PushSubscription = 6,
     Principal = 7,
-    None = 8,
+    ShareNotification = 8,
+    Calendar = 9,
+    CalendarEvent = 10,
+    CalendarEventNotification = 11,
+    Template = 12,
+    None = 13,
 }
 
 impl From<u8> for Collection {
@@ -38,6 +43,11 @@ impl From<u8> for Collection {
             5 => Collection::SieveScript,
             6 => Collection::PushSubscription,
             7 => Collection::Principal,
+            8 => Collection::ShareNotification,
+            9 => Collection::Calendar,
+            10 => Collection::CalendarEvent,
+            11 => Collection::CalendarEventNotification,
+            12 => Collection::Template,
             _ => Collection::None,
         }
     }
@@ -54,6 +64,11 @@ impl From<u64> for Collection {
             5 => Collection::SieveScript,
             6 => Collection::PushSubscription,
             7 => Collection::Principal,
+            8 => Collection::ShareNotification,
+            9 => Collection::Calendar,
+            10 => Collection::CalendarEvent,
+            11 => Collection::CalendarEventNotification,
+            12 => Collection::Template,
             _ => Collection::None,
         }
     }
@@ -83,6 +98,11 @@ impl TryFrom<Collection> for DataType {
             Collection::EmailSubmission => Ok(DataType::EmailSubmission),
             Collection::SieveScript => Ok(DataType::SieveScript),
             Collection::PushSubscription => Ok(DataType::PushSubscription),
+            Collection::ShareNotification => Ok(DataType::ShareNotification),
+            Collection::Calendar => Ok(DataType::Calendar),
+            Collection::CalendarEvent => Ok(DataType::CalendarEvent),
+            Collection::CalendarEventNotification => Ok(DataType::CalendarEventNotification),
+            Collection::Template => Ok(DataType::Template),
             _ => Err(()),
         }
     }
@@ -99,6 +119,11 @@ impl Display for Collection {
             Collection::EmailSubmission => write!(f, "emailSubmission"),
             Collection::SieveScript => write!(f, "sieveScript"),
             Collection::Principal => write!(f, "principal"),
+            Collection::ShareNotification => write!(f, "shareNotification"),
+            Collection::Calendar => write!(f, "calendar"),
+            Collection::CalendarEvent => write!(f, "calendarEvent"),
+            Collection::CalendarEventNotification => write!(f, "calendarEventNotification"),
+            Collection::Template => write!(f, "template"),
             Collection::None => write!(f, ""),
         }
     }
@@ -117,6 +142,11 @@ impl FromStr for Collection {
             "emailSubmission" => Ok(Collection::EmailSubmission),
             "sieveScript" => Ok(Collection::SieveScript),
             "principal" => Ok(Collection::Principal),
+            "shareNotification" => Ok(Collection::ShareNotification),
+            "calendar" => Ok(Collection::Calendar),
+            "calendarEvent" => Ok(Collection::CalendarEvent),
+            "calendarEventNotification" => Ok(Collection::CalendarEventNotification),
+            "template" => Ok(Collection::Template),
             _ => Err(()),
         }
     }
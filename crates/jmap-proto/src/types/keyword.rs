@@ -30,32 +30,19 @@ pub const FORWARDED: usize = 10;
 pub const MDN_SENT: usize = 11;
 pub const OTHER: usize = 12;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize)]
-#[serde(untagged)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Keyword {
-    #[serde(rename(serialize = "$seen"))]
     Seen,
-    #[serde(rename(serialize = "$draft"))]
     Draft,
-    #[serde(rename(serialize = "$flagged"))]
     Flagged,
-    #[serde(rename(serialize = "$answered"))]
     Answered,
-    #[serde(rename(serialize = "$recent"))]
     Recent,
-    #[serde(rename(serialize = "$important"))]
     Important,
-    #[serde(rename(serialize = "$phishing"))]
     Phishing,
-    #[serde(rename(serialize = "$junk"))]
     Junk,
-    #[serde(rename(serialize = "$notjunk"))]
     NotJunk,
-    #[serde(rename(serialize = "$deleted"))]
     Deleted,
-    #[serde(rename(serialize = "$forwarded"))]
     Forwarded,
-    #[serde(rename(serialize = "$mdnsent"))]
     MdnSent,
     Other(String),
 }
@@ -116,7 +103,7 @@ impl From<String> for Keyword {
             let mut hash = 0;
             let mut shift = 0;
 
-            for &ch in value.as_bytes() {
+            for &ch in value.as_bytes().iter().skip(1) {
                 if shift < 128 {
                     hash |= (ch as u128) << shift;
                     shift += 8;
@@ -166,6 +153,15 @@ impl Display for Keyword {
     }
 }
 
+impl serde::Serialize for Keyword {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
 impl ToBitmaps for Keyword {
     fn to_bitmaps(&self, ops: &mut Vec<store::write::Operation>, field: u8, set: bool) {
         ops.push(Operation::Bitmap {
@@ -119,6 +119,48 @@ pub enum Property {
     WarnLimit,
     SoftLimit,
     Scope,
+    ForEmailId,
+    IncludeOriginalMessage,
+    ReportingUA,
+    MdnGateway,
+    OriginalRecipient,
+    FinalRecipient,
+    OriginalMessageId,
+    Error,
+    ExtensionFields,
+    ActionMode,
+    SendingMode,
+    Accounts,
+    Created,
+    ChangedBy,
+    ObjectType,
+    ObjectAccountId,
+    ObjectId,
+    OldRights,
+    NewRights,
+    PrincipalId,
+    CalendarIds,
+    Uid,
+    Title,
+    Start,
+    Duration,
+    ShowWithoutTime,
+    Status,
+    RecurrenceRules,
+    Participants,
+    Sequence,
+    Updated,
+    Locations,
+    Color,
+    IsVisible,
+    IsDefault,
+    CalendarEventId,
+    Event,
+    Snoozed,
+    Until,
+    MoveToMailboxId,
+    Domain,
+    FromTemplate,
     Digest(DigestProperty),
     Data(DataProperty),
     _T(String),
@@ -247,17 +289,19 @@ impl JsonObjectParser for SetProperty {
 
         if is_patch {
             match &property {
-                Property::MailboxIds | Property::Members => match Id::parse(parser) {
-                    Ok(id) => {
-                        patch.push(Value::Id(id));
-                    }
-                    Err(Error::Method(_)) => {
-                        property = parser.invalid_property()?;
-                    }
-                    Err(err) => {
-                        return Err(err);
+                Property::MailboxIds | Property::Members | Property::CalendarIds => {
+                    match Id::parse(parser) {
+                        Ok(id) => {
+                            patch.push(Value::Id(id));
+                        }
+                        Err(Error::Method(_)) => {
+                            property = parser.invalid_property()?;
+                        }
+                        Err(err) => {
+                            return Err(err);
+                        }
                     }
-                },
+                }
                 Property::Keywords => match Keyword::parse(parser) {
                     Ok(keyword) => {
                         patch.push(Value::Keyword(keyword));
@@ -335,6 +379,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x6c63 => Property::Acl,
             0x7365_7361_696c => Property::Aliases,
             0x7374_6e65_6d68_6361_7474 => Property::Attachments,
+            0x0073_746e_756f_6363 => Property::Accounts,
             _ => return None,
         },
         b'b' => match hash {
@@ -349,6 +394,11 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x63 => Property::Cc,
             0x7465_7372_6168 => Property::Charset,
             0x6469 => Property::Cid,
+            0x6465_7461_6572 => Property::Created,
+            0x7942_6465_676e_6168 => Property::ChangedBy,
+            0x7364_4972_6164_6e65_6c61 => Property::CalendarIds,
+            0x726f_6c6f => Property::Color,
+            0x6449_746e_6576_4572_6164_6e65_6c61 => Property::CalendarEventId,
             _ => return None,
         },
         b'd' => match hash {
@@ -358,6 +408,8 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x6e6f_6974_6973_6f70_7369 => Property::Disposition,
             0x0073_6449_626f_6c42_6e73 => Property::DsnBlobIds,
             0x0061_7461 => Property::Data(DataProperty::Default),
+            0x006e_6f69_7461_7275 => Property::Duration,
+            0x006e_6961_6d6f => Property::Domain,
             _ => return None,
         },
         b'e' => match hash {
@@ -366,11 +418,17 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0073_6449_6c69_616d => Property::EmailIds,
             0x0065_706f_6c65_766e => Property::Envelope,
             0x7365_7269_7078 => Property::Expires,
+            0x726f_7272 => Property::Error,
+            0x7364_6c65_6946_6e6f_6973_6e65_7478 => Property::ExtensionFields,
+            0x746e_6576 => Property::Event,
             _ => return None,
         },
         b'f' => match hash {
             0x006d_6f72 => Property::From,
+            0x0064_496c_6961_6d45_726f => Property::ForEmailId,
+            0x0074_6e65_6970_6963_6552_6c61_6e69 => Property::FinalRecipient,
             0x0065_7461_446d_6f72 => Property::FromDate,
+            0x0065_7461_6c70_6d65_546d_6f72 => Property::FromTemplate,
             _ => return None,
         },
         b'h' => match hash {
@@ -387,6 +445,8 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0065_7669_7463_4173 => Property::IsActive,
             0x6465_6c62_616e_4573 => Property::IsEnabled,
             0x0064_6562_6972_6373_6275_5373 => Property::IsSubscribed,
+            0x656c_6269_7369_5673 => Property::IsVisible,
+            0x746c_7561_6665_4473 => Property::IsDefault,
             _ => return None,
         },
         b'k' => match hash {
@@ -397,12 +457,15 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
         b'l' => match hash {
             0x0065_6761_7567_6e61 => Property::Language,
             0x006e_6f69_7461_636f => Property::Location,
+            0x736e_6f69_7461_636f => Property::Locations,
             _ => return None,
         },
         b'm' => match hash {
             0x0073_6449_786f_626c_6961 => Property::MailboxIds,
+            0x6449_786f_626c_6961_4d6f_5465_766f => Property::MoveToMailboxId,
             0x6574_656c_6544_7961 => Property::MayDelete,
             0x0073_6449_626f_6c42_6e64 => Property::MdnBlobIds,
+            0x0079_6177_6574_6147_6e64 => Property::MdnGateway,
             0x7372_6562_6d65 => Property::Members,
             0x6449_6567_6173_7365 => Property::MessageId,
             0x0073_7468_6769_5279 => Property::MyRights,
@@ -410,13 +473,25 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
         },
         b'n' => match hash {
             0x0065_6d61 => Property::Name,
+            0x7374_6867_6952_7765 => Property::NewRights,
+            _ => return None,
+        },
+        b'o' => match hash {
+            0x746e_6569_7069_6365_526c_616e_6967_6972 => Property::OriginalRecipient,
+            0x6449_6567_6173_7365_4d6c_616e_6967_6972 => Property::OriginalMessageId,
+            0x0065_7079_5474_6365_6a62 => Property::ObjectType,
+            0x6449_746e_756f_6363_4174_6365_6a62 => Property::ObjectAccountId,
+            0x0064_4974_6365_6a62 => Property::ObjectId,
+            0x7374_6867_6952_646c => Property::OldRights,
             _ => return None,
         },
         b'p' => match hash {
             0x0064_4974_6e65_7261 => Property::ParentId,
+            0x6449_6c61_7069_636e_6972 => Property::PrincipalId,
             0x0064_4974_7261 => Property::PartId,
             0x6572_7574_6369 => Property::Picture,
             0x7765_6976_6572 => Property::Preview,
+            0x0073_746e_6170_6963_6974_7261 => Property::Participants,
             _ => return None,
         },
         b'q' => match hash {
@@ -427,7 +502,9 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0074_4164_6576_6965_6365 => Property::ReceivedAt,
             0x0073_6563_6e65_7265_6665 => Property::References,
             0x6f54_796c_7065 => Property::ReplyTo,
+            0x4155_676e_6974_726f_7065 => Property::ReportingUA,
             0x0065_6c6f => Property::Role,
+            0x7365_6c75_5265_636e_6572_7275_6365 => Property::RecurrenceRules,
             _ => return None,
         },
         b's' => match hash {
@@ -439,6 +516,12 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x7265_6472_4f74_726f => Property::SortOrder,
             0x7463_656a_6275 => Property::Subject,
             0x7374_7261_5062_7573 => Property::SubParts,
+            0x7472_6174 => Property::Start,
+            0x656d_6954_7475_6f68_7469_5777_6f68 => Property::ShowWithoutTime,
+            0x0073_7574_6174 => Property::Status,
+            0x0065_636e_6575_7165 => Property::Sequence,
+            0x6465_7a6f_6f6e => Property::Snoozed,
+            0x6570_6f63 => Property::Scope,
             _ => return None,
         },
         b't' => match hash {
@@ -446,12 +529,14 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x6572_7574_616e_6769_5374_7865 => Property::TextSignature,
             0x0064_4964_6165_7268 => Property::ThreadId,
             0x0065_6e6f_7a65_6d69 => Property::Timezone,
+            0x0065_6e6f_5a65_6d69 => Property::Timezone,
             0x6f => Property::To,
             0x0065_7461_446f => Property::ToDate,
             0x736c_6961_6d45_6c61_746f => Property::TotalEmails,
             0x0073_6461_6572_6854_6c61_746f => Property::TotalThreads,
             0x0065_7079 => Property::Type,
             0x7365_7079 => Property::Types,
+            0x656c_7469 => Property::Title,
             _ => return None,
         },
         b'u' => match hash {
@@ -459,6 +544,9 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0073_6c69_616d_4564_6165_726e => Property::UnreadEmails,
             0x7364_6165_7268_5464_6165_726e => Property::UnreadThreads,
             0x6c72 => Property::Url,
+            0x6469 => Property::Uid,
+            0x6465_7461_6470 => Property::Updated,
+            0x6c69_746e => Property::Until,
             _ => return None,
         },
         b'v' => match hash {
@@ -469,6 +557,14 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
     })
 }
 
+// Properties with names too long to be hashed into a single u128
+fn parse_long_property(value: &[u8]) -> Option<Property> {
+    match value {
+        b"includeOriginalMessage" => Some(Property::IncludeOriginalMessage),
+        _ => None,
+    }
+}
+
 fn parse_header_property(parser: &mut Parser) -> crate::parser::Result<Property> {
     let hdr_start_pos = parser.pos;
     let mut has_next = false;
@@ -612,6 +708,7 @@ impl JsonObjectParser for ObjectProperty {
         Ok(ObjectProperty(match first_char {
             b'a' => match hash {
                 0x7365_7373_6572_6464 => Property::Addresses,
+                0x0065_646f_4d6e_6f69_7463 => Property::ActionMode,
                 0x0068_7475 => Property::Auth,
                 _ => parser.invalid_property()?,
             },
@@ -660,6 +757,7 @@ impl JsonObjectParser for ObjectProperty {
                 0x656d_616e_6552_7961 => Property::MayRename,
                 0x6574_656c_6544_7961 => Property::MayDelete,
                 0x7469_6d62_7553_7961 => Property::MaySubmit,
+                0x6449_786f_626c_6961_4d6f_5465_766f => Property::MoveToMailboxId,
                 _ => parser.invalid_property()?,
             },
             b'n' => match hash {
@@ -683,6 +781,7 @@ impl JsonObjectParser for ObjectProperty {
                 0x796c_7065_5270_746d => Property::SmtpReply,
                 0x7469_6d69_4c74_666f => Property::SoftLimit,
                 0x6570_6f63 => Property::Scope,
+                0x6564_6f4d_676e_6964_6e65 => Property::SendingMode,
                 _ => parser.invalid_property()?,
             },
             b't' => match hash {
@@ -691,6 +790,7 @@ impl JsonObjectParser for ObjectProperty {
             },
             b'u' => match hash {
                 0x0064_6573 => Property::Used,
+                0x6c69_746e => Property::Until,
                 _ => parser.invalid_property()?,
             },
             b'v' => match hash {
@@ -709,10 +809,9 @@ impl JsonObjectParser for ObjectProperty {
 impl<'x> Parser<'x> {
     fn invalid_property(&mut self) -> crate::parser::Result<Property> {
         if self.is_eof || self.skip_string() {
-            Ok(Property::_T(
-                String::from_utf8_lossy(self.bytes[self.pos_marker..self.pos - 1].as_ref())
-                    .into_owned(),
-            ))
+            let property = &self.bytes[self.pos_marker..self.pos - 1];
+            Ok(parse_long_property(property)
+                .unwrap_or_else(|| Property::_T(String::from_utf8_lossy(property).into_owned())))
         } else {
             Err(self.error_unterminated())
         }
@@ -732,7 +831,8 @@ impl Property {
                         hash |= (ch as u128) << shift;
                         shift += 8;
                     } else {
-                        return Property::_T(value.to_string());
+                        return parse_long_property(value.as_bytes())
+                            .unwrap_or_else(|| Property::_T(value.to_string()));
                     }
                 } else {
                     first_char = ch;
@@ -835,7 +935,7 @@ impl Display for Property {
             Property::TextBody => write!(f, "textBody"),
             Property::TextSignature => write!(f, "textSignature"),
             Property::ThreadId => write!(f, "threadId"),
-            Property::Timezone => write!(f, "timezone"),
+            Property::Timezone => write!(f, "timeZone"),
             Property::To => write!(f, "to"),
             Property::ToDate => write!(f, "toDate"),
             Property::TotalEmails => write!(f, "totalEmails"),
@@ -881,6 +981,48 @@ impl Display for Property {
             Property::Used => write!(f, "used"),
             Property::HardLimit => write!(f, "hardLimit"),
             Property::Scope => write!(f, "scope"),
+            Property::ForEmailId => write!(f, "forEmailId"),
+            Property::IncludeOriginalMessage => write!(f, "includeOriginalMessage"),
+            Property::ReportingUA => write!(f, "reportingUA"),
+            Property::MdnGateway => write!(f, "mdnGateway"),
+            Property::OriginalRecipient => write!(f, "originalRecipient"),
+            Property::FinalRecipient => write!(f, "finalRecipient"),
+            Property::OriginalMessageId => write!(f, "originalMessageId"),
+            Property::Error => write!(f, "error"),
+            Property::ExtensionFields => write!(f, "extensionFields"),
+            Property::ActionMode => write!(f, "actionMode"),
+            Property::SendingMode => write!(f, "sendingMode"),
+            Property::Accounts => write!(f, "accounts"),
+            Property::Created => write!(f, "created"),
+            Property::ChangedBy => write!(f, "changedBy"),
+            Property::ObjectType => write!(f, "objectType"),
+            Property::ObjectAccountId => write!(f, "objectAccountId"),
+            Property::ObjectId => write!(f, "objectId"),
+            Property::OldRights => write!(f, "oldRights"),
+            Property::NewRights => write!(f, "newRights"),
+            Property::PrincipalId => write!(f, "principalId"),
+            Property::CalendarIds => write!(f, "calendarIds"),
+            Property::Uid => write!(f, "uid"),
+            Property::Title => write!(f, "title"),
+            Property::Start => write!(f, "start"),
+            Property::Duration => write!(f, "duration"),
+            Property::ShowWithoutTime => write!(f, "showWithoutTime"),
+            Property::Status => write!(f, "status"),
+            Property::RecurrenceRules => write!(f, "recurrenceRules"),
+            Property::Participants => write!(f, "participants"),
+            Property::Sequence => write!(f, "sequence"),
+            Property::Updated => write!(f, "updated"),
+            Property::Locations => write!(f, "locations"),
+            Property::Color => write!(f, "color"),
+            Property::IsVisible => write!(f, "isVisible"),
+            Property::IsDefault => write!(f, "isDefault"),
+            Property::CalendarEventId => write!(f, "calendarEventId"),
+            Property::Event => write!(f, "event"),
+            Property::Snoozed => write!(f, "snoozed"),
+            Property::Until => write!(f, "until"),
+            Property::MoveToMailboxId => write!(f, "moveToMailboxId"),
+            Property::Domain => write!(f, "domain"),
+            Property::FromTemplate => write!(f, "fromTemplate"),
             Property::WarnLimit => write!(f, "warnLimit"),
             Property::SoftLimit => write!(f, "softLimit"),
             Property::_T(s) => write!(f, "{s}"),
@@ -1063,6 +1205,48 @@ impl From<&Property> for u8 {
             Property::WarnLimit => 101,
             Property::SoftLimit => 102,
             Property::Scope => 103,
+            Property::ForEmailId => 104,
+            Property::IncludeOriginalMessage => 105,
+            Property::ReportingUA => 106,
+            Property::MdnGateway => 107,
+            Property::OriginalRecipient => 108,
+            Property::FinalRecipient => 109,
+            Property::OriginalMessageId => 110,
+            Property::Error => 111,
+            Property::ExtensionFields => 112,
+            Property::ActionMode => 113,
+            Property::SendingMode => 114,
+            Property::Accounts => 115,
+            Property::Created => 116,
+            Property::ChangedBy => 117,
+            Property::ObjectType => 118,
+            Property::ObjectAccountId => 119,
+            Property::ObjectId => 120,
+            Property::OldRights => 121,
+            Property::NewRights => 122,
+            Property::PrincipalId => 123,
+            Property::CalendarIds => 124,
+            Property::Uid => 125,
+            Property::Title => 126,
+            Property::Start => 127,
+            Property::Duration => 128,
+            Property::ShowWithoutTime => 129,
+            Property::Status => 130,
+            Property::RecurrenceRules => 131,
+            Property::Participants => 132,
+            Property::Sequence => 133,
+            Property::Updated => 134,
+            Property::Locations => 135,
+            Property::Color => 136,
+            Property::IsVisible => 137,
+            Property::IsDefault => 138,
+            Property::CalendarEventId => 139,
+            Property::Event => 140,
+            Property::Snoozed => 141,
+            Property::Until => 142,
+            Property::MoveToMailboxId => 143,
+            Property::Domain => 144,
+            Property::FromTemplate => 145,
             Property::Digest(_) | Property::Data(_) => unreachable!("invalid property"),
         }
     }
@@ -1205,6 +1389,48 @@ impl SerializeInto for Property {
             Property::WarnLimit => 101,
             Property::SoftLimit => 102,
             Property::Scope => 103,
+            Property::ForEmailId => 104,
+            Property::IncludeOriginalMessage => 105,
+            Property::ReportingUA => 106,
+            Property::MdnGateway => 107,
+            Property::OriginalRecipient => 108,
+            Property::FinalRecipient => 109,
+            Property::OriginalMessageId => 110,
+            Property::Error => 111,
+            Property::ExtensionFields => 112,
+            Property::ActionMode => 113,
+            Property::SendingMode => 114,
+            Property::Accounts => 115,
+            Property::Created => 116,
+            Property::ChangedBy => 117,
+            Property::ObjectType => 118,
+            Property::ObjectAccountId => 119,
+            Property::ObjectId => 120,
+            Property::OldRights => 121,
+            Property::NewRights => 122,
+            Property::PrincipalId => 123,
+            Property::CalendarIds => 124,
+            Property::Uid => 125,
+            Property::Title => 126,
+            Property::Start => 127,
+            Property::Duration => 128,
+            Property::ShowWithoutTime => 129,
+            Property::Status => 130,
+            Property::RecurrenceRules => 131,
+            Property::Participants => 132,
+            Property::Sequence => 133,
+            Property::Updated => 134,
+            Property::Locations => 135,
+            Property::Color => 136,
+            Property::IsVisible => 137,
+            Property::IsDefault => 138,
+            Property::CalendarEventId => 139,
+            Property::Event => 140,
+            Property::Snoozed => 141,
+            Property::Until => 142,
+            Property::MoveToMailboxId => 143,
+            Property::Domain => 144,
+            Property::FromTemplate => 145,
             Property::Digest(_) | Property::Data(_) => {
                 unreachable!("Property::Digest and Property::Data are not serializable")
             }
@@ -1323,6 +1549,48 @@ impl DeserializeFrom for Property {
             101 => Some(Property::WarnLimit),
             102 => Some(Property::SoftLimit),
             103 => Some(Property::Scope),
+            104 => Some(Property::ForEmailId),
+            105 => Some(Property::IncludeOriginalMessage),
+            106 => Some(Property::ReportingUA),
+            107 => Some(Property::MdnGateway),
+            108 => Some(Property::OriginalRecipient),
+            109 => Some(Property::FinalRecipient),
+            110 => Some(Property::OriginalMessageId),
+            111 => Some(Property::Error),
+            112 => Some(Property::ExtensionFields),
+            113 => Some(Property::ActionMode),
+            114 => Some(Property::SendingMode),
+            115 => Some(Property::Accounts),
+            116 => Some(Property::Created),
+            117 => Some(Property::ChangedBy),
+            118 => Some(Property::ObjectType),
+            119 => Some(Property::ObjectAccountId),
+            120 => Some(Property::ObjectId),
+            121 => Some(Property::OldRights),
+            122 => Some(Property::NewRights),
+            123 => Some(Property::PrincipalId),
+            124 => Some(Property::CalendarIds),
+            125 => Some(Property::Uid),
+            126 => Some(Property::Title),
+            127 => Some(Property::Start),
+            128 => Some(Property::Duration),
+            129 => Some(Property::ShowWithoutTime),
+            130 => Some(Property::Status),
+            131 => Some(Property::RecurrenceRules),
+            132 => Some(Property::Participants),
+            133 => Some(Property::Sequence),
+            134 => Some(Property::Updated),
+            135 => Some(Property::Locations),
+            136 => Some(Property::Color),
+            137 => Some(Property::IsVisible),
+            138 => Some(Property::IsDefault),
+            139 => Some(Property::CalendarEventId),
+            140 => Some(Property::Event),
+            141 => Some(Property::Snoozed),
+            142 => Some(Property::Until),
+            143 => Some(Property::MoveToMailboxId),
+            144 => Some(Property::Domain),
+            145 => Some(Property::FromTemplate),
             _ => None,
         }
     }
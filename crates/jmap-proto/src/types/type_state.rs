@@ -41,7 +41,17 @@ pub enum DataType {
     Quota = 11,
     #[serde(rename = "SieveScript")]
     SieveScript = 12,
-    None = 13,
+    #[serde(rename = "ShareNotification")]
+    ShareNotification = 13,
+    #[serde(rename = "Calendar")]
+    Calendar = 14,
+    #[serde(rename = "CalendarEvent")]
+    CalendarEvent = 15,
+    #[serde(rename = "CalendarEventNotification")]
+    CalendarEventNotification = 16,
+    #[serde(rename = "Template")]
+    Template = 17,
+    None = 18,
 }
 
 impl BitmapItem for DataType {
@@ -70,6 +80,11 @@ impl From<u64> for DataType {
             10 => DataType::Mdn,
             11 => DataType::Quota,
             12 => DataType::SieveScript,
+            13 => DataType::ShareNotification,
+            14 => DataType::Calendar,
+            15 => DataType::CalendarEvent,
+            16 => DataType::CalendarEventNotification,
+            17 => DataType::Template,
             _ => {
                 debug_assert!(false, "Invalid type_state value: {}", value);
                 DataType::None
@@ -90,18 +105,28 @@ impl JsonObjectParser for DataType {
         Self: Sized,
     {
         let mut hash = 0;
+        let mut hash_ext = 0;
         let mut shift = 0;
 
         while let Some(ch) = parser.next_unescaped()? {
             if shift < 128 {
                 hash |= (ch as u128) << shift;
-                shift += 8;
+            } else if shift < 256 {
+                hash_ext |= (ch as u128) << (shift - 128);
             } else {
                 return Err(parser.error_value());
             }
+            shift += 8;
         }
 
         match hash {
+            0x6f69_7461_6369_6669_746f_4e65_7261_6853 if hash_ext == 0x006e => {
+                Ok(DataType::ShareNotification)
+            }
+            0x746f_4e74_6e65_7645_7261_646e_656c_6143 if hash_ext == 0x006e_6f69_7461_6369_6669 => {
+                Ok(DataType::CalendarEventNotification)
+            }
+            _ if hash_ext != 0 => Err(parser.error_value()),
             0x006c_6961_6d45 => Ok(DataType::Email),
             0x0079_7265_7669_6c65_446c_6961_6d45 => Ok(DataType::EmailDelivery),
             0x006e_6f69_7373_696d_6275_536c_6961_6d45 => Ok(DataType::EmailSubmission),
@@ -115,6 +140,9 @@ impl JsonObjectParser for DataType {
             0x004e_444d => Ok(DataType::Mdn),
             0x0061_746f_7551 => Ok(DataType::Quota),
             0x0074_7069_7263_5365_7665_6953 => Ok(DataType::SieveScript),
+            0x7261_646e_656c_6143 => Ok(DataType::Calendar),
+            0x0074_6e65_7645_7261_646e_656c_6143 => Ok(DataType::CalendarEvent),
+            0x6574_616c_706d_6554 => Ok(DataType::Template),
             _ => Err(parser.error_value()),
         }
     }
@@ -125,18 +153,28 @@ impl TryFrom<&str> for DataType {
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         let mut hash = 0;
+        let mut hash_ext = 0;
         let mut shift = 0;
 
         for &ch in value.as_bytes() {
             if shift < 128 {
                 hash |= (ch as u128) << shift;
-                shift += 8;
+            } else if shift < 256 {
+                hash_ext |= (ch as u128) << (shift - 128);
             } else {
                 return Err(());
             }
+            shift += 8;
         }
 
         match hash {
+            0x6f69_7461_6369_6669_746f_4e65_7261_6853 if hash_ext == 0x006e => {
+                Ok(DataType::ShareNotification)
+            }
+            0x746f_4e74_6e65_7645_7261_646e_656c_6143 if hash_ext == 0x006e_6f69_7461_6369_6669 => {
+                Ok(DataType::CalendarEventNotification)
+            }
+            _ if hash_ext != 0 => Err(()),
             0x006c_6961_6d45 => Ok(DataType::Email),
             0x0079_7265_7669_6c65_446c_6961_6d45 => Ok(DataType::EmailDelivery),
             0x006e_6f69_7373_696d_6275_536c_6961_6d45 => Ok(DataType::EmailSubmission),
@@ -150,6 +188,9 @@ impl TryFrom<&str> for DataType {
             0x004e_444d => Ok(DataType::Mdn),
             0x0061_746f_7551 => Ok(DataType::Quota),
             0x0074_7069_7263_5365_7665_6953 => Ok(DataType::SieveScript),
+            0x7261_646e_656c_6143 => Ok(DataType::Calendar),
+            0x0074_6e65_7645_7261_646e_656c_6143 => Ok(DataType::CalendarEvent),
+            0x6574_616c_706d_6554 => Ok(DataType::Template),
             _ => Err(()),
         }
     }
@@ -171,6 +212,11 @@ impl DataType {
             DataType::Mdn => "MDN",
             DataType::Quota => "Quota",
             DataType::SieveScript => "SieveScript",
+            DataType::ShareNotification => "ShareNotification",
+            DataType::Calendar => "Calendar",
+            DataType::CalendarEvent => "CalendarEvent",
+            DataType::CalendarEventNotification => "CalendarEventNotification",
+            DataType::Template => "Template",
             DataType::None => "",
         }
     }
@@ -204,6 +250,11 @@ impl DeserializeFrom for DataType {
             10 => Some(DataType::Mdn),
             11 => Some(DataType::Quota),
             12 => Some(DataType::SieveScript),
+            13 => Some(DataType::ShareNotification),
+            14 => Some(DataType::Calendar),
+            15 => Some(DataType::CalendarEvent),
+            16 => Some(DataType::CalendarEventNotification),
+            17 => Some(DataType::Template),
             _ => None,
         }
     }
@@ -70,24 +70,14 @@ impl JMAP {
             }
         }
 
-        let mut ping = if ping > 0 {
-            #[cfg(not(feature = "test_mode"))]
-            let interval = std::cmp::max(ping, 30) * 1000;
-            #[cfg(feature = "test_mode")]
-            let interval = ping * 1000;
-
-            Ping {
-                interval: Duration::from_millis(interval as u64),
-                last_ping: Instant::now() - Duration::from_millis(interval as u64),
-                payload: Bytes::from(format!(
-                    "event: ping\ndata: {{\"interval\": {}}}\n\n",
-                    interval
-                )),
-            }
-            .into()
-        } else {
-            None
-        };
+        let mut ping = ping_interval(ping).map(|interval| Ping {
+            interval,
+            last_ping: Instant::now() - interval,
+            payload: Bytes::from(format!(
+                "event: ping\ndata: {{\"interval\": {}}}\n\n",
+                interval.as_millis()
+            )),
+        });
         let mut response = StateChangeResponse::new();
         let throttle = self.core.jmap.event_source_throttle;
 
@@ -162,3 +152,15 @@ impl JMAP {
             .unwrap()
     }
 }
+
+// Converts the requested ping interval in seconds, enforcing a minimum of 30 seconds
+pub(crate) fn ping_interval(ping: u32) -> Option<Duration> {
+    if ping > 0 {
+        #[cfg(not(feature = "test_mode"))]
+        let ping = std::cmp::max(ping, 30);
+
+        Some(Duration::from_secs(ping as u64))
+    } else {
+        None
+    }
+}
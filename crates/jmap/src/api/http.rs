@@ -224,6 +224,19 @@ impl JMAP {
                         Err(err) => err.into_http_response(),
                     };
                 }
+                ("openid-configuration", &Method::GET) => {
+                    // Newer endpoints are boxed to keep the stack usage of this function low
+                    return Box::pin(async {
+                        // Limit anonymous requests
+                        match self.is_anonymous_allowed(&session.remote_ip).await {
+                            Ok(_) => {
+                                self.handle_openid_metadata(session.resolve_url(&self.core).await)
+                            }
+                            Err(err) => err.into_http_response(),
+                        }
+                    })
+                    .await;
+                }
                 ("acme-challenge", &Method::GET) if self.core.has_acme_http_providers() => {
                     if let Some(token) = path.next() {
                         return match self
@@ -280,11 +293,39 @@ impl JMAP {
                     }
                 }
                 ("token", &Method::POST) => {
+                    return Box::pin(async {
+                        match self.is_anonymous_allowed(&session.remote_ip).await {
+                            Ok(_) => {
+                                self.handle_token_request(
+                                    &mut req,
+                                    session.resolve_url(&self.core).await,
+                                )
+                                .await
+                            }
+                            Err(err) => err.into_http_response(),
+                        }
+                    })
+                    .await;
+                }
+                ("jwks.json", &Method::GET) => {
                     return match self.is_anonymous_allowed(&session.remote_ip).await {
-                        Ok(_) => self.handle_token_request(&mut req).await,
+                        Ok(_) => self.handle_jwks_request(),
                         Err(err) => err.into_http_response(),
                     }
                 }
+                ("userinfo", &Method::GET | &Method::POST) => {
+                    return Box::pin(async {
+                        match self.authenticate_headers(&req, session.remote_ip).await {
+                            Ok(Some((_, access_token))) => {
+                                self.handle_userinfo_request(&req, access_token.primary_id())
+                                    .await
+                            }
+                            Ok(None) => RequestError::unauthorized().into_http_response(),
+                            Err(err) => err.into_http_response(),
+                        }
+                    })
+                    .await;
+                }
                 (_, &Method::OPTIONS) => {
                     return StatusCode::NO_CONTENT.into_http_response();
                 }
@@ -323,6 +364,23 @@ impl JMAP {
                         .await;
                 }
             }
+            "scim" => {
+                if path.next().unwrap_or_default() == "v2" {
+                    return Box::pin(async {
+                        let body = fetch_body(&mut req, 1024 * 1024).await;
+                        self.handle_scim_request(&req, body, &session.resolve_url(&self.core).await)
+                            .await
+                    })
+                    .await;
+                }
+            }
+            "list" => {
+                if path.next().unwrap_or_default() == "unsubscribe"
+                    && matches!(*req.method(), Method::GET | Method::POST)
+                {
+                    return Box::pin(self.handle_list_unsubscribe(&req)).await;
+                }
+            }
             "robots.txt" => {
                 return Resource {
                     content_type: "text/plain",
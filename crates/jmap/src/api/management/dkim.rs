@@ -6,7 +6,10 @@
 
 use std::str::FromStr;
 
-use common::config::smtp::auth::simple_pem_parse;
+use common::{
+    config::smtp::auth::{simple_pem_parse, DkimRotation},
+    manager::config::ConfigManager,
+};
 use hyper::Method;
 use jmap_proto::error::request::RequestError;
 use mail_auth::{
@@ -19,7 +22,7 @@ use pkcs8::Document;
 use rsa::pkcs1::DecodeRsaPublicKey;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use store::write::now;
+use store::{ahash::AHashMap, write::now};
 
 use crate::{
     api::{
@@ -111,19 +114,9 @@ impl JMAP {
         let id = request
             .id
             .unwrap_or_else(|| format!("{algo_str}-{}", request.domain));
-        let selector = request.selector.unwrap_or_else(|| {
-            let dt = DateTime::from_timestamp(now() as i64);
-            format!(
-                "{:04}{:02}{}",
-                dt.year,
-                dt.month,
-                if Algorithm::Rsa == request.algorithm {
-                    "r"
-                } else {
-                    "e"
-                }
-            )
-        });
+        let selector = request
+            .selector
+            .unwrap_or_else(|| default_selector(request.algorithm, false));
 
         // Make sure the signature does not exist already
         match self
@@ -165,33 +158,10 @@ impl JMAP {
         selector: impl Into<String>,
     ) -> store::Result<()> {
         let id = id.as_ref();
-        let (algorithm, pk_type) = match algo {
-            Algorithm::Rsa => ("rsa-sha256", "RSA PRIVATE KEY"),
-            Algorithm::Ed25519 => ("ed25519-sha256", "PRIVATE KEY"),
+        let algorithm = match algo {
+            Algorithm::Rsa => "rsa-sha256",
+            Algorithm::Ed25519 => "ed25519-sha256",
         };
-        let mut pk = format!("-----BEGIN {pk_type}-----\n").into_bytes();
-        let mut lf_count = 65;
-        for ch in base64_encode(
-            match algo {
-                Algorithm::Rsa => DkimKeyPair::generate_rsa(2048),
-                Algorithm::Ed25519 => DkimKeyPair::generate_ed25519(),
-            }
-            .map_err(|err| store::Error::InternalError(err.to_string()))?
-            .private_key(),
-        )
-        .unwrap_or_default()
-        {
-            pk.push(ch);
-            lf_count -= 1;
-            if lf_count == 0 {
-                pk.push(b'\n');
-                lf_count = 65;
-            }
-        }
-        if lf_count != 65 {
-            pk.push(b'\n');
-        }
-        pk.extend_from_slice(format!("-----END {pk_type}-----\n").as_bytes());
 
         self.core
             .storage
@@ -199,7 +169,7 @@ impl JMAP {
             .set([
                 (
                     format!("signature.{id}.private-key"),
-                    String::from_utf8(pk).unwrap(),
+                    generate_dkim_private_key(algo)?,
                 ),
                 (format!("signature.{id}.domain"), domain.into()),
                 (format!("signature.{id}.selector"), selector.into()),
@@ -222,6 +192,243 @@ impl JMAP {
     }
 }
 
+impl JMAP {
+    pub async fn rotate_dkim_keys(&self) {
+        let rotation = if let Some(rotation) = &self.core.smtp.mail_auth.dkim.rotation {
+            rotation.clone()
+        } else {
+            return;
+        };
+
+        // Reload signers and published records
+        if rotate_dkim_signatures(&self.core.storage.config, &rotation, now()).await {
+            match self.core.reload().await {
+                Ok(result) => {
+                    if let Some(core) = result.new_core {
+                        self.shared_core.store(core.into());
+                        self.inner.increment_config_version();
+                    }
+                }
+                Err(err) => {
+                    tracing::error!(
+                        context = "dkim",
+                        event = "error",
+                        "Failed to reload configuration: {}",
+                        err
+                    );
+                }
+            }
+        }
+    }
+}
+
+// Advances the rotation state of each stored DKIM signature, returns true if
+// any signing key or published selector changed.
+pub async fn rotate_dkim_signatures(
+    config: &ConfigManager,
+    rotation: &DkimRotation,
+    now: u64,
+) -> bool {
+    let keys = match config.list("signature.", true).await {
+        Ok(keys) => keys.into_iter().collect::<AHashMap<_, _>>(),
+        Err(err) => {
+            tracing::error!(
+                context = "dkim",
+                event = "error",
+                "Failed to list DKIM signatures: {}",
+                err
+            );
+            return false;
+        }
+    };
+
+    let mut has_changes = false;
+    for id in keys.keys().filter_map(|key| {
+        key.strip_suffix(".private-key")
+            .filter(|id| !id.ends_with(".rotation"))
+    }) {
+        let key = |name: &str| keys.get(&format!("{id}.{name}"));
+        let (algo, pk, selector) = match (
+            key("algorithm").and_then(|algo| algo.parse::<Algorithm>().ok()),
+            key("private-key"),
+            key("selector"),
+        ) {
+            (Some(algo), Some(pk), Some(selector)) => (algo, pk, selector),
+            _ => continue,
+        };
+        let due = key("rotation.due").and_then(|due| due.parse::<u64>().ok());
+        let state = key("rotation.state").map(|state| state.as_str());
+
+        let result = match (state, due) {
+            (None, None) => {
+                // Schedule the first rotation
+                config
+                    .set([(
+                        format!("signature.{id}.rotation.due"),
+                        (now + rotation.interval.as_secs()).to_string(),
+                    )])
+                    .await
+                    .map(|_| false)
+            }
+            (None, Some(due)) if due <= now => {
+                // Generate a new key and publish its selector
+                let mut new_selector = default_selector(algo, false);
+                if &new_selector == selector {
+                    new_selector = default_selector(algo, true);
+                }
+                tracing::info!(
+                    context = "dkim",
+                    event = "rotation-staged",
+                    signature = id,
+                    selector = new_selector,
+                    "Staged new DKIM key for rotation."
+                );
+
+                match generate_dkim_private_key(algo) {
+                    Ok(new_pk) => config
+                        .set([
+                            (format!("signature.{id}.rotation.private-key"), new_pk),
+                            (format!("signature.{id}.rotation.selector"), new_selector),
+                            (
+                                format!("signature.{id}.rotation.state"),
+                                "staged".to_string(),
+                            ),
+                            (
+                                format!("signature.{id}.rotation.due"),
+                                (now + rotation.grace_period.as_secs()).to_string(),
+                            ),
+                        ])
+                        .await
+                        .map(|_| true),
+                    Err(err) => Err(err),
+                }
+            }
+            (Some("staged"), Some(due)) if due <= now => {
+                // Start signing with the new key, keep the old selector published
+                match (key("rotation.private-key"), key("rotation.selector")) {
+                    (Some(new_pk), Some(new_selector)) => {
+                        tracing::info!(
+                            context = "dkim",
+                            event = "rotation-switched",
+                            signature = id,
+                            selector = new_selector,
+                            "Switched DKIM signing to new key."
+                        );
+
+                        config
+                            .set([
+                                (format!("signature.{id}.private-key"), new_pk.clone()),
+                                (format!("signature.{id}.selector"), new_selector.clone()),
+                                (format!("signature.{id}.rotation.private-key"), pk.clone()),
+                                (
+                                    format!("signature.{id}.rotation.selector"),
+                                    selector.clone(),
+                                ),
+                                (
+                                    format!("signature.{id}.rotation.state"),
+                                    "switched".to_string(),
+                                ),
+                                (
+                                    format!("signature.{id}.rotation.due"),
+                                    (now + rotation.retire_after.as_secs()).to_string(),
+                                ),
+                            ])
+                            .await
+                            .map(|_| true)
+                    }
+                    _ => retire_dkim_key(config, id, now + rotation.interval.as_secs())
+                        .await
+                        .map(|_| true),
+                }
+            }
+            (Some(_), Some(due)) if due <= now => {
+                // Remove the old selector
+                tracing::info!(
+                    context = "dkim",
+                    event = "rotation-retired",
+                    signature = id,
+                    selector = key("rotation.selector").map(|s| s.as_str()),
+                    "Retired previous DKIM key."
+                );
+                retire_dkim_key(config, id, now + rotation.interval.as_secs())
+                    .await
+                    .map(|_| true)
+            }
+            _ => Ok(false),
+        };
+
+        match result {
+            Ok(changed) => {
+                has_changes |= changed;
+            }
+            Err(err) => {
+                tracing::error!(
+                    context = "dkim",
+                    event = "error",
+                    signature = id,
+                    "Failed to rotate DKIM key: {}",
+                    err
+                );
+            }
+        }
+    }
+
+    has_changes
+}
+
+async fn retire_dkim_key(config: &ConfigManager, id: &str, next_due: u64) -> store::Result<()> {
+    for key in ["private-key", "selector", "state"] {
+        config
+            .clear(format!("signature.{id}.rotation.{key}"))
+            .await?;
+    }
+    config
+        .set([(format!("signature.{id}.rotation.due"), next_due.to_string())])
+        .await
+}
+
+fn default_selector(algo: Algorithm, include_day: bool) -> String {
+    let dt = DateTime::from_timestamp(now() as i64);
+    let algo = if Algorithm::Rsa == algo { "r" } else { "e" };
+    if !include_day {
+        format!("{:04}{:02}{}", dt.year, dt.month, algo)
+    } else {
+        format!("{:04}{:02}{:02}{}", dt.year, dt.month, dt.day, algo)
+    }
+}
+
+fn generate_dkim_private_key(algo: Algorithm) -> store::Result<String> {
+    let pk_type = match algo {
+        Algorithm::Rsa => "RSA PRIVATE KEY",
+        Algorithm::Ed25519 => "PRIVATE KEY",
+    };
+    let mut pk = format!("-----BEGIN {pk_type}-----\n").into_bytes();
+    let mut lf_count = 65;
+    for ch in base64_encode(
+        match algo {
+            Algorithm::Rsa => DkimKeyPair::generate_rsa(2048),
+            Algorithm::Ed25519 => DkimKeyPair::generate_ed25519(),
+        }
+        .map_err(|err| store::Error::InternalError(err.to_string()))?
+        .private_key(),
+    )
+    .unwrap_or_default()
+    {
+        pk.push(ch);
+        lf_count -= 1;
+        if lf_count == 0 {
+            pk.push(b'\n');
+            lf_count = 65;
+        }
+    }
+    if lf_count != 65 {
+        pk.push(b'\n');
+    }
+    pk.extend_from_slice(format!("-----END {pk_type}-----\n").as_bytes());
+
+    Ok(String::from_utf8(pk).unwrap())
+}
+
 pub fn obtain_dkim_public_key(algo: Algorithm, pk: &str) -> Result<String, &'static str> {
     match simple_pem_parse(pk) {
         Some(der) => match algo {
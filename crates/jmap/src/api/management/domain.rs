@@ -164,13 +164,16 @@ impl JMAP {
             });
         }
 
-        // Process DKIM keys
-        for signature_id in signature_ids {
+        // Process DKIM keys, including any keys staged or retired by a rotation
+        for (signature_id, prefix) in signature_ids
+            .iter()
+            .flat_map(|id| [(id, ""), (id, "rotation.")])
+        {
             if let (Some(algo), Some(pk), Some(selector)) = (
                 keys.get(&format!("{signature_id}.algorithm"))
                     .and_then(|algo| algo.parse::<Algorithm>().ok()),
-                keys.get(&format!("{signature_id}.private-key")),
-                keys.get(&format!("{signature_id}.selector")),
+                keys.get(&format!("{signature_id}.{prefix}private-key")),
+                keys.get(&format!("{signature_id}.{prefix}selector")),
             ) {
                 match obtain_dkim_public_key(algo, pk) {
                     Ok(public) => {
@@ -0,0 +1,419 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::config::smtp::list::{ListPolicy, ListSettings};
+use directory::{
+    backend::internal::{lookup::DirectoryStore, manage::ManageDirectory},
+    QueryBy, Type,
+};
+use hyper::{Method, StatusCode};
+use jmap_proto::error::request::RequestError;
+use mail_parser::DateTime;
+use serde_json::json;
+use smtp::queue::list::{HeldMessage, ListSubscription, SubscriptionStatus};
+use store::{
+    write::{Bincode, DirectoryClass, ReportClass, ValueClass},
+    ValueKey,
+};
+use utils::url_params::UrlParams;
+
+use crate::{
+    api::{http::ToHttpResponse, HtmlResponse, HttpRequest, HttpResponse, JsonResponse},
+    JMAP,
+};
+
+use super::{decode_path_element, queue::serialize_datetime, ManagementApiError};
+
+#[derive(Debug, serde::Serialize)]
+pub struct List {
+    pub id: String,
+    pub address: String,
+    pub name: String,
+    pub policy: &'static str,
+    pub moderators: Vec<String>,
+    pub subscribers: usize,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct Subscriber {
+    pub address: String,
+    pub status: SubscriptionStatus,
+    pub bounces: u32,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub updated: DateTime,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct Held {
+    pub id: String,
+    pub return_path: String,
+    pub from: String,
+    pub subject: String,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub created: DateTime,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub expires: DateTime,
+    pub size: usize,
+}
+
+impl JMAP {
+    pub async fn handle_manage_list(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+    ) -> HttpResponse {
+        match (
+            path.get(1).copied().map(decode_path_element),
+            path.get(2).copied(),
+            req.method(),
+        ) {
+            (None, None, &Method::GET) => {
+                let list_ids = match self.smtp.read_list_ids().await {
+                    Ok(list_ids) => list_ids,
+                    Err(err) => return err.into_http_response(),
+                };
+                let mut items = Vec::with_capacity(list_ids.len());
+                for list_id in list_ids {
+                    let (name, list) = match (
+                        self.core.storage.data.get_account_name(list_id).await,
+                        self.core.mailing_list_by_id(list_id).await,
+                    ) {
+                        (Ok(Some(name)), Ok(Some(list))) => (name, list),
+                        (Err(err), _) | (_, Err(err)) => return err.into_http_response(),
+                        _ => continue,
+                    };
+                    items.push(List {
+                        id: name,
+                        address: list.address.clone(),
+                        name: list.name.clone(),
+                        policy: match list.policy {
+                            ListPolicy::Open => "open",
+                            ListPolicy::MembersOnly => "members-only",
+                            ListPolicy::Moderated => "moderated",
+                            ListPolicy::AnnounceOnly => "announce-only",
+                        },
+                        moderators: list.moderators.clone(),
+                        subscribers: self
+                            .smtp
+                            .list_recipients(&list)
+                            .await
+                            .map_or(0, |r| r.len()),
+                    });
+                }
+                items.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+                let total = items.len();
+
+                JsonResponse::new(json!({
+                        "data": {
+                            "items": items,
+                            "total": total,
+                        },
+                }))
+                .into_http_response()
+            }
+            (Some(name), None, method) => {
+                // Lists are backed by directory principals of type list
+                let list_id = match self.core.storage.data.get_account_id(name.as_ref()).await {
+                    Ok(Some(list_id)) => list_id,
+                    Ok(None) => return RequestError::not_found().into_http_response(),
+                    Err(err) => return err.into_http_response(),
+                };
+                match self
+                    .core
+                    .storage
+                    .data
+                    .query(QueryBy::Id(list_id), false)
+                    .await
+                {
+                    Ok(Some(principal)) if principal.typ == Type::List => (),
+                    Ok(Some(_)) => {
+                        return ManagementApiError::Unsupported {
+                            details: format!("Principal {name:?} is not a mailing list.").into(),
+                        }
+                        .into_http_response()
+                    }
+                    Ok(None) => return RequestError::not_found().into_http_response(),
+                    Err(err) => return err.into_http_response(),
+                }
+
+                match *method {
+                    Method::GET => match self
+                        .core
+                        .storage
+                        .data
+                        .get_value::<Bincode<ListSettings>>(ValueKey::from(ValueClass::Directory(
+                            DirectoryClass::ListSettings(list_id),
+                        )))
+                        .await
+                    {
+                        Ok(Some(settings)) => JsonResponse::new(json!({
+                            "data": settings.inner,
+                        }))
+                        .into_http_response(),
+                        Ok(None) => RequestError::not_found().into_http_response(),
+                        Err(err) => err.into_http_response(),
+                    },
+                    Method::POST | Method::PUT => {
+                        let settings = match serde_json::from_slice::<ListSettings>(
+                            body.as_deref().unwrap_or_default(),
+                        ) {
+                            Ok(settings) => settings,
+                            Err(err) => return err.into_http_response(),
+                        };
+
+                        match self.smtp.set_list_settings(list_id, settings.into()).await {
+                            Ok(_) => JsonResponse::new(json!({
+                                "data": (),
+                            }))
+                            .into_http_response(),
+                            Err(err) => err.into_http_response(),
+                        }
+                    }
+                    Method::DELETE => match self.smtp.set_list_settings(list_id, None).await {
+                        Ok(_) => JsonResponse::new(json!({
+                            "data": (),
+                        }))
+                        .into_http_response(),
+                        Err(err) => err.into_http_response(),
+                    },
+                    _ => RequestError::not_found().into_http_response(),
+                }
+            }
+            (Some(name), Some(action), method) => {
+                let list = match self.core.storage.data.get_account_id(name.as_ref()).await {
+                    Ok(Some(list_id)) => self.core.mailing_list_by_id(list_id).await,
+                    Ok(None) => Ok(None),
+                    Err(err) => Err(err),
+                };
+                let list = match list {
+                    Ok(Some(list)) => list,
+                    Ok(None) => return RequestError::not_found().into_http_response(),
+                    Err(err) => return err.into_http_response(),
+                };
+
+                match (
+                    action,
+                    path.get(3).copied().map(decode_path_element),
+                    method,
+                ) {
+                    ("subscribers", None, &Method::GET) => {
+                        match self.smtp.read_list_subscriptions(&list).await {
+                            Ok(subscriptions) => {
+                                let items = subscriptions
+                                    .into_iter()
+                                    .map(|(address, subscription)| Subscriber {
+                                        address,
+                                        status: subscription.status,
+                                        bounces: subscription.bounces,
+                                        updated: DateTime::from_timestamp(
+                                            subscription.updated as i64,
+                                        ),
+                                    })
+                                    .collect::<Vec<_>>();
+                                let total = items.len();
+
+                                JsonResponse::new(json!({
+                                        "data": {
+                                            "items": items,
+                                            "total": total,
+                                        },
+                                }))
+                                .into_http_response()
+                            }
+                            Err(err) => err.into_http_response(),
+                        }
+                    }
+                    ("subscribers", None, &Method::POST) => {
+                        let addresses = match serde_json::from_slice::<Vec<String>>(
+                            body.as_deref().unwrap_or_default(),
+                        ) {
+                            Ok(addresses) => addresses,
+                            Err(err) => return err.into_http_response(),
+                        };
+
+                        for address in addresses {
+                            let address = address.trim().to_lowercase();
+                            if !address.contains('@') {
+                                continue;
+                            }
+                            if let Err(err) = self
+                                .smtp
+                                .set_list_subscription(
+                                    &list,
+                                    &address,
+                                    ListSubscription::new(SubscriptionStatus::Active).into(),
+                                )
+                                .await
+                            {
+                                return err.into_http_response();
+                            }
+                        }
+
+                        JsonResponse::new(json!({
+                            "data": (),
+                        }))
+                        .into_http_response()
+                    }
+                    ("subscribers", Some(address), &Method::DELETE) => {
+                        match self
+                            .smtp
+                            .set_list_subscription(&list, &address.to_lowercase(), None)
+                            .await
+                        {
+                            Ok(_) => JsonResponse::new(json!({
+                                "data": (),
+                            }))
+                            .into_http_response(),
+                            Err(err) => err.into_http_response(),
+                        }
+                    }
+                    ("held", None, &Method::GET) => {
+                        let mut items = Vec::new();
+                        let result = self
+                            .smtp
+                            .read_held_messages(|message| {
+                                if message.list_id == list.id {
+                                    items.push(Held::from(&message));
+                                }
+                                true
+                            })
+                            .await;
+
+                        match result {
+                            Ok(_) => {
+                                let total = items.len();
+                                JsonResponse::new(json!({
+                                        "data": {
+                                            "items": items,
+                                            "total": total,
+                                        },
+                                }))
+                                .into_http_response()
+                            }
+                            Err(err) => err.into_http_response(),
+                        }
+                    }
+                    ("held", Some(id), method @ (&Method::PATCH | &Method::DELETE)) => {
+                        let message = if let Some(id) = parse_held_id(id.as_ref()) {
+                            self.smtp.read_held_message(id).await
+                        } else {
+                            None
+                        }
+                        .filter(|message| message.list_id == list.id);
+                        let message = if let Some(message) = message {
+                            message
+                        } else {
+                            return RequestError::not_found().into_http_response();
+                        };
+
+                        JsonResponse::new(json!({
+                                "data": if method == Method::PATCH {
+                                    self.smtp.approve_held_message(message).await
+                                } else {
+                                    self.smtp.reject_held_message(message).await
+                                },
+                        }))
+                        .into_http_response()
+                    }
+                    _ => RequestError::not_found().into_http_response(),
+                }
+            }
+            _ => RequestError::not_found().into_http_response(),
+        }
+    }
+
+    pub async fn handle_list_unsubscribe(&self, req: &HttpRequest) -> HttpResponse {
+        let params = UrlParams::new(req.uri().query());
+        let list = match params.parse::<u32>("l") {
+            Some(list_id) => match self.core.mailing_list_by_id(list_id).await {
+                Ok(list) => list,
+                Err(err) => return err.into_http_response(),
+            },
+            None => None,
+        };
+        let (list, address) = match (list, params.get("a"), params.get("t")) {
+            (Some(list), Some(address), Some(token))
+                if self.smtp.list_token(&list, &["unsubscribe", address]) == token =>
+            {
+                (list, address.to_string())
+            }
+            _ => {
+                return HtmlResponse::with_status(
+                    StatusCode::BAD_REQUEST,
+                    "Invalid unsubscribe link.".to_string(),
+                )
+                .into_http_response();
+            }
+        };
+
+        match *req.method() {
+            // Ask for confirmation so that link scanners do not unsubscribe users
+            Method::GET => HtmlResponse::new(format!(
+                concat!(
+                    "<html><body><form method=\"post\">",
+                    "<p>Unsubscribe {} from {}?</p>",
+                    "<input type=\"hidden\" name=\"List-Unsubscribe\" value=\"One-Click\">",
+                    "<button type=\"submit\">Unsubscribe</button>",
+                    "</form></body></html>"
+                ),
+                html_escape(&address),
+                html_escape(&list.name),
+            ))
+            .into_http_response(),
+            // RFC 8058 one-click unsubscribe
+            Method::POST => {
+                let span =
+                    tracing::info_span!("list_unsubscribe", list = list.id, address = address);
+                match self.smtp.unsubscribe_list(&list, &address, &span).await {
+                    Ok(_) => HtmlResponse::new(format!(
+                        "<html><body><p>{} has been unsubscribed from {}.</p></body></html>",
+                        html_escape(&address),
+                        html_escape(&list.name),
+                    ))
+                    .into_http_response(),
+                    Err(err) => err.into_http_response(),
+                }
+            }
+            _ => RequestError::not_found().into_http_response(),
+        }
+    }
+}
+
+impl From<&HeldMessage> for Held {
+    fn from(message: &HeldMessage) -> Self {
+        Held {
+            id: format!("{}_{}", message.id, message.expires),
+            return_path: message.return_path.clone(),
+            from: message.from.clone(),
+            subject: message.subject.clone(),
+            created: DateTime::from_timestamp(message.created as i64),
+            expires: DateTime::from_timestamp(message.expires as i64),
+            size: message.size,
+        }
+    }
+}
+
+fn parse_held_id(id: &str) -> Option<ReportClass> {
+    let mut parts = id.split('_');
+    let id = parts.next()?.parse().ok()?;
+    let expires = parts.next()?.parse().ok()?;
+    Some(ReportClass::ListModeration { id, expires })
+}
+
+pub fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
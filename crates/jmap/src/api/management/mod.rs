@@ -8,8 +8,10 @@ pub mod dkim;
 pub mod domain;
 #[cfg(feature = "enterprise")]
 pub mod enterprise;
+pub mod list;
 pub mod log;
 pub mod principal;
+pub mod quarantine;
 pub mod queue;
 pub mod reload;
 pub mod report;
@@ -49,6 +51,12 @@ pub enum ManagementApiError {
     UnsupportedDirectoryOperation {
         class: Cow<'static, str>,
     },
+    PasswordPolicy {
+        details: Cow<'static, str>,
+    },
+    WebAuthnRequired {
+        options: serde_json::Value,
+    },
 }
 
 impl JMAP {
@@ -70,6 +78,7 @@ impl JMAP {
             "store" if is_superuser => self.handle_manage_store(req, path).await,
             "reload" if is_superuser => self.handle_manage_reload(req, path).await,
             "dkim" if is_superuser => self.handle_manage_dkim(req, path, body).await,
+            "list" if is_superuser => self.handle_manage_list(req, path, body).await,
             "update" if is_superuser => self.handle_manage_update(req, path).await,
             "logs" if is_superuser && req.method() == Method::GET => {
                 self.handle_view_logs(req).await
@@ -81,11 +90,19 @@ impl JMAP {
                 }
                 .into_http_response()
             }
+            // Regular users are limited to messages addressed to them
+            "quarantine" => self.handle_manage_quarantine(req, path, access_token).await,
             "oauth" => self.handle_oauth_api_request(access_token, body).await,
             "account" => match (path.get(1).copied().unwrap_or_default(), req.method()) {
                 ("crypto", &Method::POST) => self.handle_crypto_post(access_token, body).await,
                 ("crypto", &Method::GET) => self.handle_crypto_get(access_token).await,
                 ("auth", &Method::GET) => self.handle_account_auth_get(access_token).await,
+                ("login-history", &Method::GET) => {
+                    self.handle_account_login_history(access_token).await
+                }
+                ("auth", &Method::POST) if path.get(2).copied() == Some("webauthn") => {
+                    self.handle_account_webauthn_post(access_token).await
+                }
                 ("auth", &Method::POST) => {
                     self.handle_account_auth_post(req, access_token, body).await
                 }
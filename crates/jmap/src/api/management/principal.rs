@@ -7,13 +7,18 @@
 use std::sync::Arc;
 
 use directory::{
-    backend::internal::{
-        lookup::DirectoryStore, manage::ManageDirectory, PrincipalAction, PrincipalField,
-        PrincipalUpdate, PrincipalValue, SpecialSecrets,
+    backend::{
+        internal::{
+            lookup::DirectoryStore, manage::ManageDirectory, PrincipalAction, PrincipalField,
+            PrincipalUpdate, PrincipalValue, SpecialSecrets,
+        },
+        ldap::LdapDirectory,
     },
+    core::policy::PasswordStatus,
     DirectoryError, DirectoryInner, ManagementError, Principal, QueryBy, Type,
 };
 
+use common::auth::webauthn::{AssertionCredential, RegistrationCredential, WebAuthnCredential};
 use hyper::{header, Method, StatusCode};
 use jmap_proto::error::request::RequestError;
 use serde_json::json;
@@ -51,17 +56,54 @@ pub struct PrincipalResponse {
     pub members: Vec<String>,
     #[serde(default)]
     pub description: Option<String>,
+    #[serde(rename = "timeZone")]
+    #[serde(default)]
+    pub time_zone: Option<String>,
+    #[serde(rename = "undeleteRetention")]
+    #[serde(default)]
+    pub undelete_retention: u64,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "type")]
 #[serde(rename_all = "camelCase")]
 pub enum AccountAuthRequest {
-    SetPassword { password: String },
-    EnableOtpAuth { url: String },
-    DisableOtpAuth { url: Option<String> },
-    AddAppPassword { name: String, password: String },
-    RemoveAppPassword { name: String },
+    SetPassword {
+        password: String,
+    },
+    EnableOtpAuth {
+        url: String,
+    },
+    DisableOtpAuth {
+        url: Option<String>,
+    },
+    AddAppPassword {
+        name: String,
+        password: String,
+    },
+    RemoveAppPassword {
+        name: String,
+    },
+    AddWebAuthn {
+        name: String,
+        credential: RegistrationCredential,
+    },
+    RemoveWebAuthn {
+        name: String,
+    },
+}
+
+// Accounts with security keys enrolled need to include a WebAuthn
+// assertion along with their requests
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum AccountAuthRequests {
+    List(Vec<AccountAuthRequest>),
+    WithAssertion {
+        requests: Vec<AccountAuthRequest>,
+        #[serde(default)]
+        webauthn: Option<AssertionCredential>,
+    },
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -72,6 +114,8 @@ pub struct AccountAuthResponse {
     pub is_admin: bool,
     #[serde(rename = "appPasswords")]
     pub app_passwords: Vec<String>,
+    #[serde(rename = "webAuthnCredentials")]
+    pub webauthn_credentials: Vec<String>,
 }
 
 impl JMAP {
@@ -93,25 +137,39 @@ impl JMAP {
                     body.as_deref().unwrap_or_default(),
                 ) {
                     Ok(principal) => {
-                        match self
-                            .core
-                            .storage
-                            .data
-                            .create_account(
-                                Principal {
-                                    id: principal.id,
-                                    typ: principal.typ,
-                                    quota: principal.quota,
-                                    name: principal.name,
-                                    secrets: principal.secrets,
-                                    emails: principal.emails,
-                                    member_of: principal.member_of,
-                                    description: principal.description,
-                                },
-                                principal.members,
+                        let members = principal.members;
+                        let principal = Principal {
+                            id: principal.id,
+                            typ: principal.typ,
+                            quota: principal.quota,
+                            name: principal.name,
+                            secrets: principal.secrets,
+                            emails: principal.emails,
+                            member_of: principal.member_of,
+                            description: principal.description,
+                            time_zone: principal.time_zone,
+                            undelete_retention: principal.undelete_retention,
+                        };
+                        let result = if let Some(ldap) = self.writable_ldap() {
+                            ldap.create_account(
+                                principal,
+                                members,
+                                Some(&self.core.jmap.password_policy),
                             )
                             .await
-                        {
+                        } else {
+                            self.core
+                                .storage
+                                .data
+                                .create_account_with_policy(
+                                    principal,
+                                    members,
+                                    Some(&self.core.jmap.password_policy),
+                                )
+                                .await
+                        };
+
+                        match result {
                             Ok(account_id) => JsonResponse::new(json!({
                                 "data": account_id,
                             }))
@@ -130,7 +188,13 @@ impl JMAP {
                 let page: usize = params.parse("page").unwrap_or(0);
                 let limit: usize = params.parse("limit").unwrap_or(0);
 
-                match self.core.storage.data.list_accounts(filter, typ).await {
+                let result = if let Some(ldap) = self.writable_ldap() {
+                    ldap.list_accounts(filter, typ).await
+                } else {
+                    self.core.storage.data.list_accounts(filter, typ).await
+                };
+
+                match result {
                     Ok(accounts) => {
                         let (total, accounts) = if limit > 0 {
                             let offset = page.saturating_sub(1) * limit;
@@ -173,13 +237,16 @@ impl JMAP {
 
                 match *method {
                     Method::GET => {
-                        let result = match self
-                            .core
-                            .storage
-                            .data
-                            .query(QueryBy::Id(account_id), true)
-                            .await
-                        {
+                        let result = if let Some(ldap) = self.writable_ldap() {
+                            ldap.query(QueryBy::Id(account_id), true).await
+                        } else {
+                            self.core
+                                .storage
+                                .data
+                                .query(QueryBy::Id(account_id), true)
+                                .await
+                        };
+                        let result = match result {
                             Ok(Some(principal)) => {
                                 self.core.storage.data.map_group_ids(principal).await
                             }
@@ -203,6 +270,14 @@ impl JMAP {
                                         as u64;
 
                                 // Obtain member names
+                                if let Some(ldap) = self.writable_ldap() {
+                                    match ldap.get_members(&principal.name).await {
+                                        Ok(members) => {
+                                            principal.members = members;
+                                        }
+                                        Err(err) => return err.into_http_response(),
+                                    }
+                                }
                                 for member_id in self
                                     .core
                                     .storage
@@ -231,6 +306,13 @@ impl JMAP {
                         }
                     }
                     Method::DELETE => {
+                        // Delete LDAP entry
+                        if let Some(ldap) = self.writable_ldap() {
+                            if let Err(err) = ldap.delete_account(QueryBy::Id(account_id)).await {
+                                return err.into_http_response();
+                            }
+                        }
+
                         // Remove FTS index
                         if let Err(err) = self.core.storage.fts.remove_all(account_id).await {
                             return err.into_http_response();
@@ -266,7 +348,10 @@ impl JMAP {
                                     if changes.iter().any(|change| {
                                         !matches!(
                                             change.field,
-                                            PrincipalField::Quota | PrincipalField::Description
+                                            PrincipalField::Quota
+                                                | PrincipalField::Description
+                                                | PrincipalField::TimeZone
+                                                | PrincipalField::UndeleteRetention
                                         )
                                     }) {
                                         return response;
@@ -276,13 +361,26 @@ impl JMAP {
                                     .iter()
                                     .any(|change| matches!(change.field, PrincipalField::Secrets));
 
-                                match self
-                                    .core
-                                    .storage
-                                    .data
-                                    .update_account(QueryBy::Id(account_id), changes)
+                                let result = if let Some(ldap) = self.writable_ldap() {
+                                    ldap.update_account(
+                                        QueryBy::Id(account_id),
+                                        changes,
+                                        Some(&self.core.jmap.password_policy),
+                                    )
                                     .await
-                                {
+                                } else {
+                                    self.core
+                                        .storage
+                                        .data
+                                        .update_account_with_policy(
+                                            QueryBy::Id(account_id),
+                                            changes,
+                                            Some(&self.core.jmap.password_policy),
+                                        )
+                                        .await
+                                };
+
+                                match result {
                                     Ok(_) => {
                                         if is_password_change {
                                             // Remove entries from cache
@@ -315,6 +413,7 @@ impl JMAP {
             otp_auth: false,
             is_admin: access_token.is_super_user(),
             app_passwords: Vec::new(),
+            webauthn_credentials: Vec::new(),
         };
 
         if access_token.primary_id() != u32::MAX {
@@ -333,6 +432,11 @@ impl JMAP {
                             secret.strip_prefix("$app$").and_then(|s| s.split_once('$'))
                         {
                             response.app_passwords.push(app_name.to_string());
+                        } else if let Some((name, _)) = secret
+                            .strip_prefix("$webauthn$")
+                            .and_then(|s| s.split_once('$'))
+                        {
+                            response.webauthn_credentials.push(name.to_string());
                         }
                     }
                 }
@@ -349,6 +453,216 @@ impl JMAP {
         .into_http_response()
     }
 
+    pub async fn handle_account_login_history(
+        &self,
+        access_token: Arc<AccessToken>,
+    ) -> HttpResponse {
+        match self.core.login_history(access_token.primary_id()).await {
+            Ok(history) => JsonResponse::new(json!({
+                "data": history,
+            }))
+            .into_http_response(),
+            Err(err) => err.into_http_response(),
+        }
+    }
+
+    pub async fn handle_account_webauthn_post(
+        &self,
+        access_token: Arc<AccessToken>,
+    ) -> HttpResponse {
+        if access_token.primary_id() == u32::MAX {
+            return ManagementApiError::Other {
+                details: "Fallback administrator accounts do not support WebAuthn".into(),
+            }
+            .into_http_response();
+        } else if let Some(response) = self.assert_supported_directory() {
+            return response;
+        }
+
+        // Start a registration ceremony
+        let principal = match self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(access_token.primary_id()), false)
+            .await
+        {
+            Ok(Some(principal)) => principal,
+            Ok(None) => return RequestError::not_found().into_http_response(),
+            Err(err) => return err.into_http_response(),
+        };
+        match self.core.webauthn_challenge(principal.id, "register").await {
+            Ok(challenge) => JsonResponse::new(json!({
+                "data": self.core.jmap.webauthn.creation_options(
+                    &challenge,
+                    &principal,
+                    &WebAuthnCredential::from_principal(&principal),
+                ),
+            }))
+            .into_http_response(),
+            Err(err) => err.into_http_response(),
+        }
+    }
+
+    async fn verify_webauthn_registration(
+        &self,
+        access_token: &AccessToken,
+        name: String,
+        credential: &RegistrationCredential,
+    ) -> Result<WebAuthnCredential, HttpResponse> {
+        let challenge = match self
+            .core
+            .webauthn_take_challenge(access_token.primary_id(), "register")
+            .await
+        {
+            Ok(Some(challenge)) => challenge,
+            Ok(None) => {
+                return Err(ManagementApiError::Other {
+                    details: "WebAuthn registration expired, please try again".into(),
+                }
+                .into_http_response())
+            }
+            Err(err) => return Err(err.into_http_response()),
+        };
+        let principal = match self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(access_token.primary_id()), false)
+            .await
+        {
+            Ok(Some(principal)) => principal,
+            Ok(None) => return Err(RequestError::not_found().into_http_response()),
+            Err(err) => return Err(err.into_http_response()),
+        };
+
+        self.core
+            .jmap
+            .webauthn
+            .verify_registration(
+                &challenge,
+                name,
+                credential,
+                &WebAuthnCredential::from_principal(&principal),
+            )
+            .map_err(|details| {
+                ManagementApiError::Other {
+                    details: details.into(),
+                }
+                .into_http_response()
+            })
+    }
+
+    pub async fn assert_webauthn_login(
+        &self,
+        access_token: &AccessToken,
+        assertion: Option<AssertionCredential>,
+    ) -> Result<(), HttpResponse> {
+        let account_id = access_token.primary_id();
+        if account_id == u32::MAX {
+            return Ok(());
+        }
+        let credentials = match self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await
+        {
+            Ok(Some(principal)) => WebAuthnCredential::from_principal(&principal),
+            Ok(None) => return Ok(()),
+            Err(err) => return Err(err.into_http_response()),
+        };
+        if credentials.is_empty() {
+            return Ok(());
+        }
+
+        // Issue a challenge if the client did not provide an assertion
+        let assertion = if let Some(assertion) = assertion {
+            assertion
+        } else {
+            return Err(
+                match self.core.webauthn_challenge(account_id, "login").await {
+                    Ok(challenge) => ManagementApiError::WebAuthnRequired {
+                        options: self
+                            .core
+                            .jmap
+                            .webauthn
+                            .request_options(&challenge, &credentials),
+                    }
+                    .into_http_response(),
+                    Err(err) => err.into_http_response(),
+                },
+            );
+        };
+        let challenge = match self.core.webauthn_take_challenge(account_id, "login").await {
+            Ok(Some(challenge)) => challenge,
+            Ok(None) => {
+                return Err(ManagementApiError::Other {
+                    details: "WebAuthn challenge expired, please try again".into(),
+                }
+                .into_http_response())
+            }
+            Err(err) => return Err(err.into_http_response()),
+        };
+        let credential =
+            match self
+                .core
+                .jmap
+                .webauthn
+                .verify_assertion(&challenge, &assertion, &credentials)
+            {
+                Ok(credential) => credential,
+                Err(details) => {
+                    tracing::debug!(
+                        context = "webauthn",
+                        event = "error",
+                        account_id = account_id,
+                        reason = details,
+                        "WebAuthn assertion failed."
+                    );
+                    return Err(ManagementApiError::Other {
+                        details: details.into(),
+                    }
+                    .into_http_response());
+                }
+            };
+
+        // Store the updated signature counter
+        if let Some(previous) = credentials
+            .iter()
+            .find(|c| c.id == credential.id && c.sign_count != credential.sign_count)
+        {
+            let changes = vec![
+                PrincipalUpdate {
+                    action: PrincipalAction::RemoveItem,
+                    field: PrincipalField::Secrets,
+                    value: PrincipalValue::String(previous.to_secret()),
+                },
+                PrincipalUpdate {
+                    action: PrincipalAction::AddItem,
+                    field: PrincipalField::Secrets,
+                    value: PrincipalValue::String(credential.to_secret()),
+                },
+            ];
+            let result = if let Some(ldap) = self.writable_ldap() {
+                ldap.update_account(QueryBy::Id(account_id), changes, None)
+                    .await
+            } else {
+                self.core
+                    .storage
+                    .data
+                    .update_account(QueryBy::Id(account_id), changes)
+                    .await
+            };
+            if let Err(err) = result {
+                return Err(err.into_http_response());
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn handle_account_auth_post(
         &self,
         req: &HttpRequest,
@@ -356,10 +670,11 @@ impl JMAP {
         body: Option<Vec<u8>>,
     ) -> HttpResponse {
         // Parse request
-        let requests = match serde_json::from_slice::<Vec<AccountAuthRequest>>(
+        let (requests, assertion) = match serde_json::from_slice::<AccountAuthRequests>(
             body.as_deref().unwrap_or_default(),
         ) {
-            Ok(request) => request,
+            Ok(AccountAuthRequests::List(requests)) => (requests, None),
+            Ok(AccountAuthRequests::WithAssertion { requests, webauthn }) => (requests, webauthn),
             Err(err) => return err.into_http_response(),
         };
         if requests.is_empty() {
@@ -373,6 +688,8 @@ impl JMAP {
                 AccountAuthRequest::DisableOtpAuth { .. }
                     | AccountAuthRequest::EnableOtpAuth { .. }
                     | AccountAuthRequest::SetPassword { .. }
+                    | AccountAuthRequest::AddWebAuthn { .. }
+                    | AccountAuthRequest::RemoveWebAuthn { .. }
             )
         }) && req
             .headers()
@@ -386,6 +703,23 @@ impl JMAP {
             .into_http_response();
         }
 
+        // Expired passwords can only be used to choose a new one
+        if self
+            .core
+            .jmap
+            .password_policy
+            .status(access_token.password_changed_at)
+            == PasswordStatus::Expired
+            && requests
+                .iter()
+                .any(|r| !matches!(r, AccountAuthRequest::SetPassword { .. }))
+        {
+            return ManagementApiError::Other {
+                details: "Expired passwords can only be used to set a new password".into(),
+            }
+            .into_http_response();
+        }
+
         // Handle Fallback admin password changes
         if access_token.is_super_user() && access_token.primary_id() == u32::MAX {
             match requests.into_iter().next().unwrap() {
@@ -425,11 +759,26 @@ impl JMAP {
             return response;
         }
 
+        // Changes to accounts with security keys enrolled, including adding or
+        // removing a key, require a fresh WebAuthn assertion
+        if let Err(response) = self.assert_webauthn_login(&access_token, assertion).await {
+            return response;
+        }
+
         // Build actions
         let mut actions = Vec::with_capacity(requests.len());
         for request in requests {
             let (action, secret) = match request {
                 AccountAuthRequest::SetPassword { password } => {
+                    if let Err(violation) = self
+                        .core
+                        .jmap
+                        .password_policy
+                        .validate_plain_text(&password)
+                    {
+                        return violation.into_error().into_http_response();
+                    }
+
                     actions.push(PrincipalUpdate {
                         action: PrincipalAction::RemoveItem,
                         field: PrincipalField::Secrets,
@@ -449,6 +798,18 @@ impl JMAP {
                 AccountAuthRequest::RemoveAppPassword { name } => {
                     (PrincipalAction::RemoveItem, format!("$app${name}"))
                 }
+                AccountAuthRequest::AddWebAuthn { name, credential } => {
+                    match self
+                        .verify_webauthn_registration(&access_token, name, &credential)
+                        .await
+                    {
+                        Ok(credential) => (PrincipalAction::AddItem, credential.to_secret()),
+                        Err(response) => return response,
+                    }
+                }
+                AccountAuthRequest::RemoveWebAuthn { name } => {
+                    (PrincipalAction::RemoveItem, format!("$webauthn${name}$"))
+                }
             };
 
             actions.push(PrincipalUpdate {
@@ -459,13 +820,26 @@ impl JMAP {
         }
 
         // Update password
-        match self
-            .core
-            .storage
-            .data
-            .update_account(QueryBy::Id(access_token.primary_id()), actions)
+        let result = if let Some(ldap) = self.writable_ldap() {
+            ldap.update_account(
+                QueryBy::Id(access_token.primary_id()),
+                actions,
+                Some(&self.core.jmap.password_policy),
+            )
             .await
-        {
+        } else {
+            self.core
+                .storage
+                .data
+                .update_account_with_policy(
+                    QueryBy::Id(access_token.primary_id()),
+                    actions,
+                    Some(&self.core.jmap.password_policy),
+                )
+                .await
+        };
+
+        match result {
             Ok(_) => {
                 // Remove entries from cache
                 self.inner
@@ -485,17 +859,26 @@ impl JMAP {
         ManagementApiError::UnsupportedDirectoryOperation {
             class: match &self.core.storage.directory.store {
                 DirectoryInner::Internal(_) => return None,
+                DirectoryInner::Ldap(ldap) if ldap.is_writable() => return None,
                 DirectoryInner::Ldap(_) => "LDAP",
                 DirectoryInner::Sql(_) => "SQL",
                 DirectoryInner::Imap(_) => "IMAP",
                 DirectoryInner::Smtp(_) => "SMTP",
                 DirectoryInner::Memory(_) => "In-Memory",
+                DirectoryInner::Composite(_) => "Composite",
             }
             .into(),
         }
         .into_http_response()
         .into()
     }
+
+    fn writable_ldap(&self) -> Option<&LdapDirectory> {
+        match &self.core.storage.directory.store {
+            DirectoryInner::Ldap(ldap) if ldap.is_writable() => Some(ldap),
+            _ => None,
+        }
+    }
 }
 
 impl From<Principal<String>> for PrincipalResponse {
@@ -508,6 +891,8 @@ impl From<Principal<String>> for PrincipalResponse {
             emails: principal.emails,
             member_of: principal.member_of,
             description: principal.description,
+            time_zone: principal.time_zone,
+            undelete_retention: principal.undelete_retention,
             secrets: principal.secrets,
             used_quota: 0,
             members: Vec::new(),
@@ -532,6 +917,11 @@ impl ToHttpResponse for DirectoryError {
                     ManagementError::NotFound(details) => ManagementApiError::NotFound {
                         item: details.into(),
                     },
+                    ManagementError::PasswordPolicy(violation) => {
+                        ManagementApiError::PasswordPolicy {
+                            details: violation.to_string().into(),
+                        }
+                    }
                 };
                 JsonResponse::new(response).into_http_response()
             }
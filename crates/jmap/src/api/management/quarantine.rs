@@ -0,0 +1,268 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use directory::QueryBy;
+use hyper::Method;
+use jmap_proto::error::request::RequestError;
+use mail_parser::{DateTime, MessageParser};
+use serde_json::json;
+use smtp::queue::quarantine::{QuarantineReason, QuarantinedMessage};
+use store::write::ReportClass;
+use utils::url_params::UrlParams;
+
+use crate::{
+    api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    auth::AccessToken,
+    JMAP,
+};
+
+use super::{
+    decode_path_element,
+    queue::{serialize_datetime, Recipient},
+};
+
+#[derive(Debug, serde::Serialize)]
+pub struct Quarantined {
+    pub id: String,
+    pub return_path: String,
+    pub recipients: Vec<Recipient>,
+    pub from: String,
+    pub subject: String,
+    pub remote_ip: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub authenticated_as: String,
+    pub reasons: Vec<QuarantineReason>,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub created: DateTime,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub expires: DateTime,
+    pub size: usize,
+}
+
+const PREVIEW_LENGTH: usize = 1024;
+
+impl JMAP {
+    pub async fn handle_manage_quarantine(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: Arc<AccessToken>,
+    ) -> HttpResponse {
+        // Regular users can only manage messages addressed to them
+        let addresses = if !access_token.is_super_user() {
+            match self
+                .core
+                .storage
+                .directory
+                .query(QueryBy::Id(access_token.primary_id()), false)
+                .await
+            {
+                Ok(Some(principal)) => Some(
+                    principal
+                        .emails
+                        .into_iter()
+                        .map(|email| email.to_lowercase())
+                        .collect::<Vec<_>>(),
+                ),
+                Ok(None) => return RequestError::not_found().into_http_response(),
+                Err(err) => {
+                    tracing::error!(
+                        context = "quarantine",
+                        event = "error",
+                        error = ?err,
+                        "Failed to query directory.");
+                    return RequestError::internal_server_error().into_http_response();
+                }
+            }
+        } else {
+            None
+        };
+
+        match (
+            path.get(1).copied().map(decode_path_element),
+            path.get(2).copied(),
+            req.method(),
+        ) {
+            (None, None, &Method::GET) => {
+                let params = UrlParams::new(req.uri().query());
+                let filter = params.get("text");
+                let page: usize = params.parse::<usize>("page").unwrap_or_default();
+                let limit: usize = params.parse::<usize>("limit").unwrap_or_default();
+                let max_total = params.parse::<usize>("max-total").unwrap_or_default();
+                let values = params.has_key("values");
+
+                let mut result_ids = Vec::new();
+                let mut result_values = Vec::new();
+                let mut offset = page.saturating_sub(1) * limit;
+                let mut total = 0;
+                let mut total_returned = 0;
+                let result = self
+                    .smtp
+                    .read_quarantined_messages(|message| {
+                        let matches = addresses
+                            .as_ref()
+                            .map_or(true, |addresses| message.has_recipient(addresses))
+                            && filter.map_or(true, |filter| message.contains(filter));
+
+                        if matches {
+                            if offset == 0 {
+                                if limit == 0 || total_returned < limit {
+                                    if values {
+                                        result_values
+                                            .push(Quarantined::new(&message, addresses.as_deref()));
+                                    } else {
+                                        result_ids.push(quarantine_id(&message));
+                                    }
+                                    total_returned += 1;
+                                }
+                            } else {
+                                offset -= 1;
+                            }
+
+                            total += 1;
+                        }
+
+                        max_total == 0 || total < max_total
+                    })
+                    .await;
+
+                match result {
+                    Ok(_) if values => JsonResponse::new(json!({
+                            "data": {
+                                "items": result_values,
+                                "total": total,
+                            },
+                    }))
+                    .into_http_response(),
+                    Ok(_) => JsonResponse::new(json!({
+                            "data": {
+                                "items": result_ids,
+                                "total": total,
+                            },
+                    }))
+                    .into_http_response(),
+                    Err(err) => err.into_http_response(),
+                }
+            }
+            (Some(id), action, method) => {
+                let message = if let Some(message) = parse_quarantine_id(id.as_ref()) {
+                    self.smtp.read_quarantined_message(message).await
+                } else {
+                    None
+                }
+                .filter(|message| {
+                    addresses
+                        .as_ref()
+                        .map_or(true, |addresses| message.has_recipient(addresses))
+                });
+                let message = if let Some(message) = message {
+                    message
+                } else {
+                    return RequestError::not_found().into_http_response();
+                };
+
+                match (action, method) {
+                    (None, &Method::GET) => JsonResponse::new(json!({
+                            "data": Quarantined::new(&message, addresses.as_deref()),
+                    }))
+                    .into_http_response(),
+                    (Some("preview"), &Method::GET) => {
+                        match self
+                            .core
+                            .storage
+                            .blob
+                            .get_blob(message.message.blob_hash.as_slice(), 0..usize::MAX)
+                            .await
+                        {
+                            Ok(Some(raw_message)) => {
+                                let parsed = MessageParser::new().parse(&raw_message);
+                                let headers = parsed
+                                    .as_ref()
+                                    .and_then(|m| {
+                                        raw_message.get(..m.root_part().raw_body_offset())
+                                    })
+                                    .map(|h| String::from_utf8_lossy(h).into_owned())
+                                    .unwrap_or_default();
+                                let body = parsed
+                                    .as_ref()
+                                    .and_then(|m| m.body_preview(PREVIEW_LENGTH))
+                                    .map(|p| p.into_owned())
+                                    .unwrap_or_default();
+
+                                JsonResponse::new(json!({
+                                        "data": {
+                                            "headers": headers,
+                                            "preview": body,
+                                        },
+                                }))
+                                .into_http_response()
+                            }
+                            Ok(None) => RequestError::not_found().into_http_response(),
+                            Err(err) => err.into_http_response(),
+                        }
+                    }
+                    (None, &Method::PATCH) => JsonResponse::new(json!({
+                            "data": self
+                                .smtp
+                                .release_quarantined_message(message, addresses.as_deref())
+                                .await,
+                    }))
+                    .into_http_response(),
+                    (None, &Method::DELETE) => JsonResponse::new(json!({
+                            "data": self
+                                .smtp
+                                .delete_quarantined_message(message, addresses.as_deref())
+                                .await,
+                    }))
+                    .into_http_response(),
+                    _ => RequestError::not_found().into_http_response(),
+                }
+            }
+            _ => RequestError::not_found().into_http_response(),
+        }
+    }
+}
+
+impl Quarantined {
+    fn new(message: &QuarantinedMessage, addresses: Option<&[String]>) -> Self {
+        Quarantined {
+            id: quarantine_id(message),
+            return_path: message.message.return_path.clone(),
+            recipients: message
+                .message
+                .recipients
+                .iter()
+                .filter(|rcpt| addresses.map_or(true, |a| a.contains(&rcpt.address_lcase)))
+                .map(|rcpt| Recipient {
+                    address: rcpt.address.clone(),
+                    status: smtp::queue::Status::Scheduled,
+                    orcpt: rcpt.orcpt.clone(),
+                })
+                .collect(),
+            from: message.from.clone(),
+            subject: message.subject.clone(),
+            remote_ip: message.remote_ip.to_string(),
+            authenticated_as: message.authenticated_as.clone(),
+            reasons: message.reasons.clone(),
+            created: DateTime::from_timestamp(message.message.created as i64),
+            expires: DateTime::from_timestamp(message.expires as i64),
+            size: message.message.size,
+        }
+    }
+}
+
+fn quarantine_id(message: &QuarantinedMessage) -> String {
+    format!("{}_{}", message.message.id, message.expires)
+}
+
+fn parse_quarantine_id(id: &str) -> Option<ReportClass> {
+    let mut parts = id.split('_');
+    let id = parts.next()?.parse().ok()?;
+    let expires = parts.next()?.parse().ok()?;
+    Some(ReportClass::Quarantine { id, expires })
+}
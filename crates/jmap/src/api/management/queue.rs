@@ -665,7 +665,7 @@ where
     }
 }
 
-fn serialize_datetime<S>(value: &DateTime, serializer: S) -> Result<S::Ok, S::Error>
+pub(super) fn serialize_datetime<S>(value: &DateTime, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
@@ -204,6 +204,12 @@ impl JMAP {
                             Ok(None) => RequestError::not_found().into_http_response(),
                             Err(err) => err.into_http_response(),
                         },
+                        ReportClass::Quarantine { .. }
+                        | ReportClass::DsnDigest { .. }
+                        | ReportClass::ListModeration { .. }
+                        | ReportClass::Snooze { .. } => {
+                            RequestError::not_found().into_http_response()
+                        }
                     }
                 } else {
                     RequestError::not_found().into_http_response()
@@ -120,6 +120,36 @@ impl JMAP {
                 self.housekeeper_request(Event::Purge(PurgeType::Account(account_id)))
                     .await
             }
+            (Some("reindex"), id, _, &Method::GET) => {
+                let account_id = if let Some(id) = id {
+                    match self
+                        .core
+                        .storage
+                        .data
+                        .get_account_id(decode_path_element(id).as_ref())
+                        .await
+                    {
+                        Ok(Some(id)) => id.into(),
+                        Ok(None) => return RequestError::not_found().into_http_response(),
+                        Err(err) => return err.into_http_response(),
+                    }
+                } else {
+                    None
+                };
+
+                // Emails are queued in the background, large accounts can take a while
+                let jmap = self.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = jmap.fts_reindex(account_id).await {
+                        tracing::error!("Failed to queue emails for reindexing: {err:?}");
+                    }
+                });
+
+                JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response()
+            }
             _ => RequestError::not_found().into_http_response(),
         }
     }
@@ -10,8 +10,9 @@ use common::listener::ServerInstance;
 use jmap_proto::{
     error::{method::MethodError, request::RequestError},
     method::{
-        get, query,
-        set::{self},
+        get::{self, GetRequest},
+        query::{self, QueryRequest},
+        set::{self, SetRequest},
     },
     request::{method::MethodName, Call, Request, RequestMethod},
     response::{Response, ResponseMethod},
@@ -75,6 +76,24 @@ impl JMAP {
                                     self.broadcast_state_change(state_change).await;
                                 }
                             }
+                            ResponseMethod::SplitThread(split_response) => {
+                                // Publish state changes
+                                if let Some(state_change) = split_response.state_change.take() {
+                                    self.broadcast_state_change(state_change).await;
+                                }
+                            }
+                            ResponseMethod::MergeThread(merge_response) => {
+                                // Publish state changes
+                                if let Some(state_change) = merge_response.state_change.take() {
+                                    self.broadcast_state_change(state_change).await;
+                                }
+                            }
+                            ResponseMethod::UndeleteEmail(undelete_response) => {
+                                // Publish state changes
+                                if let Some(state_change) = undelete_response.state_change.take() {
+                                    self.broadcast_state_change(state_change).await;
+                                }
+                            }
                             ResponseMethod::UploadBlob(upload_response) => {
                                 // Add created blobIds
                                 upload_response.update_created_ids(&mut response);
@@ -114,151 +133,29 @@ impl JMAP {
         next_call: &mut Option<Call<RequestMethod>>,
         instance: &Arc<ServerInstance>,
     ) -> Result<ResponseMethod, MethodError> {
-        Ok(match method {
-            RequestMethod::Get(mut req) => match req.take_arguments() {
-                get::RequestArguments::Email(arguments) => {
-                    access_token.assert_has_access(req.account_id, Collection::Email)?;
-
-                    self.email_get(req.with_arguments(arguments), access_token)
-                        .await?
-                        .into()
-                }
-                get::RequestArguments::Mailbox => {
-                    access_token.assert_has_access(req.account_id, Collection::Mailbox)?;
-
-                    self.mailbox_get(req, access_token).await?.into()
-                }
-                get::RequestArguments::Thread => {
-                    access_token.assert_has_access(req.account_id, Collection::Email)?;
-
-                    self.thread_get(req).await?.into()
-                }
-                get::RequestArguments::Identity => {
-                    access_token.assert_is_member(req.account_id)?;
-
-                    self.identity_get(req).await?.into()
-                }
-                get::RequestArguments::EmailSubmission => {
-                    access_token.assert_is_member(req.account_id)?;
-
-                    self.email_submission_get(req).await?.into()
-                }
-                get::RequestArguments::PushSubscription => {
-                    self.push_subscription_get(req, access_token).await?.into()
-                }
-                get::RequestArguments::SieveScript => {
-                    access_token.assert_is_member(req.account_id)?;
-
-                    self.sieve_script_get(req).await?.into()
-                }
-                get::RequestArguments::VacationResponse => {
-                    access_token.assert_is_member(req.account_id)?;
-
-                    self.vacation_response_get(req).await?.into()
-                }
-                get::RequestArguments::Principal => {
-                    if self.core.jmap.principal_allow_lookups || access_token.is_super_user() {
-                        self.principal_get(req).await?.into()
-                    } else {
-                        return Err(MethodError::Forbidden(
-                            "Principal lookups are disabled".to_string(),
-                        ));
-                    }
-                }
-                get::RequestArguments::Quota => {
-                    access_token.assert_is_member(req.account_id)?;
-
-                    self.quota_get(req, access_token).await?.into()
-                }
-                get::RequestArguments::Blob(arguments) => {
-                    access_token.assert_is_member(req.account_id)?;
-
-                    self.blob_get(req.with_arguments(arguments), access_token)
-                        .await?
-                        .into()
-                }
-            },
-            RequestMethod::Query(mut req) => match req.take_arguments() {
-                query::RequestArguments::Email(arguments) => {
-                    access_token.assert_has_access(req.account_id, Collection::Email)?;
-
-                    self.email_query(req.with_arguments(arguments), access_token)
-                        .await?
-                        .into()
-                }
-                query::RequestArguments::Mailbox(arguments) => {
-                    access_token.assert_has_access(req.account_id, Collection::Mailbox)?;
-
-                    self.mailbox_query(req.with_arguments(arguments), access_token)
-                        .await?
-                        .into()
-                }
-                query::RequestArguments::EmailSubmission => {
-                    access_token.assert_is_member(req.account_id)?;
-
-                    self.email_submission_query(req).await?.into()
-                }
-                query::RequestArguments::SieveScript => {
-                    access_token.assert_is_member(req.account_id)?;
-
-                    self.sieve_script_query(req).await?.into()
-                }
-                query::RequestArguments::Principal => {
-                    if self.core.jmap.principal_allow_lookups || access_token.is_super_user() {
-                        self.principal_query(req).await?.into()
-                    } else {
-                        return Err(MethodError::Forbidden(
-                            "Principal lookups are disabled".to_string(),
-                        ));
-                    }
-                }
-                query::RequestArguments::Quota => {
-                    access_token.assert_is_member(req.account_id)?;
-
-                    self.quota_query(req, access_token).await?.into()
-                }
-            },
-            RequestMethod::Set(mut req) => match req.take_arguments() {
-                set::RequestArguments::Email => {
-                    access_token.assert_has_access(req.account_id, Collection::Email)?;
-
-                    self.email_set(req, access_token).await?.into()
-                }
-                set::RequestArguments::Mailbox(arguments) => {
-                    access_token.assert_has_access(req.account_id, Collection::Mailbox)?;
-
-                    self.mailbox_set(req.with_arguments(arguments), access_token)
-                        .await?
-                        .into()
-                }
-                set::RequestArguments::Identity => {
-                    access_token.assert_is_member(req.account_id)?;
-
-                    self.identity_set(req).await?.into()
-                }
-                set::RequestArguments::EmailSubmission(arguments) => {
-                    access_token.assert_is_member(req.account_id)?;
-
-                    self.email_submission_set(req.with_arguments(arguments), instance, next_call)
-                        .await?
-                        .into()
-                }
-                set::RequestArguments::PushSubscription => {
-                    self.push_subscription_set(req, access_token).await?.into()
-                }
-                set::RequestArguments::SieveScript(arguments) => {
-                    access_token.assert_is_member(req.account_id)?;
-
-                    self.sieve_script_set(req.with_arguments(arguments), access_token)
-                        .await?
-                        .into()
-                }
-                set::RequestArguments::VacationResponse => {
-                    access_token.assert_is_member(req.account_id)?;
+        // Each group of methods is handled separately to keep stack usage low
+        match method {
+            RequestMethod::Get(req) => self.handle_get_call(req, access_token).await,
+            RequestMethod::Query(req) => self.handle_query_call(req, access_token).await,
+            RequestMethod::Set(req) => {
+                self.handle_set_call(req, access_token, next_call, instance)
+                    .await
+            }
+            method => {
+                self.handle_other_call(method, access_token, next_call, instance)
+                    .await
+            }
+        }
+    }
 
-                    self.vacation_response_set(req).await?.into()
-                }
-            },
+    async fn handle_other_call(
+        &self,
+        method: RequestMethod,
+        access_token: &AccessToken,
+        next_call: &mut Option<Call<RequestMethod>>,
+        instance: &Arc<ServerInstance>,
+    ) -> Result<ResponseMethod, MethodError> {
+        Ok(match method {
             RequestMethod::Changes(req) => self.changes(req, access_token).await?.into(),
             RequestMethod::Copy(req) => {
                 access_token
@@ -303,8 +200,297 @@ impl JMAP {
 
                 self.blob_upload_many(req, access_token).await?.into()
             }
+            RequestMethod::SendMdn(req) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.mdn_send(req, instance, next_call).await?.into()
+            }
+            RequestMethod::ParseMdn(req) => {
+                access_token.assert_has_access(req.account_id, Collection::Email)?;
+
+                self.mdn_parse(req, access_token).await?.into()
+            }
+            RequestMethod::ParseCalendarEvent(req) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.calendar_event_parse(req, access_token).await?.into()
+            }
+            RequestMethod::SplitThread(req) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.thread_split(req).await?.into()
+            }
+            RequestMethod::MergeThread(req) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.thread_merge(req).await?.into()
+            }
+            RequestMethod::ExportThread(req) => {
+                access_token.assert_has_access(req.account_id, Collection::Email)?;
+
+                self.thread_export(req, access_token).await?.into()
+            }
+            RequestMethod::ListDeletedEmail(req) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.email_list_deleted(req).await?.into()
+            }
+            RequestMethod::UndeleteEmail(req) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.email_undelete(req, access_token).await?.into()
+            }
             RequestMethod::Echo(req) => req.into(),
             RequestMethod::Error(error) => return Err(error),
+            RequestMethod::Get(_) | RequestMethod::Query(_) | RequestMethod::Set(_) => {
+                unreachable!()
+            }
+        })
+    }
+
+    async fn handle_get_call(
+        &self,
+        mut req: GetRequest<get::RequestArguments>,
+        access_token: &AccessToken,
+    ) -> Result<ResponseMethod, MethodError> {
+        Ok(match req.take_arguments() {
+            get::RequestArguments::Email(arguments) => {
+                access_token.assert_has_access(req.account_id, Collection::Email)?;
+
+                self.email_get(req.with_arguments(arguments), access_token)
+                    .await?
+                    .into()
+            }
+            get::RequestArguments::Mailbox => {
+                access_token.assert_has_access(req.account_id, Collection::Mailbox)?;
+
+                self.mailbox_get(req, access_token).await?.into()
+            }
+            get::RequestArguments::Thread => {
+                access_token.assert_has_access(req.account_id, Collection::Email)?;
+
+                self.thread_get(req).await?.into()
+            }
+            get::RequestArguments::Identity => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.identity_get(req).await?.into()
+            }
+            get::RequestArguments::EmailSubmission => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.email_submission_get(req).await?.into()
+            }
+            get::RequestArguments::PushSubscription => {
+                self.push_subscription_get(req, access_token).await?.into()
+            }
+            get::RequestArguments::SieveScript => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.sieve_script_get(req).await?.into()
+            }
+            get::RequestArguments::VacationResponse => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.vacation_response_get(req).await?.into()
+            }
+            get::RequestArguments::Principal => {
+                if self.core.jmap.principal_allow_lookups || access_token.is_super_user() {
+                    self.principal_get(req, access_token).await?.into()
+                } else {
+                    return Err(MethodError::Forbidden(
+                        "Principal lookups are disabled".to_string(),
+                    ));
+                }
+            }
+            get::RequestArguments::ShareNotification => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.share_notification_get(req).await?.into()
+            }
+            get::RequestArguments::Calendar => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.calendar_get(req).await?.into()
+            }
+            get::RequestArguments::CalendarEvent => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.calendar_event_get(req).await?.into()
+            }
+            get::RequestArguments::CalendarEventNotification => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.calendar_event_notification_get(req).await?.into()
+            }
+            get::RequestArguments::Template => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.template_get(req, access_token).await?.into()
+            }
+            get::RequestArguments::Quota => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.quota_get(req, access_token).await?.into()
+            }
+            get::RequestArguments::Blob(arguments) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.blob_get(req.with_arguments(arguments), access_token)
+                    .await?
+                    .into()
+            }
+        })
+    }
+
+    async fn handle_query_call(
+        &self,
+        mut req: QueryRequest<query::RequestArguments>,
+        access_token: &AccessToken,
+    ) -> Result<ResponseMethod, MethodError> {
+        Ok(match req.take_arguments() {
+            query::RequestArguments::Email(arguments) => {
+                access_token.assert_has_access(req.account_id, Collection::Email)?;
+
+                self.email_query(req.with_arguments(arguments), access_token)
+                    .await?
+                    .into()
+            }
+            query::RequestArguments::Mailbox(arguments) => {
+                access_token.assert_has_access(req.account_id, Collection::Mailbox)?;
+
+                self.mailbox_query(req.with_arguments(arguments), access_token)
+                    .await?
+                    .into()
+            }
+            query::RequestArguments::EmailSubmission => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.email_submission_query(req).await?.into()
+            }
+            query::RequestArguments::SieveScript => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.sieve_script_query(req).await?.into()
+            }
+            query::RequestArguments::Principal => {
+                if self.core.jmap.principal_allow_lookups || access_token.is_super_user() {
+                    self.principal_query(req).await?.into()
+                } else {
+                    return Err(MethodError::Forbidden(
+                        "Principal lookups are disabled".to_string(),
+                    ));
+                }
+            }
+            query::RequestArguments::Quota => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.quota_query(req, access_token).await?.into()
+            }
+            query::RequestArguments::ShareNotification => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.share_notification_query(req).await?.into()
+            }
+            query::RequestArguments::Calendar => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.calendar_query(req).await?.into()
+            }
+            query::RequestArguments::CalendarEvent => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.calendar_event_query(req).await?.into()
+            }
+            query::RequestArguments::CalendarEventNotification => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.calendar_event_notification_query(req).await?.into()
+            }
+            query::RequestArguments::Template => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.template_query(req, access_token).await?.into()
+            }
+        })
+    }
+
+    async fn handle_set_call(
+        &self,
+        mut req: SetRequest<set::RequestArguments>,
+        access_token: &AccessToken,
+        next_call: &mut Option<Call<RequestMethod>>,
+        instance: &Arc<ServerInstance>,
+    ) -> Result<ResponseMethod, MethodError> {
+        Ok(match req.take_arguments() {
+            set::RequestArguments::Email => {
+                access_token.assert_has_access(req.account_id, Collection::Email)?;
+
+                self.email_set(req, access_token).await?.into()
+            }
+            set::RequestArguments::Mailbox(arguments) => {
+                access_token.assert_has_access(req.account_id, Collection::Mailbox)?;
+
+                self.mailbox_set(req.with_arguments(arguments), access_token)
+                    .await?
+                    .into()
+            }
+            set::RequestArguments::Identity => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.identity_set(req).await?.into()
+            }
+            set::RequestArguments::EmailSubmission(arguments) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.email_submission_set(req.with_arguments(arguments), instance, next_call)
+                    .await?
+                    .into()
+            }
+            set::RequestArguments::PushSubscription => {
+                self.push_subscription_set(req, access_token).await?.into()
+            }
+            set::RequestArguments::SieveScript(arguments) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.sieve_script_set(req.with_arguments(arguments), access_token)
+                    .await?
+                    .into()
+            }
+            set::RequestArguments::VacationResponse => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.vacation_response_set(req).await?.into()
+            }
+            set::RequestArguments::ShareNotification => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.share_notification_set(req).await?.into()
+            }
+            set::RequestArguments::Calendar(arguments) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.calendar_set(req.with_arguments(arguments))
+                    .await?
+                    .into()
+            }
+            set::RequestArguments::CalendarEvent(arguments) => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.calendar_event_set(req.with_arguments(arguments), instance)
+                    .await?
+                    .into()
+            }
+            set::RequestArguments::CalendarEventNotification => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.calendar_event_notification_set(req).await?.into()
+            }
+            set::RequestArguments::Template => {
+                access_token.assert_is_member(req.account_id)?;
+
+                self.template_set(req, access_token).await?.into()
+            }
         })
     }
 }
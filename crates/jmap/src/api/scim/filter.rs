@@ -0,0 +1,442 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{cmp::Ordering, iter::Peekable, str::Chars};
+
+use serde_json::Value;
+
+// SCIM filter expressions (RFC 7644, section 3.4.2.2)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+    Present(String),
+    Compare {
+        attr: String,
+        op: Operator,
+        value: Value,
+    },
+    ValuePath {
+        attr: String,
+        filter: Box<Filter>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Contains,
+    StartsWith,
+    EndsWith,
+    GreaterThan,
+    GreaterEqual,
+    LessThan,
+    LessEqual,
+}
+
+// A PATCH path such as 'emails[type eq "work"].value'
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributePath {
+    pub attr: String,
+    pub filter: Option<Filter>,
+    pub sub_attr: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Value(Value),
+    ParenOpen,
+    ParenClose,
+    BracketOpen,
+    BracketClose,
+}
+
+struct Tokenizer<'x> {
+    chars: Peekable<Chars<'x>>,
+    peeked: Option<Token>,
+}
+
+impl Filter {
+    pub fn parse(filter: &str) -> Result<Self, String> {
+        let mut tokens = Tokenizer::new(filter);
+        let filter = parse_or(&mut tokens)?;
+        if let Some(token) = tokens.next()? {
+            Err(format!("Unexpected token {token:?}"))
+        } else {
+            Ok(filter)
+        }
+    }
+
+    pub fn matches(&self, resource: &Value) -> bool {
+        match self {
+            Filter::And(filters) => filters.iter().all(|f| f.matches(resource)),
+            Filter::Or(filters) => filters.iter().any(|f| f.matches(resource)),
+            Filter::Not(filter) => !filter.matches(resource),
+            Filter::Present(attr) => resolve(resource, attr).iter().any(|v| match v {
+                Value::Null => false,
+                Value::String(s) => !s.is_empty(),
+                Value::Array(a) => !a.is_empty(),
+                Value::Object(o) => !o.is_empty(),
+                _ => true,
+            }),
+            Filter::Compare { attr, op, value } => resolve(resource, attr)
+                .into_iter()
+                .flat_map(|v| match v {
+                    Value::Array(items) => items.iter().collect::<Vec<_>>(),
+                    v => vec![v],
+                })
+                .any(|v| op.matches(v, value)),
+            Filter::ValuePath { attr, filter } => resolve(resource, attr)
+                .into_iter()
+                .flat_map(|v| match v {
+                    Value::Array(items) => items.iter().collect::<Vec<_>>(),
+                    v => vec![v],
+                })
+                .any(|v| filter.matches(v)),
+        }
+    }
+
+    // Returns the value of 'attr eq "value"' filters, used to avoid full scans
+    pub fn as_equality(&self, attr: &str) -> Option<&str> {
+        match self {
+            Filter::Compare {
+                attr: attr_,
+                op: Operator::Equal,
+                value: Value::String(value),
+            } if attr_.eq_ignore_ascii_case(attr) => Some(value.as_str()),
+            _ => None,
+        }
+    }
+
+    // Returns whether any of the attributes is part of the filter
+    pub fn references(&self, attrs: &[&str]) -> bool {
+        match self {
+            Filter::And(filters) | Filter::Or(filters) => {
+                filters.iter().any(|f| f.references(attrs))
+            }
+            Filter::Not(filter) => filter.references(attrs),
+            Filter::Present(attr)
+            | Filter::Compare { attr, .. }
+            | Filter::ValuePath { attr, .. } => attr
+                .split([':', '.'])
+                .any(|part| attrs.iter().any(|attr| attr.eq_ignore_ascii_case(part))),
+        }
+    }
+}
+
+impl AttributePath {
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let path = path.trim();
+        if let Some((attr, rest)) = path.split_once('[') {
+            let (filter, sub_attr) = rest
+                .rsplit_once(']')
+                .ok_or_else(|| format!("Invalid path {path:?}"))?;
+            let sub_attr = sub_attr.trim();
+            Ok(AttributePath {
+                attr: strip_schema(attr.trim()).to_string(),
+                filter: Some(Filter::parse(filter)?),
+                sub_attr: if let Some(sub_attr) = sub_attr.strip_prefix('.') {
+                    Some(sub_attr.to_string())
+                } else if sub_attr.is_empty() {
+                    None
+                } else {
+                    return Err(format!("Invalid path {path:?}"));
+                },
+            })
+        } else if !path.is_empty() {
+            let path = strip_schema(path);
+            let (attr, sub_attr) = match path.split_once('.') {
+                Some((attr, sub_attr)) if !path.starts_with("urn:") => {
+                    (attr, Some(sub_attr.to_string()))
+                }
+                _ => (path, None),
+            };
+            Ok(AttributePath {
+                attr: attr.to_string(),
+                filter: None,
+                sub_attr,
+            })
+        } else {
+            Err("Empty path".to_string())
+        }
+    }
+}
+
+impl Operator {
+    fn parse(op: &str) -> Option<Self> {
+        match op.to_ascii_lowercase().as_str() {
+            "eq" => Some(Operator::Equal),
+            "ne" => Some(Operator::NotEqual),
+            "co" => Some(Operator::Contains),
+            "sw" => Some(Operator::StartsWith),
+            "ew" => Some(Operator::EndsWith),
+            "gt" => Some(Operator::GreaterThan),
+            "ge" => Some(Operator::GreaterEqual),
+            "lt" => Some(Operator::LessThan),
+            "le" => Some(Operator::LessEqual),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, value: &Value, expected: &Value) -> bool {
+        match (value, expected) {
+            (Value::String(value), Value::String(expected)) => {
+                // All supported attributes are case insensitive
+                let value = value.to_lowercase();
+                let expected = expected.to_lowercase();
+                match self {
+                    Operator::Equal => value == expected,
+                    Operator::NotEqual => value != expected,
+                    Operator::Contains => value.contains(&expected),
+                    Operator::StartsWith => value.starts_with(&expected),
+                    Operator::EndsWith => value.ends_with(&expected),
+                    _ => self.matches_ordering(value.cmp(&expected)),
+                }
+            }
+            (Value::Number(value), Value::Number(expected)) => {
+                match (value.as_f64(), expected.as_f64()) {
+                    (Some(value), Some(expected)) => value
+                        .partial_cmp(&expected)
+                        .map_or(false, |ordering| self.matches_ordering(ordering)),
+                    _ => false,
+                }
+            }
+            (Value::Bool(value), Value::Bool(expected)) => match self {
+                Operator::Equal => value == expected,
+                Operator::NotEqual => value != expected,
+                _ => false,
+            },
+            (_, Value::Null) => match self {
+                Operator::Equal => value.is_null(),
+                Operator::NotEqual => !value.is_null(),
+                _ => false,
+            },
+            _ => matches!(self, Operator::NotEqual),
+        }
+    }
+
+    fn matches_ordering(&self, ordering: Ordering) -> bool {
+        match self {
+            Operator::Equal => ordering == Ordering::Equal,
+            Operator::NotEqual => ordering != Ordering::Equal,
+            Operator::GreaterThan => ordering == Ordering::Greater,
+            Operator::GreaterEqual => ordering != Ordering::Less,
+            Operator::LessThan => ordering == Ordering::Less,
+            Operator::LessEqual => ordering != Ordering::Greater,
+            _ => false,
+        }
+    }
+}
+
+fn parse_or(tokens: &mut Tokenizer) -> Result<Filter, String> {
+    let mut filters = vec![parse_and(tokens)?];
+    while tokens.next_if_word("or")? {
+        filters.push(parse_and(tokens)?);
+    }
+    Ok(if filters.len() == 1 {
+        filters.pop().unwrap()
+    } else {
+        Filter::Or(filters)
+    })
+}
+
+fn parse_and(tokens: &mut Tokenizer) -> Result<Filter, String> {
+    let mut filters = vec![parse_unary(tokens)?];
+    while tokens.next_if_word("and")? {
+        filters.push(parse_unary(tokens)?);
+    }
+    Ok(if filters.len() == 1 {
+        filters.pop().unwrap()
+    } else {
+        Filter::And(filters)
+    })
+}
+
+fn parse_unary(tokens: &mut Tokenizer) -> Result<Filter, String> {
+    match tokens.next()? {
+        Some(Token::ParenOpen) => {
+            let filter = parse_or(tokens)?;
+            tokens.expect(Token::ParenClose)?;
+            Ok(filter)
+        }
+        Some(Token::Word(word)) if word.eq_ignore_ascii_case("not") => {
+            tokens.expect(Token::ParenOpen)?;
+            let filter = parse_or(tokens)?;
+            tokens.expect(Token::ParenClose)?;
+            Ok(Filter::Not(Box::new(filter)))
+        }
+        Some(Token::Word(attr)) => {
+            let attr = strip_schema(&attr).to_string();
+            match tokens.next()? {
+                Some(Token::BracketOpen) => {
+                    let filter = parse_or(tokens)?;
+                    tokens.expect(Token::BracketClose)?;
+                    Ok(Filter::ValuePath {
+                        attr,
+                        filter: Box::new(filter),
+                    })
+                }
+                Some(Token::Word(op)) if op.eq_ignore_ascii_case("pr") => Ok(Filter::Present(attr)),
+                Some(Token::Word(op)) => {
+                    let op = Operator::parse(&op)
+                        .ok_or_else(|| format!("Unsupported operator {op:?}"))?;
+                    match tokens.next()? {
+                        Some(Token::Value(value)) => Ok(Filter::Compare { attr, op, value }),
+                        token => Err(format!("Expected value, found {token:?}")),
+                    }
+                }
+                token => Err(format!("Expected operator, found {token:?}")),
+            }
+        }
+        token => Err(format!("Expected attribute, found {token:?}")),
+    }
+}
+
+impl<'x> Tokenizer<'x> {
+    fn new(filter: &'x str) -> Self {
+        Tokenizer {
+            chars: filter.chars().peekable(),
+            peeked: None,
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<Token>, String> {
+        if let Some(token) = self.peeked.take() {
+            return Ok(Some(token));
+        }
+
+        while self.chars.next_if(|ch| ch.is_whitespace()).is_some() {}
+
+        match self.chars.next() {
+            Some('(') => Ok(Some(Token::ParenOpen)),
+            Some(')') => Ok(Some(Token::ParenClose)),
+            Some('[') => Ok(Some(Token::BracketOpen)),
+            Some(']') => Ok(Some(Token::BracketClose)),
+            Some('"') => {
+                let mut string = String::from('"');
+                let mut is_escaped = false;
+                loop {
+                    match self.chars.next() {
+                        Some(ch) => {
+                            string.push(ch);
+                            if is_escaped {
+                                is_escaped = false;
+                            } else if ch == '\\' {
+                                is_escaped = true;
+                            } else if ch == '"' {
+                                break;
+                            }
+                        }
+                        None => return Err("Unterminated string".to_string()),
+                    }
+                }
+                serde_json::from_str::<String>(&string)
+                    .map(|s| Some(Token::Value(Value::String(s))))
+                    .map_err(|_| "Invalid string".to_string())
+            }
+            Some(ch) => {
+                let mut word = String::from(ch);
+                while let Some(ch) = self
+                    .chars
+                    .next_if(|ch| !ch.is_whitespace() && !matches!(ch, '(' | ')' | '[' | ']'))
+                {
+                    word.push(ch);
+                }
+
+                Ok(Some(match word.to_ascii_lowercase().as_str() {
+                    "true" => Token::Value(Value::Bool(true)),
+                    "false" => Token::Value(Value::Bool(false)),
+                    "null" => Token::Value(Value::Null),
+                    _ => {
+                        if let Ok(number) = word.parse::<serde_json::Number>() {
+                            Token::Value(Value::Number(number))
+                        } else {
+                            Token::Word(word)
+                        }
+                    }
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_if_word(&mut self, expected: &str) -> Result<bool, String> {
+        match self.next()? {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(expected) => Ok(true),
+            token => {
+                self.peeked = token;
+                Ok(false)
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next()? {
+            Some(token) if token == expected => Ok(()),
+            token => Err(format!("Expected {expected:?}, found {token:?}")),
+        }
+    }
+}
+
+// Removes the core schema URN from fully qualified attribute names
+fn strip_schema(attr: &str) -> &str {
+    for schema in [super::SCHEMA_USER, super::SCHEMA_GROUP] {
+        if attr
+            .get(..schema.len())
+            .map_or(false, |prefix| prefix.eq_ignore_ascii_case(schema))
+        {
+            if let Some(attr) = attr[schema.len()..].strip_prefix(':') {
+                return attr;
+            }
+        }
+    }
+    attr
+}
+
+// Resolves an attribute path such as 'name.formatted' or 'emails.value'
+pub fn resolve<'x>(resource: &'x Value, attr: &str) -> Vec<&'x Value> {
+    let mut attr = attr;
+    let mut current = vec![resource];
+
+    // Extension attributes are nested under their schema URN
+    if attr.starts_with("urn:") {
+        if let Some((key, value)) = resource.as_object().and_then(|object| {
+            object.iter().find(|(key, _)| {
+                attr.get(..key.len())
+                    .map_or(false, |prefix| prefix.eq_ignore_ascii_case(key))
+                    && attr.as_bytes().get(key.len()) == Some(&b':')
+            })
+        }) {
+            attr = &attr[key.len() + 1..];
+            current = vec![value];
+        } else {
+            return vec![];
+        }
+    }
+
+    for name in attr.split('.') {
+        current = current
+            .into_iter()
+            .flat_map(|value| match value {
+                Value::Array(items) => items.iter().collect::<Vec<_>>(),
+                value => vec![value],
+            })
+            .filter_map(|value| {
+                value.as_object().and_then(|object| {
+                    object
+                        .iter()
+                        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                        .map(|(_, value)| value)
+                })
+            })
+            .collect();
+    }
+
+    current
+}
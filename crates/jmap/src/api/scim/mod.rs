@@ -0,0 +1,1241 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+pub mod filter;
+
+use std::borrow::Cow;
+
+use directory::{
+    backend::internal::{
+        lookup::DirectoryStore, manage::ManageDirectory, PrincipalAction, PrincipalField,
+        PrincipalUpdate, PrincipalValue, SpecialSecrets,
+    },
+    core::policy::{PasswordPolicy, PasswordViolation},
+    DirectoryError, DirectoryInner, ManagementError, Principal, QueryBy, Type,
+};
+use http_body_util::{BodyExt, Full};
+use hyper::{body::Bytes, header, Method, StatusCode};
+use jmap_proto::error::request::RequestError;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use utils::{constant_time_eq, url_params::UrlParams};
+
+use crate::JMAP;
+
+use self::filter::{resolve, AttributePath, Filter};
+
+use super::{http::ToHttpResponse, HttpRequest, HttpResponse};
+
+pub const SCHEMA_USER: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+pub const SCHEMA_GROUP: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+pub const SCHEMA_EXTENSION: &str = "urn:ietf:params:scim:schemas:extension:stalwart:2.0:User";
+const SCHEMA_LIST: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+const SCHEMA_PATCH: &str = "urn:ietf:params:scim:api:messages:2.0:PatchOp";
+const SCHEMA_ERROR: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+const SCHEMA_SERVICE_PROVIDER: &str = "urn:ietf:params:scim:schemas:core:2.0:ServiceProviderConfig";
+const SCHEMA_RESOURCE_TYPE: &str = "urn:ietf:params:scim:schemas:core:2.0:ResourceType";
+
+const DISABLED_SECRET: &str = "$disabled$";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResourceType {
+    User,
+    Group,
+}
+
+#[derive(Debug)]
+pub struct ScimResponse {
+    status: StatusCode,
+    etag: Option<String>,
+    location: Option<String>,
+    body: Value,
+}
+
+#[derive(Debug)]
+pub struct ScimError {
+    status: StatusCode,
+    scim_type: Option<&'static str>,
+    detail: Cow<'static, str>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+struct ScimResourceRequest {
+    #[serde(rename = "userName")]
+    user_name: Option<String>,
+    #[serde(rename = "displayName")]
+    display_name: Option<String>,
+    name: Option<ScimName>,
+    active: Option<Value>,
+    password: Option<String>,
+    emails: Option<Vec<ScimMultiValue>>,
+    members: Option<Vec<ScimMultiValue>>,
+    #[serde(rename = "urn:ietf:params:scim:schemas:extension:stalwart:2.0:User")]
+    extension: Option<ScimExtension>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+struct ScimName {
+    formatted: Option<String>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+struct ScimMultiValue {
+    value: String,
+    primary: bool,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+struct ScimExtension {
+    quota: Option<Value>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ScimPatchRequest {
+    #[serde(default)]
+    schemas: Vec<String>,
+    #[serde(rename = "Operations")]
+    operations: Vec<ScimPatchOperation>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ScimPatchOperation {
+    op: String,
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default)]
+    value: Option<Value>,
+}
+
+impl JMAP {
+    pub async fn handle_scim_request(
+        &self,
+        req: &HttpRequest,
+        body: Option<Vec<u8>>,
+        base_url: &str,
+    ) -> HttpResponse {
+        // SCIM is disabled unless at least one bearer token is configured
+        if self.core.jmap.scim_tokens.is_empty() {
+            return RequestError::not_found().into_http_response();
+        }
+
+        // SCIM clients authenticate with their own bearer tokens
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| {
+                h.split_once(' ')
+                    .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+                    .map(|(_, token)| token.trim())
+            })
+            .unwrap_or_default();
+        if !self
+            .core
+            .jmap
+            .scim_tokens
+            .iter()
+            .any(|t| constant_time_eq(t.as_bytes(), token.as_bytes()))
+        {
+            tracing::debug!(
+                context = "scim",
+                event = "auth-failed",
+                "Invalid or missing SCIM bearer token."
+            );
+            return ScimError::new(StatusCode::UNAUTHORIZED, "Invalid bearer token.")
+                .into_http_response();
+        }
+
+        // Provisioning is only supported by the internal directory
+        if !matches!(
+            &self.core.storage.directory.store,
+            DirectoryInner::Internal(_)
+        ) {
+            return ScimError::new(
+                StatusCode::NOT_IMPLEMENTED,
+                "SCIM provisioning requires the internal directory.",
+            )
+            .into_http_response();
+        }
+
+        let path = req
+            .uri()
+            .path()
+            .split('/')
+            .skip(3)
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>();
+        let base_url = format!("{}/scim/v2", base_url.trim_end_matches('/'));
+
+        let result = match (path.as_slice(), req.method()) {
+            (["ServiceProviderConfig"], &Method::GET) => Ok(self.scim_service_provider_config()),
+            (["ResourceTypes"], &Method::GET) => Ok(ScimResponse::new(json!({
+                "schemas": [SCHEMA_LIST],
+                "totalResults": 2,
+                "startIndex": 1,
+                "itemsPerPage": 2,
+                "Resources": [
+                    ResourceType::User.describe(&base_url),
+                    ResourceType::Group.describe(&base_url),
+                ],
+            }))),
+            (["ResourceTypes", name], &Method::GET) => match ResourceType::parse(name) {
+                Some(typ) => Ok(ScimResponse::new(typ.describe(&base_url))),
+                None => Err(ScimError::not_found()),
+            },
+            ([resource], method) => match ResourceType::parse(resource) {
+                Some(typ) => match *method {
+                    Method::GET => self.scim_list(typ, req, &base_url).await,
+                    Method::POST => self.scim_create(typ, body, &base_url).await,
+                    _ => Err(ScimError::not_found()),
+                },
+                None => Err(ScimError::not_found()),
+            },
+            ([resource, id], method) => match (ResourceType::parse(resource), id.parse::<u32>()) {
+                (Some(typ), Ok(account_id)) => {
+                    self.scim_handle_resource(typ, account_id, method, req, body, &base_url)
+                        .await
+                }
+                _ => Err(ScimError::not_found()),
+            },
+            _ => Err(ScimError::not_found()),
+        };
+
+        match result {
+            Ok(response) => response.into_http_response(),
+            Err(err) => err.into_http_response(),
+        }
+    }
+
+    async fn scim_handle_resource(
+        &self,
+        typ: ResourceType,
+        account_id: u32,
+        method: &Method,
+        req: &HttpRequest,
+        body: Option<Vec<u8>>,
+        base_url: &str,
+    ) -> Result<ScimResponse, ScimError> {
+        let resource = self
+            .scim_fetch(typ, account_id, base_url)
+            .await?
+            .ok_or_else(ScimError::not_found)?;
+        let version = resource["meta"]["version"].as_str().unwrap_or_default();
+
+        // Validate preconditions
+        if let Some(if_match) = req
+            .headers()
+            .get(header::IF_MATCH)
+            .and_then(|h| h.to_str().ok())
+        {
+            if !if_match
+                .split(',')
+                .any(|etag| matches!(etag.trim(), "*") || etag.trim() == version)
+            {
+                return Err(ScimError::new(
+                    StatusCode::PRECONDITION_FAILED,
+                    "Resource has been modified.",
+                ));
+            }
+        }
+
+        match *method {
+            Method::GET => {
+                if req
+                    .headers()
+                    .get(header::IF_NONE_MATCH)
+                    .and_then(|h| h.to_str().ok())
+                    .map_or(false, |h| h.split(',').any(|etag| etag.trim() == version))
+                {
+                    Ok(ScimResponse::new(Value::Null)
+                        .with_status(StatusCode::NOT_MODIFIED)
+                        .with_etag(version))
+                } else {
+                    Ok(ScimResponse::resource(resource))
+                }
+            }
+            Method::PUT => {
+                let request = parse_body::<ScimResourceRequest>(body)?;
+                let changes = self.scim_replace_changes(typ, request).await?;
+                self.scim_update(typ, account_id, changes, base_url).await
+            }
+            Method::PATCH => {
+                let request = parse_body::<ScimPatchRequest>(body)?;
+                if !request.schemas.is_empty() && !request.schemas.iter().any(|s| s == SCHEMA_PATCH)
+                {
+                    return Err(ScimError::bad_request(
+                        "invalidSyntax",
+                        "Missing PatchOp schema.",
+                    ));
+                }
+                let mut changes = Vec::new();
+                for operation in request.operations {
+                    self.scim_patch_changes(typ, &resource, operation, &mut changes)
+                        .await?;
+                }
+                self.scim_update(typ, account_id, changes, base_url).await
+            }
+            Method::DELETE => {
+                // Remove FTS index
+                self.core
+                    .storage
+                    .fts
+                    .remove_all(account_id)
+                    .await
+                    .map_err(ScimError::from)?;
+
+                // Delete account
+                self.core
+                    .storage
+                    .data
+                    .delete_account(QueryBy::Id(account_id))
+                    .await?;
+
+                // Remove entries from cache
+                self.inner.sessions.retain(|_, id| id.item != account_id);
+
+                Ok(ScimResponse::new(Value::Null).with_status(StatusCode::NO_CONTENT))
+            }
+            _ => Err(ScimError::not_found()),
+        }
+    }
+
+    async fn scim_list(
+        &self,
+        typ: ResourceType,
+        req: &HttpRequest,
+        base_url: &str,
+    ) -> Result<ScimResponse, ScimError> {
+        let params = UrlParams::new(req.uri().query());
+        let filter = params
+            .get("filter")
+            .map(Filter::parse)
+            .transpose()
+            .map_err(|err| ScimError::bad_request("invalidFilter", err))?;
+        let start_index = params.parse::<usize>("startIndex").unwrap_or(1).max(1);
+        let count = params
+            .parse::<usize>("count")
+            .unwrap_or(self.core.jmap.query_max_results)
+            .min(self.core.jmap.query_max_results);
+
+        let offset = start_index - 1;
+        let is_type =
+            |principal: &Principal<u32>| typ.principal_type() == principal.typ.into_base_type();
+
+        let mut resources = Vec::new();
+        let mut total = 0;
+        if let Some(name) = filter
+            .as_ref()
+            .filter(|_| typ == ResourceType::User)
+            .and_then(|f| f.as_equality("userName"))
+        {
+            // Avoid full scans when looking up users by name
+            if let Some(account_id) = self
+                .core
+                .storage
+                .data
+                .get_account_id(&name.to_lowercase())
+                .await?
+            {
+                if let Some(resource) = self.scim_fetch(typ, account_id, base_url).await? {
+                    total = 1;
+                    if offset == 0 && count > 0 {
+                        resources.push(resource);
+                    }
+                }
+            }
+        } else if let Some(filter) = filter
+            .as_ref()
+            .filter(|f| f.references(&["groups", "members", "meta", "usedQuota"]))
+        {
+            // Linked and volatile attributes have to be resolved for every candidate
+            let (_, principals) = self
+                .core
+                .storage
+                .data
+                .list_principals(is_type, 0, usize::MAX)
+                .await?;
+            for principal in principals {
+                let resource = self.scim_resource(typ, principal, base_url).await?;
+                if filter.matches(&resource) {
+                    if total >= offset && resources.len() < count {
+                        resources.push(resource);
+                    }
+                    total += 1;
+                }
+            }
+        } else {
+            let (matches, principals) = self
+                .core
+                .storage
+                .data
+                .list_principals(
+                    |principal| {
+                        is_type(principal)
+                            && filter.as_ref().map_or(true, |f| {
+                                f.matches(&Value::Object(typ.attributes(principal)))
+                            })
+                    },
+                    offset,
+                    count,
+                )
+                .await?;
+            total = matches;
+            for principal in principals {
+                resources.push(self.scim_resource(typ, principal, base_url).await?);
+            }
+        }
+
+        Ok(ScimResponse::new(json!({
+            "schemas": [SCHEMA_LIST],
+            "totalResults": total,
+            "startIndex": start_index,
+            "itemsPerPage": resources.len(),
+            "Resources": resources,
+        })))
+    }
+
+    async fn scim_create(
+        &self,
+        typ: ResourceType,
+        body: Option<Vec<u8>>,
+        base_url: &str,
+    ) -> Result<ScimResponse, ScimError> {
+        let request = parse_body::<ScimResourceRequest>(body)?;
+
+        let mut emails = request.emails.unwrap_or_default();
+        emails.sort_by_key(|email| !email.primary);
+        let description = request
+            .display_name
+            .clone()
+            .or_else(|| request.name.and_then(|name| name.formatted))
+            .filter(|name| !name.is_empty());
+        let (name, members) = match typ {
+            ResourceType::User => (request.user_name, Vec::new()),
+            ResourceType::Group => (
+                request.display_name,
+                self.scim_member_names(request.members.unwrap_or_default())
+                    .await?,
+            ),
+        };
+        let name = name
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| ScimError::bad_request("invalidValue", "Missing resource name."))?;
+        if let Some(password) = &request.password {
+            self.core
+                .jmap
+                .password_policy
+                .validate_plain_text(password)
+                .map_err(PasswordViolation::into_error)?;
+        }
+        let mut secrets = request.password.into_iter().collect::<Vec<_>>();
+        if request.active.map_or(false, |active| !parse_bool(&active)) {
+            secrets.push(DISABLED_SECRET.to_string());
+        }
+
+        let account_id = self
+            .core
+            .storage
+            .data
+            .create_account_with_policy(
+                Principal {
+                    id: 0,
+                    typ: typ.principal_type(),
+                    quota: request
+                        .extension
+                        .and_then(|ext| ext.quota)
+                        .map(|quota| parse_quota(&quota))
+                        .transpose()?
+                        .unwrap_or_default(),
+                    name,
+                    secrets,
+                    emails: emails.into_iter().map(|email| email.value).collect(),
+                    member_of: Vec::new(),
+                    description,
+                    time_zone: None,
+                    undelete_retention: 0,
+                },
+                members,
+                Some(&self.core.jmap.password_policy),
+            )
+            .await?;
+
+        let resource = self
+            .scim_fetch(typ, account_id, base_url)
+            .await?
+            .ok_or_else(ScimError::not_found)?;
+        let location = resource["meta"]["location"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+
+        Ok(ScimResponse::resource(resource)
+            .with_status(StatusCode::CREATED)
+            .with_location(location))
+    }
+
+    async fn scim_update(
+        &self,
+        typ: ResourceType,
+        account_id: u32,
+        changes: Vec<PrincipalUpdate>,
+        base_url: &str,
+    ) -> Result<ScimResponse, ScimError> {
+        if !changes.is_empty() {
+            let is_password_change = changes
+                .iter()
+                .any(|change| matches!(change.field, PrincipalField::Secrets));
+
+            self.core
+                .storage
+                .data
+                .update_account_with_policy(
+                    QueryBy::Id(account_id),
+                    changes,
+                    Some(&self.core.jmap.password_policy),
+                )
+                .await?;
+
+            if is_password_change {
+                // Remove entries from cache
+                self.inner.sessions.retain(|_, id| id.item != account_id);
+            }
+        }
+
+        self.scim_fetch(typ, account_id, base_url)
+            .await?
+            .map(ScimResponse::resource)
+            .ok_or_else(ScimError::not_found)
+    }
+
+    async fn scim_fetch(
+        &self,
+        typ: ResourceType,
+        account_id: u32,
+        base_url: &str,
+    ) -> Result<Option<Value>, ScimError> {
+        match self
+            .core
+            .storage
+            .data
+            .query(QueryBy::Id(account_id), false)
+            .await?
+        {
+            Some(principal) if typ.principal_type() == principal.typ.into_base_type() => {
+                self.scim_resource(typ, principal, base_url).await.map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn scim_resource(
+        &self,
+        typ: ResourceType,
+        principal: Principal<u32>,
+        base_url: &str,
+    ) -> Result<Value, ScimError> {
+        let account_id = principal.id;
+        let mut resource = typ.attributes(&principal);
+
+        match typ {
+            ResourceType::User => {
+                let member_of = self.core.storage.data.get_member_of(account_id).await?;
+                let mut groups = Vec::with_capacity(member_of.len());
+                for group_id in member_of {
+                    if let Some(group) = self
+                        .core
+                        .storage
+                        .data
+                        .query(QueryBy::Id(group_id), false)
+                        .await?
+                        .filter(|group| group.typ == Type::Group)
+                    {
+                        groups.push(json!({
+                            "value": group_id.to_string(),
+                            "display": group.description.unwrap_or(group.name),
+                            "$ref": format!("{base_url}/Groups/{group_id}"),
+                        }));
+                    }
+                }
+                resource.insert("groups".to_string(), Value::Array(groups));
+            }
+            ResourceType::Group => {
+                let mut members = Vec::new();
+                for member_id in self.core.storage.data.get_members(account_id).await? {
+                    if let Some(member) = self
+                        .core
+                        .storage
+                        .data
+                        .query(QueryBy::Id(member_id), false)
+                        .await?
+                    {
+                        let (member_type, display) = if member.typ == Type::Group {
+                            ("Group", member.description.unwrap_or(member.name))
+                        } else {
+                            ("User", member.name)
+                        };
+                        members.push(json!({
+                            "value": member_id.to_string(),
+                            "display": display,
+                            "type": member_type,
+                            "$ref": format!("{base_url}/{member_type}s/{member_id}"),
+                        }));
+                    }
+                }
+                resource.insert("members".to_string(), Value::Array(members));
+            }
+        }
+
+        // The version is calculated before adding volatile attributes
+        let mut resource = Value::Object(resource);
+        let version = format!(
+            "W/\"{}\"",
+            Sha256::digest(resource.to_string().as_bytes())
+                .iter()
+                .take(8)
+                .map(|b| format!("{b:02x}"))
+                .collect::<String>()
+        );
+        if typ == ResourceType::User {
+            resource[SCHEMA_EXTENSION]["usedQuota"] =
+                Value::from(self.get_used_quota(account_id).await.unwrap_or_default());
+        }
+        resource["meta"] = json!({
+            "resourceType": typ.name(),
+            "location": format!("{base_url}/{}s/{account_id}", typ.name()),
+            "version": version,
+        });
+
+        Ok(resource)
+    }
+
+    async fn scim_replace_changes(
+        &self,
+        typ: ResourceType,
+        request: ScimResourceRequest,
+    ) -> Result<Vec<PrincipalUpdate>, ScimError> {
+        let mut changes = Vec::new();
+
+        if typ == ResourceType::User {
+            if let Some(name) = request.user_name.filter(|name| !name.is_empty()) {
+                changes.push(PrincipalUpdate::set(
+                    PrincipalField::Name,
+                    PrincipalValue::String(name),
+                ));
+            }
+        }
+        changes.push(PrincipalUpdate::set(
+            PrincipalField::Description,
+            PrincipalValue::String(
+                request
+                    .display_name
+                    .or_else(|| request.name.and_then(|name| name.formatted))
+                    .unwrap_or_default(),
+            ),
+        ));
+        let mut emails = request.emails.unwrap_or_default();
+        emails.sort_by_key(|email| !email.primary);
+        changes.push(PrincipalUpdate::set(
+            PrincipalField::Emails,
+            PrincipalValue::StringList(emails.into_iter().map(|email| email.value).collect()),
+        ));
+
+        match typ {
+            ResourceType::User => {
+                if let Some(password) = request.password {
+                    set_password(&self.core.jmap.password_policy, password, &mut changes)?;
+                }
+                if let Some(active) = request.active {
+                    set_active(parse_bool(&active), &mut changes);
+                }
+                if let Some(quota) = request.extension.and_then(|ext| ext.quota) {
+                    changes.push(PrincipalUpdate::set(
+                        PrincipalField::Quota,
+                        PrincipalValue::Integer(parse_quota(&quota)?),
+                    ));
+                }
+            }
+            ResourceType::Group => {
+                changes.push(PrincipalUpdate::set(
+                    PrincipalField::Members,
+                    PrincipalValue::StringList(
+                        self.scim_member_names(request.members.unwrap_or_default())
+                            .await?,
+                    ),
+                ));
+            }
+        }
+
+        Ok(changes)
+    }
+
+    async fn scim_patch_changes(
+        &self,
+        typ: ResourceType,
+        resource: &Value,
+        operation: ScimPatchOperation,
+        changes: &mut Vec<PrincipalUpdate>,
+    ) -> Result<(), ScimError> {
+        let op = operation.op.to_ascii_lowercase();
+        if !matches!(op.as_str(), "add" | "replace" | "remove") {
+            return Err(ScimError::bad_request(
+                "invalidSyntax",
+                format!("Unsupported operation {:?}.", operation.op),
+            ));
+        }
+        let is_remove = op == "remove";
+        let value = operation.value.unwrap_or(Value::Null);
+
+        // Operations without a path contain a map of attributes
+        let path = match operation.path.filter(|path| !path.trim().is_empty()) {
+            Some(path) => AttributePath::parse(&path)
+                .map_err(|err| ScimError::bad_request("invalidPath", err))?,
+            None if !is_remove => {
+                if let Value::Object(attributes) = value {
+                    for (path, value) in attributes {
+                        Box::pin(self.scim_patch_changes(
+                            typ,
+                            resource,
+                            ScimPatchOperation {
+                                op: op.clone(),
+                                path: Some(path),
+                                value: Some(value),
+                            },
+                            changes,
+                        ))
+                        .await?;
+                    }
+                    return Ok(());
+                } else {
+                    return Err(ScimError::bad_request(
+                        "invalidValue",
+                        "Expected an object value.",
+                    ));
+                }
+            }
+            None => {
+                return Err(ScimError::bad_request(
+                    "noTarget",
+                    "Remove operations require a path.",
+                ))
+            }
+        };
+
+        let attr = path.attr.to_ascii_lowercase();
+        let sub_attr = path.sub_attr.as_deref().map(|s| s.to_ascii_lowercase());
+        match (attr.as_str(), sub_attr.as_deref(), typ) {
+            ("username", None, ResourceType::User) => {
+                if is_remove {
+                    return Err(ScimError::bad_request(
+                        "mutability",
+                        "userName is required.",
+                    ));
+                }
+                changes.push(PrincipalUpdate::set(
+                    PrincipalField::Name,
+                    PrincipalValue::String(parse_string(&value)?),
+                ));
+            }
+            ("displayname", None, _) | ("name", Some("formatted"), ResourceType::User) => {
+                changes.push(PrincipalUpdate::set(
+                    PrincipalField::Description,
+                    PrincipalValue::String(if is_remove {
+                        String::new()
+                    } else {
+                        parse_string(&value)?
+                    }),
+                ));
+            }
+            ("name", None, ResourceType::User) => {
+                changes.push(PrincipalUpdate::set(
+                    PrincipalField::Description,
+                    PrincipalValue::String(
+                        value
+                            .get("formatted")
+                            .and_then(|v| v.as_str())
+                            .filter(|_| !is_remove)
+                            .unwrap_or_default()
+                            .to_string(),
+                    ),
+                ));
+            }
+            ("active", None, ResourceType::User) if !is_remove => {
+                set_active(parse_bool(&value), changes);
+            }
+            ("password", None, ResourceType::User) if !is_remove => {
+                set_password(
+                    &self.core.jmap.password_policy,
+                    parse_string(&value)?,
+                    changes,
+                )?;
+            }
+            ("emails", _, _) | ("members", _, ResourceType::Group) => {
+                let (field, is_members) = if attr == "emails" {
+                    (PrincipalField::Emails, false)
+                } else {
+                    (PrincipalField::Members, true)
+                };
+
+                // Obtain the new values
+                let values = if !is_remove || !value.is_null() {
+                    let values = match (&value, sub_attr.as_deref()) {
+                        (Value::String(value), Some("value")) => vec![value.clone()],
+                        (Value::Array(values), None) => values
+                            .iter()
+                            .filter_map(|v| v.get("value").and_then(|v| v.as_str()))
+                            .map(|v| v.to_string())
+                            .collect(),
+                        (Value::Object(_), None) => value
+                            .get("value")
+                            .and_then(|v| v.as_str())
+                            .map(|v| vec![v.to_string()])
+                            .unwrap_or_default(),
+                        (Value::Null, _) => vec![],
+                        // Other sub-attributes such as 'type' or 'primary' are not stored
+                        _ => return Ok(()),
+                    };
+                    if is_members {
+                        let mut names = Vec::with_capacity(values.len());
+                        for value in values {
+                            names.push(self.scim_member_name(&value).await?);
+                        }
+                        names
+                    } else {
+                        values
+                    }
+                } else {
+                    vec![]
+                };
+
+                if let Some(filter) = &path.filter {
+                    // Remove or replace the values matching the filter
+                    for item in resolve(resource, &path.attr) {
+                        if filter.matches(item) {
+                            if let Some(id) = item.get("value").and_then(|v| v.as_str()) {
+                                let current = if is_members {
+                                    self.scim_member_name(id).await?
+                                } else {
+                                    id.to_string()
+                                };
+                                changes.push(PrincipalUpdate::remove_item(
+                                    field,
+                                    PrincipalValue::String(current),
+                                ));
+                            }
+                        }
+                    }
+                    if !is_remove {
+                        for value in values {
+                            changes.push(PrincipalUpdate::add_item(
+                                field,
+                                PrincipalValue::String(value),
+                            ));
+                        }
+                    }
+                } else if op == "replace" || (is_remove && value.is_null()) {
+                    changes.push(PrincipalUpdate::set(
+                        field,
+                        PrincipalValue::StringList(values),
+                    ));
+                } else {
+                    for value in values {
+                        changes.push(if is_remove {
+                            PrincipalUpdate::remove_item(field, PrincipalValue::String(value))
+                        } else {
+                            PrincipalUpdate::add_item(field, PrincipalValue::String(value))
+                        });
+                    }
+                }
+            }
+            ("groups", _, ResourceType::User) => {
+                return Err(ScimError::bad_request(
+                    "mutability",
+                    "Group memberships are managed through the Groups endpoint.",
+                ));
+            }
+            (attr, sub_attr, ResourceType::User)
+                if attr.eq_ignore_ascii_case(SCHEMA_EXTENSION)
+                    || attr
+                        .strip_suffix(":quota")
+                        .map_or(false, |attr| attr.eq_ignore_ascii_case(SCHEMA_EXTENSION)) =>
+            {
+                let quota = if is_remove {
+                    0
+                } else if attr.ends_with(":quota") && sub_attr.is_none() {
+                    parse_quota(&value)?
+                } else if let Some(quota) = value.get("quota") {
+                    parse_quota(quota)?
+                } else {
+                    return Ok(());
+                };
+                changes.push(PrincipalUpdate::set(
+                    PrincipalField::Quota,
+                    PrincipalValue::Integer(quota),
+                ));
+            }
+            _ => {
+                // Attributes such as 'externalId' or 'title' have no equivalent
+                tracing::debug!(
+                    context = "scim",
+                    event = "ignore",
+                    path = path.attr,
+                    "Ignoring unsupported SCIM attribute."
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn scim_member_names(
+        &self,
+        members: Vec<ScimMultiValue>,
+    ) -> Result<Vec<String>, ScimError> {
+        let mut names = Vec::with_capacity(members.len());
+        for member in members {
+            names.push(self.scim_member_name(&member.value).await?);
+        }
+        Ok(names)
+    }
+
+    async fn scim_member_name(&self, id: &str) -> Result<String, ScimError> {
+        match id.parse::<u32>() {
+            Ok(account_id) => self.core.storage.data.get_account_name(account_id).await?,
+            Err(_) => None,
+        }
+        .ok_or_else(|| {
+            ScimError::bad_request("invalidValue", format!("Member {id:?} does not exist."))
+        })
+    }
+
+    fn scim_service_provider_config(&self) -> ScimResponse {
+        ScimResponse::new(json!({
+            "schemas": [SCHEMA_SERVICE_PROVIDER],
+            "patch": {"supported": true},
+            "bulk": {"supported": false, "maxOperations": 0, "maxPayloadSize": 0},
+            "filter": {"supported": true, "maxResults": self.core.jmap.query_max_results},
+            "changePassword": {"supported": true},
+            "sort": {"supported": false},
+            "etag": {"supported": true},
+            "authenticationSchemes": [{
+                "type": "oauthbearertoken",
+                "name": "Bearer Token",
+                "description": "Authentication using a SCIM bearer token",
+                "primary": true,
+            }],
+        }))
+    }
+}
+
+impl ResourceType {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "Users" | "User" => Some(ResourceType::User),
+            "Groups" | "Group" => Some(ResourceType::Group),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ResourceType::User => "User",
+            ResourceType::Group => "Group",
+        }
+    }
+
+    fn principal_type(&self) -> Type {
+        match self {
+            ResourceType::User => Type::Individual,
+            ResourceType::Group => Type::Group,
+        }
+    }
+
+    // Attributes stored in the principal, used to evaluate filters without further lookups
+    fn attributes(&self, principal: &Principal<u32>) -> Map<String, Value> {
+        let mut resource = Map::new();
+        resource.insert("id".to_string(), Value::String(principal.id.to_string()));
+        let emails = principal
+            .emails
+            .iter()
+            .enumerate()
+            .map(|(idx, email)| json!({"value": email, "primary": idx == 0}))
+            .collect::<Vec<_>>();
+
+        match self {
+            ResourceType::User => {
+                resource.insert(
+                    "schemas".to_string(),
+                    json!([SCHEMA_USER, SCHEMA_EXTENSION]),
+                );
+                resource.insert(
+                    "userName".to_string(),
+                    Value::String(principal.name.clone()),
+                );
+                if let Some(description) = &principal.description {
+                    resource.insert("name".to_string(), json!({"formatted": description}));
+                    resource.insert(
+                        "displayName".to_string(),
+                        Value::String(description.clone()),
+                    );
+                }
+                resource.insert(
+                    "active".to_string(),
+                    Value::Bool(!principal.secrets.iter().any(|s| s.is_disabled())),
+                );
+                resource.insert("emails".to_string(), Value::Array(emails));
+                resource.insert(
+                    SCHEMA_EXTENSION.to_string(),
+                    json!({"quota": principal.quota}),
+                );
+            }
+            ResourceType::Group => {
+                resource.insert("schemas".to_string(), json!([SCHEMA_GROUP]));
+                resource.insert(
+                    "displayName".to_string(),
+                    Value::String(
+                        principal
+                            .description
+                            .clone()
+                            .unwrap_or_else(|| principal.name.clone()),
+                    ),
+                );
+                if !emails.is_empty() {
+                    resource.insert("emails".to_string(), Value::Array(emails));
+                }
+            }
+        }
+
+        resource
+    }
+
+    fn describe(&self, base_url: &str) -> Value {
+        let (schema, extensions) = match self {
+            ResourceType::User => (
+                SCHEMA_USER,
+                json!([{"schema": SCHEMA_EXTENSION, "required": false}]),
+            ),
+            ResourceType::Group => (SCHEMA_GROUP, json!([])),
+        };
+
+        json!({
+            "schemas": [SCHEMA_RESOURCE_TYPE],
+            "id": self.name(),
+            "name": self.name(),
+            "endpoint": format!("/{}s", self.name()),
+            "schema": schema,
+            "schemaExtensions": extensions,
+            "meta": {
+                "resourceType": "ResourceType",
+                "location": format!("{base_url}/ResourceTypes/{}", self.name()),
+            },
+        })
+    }
+}
+
+fn set_password(
+    policy: &PasswordPolicy,
+    password: String,
+    changes: &mut Vec<PrincipalUpdate>,
+) -> Result<(), ScimError> {
+    policy
+        .validate_plain_text(&password)
+        .map_err(PasswordViolation::into_error)?;
+    changes.push(PrincipalUpdate::remove_item(
+        PrincipalField::Secrets,
+        PrincipalValue::String(String::new()),
+    ));
+    changes.push(PrincipalUpdate {
+        action: PrincipalAction::AddItem,
+        field: PrincipalField::Secrets,
+        value: PrincipalValue::String(password),
+    });
+
+    Ok(())
+}
+
+fn set_active(active: bool, changes: &mut Vec<PrincipalUpdate>) {
+    let value = PrincipalValue::String(DISABLED_SECRET.to_string());
+    changes.push(if active {
+        PrincipalUpdate::remove_item(PrincipalField::Secrets, value)
+    } else {
+        PrincipalUpdate::add_item(PrincipalField::Secrets, value)
+    });
+}
+
+// Some identity providers send booleans as strings
+fn parse_bool(value: &Value) -> bool {
+    match value {
+        Value::Bool(value) => *value,
+        Value::String(value) => value.eq_ignore_ascii_case("true"),
+        _ => false,
+    }
+}
+
+fn parse_string(value: &Value) -> Result<String, ScimError> {
+    value
+        .as_str()
+        .map(|value| value.to_string())
+        .ok_or_else(|| ScimError::bad_request("invalidValue", "Expected a string value."))
+}
+
+fn parse_quota(value: &Value) -> Result<u64, ScimError> {
+    match value {
+        Value::Number(quota) => quota.as_u64(),
+        Value::String(quota) => quota.parse().ok(),
+        Value::Null => Some(0),
+        _ => None,
+    }
+    .ok_or_else(|| ScimError::bad_request("invalidValue", "Invalid quota."))
+}
+
+fn parse_body<T: serde::de::DeserializeOwned>(body: Option<Vec<u8>>) -> Result<T, ScimError> {
+    serde_json::from_slice::<T>(body.as_deref().unwrap_or_default())
+        .map_err(|err| ScimError::bad_request("invalidSyntax", err.to_string()))
+}
+
+impl ScimResponse {
+    fn new(body: Value) -> Self {
+        ScimResponse {
+            status: StatusCode::OK,
+            etag: None,
+            location: None,
+            body,
+        }
+    }
+
+    fn resource(body: Value) -> Self {
+        let etag = body["meta"]["version"].as_str().map(|v| v.to_string());
+        ScimResponse {
+            status: StatusCode::OK,
+            etag,
+            location: None,
+            body,
+        }
+    }
+
+    fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    fn with_etag(mut self, etag: impl Into<String>) -> Self {
+        self.etag = Some(etag.into());
+        self
+    }
+
+    fn with_location(mut self, location: String) -> Self {
+        self.location = Some(location);
+        self
+    }
+}
+
+impl ScimError {
+    fn new(status: StatusCode, detail: impl Into<Cow<'static, str>>) -> Self {
+        ScimError {
+            status,
+            scim_type: None,
+            detail: detail.into(),
+        }
+    }
+
+    fn bad_request(scim_type: &'static str, detail: impl Into<Cow<'static, str>>) -> Self {
+        ScimError {
+            status: StatusCode::BAD_REQUEST,
+            scim_type: Some(scim_type),
+            detail: detail.into(),
+        }
+    }
+
+    fn not_found() -> Self {
+        ScimError::new(StatusCode::NOT_FOUND, "Resource not found.")
+    }
+}
+
+impl From<DirectoryError> for ScimError {
+    fn from(err: DirectoryError) -> Self {
+        match err {
+            DirectoryError::Management(ManagementError::AlreadyExists { field, value }) => {
+                ScimError {
+                    status: StatusCode::CONFLICT,
+                    scim_type: Some("uniqueness"),
+                    detail: format!("A resource with {field} {value:?} already exists.").into(),
+                }
+            }
+            DirectoryError::Management(ManagementError::MissingField(field)) => {
+                ScimError::bad_request("invalidValue", format!("Missing field {field}."))
+            }
+            DirectoryError::Management(ManagementError::NotFound(item)) => {
+                ScimError::bad_request("invalidValue", format!("{item:?} does not exist."))
+            }
+            DirectoryError::Management(ManagementError::PasswordPolicy(violation)) => {
+                ScimError::bad_request("invalidValue", format!("{violation}."))
+            }
+            DirectoryError::Unsupported => {
+                ScimError::bad_request("mutability", "Requested change is not supported.")
+            }
+            err => {
+                tracing::warn!(
+                    context = "scim",
+                    event = "error",
+                    reason = ?err,
+                    "Directory error"
+                );
+
+                ScimError::new(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error.")
+            }
+        }
+    }
+}
+
+impl From<store::Error> for ScimError {
+    fn from(err: store::Error) -> Self {
+        tracing::error!(context = "scim", error = %err, "Database error");
+
+        ScimError::new(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error.")
+    }
+}
+
+impl ToHttpResponse for ScimResponse {
+    fn into_http_response(self) -> HttpResponse {
+        let mut response = hyper::Response::builder().status(self.status);
+        if let Some(etag) = self.etag {
+            response = response.header(header::ETAG, etag);
+        }
+        if let Some(location) = self.location {
+            response = response.header(header::LOCATION, location);
+        }
+        let body = if !self.body.is_null() {
+            response = response.header(header::CONTENT_TYPE, "application/scim+json");
+            Bytes::from(self.body.to_string())
+        } else {
+            Bytes::new()
+        };
+
+        response
+            .body(Full::new(body).map_err(|never| match never {}).boxed())
+            .unwrap()
+    }
+}
+
+impl ToHttpResponse for ScimError {
+    fn into_http_response(self) -> HttpResponse {
+        let mut body = json!({
+            "schemas": [SCHEMA_ERROR],
+            "status": self.status.as_u16().to_string(),
+            "detail": self.detail,
+        });
+        if let Some(scim_type) = self.scim_type {
+            body["scimType"] = Value::String(scim_type.to_string());
+        }
+
+        ScimResponse::new(body)
+            .with_status(self.status)
+            .into_http_response()
+    }
+}
@@ -9,7 +9,10 @@ use std::sync::Arc;
 use directory::QueryBy;
 use jmap_proto::{
     error::request::RequestError,
-    request::capability::{Capability, Session},
+    request::capability::{
+        Capabilities, Capability, PrincipalsAccountCapabilities, PrincipalsOwnerCapabilities,
+        Session,
+    },
     types::{acl::Acl, collection::Collection, id::Id},
 };
 
@@ -23,8 +26,9 @@ impl JMAP {
     ) -> Result<Session, RequestError> {
         let mut session = Session::new(base_url, &self.core.jmap.capabilities);
         session.set_state(access_token.state());
+        let primary_id: Id = access_token.primary_id().into();
         session.set_primary_account(
-            access_token.primary_id().into(),
+            primary_id,
             access_token.name.clone(),
             access_token
                 .description
@@ -33,9 +37,25 @@ impl JMAP {
             None,
             &self.core.jmap.capabilities.account,
         );
+        session.set_account_capability(
+            primary_id,
+            Capability::Principals,
+            Capabilities::PrincipalsAccount(PrincipalsAccountCapabilities {
+                current_user_principal_id: primary_id.into(),
+            }),
+        );
+        session.set_account_capability(
+            primary_id,
+            Capability::PrincipalsOwner,
+            Capabilities::PrincipalsOwner(PrincipalsOwnerCapabilities {
+                account_id_for_principal: primary_id,
+                principal_id: primary_id,
+            }),
+        );
 
         // Add secondary accounts
         for id in access_token.secondary_ids() {
+            let account_id = Id::from(*id);
             let is_personal = !access_token.is_member(*id);
             let is_readonly = is_personal
                 && self
@@ -44,7 +64,7 @@ impl JMAP {
                     .map_or(true, |ids| ids.is_empty());
 
             session.add_account(
-                (*id).into(),
+                account_id,
                 self.core
                     .storage
                     .directory
@@ -52,12 +72,22 @@ impl JMAP {
                     .await
                     .unwrap_or_default()
                     .map(|p| p.name)
-                    .unwrap_or_else(|| Id::from(*id).to_string()),
+                    .unwrap_or_else(|| account_id.to_string()),
                 is_personal,
                 is_readonly,
                 Some(&[Capability::Mail, Capability::Quota, Capability::Blob]),
                 &self.core.jmap.capabilities.account,
             );
+
+            // Each account is owned by the principal with the same id
+            session.set_account_capability(
+                account_id,
+                Capability::PrincipalsOwner,
+                Capabilities::PrincipalsOwner(PrincipalsOwnerCapabilities {
+                    account_id_for_principal: account_id,
+                    principal_id: account_id,
+                }),
+            );
         }
 
         Ok(session)
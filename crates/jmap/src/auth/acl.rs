@@ -11,14 +11,16 @@ use jmap_proto::{
     types::{
         acl::Acl,
         collection::Collection,
+        id::Id,
         property::Property,
         value::{AclGrant, MaybePatchValue, Value},
     },
 };
 use store::{
+    ahash::AHashSet,
     query::acl::AclQuery,
     roaring::RoaringBitmap,
-    write::{assert::HashedValue, ValueClass},
+    write::{assert::HashedValue, now, ValueClass},
     ValueKey,
 };
 use utils::map::bitmap::{Bitmap, BitmapItem};
@@ -405,6 +407,101 @@ impl JMAP {
         }
     }
 
+    pub async fn notify_acl_changes(
+        &self,
+        changed_by: u32,
+        account_id: u32,
+        mailbox_id: u32,
+        mailbox_name: Option<&str>,
+        acl_current: &[AclGrant],
+        acl_changes: &[AclGrant],
+    ) {
+        let mut changed_by_value = None;
+
+        for grantee_id in acl_current
+            .iter()
+            .chain(acl_changes.iter())
+            .map(|item| item.account_id)
+            .collect::<AHashSet<_>>()
+        {
+            let old_rights = acl_current
+                .iter()
+                .find(|item| item.account_id == grantee_id)
+                .map(|item| item.grants)
+                .unwrap_or_default();
+            let new_rights = acl_changes
+                .iter()
+                .find(|item| item.account_id == grantee_id)
+                .map(|item| item.grants)
+                .unwrap_or_default();
+            if old_rights == new_rights || grantee_id == account_id {
+                continue;
+            }
+
+            // Update the accounts pushed to the grantee's subscribers
+            self.update_shared_accounts(grantee_id).await;
+            if grantee_id == changed_by {
+                continue;
+            }
+
+            // Obtain the principal that changed the ACL
+            if changed_by_value.is_none() {
+                let principal = self
+                    .core
+                    .storage
+                    .directory
+                    .query(QueryBy::Id(changed_by), false)
+                    .await
+                    .unwrap_or_default()
+                    .unwrap_or_default();
+                changed_by_value = Value::Object(
+                    Object::with_capacity(3)
+                        .with_property(Property::PrincipalId, Id::from(changed_by))
+                        .with_property(Property::Name, principal.name)
+                        .with_property(
+                            Property::Email,
+                            principal
+                                .emails
+                                .into_iter()
+                                .next()
+                                .map(Value::Text)
+                                .unwrap_or_default(),
+                        ),
+                )
+                .into();
+            }
+
+            let notification = Object::with_capacity(8)
+                .with_property(Property::Created, now())
+                .with_property(Property::ChangedBy, changed_by_value.clone().unwrap())
+                .with_property(Property::ObjectType, "Mailbox")
+                .with_property(Property::ObjectAccountId, Id::from(account_id))
+                .with_property(Property::ObjectId, Id::from(mailbox_id))
+                .with_property(Property::OldRights, mailbox_rights(old_rights))
+                .with_property(Property::NewRights, mailbox_rights(new_rights))
+                .with_property(
+                    Property::Name,
+                    mailbox_name
+                        .map(|name| Value::Text(name.to_string()))
+                        .unwrap_or_default(),
+                );
+
+            if self
+                .share_notification_create(grantee_id, notification)
+                .await
+                .is_err()
+            {
+                tracing::warn!(
+                    event = "error",
+                    context = "share_notification",
+                    account_id = grantee_id,
+                    mailbox_id = mailbox_id,
+                    "Failed to create share notification."
+                );
+            }
+        }
+    }
+
     async fn map_acl_set(&self, acl_set: Vec<Value>) -> Result<Vec<AclGrant>, SetError> {
         let mut acls = Vec::with_capacity(acl_set.len() / 2);
         for item in acl_set.chunks_exact(2) {
@@ -497,3 +594,17 @@ impl EffectiveAcl for Object<Value> {
         acl
     }
 }
+
+fn mailbox_rights(acl: Bitmap<Acl>) -> Value {
+    Object::with_capacity(9)
+        .with_property(Property::MayReadItems, acl.contains(Acl::ReadItems))
+        .with_property(Property::MayAddItems, acl.contains(Acl::AddItems))
+        .with_property(Property::MayRemoveItems, acl.contains(Acl::RemoveItems))
+        .with_property(Property::MaySetSeen, acl.contains(Acl::ModifyItems))
+        .with_property(Property::MaySetKeywords, acl.contains(Acl::ModifyItems))
+        .with_property(Property::MayCreateChild, acl.contains(Acl::CreateChild))
+        .with_property(Property::MayRename, acl.contains(Acl::Modify))
+        .with_property(Property::MayDelete, acl.contains(Acl::Delete))
+        .with_property(Property::MaySubmit, acl.contains(Acl::Submit))
+        .into()
+}
@@ -7,10 +7,16 @@
 use std::{net::IpAddr, sync::Arc, time::Instant};
 
 use common::{
-    config::server::ServerProtocol, listener::limiter::InFlight, AuthFailureReason, AuthResult,
+    auth::{
+        sasl::{SaslStep, ScramSession},
+        webauthn::WebAuthnCredential,
+    },
+    config::server::ServerProtocol,
+    listener::limiter::InFlight,
+    AuthFailureReason, AuthResult,
 };
-use directory::{Principal, QueryBy};
-use hyper::header;
+use directory::{core::secret::verify_secret_hash, Principal, QueryBy};
+use hyper::{header, Method};
 use jmap_proto::error::request::RequestError;
 use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
@@ -52,6 +58,37 @@ impl JMAP {
                             .authenticate_plain(&account, &secret, remote_ip, ServerProtocol::Http)
                             .await
                         {
+                            AuthResult::Success(access_token)
+                                if self
+                                    .is_webauthn_required(access_token.primary_id(), &secret)
+                                    .await =>
+                            {
+                                // Password-only sessions are not cached, they are only valid
+                                // for requests that verify a WebAuthn assertion
+                                if req.method() == Method::POST
+                                    && matches!(
+                                        req.uri().path(),
+                                        "/api/oauth"
+                                            | "/api/account/auth"
+                                            | "/api/account/auth/webauthn"
+                                    )
+                                {
+                                    let access_token = Arc::new(access_token);
+                                    return Ok(Some((
+                                        self.is_account_allowed(&access_token).await?,
+                                        access_token,
+                                    )));
+                                }
+
+                                return Err(RequestError::blank(
+                                    403,
+                                    "Security key required",
+                                    concat!(
+                                        "This account is protected with a security key. ",
+                                        "Log in using OAuth or authenticate using an app password."
+                                    ),
+                                ));
+                            }
                             AuthResult::Success(access_token) => Some(access_token),
                             AuthResult::Failure(AuthFailureReason::MissingTotp) => {
                                 return Err(RequestError::blank(
@@ -63,6 +100,38 @@ impl JMAP {
                                     ),
                                 ));
                             }
+                            AuthResult::Failure(AuthFailureReason::PasswordExpired) => {
+                                // Expired passwords can only be used to choose a new one
+                                if req.method() == Method::POST
+                                    && req.uri().path() == "/api/account/auth"
+                                {
+                                    if let Some(access_token) =
+                                        self.authenticate_expired(&account, &secret).await
+                                    {
+                                        let access_token = Arc::new(access_token);
+                                        return Ok(Some((
+                                            self.is_account_allowed(&access_token).await?,
+                                            access_token,
+                                        )));
+                                    }
+                                }
+
+                                return Err(RequestError::blank(
+                                    403,
+                                    "Password expired",
+                                    "The password of this account has expired.",
+                                ));
+                            }
+                            AuthResult::Failure(AuthFailureReason::AccountLocked) => {
+                                return Err(RequestError::blank(
+                                    403,
+                                    "Account locked",
+                                    concat!(
+                                        "This account has been temporarily locked after too many ",
+                                        "failed login attempts."
+                                    ),
+                                ));
+                            }
                             _ => None,
                         }
                     } else {
@@ -77,16 +146,12 @@ impl JMAP {
                     // Enforce anonymous rate limit for bearer auth requests
                     self.is_anonymous_allowed(&remote_ip).await?;
 
-                    match self.validate_access_token("access_token", &token).await {
-                        Ok((account_id, _, _)) => self.get_access_token(account_id).await,
-                        Err(err) => {
-                            tracing::debug!(
-                                context = "authenticate_headers",
-                                err = err,
-                                "Failed to validate access token."
-                            );
-                            None
-                        }
+                    match self
+                        .authenticate_bearer(&token, remote_ip, ServerProtocol::Http)
+                        .await
+                    {
+                        AuthResult::Success(access_token) => Some(access_token),
+                        _ => None,
                     }
                 } else {
                     // Enforce anonymous rate limit
@@ -168,7 +233,10 @@ impl JMAP {
         {
             Ok(AuthResult::Success(principal)) => AuthResult::Success(AccessToken::new(principal)),
             Ok(AuthResult::Failure(reason)) => {
-                if !matches!(reason, AuthFailureReason::MissingTotp) {
+                if !matches!(
+                    reason,
+                    AuthFailureReason::MissingTotp | AuthFailureReason::PasswordExpired
+                ) {
                     let _ = self.is_auth_allowed_hard(&remote_ip).await;
                 }
                 AuthResult::Failure(reason)
@@ -177,6 +245,158 @@ impl JMAP {
         }
     }
 
+    // Expired sessions are not cached, they are only valid for the request
+    // that changes the password
+    async fn authenticate_expired(&self, username: &str, secret: &str) -> Option<AccessToken> {
+        match self
+            .core
+            .storage
+            .directory
+            .query(
+                QueryBy::Credentials(&Credentials::Plain {
+                    username: username.to_string(),
+                    secret: secret.to_string(),
+                }),
+                true,
+            )
+            .await
+        {
+            Ok(Some(principal)) => AccessToken::new(principal).into(),
+            Ok(None) => None,
+            Err(err) => {
+                tracing::warn!(
+                    context = "authenticate",
+                    event = "error",
+                    reason = %err,
+                    "Failed to query directory."
+                );
+                None
+            }
+        }
+    }
+
+    // Accounts with security keys enrolled can only log in with their password
+    // through a WebAuthn ceremony, app passwords are exempt
+    async fn is_webauthn_required(&self, account_id: u32, secret: &str) -> bool {
+        if account_id == u32::MAX {
+            return false;
+        }
+
+        match self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await
+        {
+            Ok(Some(principal)) => {
+                if WebAuthnCredential::from_principal(&principal).is_empty() {
+                    return false;
+                }
+                for app_secret in principal.secrets.iter().filter_map(|secret| {
+                    secret
+                        .strip_prefix("$app$")
+                        .and_then(|s| s.split_once('$'))
+                        .map(|(_, app_secret)| app_secret)
+                }) {
+                    if verify_secret_hash(app_secret, secret).await {
+                        return false;
+                    }
+                }
+
+                true
+            }
+            Ok(None) => false,
+            Err(err) => {
+                tracing::warn!(
+                    context = "authenticate",
+                    event = "error",
+                    reason = %err,
+                    "Failed to query directory."
+                );
+                true
+            }
+        }
+    }
+
+    pub async fn authenticate_bearer(
+        &self,
+        token: &str,
+        remote_ip: IpAddr,
+        protocol: ServerProtocol,
+    ) -> AuthResult<AccessToken> {
+        match self.validate_access_token("access_token", token).await {
+            Ok((account_id, _, _, _)) => {
+                return match self.get_access_token(account_id).await {
+                    Some(access_token) => AuthResult::Success(access_token),
+                    None => AuthResult::Failure(AuthFailureReason::InvalidCredentials),
+                };
+            }
+            Err(err) => {
+                tracing::debug!(
+                    context = "authenticate",
+                    err = err,
+                    "Failed to validate access token."
+                );
+                if self.core.jmap.oidc.is_none() {
+                    return AuthResult::Failure(AuthFailureReason::InvalidCredentials);
+                }
+            }
+        }
+
+        // Try validating the token with the external OpenID Connect provider
+        match self
+            .core
+            .authenticate(
+                &self.core.storage.directory,
+                &self.smtp.inner.ipc,
+                &Credentials::OAuthBearer {
+                    token: token.to_string(),
+                },
+                remote_ip,
+                protocol,
+                true,
+            )
+            .await
+        {
+            Ok(AuthResult::Success(principal)) => AuthResult::Success(AccessToken::new(principal)),
+            Ok(AuthResult::Failure(reason)) => {
+                let _ = self.is_auth_allowed_hard(&remote_ip).await;
+                AuthResult::Failure(reason)
+            }
+            Err(err) => AuthResult::Failure(AuthFailureReason::InternalError(err)),
+        }
+    }
+
+    pub async fn authenticate_scram(
+        &self,
+        session: &mut ScramSession,
+        message: &[u8],
+        remote_ip: IpAddr,
+        protocol: ServerProtocol,
+    ) -> SaslStep<AccessToken> {
+        match self
+            .core
+            .authenticate_scram(
+                &self.core.storage.directory,
+                &self.smtp.inner.ipc,
+                session,
+                message,
+                remote_ip,
+                protocol,
+                true,
+            )
+            .await
+        {
+            SaslStep::Challenge(challenge) => SaslStep::Challenge(challenge),
+            SaslStep::Success(principal) => SaslStep::Success(AccessToken::new(principal)),
+            SaslStep::Failure(reason) => {
+                let _ = self.is_auth_allowed_hard(&remote_ip).await;
+                SaslStep::Failure(reason)
+            }
+        }
+    }
+
     pub async fn get_access_token(&self, account_id: u32) -> Option<AccessToken> {
         match self
             .core
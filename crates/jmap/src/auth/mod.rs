@@ -36,11 +36,13 @@ pub struct AccessToken {
     pub description: Option<String>,
     pub quota: u64,
     pub is_superuser: bool,
+    pub password_changed_at: Option<u64>,
 }
 
 impl AccessToken {
     pub fn new(principal: Principal<u32>) -> Self {
         Self {
+            password_changed_at: principal.password_changed_at(),
             primary_id: principal.id,
             member_of: principal.member_of,
             access_to: Vec::new(),
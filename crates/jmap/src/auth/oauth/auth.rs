@@ -41,6 +41,9 @@ impl JMAP {
                     OAuthCodeRequest::Code {
                         client_id,
                         redirect_uri,
+                        scope,
+                        nonce,
+                        webauthn,
                     } => {
                         // Validate clientId
                         if client_id.len() > CLIENT_ID_MAX_LEN {
@@ -48,6 +51,23 @@ impl JMAP {
                                 details: "Client ID is invalid.".into(),
                             }
                             .into_http_response();
+                        } else if let Some(client) = self.core.jmap.oauth_clients.get(&client_id) {
+                            // Registered clients must use one of their redirect URIs
+                            if !redirect_uri
+                                .as_ref()
+                                .map_or(false, |uri| client.is_valid_redirect_uri(uri))
+                            {
+                                return ManagementApiError::Other {
+                                    details: "Redirect URI is not registered for this client."
+                                        .into(),
+                                }
+                                .into_http_response();
+                            }
+                        } else if self.core.jmap.oauth_require_registration {
+                            return ManagementApiError::Other {
+                                details: "Client ID is not registered.".into(),
+                            }
+                            .into_http_response();
                         } else if redirect_uri
                             .as_ref()
                             .map_or(false, |uri| !uri.starts_with("https://"))
@@ -58,6 +78,13 @@ impl JMAP {
                             .into_http_response();
                         }
 
+                        // Accounts with security keys enrolled require a WebAuthn assertion
+                        if let Err(response) =
+                            self.assert_webauthn_login(&access_token, webauthn).await
+                        {
+                            return response;
+                        }
+
                         // Generate client code
                         let client_code = thread_rng()
                             .sample_iter(Alphanumeric)
@@ -71,6 +98,8 @@ impl JMAP {
                             account_id: access_token.primary_id(),
                             client_id,
                             params: redirect_uri.unwrap_or_default(),
+                            scope,
+                            nonce,
                         })
                         .serialize();
 
@@ -102,9 +131,16 @@ impl JMAP {
                             },
                         })
                     }
-                    OAuthCodeRequest::Device { code } => {
+                    OAuthCodeRequest::Device { code, webauthn } => {
                         let mut success = false;
 
+                        // Device authorizations also issue tokens, the same assertion is required
+                        if let Err(response) =
+                            self.assert_webauthn_login(&access_token, webauthn).await
+                        {
+                            return response;
+                        }
+
                         // Obtain code
                         match self
                             .core
@@ -173,7 +209,13 @@ impl JMAP {
             .await
             .map(|mut p| p.remove("client_id"))
         {
-            Ok(Some(client_id)) if client_id.len() < CLIENT_ID_MAX_LEN => client_id,
+            Ok(Some(client_id))
+                if client_id.len() < CLIENT_ID_MAX_LEN
+                    && (!self.core.jmap.oauth_require_registration
+                        || self.core.jmap.oauth_clients.contains_key(&client_id)) =>
+            {
+                client_id
+            }
             Err(err) => return err,
             _ => {
                 return HtmlResponse::with_status(
@@ -211,6 +253,8 @@ impl JMAP {
             account_id: u32::MAX,
             client_id,
             params: device_code.clone(),
+            scope: None,
+            nonce: None,
         })
         .serialize();
 
@@ -6,6 +6,7 @@
 
 use std::collections::HashMap;
 
+use common::auth::webauthn::AssertionCredential;
 use hyper::{header::CONTENT_TYPE, StatusCode};
 use serde::{Deserialize, Serialize};
 
@@ -15,6 +16,7 @@ use crate::api::{
 };
 
 pub mod auth;
+pub mod openid;
 pub mod token;
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -50,6 +52,8 @@ pub struct OAuthCode {
     pub account_id: u32,
     pub client_id: String,
     pub params: String,
+    pub scope: Option<String>,
+    pub nonce: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -121,6 +125,8 @@ pub struct OAuthResponse {
     pub refresh_token: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -183,9 +189,17 @@ pub enum OAuthCodeRequest {
     Code {
         client_id: String,
         redirect_uri: Option<String>,
+        #[serde(default)]
+        scope: Option<String>,
+        #[serde(default)]
+        nonce: Option<String>,
+        #[serde(default)]
+        webauthn: Option<AssertionCredential>,
     },
     Device {
         code: String,
+        #[serde(default)]
+        webauthn: Option<AssertionCredential>,
     },
 }
 
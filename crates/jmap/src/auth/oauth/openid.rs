@@ -0,0 +1,226 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::SystemTime;
+
+use directory::QueryBy;
+use jmap_proto::error::request::RequestError;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    JMAP,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenIdMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
+    pub scopes_supported: Vec<String>,
+    pub response_types_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+}
+
+impl OpenIdMetadata {
+    pub fn new(base_url: impl AsRef<str>, algorithm: &str) -> Self {
+        let base_url = base_url.as_ref();
+        OpenIdMetadata {
+            issuer: base_url.into(),
+            authorization_endpoint: format!("{}/authorize/code", base_url),
+            token_endpoint: format!("{}/auth/token", base_url),
+            userinfo_endpoint: format!("{}/auth/userinfo", base_url),
+            jwks_uri: format!("{}/auth/jwks.json", base_url),
+            scopes_supported: vec![
+                "openid".to_string(),
+                "profile".to_string(),
+                "email".to_string(),
+                "offline_access".to_string(),
+            ],
+            response_types_supported: vec!["code".to_string()],
+            grant_types_supported: vec![
+                "authorization_code".to_string(),
+                "refresh_token".to_string(),
+                "urn:ietf:params:oauth:grant-type:device_code".to_string(),
+            ],
+            subject_types_supported: vec!["public".to_string()],
+            id_token_signing_alg_values_supported: vec![algorithm.to_string()],
+            token_endpoint_auth_methods_supported: vec![
+                "client_secret_basic".to_string(),
+                "client_secret_post".to_string(),
+                "none".to_string(),
+            ],
+            claims_supported: vec![
+                "iss".to_string(),
+                "sub".to_string(),
+                "aud".to_string(),
+                "exp".to_string(),
+                "iat".to_string(),
+                "nonce".to_string(),
+                "name".to_string(),
+                "preferred_username".to_string(),
+                "email".to_string(),
+                "email_verified".to_string(),
+            ],
+        }
+    }
+}
+
+impl JMAP {
+    pub fn handle_openid_metadata(&self, base_url: impl AsRef<str>) -> HttpResponse {
+        if let Some(signer) = &self.core.jmap.oidc_signer {
+            JsonResponse::new(OpenIdMetadata::new(base_url, signer.algorithm)).into_http_response()
+        } else {
+            RequestError::not_found().into_http_response()
+        }
+    }
+
+    pub fn handle_jwks_request(&self) -> HttpResponse {
+        if let Some(signer) = &self.core.jmap.oidc_signer {
+            JsonResponse::new(signer.jwks()).into_http_response()
+        } else {
+            RequestError::not_found().into_http_response()
+        }
+    }
+
+    pub async fn handle_userinfo_request(
+        &self,
+        req: &HttpRequest,
+        account_id: u32,
+    ) -> HttpResponse {
+        if self.core.jmap.oidc_signer.is_none() {
+            return RequestError::not_found().into_http_response();
+        }
+
+        // Only bearer tokens are accepted by the UserInfo endpoint
+        let token = if let Some((_, token)) = req
+            .headers()
+            .get(hyper::header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.split_once(' '))
+            .filter(|(mechanism, _)| mechanism.eq_ignore_ascii_case("bearer"))
+        {
+            token.trim()
+        } else {
+            return RequestError::unauthorized().into_http_response();
+        };
+
+        // Claims are limited to the scope granted to the token
+        let scope = match self.validate_access_token("access_token", token).await {
+            Ok((token_account_id, _, _, Some(scope)))
+                if token_account_id == account_id
+                    && scope.split_ascii_whitespace().any(|s| s == "openid") =>
+            {
+                scope
+            }
+            _ => return RequestError::forbidden().into_http_response(),
+        };
+
+        match self.user_claims(account_id, &scope).await {
+            Ok(claims) => JsonResponse::new(Value::Object(claims)).into_http_response(),
+            Err(err) => {
+                tracing::debug!(
+                    context = "oauth",
+                    event = "userinfo",
+                    account_id = account_id,
+                    reason = err,
+                    "Failed to obtain user claims."
+                );
+                RequestError::unauthorized().into_http_response()
+            }
+        }
+    }
+
+    pub async fn issue_id_token(
+        &self,
+        account_id: u32,
+        client_id: &str,
+        scope: &str,
+        nonce: Option<&str>,
+        issuer: &str,
+    ) -> Result<String, &'static str> {
+        let signer = self
+            .core
+            .jmap
+            .oidc_signer
+            .as_ref()
+            .ok_or("OpenID Connect is not configured.")?;
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let mut claims = self.user_claims(account_id, scope).await?;
+        claims.insert("iss".to_string(), json!(issuer));
+        claims.insert("aud".to_string(), json!(client_id));
+        claims.insert("iat".to_string(), json!(now));
+        claims.insert(
+            "exp".to_string(),
+            json!(now + self.core.jmap.oauth_expiry_token),
+        );
+        if let Some(nonce) = nonce {
+            claims.insert("nonce".to_string(), json!(nonce));
+        }
+
+        signer.sign(&Value::Object(claims))
+    }
+
+    async fn user_claims(
+        &self,
+        account_id: u32,
+        scope: &str,
+    ) -> Result<Map<String, Value>, &'static str> {
+        let (name, description, email) = if account_id != u32::MAX {
+            let principal = self
+                .core
+                .storage
+                .directory
+                .query(QueryBy::Id(account_id), false)
+                .await
+                .map_err(|_| "Temporary lookup error")?
+                .ok_or("Account no longer exists")?;
+            (
+                principal.name,
+                principal.description,
+                principal.emails.into_iter().next(),
+            )
+        } else if let Some((name, _)) = &self.core.jmap.fallback_admin {
+            (name.clone(), None, None)
+        } else {
+            return Err("Invalid account id.");
+        };
+
+        let mut claims = Map::new();
+        claims.insert("sub".to_string(), json!(account_id.to_string()));
+        for scope in scope.split_ascii_whitespace() {
+            match scope {
+                "profile" => {
+                    claims.insert("preferred_username".to_string(), json!(name));
+                    claims.insert(
+                        "name".to_string(),
+                        json!(description.as_deref().unwrap_or(name.as_str())),
+                    );
+                }
+                "email" => {
+                    if let Some(email) = &email {
+                        claims.insert("email".to_string(), json!(email));
+                        claims.insert("email_verified".to_string(), json!(true));
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(claims)
+    }
+}
@@ -7,7 +7,7 @@
 use std::time::SystemTime;
 
 use directory::QueryBy;
-use hyper::StatusCode;
+use hyper::{header, StatusCode};
 use mail_builder::encoders::base64::base64_encode;
 use mail_parser::decoders::base64::base64_decode;
 use store::{
@@ -15,7 +15,10 @@ use store::{
     rand::{thread_rng, Rng},
     write::Bincode,
 };
-use utils::codec::leb128::{Leb128Iterator, Leb128Vec};
+use utils::{
+    codec::leb128::{Leb128Iterator, Leb128Vec},
+    constant_time_eq,
+};
 
 use crate::{
     api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
@@ -30,22 +33,47 @@ use super::{
 
 impl JMAP {
     // Token endpoint
-    pub async fn handle_token_request(&self, req: &mut HttpRequest) -> HttpResponse {
+    pub async fn handle_token_request(
+        &self,
+        req: &mut HttpRequest,
+        base_url: impl AsRef<str>,
+    ) -> HttpResponse {
+        // Obtain client credentials sent using HTTP Basic authentication
+        let basic_credentials = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.split_once(' '))
+            .filter(|(mechanism, _)| mechanism.eq_ignore_ascii_case("basic"))
+            .and_then(|(_, token)| base64_decode(token.trim().as_bytes()))
+            .and_then(|token| String::from_utf8(token).ok())
+            .and_then(|token| {
+                token
+                    .split_once(':')
+                    .map(|(id, secret)| (id.to_string(), secret.to_string()))
+            });
+
         // Parse form
         let params = match FormData::from_request(req, MAX_POST_LEN).await {
             Ok(params) => params,
             Err(err) => return err,
         };
         let grant_type = params.get("grant_type").unwrap_or_default();
+        let client_id = params
+            .get("client_id")
+            .or_else(|| basic_credentials.as_ref().map(|(id, _)| id.as_str()));
+        let client_secret = params.get("client_secret").or_else(|| {
+            basic_credentials
+                .as_ref()
+                .map(|(_, secret)| secret.as_str())
+        });
 
         let mut response = TokenResponse::error(ErrorType::InvalidGrant);
 
         if grant_type.eq_ignore_ascii_case("authorization_code") {
-            response = if let (Some(code), Some(client_id), Some(redirect_uri)) = (
-                params.get("code"),
-                params.get("client_id"),
-                params.get("redirect_uri"),
-            ) {
+            response = if let (Some(code), Some(client_id), Some(redirect_uri)) =
+                (params.get("code"), client_id, params.get("redirect_uri"))
+            {
                 // Obtain code
                 match self
                     .core
@@ -56,7 +84,10 @@ impl JMAP {
                 {
                     Ok(Some(auth_code)) => {
                         let oauth = auth_code.inner;
-                        if client_id != oauth.client_id || redirect_uri != oauth.params {
+                        if client_id != oauth.client_id
+                            || redirect_uri != oauth.params
+                            || !self.is_valid_client(client_id, client_secret)
+                        {
                             TokenResponse::error(ErrorType::InvalidClient)
                         } else if oauth.status == OAuthStatus::Authorized {
                             // Mark this token as issued
@@ -71,13 +102,56 @@ impl JMAP {
                             }
 
                             // Issue token
-                            self.issue_token(oauth.account_id, &oauth.client_id, true)
+                            match self
+                                .issue_token(
+                                    oauth.account_id,
+                                    &oauth.client_id,
+                                    oauth.scope.as_deref(),
+                                    true,
+                                )
                                 .await
-                                .map(TokenResponse::Granted)
-                                .unwrap_or_else(|err| {
+                            {
+                                Ok(mut granted) => {
+                                    // Issue an ID token for OpenID Connect requests
+                                    if oauth.scope.as_deref().map_or(false, |scope| {
+                                        scope.split_ascii_whitespace().any(|s| s == "openid")
+                                    }) && self.core.jmap.oidc_signer.is_some()
+                                    {
+                                        match self
+                                            .issue_id_token(
+                                                oauth.account_id,
+                                                &oauth.client_id,
+                                                oauth.scope.as_deref().unwrap_or_default(),
+                                                oauth.nonce.as_deref(),
+                                                base_url.as_ref(),
+                                            )
+                                            .await
+                                        {
+                                            Ok(id_token) => {
+                                                granted.id_token = id_token.into();
+                                            }
+                                            Err(err) => {
+                                                tracing::error!(
+                                                    "Failed to generate ID token: {}",
+                                                    err
+                                                );
+                                                return JsonResponse::with_status(
+                                                    StatusCode::BAD_REQUEST,
+                                                    TokenResponse::error(ErrorType::InvalidRequest),
+                                                )
+                                                .into_http_response();
+                                            }
+                                        }
+                                    }
+                                    granted.scope = oauth.scope;
+
+                                    TokenResponse::Granted(granted)
+                                }
+                                Err(err) => {
                                     tracing::error!("Failed to generate OAuth token: {}", err);
                                     TokenResponse::error(ErrorType::InvalidRequest)
-                                })
+                                }
+                            }
                         } else {
                             TokenResponse::error(ErrorType::InvalidGrant)
                         }
@@ -91,9 +165,7 @@ impl JMAP {
         } else if grant_type.eq_ignore_ascii_case("urn:ietf:params:oauth:grant-type:device_code") {
             response = TokenResponse::error(ErrorType::ExpiredToken);
 
-            if let (Some(device_code), Some(client_id)) =
-                (params.get("device_code"), params.get("client_id"))
-            {
+            if let (Some(device_code), Some(client_id)) = (params.get("device_code"), client_id) {
                 // Obtain code
                 match self
                     .core
@@ -104,7 +176,9 @@ impl JMAP {
                 {
                     Ok(Some(auth_code)) => {
                         let oauth = auth_code.inner;
-                        response = if oauth.client_id != client_id {
+                        response = if oauth.client_id != client_id
+                            || !self.is_valid_client(client_id, client_secret)
+                        {
                             TokenResponse::error(ErrorType::InvalidClient)
                         } else {
                             match oauth.status {
@@ -121,16 +195,18 @@ impl JMAP {
                                     }
 
                                     // Issue token
-                                    self.issue_token(oauth.account_id, &oauth.client_id, true)
-                                        .await
-                                        .map(TokenResponse::Granted)
-                                        .unwrap_or_else(|err| {
-                                            tracing::error!(
-                                                "Failed to generate OAuth token: {}",
-                                                err
-                                            );
-                                            TokenResponse::error(ErrorType::InvalidRequest)
-                                        })
+                                    self.issue_token(
+                                        oauth.account_id,
+                                        &oauth.client_id,
+                                        oauth.scope.as_deref(),
+                                        true,
+                                    )
+                                    .await
+                                    .map(TokenResponse::Granted)
+                                    .unwrap_or_else(|err| {
+                                        tracing::error!("Failed to generate OAuth token: {}", err);
+                                        TokenResponse::error(ErrorType::InvalidRequest)
+                                    })
                                 }
                                 OAuthStatus::Pending => {
                                     TokenResponse::error(ErrorType::AuthorizationPending)
@@ -147,15 +223,24 @@ impl JMAP {
             }
         } else if grant_type.eq_ignore_ascii_case("refresh_token") {
             if let Some(refresh_token) = params.get("refresh_token") {
-                if let Ok((account_id, client_id, time_left)) = self
+                if let Ok((account_id, client_id, time_left, scope)) = self
                     .validate_access_token("refresh_token", refresh_token)
                     .await
+                    .and_then(|(account_id, token_client_id, time_left, scope)| {
+                        // Confidential clients have to authenticate when refreshing tokens
+                        if self.is_valid_client(&token_client_id, client_secret) {
+                            Ok((account_id, token_client_id, time_left, scope))
+                        } else {
+                            Err("Client authentication failed.")
+                        }
+                    })
                 {
                     // TODO: implement revoking client ids
                     response = self
                         .issue_token(
                             account_id,
                             &client_id,
+                            scope.as_deref(),
                             time_left <= self.core.jmap.oauth_expiry_refresh_token_renew,
                         )
                         .await
@@ -205,6 +290,7 @@ impl JMAP {
         &self,
         account_id: u32,
         client_id: &str,
+        scope: Option<&str>,
         with_refresh_token: bool,
     ) -> Result<OAuthResponse, &'static str> {
         let password_hash = self.password_hash(account_id).await?;
@@ -215,6 +301,7 @@ impl JMAP {
                 account_id,
                 &password_hash,
                 client_id,
+                scope,
                 self.core.jmap.oauth_expiry_token,
             )?,
             token_type: "bearer".to_string(),
@@ -225,6 +312,7 @@ impl JMAP {
                     account_id,
                     &password_hash,
                     client_id,
+                    scope,
                     self.core.jmap.oauth_expiry_refresh_token,
                 )?
                 .into()
@@ -232,15 +320,30 @@ impl JMAP {
                 None
             },
             scope: None,
+            id_token: None,
         })
     }
 
+    fn is_valid_client(&self, client_id: &str, client_secret: Option<&str>) -> bool {
+        match self.core.jmap.oauth_clients.get(client_id) {
+            Some(client) => match (&client.secret, client_secret) {
+                (Some(secret), Some(client_secret)) => {
+                    constant_time_eq(secret.as_bytes(), client_secret.as_bytes())
+                }
+                (Some(_), None) => false,
+                (None, _) => true,
+            },
+            None => !self.core.jmap.oauth_require_registration,
+        }
+    }
+
     fn encode_access_token(
         &self,
         grant_type: &str,
         account_id: u32,
         password_hash: &str,
         client_id: &str,
+        scope: Option<&str>,
         expiry_in: u64,
     ) -> Result<String, &'static str> {
         // Build context
@@ -248,10 +351,13 @@ impl JMAP {
             return Err("ClientId is too long");
         }
         let key = self.core.jmap.oauth_key.clone();
-        let context = format!(
+        let mut context = format!(
             "{} {} {} {}",
             grant_type, client_id, account_id, password_hash
         );
+        if let Some(scope) = scope {
+            context = format!("{} {}", context, scope);
+        }
         let context_nonce = format!("{} nonce {}", grant_type, password_hash);
 
         // Set expiration time
@@ -282,6 +388,12 @@ impl JMAP {
         token.push_leb128(expiry);
         token.extend_from_slice(client_id.as_bytes());
 
+        // The granted scope follows the client id
+        if let Some(scope) = scope {
+            token.push(0);
+            token.extend_from_slice(scope.as_bytes());
+        }
+
         Ok(String::from_utf8(base64_encode(&token).unwrap_or_default()).unwrap())
     }
 
@@ -289,17 +401,28 @@ impl JMAP {
         &self,
         grant_type: &str,
         token: &str,
-    ) -> Result<(u32, String, u64), &'static str> {
+    ) -> Result<(u32, String, u64, Option<String>), &'static str> {
         // Base64 decode token
         let token = base64_decode(token.as_bytes()).ok_or("Failed to decode.")?;
-        let (account_id, expiry, client_id) = token
+        let (account_id, expiry, client_id, scope) = token
             .get((RANDOM_CODE_LEN + SymmetricEncrypt::ENCRYPT_TAG_LEN)..)
             .and_then(|bytes| {
                 let mut bytes = bytes.iter();
+                let account_id = bytes.next_leb128()?;
+                let expiry = bytes.next_leb128::<u64>()?;
+                let mut fields = bytes.as_slice().splitn(2, |&b| b == 0);
                 (
-                    bytes.next_leb128()?,
-                    bytes.next_leb128::<u64>()?,
-                    bytes.copied().map(char::from).collect::<String>(),
+                    account_id,
+                    expiry,
+                    fields
+                        .next()?
+                        .iter()
+                        .copied()
+                        .map(char::from)
+                        .collect::<String>(),
+                    fields
+                        .next()
+                        .map(|scope| scope.iter().copied().map(char::from).collect::<String>()),
                 )
                     .into()
             })
@@ -320,10 +443,13 @@ impl JMAP {
 
         // Build context
         let key = self.core.jmap.oauth_key.clone();
-        let context = format!(
+        let mut context = format!(
             "{} {} {} {}",
             grant_type, client_id, account_id, password_hash
         );
+        if let Some(scope) = &scope {
+            context = format!("{} {}", context, scope);
+        }
         let context_nonce = format!("{} nonce {}", grant_type, password_hash);
 
         // Calculate nonce
@@ -347,6 +473,6 @@ impl JMAP {
             .map_err(|_| "Failed to decrypt token.")?;
 
         // Success
-        Ok((account_id, client_id, expiry - now))
+        Ok((account_id, client_id, expiry - now, scope))
     }
 }
@@ -0,0 +1,127 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::get::{GetRequest, GetResponse, RequestArguments},
+    object::Object,
+    types::{collection::Collection, property::Property, value::Value},
+};
+
+use crate::JMAP;
+
+impl JMAP {
+    pub async fn calendar_get(
+        &self,
+        mut request: GetRequest<RequestArguments>,
+    ) -> Result<GetResponse, MethodError> {
+        let ids = request.unwrap_ids(self.core.jmap.get_max_objects)?;
+        let properties = request.unwrap_properties(&[
+            Property::Id,
+            Property::Name,
+            Property::Description,
+            Property::Color,
+            Property::SortOrder,
+            Property::IsSubscribed,
+            Property::IsVisible,
+            Property::IsDefault,
+            Property::Timezone,
+            Property::MyRights,
+        ]);
+        let account_id = request.account_id.document_id();
+        let calendar_ids = self
+            .get_document_ids(account_id, Collection::Calendar)
+            .await?
+            .unwrap_or_default();
+        let ids = if let Some(ids) = ids {
+            ids
+        } else {
+            calendar_ids
+                .iter()
+                .take(self.core.jmap.get_max_objects)
+                .map(Into::into)
+                .collect::<Vec<_>>()
+        };
+        let mut response = GetResponse {
+            account_id: request.account_id.into(),
+            state: self
+                .get_state(account_id, Collection::Calendar)
+                .await?
+                .into(),
+            list: Vec::with_capacity(ids.len()),
+            not_found: vec![],
+        };
+
+        for id in ids {
+            // Obtain the calendar object
+            let document_id = id.document_id();
+            if !calendar_ids.contains(document_id) {
+                response.not_found.push(id.into());
+                continue;
+            }
+            let mut calendar = if let Some(calendar) = self
+                .get_property::<Object<Value>>(
+                    account_id,
+                    Collection::Calendar,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                calendar
+            } else {
+                response.not_found.push(id.into());
+                continue;
+            };
+
+            let mut result = Object::with_capacity(properties.len());
+            for property in &properties {
+                let value = match property {
+                    Property::Id => Value::Id(id),
+                    Property::Name
+                    | Property::Description
+                    | Property::Color
+                    | Property::Timezone => calendar.remove(property),
+                    Property::SortOrder => {
+                        Value::UnsignedInt(calendar.get(property).as_uint().unwrap_or(0))
+                    }
+                    Property::IsSubscribed | Property::IsVisible => {
+                        Value::Bool(calendar.get(property).as_bool().unwrap_or(true))
+                    }
+                    Property::IsDefault => {
+                        Value::Bool(calendar.get(property).as_bool().unwrap_or(false))
+                    }
+                    Property::MyRights => {
+                        // Calendars can only be accessed by account members
+                        let mut rights = Object::with_capacity(11);
+                        for right in [
+                            "mayReadFreeBusy",
+                            "mayReadItems",
+                            "mayAddItems",
+                            "mayUpdatePrivate",
+                            "mayRSVP",
+                            "mayUpdateOwn",
+                            "mayUpdateAll",
+                            "mayRemoveOwn",
+                            "mayRemoveAll",
+                            "mayAdmin",
+                            "mayDelete",
+                        ] {
+                            rights.append(Property::parse(right), true);
+                        }
+                        Value::Object(rights)
+                    }
+                    _ => Value::Null,
+                };
+
+                result.append(property.clone(), value);
+            }
+            response.list.push(result);
+        }
+
+        Ok(response)
+    }
+}
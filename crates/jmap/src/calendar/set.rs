@@ -0,0 +1,436 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    error::{
+        method::MethodError,
+        set::{SetError, SetErrorType},
+    },
+    method::set::{SetRequest, SetResponse},
+    object::{
+        calendar::SetArguments,
+        index::{IndexAs, IndexProperty, ObjectIndexBuilder},
+        Object,
+    },
+    response::references::EvalObjectReferences,
+    types::{
+        collection::Collection,
+        property::Property,
+        state::StateChange,
+        type_state::DataType,
+        value::{MaybePatchValue, SetValue, Value},
+    },
+};
+use store::{
+    query::Filter,
+    write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder},
+};
+
+use crate::JMAP;
+
+pub static SCHEMA: &[IndexProperty] = &[
+    IndexProperty::new(Property::Name)
+        .index_as(IndexAs::Text {
+            tokenize: true,
+            index: true,
+        })
+        .required(),
+    IndexProperty::new(Property::SortOrder).index_as(IndexAs::Integer),
+];
+
+impl JMAP {
+    pub async fn calendar_set(
+        &self,
+        mut request: SetRequest<SetArguments>,
+    ) -> Result<SetResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let on_destroy_remove_events = request.arguments.on_destroy_remove_events.unwrap_or(false);
+        let mut calendar_ids = self
+            .get_document_ids(account_id, Collection::Calendar)
+            .await?
+            .unwrap_or_default();
+        let mut response = self
+            .prepare_set_response(&request, Collection::Calendar)
+            .await?;
+        let will_destroy = request.unwrap_destroy();
+
+        // Process creates
+        let mut changes = ChangeLogBuilder::new();
+        for (id, object) in request.unwrap_create() {
+            match self.calendar_set_item(object, None, &response) {
+                Ok(builder) => {
+                    let mut batch = BatchBuilder::new();
+                    batch
+                        .with_account_id(account_id)
+                        .with_collection(Collection::Calendar)
+                        .create_document()
+                        .custom(builder);
+                    let document_id = self.write_batch_expect_id(batch).await?;
+                    calendar_ids.insert(document_id);
+                    changes.log_insert(Collection::Calendar, document_id);
+                    response.created(id, document_id);
+                }
+                Err(err) => {
+                    response.not_created.append(id, err);
+                }
+            }
+        }
+
+        // Process updates
+        'update: for (id, object) in request.unwrap_update() {
+            // Make sure id won't be destroyed
+            if will_destroy.contains(&id) {
+                response.not_updated.append(id, SetError::will_destroy());
+                continue 'update;
+            }
+
+            // Obtain calendar
+            let document_id = id.document_id();
+            let calendar = if let Some(calendar) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account_id,
+                    Collection::Calendar,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                calendar
+            } else {
+                response.not_updated.append(id, SetError::not_found());
+                continue 'update;
+            };
+
+            match self.calendar_set_item(object, calendar.into(), &response) {
+                Ok(builder) => {
+                    let mut batch = BatchBuilder::new();
+                    batch
+                        .with_account_id(account_id)
+                        .with_collection(Collection::Calendar)
+                        .update_document(document_id)
+                        .custom(builder);
+                    if !batch.is_empty() {
+                        match self.core.storage.data.write(batch.build()).await {
+                            Ok(_) => {
+                                changes.log_update(Collection::Calendar, document_id);
+                            }
+                            Err(store::Error::AssertValueFailed) => {
+                                response.not_updated.append(
+                                    id,
+                                    SetError::forbidden().with_description(
+                                        "Another process modified this calendar, please try again.",
+                                    ),
+                                );
+                                continue 'update;
+                            }
+                            Err(err) => {
+                                tracing::error!(
+                                    event = "error",
+                                    context = "calendar_set",
+                                    account_id = account_id,
+                                    error = ?err,
+                                    "Failed to update calendar(s).");
+                                return Err(MethodError::ServerPartialFail);
+                            }
+                        }
+                    }
+                    response.updated.append(id, None);
+                }
+                Err(err) => {
+                    response.not_updated.append(id, err);
+                }
+            }
+        }
+
+        // Process deletions
+        let mut did_remove_events = false;
+        for id in will_destroy {
+            let document_id = id.document_id();
+            if !calendar_ids.contains(document_id) {
+                response.not_destroyed.append(id, SetError::not_found());
+                continue;
+            }
+
+            match self
+                .calendar_destroy(
+                    account_id,
+                    document_id,
+                    &mut changes,
+                    on_destroy_remove_events,
+                )
+                .await?
+            {
+                Ok(removed_events) => {
+                    did_remove_events |= removed_events;
+                    response.destroyed.push(id);
+                }
+                Err(err) => {
+                    response.not_destroyed.append(id, err);
+                }
+            }
+        }
+
+        // Write changes
+        if !changes.is_empty() {
+            let change_id = self.commit_changes(account_id, changes).await?;
+            let state_change =
+                StateChange::new(account_id).with_change(DataType::Calendar, change_id);
+            response.state_change = if did_remove_events {
+                state_change.with_change(DataType::CalendarEvent, change_id)
+            } else {
+                state_change
+            }
+            .into();
+            response.new_state = Some(change_id.into());
+        }
+
+        Ok(response)
+    }
+
+    pub async fn calendar_destroy(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        changes: &mut ChangeLogBuilder,
+        remove_events: bool,
+    ) -> Result<Result<bool, SetError>, MethodError> {
+        // Verify that the calendar is empty
+        let event_ids = self
+            .filter(
+                account_id,
+                Collection::CalendarEvent,
+                vec![Filter::eq(Property::CalendarIds, document_id)],
+            )
+            .await?
+            .results;
+        let did_remove_events = !event_ids.is_empty();
+        if did_remove_events {
+            if !remove_events {
+                return Ok(Err(SetError::new(SetErrorType::CalendarHasEvent)
+                    .with_description("Calendar is not empty.")));
+            }
+
+            // Events that belong to other calendars are only removed from this
+            // calendar, otherwise they are deleted.
+            for (event_id, event) in self
+                .get_properties::<HashedValue<Object<Value>>, _, _>(
+                    account_id,
+                    Collection::CalendarEvent,
+                    &event_ids,
+                    Property::Value,
+                )
+                .await?
+            {
+                let mut calendar_ids = event
+                    .inner
+                    .get(&Property::CalendarIds)
+                    .as_list()
+                    .cloned()
+                    .unwrap_or_default();
+                calendar_ids.retain(|id| {
+                    id.as_id()
+                        .map_or(false, |id| id.document_id() != document_id)
+                });
+
+                let mut batch = BatchBuilder::new();
+                batch
+                    .with_account_id(account_id)
+                    .with_collection(Collection::CalendarEvent);
+                if !calendar_ids.is_empty() {
+                    batch.update_document(event_id).custom(
+                        ObjectIndexBuilder::new(crate::calendar_event::set::SCHEMA)
+                            .with_current(event)
+                            .with_changes(
+                                Object::with_capacity(1)
+                                    .with_property(Property::CalendarIds, calendar_ids),
+                            ),
+                    );
+                    changes.log_update(Collection::CalendarEvent, event_id);
+                } else {
+                    batch.delete_document(event_id).custom(
+                        ObjectIndexBuilder::new(crate::calendar_event::set::SCHEMA)
+                            .with_current(event),
+                    );
+                    changes.log_delete(Collection::CalendarEvent, event_id);
+                }
+
+                match self.core.storage.data.write(batch.build()).await {
+                    Ok(_) => (),
+                    Err(store::Error::AssertValueFailed) => {
+                        return Ok(Err(SetError::forbidden().with_description(concat!(
+                            "Another process modified an event in this calendar ",
+                            "while deleting it, please try again."
+                        ))));
+                    }
+                    Err(err) => {
+                        tracing::error!(
+                            event = "error",
+                            context = "calendar_set",
+                            account_id = account_id,
+                            calendar_id = document_id,
+                            event_id = event_id,
+                            error = ?err,
+                            "Failed to update event while deleting calendar.");
+                        return Err(MethodError::ServerPartialFail);
+                    }
+                }
+            }
+        }
+
+        // Delete calendar
+        if let Some(calendar) = self
+            .get_property::<HashedValue<Object<Value>>>(
+                account_id,
+                Collection::Calendar,
+                document_id,
+                Property::Value,
+            )
+            .await?
+        {
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Calendar)
+                .delete_document(document_id)
+                .custom(ObjectIndexBuilder::new(SCHEMA).with_current(calendar));
+            match self.core.storage.data.write(batch.build()).await {
+                Ok(_) => {
+                    changes.log_delete(Collection::Calendar, document_id);
+                    Ok(Ok(did_remove_events))
+                }
+                Err(store::Error::AssertValueFailed) => Ok(Err(SetError::forbidden()
+                    .with_description(
+                        "Another process modified this calendar, please try again.",
+                    ))),
+                Err(err) => {
+                    tracing::error!(
+                        event = "error",
+                        context = "calendar_set",
+                        account_id = account_id,
+                        document_id = document_id,
+                        error = ?err,
+                        "Failed to delete calendar.");
+                    Err(MethodError::ServerPartialFail)
+                }
+            }
+        } else {
+            Ok(Err(SetError::not_found()))
+        }
+    }
+
+    fn calendar_set_item(
+        &self,
+        changes_: Object<SetValue>,
+        current: Option<HashedValue<Object<Value>>>,
+        response: &SetResponse,
+    ) -> Result<ObjectIndexBuilder, SetError> {
+        let mut changes = Object::with_capacity(changes_.properties.len());
+        for (property, value) in changes_.properties {
+            let value = match (&property, response.eval_object_references(value)?) {
+                (Property::Name, MaybePatchValue::Value(Value::Text(value))) => {
+                    let value = value.trim();
+                    if !value.is_empty() && value.len() < self.core.jmap.mailbox_name_max_len {
+                        Value::Text(value.to_string())
+                    } else {
+                        return Err(SetError::invalid_properties()
+                            .with_property(Property::Name)
+                            .with_description(if !value.is_empty() {
+                                "Calendar name is too long."
+                            } else {
+                                "Calendar name cannot be empty."
+                            }));
+                    }
+                }
+                (
+                    Property::Description | Property::Color | Property::Timezone,
+                    MaybePatchValue::Value(Value::Text(value)),
+                ) if value.len() < 1024 => Value::Text(value),
+                (Property::SortOrder, MaybePatchValue::Value(Value::UnsignedInt(value))) => {
+                    Value::UnsignedInt(value)
+                }
+                (
+                    Property::IsSubscribed | Property::IsVisible | Property::IsDefault,
+                    MaybePatchValue::Value(Value::Bool(value)),
+                ) => Value::Bool(value),
+                (
+                    Property::Description
+                    | Property::Color
+                    | Property::Timezone
+                    | Property::SortOrder
+                    | Property::IsSubscribed
+                    | Property::IsVisible
+                    | Property::IsDefault,
+                    MaybePatchValue::Value(Value::Null),
+                ) => {
+                    if current.is_none() {
+                        continue;
+                    }
+                    Value::Null
+                }
+                (property, _) => {
+                    return Err(SetError::invalid_properties()
+                        .with_property(property.clone())
+                        .with_description("Field could not be set."));
+                }
+            };
+            changes.append(property, value);
+        }
+
+        ObjectIndexBuilder::new(SCHEMA)
+            .with_changes(changes)
+            .with_current_opt(current)
+            .validate()
+    }
+
+    pub async fn calendar_default_id(&self, account_id: u32) -> Result<u32, MethodError> {
+        let calendar_ids = self
+            .get_document_ids(account_id, Collection::Calendar)
+            .await?
+            .unwrap_or_default();
+
+        // Use the calendar flagged as default, or the oldest one
+        if !calendar_ids.is_empty() {
+            for (document_id, calendar) in self
+                .get_properties::<Object<Value>, _, _>(
+                    account_id,
+                    Collection::Calendar,
+                    &calendar_ids,
+                    Property::Value,
+                )
+                .await?
+            {
+                if matches!(calendar.get(&Property::IsDefault), Value::Bool(true)) {
+                    return Ok(document_id);
+                }
+            }
+            return Ok(calendar_ids.min().unwrap_or_default());
+        }
+
+        // Create the default calendar
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Calendar)
+            .create_document()
+            .custom(
+                ObjectIndexBuilder::new(SCHEMA).with_changes(
+                    Object::with_capacity(2)
+                        .with_property(Property::Name, self.core.jmap.calendar_default_name.clone())
+                        .with_property(Property::IsDefault, true),
+                ),
+            );
+        let document_id = self.write_batch_expect_id(batch).await?;
+        let mut changes = ChangeLogBuilder::new();
+        changes.log_insert(Collection::Calendar, document_id);
+        let change_id = self.commit_changes(account_id, changes).await?;
+        self.broadcast_state_change(
+            StateChange::new(account_id).with_change(DataType::Calendar, change_id),
+        )
+        .await;
+
+        Ok(document_id)
+    }
+}
@@ -0,0 +1,129 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::get::{GetRequest, GetResponse, RequestArguments},
+    object::Object,
+    types::{collection::Collection, property::Property, value::Value},
+};
+
+use crate::JMAP;
+
+use super::event_to_jscalendar;
+
+impl JMAP {
+    pub async fn calendar_event_get(
+        &self,
+        mut request: GetRequest<RequestArguments>,
+    ) -> Result<GetResponse, MethodError> {
+        let ids = request.unwrap_ids(self.core.jmap.get_max_objects)?;
+        let properties = request.unwrap_properties(&[
+            Property::Id,
+            Property::CalendarIds,
+            Property::Uid,
+            Property::Title,
+            Property::Description,
+            Property::Start,
+            Property::Timezone,
+            Property::Duration,
+            Property::ShowWithoutTime,
+            Property::Status,
+            Property::RecurrenceRules,
+            Property::Participants,
+            Property::Locations,
+            Property::Sequence,
+            Property::Color,
+            Property::Created,
+            Property::Updated,
+        ]);
+        let account_id = request.account_id.document_id();
+        let event_ids = self
+            .get_document_ids(account_id, Collection::CalendarEvent)
+            .await?
+            .unwrap_or_default();
+        let ids = if let Some(ids) = ids {
+            ids
+        } else {
+            event_ids
+                .iter()
+                .take(self.core.jmap.get_max_objects)
+                .map(Into::into)
+                .collect::<Vec<_>>()
+        };
+        let mut response = GetResponse {
+            account_id: request.account_id.into(),
+            state: self
+                .get_state(account_id, Collection::CalendarEvent)
+                .await?
+                .into(),
+            list: Vec::with_capacity(ids.len()),
+            not_found: vec![],
+        };
+
+        for id in ids {
+            // Obtain the event object
+            let document_id = id.document_id();
+            if !event_ids.contains(document_id) {
+                response.not_found.push(id.into());
+                continue;
+            }
+            let mut event = if let Some(event) = self
+                .get_property::<Object<Value>>(
+                    account_id,
+                    Collection::CalendarEvent,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                event_to_jscalendar(event)
+            } else {
+                response.not_found.push(id.into());
+                continue;
+            };
+
+            let mut result = Object::with_capacity(properties.len());
+            for property in &properties {
+                let value = match property {
+                    Property::Id => Value::Id(id),
+                    Property::ShowWithoutTime => {
+                        Value::Bool(event.get(property).as_bool().unwrap_or(false))
+                    }
+                    Property::Sequence => {
+                        Value::UnsignedInt(event.get(property).as_uint().unwrap_or(0))
+                    }
+                    Property::Status => match event.remove(property) {
+                        Value::Null => Value::Text("confirmed".to_string()),
+                        status => status,
+                    },
+                    Property::Duration => match event.remove(property) {
+                        Value::Null => Value::Text("PT0S".to_string()),
+                        duration => duration,
+                    },
+                    Property::CalendarIds
+                    | Property::Uid
+                    | Property::Title
+                    | Property::Description
+                    | Property::Start
+                    | Property::Timezone
+                    | Property::RecurrenceRules
+                    | Property::Participants
+                    | Property::Locations
+                    | Property::Color
+                    | Property::Created
+                    | Property::Updated => event.remove(property),
+                    _ => Value::Null,
+                };
+
+                result.append(property.clone(), value);
+            }
+            response.list.push(result);
+        }
+
+        Ok(response)
+    }
+}
@@ -0,0 +1,656 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::fmt::Write;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use jmap_proto::{
+    object::Object,
+    types::{property::Property, value::Value},
+};
+
+use super::{format_duration, format_local_datetime, parse_duration, parse_local_datetime};
+
+pub struct ICalendar {
+    pub method: Option<String>,
+    pub events: Vec<Object<Value>>,
+}
+
+struct ContentLine {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl ICalendar {
+    /// Parses an iCalendar stream into JSCalendar events. Only the subset of
+    /// VEVENT properties that maps to the properties supported by
+    /// CalendarEvent objects is converted, everything else is ignored.
+    pub fn parse(text: &str) -> Option<ICalendar> {
+        let mut method = None;
+        let mut events = Vec::new();
+        let mut in_calendar = false;
+        let mut event: Option<Vec<ContentLine>> = None;
+        let mut depth = 0;
+
+        for line in unfold(text) {
+            let line = if let Some(line) = ContentLine::parse(&line) {
+                line
+            } else {
+                continue;
+            };
+
+            match line.name.as_str() {
+                "BEGIN" => {
+                    if line.value.eq_ignore_ascii_case("VCALENDAR") && depth == 0 {
+                        in_calendar = true;
+                    } else if line.value.eq_ignore_ascii_case("VEVENT") && depth == 1 {
+                        event = Some(Vec::new());
+                    }
+                    depth += 1;
+                }
+                "END" => {
+                    depth -= 1;
+                    if line.value.eq_ignore_ascii_case("VEVENT") && depth == 1 {
+                        if let Some(event) = event.take().and_then(parse_event) {
+                            events.push(event);
+                        }
+                    } else if line.value.eq_ignore_ascii_case("VCALENDAR") && depth == 0 {
+                        in_calendar = false;
+                    }
+                }
+                "METHOD" if depth == 1 && in_calendar => {
+                    method = Some(line.value.to_ascii_uppercase());
+                }
+                _ => {
+                    // Nested components such as VALARM are skipped
+                    if depth == 2 {
+                        if let Some(event) = &mut event {
+                            event.push(line);
+                        }
+                    }
+                }
+            }
+        }
+
+        if !events.is_empty() || method.is_some() {
+            Some(ICalendar { method, events })
+        } else {
+            None
+        }
+    }
+}
+
+fn parse_event(lines: Vec<ContentLine>) -> Option<Object<Value>> {
+    let mut event = Object::with_capacity(lines.len() + 1);
+    let mut start = None;
+    let mut end = None;
+    let mut participants: Vec<(String, Object<Value>)> = Vec::new();
+    let mut locations = Object::with_capacity(1);
+    let mut recurrence_rules = Vec::new();
+
+    event.append(Property::_T("@type".to_string()), "Event");
+
+    for line in lines {
+        match line.name.as_str() {
+            "UID" => {
+                event.set(Property::Uid, line.value);
+            }
+            "SUMMARY" => {
+                event.set(Property::Title, unescape_text(&line.value));
+            }
+            "DESCRIPTION" => {
+                event.set(Property::Description, unescape_text(&line.value));
+            }
+            "DTSTART" => {
+                start = parse_ical_datetime(&line);
+            }
+            "DTEND" => {
+                end = parse_ical_datetime(&line);
+            }
+            "DURATION" => {
+                if let Some(duration) = parse_duration(&line.value) {
+                    event.set(Property::Duration, format_duration(duration));
+                }
+            }
+            "STATUS" => {
+                let status = line.value.to_ascii_lowercase();
+                if ["confirmed", "cancelled", "tentative"].contains(&status.as_str()) {
+                    event.set(Property::Status, status);
+                }
+            }
+            "SEQUENCE" => {
+                if let Ok(sequence) = line.value.trim().parse::<u64>() {
+                    event.set(Property::Sequence, sequence);
+                }
+            }
+            "RRULE" => {
+                if let Some(rule) = parse_rrule(&line.value) {
+                    recurrence_rules.push(Value::Object(rule));
+                }
+            }
+            "LOCATION" => {
+                locations.append(
+                    Property::_T((locations.properties.len() + 1).to_string()),
+                    Object::with_capacity(2)
+                        .with_property(Property::_T("@type".to_string()), "Location")
+                        .with_property(Property::Name, unescape_text(&line.value)),
+                );
+            }
+            "ORGANIZER" | "ATTENDEE" => {
+                let email = if let Some(email) = parse_cal_address(&line.value) {
+                    email
+                } else {
+                    continue;
+                };
+                let role = if line.name == "ORGANIZER" {
+                    "owner"
+                } else {
+                    "attendee"
+                };
+                let participant = if let Some((_, participant)) = participants
+                    .iter_mut()
+                    .find(|(addr, _)| addr.eq_ignore_ascii_case(&email))
+                {
+                    participant
+                } else {
+                    let participant = Object::with_capacity(6)
+                        .with_property(Property::_T("@type".to_string()), "Participant")
+                        .with_property(Property::Email, email.clone())
+                        .with_property(
+                            Property::parse("sendTo"),
+                            Object::with_capacity(1)
+                                .with_property(Property::parse("imip"), format!("mailto:{email}")),
+                        )
+                        .with_property(Property::parse("roles"), Object::with_capacity(2));
+                    participants.push((email, participant));
+                    &mut participants.last_mut().unwrap().1
+                };
+                if let Some(Value::Object(roles)) =
+                    participant.properties.get_mut(&Property::parse("roles"))
+                {
+                    roles.set(Property::parse(role), true);
+                }
+
+                for (name, value) in line.params {
+                    match name.as_str() {
+                        "CN" => {
+                            participant.set(Property::Name, value);
+                        }
+                        "PARTSTAT" if role == "attendee" => {
+                            participant.set(
+                                Property::parse("participationStatus"),
+                                value.to_ascii_lowercase(),
+                            );
+                        }
+                        "RSVP" if role == "attendee" => {
+                            participant.set(
+                                Property::parse("expectReply"),
+                                value.eq_ignore_ascii_case("TRUE"),
+                            );
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // Events without a UID or start date cannot be stored
+    event.properties.get(&Property::Uid)?;
+    let (start, time_zone, is_date) = start?;
+    event.set(Property::Start, format_local_datetime(start));
+    if let Some(time_zone) = time_zone {
+        event.set(Property::Timezone, time_zone);
+    }
+    if is_date {
+        event.set(Property::ShowWithoutTime, true);
+    }
+    if let Some((end, _, _)) = end {
+        if !event.properties.contains_key(&Property::Duration) && end > start {
+            event.set(Property::Duration, format_duration(end - start));
+        }
+    }
+    if !recurrence_rules.is_empty() {
+        event.set(Property::RecurrenceRules, Value::List(recurrence_rules));
+    }
+    if !locations.properties.is_empty() {
+        event.set(Property::Locations, locations);
+    }
+    if !participants.is_empty() {
+        let mut map = Object::with_capacity(participants.len());
+        for (num, (_, participant)) in participants.into_iter().enumerate() {
+            map.append(Property::_T((num + 1).to_string()), participant);
+        }
+        event.set(Property::Participants, map);
+    }
+
+    Some(event)
+}
+
+/// Serializes a JSCalendar event as an iCalendar stream. When `reply_from` is
+/// set, only the organizer and the attendee with that address are included,
+/// as required for iTIP REPLY messages.
+pub fn build_ical(method: Option<&str>, event: &Object<Value>, reply_from: Option<&str>) -> String {
+    let mut ical = String::with_capacity(512);
+    ical.push_str("BEGIN:VCALENDAR\r\n");
+    ical.push_str("VERSION:2.0\r\n");
+    ical.push_str("PRODID:-//Stalwart Labs Ltd.//Stalwart Mail Server//EN\r\n");
+    if let Some(method) = method {
+        let _ = write!(ical, "METHOD:{method}\r\n");
+    }
+    ical.push_str("BEGIN:VEVENT\r\n");
+
+    if let Some(uid) = event.get(&Property::Uid).as_string() {
+        write_line(&mut ical, "UID", uid);
+    }
+    let _ = write!(
+        ical,
+        "DTSTAMP:{}Z\r\n",
+        format_ical_datetime(store::write::now() as i64)
+    );
+    if let Some(sequence) = event.get(&Property::Sequence).as_uint() {
+        let _ = write!(ical, "SEQUENCE:{sequence}\r\n");
+    }
+    if let Some(title) = event.get(&Property::Title).as_string() {
+        write_line(&mut ical, "SUMMARY", &escape_text(title));
+    }
+    if let Some(description) = event.get(&Property::Description).as_string() {
+        write_line(&mut ical, "DESCRIPTION", &escape_text(description));
+    }
+    if let Some(start) = event
+        .get(&Property::Start)
+        .as_string()
+        .and_then(parse_local_datetime)
+    {
+        let time_zone = event.get(&Property::Timezone).as_string();
+        if matches!(event.get(&Property::ShowWithoutTime), Value::Bool(true)) {
+            let _ = write!(
+                ical,
+                "DTSTART;VALUE=DATE:{}\r\n",
+                &format_ical_datetime(start)[..8]
+            );
+        } else if matches!(time_zone, Some("Etc/UTC" | "UTC")) {
+            let _ = write!(ical, "DTSTART:{}Z\r\n", format_ical_datetime(start));
+        } else if let Some(time_zone) = time_zone {
+            write_line(
+                &mut ical,
+                &format!("DTSTART;TZID={time_zone}"),
+                &format_ical_datetime(start),
+            );
+        } else {
+            let _ = write!(ical, "DTSTART:{}\r\n", format_ical_datetime(start));
+        }
+    }
+    if let Some(duration) = event.get(&Property::Duration).as_string() {
+        let _ = write!(ical, "DURATION:{duration}\r\n");
+    }
+    if let Some(status) = event.get(&Property::Status).as_string() {
+        let _ = write!(ical, "STATUS:{}\r\n", status.to_ascii_uppercase());
+    }
+    if let Some(rules) = event.get(&Property::RecurrenceRules).as_list() {
+        for rule in rules {
+            if let Some(rule) = rule.as_obj().and_then(build_rrule) {
+                write_line(&mut ical, "RRULE", &rule);
+            }
+        }
+    }
+    if let Some(locations) = event.get(&Property::Locations).as_obj() {
+        for location in locations.properties.values() {
+            if let Some(name) = location
+                .as_obj()
+                .and_then(|location| location.get(&Property::Name).as_string())
+            {
+                write_line(&mut ical, "LOCATION", &escape_text(name));
+            }
+        }
+    }
+    if let Some(participants) = event.get(&Property::Participants).as_obj() {
+        for participant in participants.properties.values() {
+            let participant = if let Some(participant) = participant.as_obj() {
+                participant
+            } else {
+                continue;
+            };
+            let email = if let Some(email) = participant_email(participant) {
+                email
+            } else {
+                continue;
+            };
+            let mut params = String::new();
+            if let Some(name) = participant.get(&Property::Name).as_string() {
+                let _ = write!(params, ";CN=\"{}\"", name.replace('"', "'"));
+            }
+
+            if participant_has_role(participant, "owner") {
+                write_line(
+                    &mut ical,
+                    &format!("ORGANIZER{params}"),
+                    &format!("mailto:{email}"),
+                );
+            }
+            if participant_has_role(participant, "attendee")
+                && reply_from.map_or(true, |addr| addr.eq_ignore_ascii_case(email))
+            {
+                if let Some(status) = participant
+                    .get(&Property::parse("participationStatus"))
+                    .as_string()
+                {
+                    let _ = write!(params, ";PARTSTAT={}", status.to_ascii_uppercase());
+                }
+                if matches!(
+                    participant.get(&Property::parse("expectReply")),
+                    Value::Bool(true)
+                ) {
+                    params.push_str(";RSVP=TRUE");
+                }
+                write_line(
+                    &mut ical,
+                    &format!("ATTENDEE{params}"),
+                    &format!("mailto:{email}"),
+                );
+            }
+        }
+    }
+
+    ical.push_str("END:VEVENT\r\n");
+    ical.push_str("END:VCALENDAR\r\n");
+    ical
+}
+
+pub fn participant_email(participant: &Object<Value>) -> Option<&str> {
+    participant.get(&Property::Email).as_string().or_else(|| {
+        participant
+            .get(&Property::parse("sendTo"))
+            .as_obj()
+            .and_then(|send_to| send_to.get(&Property::parse("imip")).as_string())
+            .and_then(|uri| {
+                uri.strip_prefix("mailto:")
+                    .or_else(|| uri.strip_prefix("MAILTO:"))
+            })
+    })
+}
+
+pub fn participant_has_role(participant: &Object<Value>, role: &str) -> bool {
+    participant
+        .get(&Property::parse("roles"))
+        .as_obj()
+        .map_or(false, |roles| {
+            matches!(roles.get(&Property::parse(role)), Value::Bool(true))
+        })
+}
+
+impl ContentLine {
+    fn parse(line: &str) -> Option<ContentLine> {
+        let mut in_quotes = false;
+        let mut value_pos = None;
+        let mut parts = Vec::new();
+        let mut last_pos = 0;
+
+        for (pos, ch) in line.char_indices() {
+            match ch {
+                '"' => in_quotes = !in_quotes,
+                ';' if !in_quotes => {
+                    parts.push(&line[last_pos..pos]);
+                    last_pos = pos + 1;
+                }
+                ':' if !in_quotes => {
+                    parts.push(&line[last_pos..pos]);
+                    value_pos = Some(pos + 1);
+                    break;
+                }
+                _ => (),
+            }
+        }
+
+        let mut parts = parts.into_iter();
+        let name = parts.next()?.trim().to_ascii_uppercase();
+        let params = parts
+            .filter_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                Some((
+                    key.trim().to_ascii_uppercase(),
+                    value.trim().trim_matches('"').to_string(),
+                ))
+            })
+            .collect();
+
+        Some(ContentLine {
+            name,
+            params,
+            value: line[value_pos?..].to_string(),
+        })
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if let Some(continuation) = line.strip_prefix([' ', '\t']) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(continuation);
+                continue;
+            }
+        }
+        if !line.is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+    lines
+}
+
+fn write_line(ical: &mut String, name: &str, value: &str) {
+    // Fold lines longer than 75 octets
+    let line = format!("{name}:{value}");
+    let mut line_len = 0;
+    for ch in line.chars() {
+        if line_len + ch.len_utf8() > 75 {
+            ical.push_str("\r\n ");
+            line_len = 1;
+        }
+        ical.push(ch);
+        line_len += ch.len_utf8();
+    }
+    ical.push_str("\r\n");
+}
+
+fn parse_ical_datetime(line: &ContentLine) -> Option<(i64, Option<String>, bool)> {
+    let value = line.value.trim();
+    if line
+        .param("VALUE")
+        .map_or(false, |v| v.eq_ignore_ascii_case("DATE"))
+        || value.len() == 8
+    {
+        NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|dt| (dt.and_utc().timestamp(), None, true))
+    } else if let Some(value) = value.strip_suffix(['Z', 'z']) {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .ok()
+            .map(|dt| (dt.and_utc().timestamp(), Some("Etc/UTC".to_string()), false))
+    } else {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .ok()
+            .map(|dt| {
+                (
+                    dt.and_utc().timestamp(),
+                    line.param("TZID").map(|tz| tz.to_string()),
+                    false,
+                )
+            })
+    }
+}
+
+fn format_ical_datetime(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .format("%Y%m%dT%H%M%S")
+        .to_string()
+}
+
+fn parse_cal_address(value: &str) -> Option<String> {
+    let value = value.trim();
+    let email = if value.len() > 7 && value[..7].eq_ignore_ascii_case("mailto:") {
+        &value[7..]
+    } else {
+        value
+    };
+    if email.contains('@') {
+        Some(email.to_lowercase())
+    } else {
+        None
+    }
+}
+
+fn parse_rrule(value: &str) -> Option<Object<Value>> {
+    let mut rule =
+        Object::with_capacity(6).with_property(Property::_T("@type".to_string()), "RecurrenceRule");
+    let mut has_frequency = false;
+
+    for part in value.split(';') {
+        let (key, value) = if let Some(kv) = part.split_once('=') {
+            kv
+        } else {
+            continue;
+        };
+        match key.trim().to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                rule.set(Property::parse("frequency"), value.to_ascii_lowercase());
+                has_frequency = true;
+            }
+            "INTERVAL" => {
+                if let Ok(interval) = value.parse::<u64>() {
+                    rule.set(Property::parse("interval"), interval);
+                }
+            }
+            "COUNT" => {
+                if let Ok(count) = value.parse::<u64>() {
+                    rule.set(Property::parse("count"), count);
+                }
+            }
+            "UNTIL" => {
+                let line = ContentLine {
+                    name: "UNTIL".to_string(),
+                    params: vec![],
+                    value: value.to_string(),
+                };
+                if let Some((until, _, _)) = parse_ical_datetime(&line) {
+                    rule.set(Property::parse("until"), format_local_datetime(until));
+                }
+            }
+            "BYDAY" => {
+                let mut by_day = Vec::new();
+                for day in value.split(',') {
+                    // Negative ordinals are not supported
+                    let day = day.trim();
+                    let split_pos = day.len().saturating_sub(2);
+                    let (nth, weekday) = day.split_at(split_pos);
+                    if nth.starts_with('-') || weekday.len() != 2 {
+                        continue;
+                    }
+                    let mut n_day = Object::with_capacity(3)
+                        .with_property(Property::_T("@type".to_string()), "NDay")
+                        .with_property(Property::parse("day"), weekday.to_ascii_lowercase());
+                    if let Ok(nth) = nth.trim_start_matches('+').parse::<u64>() {
+                        n_day.set(Property::parse("nthOfPeriod"), nth);
+                    }
+                    by_day.push(Value::Object(n_day));
+                }
+                if !by_day.is_empty() {
+                    rule.set(Property::parse("byDay"), Value::List(by_day));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if has_frequency {
+        Some(rule)
+    } else {
+        None
+    }
+}
+
+fn build_rrule(rule: &Object<Value>) -> Option<String> {
+    let mut rrule = format!(
+        "FREQ={}",
+        rule.get(&Property::parse("frequency"))
+            .as_string()?
+            .to_ascii_uppercase()
+    );
+    if let Some(interval) = rule.get(&Property::parse("interval")).as_uint() {
+        let _ = write!(rrule, ";INTERVAL={interval}");
+    }
+    if let Some(count) = rule.get(&Property::parse("count")).as_uint() {
+        let _ = write!(rrule, ";COUNT={count}");
+    }
+    if let Some(until) = rule
+        .get(&Property::parse("until"))
+        .as_string()
+        .and_then(parse_local_datetime)
+    {
+        let _ = write!(rrule, ";UNTIL={}", format_ical_datetime(until));
+    }
+    if let Some(by_day) = rule.get(&Property::parse("byDay")).as_list() {
+        let mut days = Vec::with_capacity(by_day.len());
+        for n_day in by_day.iter().filter_map(|day| day.as_obj()) {
+            if let Some(day) = n_day.get(&Property::parse("day")).as_string() {
+                if let Some(nth) = n_day.get(&Property::parse("nthOfPeriod")).as_uint() {
+                    days.push(format!("{nth}{}", day.to_ascii_uppercase()));
+                } else {
+                    days.push(day.to_ascii_uppercase());
+                }
+            }
+        }
+        if !days.is_empty() {
+            let _ = write!(rrule, ";BYDAY={}", days.join(","));
+        }
+    }
+
+    Some(rrule)
+}
+
+fn escape_text(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' => result.push_str("\\\\"),
+            ';' => result.push_str("\\;"),
+            ',' => result.push_str("\\,"),
+            '\n' => result.push_str("\\n"),
+            '\r' => (),
+            _ => result.push(ch),
+        }
+    }
+    result
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            match chars.next() {
+                Some('n' | 'N') => result.push('\n'),
+                Some(ch) => result.push(ch),
+                None => (),
+            }
+        } else {
+            result.push(ch);
+        }
+    }
+    result
+}
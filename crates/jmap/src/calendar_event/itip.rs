@@ -0,0 +1,642 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use common::listener::ServerInstance;
+use directory::QueryBy;
+use jmap_proto::{
+    error::method::MethodError,
+    object::{index::ObjectIndexBuilder, Object},
+    types::{
+        collection::Collection, id::Id, property::Property, state::StateChange,
+        type_state::DataType, value::Value,
+    },
+};
+use mail_builder::{
+    headers::{address::Address, content_type::ContentType},
+    mime::{BodyPart, MimePart},
+    MessageBuilder,
+};
+use smtp_proto::{MailFrom, RcptTo};
+use store::{
+    query::Filter,
+    write::{assert::HashedValue, log::ChangeLogBuilder, now, BatchBuilder},
+};
+
+use crate::JMAP;
+
+use super::{
+    event_from_jscalendar, event_to_jscalendar,
+    ical::{build_ical, participant_email, participant_has_role, ICalendar},
+    parse::calendar_parts,
+    set::SCHEMA,
+};
+
+// Properties an organizer may change through an iTIP REQUEST
+const ORGANIZER_PROPERTIES: &[Property] = &[
+    Property::Title,
+    Property::Description,
+    Property::Timezone,
+    Property::Start,
+    Property::Duration,
+    Property::Status,
+    Property::ShowWithoutTime,
+    Property::Sequence,
+    Property::RecurrenceRules,
+    Property::Participants,
+    Property::Locations,
+];
+
+struct ItipMessage {
+    method: &'static str,
+    event: Object<Value>,
+    reply_from: Option<String>,
+    rcpt_to: Vec<String>,
+}
+
+impl JMAP {
+    /// Sends iMIP messages to the participants of an event after it was created,
+    /// updated or destroyed. Organizers send REQUEST and CANCEL messages to the
+    /// attendees while attendees send a REPLY to the organizer whenever their
+    /// participation status changes.
+    pub async fn calendar_event_schedule(
+        &self,
+        account_id: u32,
+        instance: &Arc<ServerInstance>,
+        previous: Option<&Object<Value>>,
+        event: Option<&Object<Value>>,
+    ) {
+        let current = if let Some(current) = event.or(previous) {
+            current
+        } else {
+            return;
+        };
+        let addresses = match self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await
+        {
+            Ok(Some(principal)) => principal.emails,
+            _ => return,
+        };
+        let is_own = |email: &str| {
+            addresses
+                .iter()
+                .any(|addr| addr.eq_ignore_ascii_case(email))
+        };
+        let organizer = if let Some(organizer) = organizer_email(current) {
+            organizer.to_string()
+        } else {
+            return;
+        };
+
+        let mut messages = Vec::new();
+        if is_own(&organizer) {
+            let previous_attendees = previous
+                .map(|previous| attendee_emails(previous, &is_own))
+                .unwrap_or_default();
+
+            if let Some(event) = event {
+                let attendees = attendee_emails(event, &is_own);
+                let is_cancelled = event.get(&Property::Status).as_string() == Some("cancelled");
+
+                // Notify attendees that were removed from the event
+                let removed = previous_attendees
+                    .into_iter()
+                    .filter(|email| !attendees.contains(email))
+                    .collect::<Vec<_>>();
+                if !removed.is_empty() {
+                    messages.push(ItipMessage {
+                        method: "CANCEL",
+                        event: cancelled(previous.unwrap()),
+                        reply_from: None,
+                        rcpt_to: removed,
+                    });
+                }
+
+                if !attendees.is_empty() {
+                    messages.push(ItipMessage {
+                        method: if is_cancelled { "CANCEL" } else { "REQUEST" },
+                        event: event.clone(),
+                        reply_from: None,
+                        rcpt_to: attendees,
+                    });
+                }
+            } else if !previous_attendees.is_empty() {
+                messages.push(ItipMessage {
+                    method: "CANCEL",
+                    event: cancelled(current),
+                    reply_from: None,
+                    rcpt_to: previous_attendees,
+                });
+            }
+        } else if let Some(attendee) = participants(current)
+            .filter(|participant| participant_has_role(participant, "attendee"))
+            .filter_map(participant_email)
+            .find(|email| is_own(email))
+            .map(|email| email.to_string())
+        {
+            // Attendees that remove an event decline the invitation
+            let old_status =
+                previous.and_then(|previous| participation_status(previous, &attendee));
+            let (new_status, mut reply) = if let Some(event) = event {
+                (participation_status(event, &attendee), event.clone())
+            } else {
+                (Some("declined".to_string()), current.clone())
+            };
+
+            if let Some(new_status) =
+                new_status.filter(|new_status| old_status.as_ref() != Some(new_status))
+            {
+                set_participation_status(&mut reply, &attendee, new_status);
+                messages.push(ItipMessage {
+                    method: "REPLY",
+                    event: reply,
+                    reply_from: attendee.into(),
+                    rcpt_to: vec![organizer.clone()],
+                });
+            }
+        }
+
+        for message in messages {
+            let from = message.reply_from.as_deref().unwrap_or(organizer.as_str());
+            let title = message
+                .event
+                .get(&Property::Title)
+                .as_string()
+                .unwrap_or("(no title)");
+            let (subject, text_body) = match message.method {
+                "REQUEST" => (
+                    format!("Invitation: {title}"),
+                    format!("You have been invited to \"{title}\" by {from}.\r\n"),
+                ),
+                "CANCEL" => (
+                    format!("Cancelled: {title}"),
+                    format!("The event \"{title}\" has been cancelled by {from}.\r\n"),
+                ),
+                _ => {
+                    let status = participation_status(&message.event, from)
+                        .unwrap_or_else(|| "needs-action".to_string());
+                    (
+                        format!("Reply ({status}): {title}"),
+                        format!("{from} has replied \"{status}\" to the event \"{title}\".\r\n"),
+                    )
+                }
+            };
+            let ical = build_ical(
+                message.method.into(),
+                &message.event,
+                message.reply_from.as_deref(),
+            );
+            let raw_message = MessageBuilder::new()
+                .from(Address::new_address(None::<&str>, from))
+                .to(Address::new_list(
+                    message
+                        .rcpt_to
+                        .iter()
+                        .map(|email| Address::new_address(None::<&str>, email.as_str()))
+                        .collect(),
+                ))
+                .subject(subject)
+                .body(MimePart::new(
+                    ContentType::new("multipart/alternative"),
+                    BodyPart::Multipart(vec![
+                        MimePart::new(
+                            ContentType::new("text/plain"),
+                            BodyPart::Text(text_body.into()),
+                        ),
+                        MimePart::new(
+                            ContentType::new("text/calendar")
+                                .attribute("method", message.method)
+                                .attribute("charset", "utf-8"),
+                            BodyPart::Text(ical.into()),
+                        ),
+                    ]),
+                ))
+                .write_to_vec()
+                .unwrap_or_default();
+
+            if let Err(err) = self
+                .submit_message(
+                    instance,
+                    MailFrom {
+                        address: from.to_string(),
+                        ..Default::default()
+                    },
+                    message
+                        .rcpt_to
+                        .into_iter()
+                        .map(|address| RcptTo {
+                            address,
+                            ..Default::default()
+                        })
+                        .collect(),
+                    raw_message,
+                )
+                .await
+            {
+                tracing::warn!(
+                    event = "error",
+                    context = "calendar_itip",
+                    account_id = account_id,
+                    method = message.method,
+                    error = ?err,
+                    "Failed to send iMIP message."
+                );
+            }
+        }
+    }
+
+    /// Processes the iTIP messages contained in a message delivered to an
+    /// account, updating its calendars accordingly.
+    pub async fn calendar_itip_ingest(
+        &self,
+        account_id: u32,
+        raw_message: &[u8],
+        sender: &str,
+    ) -> Result<(), MethodError> {
+        let addresses = self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    event = "error",
+                    context = "calendar_itip",
+                    error = ?err,
+                    "Failed to query directory.");
+                MethodError::ServerPartialFail
+            })?
+            .map(|principal| principal.emails)
+            .unwrap_or_default();
+        let is_own = |email: &str| {
+            addresses
+                .iter()
+                .any(|addr| addr.eq_ignore_ascii_case(email))
+        };
+        let mut changes = ChangeLogBuilder::new();
+
+        for ical in calendar_parts(raw_message)
+            .into_iter()
+            .filter_map(|part| ICalendar::parse(&part))
+        {
+            let method = match ical.method.as_deref() {
+                Some(method @ ("REQUEST" | "REPLY" | "CANCEL")) => method.to_string(),
+                _ => continue,
+            };
+
+            for event in ical.events {
+                let uid = if let Some(uid) = event.get(&Property::Uid).as_string() {
+                    uid.to_string()
+                } else {
+                    continue;
+                };
+
+                // Only the organizer can send requests and cancellations
+                if method != "REPLY" && !is_organizer(&event, sender, &is_own) {
+                    tracing::debug!(
+                        context = "calendar_itip",
+                        account_id = account_id,
+                        uid = uid,
+                        sender = sender,
+                        "Ignoring iTIP {method} not sent by the organizer."
+                    );
+                    continue;
+                }
+
+                // Obtain the existing event
+                let existing_id = self
+                    .filter(
+                        account_id,
+                        Collection::CalendarEvent,
+                        vec![Filter::eq(Property::Uid, uid.as_str())],
+                    )
+                    .await?
+                    .results
+                    .min();
+                let existing = if let Some(document_id) = existing_id {
+                    self.get_property::<HashedValue<Object<Value>>>(
+                        account_id,
+                        Collection::CalendarEvent,
+                        document_id,
+                        Property::Value,
+                    )
+                    .await?
+                    .map(|event| (document_id, event))
+                } else {
+                    None
+                };
+
+                // Requests and cancellations can only modify events organized by the sender,
+                // while replies are only accepted for events organized by this account
+                if let Some((_, current)) = &existing {
+                    let is_authorized = if method == "REPLY" {
+                        organizer_email(&current.inner).map_or(false, &is_own)
+                    } else {
+                        is_organizer(&current.inner, sender, &is_own)
+                    };
+                    if !is_authorized {
+                        tracing::debug!(
+                            context = "calendar_itip",
+                            account_id = account_id,
+                            uid = uid,
+                            sender = sender,
+                            "Ignoring iTIP {method} for an event not organized by the sender."
+                        );
+                        continue;
+                    }
+                }
+
+                let (document_id, updated) = match (method.as_str(), existing) {
+                    ("REQUEST", None) => {
+                        let calendar_id = self.calendar_default_id(account_id).await?;
+                        let mut event =
+                            if let Some(event) = event_from_jscalendar(event, vec![calendar_id]) {
+                                event
+                            } else {
+                                continue;
+                            };
+                        let now = now();
+                        event.set(Property::Created, now);
+                        event.set(Property::Updated, now);
+
+                        let mut batch = BatchBuilder::new();
+                        batch
+                            .with_account_id(account_id)
+                            .with_collection(Collection::CalendarEvent)
+                            .create_document()
+                            .custom(ObjectIndexBuilder::new(SCHEMA).with_changes(event.clone()));
+                        let document_id = self.write_batch_expect_id(batch).await?;
+                        changes.log_insert(Collection::CalendarEvent, document_id);
+                        self.calendar_itip_notify(
+                            account_id,
+                            document_id,
+                            "created",
+                            sender,
+                            event,
+                        )
+                        .await;
+                        continue;
+                    }
+                    ("REQUEST", Some((document_id, current))) => {
+                        // Ignore out of date requests
+                        let sequence = event.get(&Property::Sequence).as_uint().unwrap_or(0);
+                        if sequence
+                            < current
+                                .inner
+                                .get(&Property::Sequence)
+                                .as_uint()
+                                .unwrap_or(0)
+                        {
+                            continue;
+                        }
+                        let event = if let Some(event) = event_from_jscalendar(event, vec![]) {
+                            event
+                        } else {
+                            continue;
+                        };
+
+                        // Only replace the properties controlled by the organizer, keeping
+                        // per-user ones such as the calendars or colour
+                        let mut updated = Object::with_capacity(ORGANIZER_PROPERTIES.len() + 1);
+                        for property in ORGANIZER_PROPERTIES {
+                            if let Some(value) = event.properties.get(property) {
+                                updated.append(property.clone(), value.clone());
+                            } else if current.inner.properties.contains_key(property) {
+                                updated.append(property.clone(), Value::Null);
+                            }
+                        }
+
+                        // Keep the participation status chosen by this account
+                        if let Some(Value::Object(participants)) =
+                            updated.properties.get_mut(&Property::Participants)
+                        {
+                            for participant in participants.properties.values_mut() {
+                                if let Some(participant) = participant.as_obj_mut() {
+                                    if let Some(status) = participant_email(participant)
+                                        .filter(|email| is_own(email))
+                                        .and_then(|email| {
+                                            participation_status(&current.inner, email)
+                                        })
+                                    {
+                                        participant
+                                            .set(Property::parse("participationStatus"), status);
+                                    }
+                                }
+                            }
+                        }
+                        updated.set(Property::Updated, now());
+                        (document_id, (current, updated))
+                    }
+                    ("CANCEL", Some((document_id, current))) => {
+                        let updated = Object::with_capacity(3)
+                            .with_property(Property::Status, "cancelled")
+                            .with_property(
+                                Property::Sequence,
+                                event.get(&Property::Sequence).as_uint().unwrap_or(0),
+                            )
+                            .with_property(Property::Updated, now());
+                        (document_id, (current, updated))
+                    }
+                    ("REPLY", Some((document_id, current))) => {
+                        // Update the participation status of the attendee that replied
+                        let mut participants_ = current.inner.get(&Property::Participants).clone();
+                        let mut has_changes = false;
+                        for reply in participants(&event)
+                            .filter(|participant| participant_has_role(participant, "attendee"))
+                        {
+                            let (email, status) = match (
+                                participant_email(reply),
+                                reply
+                                    .get(&Property::parse("participationStatus"))
+                                    .as_string(),
+                            ) {
+                                (Some(email), Some(status))
+                                    if email.eq_ignore_ascii_case(sender) =>
+                                {
+                                    (email, status)
+                                }
+                                _ => continue,
+                            };
+                            if let Some(participants_) = participants_.as_obj_mut() {
+                                for participant in participants_.properties.values_mut() {
+                                    if let Some(participant) = participant.as_obj_mut() {
+                                        if participant_email(participant)
+                                            .map_or(false, |addr| addr.eq_ignore_ascii_case(email))
+                                        {
+                                            participant.set(
+                                                Property::parse("participationStatus"),
+                                                status.to_string(),
+                                            );
+                                            has_changes = true;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        if !has_changes {
+                            continue;
+                        }
+                        let updated = Object::with_capacity(2)
+                            .with_property(Property::Participants, participants_)
+                            .with_property(Property::Updated, now());
+                        (document_id, (current, updated))
+                    }
+                    _ => continue,
+                };
+
+                // Update the existing event
+                let (current, updated) = updated;
+                let mut event = current.inner.clone();
+                for (property, value) in updated.properties.iter() {
+                    if value != &Value::Null {
+                        event.set(property.clone(), value.clone());
+                    } else {
+                        event.remove(property);
+                    }
+                }
+                let mut batch = BatchBuilder::new();
+                batch
+                    .with_account_id(account_id)
+                    .with_collection(Collection::CalendarEvent)
+                    .update_document(document_id)
+                    .custom(
+                        ObjectIndexBuilder::new(SCHEMA)
+                            .with_current(current)
+                            .with_changes(updated),
+                    );
+                self.write_batch(batch).await?;
+                changes.log_update(Collection::CalendarEvent, document_id);
+                self.calendar_itip_notify(account_id, document_id, "updated", sender, event)
+                    .await;
+            }
+        }
+
+        if !changes.is_empty() {
+            let change_id = self.commit_changes(account_id, changes).await?;
+            self.broadcast_state_change(
+                StateChange::new(account_id).with_change(DataType::CalendarEvent, change_id),
+            )
+            .await;
+        }
+
+        Ok(())
+    }
+
+    async fn calendar_itip_notify(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        notification_type: &str,
+        sender: &str,
+        event: Object<Value>,
+    ) {
+        let notification = Object::with_capacity(5)
+            .with_property(Property::Created, now())
+            .with_property(
+                Property::ChangedBy,
+                Object::with_capacity(2)
+                    .with_property(Property::Name, Value::Null)
+                    .with_property(Property::Email, sender.to_string()),
+            )
+            .with_property(Property::Type, notification_type.to_string())
+            .with_property(Property::CalendarEventId, Id::from(document_id))
+            .with_property(Property::Event, event_to_jscalendar(event));
+
+        if self
+            .calendar_event_notification_create(account_id, notification)
+            .await
+            .is_err()
+        {
+            tracing::warn!(
+                event = "error",
+                context = "calendar_itip",
+                account_id = account_id,
+                document_id = document_id,
+                "Failed to create calendar event notification."
+            );
+        }
+    }
+}
+
+/// Returns whether a message might contain an iCalendar part.
+pub fn has_calendar_part(raw_message: &[u8]) -> bool {
+    raw_message
+        .windows(13)
+        .any(|window| window.eq_ignore_ascii_case(b"text/calendar"))
+}
+
+fn participants(event: &Object<Value>) -> impl Iterator<Item = &Object<Value>> {
+    event
+        .get(&Property::Participants)
+        .as_obj()
+        .into_iter()
+        .flat_map(|participants| participants.properties.values())
+        .filter_map(|participant| participant.as_obj())
+}
+
+fn organizer_email(event: &Object<Value>) -> Option<&str> {
+    participants(event)
+        .find(|participant| participant_has_role(participant, "owner"))
+        .and_then(participant_email)
+}
+
+fn is_organizer(event: &Object<Value>, sender: &str, is_own: &impl Fn(&str) -> bool) -> bool {
+    organizer_email(event).map_or(false, |organizer| {
+        organizer.eq_ignore_ascii_case(sender) && !is_own(organizer)
+    })
+}
+
+fn attendee_emails(event: &Object<Value>, is_own: &impl Fn(&str) -> bool) -> Vec<String> {
+    let mut emails: Vec<String> = Vec::new();
+    for email in participants(event)
+        .filter(|participant| participant_has_role(participant, "attendee"))
+        .filter_map(participant_email)
+    {
+        if !is_own(email) && !emails.iter().any(|addr| addr.eq_ignore_ascii_case(email)) {
+            emails.push(email.to_string());
+        }
+    }
+    emails
+}
+
+fn participation_status(event: &Object<Value>, email: &str) -> Option<String> {
+    participants(event)
+        .find(|participant| {
+            participant_email(participant).map_or(false, |addr| addr.eq_ignore_ascii_case(email))
+        })
+        .and_then(|participant| {
+            participant
+                .get(&Property::parse("participationStatus"))
+                .as_string()
+        })
+        .map(|status| status.to_string())
+}
+
+fn set_participation_status(event: &mut Object<Value>, email: &str, status: String) {
+    if let Some(Value::Object(participants)) = event.properties.get_mut(&Property::Participants) {
+        for participant in participants.properties.values_mut() {
+            if let Some(participant) = participant.as_obj_mut() {
+                if participant_email(participant)
+                    .map_or(false, |addr| addr.eq_ignore_ascii_case(email))
+                {
+                    participant.set(Property::parse("participationStatus"), status.clone());
+                }
+            }
+        }
+    }
+}
+
+fn cancelled(event: &Object<Value>) -> Object<Value> {
+    let mut event = event.clone();
+    event.set(Property::Status, "cancelled");
+    event
+}
@@ -0,0 +1,162 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use chrono::NaiveDateTime;
+use jmap_proto::{
+    object::Object,
+    types::{date::UTCDate, id::Id, property::Property, value::Value},
+};
+
+pub mod get;
+pub mod ical;
+pub mod itip;
+pub mod parse;
+pub mod query;
+pub mod recurrence;
+pub mod set;
+
+/*
+ * Event start times are JSCalendar LocalDateTime values. Since no time zone
+ * database is available, they are indexed as "floating" timestamps, that is,
+ * the number of seconds since the epoch as if the local time was UTC.
+ */
+
+pub fn parse_local_datetime(value: &str) -> Option<i64> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+        .ok()
+        .map(|dt| dt.and_utc().timestamp())
+}
+
+pub fn format_local_datetime(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%dT%H:%M:%S")
+        .to_string()
+}
+
+pub fn parse_duration(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let value = value.strip_prefix('+').unwrap_or(value);
+    let chars = value.strip_prefix(['P', 'p'])?.chars();
+    let mut seconds = 0i64;
+    let mut num = None;
+    let mut in_time = false;
+    let mut has_value = false;
+
+    for ch in chars {
+        match ch {
+            '0'..='9' => {
+                num = Some(
+                    num.unwrap_or(0i64)
+                        .checked_mul(10)?
+                        .checked_add(ch as i64 - '0' as i64)?,
+                );
+            }
+            'T' | 't' if !in_time && num.is_none() => {
+                in_time = true;
+            }
+            _ => {
+                let multiplier = match (ch.to_ascii_uppercase(), in_time) {
+                    ('W', false) => 7 * 86400,
+                    ('D', false) => 86400,
+                    ('H', true) => 3600,
+                    ('M', true) => 60,
+                    ('S', true) => 1,
+                    _ => return None,
+                };
+                seconds = seconds.checked_add(num.take()?.checked_mul(multiplier)?)?;
+                has_value = true;
+            }
+        }
+    }
+
+    if has_value && num.is_none() {
+        Some(seconds)
+    } else {
+        None
+    }
+}
+
+pub fn format_duration(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    let days = seconds / 86400;
+    let hours = (seconds % 86400) / 3600;
+    let minutes = (seconds % 3600) / 60;
+    let secs = seconds % 60;
+
+    let mut result = String::from("P");
+    if days > 0 {
+        result.push_str(&format!("{days}D"));
+    }
+    if hours > 0 || minutes > 0 || secs > 0 || days == 0 {
+        result.push('T');
+        if hours > 0 {
+            result.push_str(&format!("{hours}H"));
+        }
+        if minutes > 0 {
+            result.push_str(&format!("{minutes}M"));
+        }
+        if secs > 0 || (hours == 0 && minutes == 0) {
+            result.push_str(&format!("{secs}S"));
+        }
+    }
+    result
+}
+
+/// Converts a stored event into its JSCalendar representation.
+pub fn event_to_jscalendar(mut event: Object<Value>) -> Object<Value> {
+    for (property, value) in event.properties.iter_mut() {
+        match property {
+            Property::CalendarIds => {
+                let ids = std::mem::take(value).try_unwrap_list().unwrap_or_default();
+                let mut obj = Object::with_capacity(ids.len());
+                for id in ids {
+                    if let Value::Id(id) = id {
+                        obj.append(Property::_T(id.to_string()), true);
+                    }
+                }
+                *value = Value::Object(obj);
+            }
+            Property::Start => {
+                if let Some(start) = value.as_uint() {
+                    *value = Value::Text(format_local_datetime(start as i64));
+                }
+            }
+            Property::Created | Property::Updated => {
+                if let Some(timestamp) = value.as_uint() {
+                    *value = Value::Date(UTCDate::from_timestamp(timestamp as i64));
+                }
+            }
+            _ => (),
+        }
+    }
+    event
+}
+
+/// Converts a JSCalendar event obtained from an iCalendar stream into its
+/// stored representation.
+pub fn event_from_jscalendar(
+    mut event: Object<Value>,
+    calendar_ids: Vec<u32>,
+) -> Option<Object<Value>> {
+    let start = event
+        .get(&Property::Start)
+        .as_string()
+        .and_then(parse_local_datetime)
+        .filter(|start| *start >= 0)?;
+    event.set(Property::Start, start as u64);
+    event.set(
+        Property::CalendarIds,
+        Value::List(
+            calendar_ids
+                .into_iter()
+                .map(|id| Value::Id(Id::from(id)))
+                .collect(),
+        ),
+    );
+    event.remove(&Property::_T("@type".to_string()));
+    Some(event)
+}
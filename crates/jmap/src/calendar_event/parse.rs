@@ -0,0 +1,106 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::parse::{ParseCalendarEventRequest, ParseCalendarEventResponse},
+    object::Object,
+    types::{property::Property, value::Value},
+};
+use mail_parser::{MessageParser, MimeHeaders};
+use utils::map::vec_map::VecMap;
+
+use crate::{auth::AccessToken, JMAP};
+
+use super::ical::ICalendar;
+
+impl JMAP {
+    pub async fn calendar_event_parse(
+        &self,
+        request: ParseCalendarEventRequest,
+        access_token: &AccessToken,
+    ) -> Result<ParseCalendarEventResponse, MethodError> {
+        if request.blob_ids.len() > self.core.jmap.mail_parse_max_items {
+            return Err(MethodError::RequestTooLarge);
+        }
+        let mut response = ParseCalendarEventResponse {
+            account_id: request.account_id,
+            parsed: VecMap::with_capacity(request.blob_ids.len()),
+            not_parsable: vec![],
+            not_found: vec![],
+        };
+
+        for blob_id in request.blob_ids {
+            // Fetch blob to parse
+            let raw_blob = match self.blob_download(&blob_id, access_token).await? {
+                Some(raw_blob) => raw_blob,
+                None => {
+                    response.not_found.push(blob_id);
+                    continue;
+                }
+            };
+
+            let events = calendar_parts(&raw_blob)
+                .into_iter()
+                .filter_map(|part| ICalendar::parse(&part))
+                .flat_map(|ical| ical.events)
+                .map(|event| filter_properties(event, request.properties.as_deref()))
+                .collect::<Vec<_>>();
+
+            if !events.is_empty() {
+                response.parsed.append(blob_id, events);
+            } else {
+                response.not_parsable.push(blob_id);
+            }
+        }
+
+        Ok(response)
+    }
+}
+
+/// Returns the iCalendar streams contained in a blob, which can either be
+/// an iCalendar file or an e-mail message with `text/calendar` parts.
+pub fn calendar_parts(raw_blob: &[u8]) -> Vec<String> {
+    let text = String::from_utf8_lossy(raw_blob);
+    if text
+        .trim_start()
+        .get(..15)
+        .map_or(false, |begin| begin.eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return vec![text.into_owned()];
+    }
+
+    MessageParser::new()
+        .parse(raw_blob)
+        .map(|message| {
+            message
+                .parts
+                .iter()
+                .filter(|part| {
+                    part.content_type().map_or(false, |ct| {
+                        ct.ctype().eq_ignore_ascii_case("text")
+                            && ct
+                                .subtype()
+                                .map_or(false, |st| st.eq_ignore_ascii_case("calendar"))
+                    })
+                })
+                .map(|part| String::from_utf8_lossy(part.contents()).into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn filter_properties(event: Object<Value>, properties: Option<&[Property]>) -> Object<Value> {
+    if let Some(properties) = properties {
+        let mut result = Object::with_capacity(properties.len());
+        for property in properties {
+            result.append(property.clone(), event.get(property).clone());
+        }
+        result
+    } else {
+        event
+    }
+}
@@ -0,0 +1,123 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::query::{
+        Comparator, Filter, QueryRequest, QueryResponse, RequestArguments, SortProperty,
+    },
+    object::Object,
+    types::{collection::Collection, property::Property, value::Value},
+};
+use store::query::{self};
+
+use crate::JMAP;
+
+use super::{
+    parse_duration,
+    recurrence::{event_overlaps, RecurrenceRule},
+};
+
+impl JMAP {
+    pub async fn calendar_event_query(
+        &self,
+        mut request: QueryRequest<RequestArguments>,
+    ) -> Result<QueryResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let mut filters = Vec::with_capacity(request.filter.len());
+        let mut after = None;
+        let mut before = None;
+
+        for cond in std::mem::take(&mut request.filter) {
+            match cond {
+                Filter::InCalendar(id) => {
+                    filters.push(query::Filter::eq(Property::CalendarIds, id.document_id()))
+                }
+                Filter::Uid(uid) => filters.push(query::Filter::eq(Property::Uid, uid)),
+                Filter::Title(text) | Filter::Text(text) => {
+                    filters.push(query::Filter::has_text(Property::Title, &text))
+                }
+                Filter::Before(date) => {
+                    // Events starting after the end of the range cannot overlap it
+                    let timestamp = date.timestamp();
+                    filters.push(query::Filter::lt(Property::Start, timestamp.max(0) as u64));
+                    before = Some(timestamp);
+                }
+                Filter::After(date) => {
+                    // Occurrences are expanded after filtering
+                    after = Some(date.timestamp());
+                }
+                Filter::And | Filter::Or | Filter::Not | Filter::Close => {
+                    filters.push(cond.into());
+                }
+                other => return Err(MethodError::UnsupportedFilter(other.to_string())),
+            }
+        }
+
+        let mut result_set = self
+            .filter(account_id, Collection::CalendarEvent, filters)
+            .await?;
+
+        // Remove events that do not have any occurrence within the time range
+        if (after.is_some() || before.is_some()) && !result_set.results.is_empty() {
+            let limit = self.core.jmap.calendar_max_occurrences;
+            for (document_id, event) in self
+                .get_properties::<Object<Value>, _, _>(
+                    account_id,
+                    Collection::CalendarEvent,
+                    &result_set.results,
+                    Property::Value,
+                )
+                .await?
+            {
+                let start = event.get(&Property::Start).as_uint().unwrap_or(0) as i64;
+                let duration = event
+                    .get(&Property::Duration)
+                    .as_string()
+                    .and_then(parse_duration)
+                    .unwrap_or(0);
+                let rules = event
+                    .get(&Property::RecurrenceRules)
+                    .as_list()
+                    .map(|rules| {
+                        rules
+                            .iter()
+                            .filter_map(|rule| rule.as_obj().and_then(RecurrenceRule::parse))
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+
+                if !event_overlaps(start, duration, &rules, after, before, limit) {
+                    result_set.results.remove(document_id);
+                }
+            }
+        }
+
+        let (response, paginate) = self.build_query_response(&result_set, &request).await?;
+
+        if let Some(paginate) = paginate {
+            // Parse sort criteria
+            let mut comparators = Vec::with_capacity(request.sort.as_ref().map_or(1, |s| s.len()));
+            for comparator in request
+                .sort
+                .and_then(|s| if !s.is_empty() { s.into() } else { None })
+                .unwrap_or_else(|| vec![Comparator::ascending(SortProperty::Start)])
+            {
+                comparators.push(match comparator.property {
+                    SortProperty::Start => {
+                        query::Comparator::field(Property::Start, comparator.is_ascending)
+                    }
+                    other => return Err(MethodError::UnsupportedSort(other.to_string())),
+                });
+            }
+
+            // Sort results
+            self.sort(result_set, comparators, paginate, response).await
+        } else {
+            Ok(response)
+        }
+    }
+}
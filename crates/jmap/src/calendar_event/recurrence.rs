@@ -0,0 +1,232 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Weekday};
+use jmap_proto::{
+    object::Object,
+    types::{property::Property, value::Value},
+};
+
+use super::parse_local_datetime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Yearly,
+    Monthly,
+    Weekly,
+    Daily,
+    Hourly,
+    Minutely,
+    Secondly,
+}
+
+#[derive(Debug)]
+pub struct RecurrenceRule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<usize>,
+    until: Option<i64>,
+    by_day: Vec<(Weekday, Option<u32>)>,
+}
+
+impl RecurrenceRule {
+    pub fn parse(rule: &Object<Value>) -> Option<Self> {
+        let frequency = match rule.get(&Property::parse("frequency")).as_string()? {
+            "yearly" => Frequency::Yearly,
+            "monthly" => Frequency::Monthly,
+            "weekly" => Frequency::Weekly,
+            "daily" => Frequency::Daily,
+            "hourly" => Frequency::Hourly,
+            "minutely" => Frequency::Minutely,
+            "secondly" => Frequency::Secondly,
+            _ => return None,
+        };
+        let mut by_day = Vec::new();
+        if let Some(days) = rule.get(&Property::parse("byDay")).as_list() {
+            for n_day in days {
+                let n_day = n_day.as_obj()?;
+                let weekday = match n_day.get(&Property::parse("day")).as_string()? {
+                    "mo" => Weekday::Mon,
+                    "tu" => Weekday::Tue,
+                    "we" => Weekday::Wed,
+                    "th" => Weekday::Thu,
+                    "fr" => Weekday::Fri,
+                    "sa" => Weekday::Sat,
+                    "su" => Weekday::Sun,
+                    _ => return None,
+                };
+                let nth = n_day
+                    .get(&Property::parse("nthOfPeriod"))
+                    .as_uint()
+                    .map(|nth| nth as u32);
+                by_day.push((weekday, nth));
+            }
+        }
+
+        Some(RecurrenceRule {
+            frequency,
+            interval: rule
+                .get(&Property::parse("interval"))
+                .as_uint()
+                .unwrap_or(1)
+                .clamp(1, u32::MAX as u64) as u32,
+            count: rule
+                .get(&Property::parse("count"))
+                .as_uint()
+                .map(|count| count as usize),
+            until: match rule.get(&Property::parse("until")) {
+                Value::Text(until) => Some(parse_local_datetime(until)?),
+                Value::Null => None,
+                _ => return None,
+            },
+            by_day,
+        })
+    }
+
+    /// Returns the start times of all occurrences up to (and excluding) `end`,
+    /// including the first occurrence at `start`. At most `limit` occurrences
+    /// are returned.
+    pub fn expand(&self, start: i64, end: i64, limit: usize) -> Vec<i64> {
+        let dtstart = if let Some(dtstart) = DateTime::from_timestamp(start, 0) {
+            dtstart.naive_utc()
+        } else {
+            return vec![];
+        };
+        let max_count = self.count.unwrap_or(usize::MAX).min(limit);
+        let mut occurrences = Vec::new();
+        if max_count == 0 || start >= end {
+            return occurrences;
+        }
+        occurrences.push(start);
+
+        // Bound the number of iterations in case the rule yields no
+        // matches for most periods (e.g. the 31st of each month)
+        let max_iterations = limit.saturating_mul(12).max(366);
+        let interval = self.interval as i64;
+
+        'outer: for period in 0..max_iterations as i64 {
+            let candidates = match self.frequency {
+                Frequency::Secondly | Frequency::Minutely | Frequency::Hourly => {
+                    let unit = match self.frequency {
+                        Frequency::Secondly => 1,
+                        Frequency::Minutely => 60,
+                        _ => 3600,
+                    };
+                    vec![dtstart + Duration::seconds(period * interval * unit)]
+                }
+                Frequency::Daily => {
+                    let date = dtstart + Duration::days(period * interval);
+                    if self.by_day.is_empty()
+                        || self.by_day.iter().any(|(day, _)| *day == date.weekday())
+                    {
+                        vec![date]
+                    } else {
+                        vec![]
+                    }
+                }
+                Frequency::Weekly => {
+                    let week_start = dtstart
+                        - Duration::days(dtstart.weekday().num_days_from_monday() as i64)
+                        + Duration::weeks(period * interval);
+                    let mut days = if self.by_day.is_empty() {
+                        vec![dtstart.weekday()]
+                    } else {
+                        self.by_day.iter().map(|(day, _)| *day).collect()
+                    };
+                    days.sort_by_key(|day| day.num_days_from_monday());
+                    days.dedup();
+                    days.into_iter()
+                        .map(|day| week_start + Duration::days(day.num_days_from_monday() as i64))
+                        .collect()
+                }
+                Frequency::Monthly => {
+                    let month = if let Some(month) = dtstart.date().with_day(1).and_then(|date| {
+                        date.checked_add_months(Months::new((period * interval) as u32))
+                    }) {
+                        month
+                    } else {
+                        break;
+                    };
+                    if self.by_day.is_empty() {
+                        month
+                            .with_day(dtstart.day())
+                            .map(|date| vec![date.and_time(dtstart.time())])
+                            .unwrap_or_default()
+                    } else {
+                        let mut dates = Vec::new();
+                        for (weekday, nth) in &self.by_day {
+                            let mut date = month;
+                            let mut num = 0;
+                            while date.month() == month.month() {
+                                if date.weekday() == *weekday {
+                                    num += 1;
+                                    if nth.map_or(true, |nth| nth == num) {
+                                        dates.push(date.and_time(dtstart.time()));
+                                    }
+                                }
+                                date = if let Some(date) = date.succ_opt() {
+                                    date
+                                } else {
+                                    break;
+                                };
+                            }
+                        }
+                        dates.sort();
+                        dates
+                    }
+                }
+                Frequency::Yearly => NaiveDate::from_ymd_opt(
+                    dtstart.year() + (period * interval) as i32,
+                    dtstart.month(),
+                    dtstart.day(),
+                )
+                .map(|date| vec![date.and_time(dtstart.time())])
+                .unwrap_or_default(),
+            };
+
+            for candidate in candidates {
+                let timestamp = candidate.and_utc().timestamp();
+                if timestamp <= start {
+                    continue;
+                } else if timestamp >= end || self.until.map_or(false, |until| timestamp > until) {
+                    break 'outer;
+                }
+                occurrences.push(timestamp);
+                if occurrences.len() >= max_count {
+                    break 'outer;
+                }
+            }
+        }
+
+        occurrences
+    }
+}
+
+/// Returns whether an event (or any of its occurrences) overlaps with the
+/// specified time range.
+pub fn event_overlaps(
+    start: i64,
+    duration: i64,
+    rules: &[RecurrenceRule],
+    after: Option<i64>,
+    before: Option<i64>,
+    limit: usize,
+) -> bool {
+    let overlaps = |occurrence: i64| {
+        after.map_or(true, |after| {
+            occurrence >= after || occurrence + duration > after
+        }) && before.map_or(true, |before| occurrence < before)
+    };
+
+    if overlaps(start) {
+        true
+    } else {
+        let end = before.unwrap_or(i64::MAX);
+        rules
+            .iter()
+            .any(|rule| rule.expand(start, end, limit).into_iter().any(overlaps))
+    }
+}
@@ -0,0 +1,483 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use common::listener::ServerInstance;
+use jmap_proto::{
+    error::{method::MethodError, set::SetError},
+    method::set::{SetRequest, SetResponse},
+    object::{
+        calendar_event::SetArguments,
+        index::{IndexAs, IndexProperty, ObjectIndexBuilder},
+        Object,
+    },
+    response::references::EvalObjectReferences,
+    types::{
+        collection::Collection,
+        id::Id,
+        property::Property,
+        state::StateChange,
+        type_state::DataType,
+        value::{MaybePatchValue, SetValue, Value},
+    },
+};
+use store::{
+    roaring::RoaringBitmap,
+    write::{assert::HashedValue, log::ChangeLogBuilder, now, BatchBuilder},
+};
+
+use crate::JMAP;
+
+use super::{
+    event_to_jscalendar, parse_duration, parse_local_datetime, recurrence::RecurrenceRule,
+};
+
+pub static SCHEMA: &[IndexProperty] = &[
+    IndexProperty::new(Property::CalendarIds).index_as(IndexAs::IntegerList),
+    IndexProperty::new(Property::Uid)
+        .index_as(IndexAs::Text {
+            tokenize: false,
+            index: true,
+        })
+        .required(),
+    IndexProperty::new(Property::Title).index_as(IndexAs::Text {
+        tokenize: true,
+        index: false,
+    }),
+    IndexProperty::new(Property::Start).index_as(IndexAs::LongInteger),
+];
+
+impl JMAP {
+    pub async fn calendar_event_set(
+        &self,
+        mut request: SetRequest<SetArguments>,
+        instance: &Arc<ServerInstance>,
+    ) -> Result<SetResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let send_scheduling_messages = request.arguments.send_scheduling_messages.unwrap_or(false);
+        let calendar_ids = self
+            .get_document_ids(account_id, Collection::Calendar)
+            .await?
+            .unwrap_or_default();
+        let mut event_ids = self
+            .get_document_ids(account_id, Collection::CalendarEvent)
+            .await?
+            .unwrap_or_default();
+        let mut response = self
+            .prepare_set_response(&request, Collection::CalendarEvent)
+            .await?;
+        let will_destroy = request.unwrap_destroy();
+        let mut scheduling = Vec::new();
+
+        // Process creates
+        let mut changes = ChangeLogBuilder::new();
+        for (id, object) in request.unwrap_create() {
+            match self.calendar_event_set_item(object, None, &calendar_ids, &response) {
+                Ok(builder) => {
+                    let event = builder.changes().cloned().unwrap_or_default();
+                    let mut batch = BatchBuilder::new();
+                    batch
+                        .with_account_id(account_id)
+                        .with_collection(Collection::CalendarEvent)
+                        .create_document()
+                        .custom(builder);
+                    let document_id = self.write_batch_expect_id(batch).await?;
+                    event_ids.insert(document_id);
+                    changes.log_insert(Collection::CalendarEvent, document_id);
+                    response.created(id, document_id);
+
+                    if send_scheduling_messages {
+                        scheduling.push((None, Some(event)));
+                    }
+                }
+                Err(err) => {
+                    response.not_created.append(id, err);
+                }
+            }
+        }
+
+        // Process updates
+        'update: for (id, object) in request.unwrap_update() {
+            // Make sure id won't be destroyed
+            if will_destroy.contains(&id) {
+                response.not_updated.append(id, SetError::will_destroy());
+                continue 'update;
+            }
+
+            // Obtain event
+            let document_id = id.document_id();
+            let event = if let Some(event) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account_id,
+                    Collection::CalendarEvent,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                event
+            } else {
+                response.not_updated.append(id, SetError::not_found());
+                continue 'update;
+            };
+            let previous = send_scheduling_messages.then(|| event.inner.clone());
+
+            match self.calendar_event_set_item(object, event.into(), &calendar_ids, &response) {
+                Ok(builder) => {
+                    let updated = previous.as_ref().map(|previous| {
+                        let mut updated = previous.clone();
+                        for (property, value) in builder.changes().unwrap().properties.iter() {
+                            if value != &Value::Null {
+                                updated.set(property.clone(), value.clone());
+                            } else {
+                                updated.remove(property);
+                            }
+                        }
+                        updated
+                    });
+
+                    let mut batch = BatchBuilder::new();
+                    batch
+                        .with_account_id(account_id)
+                        .with_collection(Collection::CalendarEvent)
+                        .update_document(document_id)
+                        .custom(builder);
+                    if !batch.is_empty() {
+                        match self.core.storage.data.write(batch.build()).await {
+                            Ok(_) => {
+                                changes.log_update(Collection::CalendarEvent, document_id);
+                            }
+                            Err(store::Error::AssertValueFailed) => {
+                                response.not_updated.append(
+                                    id,
+                                    SetError::forbidden().with_description(
+                                        "Another process modified this event, please try again.",
+                                    ),
+                                );
+                                continue 'update;
+                            }
+                            Err(err) => {
+                                tracing::error!(
+                                    event = "error",
+                                    context = "calendar_event_set",
+                                    account_id = account_id,
+                                    error = ?err,
+                                    "Failed to update calendar event(s).");
+                                return Err(MethodError::ServerPartialFail);
+                            }
+                        }
+                    }
+                    response.updated.append(id, None);
+
+                    if let Some(updated) = updated {
+                        scheduling.push((previous, Some(updated)));
+                    }
+                }
+                Err(err) => {
+                    response.not_updated.append(id, err);
+                }
+            }
+        }
+
+        // Process deletions
+        for id in will_destroy {
+            let document_id = id.document_id();
+            if !event_ids.contains(document_id) {
+                response.not_destroyed.append(id, SetError::not_found());
+                continue;
+            }
+
+            if let Some(event) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account_id,
+                    Collection::CalendarEvent,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                let previous = send_scheduling_messages.then(|| event.inner.clone());
+                let mut batch = BatchBuilder::new();
+                batch
+                    .with_account_id(account_id)
+                    .with_collection(Collection::CalendarEvent)
+                    .delete_document(document_id)
+                    .custom(ObjectIndexBuilder::new(SCHEMA).with_current(event));
+                match self.core.storage.data.write(batch.build()).await {
+                    Ok(_) => {
+                        changes.log_delete(Collection::CalendarEvent, document_id);
+                        response.destroyed.push(id);
+
+                        if previous.is_some() {
+                            scheduling.push((previous, None));
+                        }
+                    }
+                    Err(store::Error::AssertValueFailed) => {
+                        response.not_destroyed.append(
+                            id,
+                            SetError::forbidden().with_description(
+                                "Another process modified this event, please try again.",
+                            ),
+                        );
+                    }
+                    Err(err) => {
+                        tracing::error!(
+                            event = "error",
+                            context = "calendar_event_set",
+                            account_id = account_id,
+                            document_id = document_id,
+                            error = ?err,
+                            "Failed to delete calendar event.");
+                        return Err(MethodError::ServerPartialFail);
+                    }
+                }
+            } else {
+                response.not_destroyed.append(id, SetError::not_found());
+            }
+        }
+
+        // Write changes
+        if !changes.is_empty() {
+            let change_id = self.commit_changes(account_id, changes).await?;
+            response.state_change = StateChange::new(account_id)
+                .with_change(DataType::CalendarEvent, change_id)
+                .into();
+            response.new_state = Some(change_id.into());
+        }
+
+        // Send iTIP messages to the participants in the background
+        if !scheduling.is_empty() {
+            let jmap = self.clone();
+            let instance = instance.clone();
+            tokio::spawn(async move {
+                for (previous, event) in scheduling {
+                    jmap.calendar_event_schedule(
+                        account_id,
+                        &instance,
+                        previous.map(event_to_jscalendar).as_ref(),
+                        event.map(event_to_jscalendar).as_ref(),
+                    )
+                    .await;
+                }
+            });
+        }
+
+        Ok(response)
+    }
+
+    fn calendar_event_set_item(
+        &self,
+        changes_: Object<SetValue>,
+        current: Option<HashedValue<Object<Value>>>,
+        calendar_ids: &RoaringBitmap,
+        response: &SetResponse,
+    ) -> Result<ObjectIndexBuilder, SetError> {
+        let mut changes = Object::with_capacity(changes_.properties.len() + 2);
+        let mut event_calendar_ids = current
+            .as_ref()
+            .and_then(|current| current.inner.get(&Property::CalendarIds).as_list())
+            .map(|ids| {
+                ids.iter()
+                    .filter_map(|id| id.as_id().map(|id| id.document_id()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let mut calendar_ids_changed = false;
+
+        for (property, value) in changes_.properties {
+            let value = match (&property, response.eval_object_references(value)?) {
+                (Property::CalendarIds, MaybePatchValue::Value(Value::List(ids))) => {
+                    event_calendar_ids = ids
+                        .into_iter()
+                        .filter_map(|id| id.try_unwrap_id()?.document_id().into())
+                        .collect();
+                    calendar_ids_changed = true;
+                    continue;
+                }
+                (Property::CalendarIds, MaybePatchValue::Patch(patch)) => {
+                    let mut patch = patch.into_iter();
+                    if let Some(document_id) = patch.next().unwrap().try_unwrap_id() {
+                        let document_id = document_id.document_id();
+                        if patch.next().unwrap().try_unwrap_bool().unwrap_or_default() {
+                            if !event_calendar_ids.contains(&document_id) {
+                                event_calendar_ids.push(document_id);
+                            }
+                        } else {
+                            event_calendar_ids.retain(|id| id != &document_id);
+                        }
+                    }
+                    calendar_ids_changed = true;
+                    continue;
+                }
+                (Property::Uid, MaybePatchValue::Value(Value::Text(value))) => {
+                    if let Some(current) = &current {
+                        if current.inner.get(&Property::Uid).as_string() != Some(value.as_str()) {
+                            return Err(SetError::invalid_properties()
+                                .with_property(Property::Uid)
+                                .with_description("The uid of an event cannot be changed."));
+                        }
+                    } else if value.is_empty() || value.len() > 255 {
+                        return Err(SetError::invalid_properties()
+                            .with_property(Property::Uid)
+                            .with_description("Invalid uid."));
+                    }
+                    Value::Text(value)
+                }
+                (
+                    Property::Title | Property::Description | Property::Color | Property::Timezone,
+                    MaybePatchValue::Value(Value::Text(value)),
+                ) if value.len() < 16384 => Value::Text(value),
+                (Property::Start, MaybePatchValue::Value(Value::Text(value))) => {
+                    match parse_local_datetime(&value) {
+                        Some(start) if start >= 0 => Value::UnsignedInt(start as u64),
+                        _ => {
+                            return Err(SetError::invalid_properties()
+                                .with_property(Property::Start)
+                                .with_description("Invalid start date."));
+                        }
+                    }
+                }
+                (Property::Duration, MaybePatchValue::Value(Value::Text(value))) => {
+                    if parse_duration(&value).is_some() {
+                        Value::Text(value)
+                    } else {
+                        return Err(SetError::invalid_properties()
+                            .with_property(Property::Duration)
+                            .with_description("Invalid duration."));
+                    }
+                }
+                (Property::Status, MaybePatchValue::Value(Value::Text(value)))
+                    if ["confirmed", "cancelled", "tentative"].contains(&value.as_str()) =>
+                {
+                    Value::Text(value)
+                }
+                (Property::ShowWithoutTime, MaybePatchValue::Value(Value::Bool(value))) => {
+                    Value::Bool(value)
+                }
+                (Property::Sequence, MaybePatchValue::Value(Value::UnsignedInt(value))) => {
+                    Value::UnsignedInt(value)
+                }
+                (Property::RecurrenceRules, MaybePatchValue::Value(Value::List(rules))) => {
+                    if rules
+                        .iter()
+                        .all(|rule| rule.as_obj().and_then(RecurrenceRule::parse).is_some())
+                    {
+                        Value::List(rules)
+                    } else {
+                        return Err(SetError::invalid_properties()
+                            .with_property(Property::RecurrenceRules)
+                            .with_description("Invalid recurrence rule."));
+                    }
+                }
+                (Property::Participants, MaybePatchValue::Value(Value::Object(participants))) => {
+                    if participants.properties.len() > self.core.jmap.calendar_max_participants {
+                        return Err(SetError::invalid_properties()
+                            .with_property(Property::Participants)
+                            .with_description("Too many participants."));
+                    } else if participants
+                        .properties
+                        .values()
+                        .any(|participant| participant.as_obj().is_none())
+                    {
+                        return Err(SetError::invalid_properties()
+                            .with_property(Property::Participants)
+                            .with_description("Invalid participant."));
+                    }
+                    Value::Object(participants)
+                }
+                (Property::Locations, MaybePatchValue::Value(Value::Object(locations))) => {
+                    if locations
+                        .properties
+                        .values()
+                        .any(|location| location.as_obj().is_none())
+                    {
+                        return Err(SetError::invalid_properties()
+                            .with_property(Property::Locations)
+                            .with_description("Invalid location."));
+                    }
+                    Value::Object(locations)
+                }
+                (Property::_T(name), _) if name == "@type" => {
+                    continue;
+                }
+                (
+                    Property::Title
+                    | Property::Description
+                    | Property::Color
+                    | Property::Timezone
+                    | Property::Duration
+                    | Property::Status
+                    | Property::ShowWithoutTime
+                    | Property::Sequence
+                    | Property::RecurrenceRules
+                    | Property::Participants
+                    | Property::Locations,
+                    MaybePatchValue::Value(Value::Null),
+                ) => {
+                    if current.is_none() {
+                        continue;
+                    }
+                    Value::Null
+                }
+                (property, _) => {
+                    return Err(SetError::invalid_properties()
+                        .with_property(property.clone())
+                        .with_description("Field could not be set."));
+                }
+            };
+            changes.append(property, value);
+        }
+
+        // Validate calendar ids
+        if calendar_ids_changed || current.is_none() {
+            if event_calendar_ids.is_empty() {
+                return Err(SetError::invalid_properties()
+                    .with_property(Property::CalendarIds)
+                    .with_description("Event has to belong to at least one calendar."));
+            }
+            for calendar_id in &event_calendar_ids {
+                if !calendar_ids.contains(*calendar_id) {
+                    return Err(SetError::invalid_properties()
+                        .with_property(Property::CalendarIds)
+                        .with_description(format!(
+                            "calendarId {} does not exist.",
+                            Id::from(*calendar_id)
+                        )));
+                }
+            }
+            changes.append(
+                Property::CalendarIds,
+                Value::List(
+                    event_calendar_ids
+                        .into_iter()
+                        .map(|id| Value::Id(Id::from(id)))
+                        .collect(),
+                ),
+            );
+        }
+
+        // Add server-set properties
+        let now = now();
+        if current.is_none() {
+            if !changes.properties.contains_key(&Property::Start) {
+                return Err(SetError::invalid_properties()
+                    .with_property(Property::Start)
+                    .with_description("Missing start date."));
+            }
+            if !changes.properties.contains_key(&Property::Uid) {
+                changes.append(Property::Uid, format!("{:032x}", rand::random::<u128>()));
+            }
+            changes.append(Property::Created, now);
+        }
+        changes.append(Property::Updated, now);
+
+        ObjectIndexBuilder::new(SCHEMA)
+            .with_changes(changes)
+            .with_current_opt(current)
+            .validate()
+    }
+}
@@ -0,0 +1,99 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::get::{GetRequest, GetResponse, RequestArguments},
+    object::Object,
+    types::{collection::Collection, date::UTCDate, property::Property, value::Value},
+};
+
+use crate::JMAP;
+
+impl JMAP {
+    pub async fn calendar_event_notification_get(
+        &self,
+        mut request: GetRequest<RequestArguments>,
+    ) -> Result<GetResponse, MethodError> {
+        let ids = request.unwrap_ids(self.core.jmap.get_max_objects)?;
+        let properties = request.unwrap_properties(&[
+            Property::Id,
+            Property::Created,
+            Property::ChangedBy,
+            Property::Type,
+            Property::CalendarEventId,
+            Property::Event,
+        ]);
+        let account_id = request.account_id.document_id();
+        let notification_ids = self
+            .get_document_ids(account_id, Collection::CalendarEventNotification)
+            .await?
+            .unwrap_or_default();
+        let ids = if let Some(ids) = ids {
+            ids
+        } else {
+            notification_ids
+                .iter()
+                .take(self.core.jmap.get_max_objects)
+                .map(Into::into)
+                .collect::<Vec<_>>()
+        };
+        let mut response = GetResponse {
+            account_id: request.account_id.into(),
+            state: self
+                .get_state(account_id, Collection::CalendarEventNotification)
+                .await?
+                .into(),
+            list: Vec::with_capacity(ids.len()),
+            not_found: vec![],
+        };
+
+        for id in ids {
+            // Obtain the notification object
+            let document_id = id.document_id();
+            if !notification_ids.contains(document_id) {
+                response.not_found.push(id.into());
+                continue;
+            }
+            let mut notification = if let Some(notification) = self
+                .get_property::<Object<Value>>(
+                    account_id,
+                    Collection::CalendarEventNotification,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                notification
+            } else {
+                response.not_found.push(id.into());
+                continue;
+            };
+
+            let mut result = Object::with_capacity(properties.len());
+            for property in &properties {
+                let value = match property {
+                    Property::Id => Value::Id(id),
+                    Property::Created => notification
+                        .get(property)
+                        .as_uint()
+                        .map(|created| Value::Date(UTCDate::from_timestamp(created as i64)))
+                        .unwrap_or_default(),
+                    Property::ChangedBy
+                    | Property::Type
+                    | Property::CalendarEventId
+                    | Property::Event => notification.remove(property),
+                    _ => Value::Null,
+                };
+
+                result.append(property.clone(), value);
+            }
+            response.list.push(result);
+        }
+
+        Ok(response)
+    }
+}
@@ -11,7 +11,7 @@ use jmap_proto::{
 };
 use store::query::log::{Change, Changes, Query};
 
-use crate::{auth::AccessToken, JMAP};
+use crate::{auth::AccessToken, template::DOMAIN_TEMPLATES_ID, JMAP};
 
 impl JMAP {
     pub async fn changes(
@@ -45,6 +45,31 @@ impl JMAP {
 
                 Collection::EmailSubmission
             }
+            RequestArguments::ShareNotification => {
+                access_token.assert_is_member(request.account_id)?;
+
+                Collection::ShareNotification
+            }
+            RequestArguments::Calendar => {
+                access_token.assert_is_member(request.account_id)?;
+
+                Collection::Calendar
+            }
+            RequestArguments::CalendarEvent => {
+                access_token.assert_is_member(request.account_id)?;
+
+                Collection::CalendarEvent
+            }
+            RequestArguments::CalendarEventNotification => {
+                access_token.assert_is_member(request.account_id)?;
+
+                Collection::CalendarEventNotification
+            }
+            RequestArguments::Template => {
+                access_token.assert_is_member(request.account_id)?;
+
+                Collection::Template
+            }
             RequestArguments::Quota => {
                 access_token.assert_is_member(request.account_id)?;
 
@@ -71,6 +96,24 @@ impl JMAP {
         };
         let account_id = request.account_id.document_id();
 
+        // Domain templates are logged separately, clients have to fetch
+        // templates again if any of them changed after the requested state
+        if collection == Collection::Template {
+            if let State::Exact(domain_change_id) = self
+                .get_state(DOMAIN_TEMPLATES_ID, Collection::Template)
+                .await?
+            {
+                let since_change_id = match &request.since_state {
+                    State::Initial => 0,
+                    State::Exact(change_id) => *change_id,
+                    State::Intermediate(intermediate_state) => intermediate_state.from_id,
+                };
+                if since_change_id < domain_change_id {
+                    return Err(MethodError::CannotCalculateChanges);
+                }
+            }
+        }
+
         let (items_sent, mut changelog) = match &request.since_state {
             State::Initial => {
                 let changelog = self.changes_(account_id, collection, Query::All).await?;
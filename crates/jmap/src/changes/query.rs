@@ -35,6 +35,16 @@ impl JMAP {
                             changes::RequestArguments::EmailSubmission
                         }
                         query::RequestArguments::Quota => changes::RequestArguments::Quota,
+                        query::RequestArguments::ShareNotification => {
+                            changes::RequestArguments::ShareNotification
+                        }
+                        query::RequestArguments::Calendar => changes::RequestArguments::Calendar,
+                        query::RequestArguments::CalendarEvent => {
+                            changes::RequestArguments::CalendarEvent
+                        }
+                        query::RequestArguments::CalendarEventNotification => {
+                            changes::RequestArguments::CalendarEventNotification
+                        }
                         _ => return Err(MethodError::UnknownMethod("Unknown method".to_string())),
                     },
                 },
@@ -82,6 +92,14 @@ impl JMAP {
                     self.email_submission_query(query).await?
                 }
                 query::RequestArguments::Quota => self.quota_query(query, access_token).await?,
+                query::RequestArguments::ShareNotification => {
+                    self.share_notification_query(query).await?
+                }
+                query::RequestArguments::Calendar => self.calendar_query(query).await?,
+                query::RequestArguments::CalendarEvent => self.calendar_event_query(query).await?,
+                query::RequestArguments::CalendarEventNotification => {
+                    self.calendar_event_notification_query(query).await?
+                }
                 _ => unreachable!(),
             };
 
@@ -339,7 +339,7 @@ impl JMAP {
         }
 
         let thread_id = if !references.is_empty() {
-            self.find_or_merge_thread(account_id, subject, &references)
+            self.find_or_merge_thread(account_id, subject, &references, metadata.received_at)
                 .await
                 .map_err(|_| MethodError::ServerPartialFail)?
         } else {
@@ -134,7 +134,20 @@ impl JMAP {
             .with_account_id(account_id)
             .with_collection(Collection::Email);
 
+        let undelete_retention = self.undelete_retention(account_id).await?;
         for (document_id, delete_properties) in delete_properties {
+            // Hold message for user-initiated undeletion
+            if let Some(retention) = undelete_retention {
+                self.hold_deleted_email(
+                    &mut batch,
+                    account_id,
+                    document_id,
+                    &delete_properties.mailboxes,
+                    retention,
+                )
+                .await?;
+            }
+
             batch.update_document(document_id);
 
             if !delete_properties.mailboxes.is_empty() {
@@ -467,6 +480,7 @@ impl JMAP {
                 .with_collection(Collection::Email)
                 .delete_document(document_id)
                 .clear(Property::Cid)
+                .clear(Property::Snoozed)
                 .tag(
                     Property::MailboxIds,
                     TagValue::Id(MaybeDynamicId::Static(TOMBSTONE_ID)),
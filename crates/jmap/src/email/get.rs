@@ -255,6 +255,20 @@ impl JMAP {
                             continue 'outer;
                         }
                     }
+                    Property::Snoozed => {
+                        email.append(
+                            Property::Snoozed,
+                            self.get_property::<Object<Value>>(
+                                account_id,
+                                Collection::Email,
+                                id.document_id(),
+                                &Property::Snoozed,
+                            )
+                            .await?
+                            .map(Value::Object)
+                            .unwrap_or_default(),
+                        );
+                    }
                     Property::Size => {
                         email.append(Property::Size, metadata.size);
                     }
@@ -11,7 +11,7 @@ use mail_parser::{
     decoders::html::html_to_text,
     parsers::{fields::thread::thread_name, preview::preview_text},
     Addr, Address, GetHeader, Group, Header, HeaderName, HeaderValue, Message, MessagePart,
-    PartType,
+    MimeHeaders, PartType,
 };
 use nlp::language::Language;
 use store::{
@@ -34,6 +34,9 @@ pub const MAX_SORT_FIELD_LENGTH: usize = 255;
 pub const MAX_STORED_FIELD_LENGTH: usize = 512;
 pub const PREVIEW_LENGTH: usize = 256;
 
+pub const KEYWORD_ENCRYPTED: &str = "$encrypted";
+pub const KEYWORD_SIGNED: &str = "$signed";
+
 #[derive(Debug)]
 pub struct SortedAddressBuilder {
     last_is_space: bool,
@@ -328,6 +331,29 @@ impl<'x> IndexMessageText<'x> for FtsDocument<'x, HeaderName<'x>> {
                 }
             }
 
+            // Index isEncrypted and isSigned properties, security parts are
+            // often nested inside a multipart/mixed
+            if let Some(keyword) = part.security_keyword() {
+                self.index_keyword(Field::Keyword, keyword);
+            }
+
+            // Index attachment names and content types
+            if message.attachments.contains(&part_id) {
+                if let Some(name) = part.attachment_name() {
+                    self.index_tokenized(Field::AttachmentName, name.to_string());
+                }
+                if let Some(content_type) = part.content_type() {
+                    let main_type = content_type.ctype().to_ascii_lowercase();
+                    if let Some(sub_type) = content_type.subtype() {
+                        self.index_keyword(
+                            Field::AttachmentType,
+                            format!("{main_type}/{}", sub_type.to_ascii_lowercase()),
+                        );
+                    }
+                    self.index_keyword(Field::AttachmentType, main_type);
+                }
+            }
+
             match &part.body {
                 PartType::Text(text) => {
                     if message.text_body.contains(&part_id) || message.html_body.contains(&part_id)
@@ -502,6 +528,42 @@ trait GetContentLanguage {
     fn language(&self) -> Option<Language>;
 }
 
+trait GetSecurityKeyword {
+    fn security_keyword(&self) -> Option<&'static str>;
+}
+
+impl GetSecurityKeyword for MessagePart<'_> {
+    fn security_keyword(&self) -> Option<&'static str> {
+        let content_type = self.content_type()?;
+        let main_type = content_type.ctype();
+        let sub_type = content_type.subtype().unwrap_or_default();
+
+        if main_type.eq_ignore_ascii_case("multipart") {
+            if sub_type.eq_ignore_ascii_case("encrypted") {
+                Some(KEYWORD_ENCRYPTED)
+            } else if sub_type.eq_ignore_ascii_case("signed") {
+                Some(KEYWORD_SIGNED)
+            } else {
+                None
+            }
+        } else if main_type.eq_ignore_ascii_case("application")
+            && (sub_type.eq_ignore_ascii_case("pkcs7-mime")
+                || sub_type.eq_ignore_ascii_case("x-pkcs7-mime"))
+        {
+            // S/MIME messages declare their contents in the smime-type parameter
+            match content_type.attribute("smime-type") {
+                Some(smime_type) if smime_type.eq_ignore_ascii_case("signed-data") => {
+                    Some(KEYWORD_SIGNED)
+                }
+                Some(smime_type) if smime_type.eq_ignore_ascii_case("certs-only") => None,
+                _ => Some(KEYWORD_ENCRYPTED),
+            }
+        } else {
+            None
+        }
+    }
+}
+
 impl GetContentLanguage for MessagePart<'_> {
     fn language(&self) -> Option<Language> {
         self.headers
@@ -6,7 +6,10 @@
 
 use std::{borrow::Cow, time::Duration};
 
-use common::webhooks::{WebhookIngestSource, WebhookPayload, WebhookType};
+use common::{
+    config::jmap::settings::ThreadStrategy,
+    webhooks::{WebhookIngestSource, WebhookPayload, WebhookType},
+};
 use jmap_proto::{
     object::Object,
     types::{
@@ -71,7 +74,7 @@ pub enum IngestSource {
     Imap,
 }
 
-const MAX_RETRIES: u32 = 10;
+pub(crate) const MAX_RETRIES: u32 = 10;
 
 impl JMAP {
     #[allow(clippy::blocks_in_conditions)]
@@ -191,8 +194,13 @@ impl JMAP {
             }
 
             if !references.is_empty() {
-                self.find_or_merge_thread(params.account_id, subject, &references)
-                    .await?
+                self.find_or_merge_thread(
+                    params.account_id,
+                    subject,
+                    &references,
+                    params.received_at.unwrap_or_else(now),
+                )
+                .await?
             } else {
                 None
             }
@@ -422,20 +430,34 @@ impl JMAP {
         account_id: u32,
         thread_name: &str,
         references: &[&str],
+        received_at: u64,
     ) -> Result<Option<u32>, IngestError> {
         let mut try_count = 0;
 
         loop {
             // Find messages with matching references
-            let mut filters = Vec::with_capacity(references.len() + 3);
-            filters.push(Filter::eq(
-                Property::Subject,
-                if !thread_name.is_empty() {
-                    thread_name
-                } else {
-                    "!"
-                },
-            ));
+            let mut filters = Vec::with_capacity(references.len() + 5);
+            if self.core.jmap.mail_thread_strategy == ThreadStrategy::SubjectReferences {
+                filters.push(Filter::eq(
+                    Property::Subject,
+                    if !thread_name.is_empty() {
+                        thread_name
+                    } else {
+                        "!"
+                    },
+                ));
+            }
+            if let Some(window) = self.core.jmap.mail_thread_window {
+                let window = window.as_secs();
+                filters.push(Filter::ge(
+                    Property::ReceivedAt,
+                    received_at.saturating_sub(window),
+                ));
+                filters.push(Filter::le(
+                    Property::ReceivedAt,
+                    received_at.saturating_add(window),
+                ));
+            }
             filters.push(Filter::Or);
             for reference in references {
                 filters.push(Filter::eq(Property::References, *reference));
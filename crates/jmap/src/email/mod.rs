@@ -19,3 +19,5 @@ pub mod parse;
 pub mod query;
 pub mod set;
 pub mod snippet;
+pub mod snooze;
+pub mod undelete;
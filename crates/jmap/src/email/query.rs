@@ -20,7 +20,11 @@ use store::{
     ValueKey,
 };
 
-use crate::{auth::AccessToken, JMAP};
+use crate::{
+    auth::AccessToken,
+    email::index::{KEYWORD_ENCRYPTED, KEYWORD_SIGNED},
+    JMAP,
+};
 
 impl JMAP {
     pub async fn email_query(
@@ -150,6 +154,39 @@ impl JMAP {
                                     None => (),
                                 }
                             }
+                            Filter::AttachmentName(text) => fts_filters.push(FtsFilter::has_text(
+                                Field::AttachmentName,
+                                text,
+                                Language::None,
+                            )),
+                            Filter::AttachmentType(content_type) => {
+                                fts_filters.push(FtsFilter::has_keyword(
+                                    Field::AttachmentType,
+                                    content_type.to_lowercase(),
+                                ))
+                            }
+                            Filter::IsEncrypted(is_encrypted) => {
+                                if !is_encrypted {
+                                    fts_filters.push(FtsFilter::Not);
+                                }
+                                fts_filters.push(FtsFilter::has_keyword(
+                                    Field::Keyword,
+                                    KEYWORD_ENCRYPTED,
+                                ));
+                                if !is_encrypted {
+                                    fts_filters.push(FtsFilter::End);
+                                }
+                            }
+                            Filter::IsSigned(is_signed) => {
+                                if !is_signed {
+                                    fts_filters.push(FtsFilter::Not);
+                                }
+                                fts_filters
+                                    .push(FtsFilter::has_keyword(Field::Keyword, KEYWORD_SIGNED));
+                                if !is_signed {
+                                    fts_filters.push(FtsFilter::End);
+                                }
+                            }
                             Filter::And | Filter::Or | Filter::Not | Filter::Close => {
                                 fts_filters.push(cond.into());
                             }
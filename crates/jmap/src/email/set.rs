@@ -12,6 +12,7 @@ use jmap_proto::{
         set::{SetError, SetErrorType},
     },
     method::set::{RequestArguments, SetRequest, SetResponse},
+    object::Object,
     response::references::EvalObjectReferences,
     types::{
         acl::Acl,
@@ -47,6 +48,7 @@ use crate::{auth::AccessToken, mailbox::UidMailbox, IngestError, JMAP};
 use super::{
     headers::{BuildHeader, ValueToHeader},
     ingest::{IngestEmail, IngestSource},
+    snooze::{snooze_index, SNOOZED_KEYWORD},
 };
 
 impl JMAP {
@@ -101,6 +103,8 @@ impl JMAP {
             let mut mailboxes = Vec::new();
             let mut keywords = Vec::new();
             let mut received_at = None;
+            let mut from_template = None;
+            let mut from_address = None;
 
             // Parse body values
             let body_values = object
@@ -204,6 +208,14 @@ impl JMAP {
                         | Property::ReplyTo),
                         MaybePatchValue::Value(value),
                     ) => {
+                        if header == Property::From {
+                            from_address = value
+                                .as_list()
+                                .and_then(|addresses| addresses.first())
+                                .and_then(|address| address.as_obj())
+                                .and_then(|address| address.get(&Property::Email).as_string())
+                                .map(|email| email.to_string());
+                        }
                         if let Some(addresses) = value.try_into_address_list() {
                             builder =
                                 builder.header(header.as_rfc_header(), Address::List(addresses));
@@ -216,6 +228,10 @@ impl JMAP {
                         builder = builder.subject(value);
                     }
 
+                    (Property::FromTemplate, MaybePatchValue::Value(Value::Id(value))) => {
+                        from_template = value.into();
+                    }
+
                     (Property::ReceivedAt, MaybePatchValue::Value(Value::Date(value))) => {
                         received_at = (value.timestamp() as u64).into();
                     }
@@ -648,6 +664,56 @@ impl JMAP {
                 }
             }
 
+            // Merge the template, explicit subject and body parts take precedence
+            if let Some(template_id) = from_template {
+                let template = if let Some(template) = self
+                    .template_for_draft(
+                        account_id,
+                        template_id,
+                        from_address.as_deref(),
+                        access_token,
+                    )
+                    .await?
+                {
+                    template
+                } else {
+                    response.not_created.append(
+                        id,
+                        SetError::invalid_properties()
+                            .with_property(Property::FromTemplate)
+                            .with_description("Template not found."),
+                    );
+                    continue 'create;
+                };
+
+                if let Some(subject) = template.subject {
+                    if !builder
+                        .headers
+                        .iter()
+                        .any(|(name, _)| name.eq_ignore_ascii_case("Subject"))
+                    {
+                        builder = builder.subject(subject);
+                    }
+                }
+                if builder.body.is_none()
+                    && builder.text_body.is_none()
+                    && builder.html_body.is_none()
+                {
+                    builder.text_body = template.text_body.map(|text| {
+                        MimePart::new(
+                            ContentType::new("text/plain").attribute("charset", "utf-8"),
+                            BodyPart::Text(text.into()),
+                        )
+                    });
+                    builder.html_body = template.html_body.map(|html| {
+                        MimePart::new(
+                            ContentType::new("text/html").attribute("charset", "utf-8"),
+                            BodyPart::Text(html.into()),
+                        )
+                    });
+                }
+            }
+
             // Make sure message belongs to at least one mailbox
             if mailboxes.is_empty() {
                 response.not_created.append(
@@ -777,6 +843,7 @@ impl JMAP {
             batch
                 .with_account_id(account_id)
                 .with_collection(Collection::Email);
+            let mut snoozed = None;
 
             for (property, value) in object.properties {
                 let value = match response.eval_object_references(value) {
@@ -823,6 +890,45 @@ impl JMAP {
                             );
                         }
                     }
+                    (Property::Snoozed, MaybePatchValue::Value(Value::Object(snooze))) => {
+                        // Validate snooze
+                        match snooze.get(&Property::MoveToMailboxId) {
+                            Value::Id(mailbox_id)
+                                if !mailbox_ids.contains(mailbox_id.document_id()) =>
+                            {
+                                response.not_updated.append(
+                                    id,
+                                    SetError::invalid_properties()
+                                        .with_property(Property::Snoozed)
+                                        .with_description(format!(
+                                            "moveToMailboxId {mailbox_id} does not exist."
+                                        )),
+                                );
+                                continue 'update;
+                            }
+                            Value::Id(_) | Value::Null => (),
+                            _ => {
+                                response.invalid_property_update(id, Property::Snoozed);
+                                continue 'update;
+                            }
+                        }
+                        if !matches!(snooze.get(&Property::Until), Value::Date(_)) {
+                            response.not_updated.append(
+                                id,
+                                SetError::invalid_properties()
+                                    .with_property(Property::Snoozed)
+                                    .with_description("Missing or invalid until date."),
+                            );
+                            continue 'update;
+                        }
+
+                        keywords.update(Keyword::Other(SNOOZED_KEYWORD.to_string()), true);
+                        snoozed = Some(Some(snooze));
+                    }
+                    (Property::Snoozed, MaybePatchValue::Value(Value::Null)) => {
+                        keywords.update(Keyword::Other(SNOOZED_KEYWORD.to_string()), false);
+                        snoozed = Some(None);
+                    }
                     (property, _) => {
                         response.invalid_property_update(id, property);
                         continue 'update;
@@ -830,7 +936,7 @@ impl JMAP {
                 }
             }
 
-            if !mailboxes.has_changes() && !keywords.has_changes() {
+            if !mailboxes.has_changes() && !keywords.has_changes() && snoozed.is_none() {
                 response.not_updated.append(
                     id,
                     SetError::invalid_properties()
@@ -959,6 +1065,30 @@ impl JMAP {
                 mailboxes.update_batch(&mut batch, Property::MailboxIds);
             }
 
+            // Update snooze
+            if let Some(snoozed) = snoozed {
+                if let Some(index) = self
+                    .get_property::<Object<Value>>(
+                        account_id,
+                        Collection::Email,
+                        document_id,
+                        Property::Snoozed,
+                    )
+                    .await?
+                    .and_then(|snooze| snooze_index(account_id, document_id, &snooze))
+                {
+                    batch.clear(index);
+                }
+                if let Some(snooze) = snoozed {
+                    if let Some(index) = snooze_index(account_id, document_id, &snooze) {
+                        batch.set(index, Vec::new());
+                    }
+                    batch.value(Property::Snoozed, snooze, F_VALUE);
+                } else {
+                    batch.clear(Property::Snoozed);
+                }
+            }
+
             // Log mailbox changes
             for mailbox_id in changed_mailboxes {
                 changes.log_child_update(Collection::Mailbox, mailbox_id);
@@ -0,0 +1,216 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::set::{RequestArguments, SetRequest},
+    object::Object,
+    types::{
+        collection::Collection,
+        id::Id,
+        keyword::Keyword,
+        property::Property,
+        value::{SetValue, Value},
+    },
+};
+use store::{
+    ahash::AHashMap,
+    roaring::RoaringBitmap,
+    write::{
+        key::DeserializeBigEndian, now, BatchBuilder, MaybeDynamicId, ReportClass, ValueClass,
+    },
+    IterateParams, ValueKey, U64_LEN,
+};
+use utils::map::vec_map::VecMap;
+
+use crate::{mailbox::INBOX_ID, JMAP};
+
+pub const SNOOZED_KEYWORD: &str = "$snoozed";
+
+impl JMAP {
+    pub async fn emails_wake_snoozed(&self) {
+        // Obtain snoozed messages that are due, grouped by account
+        let now = now();
+        let mut due_ids: AHashMap<u32, Vec<(u32, u64)>> = AHashMap::new();
+        if let Err(err) = self
+            .core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::Report(ReportClass::Snooze {
+                        id: 0,
+                        expires: 0,
+                    })),
+                    ValueKey::from(ValueClass::Report(ReportClass::Snooze {
+                        id: u64::MAX,
+                        expires: now,
+                    })),
+                )
+                .ascending()
+                .no_values(),
+                |key, _| {
+                    let expires = key.deserialize_be_u64(1)?;
+                    let id = key.deserialize_be_u64(U64_LEN + 1)?;
+                    due_ids
+                        .entry((id >> 32) as u32)
+                        .or_default()
+                        .push((id as u32, expires));
+
+                    Ok(true)
+                },
+            )
+            .await
+        {
+            tracing::error!(
+                event = "error",
+                context = "email_snooze",
+                error = ?err,
+                "Failed to obtain snoozed messages."
+            );
+            return;
+        }
+
+        for (account_id, due_ids) in due_ids {
+            if let Err(err) = self.emails_wake_snoozed_account(account_id, &due_ids).await {
+                tracing::error!(
+                    event = "error",
+                    context = "email_snooze",
+                    account_id = account_id,
+                    error = ?err,
+                    "Failed to wake snoozed messages."
+                );
+            }
+        }
+    }
+
+    async fn emails_wake_snoozed_account(
+        &self,
+        account_id: u32,
+        due_ids: &[(u32, u64)],
+    ) -> Result<(), MethodError> {
+        // Remove the index entries, stale entries left behind by deleted
+        // or rescheduled messages are discarded as well
+        let mut batch = BatchBuilder::new();
+        for (document_id, expires) in due_ids {
+            batch.clear(ValueClass::Report(ReportClass::Snooze {
+                id: snooze_id(account_id, *document_id),
+                expires: *expires,
+            }));
+        }
+        self.write_batch(batch).await?;
+
+        // Find messages that are still snoozed and due
+        let mailbox_ids = self.mailbox_get_or_create(account_id).await?;
+        let now = now() as i64;
+        let mut due = Vec::new();
+        for (document_id, snooze) in self
+            .get_properties::<Object<Value>, _, _>(
+                account_id,
+                Collection::Email,
+                &due_ids
+                    .iter()
+                    .map(|(document_id, _)| *document_id)
+                    .collect::<RoaringBitmap>(),
+                Property::Snoozed,
+            )
+            .await?
+        {
+            if matches!(snooze.get(&Property::Until), Value::Date(until) if until.timestamp() <= now)
+            {
+                // Fall back to the inbox if the mailbox was deleted in the meantime
+                let mailbox_id = match snooze.get(&Property::MoveToMailboxId) {
+                    Value::Id(mailbox_id) if mailbox_ids.contains(mailbox_id.document_id()) => {
+                        *mailbox_id
+                    }
+                    _ => Id::from(INBOX_ID),
+                };
+                due.push((document_id, mailbox_id));
+            }
+        }
+        if due.is_empty() {
+            return Ok(());
+        }
+
+        tracing::debug!(
+            event = "info",
+            context = "email_snooze",
+            account_id = account_id,
+            count = due.len(),
+            "Waking snoozed messages."
+        );
+
+        // Move messages back to their mailbox, clear the snooze and mark them as unread
+        let mut update = VecMap::with_capacity(due.len());
+        for (document_id, thread_id) in self
+            .get_cached_thread_ids(account_id, due.iter().map(|(document_id, _)| *document_id))
+            .await?
+        {
+            if let Some((_, mailbox_id)) = due.iter().find(|(id, _)| *id == document_id) {
+                update.append(
+                    Id::from_parts(thread_id, document_id),
+                    Object {
+                        properties: VecMap::from_iter([
+                            (
+                                Property::MailboxIds,
+                                SetValue::Value(Value::List(vec![Value::Id(*mailbox_id)])),
+                            ),
+                            (
+                                Property::Keywords,
+                                SetValue::Patch(vec![
+                                    Value::Keyword(Keyword::Seen),
+                                    Value::Bool(false),
+                                ]),
+                            ),
+                            (Property::Snoozed, SetValue::Value(Value::Null)),
+                        ]),
+                    },
+                );
+            }
+        }
+        let access_token = self
+            .get_access_token(account_id)
+            .await
+            .ok_or(MethodError::ServerPartialFail)?;
+        let mut response = self
+            .email_set(
+                SetRequest {
+                    account_id: Id::from(account_id),
+                    if_in_state: None,
+                    create: None,
+                    update: update.into(),
+                    destroy: None,
+                    arguments: RequestArguments::Email,
+                },
+                &access_token,
+            )
+            .await?;
+        if let Some(state_change) = response.state_change.take() {
+            self.broadcast_state_change(state_change).await;
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn snooze_index(
+    account_id: u32,
+    document_id: u32,
+    snooze: &Object<Value>,
+) -> Option<ValueClass<MaybeDynamicId>> {
+    match snooze.get(&Property::Until) {
+        Value::Date(until) => Some(ValueClass::Report(ReportClass::Snooze {
+            id: snooze_id(account_id, document_id),
+            expires: until.timestamp().max(0) as u64,
+        })),
+        _ => None,
+    }
+}
+
+#[inline(always)]
+fn snooze_id(account_id: u32, document_id: u32) -> u64 {
+    ((account_id as u64) << 32) | document_id as u64
+}
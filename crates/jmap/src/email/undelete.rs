@@ -0,0 +1,498 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use directory::QueryBy;
+use jmap_proto::{
+    error::{
+        method::MethodError,
+        set::{SetError, SetErrorType},
+    },
+    method::undelete::{
+        DeletedEmail, ListDeletedEmailRequest, ListDeletedEmailResponse, UndeleteEmailRequest,
+        UndeleteEmailResponse,
+    },
+    types::{
+        blob::BlobId,
+        collection::Collection,
+        date::UTCDate,
+        id::Id,
+        keyword::Keyword,
+        property::Property,
+        state::{State, StateChange},
+        type_state::DataType,
+    },
+};
+use mail_parser::{GetHeader, HeaderName, MessageParser};
+use store::{
+    ahash::AHashMap,
+    write::{
+        key::{DeserializeBigEndian, KeySerializer},
+        now, BatchBuilder, Bincode, BlobOp, ValueClass,
+    },
+    BlobClass, Deserialize, IterateParams, Serialize, ValueKey, U32_LEN, U64_LEN,
+};
+use utils::{map::vec_map::VecMap, BlobHash, BLOB_HASH_LEN};
+
+use crate::{
+    auth::AccessToken,
+    mailbox::{UidMailbox, INBOX_ID, RECENTLY_DELETED_ID},
+    IngestError, JMAP,
+};
+
+use super::{
+    ingest::{IngestEmail, IngestSource},
+    metadata::MessageMetadata,
+};
+
+// Held emails share the blob reservation subspace with temporary uploads
+// and enterprise undelete holds, their values start with a type tag.
+const HELD_EMAIL_TAG: &[u8] = b"$held$";
+const HELD_EMAIL_HEADER_LEN: usize = HELD_EMAIL_TAG.len() + U32_LEN + U32_LEN + U64_LEN + U64_LEN;
+
+#[derive(Debug)]
+pub struct HeldEmail {
+    pub uid: u32,
+    pub size: u32,
+    pub deleted_at: u64,
+    pub received_at: u64,
+    pub metadata: HeldEmailMetadata,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct HeldEmailMetadata {
+    pub mailbox_ids: Vec<u32>,
+    pub keywords: Vec<String>,
+    pub subject: String,
+}
+
+impl JMAP {
+    // The principal's retention overrides the server-wide default
+    pub async fn undelete_retention(
+        &self,
+        account_id: u32,
+    ) -> Result<Option<Duration>, MethodError> {
+        let retention = self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    event = "error",
+                    context = "undelete_retention",
+                    account_id = account_id,
+                    error = ?err,
+                    "Failed to obtain undelete retention for account.");
+                MethodError::ServerPartialFail
+            })?
+            .map(|p| p.undelete_retention)
+            .unwrap_or_default();
+
+        Ok(if retention > 0 {
+            Some(Duration::from_secs(retention))
+        } else {
+            self.core.jmap.mail_undelete_retention
+        })
+    }
+
+    pub async fn hold_deleted_email(
+        &self,
+        batch: &mut BatchBuilder,
+        account_id: u32,
+        document_id: u32,
+        mailboxes: &[UidMailbox],
+        retention: Duration,
+    ) -> Result<(), MethodError> {
+        let metadata = if let Some(metadata) = self
+            .get_property::<Bincode<MessageMetadata>>(
+                account_id,
+                Collection::Email,
+                document_id,
+                &Property::BodyStructure,
+            )
+            .await?
+        {
+            metadata.inner
+        } else {
+            return Ok(());
+        };
+        let keywords = self
+            .get_property::<Vec<Keyword>>(
+                account_id,
+                Collection::Email,
+                document_id,
+                Property::Keywords,
+            )
+            .await?
+            .unwrap_or_default();
+
+        // Held emails are listed over IMAP in a virtual mailbox with its own UID sequence
+        let uid = self
+            .assign_imap_uid(account_id, RECENTLY_DELETED_ID)
+            .await
+            .map_err(|err| {
+                tracing::error!(event = "error",
+                                context = "store",
+                                account_id = account_id,
+                                error = ?err,
+                                "Failed to assign UID to held email");
+                MethodError::ServerPartialFail
+            })?;
+
+        // The blob reservation keeps the message alive after it is purged,
+        // expired reservations are removed by the housekeeper's blob purge.
+        let now = now();
+        batch.set(
+            BlobOp::Reserve {
+                hash: metadata.blob_hash.clone(),
+                until: now + retention.as_secs(),
+            },
+            HeldEmail {
+                uid,
+                size: metadata.size as u32,
+                deleted_at: now,
+                received_at: metadata.received_at,
+                metadata: HeldEmailMetadata {
+                    mailbox_ids: mailboxes.iter().map(|m| m.mailbox_id).collect(),
+                    keywords: keywords
+                        .iter()
+                        .filter(|k| **k != Keyword::Deleted)
+                        .map(|k| k.to_string())
+                        .collect(),
+                    subject: metadata
+                        .contents
+                        .root_part()
+                        .headers
+                        .header_value(&HeaderName::Subject)
+                        .and_then(|v| v.as_text())
+                        .unwrap_or_default()
+                        .to_string(),
+                },
+            }
+            .serialize(),
+        );
+
+        Ok(())
+    }
+
+    pub async fn list_held_emails(
+        &self,
+        account_id: u32,
+    ) -> Result<Vec<(BlobHash, u64, HeldEmail)>, MethodError> {
+        let from_key = ValueKey {
+            account_id,
+            collection: 0,
+            document_id: 0,
+            class: ValueClass::Blob(BlobOp::Reserve {
+                hash: BlobHash::default(),
+                until: 0,
+            }),
+        };
+        let to_key = ValueKey {
+            account_id: account_id + 1,
+            collection: 0,
+            document_id: 0,
+            class: ValueClass::Blob(BlobOp::Reserve {
+                hash: BlobHash::default(),
+                until: 0,
+            }),
+        };
+
+        let now = now();
+        let mut results = Vec::new();
+
+        self.core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(from_key, to_key).ascending(),
+                |key, value| {
+                    let expires_at = key.deserialize_be_u64(key.len() - U64_LEN)?;
+                    if value.starts_with(HELD_EMAIL_TAG) && expires_at > now {
+                        // Skip entries that cannot be read rather than failing the whole listing
+                        match key
+                            .get(U32_LEN..U32_LEN + BLOB_HASH_LEN)
+                            .and_then(|hash| BlobHash::try_from_hash_slice(hash).ok())
+                            .ok_or_else(|| {
+                                store::Error::InternalError(format!(
+                                    "Invalid key {key:?} in blob hash tables"
+                                ))
+                            })
+                            .and_then(|hash| HeldEmail::deserialize(value).map(|held| (hash, held)))
+                        {
+                            Ok((hash, held)) => {
+                                results.push((hash, expires_at, held));
+                            }
+                            Err(err) => {
+                                tracing::warn!(event = "error",
+                                               context = "store",
+                                               account_id = account_id,
+                                               error = ?err,
+                                               "Skipping unreadable held email");
+                            }
+                        }
+                    }
+                    Ok(true)
+                },
+            )
+            .await
+            .map_err(|err| {
+                tracing::error!(event = "error",
+                                context = "store",
+                                account_id = account_id,
+                                error = ?err,
+                                "Failed to list held emails");
+                MethodError::ServerPartialFail
+            })?;
+
+        Ok(results)
+    }
+
+    pub async fn email_list_deleted(
+        &self,
+        request: ListDeletedEmailRequest,
+    ) -> Result<ListDeletedEmailResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let mut held_emails = self.list_held_emails(account_id).await?;
+
+        // Most recently deleted first
+        held_emails.sort_by_key(|(_, _, held)| std::cmp::Reverse(held.deleted_at));
+
+        Ok(ListDeletedEmailResponse {
+            account_id: request.account_id,
+            list: held_emails
+                .into_iter()
+                .map(|(hash, expires_at, held)| DeletedEmail {
+                    blob_id: BlobId::new(
+                        hash,
+                        BlobClass::Reserved {
+                            account_id,
+                            expires: expires_at,
+                        },
+                    ),
+                    size: held.size as usize,
+                    subject: held.metadata.subject,
+                    mailbox_ids: held
+                        .metadata
+                        .mailbox_ids
+                        .into_iter()
+                        .map(|mailbox_id| (Id::from(mailbox_id), true))
+                        .collect(),
+                    keywords: held
+                        .metadata
+                        .keywords
+                        .into_iter()
+                        .map(|keyword| (Keyword::from(keyword), true))
+                        .collect(),
+                    received_at: UTCDate::from_timestamp(held.received_at as i64),
+                    deleted_at: UTCDate::from_timestamp(held.deleted_at as i64),
+                    expires_at: UTCDate::from_timestamp(expires_at as i64),
+                })
+                .collect(),
+        })
+    }
+
+    pub async fn email_undelete(
+        &self,
+        request: UndeleteEmailRequest,
+        access_token: &AccessToken,
+    ) -> Result<UndeleteEmailResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let old_state = self.get_state(account_id, Collection::Email).await?;
+        let mut response = UndeleteEmailResponse {
+            account_id: request.account_id,
+            new_state: old_state.clone(),
+            old_state,
+            created: VecMap::new(),
+            not_created: VecMap::new(),
+            state_change: None,
+        };
+
+        if request.blob_ids.len() > self.core.jmap.set_max_objects {
+            return Err(MethodError::RequestTooLarge);
+        }
+
+        let mut held_emails = self
+            .list_held_emails(account_id)
+            .await?
+            .into_iter()
+            .map(|(hash, expires_at, held)| ((hash, expires_at), held))
+            .collect::<AHashMap<_, _>>();
+        let valid_mailbox_ids = self.mailbox_get_or_create(account_id).await?;
+
+        // Messages are restored to their original mailboxes unless a destination is given
+        let mut dest_mailbox_ids = Vec::with_capacity(request.mailbox_ids.len());
+        for mailbox_id in &request.mailbox_ids {
+            let mailbox_id = mailbox_id.document_id();
+            if valid_mailbox_ids.contains(mailbox_id) {
+                dest_mailbox_ids.push(mailbox_id);
+            } else {
+                return Err(MethodError::InvalidArguments(format!(
+                    "Mailbox {} does not exist.",
+                    Id::from(mailbox_id)
+                )));
+            }
+        }
+
+        // Restored messages count towards the quota again
+        let account_quota = self.get_quota(access_token, account_id).await?;
+
+        let mut batch = BatchBuilder::new();
+        batch.with_account_id(account_id);
+
+        for blob_id in request.blob_ids {
+            let held = match &blob_id.class {
+                BlobClass::Reserved {
+                    account_id: blob_account_id,
+                    expires,
+                } if *blob_account_id == account_id && blob_id.section.is_none() => {
+                    held_emails.remove(&(blob_id.hash.clone(), *expires))
+                }
+                _ => None,
+            };
+            let held = if let Some(held) = held {
+                held
+            } else {
+                response.not_created.append(
+                    blob_id,
+                    SetError::new(SetErrorType::BlobNotFound)
+                        .with_description("Deleted message not found or expired."),
+                );
+                continue;
+            };
+
+            // Fetch raw message
+            let raw_message =
+                if let Some(raw_message) = self.get_blob(&blob_id.hash, 0..usize::MAX).await? {
+                    raw_message
+                } else {
+                    response.not_created.append(
+                        blob_id,
+                        SetError::new(SetErrorType::BlobNotFound)
+                            .with_description("Deleted message not found or expired."),
+                    );
+                    continue;
+                };
+
+            // Restore to the original mailboxes, or to the Inbox if none of them exist anymore
+            let mut mailbox_ids = if dest_mailbox_ids.is_empty() {
+                held.metadata
+                    .mailbox_ids
+                    .into_iter()
+                    .filter(|mailbox_id| valid_mailbox_ids.contains(*mailbox_id))
+                    .collect::<Vec<_>>()
+            } else {
+                dest_mailbox_ids.clone()
+            };
+            if mailbox_ids.is_empty() {
+                mailbox_ids.push(INBOX_ID);
+            }
+
+            match self
+                .email_ingest(IngestEmail {
+                    raw_message: &raw_message,
+                    message: MessageParser::new().parse(&raw_message),
+                    account_id,
+                    account_quota,
+                    mailbox_ids,
+                    keywords: held
+                        .metadata
+                        .keywords
+                        .into_iter()
+                        .map(Keyword::from)
+                        .collect(),
+                    received_at: held.received_at.into(),
+                    source: IngestSource::Jmap,
+                    encrypt: false,
+                })
+                .await
+            {
+                Ok(email) => {
+                    if let BlobClass::Reserved { expires, .. } = &blob_id.class {
+                        batch.clear(BlobOp::Reserve {
+                            hash: blob_id.hash.clone(),
+                            until: *expires,
+                        });
+                    }
+                    response.created.append(blob_id, email.into());
+                }
+                Err(IngestError::Permanent { reason, .. }) => {
+                    response.not_created.append(
+                        blob_id,
+                        SetError::new(SetErrorType::InvalidEmail).with_description(reason),
+                    );
+                }
+                Err(IngestError::OverQuota) => {
+                    response.not_created.append(
+                        blob_id,
+                        SetError::new(SetErrorType::OverQuota)
+                            .with_description("You have exceeded your disk quota."),
+                    );
+                }
+                Err(IngestError::Temporary) => {
+                    return Err(MethodError::ServerPartialFail);
+                }
+            }
+        }
+
+        // Release restored messages
+        if !batch.is_empty() {
+            self.write_batch(batch).await?;
+        }
+
+        // Update state
+        if !response.created.is_empty() {
+            response.new_state = self.get_state(account_id, Collection::Email).await?;
+            if let State::Exact(change_id) = &response.new_state {
+                response.state_change = StateChange::new(account_id)
+                    .with_change(DataType::Email, *change_id)
+                    .with_change(DataType::Mailbox, *change_id)
+                    .with_change(DataType::Thread, *change_id)
+                    .into()
+            }
+        }
+
+        Ok(response)
+    }
+}
+
+impl Serialize for HeldEmail {
+    fn serialize(self) -> Vec<u8> {
+        let metadata = Bincode::new(self.metadata).serialize();
+        KeySerializer::new(HELD_EMAIL_HEADER_LEN + metadata.len())
+            .write(HELD_EMAIL_TAG)
+            .write(self.uid)
+            .write(self.size)
+            .write(self.deleted_at)
+            .write(self.received_at)
+            .write(metadata.as_slice())
+            .finalize()
+    }
+}
+
+impl Deserialize for HeldEmail {
+    fn deserialize(bytes: &[u8]) -> store::Result<Self> {
+        let bytes = bytes.strip_prefix(HELD_EMAIL_TAG).ok_or_else(|| {
+            store::Error::InternalError("Invalid held email type tag".to_string())
+        })?;
+
+        Ok(HeldEmail {
+            uid: bytes.deserialize_be_u32(0)?,
+            size: bytes.deserialize_be_u32(U32_LEN)?,
+            deleted_at: bytes.deserialize_be_u64(U32_LEN + U32_LEN)?,
+            received_at: bytes.deserialize_be_u64(U32_LEN + U32_LEN + U64_LEN)?,
+            metadata: Bincode::<HeldEmailMetadata>::deserialize(
+                bytes
+                    .get(HELD_EMAIL_HEADER_LEN - HELD_EMAIL_TAG.len()..)
+                    .unwrap_or_default(),
+            )?
+            .inner,
+        })
+    }
+}
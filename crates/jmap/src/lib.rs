@@ -57,16 +57,22 @@ use utils::{
 pub mod api;
 pub mod auth;
 pub mod blob;
+pub mod calendar;
+pub mod calendar_event;
+pub mod calendar_event_notification;
 pub mod changes;
 pub mod email;
 pub mod identity;
 pub mod mailbox;
+pub mod mdn;
 pub mod principal;
 pub mod push;
 pub mod quota;
 pub mod services;
+pub mod share_notification;
 pub mod sieve;
 pub mod submission;
+pub mod template;
 pub mod thread;
 pub mod vacation;
 pub mod websocket;
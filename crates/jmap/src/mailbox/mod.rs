@@ -25,6 +25,7 @@ pub const DRAFTS_ID: u32 = 3;
 pub const SENT_ID: u32 = 4;
 pub const ARCHIVE_ID: u32 = 5;
 pub const TOMBSTONE_ID: u32 = u32::MAX - 1;
+pub const RECENTLY_DELETED_ID: u32 = u32::MAX - 2;
 
 #[derive(Debug, Clone, Copy)]
 pub struct UidMailbox {
@@ -24,7 +24,7 @@ use jmap_proto::{
         property::Property,
         state::StateChange,
         type_state::DataType,
-        value::{MaybePatchValue, SetValue, Value},
+        value::{AclGrant, MaybePatchValue, SetValue, Value},
     },
 };
 use store::{
@@ -115,6 +115,7 @@ impl JMAP {
                         }
                     }
 
+                    let acl_changes = acl_changes(&builder);
                     batch.create_document().custom(builder);
 
                     match self
@@ -126,6 +127,17 @@ impl JMAP {
                         .and_then(|ids| ids.last_document_id())
                     {
                         Ok(document_id) => {
+                            if let Some((acl_current, acl_new, name)) = acl_changes {
+                                self.notify_acl_changes(
+                                    access_token.primary_id(),
+                                    account_id,
+                                    document_id,
+                                    name.as_deref(),
+                                    &acl_current,
+                                    &acl_new,
+                                )
+                                .await;
+                            }
                             changes.log_insert(Collection::Mailbox, document_id);
                             ctx.mailbox_ids.insert(document_id);
                             ctx.response.created(id, document_id);
@@ -222,11 +234,23 @@ impl JMAP {
                             }
                         }
 
+                        let acl_changes = acl_changes(&builder);
                         batch.update_document(document_id).custom(builder);
 
                         if !batch.is_empty() {
                             match self.core.storage.data.write(batch.build()).await {
                                 Ok(_) => {
+                                    if let Some((acl_current, acl_new, name)) = acl_changes {
+                                        self.notify_acl_changes(
+                                            access_token.primary_id(),
+                                            account_id,
+                                            document_id,
+                                            name.as_deref(),
+                                            &acl_current,
+                                            &acl_new,
+                                        )
+                                        .await;
+                                    }
                                     changes.log_update(Collection::Mailbox, document_id);
                                 }
                                 Err(store::Error::AssertValueFailed) => {
@@ -988,3 +1012,31 @@ impl MailboxSubscribe for Object<Value> {
         }
     }
 }
+
+#[allow(clippy::type_complexity)]
+fn acl_changes(
+    builder: &ObjectIndexBuilder,
+) -> Option<(Vec<AclGrant>, Vec<AclGrant>, Option<String>)> {
+    builder
+        .changes()
+        .filter(|changes| changes.properties.contains_key(&Property::Acl))
+        .map(|_| {
+            (
+                builder
+                    .current()
+                    .and_then(|current| current.inner.properties.get(&Property::Acl))
+                    .and_then(|acl| acl.as_acl())
+                    .cloned()
+                    .unwrap_or_default(),
+                builder
+                    .get(&Property::Acl)
+                    .as_acl()
+                    .cloned()
+                    .unwrap_or_default(),
+                builder
+                    .get(&Property::Name)
+                    .as_string()
+                    .map(|name| name.to_string()),
+            )
+        })
+}
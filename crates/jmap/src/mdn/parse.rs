@@ -0,0 +1,222 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::mdn::{ParseMdnRequest, ParseMdnResponse},
+    object::Object,
+    types::{collection::Collection, id::Id, property::Property, value::Value},
+};
+use mail_parser::{MessageParser, MimeHeaders};
+use store::query::Filter;
+use utils::map::vec_map::VecMap;
+
+use crate::{auth::AccessToken, JMAP};
+
+impl JMAP {
+    pub async fn mdn_parse(
+        &self,
+        request: ParseMdnRequest,
+        access_token: &AccessToken,
+    ) -> Result<ParseMdnResponse, MethodError> {
+        if request.blob_ids.len() > self.core.jmap.mail_parse_max_items {
+            return Err(MethodError::RequestTooLarge);
+        }
+        let account_id = request.account_id.document_id();
+        let mut response = ParseMdnResponse {
+            account_id: request.account_id,
+            parsed: VecMap::with_capacity(request.blob_ids.len()),
+            not_parsable: vec![],
+            not_found: vec![],
+        };
+
+        for blob_id in request.blob_ids {
+            // Fetch raw message to parse
+            let raw_message = match self.blob_download(&blob_id, access_token).await? {
+                Some(raw_message) => raw_message,
+                None => {
+                    response.not_found.push(blob_id);
+                    continue;
+                }
+            };
+            let message = if let Some(message) = MessageParser::new().parse(&raw_message) {
+                message
+            } else {
+                response.not_parsable.push(blob_id);
+                continue;
+            };
+
+            // Find the disposition notification part
+            let report = if let Some(report) = message.parts.iter().find(|part| {
+                part.content_type().map_or(false, |ct| {
+                    ct.ctype().eq_ignore_ascii_case("message")
+                        && ct.subtype().map_or(false, |st| {
+                            st.eq_ignore_ascii_case("disposition-notification")
+                        })
+                })
+            }) {
+                String::from_utf8_lossy(report.contents()).into_owned()
+            } else {
+                response.not_parsable.push(blob_id);
+                continue;
+            };
+
+            let mut mdn = Object::with_capacity(12)
+                .with_property(Property::ForEmailId, Value::Null)
+                .with_property(
+                    Property::Subject,
+                    message
+                        .subject()
+                        .map(|subject| Value::Text(subject.to_string()))
+                        .unwrap_or_default(),
+                )
+                .with_property(
+                    Property::TextBody,
+                    message
+                        .body_text(0)
+                        .map(|text| Value::Text(text.into_owned()))
+                        .unwrap_or_default(),
+                )
+                .with_property(
+                    Property::IncludeOriginalMessage,
+                    message.parts.iter().any(|part| part.is_message()),
+                )
+                .with_property(Property::ReportingUA, Value::Null)
+                .with_property(Property::MdnGateway, Value::Null)
+                .with_property(Property::OriginalRecipient, Value::Null)
+                .with_property(Property::FinalRecipient, Value::Null)
+                .with_property(Property::OriginalMessageId, Value::Null)
+                .with_property(Property::Error, Value::Null);
+            let mut disposition = None;
+            let mut errors = Vec::new();
+            let mut extension_fields = Object::with_capacity(0);
+
+            for (name, value) in parse_report_fields(&report) {
+                let property = match name.to_ascii_lowercase().as_str() {
+                    "reporting-ua" => Property::ReportingUA,
+                    "mdn-gateway" => Property::MdnGateway,
+                    "original-recipient" => Property::OriginalRecipient,
+                    "final-recipient" => Property::FinalRecipient,
+                    "original-message-id" => Property::OriginalMessageId,
+                    "disposition" => {
+                        disposition = parse_disposition(&value);
+                        continue;
+                    }
+                    "error" => {
+                        errors.push(Value::Text(value));
+                        continue;
+                    }
+                    _ => {
+                        extension_fields.append(Property::_T(name), value);
+                        continue;
+                    }
+                };
+                mdn.set(property, value);
+            }
+
+            // Disposition is mandatory
+            if let Some(disposition) = disposition {
+                mdn.set(Property::Disposition, disposition);
+            } else {
+                response.not_parsable.push(blob_id);
+                continue;
+            }
+            if !errors.is_empty() {
+                mdn.set(Property::Error, Value::List(errors));
+            }
+            mdn.set(
+                Property::ExtensionFields,
+                if !extension_fields.properties.is_empty() {
+                    Value::Object(extension_fields)
+                } else {
+                    Value::Null
+                },
+            );
+
+            // Find the original message
+            if let Some(message_id) = mdn
+                .get(&Property::OriginalMessageId)
+                .as_string()
+                .map(|id| id.trim().trim_start_matches('<').trim_end_matches('>'))
+                .filter(|id| !id.is_empty())
+            {
+                if let Some(document_id) = self
+                    .filter(
+                        account_id,
+                        Collection::Email,
+                        vec![Filter::eq(Property::MessageId, message_id)],
+                    )
+                    .await?
+                    .results
+                    .min()
+                {
+                    if let Some(thread_id) = self
+                        .get_property::<u32>(
+                            account_id,
+                            Collection::Email,
+                            document_id,
+                            Property::ThreadId,
+                        )
+                        .await?
+                    {
+                        mdn.set(Property::ForEmailId, Id::from_parts(thread_id, document_id));
+                    }
+                }
+            }
+
+            response.parsed.append(blob_id, mdn);
+        }
+
+        Ok(response)
+    }
+}
+
+fn parse_report_fields(report: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+
+    for line in report.lines() {
+        if line.starts_with([' ', '\t']) {
+            // Unfold continuation lines
+            if let Some((_, value)) = fields.last_mut() {
+                let line = line.trim();
+                if !line.is_empty() {
+                    if !value.is_empty() {
+                        value.push(' ');
+                    }
+                    value.push_str(line);
+                }
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            let name = name.trim();
+            if !name.is_empty() {
+                fields.push((name.to_string(), value.trim().to_string()));
+            }
+        }
+    }
+
+    fields
+}
+
+fn parse_disposition(value: &str) -> Option<Value> {
+    let (modes, disposition_type) = value.split_once(';')?;
+    let (action_mode, sending_mode) = modes.split_once('/')?;
+    let disposition_type = disposition_type
+        .split_once('/')
+        .map_or(disposition_type, |(disposition_type, _)| disposition_type);
+
+    Some(Value::Object(
+        Object::with_capacity(3)
+            .with_property(
+                Property::ActionMode,
+                action_mode.trim().to_ascii_lowercase(),
+            )
+            .with_property(
+                Property::SendingMode,
+                sending_mode.trim().to_ascii_lowercase(),
+            )
+            .with_property(Property::Type, disposition_type.trim().to_ascii_lowercase()),
+    ))
+}
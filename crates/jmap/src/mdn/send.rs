@@ -0,0 +1,655 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{borrow::Cow, fmt::Write, sync::Arc};
+
+use common::listener::ServerInstance;
+use jmap_proto::{
+    error::{
+        method::MethodError,
+        set::{SetError, SetErrorType},
+    },
+    method::{
+        mdn::{SendMdnRequest, SendMdnResponse},
+        set::{self, SetRequest},
+    },
+    object::Object,
+    request::{
+        method::{MethodFunction, MethodName, MethodObject},
+        reference::MaybeReference,
+        Call, RequestMethod,
+    },
+    response::references::EvalObjectReferences,
+    types::{
+        collection::Collection,
+        id::Id,
+        keyword::Keyword,
+        property::{HeaderForm, HeaderProperty, Property},
+        state::StateChange,
+        type_state::DataType,
+        value::{MaybePatchValue, SetValue, Value},
+    },
+};
+use mail_builder::{
+    headers::{address::Address, content_type::ContentType, HeaderType},
+    mime::{BodyPart, MimePart},
+    MessageBuilder,
+};
+use smtp_proto::{MailFrom, RcptTo};
+use store::write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder, Bincode, F_VALUE};
+use utils::map::vec_map::VecMap;
+
+use crate::{
+    email::{
+        headers::HeaderToValue, ingest::MAX_RETRIES, metadata::MessageMetadata, set::TagManager,
+    },
+    identity::set::sanitize_email,
+    JMAP,
+};
+
+struct MdnIdentity {
+    name: Option<String>,
+    email: String,
+}
+
+impl JMAP {
+    pub async fn mdn_send(
+        &self,
+        request: SendMdnRequest,
+        instance: &Arc<ServerInstance>,
+        next_call: &mut Option<Call<RequestMethod>>,
+    ) -> Result<SendMdnResponse, MethodError> {
+        if request.send.len() > self.core.jmap.set_max_objects {
+            return Err(MethodError::RequestTooLarge);
+        }
+
+        let account_id = request.account_id.document_id();
+        let mut response = SendMdnResponse {
+            account_id: request.account_id,
+            sent: VecMap::with_capacity(request.send.len()),
+            not_sent: VecMap::new(),
+        };
+
+        // Fetch identity
+        let identity = if let Some(mut identity) = self
+            .get_property::<Object<Value>>(
+                account_id,
+                Collection::Identity,
+                request.identity_id.document_id(),
+                Property::Value,
+            )
+            .await?
+        {
+            if let Some(email) = identity
+                .properties
+                .remove(&Property::Email)
+                .and_then(|value| value.try_unwrap_string())
+            {
+                MdnIdentity {
+                    name: identity
+                        .properties
+                        .remove(&Property::Name)
+                        .and_then(|value| value.try_unwrap_string())
+                        .filter(|name| !name.is_empty()),
+                    email,
+                }
+            } else {
+                return Err(MethodError::InvalidArguments(
+                    "Identity has no email address.".to_string(),
+                ));
+            }
+        } else {
+            return Err(MethodError::InvalidArguments(format!(
+                "Identity {} not found.",
+                request.identity_id
+            )));
+        };
+
+        // Obtain server hostname
+        let hostname = self
+            .core
+            .storage
+            .config
+            .get("lookup.default.hostname")
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| "localhost".to_string());
+
+        // Send MDNs
+        let mut sent_email_ids = VecMap::new();
+        let mut changes = ChangeLogBuilder::new();
+        for (id, object) in request.send {
+            match self
+                .send_mdn(
+                    account_id, &identity, &hostname, &response, instance, object,
+                )
+                .await?
+            {
+                Ok((email_id, mdn)) => {
+                    self.mdn_set_sent(account_id, email_id, &mut changes)
+                        .await?;
+                    sent_email_ids.append(id.clone(), email_id);
+                    response.sent.append(id, mdn);
+                }
+                Err(err) => {
+                    response.not_sent.append(id, err);
+                }
+            }
+        }
+
+        // Write changes
+        if !changes.is_empty() {
+            let change_id = self.commit_changes(account_id, changes).await?;
+            self.broadcast_state_change(
+                StateChange::new(account_id).with_change(DataType::Email, change_id),
+            )
+            .await;
+        }
+
+        // Apply any additional updates on success
+        if let Some(on_success_update_email) = request
+            .on_success_update_email
+            .filter(|_| !sent_email_ids.is_empty())
+        {
+            let mut update: VecMap<Id, Object<SetValue>> = VecMap::new();
+            for (id, object) in on_success_update_email {
+                let email_id = match id {
+                    MaybeReference::Value(id) => id,
+                    MaybeReference::Reference(id_ref) => {
+                        if let Some(email_id) = sent_email_ids.get(&id_ref) {
+                            *email_id
+                        } else {
+                            continue;
+                        }
+                    }
+                };
+                let update = &mut update
+                    .get_mut_or_insert_with(email_id, || Object {
+                        properties: VecMap::with_capacity(object.properties.len()),
+                    })
+                    .properties;
+                for (property, value) in object.properties {
+                    update.append(property, value);
+                }
+            }
+
+            if !update.is_empty() {
+                *next_call = Call {
+                    id: String::new(),
+                    name: MethodName::new(MethodObject::Email, MethodFunction::Set),
+                    method: RequestMethod::Set(SetRequest {
+                        account_id: request.account_id,
+                        if_in_state: None,
+                        create: None,
+                        update: update.into(),
+                        destroy: None,
+                        arguments: set::RequestArguments::Email,
+                    }),
+                }
+                .into();
+            }
+        }
+
+        Ok(response)
+    }
+
+    async fn send_mdn(
+        &self,
+        account_id: u32,
+        identity: &MdnIdentity,
+        hostname: &str,
+        response: &SendMdnResponse,
+        instance: &Arc<ServerInstance>,
+        object: Object<SetValue>,
+    ) -> Result<Result<(Id, Object<Value>), SetError>, MethodError> {
+        let mut email_id = None;
+        let mut subject = None;
+        let mut text_body = None;
+        let mut include_original = false;
+        let mut reporting_ua = None;
+        let mut final_recipient = None;
+        let mut disposition = None;
+        let mut errors = Vec::new();
+        let mut extension_fields = Vec::new();
+
+        for (property, value) in object.properties {
+            let value = match response.eval_object_references(value) {
+                Ok(MaybePatchValue::Value(value)) => value,
+                Ok(MaybePatchValue::Patch(_)) => {
+                    return Ok(Err(SetError::invalid_properties()
+                        .with_property(property)
+                        .with_description("Patches are not supported.")));
+                }
+                Err(err) => {
+                    return Ok(Err(err));
+                }
+            };
+
+            match (&property, value) {
+                (Property::ForEmailId, Value::Id(value)) => {
+                    email_id = value.into();
+                }
+                (Property::Subject, Value::Text(value)) => {
+                    subject = value.into();
+                }
+                (Property::TextBody, Value::Text(value)) => {
+                    text_body = value.into();
+                }
+                (Property::IncludeOriginalMessage, Value::Bool(value)) => {
+                    include_original = value;
+                }
+                (Property::ReportingUA, Value::Text(value)) => {
+                    reporting_ua = value.into();
+                }
+                (Property::FinalRecipient, Value::Text(value)) => {
+                    final_recipient = value.into();
+                }
+                (Property::Disposition, Value::Object(value)) => match parse_disposition(value) {
+                    Ok(value) => {
+                        disposition = value.into();
+                    }
+                    Err(err) => {
+                        return Ok(Err(err));
+                    }
+                },
+                (Property::Error, Value::List(values)) => {
+                    for value in values {
+                        if let Value::Text(value) = value {
+                            errors.push(value);
+                        } else {
+                            return Ok(Err(SetError::invalid_properties()
+                                .with_property(Property::Error)
+                                .with_description("Error values must be strings.")));
+                        }
+                    }
+                }
+                (Property::ExtensionFields, Value::Object(value)) => {
+                    for (name, value) in value.properties {
+                        match (name, value) {
+                            (Property::_T(name), Value::Text(value))
+                                if is_valid_field_name(&name) =>
+                            {
+                                extension_fields.push((name, value));
+                            }
+                            _ => {
+                                return Ok(Err(SetError::invalid_properties()
+                                    .with_property(Property::ExtensionFields)
+                                    .with_description("Invalid extension field.")));
+                            }
+                        }
+                    }
+                }
+                (
+                    Property::Subject
+                    | Property::TextBody
+                    | Property::IncludeOriginalMessage
+                    | Property::ReportingUA
+                    | Property::FinalRecipient
+                    | Property::Error
+                    | Property::ExtensionFields,
+                    Value::Null,
+                ) => (),
+                _ => {
+                    return Ok(Err(SetError::invalid_properties()
+                        .with_property(property)
+                        .with_description("Field could not be set.")));
+                }
+            }
+        }
+
+        // Make sure we have all required fields.
+        let (email_id, (action_mode, sending_mode, disposition_type)) =
+            if let (Some(email_id), Some(disposition)) = (email_id, disposition) {
+                (email_id, disposition)
+            } else {
+                return Ok(Err(SetError::invalid_properties()
+                    .with_properties([Property::ForEmailId, Property::Disposition])
+                    .with_description(
+                        "forEmailId and disposition properties are required.",
+                    )));
+            };
+
+        // Obtain message metadata
+        let metadata = if let Some(metadata) = self
+            .get_property::<Bincode<MessageMetadata>>(
+                account_id,
+                Collection::Email,
+                email_id.document_id(),
+                Property::BodyStructure,
+            )
+            .await?
+        {
+            metadata.inner
+        } else {
+            return Ok(Err(SetError::not_found()
+                .with_property(Property::ForEmailId)
+                .with_description("Email not found.")));
+        };
+
+        // An MDN can only be sent once per message
+        if self
+            .get_property::<Vec<Keyword>>(
+                account_id,
+                Collection::Email,
+                email_id.document_id(),
+                Property::Keywords,
+            )
+            .await?
+            .unwrap_or_default()
+            .contains(&Keyword::MdnSent)
+        {
+            return Ok(Err(SetError::new(SetErrorType::MdnAlreadySent)
+                .with_description(
+                    "An MDN has already been sent for this message.",
+                )));
+        }
+
+        // Obtain the addresses requesting the MDN
+        let headers = &metadata.contents.parts[0].headers;
+        let mut rcpt_to: Vec<RcptTo<String>> = Vec::new();
+        let mut to = Vec::new();
+        if let Value::List(addresses) = headers.header_to_value(
+            &Property::Header(HeaderProperty {
+                form: HeaderForm::Addresses,
+                header: "Disposition-Notification-To".to_string(),
+                all: false,
+            }),
+            &metadata.raw_headers,
+        ) {
+            for address in addresses {
+                if let Value::Object(mut address) = address {
+                    if let Some(email) = address
+                        .properties
+                        .remove(&Property::Email)
+                        .and_then(|value| value.try_unwrap_string())
+                        .and_then(|email| sanitize_email(&email))
+                    {
+                        if !rcpt_to.iter().any(|rcpt| rcpt.address == email) {
+                            to.push(Address::new_address(
+                                address
+                                    .properties
+                                    .remove(&Property::Name)
+                                    .and_then(|value| value.try_unwrap_string()),
+                                email.clone(),
+                            ));
+                            rcpt_to.push(RcptTo {
+                                address: email,
+                                ..Default::default()
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        if rcpt_to.is_empty() {
+            return Ok(Err(SetError::invalid_properties()
+                .with_property(Property::ForEmailId)
+                .with_description(
+                    "The email does not contain a valid Disposition-Notification-To header.",
+                )));
+        }
+
+        // Obtain original message headers
+        let original_recipient = headers
+            .header_to_value(
+                &Property::Header(HeaderProperty {
+                    form: HeaderForm::Text,
+                    header: "Original-Recipient".to_string(),
+                    all: false,
+                }),
+                &metadata.raw_headers,
+            )
+            .try_unwrap_string();
+        let original_message_id =
+            match headers.header_to_value(&Property::MessageId, &metadata.raw_headers) {
+                Value::List(ids) => ids.into_iter().next().and_then(|id| id.try_unwrap_string()),
+                _ => None,
+            };
+        let original_subject = headers
+            .header_to_value(&Property::Subject, &metadata.raw_headers)
+            .try_unwrap_string();
+
+        // Build server-set and default values
+        let mut mdn = Object::with_capacity(6);
+        let subject = subject.unwrap_or_else(|| {
+            let subject = format!(
+                "Read: {}",
+                original_subject.as_deref().unwrap_or("(no subject)")
+            );
+            mdn.append(Property::Subject, subject.clone());
+            subject
+        });
+        let text_body = text_body.unwrap_or_else(|| {
+            let text_body = format!(
+                concat!(
+                    "This is a disposition notification for the message ",
+                    "sent to {} with subject \"{}\".\r\n\r\n",
+                    "The message has been {}. There is no guarantee that ",
+                    "the message has been read or understood.\r\n"
+                ),
+                identity.email,
+                original_subject.as_deref().unwrap_or("(no subject)"),
+                disposition_type
+            );
+            mdn.append(Property::TextBody, text_body.clone());
+            text_body
+        });
+        let reporting_ua = reporting_ua.unwrap_or_else(|| {
+            let reporting_ua = format!("{hostname}; Stalwart Mail Server");
+            mdn.append(Property::ReportingUA, reporting_ua.clone());
+            reporting_ua
+        });
+        let final_recipient = final_recipient.unwrap_or_else(|| {
+            let final_recipient = format!("rfc822; {}", identity.email);
+            mdn.append(Property::FinalRecipient, final_recipient.clone());
+            final_recipient
+        });
+        if let Some(original_recipient) = &original_recipient {
+            mdn.append(Property::OriginalRecipient, original_recipient.clone());
+        }
+        if let Some(original_message_id) = &original_message_id {
+            mdn.append(
+                Property::OriginalMessageId,
+                format!("<{original_message_id}>"),
+            );
+        }
+
+        // Build the disposition notification fields
+        let mut report = String::with_capacity(256);
+        let _ = write!(report, "Reporting-UA: {reporting_ua}\r\n");
+        if let Some(original_recipient) = &original_recipient {
+            let _ = write!(report, "Original-Recipient: {original_recipient}\r\n");
+        }
+        let _ = write!(report, "Final-Recipient: {final_recipient}\r\n");
+        if let Some(original_message_id) = &original_message_id {
+            let _ = write!(report, "Original-Message-ID: <{original_message_id}>\r\n");
+        }
+        let _ = write!(
+            report,
+            "Disposition: {action_mode}/{sending_mode}; {disposition_type}\r\n"
+        );
+        for error in &errors {
+            let _ = write!(report, "Error: {error}\r\n");
+        }
+        for (name, value) in &extension_fields {
+            let _ = write!(report, "{name}: {value}\r\n");
+        }
+
+        // Build message
+        let mut parts = vec![
+            MimePart::new(
+                ContentType::new("text/plain"),
+                BodyPart::Text(text_body.into()),
+            ),
+            MimePart::new(
+                ContentType::new("message/disposition-notification"),
+                BodyPart::Text(report.into()),
+            ),
+        ];
+        if include_original {
+            if let Some(original) = self.get_blob(&metadata.blob_hash, 0..usize::MAX).await? {
+                parts.push(MimePart::new(
+                    ContentType::new("message/rfc822"),
+                    BodyPart::Binary(original.into()),
+                ));
+            } else {
+                return Ok(Err(SetError::invalid_properties()
+                    .with_property(Property::ForEmailId)
+                    .with_description("Blob for email not found.")));
+            }
+        }
+        let mut builder = MessageBuilder::new()
+            .from(Address::new_address(
+                identity.name.as_deref().map(Cow::from),
+                identity.email.as_str(),
+            ))
+            .to(Address::new_list(to))
+            .subject(subject);
+        if let Some(original_message_id) = &original_message_id {
+            builder = builder
+                .in_reply_to(original_message_id.as_str())
+                .references(original_message_id.as_str());
+        }
+        if action_mode == "automatic-action" {
+            builder = builder.header("Auto-Submitted", HeaderType::Text("auto-replied".into()));
+        }
+        let message = builder
+            .body(MimePart::new(
+                ContentType::new("multipart/report")
+                    .attribute("report-type", "disposition-notification"),
+                BodyPart::Multipart(parts),
+            ))
+            .write_to_vec()
+            .unwrap_or_default();
+
+        // Submit message using a null return path
+        match self
+            .submit_message(instance, MailFrom::default(), rcpt_to, message)
+            .await
+        {
+            Ok((Some(_), _)) => Ok(Ok((email_id, mdn))),
+            Ok((None, responses)) => Ok(Err(SetError::new(SetErrorType::ForbiddenToSend)
+                .with_description(format!(
+                    "All recipients were rejected: {}",
+                    responses
+                        .into_iter()
+                        .filter_map(|(_, response)| response)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                        .trim()
+                )))),
+            Err(err) => Ok(Err(err)),
+        }
+    }
+
+    async fn mdn_set_sent(
+        &self,
+        account_id: u32,
+        email_id: Id,
+        changes: &mut ChangeLogBuilder,
+    ) -> Result<(), MethodError> {
+        let document_id = email_id.document_id();
+        let mut try_count = 0;
+
+        loop {
+            // Obtain current keywords
+            let (mut keywords, thread_id) = if let (Some(keywords), Some(thread_id)) = (
+                self.get_property::<HashedValue<Vec<Keyword>>>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::Keywords,
+                )
+                .await?,
+                self.get_property::<u32>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::ThreadId,
+                )
+                .await?,
+            ) {
+                (TagManager::new(keywords), thread_id)
+            } else {
+                return Ok(());
+            };
+
+            keywords.update(Keyword::MdnSent, true);
+            if !keywords.has_changes() {
+                return Ok(());
+            }
+
+            // Write changes
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Email)
+                .update_document(document_id);
+            keywords.update_batch(&mut batch, Property::Keywords);
+            if changes.change_id == u64::MAX {
+                changes.change_id = self.assign_change_id(account_id).await?;
+            }
+            batch.value(Property::Cid, changes.change_id, F_VALUE);
+            match self.write_batch(batch).await {
+                Ok(_) => {
+                    changes.log_update(Collection::Email, Id::from_parts(thread_id, document_id));
+                    return Ok(());
+                }
+                Err(MethodError::ServerUnavailable) if try_count < MAX_RETRIES => {
+                    try_count += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+fn parse_disposition(disposition: Object<Value>) -> Result<(String, String, String), SetError> {
+    let mut action_mode = None;
+    let mut sending_mode = None;
+    let mut disposition_type = None;
+
+    for (property, value) in disposition.properties {
+        match (property, value) {
+            (Property::ActionMode, Value::Text(value))
+                if ["manual-action", "automatic-action"].contains(&value.as_str()) =>
+            {
+                action_mode = value.into();
+            }
+            (Property::SendingMode, Value::Text(value))
+                if ["mdn-sent-manually", "mdn-sent-automatically"].contains(&value.as_str()) =>
+            {
+                sending_mode = value.into();
+            }
+            (Property::Type, Value::Text(value))
+                if ["deleted", "dispatched", "displayed", "processed"]
+                    .contains(&value.as_str()) =>
+            {
+                disposition_type = value.into();
+            }
+            (property, _) => {
+                return Err(SetError::invalid_properties()
+                    .with_property(Property::Disposition)
+                    .with_description(format!("Invalid disposition property {property}.")));
+            }
+        }
+    }
+
+    if let (Some(action_mode), Some(sending_mode), Some(disposition_type)) =
+        (action_mode, sending_mode, disposition_type)
+    {
+        Ok((action_mode, sending_mode, disposition_type))
+    } else {
+        Err(SetError::invalid_properties()
+            .with_property(Property::Disposition)
+            .with_description("Disposition requires actionMode, sendingMode and type."))
+    }
+}
+
+fn is_valid_field_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|ch| ch.is_ascii_graphic() && ch != b':')
+}
@@ -4,20 +4,26 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use directory::QueryBy;
+use directory::{Principal, QueryBy, Type};
 use jmap_proto::{
     error::method::MethodError,
     method::get::{GetRequest, GetResponse, RequestArguments},
     object::Object,
-    types::{collection::Collection, property::Property, state::State, value::Value},
+    request::capability::{
+        Capabilities, Capability, PrincipalsAccountCapabilities, PrincipalsOwnerCapabilities,
+    },
+    types::{
+        acl::Acl, collection::Collection, id::Id, property::Property, state::State, value::Value,
+    },
 };
 
-use crate::JMAP;
+use crate::{auth::AccessToken, JMAP};
 
 impl JMAP {
     pub async fn principal_get(
         &self,
         mut request: GetRequest<RequestArguments>,
+        access_token: &AccessToken,
     ) -> Result<GetResponse, MethodError> {
         let ids = request.unwrap_ids(self.core.jmap.get_max_objects)?;
         let properties = request.unwrap_properties(&[
@@ -26,8 +32,9 @@ impl JMAP {
             Property::Name,
             Property::Description,
             Property::Email,
-            //Property::Timezone,
-            //Property::Capabilities,
+            Property::Timezone,
+            Property::Capabilities,
+            Property::Accounts,
         ]);
         let email_submission_ids = self
             .get_document_ids(u32::MAX, Collection::EmailSubmission)
@@ -81,6 +88,18 @@ impl JMAP {
                         .first()
                         .map(|email| Value::Text(email.clone()))
                         .unwrap_or(Value::Null),
+                    Property::Timezone => principal
+                        .time_zone
+                        .clone()
+                        .map(Value::Text)
+                        .unwrap_or(Value::Null),
+                    Property::Capabilities => {
+                        self.principal_capabilities(id.document_id(), &principal, access_token)
+                    }
+                    Property::Accounts => {
+                        self.principal_accounts(id.document_id(), &principal.name, access_token)
+                            .await?
+                    }
                     _ => Value::Null,
                 };
 
@@ -91,4 +110,169 @@ impl JMAP {
 
         Ok(response)
     }
+
+    fn principal_capabilities(
+        &self,
+        principal_id: u32,
+        principal: &Principal<u32>,
+        access_token: &AccessToken,
+    ) -> Value {
+        // Lists and other principals do not own an account
+        if !matches!(
+            principal.typ,
+            Type::Individual | Type::Superuser | Type::Group | Type::Resource | Type::Location
+        ) {
+            return Value::Object(Object::with_capacity(0));
+        }
+
+        // The account id is only disclosed to users that have access to it
+        let account_id = if access_token.is_primary_id(principal_id)
+            || access_token.secondary_ids().any(|id| *id == principal_id)
+        {
+            Value::Id(Id::from(principal_id))
+        } else {
+            Value::Null
+        };
+        let address = principal.emails.first();
+        let mut capabilities = Object::with_capacity(3);
+        for capability in [
+            Capability::Mail,
+            Capability::Submission,
+            Capability::Calendars,
+        ] {
+            if !self
+                .core
+                .jmap
+                .capabilities
+                .account
+                .contains_key(&capability)
+            {
+                continue;
+            }
+            let value = match capability {
+                Capability::Mail => Object::with_capacity(1),
+                Capability::Submission if address.is_some() => Object::with_capacity(1),
+                Capability::Calendars => Object::with_capacity(4)
+                    .with_property(Property::_T("mayGetAvailability".to_string()), false)
+                    .with_property(
+                        Property::_T("mayShareWith".to_string()),
+                        matches!(
+                            principal.typ,
+                            Type::Individual | Type::Superuser | Type::Group
+                        ),
+                    )
+                    .with_property(
+                        Property::_T("calendarAddress".to_string()),
+                        address
+                            .map(|address| Value::Text(format!("mailto:{address}")))
+                            .unwrap_or(Value::Null),
+                    ),
+                _ => continue,
+            };
+            capabilities.append(
+                Property::_T(capability_uri(&capability)),
+                value.with_property(Property::_T("accountId".to_string()), account_id.clone()),
+            );
+        }
+
+        Value::Object(capabilities)
+    }
+
+    async fn principal_accounts(
+        &self,
+        principal_id: u32,
+        name: &str,
+        access_token: &AccessToken,
+    ) -> Result<Value, MethodError> {
+        // Each principal owns the account with the same id, which is
+        // only disclosed to users that have access to it.
+        let is_primary = access_token.is_primary_id(principal_id);
+        if !is_primary && !access_token.secondary_ids().any(|id| *id == principal_id) {
+            return Ok(Value::Null);
+        }
+
+        let is_personal = is_primary || !access_token.is_member(principal_id);
+        let is_read_only = !access_token.is_member(principal_id)
+            && self
+                .shared_documents(
+                    access_token,
+                    principal_id,
+                    Collection::Mailbox,
+                    Acl::AddItems,
+                )
+                .await
+                .map_or(true, |ids| ids.is_empty());
+        let account_id = Id::from(principal_id);
+        let mut capabilities = Object::with_capacity(self.core.jmap.capabilities.account.len());
+        for (capability, value) in self.core.jmap.capabilities.account.iter() {
+            let value = match capability {
+                Capability::Principals if is_primary => {
+                    Capabilities::PrincipalsAccount(PrincipalsAccountCapabilities {
+                        current_user_principal_id: account_id.into(),
+                    })
+                }
+                Capability::Mail | Capability::Quota | Capability::Blob => value.clone(),
+                _ if is_primary => value.clone(),
+                _ => continue,
+            };
+            capabilities.append(Property::_T(capability_uri(capability)), to_value(&value));
+        }
+        capabilities.append(
+            Property::_T(capability_uri(&Capability::PrincipalsOwner)),
+            to_value(&Capabilities::PrincipalsOwner(
+                PrincipalsOwnerCapabilities {
+                    account_id_for_principal: account_id,
+                    principal_id: account_id,
+                },
+            )),
+        );
+
+        Ok(Value::Object(
+            Object::with_capacity(1).with_property(
+                Property::_T(account_id.to_string()),
+                Object::with_capacity(4)
+                    .with_property(Property::Name, name)
+                    .with_property(Property::_T("isPersonal".to_string()), is_personal)
+                    .with_property(Property::_T("isReadOnly".to_string()), is_read_only)
+                    .with_property(
+                        Property::_T("accountCapabilities".to_string()),
+                        capabilities,
+                    ),
+            ),
+        ))
+    }
+}
+
+fn capability_uri(capability: &Capability) -> String {
+    match serde_json::to_value(capability) {
+        Ok(serde_json::Value::String(uri)) => uri,
+        _ => String::new(),
+    }
+}
+
+fn to_value(value: &impl serde::Serialize) -> Value {
+    serde_json::to_value(value)
+        .map(json_to_value)
+        .unwrap_or_default()
+}
+
+fn json_to_value(value: serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Bool(value) => Value::Bool(value),
+        serde_json::Value::Number(value) => {
+            value.as_u64().map(Value::UnsignedInt).unwrap_or_default()
+        }
+        serde_json::Value::String(value) => Value::Text(value),
+        serde_json::Value::Array(values) => {
+            Value::List(values.into_iter().map(json_to_value).collect())
+        }
+        serde_json::Value::Object(values) => {
+            let mut object = Object::with_capacity(values.len());
+            for (key, value) in values {
+                object.append(Property::_T(key), json_to_value(value));
+            }
+            Value::Object(object)
+        }
+        serde_json::Value::Null => Value::Null,
+    }
 }
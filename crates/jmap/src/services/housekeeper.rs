@@ -47,8 +47,12 @@ struct Action {
 enum ActionClass {
     Session,
     Account,
+    Snooze,
     Store(usize),
     Acme(String),
+    QuarantineDigest,
+    DsnDigest,
+    DkimRotation,
     #[cfg(feature = "enterprise")]
     ReloadLicense,
 }
@@ -83,12 +87,34 @@ pub fn spawn_housekeeper(core: JmapInstance, mut rx: mpsc::Receiver<Event>) {
                 Instant::now() + core_.jmap.account_purge_frequency.time_to_next(),
                 ActionClass::Account,
             );
+            queue.schedule(
+                Instant::now() + core_.jmap.mail_snooze_frequency,
+                ActionClass::Snooze,
+            );
             for (idx, schedule) in core_.storage.purge_schedules.iter().enumerate() {
                 queue.schedule(
                     Instant::now() + schedule.cron.time_to_next(),
                     ActionClass::Store(idx),
                 );
             }
+            if let Some(digest) = &core_.smtp.quarantine.digest {
+                queue.schedule(
+                    Instant::now() + digest.frequency.time_to_next(),
+                    ActionClass::QuarantineDigest,
+                );
+            }
+            if let Some(digest) = &core_.smtp.queue.dsn.digest {
+                queue.schedule(
+                    Instant::now() + digest.frequency.time_to_next(),
+                    ActionClass::DsnDigest,
+                );
+            }
+            if let Some(rotation) = &core_.smtp.mail_auth.dkim.rotation {
+                queue.schedule(
+                    Instant::now() + rotation.frequency.time_to_next(),
+                    ActionClass::DkimRotation,
+                );
+            }
 
             // Add all ACME renewals to heap
             for provider in core_.tls.acme_providers.values() {
@@ -295,6 +321,17 @@ pub fn spawn_housekeeper(core: JmapInstance, mut rx: mpsc::Receiver<Event>) {
                                     ActionClass::Account,
                                 );
                             }
+                            ActionClass::Snooze => {
+                                let jmap = JMAP::from(core.clone());
+                                tokio::spawn(async move {
+                                    tracing::debug!("Waking snoozed messages.");
+                                    jmap.emails_wake_snoozed().await;
+                                });
+                                queue.schedule(
+                                    Instant::now() + core_.jmap.mail_snooze_frequency,
+                                    ActionClass::Snooze,
+                                );
+                            }
                             ActionClass::Session => {
                                 let inner = core.jmap_inner.clone();
                                 tokio::spawn(async move {
@@ -307,6 +344,45 @@ pub fn spawn_housekeeper(core: JmapInstance, mut rx: mpsc::Receiver<Event>) {
                                     ActionClass::Session,
                                 );
                             }
+                            ActionClass::QuarantineDigest => {
+                                if let Some(digest) = &core_.smtp.quarantine.digest {
+                                    let jmap = JMAP::from(core.clone());
+                                    tokio::spawn(async move {
+                                        tracing::debug!("Sending quarantine digests.");
+                                        jmap.smtp.send_quarantine_digest().await;
+                                    });
+                                    queue.schedule(
+                                        Instant::now() + digest.frequency.time_to_next(),
+                                        ActionClass::QuarantineDigest,
+                                    );
+                                }
+                            }
+                            ActionClass::DsnDigest => {
+                                if let Some(digest) = &core_.smtp.queue.dsn.digest {
+                                    let jmap = JMAP::from(core.clone());
+                                    tokio::spawn(async move {
+                                        tracing::debug!("Sending delivery status digests.");
+                                        jmap.smtp.send_dsn_digests().await;
+                                    });
+                                    queue.schedule(
+                                        Instant::now() + digest.frequency.time_to_next(),
+                                        ActionClass::DsnDigest,
+                                    );
+                                }
+                            }
+                            ActionClass::DkimRotation => {
+                                if let Some(rotation) = &core_.smtp.mail_auth.dkim.rotation {
+                                    let jmap = JMAP::from(core.clone());
+                                    tokio::spawn(async move {
+                                        tracing::debug!("Rotating DKIM keys.");
+                                        jmap.rotate_dkim_keys().await;
+                                    });
+                                    queue.schedule(
+                                        Instant::now() + rotation.frequency.time_to_next(),
+                                        ActionClass::DkimRotation,
+                                    );
+                                }
+                            }
                             ActionClass::Store(idx) => {
                                 if let Some(schedule) =
                                     core_.storage.purge_schedules.get(idx).cloned()
@@ -4,9 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use jmap_proto::types::{collection::Collection, property::Property};
+use jmap_proto::{
+    error::method::MethodError,
+    types::{collection::Collection, property::Property},
+};
 use store::{
     fts::index::FtsDocument,
+    roaring::RoaringBitmap,
     write::{
         key::DeserializeBigEndian, now, BatchBuilder, Bincode, FtsQueueClass, MaybeDynamicId,
         ValueClass,
@@ -212,6 +216,71 @@ impl JMAP {
         }
     }
 
+    // Queues stored emails for indexing again, messages indexed by older
+    // versions are missing any fields that were added since.
+    pub async fn fts_reindex(&self, account_id: Option<u32>) -> Result<(), MethodError> {
+        let account_ids = if let Some(account_id) = account_id {
+            RoaringBitmap::from_iter([account_id])
+        } else {
+            self.get_document_ids(u32::MAX, Collection::Principal)
+                .await?
+                .unwrap_or_default()
+        };
+
+        for account_id in account_ids {
+            let document_ids = self
+                .get_document_ids(account_id, Collection::Email)
+                .await?
+                .unwrap_or_default();
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Email);
+
+            for document_id in document_ids {
+                let metadata = if let Some(metadata) = self
+                    .get_property::<Bincode<MessageMetadata>>(
+                        account_id,
+                        Collection::Email,
+                        document_id,
+                        Property::BodyStructure,
+                    )
+                    .await?
+                {
+                    metadata.inner
+                } else {
+                    continue;
+                };
+
+                batch.update_document(document_id).set(
+                    ValueClass::FtsQueue(FtsQueueClass {
+                        seq: self.generate_snowflake_id()?,
+                        hash: metadata.blob_hash,
+                    }),
+                    0u64.serialize(),
+                );
+                if batch.ops.len() >= 1000 {
+                    self.write_batch(batch).await?;
+                    batch = BatchBuilder::new();
+                    batch
+                        .with_account_id(account_id)
+                        .with_collection(Collection::Email);
+                }
+            }
+
+            if !batch.is_empty() {
+                self.write_batch(batch).await?;
+            }
+        }
+
+        // Start indexing
+        if let Err(err) = self.inner.housekeeper_tx.send(Event::IndexStart).await {
+            tracing::warn!("Failed to send index start event to housekeeper: {}", err);
+        }
+
+        Ok(())
+    }
+
     async fn try_lock_index(&self, event: &IndexEmail) -> bool {
         let mut batch = BatchBuilder::new();
         batch
@@ -11,6 +11,7 @@ use mail_parser::MessageParser;
 use store::ahash::AHashMap;
 
 use crate::{
+    calendar_event::itip::has_calendar_part,
     email::ingest::{IngestEmail, IngestSource},
     mailbox::INBOX_ID,
     IngestError, JMAP,
@@ -135,6 +136,22 @@ impl JMAP {
                                 .with_change(DataType::Thread, ingested_message.change_id),
                         )
                         .await;
+
+                        // Process iTIP scheduling messages, only trusting senders that
+                        // authenticated or passed DMARC as the envelope is otherwise forgeable
+                        if message.sender_verified && has_calendar_part(&raw_message) {
+                            if let Err(err) = self
+                                .calendar_itip_ingest(*uid, &raw_message, &message.sender_address)
+                                .await
+                            {
+                                tracing::warn!(
+                                    context = "ingest",
+                                    account_id = *uid,
+                                    error = ?err,
+                                    "Failed to process iTIP message."
+                                );
+                            }
+                        }
                     }
                 }
                 Err(err) => match err {
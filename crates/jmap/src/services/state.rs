@@ -410,6 +410,22 @@ impl JMAP {
         }
     }
 
+    pub async fn update_shared_accounts(&self, account_id: u32) -> bool {
+        match self
+            .inner
+            .state_tx
+            .clone()
+            .send(Event::UpdateSharedAccounts { account_id })
+            .await
+        {
+            Ok(_) => true,
+            Err(err) => {
+                tracing::error!("Channel failure while updating shared accounts: {}", err);
+                false
+            }
+        }
+    }
+
     pub async fn update_push_subscriptions(&self, account_id: u32) -> bool {
         let push_subs = match self.fetch_push_subscriptions(account_id).await {
             Ok(push_subs) => push_subs,
@@ -0,0 +1,148 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    error::{method::MethodError, set::SetError},
+    method::set::{RequestArguments, SetRequest, SetResponse},
+    object::{
+        index::{IndexAs, IndexProperty, ObjectIndexBuilder},
+        Object,
+    },
+    types::{
+        collection::Collection, property::Property, state::StateChange, type_state::DataType,
+        value::Value,
+    },
+};
+use store::write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder};
+
+use crate::JMAP;
+
+pub static SCHEMA: &[IndexProperty] = &[
+    IndexProperty::new(Property::Created).index_as(IndexAs::LongInteger),
+    IndexProperty::new(Property::ObjectType).index_as(IndexAs::Text {
+        tokenize: false,
+        index: true,
+    }),
+    IndexProperty::new(Property::ObjectAccountId).index_as(IndexAs::Integer),
+];
+
+impl JMAP {
+    pub async fn share_notification_set(
+        &self,
+        mut request: SetRequest<RequestArguments>,
+    ) -> Result<SetResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let notification_ids = self
+            .get_document_ids(account_id, Collection::ShareNotification)
+            .await?
+            .unwrap_or_default();
+        let mut response = SetResponse::from_request(&request, self.core.jmap.set_max_objects)?;
+        let will_destroy = request.unwrap_destroy();
+
+        // Share notifications are created by the server and are immutable
+        for (id, _) in request.unwrap_create() {
+            response.not_created.append(
+                id,
+                SetError::forbidden()
+                    .with_description("Share notifications cannot be created by clients."),
+            );
+        }
+        for (id, _) in request.unwrap_update() {
+            response.not_updated.append(
+                id,
+                SetError::forbidden().with_description("Share notifications cannot be modified."),
+            );
+        }
+
+        // Process deletions
+        let mut changes = ChangeLogBuilder::new();
+        for id in will_destroy {
+            let document_id = id.document_id();
+            if !notification_ids.contains(document_id) {
+                response.not_destroyed.append(id, SetError::not_found());
+                continue;
+            }
+
+            if let Some(notification) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account_id,
+                    Collection::ShareNotification,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                let mut batch = BatchBuilder::new();
+                batch
+                    .with_account_id(account_id)
+                    .with_collection(Collection::ShareNotification)
+                    .delete_document(document_id)
+                    .custom(ObjectIndexBuilder::new(SCHEMA).with_current(notification));
+                match self.core.storage.data.write(batch.build()).await {
+                    Ok(_) => {
+                        changes.log_delete(Collection::ShareNotification, document_id);
+                        response.destroyed.push(id);
+                    }
+                    Err(store::Error::AssertValueFailed) => {
+                        response.not_destroyed.append(
+                            id,
+                            SetError::forbidden().with_description(
+                                "Another process modified this notification, please try again.",
+                            ),
+                        );
+                    }
+                    Err(err) => {
+                        tracing::error!(
+                            event = "error",
+                            context = "share_notification_set",
+                            account_id = account_id,
+                            document_id = document_id,
+                            error = ?err,
+                            "Failed to delete share notification.");
+                        return Err(MethodError::ServerPartialFail);
+                    }
+                }
+            } else {
+                response.not_destroyed.append(id, SetError::not_found());
+            }
+        }
+
+        // Write changes
+        if !changes.is_empty() {
+            let change_id = self.commit_changes(account_id, changes).await?;
+            response.state_change = StateChange::new(account_id)
+                .with_change(DataType::ShareNotification, change_id)
+                .into();
+            response.new_state = Some(change_id.into());
+        }
+
+        Ok(response)
+    }
+
+    pub async fn share_notification_create(
+        &self,
+        account_id: u32,
+        notification: Object<Value>,
+    ) -> Result<(), MethodError> {
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::ShareNotification)
+            .create_document()
+            .custom(ObjectIndexBuilder::new(SCHEMA).with_changes(notification));
+        let document_id = self.write_batch_expect_id(batch).await?;
+
+        let mut changes = ChangeLogBuilder::new();
+        changes.log_insert(Collection::ShareNotification, document_id);
+        let change_id = self.commit_changes(account_id, changes).await?;
+        self.broadcast_state_change(
+            StateChange::new(account_id).with_change(DataType::ShareNotification, change_id),
+        )
+        .await;
+
+        Ok(())
+    }
+}
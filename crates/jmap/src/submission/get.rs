@@ -138,7 +138,13 @@ impl JMAP {
                         if queued_message.is_some() {
                             Value::Text("pending".to_string())
                         } else {
-                            push.remove(property)
+                            // Scheduled submissions become final once released from the queue
+                            match push.remove(property) {
+                                Value::Text(status) if status == "pending" => {
+                                    Value::Text("final".to_string())
+                                }
+                                value => value,
+                            }
                         }
                     }
                     Property::EmailId
@@ -301,6 +301,7 @@ impl JMAP {
         let mut identity_id = u32::MAX;
         let mut mail_from = None;
         let mut rcpt_to: Vec<RcptTo<String>> = Vec::new();
+        let mut send_at = None;
 
         for (property, value) in object.properties {
             let value = match response.eval_object_references(value) {
@@ -399,6 +400,11 @@ impl JMAP {
                     continue;
                 }
                 (Property::UndoStatus, MaybePatchValue::Value(Value::Text(_))) => continue,
+                (Property::SendAt, MaybePatchValue::Value(Value::Date(value))) => {
+                    send_at = (value.timestamp() as u64).into();
+                    continue;
+                }
+                (Property::SendAt, MaybePatchValue::Value(Value::Null)) => continue,
                 _ => {
                     return Ok(Err(SetError::invalid_properties()
                         .with_property(property)
@@ -438,7 +444,7 @@ impl JMAP {
         };
 
         // Make sure the envelope address matches the identity email address
-        let mail_from = if let Some(mail_from) = mail_from {
+        let mut mail_from = if let Some(mail_from) = mail_from {
             if !mail_from.address.eq_ignore_ascii_case(&identity_mail_from) {
                 return Ok(Err(SetError::new(SetErrorType::ForbiddenFrom)
                     .with_description(
@@ -524,17 +530,27 @@ impl JMAP {
             }
         }
 
+        // Hold the message in the queue until sendAt
+        if let Some(send_at) = send_at.filter(|send_at| *send_at > now()) {
+            if mail_from.hold_until > 0 || mail_from.hold_for > 0 {
+                return Ok(Err(SetError::invalid_properties()
+                    .with_properties([Property::SendAt, Property::Envelope])
+                    .with_description(
+                        "sendAt cannot be combined with FUTURERELEASE parameters.",
+                    )));
+            }
+            mail_from.hold_until = send_at;
+        }
+
         // Update sendAt
-        submission.append(
-            Property::SendAt,
-            UTCDate::from_timestamp(if mail_from.hold_until > 0 {
-                mail_from.hold_until
-            } else if mail_from.hold_for > 0 {
-                mail_from.hold_for + now()
-            } else {
-                now()
-            } as i64),
-        );
+        let send_at = if mail_from.hold_until > 0 {
+            mail_from.hold_until
+        } else if mail_from.hold_for > 0 {
+            mail_from.hold_for + now()
+        } else {
+            now()
+        };
+        submission.append(Property::SendAt, UTCDate::from_timestamp(send_at as i64));
 
         // Obtain raw message
         let message =
@@ -554,52 +570,29 @@ impl JMAP {
                     .with_description("Blob for email not found.")));
             };
 
-        // Begin local SMTP session
-        let mut session =
-            Session::<NullIo>::local(self.smtp.clone(), instance.clone(), SessionData::default());
-
-        // MAIL FROM
-        let _ = session.handle_mail_from(mail_from).await;
-        if let Some(error) = session.has_failed() {
-            return Ok(Err(SetError::new(SetErrorType::ForbiddenMailFrom)
-                .with_description(format!(
-                    "Server rejected MAIL-FROM: {}",
-                    error.trim()
-                ))));
-        }
-
-        // RCPT TO
-        let mut responses = Vec::new();
-        let mut has_success = false;
-        for rcpt in rcpt_to {
-            let addr = rcpt.address.clone();
-            let _ = session.handle_rcpt_to(rcpt).await;
-            let response = session.has_failed();
-            if response.is_none() {
-                has_success = true;
-            }
-            responses.push((addr, response));
-        }
-
-        // DATA
-        if has_success {
-            session.data.message = message;
-            let response = session.queue_message().await;
-            if let State::Accepted(queue_id) = session.state {
-                submission.append(Property::MessageId, queue_id);
-            } else {
-                return Ok(Err(SetError::new(SetErrorType::ForbiddenToSend)
-                    .with_description(format!(
-                        "Server rejected DATA: {}",
-                        std::str::from_utf8(&response).unwrap().trim()
-                    ))));
-            }
+        // Submit message
+        let (queue_id, responses) = match self
+            .submit_message(instance, mail_from, rcpt_to, message)
+            .await
+        {
+            Ok(result) => result,
+            Err(err) => return Ok(Err(err)),
+        };
+        let has_success = queue_id.is_some();
+        if let Some(queue_id) = queue_id {
+            submission.append(Property::MessageId, queue_id);
         }
 
         // Set responses
         submission.append(
             Property::UndoStatus,
-            if has_success { "final" } else { "failed" },
+            if !has_success {
+                "failed"
+            } else if send_at > now() {
+                "pending"
+            } else {
+                "final"
+            },
         );
         submission.append(
             Property::DeliveryStatus,
@@ -629,6 +622,63 @@ impl JMAP {
 
         Ok(Ok(submission))
     }
+
+    // Submits a message through a local SMTP session, returning the queue id
+    // (if any recipient was accepted) and the response for each recipient.
+    pub(crate) async fn submit_message(
+        &self,
+        instance: &Arc<ServerInstance>,
+        mail_from: MailFrom<String>,
+        rcpt_to: Vec<RcptTo<String>>,
+        message: Vec<u8>,
+    ) -> Result<(Option<u64>, Vec<(String, Option<String>)>), SetError> {
+        // Begin local SMTP session
+        let mut session =
+            Session::<NullIo>::local(self.smtp.clone(), instance.clone(), SessionData::default());
+
+        // Callers have already verified that the sender belongs to the account
+        if !mail_from.address.is_empty() {
+            session.data.authenticated_emails = vec![mail_from.address.to_lowercase()];
+        }
+
+        // MAIL FROM
+        let _ = session.handle_mail_from(mail_from).await;
+        if let Some(error) = session.has_failed() {
+            return Err(SetError::new(SetErrorType::ForbiddenMailFrom)
+                .with_description(format!("Server rejected MAIL-FROM: {}", error.trim())));
+        }
+
+        // RCPT TO
+        let mut responses = Vec::new();
+        let mut has_success = false;
+        for rcpt in rcpt_to {
+            let addr = rcpt.address.clone();
+            let _ = session.handle_rcpt_to(rcpt).await;
+            let response = session.has_failed();
+            if response.is_none() {
+                has_success = true;
+            }
+            responses.push((addr, response));
+        }
+
+        // DATA
+        if has_success {
+            session.data.message = message;
+            let response = session.queue_message().await;
+            if let State::Accepted(queue_id) = session.state {
+                Ok((Some(queue_id), responses))
+            } else {
+                Err(
+                    SetError::new(SetErrorType::ForbiddenToSend).with_description(format!(
+                        "Server rejected DATA: {}",
+                        std::str::from_utf8(&response).unwrap().trim()
+                    )),
+                )
+            }
+        } else {
+            Ok((None, responses))
+        }
+    }
 }
 
 fn parse_envelope_address(envelope: &Value) -> Result<(String, Option<String>), SetError> {
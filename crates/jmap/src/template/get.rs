@@ -0,0 +1,99 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::get::{GetRequest, GetResponse, RequestArguments},
+    object::Object,
+    types::{collection::Collection, id::Id, property::Property, value::Value},
+};
+use store::roaring::RoaringBitmap;
+
+use crate::{auth::AccessToken, JMAP};
+
+use super::DOMAIN_TEMPLATES_ID;
+
+impl JMAP {
+    pub async fn template_get(
+        &self,
+        mut request: GetRequest<RequestArguments>,
+        access_token: &AccessToken,
+    ) -> Result<GetResponse, MethodError> {
+        let ids = request.unwrap_ids(self.core.jmap.get_max_objects)?;
+        let properties = request.unwrap_properties(&[
+            Property::Id,
+            Property::Name,
+            Property::Scope,
+            Property::Domain,
+            Property::Subject,
+            Property::TextBody,
+            Property::HtmlBody,
+        ]);
+        let account_id = request.account_id.document_id();
+        let domains = self.template_domains(account_id, access_token).await?;
+        let ids = if let Some(ids) = ids {
+            ids
+        } else {
+            let template_ids = if account_id != DOMAIN_TEMPLATES_ID {
+                self.get_document_ids(account_id, Collection::Template)
+                    .await?
+                    .unwrap_or_default()
+            } else {
+                RoaringBitmap::new()
+            };
+            let domain_template_ids = self.template_domain_ids(domains.as_ref()).await?;
+
+            template_ids
+                .iter()
+                .map(Id::from)
+                .chain(
+                    domain_template_ids
+                        .iter()
+                        .map(|document_id| Id::from_parts(DOMAIN_TEMPLATES_ID, document_id)),
+                )
+                .take(self.core.jmap.get_max_objects)
+                .collect::<Vec<_>>()
+        };
+        let mut response = GetResponse {
+            account_id: request.account_id.into(),
+            state: self.template_state(account_id).await?.into(),
+            list: Vec::with_capacity(ids.len()),
+            not_found: vec![],
+        };
+
+        for id in ids {
+            // Obtain the template object
+            let mut template = if let Some(template) = self
+                .template_get_object(account_id, id, domains.as_ref())
+                .await?
+            {
+                template
+            } else {
+                response.not_found.push(id.into());
+                continue;
+            };
+
+            let mut result = Object::with_capacity(properties.len());
+            for property in &properties {
+                let value = match property {
+                    Property::Id => Value::Id(id),
+                    Property::Name
+                    | Property::Scope
+                    | Property::Domain
+                    | Property::Subject
+                    | Property::TextBody
+                    | Property::HtmlBody => template.remove(property),
+                    _ => Value::Null,
+                };
+
+                result.append(property.clone(), value);
+            }
+            response.list.push(result);
+        }
+
+        Ok(response)
+    }
+}
@@ -0,0 +1,296 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use directory::QueryBy;
+use jmap_proto::{
+    error::method::MethodError,
+    object::Object,
+    types::{collection::Collection, id::Id, property::Property, state::State, value::Value},
+};
+use store::{ahash::AHashSet, query::Filter, roaring::RoaringBitmap};
+
+use crate::{api::management::list::html_escape, auth::AccessToken, JMAP};
+
+pub mod get;
+pub mod query;
+pub mod set;
+
+// Domain templates are stored in the global account and their ids
+// are prefixed with it to tell them apart from account templates.
+// The fallback administrator shares this account id, so it cannot
+// have account templates of its own.
+pub const DOMAIN_TEMPLATES_ID: u32 = u32::MAX;
+
+pub struct DraftTemplate {
+    pub subject: Option<String>,
+    pub text_body: Option<String>,
+    pub html_body: Option<String>,
+}
+
+pub trait TemplateId {
+    fn is_domain_template(&self) -> bool;
+}
+
+impl TemplateId for Id {
+    fn is_domain_template(&self) -> bool {
+        self.prefix_id() == DOMAIN_TEMPLATES_ID
+    }
+}
+
+impl JMAP {
+    // Returns the domains whose templates are visible from an account,
+    // or None if all domain templates are visible.
+    pub async fn template_domains(
+        &self,
+        account_id: u32,
+        access_token: &AccessToken,
+    ) -> Result<Option<AHashSet<String>>, MethodError> {
+        if access_token.is_super_user() {
+            return Ok(None);
+        }
+
+        // Use the domains of the account's identities, or the principal's
+        // addresses if no identities were created yet
+        let mut domains = AHashSet::new();
+        let identity_ids = self
+            .get_document_ids(account_id, Collection::Identity)
+            .await?
+            .unwrap_or_default();
+        if !identity_ids.is_empty() {
+            for (_, identity) in self
+                .get_properties::<Object<Value>, _, _>(
+                    account_id,
+                    Collection::Identity,
+                    &identity_ids,
+                    Property::Value,
+                )
+                .await?
+            {
+                if let Some((_, domain)) = identity
+                    .get(&Property::Email)
+                    .as_string()
+                    .and_then(|email| email.rsplit_once('@'))
+                {
+                    domains.insert(domain.to_lowercase());
+                }
+            }
+        } else if let Some(principal) = self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    event = "error",
+                    context = "template_domains",
+                    error = ?err,
+                    "Failed to query directory.");
+                MethodError::ServerPartialFail
+            })?
+        {
+            for email in &principal.emails {
+                if let Some((_, domain)) = email.rsplit_once('@') {
+                    domains.insert(domain.to_lowercase());
+                }
+            }
+        }
+
+        Ok(Some(domains))
+    }
+
+    // Domain templates keep their own change log, the Template state of an
+    // account is the most recent change of either log.
+    pub async fn template_state(&self, account_id: u32) -> Result<State, MethodError> {
+        match (
+            self.get_state(account_id, Collection::Template).await?,
+            self.get_state(DOMAIN_TEMPLATES_ID, Collection::Template)
+                .await?,
+        ) {
+            (State::Exact(account_change_id), State::Exact(domain_change_id)) => {
+                Ok(State::Exact(account_change_id.max(domain_change_id)))
+            }
+            (State::Initial, state) | (state, _) => Ok(state),
+        }
+    }
+
+    pub async fn template_domain_ids(
+        &self,
+        domains: Option<&AHashSet<String>>,
+    ) -> Result<RoaringBitmap, MethodError> {
+        match domains {
+            Some(domains) if domains.is_empty() => Ok(RoaringBitmap::new()),
+            Some(domains) => {
+                let mut filters = Vec::with_capacity(domains.len() + 2);
+                filters.push(Filter::Or);
+                for domain in domains {
+                    filters.push(Filter::eq(Property::Domain, domain.as_str()));
+                }
+                filters.push(Filter::End);
+
+                self.filter(DOMAIN_TEMPLATES_ID, Collection::Template, filters)
+                    .await
+                    .map(|result_set| result_set.results)
+            }
+            None => self
+                .get_document_ids(DOMAIN_TEMPLATES_ID, Collection::Template)
+                .await
+                .map(|ids| ids.unwrap_or_default()),
+        }
+    }
+
+    pub async fn template_get_object(
+        &self,
+        account_id: u32,
+        id: Id,
+        domains: Option<&AHashSet<String>>,
+    ) -> Result<Option<Object<Value>>, MethodError> {
+        let template = if id.is_domain_template() {
+            self.get_property::<Object<Value>>(
+                DOMAIN_TEMPLATES_ID,
+                Collection::Template,
+                id.document_id(),
+                Property::Value,
+            )
+            .await?
+            .filter(|template| {
+                domains.map_or(true, |domains| {
+                    template
+                        .get(&Property::Domain)
+                        .as_string()
+                        .map_or(false, |domain| domains.contains(domain))
+                })
+            })
+        } else if id.prefix_id() == 0 && account_id != DOMAIN_TEMPLATES_ID {
+            self.get_property::<Object<Value>>(
+                account_id,
+                Collection::Template,
+                id.document_id(),
+                Property::Value,
+            )
+            .await?
+        } else {
+            None
+        };
+
+        Ok(template)
+    }
+
+    pub async fn template_for_draft(
+        &self,
+        account_id: u32,
+        template_id: Id,
+        from: Option<&str>,
+        access_token: &AccessToken,
+    ) -> Result<Option<DraftTemplate>, MethodError> {
+        let domains = self.template_domains(account_id, access_token).await?;
+        let mut template = if let Some(template) = self
+            .template_get_object(account_id, template_id, domains.as_ref())
+            .await?
+        {
+            template
+        } else {
+            return Ok(None);
+        };
+
+        // Use the identity matching the sender address, or the first one
+        let identity_ids = self.identity_get_or_create(account_id).await?;
+        let mut identity = None;
+        for (_, object) in self
+            .get_properties::<Object<Value>, _, _>(
+                account_id,
+                Collection::Identity,
+                &identity_ids,
+                Property::Value,
+            )
+            .await?
+        {
+            let is_sender = from.map_or(false, |from| {
+                object
+                    .get(&Property::Email)
+                    .as_string()
+                    .map_or(false, |email| email.eq_ignore_ascii_case(from))
+            });
+            if is_sender {
+                identity = Some(object);
+                break;
+            } else if identity.is_none() {
+                identity = Some(object);
+            }
+        }
+        let identity = identity.unwrap_or_default();
+
+        Ok(Some(DraftTemplate {
+            subject: template
+                .remove(&Property::Subject)
+                .try_unwrap_string()
+                .map(|text| substitute_variables(&text, &identity, false)),
+            text_body: template
+                .remove(&Property::TextBody)
+                .try_unwrap_string()
+                .map(|text| substitute_variables(&text, &identity, false)),
+            html_body: template
+                .remove(&Property::HtmlBody)
+                .try_unwrap_string()
+                .map(|text| substitute_variables(&text, &identity, true)),
+        }))
+    }
+}
+
+// Replaces {{name}}, {{email}} and {{signature}} with the sender identity's
+// values, unknown variables are left untouched.
+pub fn substitute_variables(text: &str, identity: &Object<Value>, is_html: bool) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut text = text;
+
+    while let Some((before, after)) = text.split_once("{{") {
+        let (variable, after) = if let Some(variable) = after.split_once("}}") {
+            variable
+        } else {
+            break;
+        };
+        result.push_str(before);
+
+        match variable.trim() {
+            property @ ("name" | "email") => {
+                let value = identity
+                    .get(&Property::parse(property))
+                    .as_string()
+                    .unwrap_or_default();
+                if is_html {
+                    result.push_str(&html_escape(value));
+                } else {
+                    result.push_str(value);
+                }
+            }
+            "signature" => match (
+                identity.get(&Property::HtmlSignature).as_string(),
+                identity.get(&Property::TextSignature).as_string(),
+            ) {
+                (Some(signature), _) if is_html && !signature.is_empty() => {
+                    result.push_str(signature);
+                }
+                (_, Some(signature)) if is_html => {
+                    result.push_str(&html_escape(signature));
+                }
+                (_, Some(signature)) => {
+                    result.push_str(signature);
+                }
+                _ => (),
+            },
+            _ => {
+                result.push_str("{{");
+                result.push_str(variable);
+                result.push_str("}}");
+            }
+        }
+
+        text = after;
+    }
+    result.push_str(text);
+
+    result
+}
@@ -0,0 +1,156 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::query::{
+        Comparator, Filter, QueryRequest, QueryResponse, RequestArguments, SortProperty,
+    },
+    object::Object,
+    types::{collection::Collection, property::Property, value::Value},
+};
+use store::query::{self, sort::Pagination};
+
+use crate::{auth::AccessToken, UpdateResults, JMAP};
+
+use super::DOMAIN_TEMPLATES_ID;
+
+impl JMAP {
+    pub async fn template_query(
+        &self,
+        mut request: QueryRequest<RequestArguments>,
+        access_token: &AccessToken,
+    ) -> Result<QueryResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let conditions = std::mem::take(&mut request.filter);
+
+        // Parse sort criteria
+        let mut is_ascending = true;
+        for comparator in request
+            .sort
+            .take()
+            .and_then(|s| if !s.is_empty() { s.into() } else { None })
+            .unwrap_or_else(|| vec![Comparator::ascending(SortProperty::Name)])
+        {
+            match comparator.property {
+                SortProperty::Name => {
+                    is_ascending = comparator.is_ascending;
+                }
+                other => return Err(MethodError::UnsupportedSort(other.to_string())),
+            }
+        }
+
+        // Query account templates and the domain templates visible from this account
+        let domains = self.template_domains(account_id, access_token).await?;
+        let domain_template_ids = self.template_domain_ids(domains.as_ref()).await?;
+        let mut results = Vec::new();
+        for (store_account_id, prefix_id) in
+            [(account_id, 0), (DOMAIN_TEMPLATES_ID, DOMAIN_TEMPLATES_ID)]
+        {
+            if store_account_id == DOMAIN_TEMPLATES_ID && prefix_id == 0 {
+                continue;
+            }
+            let mut filters = template_filters(&conditions)?;
+            if prefix_id == DOMAIN_TEMPLATES_ID {
+                filters.push(query::Filter::is_in_set(domain_template_ids.clone()));
+            }
+            let result_set = self
+                .filter(store_account_id, Collection::Template, filters)
+                .await?;
+            if !result_set.results.is_empty() {
+                for (document_id, template) in self
+                    .get_properties::<Object<Value>, _, _>(
+                        store_account_id,
+                        Collection::Template,
+                        &result_set.results,
+                        Property::Value,
+                    )
+                    .await?
+                {
+                    let name = template
+                        .get(&Property::Name)
+                        .as_string()
+                        .unwrap_or_default()
+                        .to_lowercase();
+                    results.push((name, prefix_id, document_id));
+                }
+            }
+        }
+
+        // Account templates are listed before domain templates with the same name
+        results.sort_unstable();
+        if !is_ascending {
+            results.reverse();
+        }
+
+        let total = results.len();
+        let (limit_total, limit) = if let Some(limit) = request.limit {
+            if limit > 0 {
+                let limit = std::cmp::min(limit, self.core.jmap.query_max_results);
+                (std::cmp::min(limit, total), limit)
+            } else {
+                (0, 0)
+            }
+        } else {
+            (
+                std::cmp::min(self.core.jmap.query_max_results, total),
+                self.core.jmap.query_max_results,
+            )
+        };
+        let mut response = QueryResponse {
+            account_id: request.account_id,
+            query_state: self.template_state(account_id).await?,
+            can_calculate_changes: false,
+            position: 0,
+            ids: vec![],
+            total: if request.calculate_total.unwrap_or(false) {
+                Some(total)
+            } else {
+                None
+            },
+            limit: if total > limit { Some(limit) } else { None },
+        };
+
+        if limit_total > 0 {
+            let mut paginate = Pagination::new(
+                limit_total,
+                request.position.unwrap_or(0),
+                request.anchor.map(|a| a.document_id()),
+                request.anchor_offset.unwrap_or(0),
+            );
+            for (_, prefix_id, document_id) in results {
+                if !paginate.add(prefix_id, document_id) {
+                    break;
+                }
+            }
+            response.update_results(paginate.build())?;
+        }
+
+        Ok(response)
+    }
+}
+
+fn template_filters(conditions: &[Filter]) -> Result<Vec<query::Filter>, MethodError> {
+    let mut filters = Vec::with_capacity(conditions.len() + 1);
+
+    for cond in conditions {
+        match cond {
+            Filter::Name(name) => filters.push(query::Filter::has_text(Property::Name, name)),
+            Filter::Scope(scope) => {
+                filters.push(query::Filter::eq(Property::Scope, scope.to_lowercase()))
+            }
+            Filter::DomainName(domain) => {
+                filters.push(query::Filter::eq(Property::Domain, domain.to_lowercase()))
+            }
+            Filter::And | Filter::Or | Filter::Not | Filter::Close => {
+                filters.push(cond.clone().into());
+            }
+            other => return Err(MethodError::UnsupportedFilter(other.to_string())),
+        }
+    }
+
+    Ok(filters)
+}
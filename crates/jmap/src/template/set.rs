@@ -0,0 +1,437 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    error::{
+        method::MethodError,
+        set::{SetError, SetErrorType},
+    },
+    method::set::{RequestArguments, SetRequest, SetResponse},
+    object::{
+        index::{IndexAs, IndexProperty, ObjectIndexBuilder},
+        Object,
+    },
+    response::references::EvalObjectReferences,
+    types::{
+        collection::Collection,
+        id::Id,
+        property::Property,
+        state::StateChange,
+        type_state::DataType,
+        value::{MaybePatchValue, SetValue, Value},
+    },
+};
+use store::write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder};
+
+use crate::{auth::AccessToken, JMAP};
+
+use super::{TemplateId, DOMAIN_TEMPLATES_ID};
+
+pub static SCHEMA: &[IndexProperty] = &[
+    IndexProperty::new(Property::Name)
+        .index_as(IndexAs::Text {
+            tokenize: true,
+            index: true,
+        })
+        .required(),
+    IndexProperty::new(Property::Scope).index_as(IndexAs::Text {
+        tokenize: false,
+        index: true,
+    }),
+    IndexProperty::new(Property::Domain).index_as(IndexAs::Text {
+        tokenize: false,
+        index: true,
+    }),
+];
+
+impl JMAP {
+    pub async fn template_set(
+        &self,
+        mut request: SetRequest<RequestArguments>,
+        access_token: &AccessToken,
+    ) -> Result<SetResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let mut template_ids = self
+            .get_document_ids(account_id, Collection::Template)
+            .await?
+            .unwrap_or_default();
+        let old_state = self.template_state(account_id).await?;
+        if request
+            .if_in_state
+            .as_ref()
+            .map_or(false, |if_in_state| if_in_state != &old_state)
+        {
+            return Err(MethodError::StateMismatch);
+        }
+        let mut response = SetResponse::from_request(&request, self.core.jmap.set_max_objects)?
+            .with_state(old_state);
+        let will_destroy = request.unwrap_destroy();
+
+        // Process creates
+        let mut changes = ChangeLogBuilder::new();
+        let mut domain_changes = ChangeLogBuilder::new();
+        'create: for (id, object) in request.unwrap_create() {
+            let mut template = match self.template_set_item(object, true, &response) {
+                Ok(template) => template,
+                Err(err) => {
+                    response.not_created.append(id, err);
+                    continue 'create;
+                }
+            };
+
+            // Domain templates can only be managed by administrators
+            let is_domain = if template.get(&Property::Scope).as_string() == Some("domain") {
+                if !access_token.is_super_user() {
+                    response.not_created.append(
+                        id,
+                        SetError::forbidden()
+                            .with_description("Only administrators can create domain templates."),
+                    );
+                    continue 'create;
+                }
+
+                let is_local_domain = match template.get(&Property::Domain).as_string() {
+                    Some(domain) => self
+                        .core
+                        .storage
+                        .directory
+                        .is_local_domain(domain)
+                        .await
+                        .unwrap_or_default(),
+                    None => false,
+                };
+                if !is_local_domain {
+                    response.not_created.append(
+                        id,
+                        SetError::invalid_properties()
+                            .with_property(Property::Domain)
+                            .with_description("Domain does not exist."),
+                    );
+                    continue 'create;
+                }
+
+                true
+            } else if !matches!(template.get(&Property::Domain), Value::Null) {
+                response.not_created.append(
+                    id,
+                    SetError::invalid_properties()
+                        .with_property(Property::Domain)
+                        .with_description("Only domain templates can have a domain."),
+                );
+                continue 'create;
+            } else if account_id == DOMAIN_TEMPLATES_ID {
+                response.not_created.append(
+                    id,
+                    SetError::forbidden()
+                        .with_description("This account cannot have account templates."),
+                );
+                continue 'create;
+            } else if template_ids.len() as usize >= self.core.jmap.template_max_templates {
+                response.not_created.append(
+                    id,
+                    SetError::new(SetErrorType::OverQuota).with_description(concat!(
+                        "There are too many templates, ",
+                        "please delete some before adding a new one."
+                    )),
+                );
+                continue 'create;
+            } else {
+                template.set(Property::Scope, "account");
+                false
+            };
+
+            let builder = match ObjectIndexBuilder::new(SCHEMA)
+                .with_changes(template)
+                .validate()
+            {
+                Ok(builder) => builder,
+                Err(err) => {
+                    response.not_created.append(id, err);
+                    continue 'create;
+                }
+            };
+
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(if is_domain {
+                    DOMAIN_TEMPLATES_ID
+                } else {
+                    account_id
+                })
+                .with_collection(Collection::Template)
+                .create_document()
+                .custom(builder);
+            let document_id = self.write_batch_expect_id(batch).await?;
+            if is_domain {
+                domain_changes.log_insert(Collection::Template, document_id);
+                response.created.insert(
+                    id,
+                    Object::with_capacity(1).with_property(
+                        Property::Id,
+                        Value::Id(Id::from_parts(DOMAIN_TEMPLATES_ID, document_id)),
+                    ),
+                );
+            } else {
+                template_ids.insert(document_id);
+                changes.log_insert(Collection::Template, document_id);
+                response.created(id, document_id);
+            }
+        }
+
+        // Process updates
+        'update: for (id, object) in request.unwrap_update() {
+            // Make sure id won't be destroyed
+            if will_destroy.contains(&id) {
+                response.not_updated.append(id, SetError::will_destroy());
+                continue 'update;
+            }
+
+            // Obtain template
+            let store_account_id = match self.template_store_id(account_id, id, access_token) {
+                Ok(store_account_id) => store_account_id,
+                Err(err) => {
+                    response.not_updated.append(id, err);
+                    continue 'update;
+                }
+            };
+            let document_id = id.document_id();
+            let template = if let Some(template) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    store_account_id,
+                    Collection::Template,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                template
+            } else {
+                response.not_updated.append(id, SetError::not_found());
+                continue 'update;
+            };
+
+            let builder =
+                match self
+                    .template_set_item(object, false, &response)
+                    .and_then(|changes| {
+                        ObjectIndexBuilder::new(SCHEMA)
+                            .with_current(template)
+                            .with_changes(changes)
+                            .validate()
+                    }) {
+                    Ok(builder) => builder,
+                    Err(err) => {
+                        response.not_updated.append(id, err);
+                        continue 'update;
+                    }
+                };
+
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(store_account_id)
+                .with_collection(Collection::Template)
+                .update_document(document_id)
+                .custom(builder);
+            if !batch.is_empty() {
+                match self.core.storage.data.write(batch.build()).await {
+                    Ok(_) => {
+                        if store_account_id == DOMAIN_TEMPLATES_ID {
+                            domain_changes.log_update(Collection::Template, document_id);
+                        } else {
+                            changes.log_update(Collection::Template, document_id);
+                        }
+                    }
+                    Err(store::Error::AssertValueFailed) => {
+                        response.not_updated.append(
+                            id,
+                            SetError::forbidden().with_description(
+                                "Another process modified this template, please try again.",
+                            ),
+                        );
+                        continue 'update;
+                    }
+                    Err(err) => {
+                        tracing::error!(
+                            event = "error",
+                            context = "template_set",
+                            account_id = account_id,
+                            error = ?err,
+                            "Failed to update template(s).");
+                        return Err(MethodError::ServerPartialFail);
+                    }
+                }
+            }
+            response.updated.append(id, None);
+        }
+
+        // Process deletions
+        for id in will_destroy {
+            let store_account_id = match self.template_store_id(account_id, id, access_token) {
+                Ok(store_account_id) => store_account_id,
+                Err(err) => {
+                    response.not_destroyed.append(id, err);
+                    continue;
+                }
+            };
+            let document_id = id.document_id();
+            let template = if let Some(template) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    store_account_id,
+                    Collection::Template,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                template
+            } else {
+                response.not_destroyed.append(id, SetError::not_found());
+                continue;
+            };
+
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(store_account_id)
+                .with_collection(Collection::Template)
+                .delete_document(document_id)
+                .custom(ObjectIndexBuilder::new(SCHEMA).with_current(template));
+            match self.core.storage.data.write(batch.build()).await {
+                Ok(_) => {
+                    if store_account_id == DOMAIN_TEMPLATES_ID {
+                        domain_changes.log_delete(Collection::Template, document_id);
+                    } else {
+                        changes.log_delete(Collection::Template, document_id);
+                    }
+                    response.destroyed.push(id);
+                }
+                Err(store::Error::AssertValueFailed) => {
+                    response.not_destroyed.append(
+                        id,
+                        SetError::forbidden().with_description(
+                            "Another process modified this template, please try again.",
+                        ),
+                    );
+                }
+                Err(err) => {
+                    tracing::error!(
+                        event = "error",
+                        context = "template_set",
+                        account_id = account_id,
+                        document_id = document_id,
+                        error = ?err,
+                        "Failed to delete template.");
+                    return Err(MethodError::ServerPartialFail);
+                }
+            }
+        }
+
+        // Write changes
+        let mut change_id = None;
+        if !changes.is_empty() {
+            change_id = self.commit_changes(account_id, changes).await?.into();
+        }
+        if !domain_changes.is_empty() {
+            change_id = self
+                .commit_changes(DOMAIN_TEMPLATES_ID, domain_changes)
+                .await?
+                .into();
+        }
+        if let Some(change_id) = change_id {
+            response.state_change = StateChange::new(account_id)
+                .with_change(DataType::Template, change_id)
+                .into();
+            response.new_state = Some(change_id.into());
+        }
+
+        Ok(response)
+    }
+
+    fn template_store_id(
+        &self,
+        account_id: u32,
+        id: Id,
+        access_token: &AccessToken,
+    ) -> Result<u32, SetError> {
+        if id.is_domain_template() {
+            if access_token.is_super_user() {
+                Ok(DOMAIN_TEMPLATES_ID)
+            } else {
+                Err(SetError::forbidden()
+                    .with_description("Only administrators can modify domain templates."))
+            }
+        } else if id.prefix_id() == 0 && account_id != DOMAIN_TEMPLATES_ID {
+            Ok(account_id)
+        } else {
+            Err(SetError::not_found())
+        }
+    }
+
+    fn template_set_item(
+        &self,
+        changes_: Object<SetValue>,
+        is_create: bool,
+        response: &SetResponse,
+    ) -> Result<Object<Value>, SetError> {
+        let mut changes = Object::with_capacity(changes_.properties.len());
+        for (property, value) in changes_.properties {
+            let value = match (&property, response.eval_object_references(value)?) {
+                (Property::Name, MaybePatchValue::Value(Value::Text(value))) => {
+                    let value = value.trim();
+                    if !value.is_empty() && value.len() <= self.core.jmap.template_name_max_len {
+                        Value::Text(value.to_string())
+                    } else {
+                        return Err(SetError::invalid_properties()
+                            .with_property(Property::Name)
+                            .with_description(if !value.is_empty() {
+                                "Template name is too long."
+                            } else {
+                                "Template name cannot be empty."
+                            }));
+                    }
+                }
+                (Property::Subject, MaybePatchValue::Value(Value::Text(value)))
+                    if value.len() < 1024 =>
+                {
+                    Value::Text(value)
+                }
+                (
+                    Property::TextBody | Property::HtmlBody,
+                    MaybePatchValue::Value(Value::Text(value)),
+                ) if value.len() <= self.core.jmap.template_max_size => Value::Text(value),
+                (Property::Scope, MaybePatchValue::Value(Value::Text(value)))
+                    if is_create && matches!(value.as_str(), "account" | "domain") =>
+                {
+                    Value::Text(value)
+                }
+                (Property::Domain, MaybePatchValue::Value(Value::Text(value))) if is_create => {
+                    Value::Text(value.trim().to_lowercase())
+                }
+                (
+                    Property::Subject | Property::TextBody | Property::HtmlBody,
+                    MaybePatchValue::Value(Value::Null),
+                ) => {
+                    if is_create {
+                        continue;
+                    }
+                    Value::Null
+                }
+                (Property::Scope | Property::Domain, MaybePatchValue::Value(Value::Null))
+                    if is_create =>
+                {
+                    continue;
+                }
+                (property, _) => {
+                    return Err(SetError::invalid_properties()
+                        .with_property(property.clone())
+                        .with_description("Field could not be set."));
+                }
+            };
+            changes.append(property, value);
+        }
+
+        Ok(changes)
+    }
+}
@@ -0,0 +1,221 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::io::{Cursor, Write};
+
+use jmap_proto::{
+    error::{method::MethodError, set::SetError},
+    method::thread::{ExportFormat, ExportThreadObject, ExportThreadRequest, ExportThreadResponse},
+    types::{acl::Acl, collection::Collection, property::Property},
+};
+use store::{
+    query::{sort::Pagination, Comparator, ResultSet},
+    write::Bincode,
+};
+use utils::map::vec_map::VecMap;
+use zip::write::SimpleFileOptions;
+
+use crate::{auth::AccessToken, email::metadata::MessageMetadata, JMAP};
+
+impl JMAP {
+    pub async fn thread_export(
+        &self,
+        request: ExportThreadRequest,
+        access_token: &AccessToken,
+    ) -> Result<ExportThreadResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let mut response = ExportThreadResponse {
+            account_id: request.account_id,
+            exported: VecMap::new(),
+            not_exported: VecMap::new(),
+        };
+
+        if request.thread_ids.len() > self.core.jmap.get_max_objects {
+            return Err(MethodError::RequestTooLarge);
+        }
+
+        let message_ids = self
+            .owned_or_shared_messages(access_token, account_id, Acl::ReadItems)
+            .await?;
+
+        'outer: for id in request.thread_ids {
+            // Obtain the messages in the thread, oldest first
+            let mut document_ids = self
+                .get_tag(
+                    account_id,
+                    Collection::Email,
+                    Property::ThreadId,
+                    id.document_id(),
+                )
+                .await?
+                .unwrap_or_default();
+            document_ids &= &message_ids;
+            if document_ids.is_empty() {
+                response.not_exported.append(id, SetError::not_found());
+                continue;
+            }
+            let document_ids = self
+                .core
+                .storage
+                .data
+                .sort(
+                    ResultSet::new(account_id, Collection::Email, document_ids.clone()),
+                    vec![Comparator::ascending(Property::ReceivedAt)],
+                    Pagination::new(document_ids.len() as usize, 0, None, 0),
+                )
+                .await
+                .map_err(|err| {
+                    tracing::error!(event = "error",
+                                    context = "store",
+                                    account_id = account_id,
+                                    collection = "email",
+                                    error = ?err,
+                                    "Thread emailIds sort failed");
+                    MethodError::ServerPartialFail
+                })?
+                .ids;
+
+            // Fetch the raw messages
+            let mut messages = Vec::with_capacity(document_ids.len());
+            let mut total_size = 0;
+            for document_id in document_ids {
+                let document_id = document_id as u32;
+                let metadata = if let Some(metadata) = self
+                    .get_property::<Bincode<MessageMetadata>>(
+                        account_id,
+                        Collection::Email,
+                        document_id,
+                        &Property::BodyStructure,
+                    )
+                    .await?
+                {
+                    metadata.inner
+                } else {
+                    continue;
+                };
+                if let Some(raw_message) = self.get_blob(&metadata.blob_hash, 0..usize::MAX).await?
+                {
+                    total_size += raw_message.len();
+                    if total_size > self.core.jmap.upload_max_size {
+                        response.not_exported.append(
+                            id,
+                            SetError::too_large().with_description(format!(
+                                "Export size exceeds maximum of {} bytes.",
+                                self.core.jmap.upload_max_size
+                            )),
+                        );
+                        continue 'outer;
+                    }
+                    messages.push((metadata.received_at, raw_message));
+                } else {
+                    tracing::warn!(event = "not-found",
+                        account_id = account_id,
+                        collection = ?Collection::Email,
+                        document_id = document_id,
+                        blob_id = ?metadata.blob_hash,
+                        "Blob not found");
+                }
+            }
+
+            // Build the bundle
+            let bundle = match request.format {
+                ExportFormat::Mbox => build_mbox(&messages),
+                ExportFormat::Zip => build_zip(&messages).map_err(|err| {
+                    tracing::error!(event = "error",
+                                    context = "thread_export",
+                                    account_id = account_id,
+                                    error = ?err,
+                                    "Failed to build zip archive");
+                    MethodError::ServerPartialFail
+                })?,
+            };
+
+            // Enforce quota
+            let used = self
+                .core
+                .storage
+                .data
+                .blob_quota(access_token.primary_id())
+                .await
+                .map_err(|err| {
+                    tracing::error!(event = "error",
+                    context = "blob_store",
+                    account_id = access_token.primary_id(),
+                    error = ?err,
+                    "Failed to obtain blob quota");
+                    MethodError::ServerPartialFail
+                })?;
+            if ((self.core.jmap.upload_tmp_quota_size > 0
+                && used.bytes + bundle.len() > self.core.jmap.upload_tmp_quota_size)
+                || (self.core.jmap.upload_tmp_quota_amount > 0
+                    && used.count + 1 > self.core.jmap.upload_tmp_quota_amount))
+                && !access_token.is_super_user()
+            {
+                response.not_exported.append(
+                    id,
+                    SetError::over_quota().with_description(format!(
+                        "You have exceeded the blob upload quota of {} files or {} bytes.",
+                        self.core.jmap.upload_tmp_quota_amount,
+                        self.core.jmap.upload_tmp_quota_size
+                    )),
+                );
+                continue;
+            }
+
+            // Store the bundle as a temporary blob, it can then be retrieved
+            // using the download endpoint
+            response.exported.append(
+                id,
+                ExportThreadObject {
+                    blob_id: self
+                        .put_blob(access_token.primary_id(), &bundle, true)
+                        .await?,
+                    type_: request.format.content_type(),
+                    size: bundle.len(),
+                },
+            );
+        }
+
+        Ok(response)
+    }
+}
+
+fn build_mbox(messages: &[(u64, Vec<u8>)]) -> Vec<u8> {
+    let mut mbox = Vec::with_capacity(messages.iter().map(|(_, raw)| raw.len() + 64).sum());
+    for (received_at, raw_message) in messages {
+        let date = chrono::DateTime::from_timestamp(*received_at as i64, 0).unwrap_or_default();
+        mbox.extend_from_slice(
+            format!(
+                "From MAILER-DAEMON {}\n",
+                date.format("%a %b %e %H:%M:%S %Y")
+            )
+            .as_bytes(),
+        );
+
+        // Quote lines starting with "From " using the mboxrd convention
+        for line in raw_message.split_inclusive(|&ch| ch == b'\n') {
+            let start = line.iter().position(|&ch| ch != b'>').unwrap_or(line.len());
+            if line[start..].starts_with(b"From ") {
+                mbox.push(b'>');
+            }
+            mbox.extend_from_slice(line);
+        }
+        if !mbox.ends_with(b"\n") {
+            mbox.push(b'\n');
+        }
+        mbox.push(b'\n');
+    }
+    mbox
+}
+
+fn build_zip(messages: &[(u64, Vec<u8>)]) -> zip::result::ZipResult<Vec<u8>> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (num, (_, raw_message)) in messages.iter().enumerate() {
+        zip.start_file(format!("{:04}.eml", num + 1), SimpleFileOptions::default())?;
+        zip.write_all(raw_message)?;
+    }
+    zip.finish().map(|cursor| cursor.into_inner())
+}
@@ -0,0 +1,106 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::thread::{MergeThreadRequest, MergeThreadResponse},
+    types::{
+        collection::Collection, id::Id, property::Property, state::StateChange,
+        type_state::DataType,
+    },
+};
+use store::write::{log::ChangeLogBuilder, BatchBuilder, F_BITMAP, F_CLEAR, F_VALUE};
+
+use crate::JMAP;
+
+impl JMAP {
+    pub async fn thread_merge(
+        &self,
+        request: MergeThreadRequest,
+    ) -> Result<MergeThreadResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let old_state = self.get_state(account_id, Collection::Thread).await?;
+        let mut response = MergeThreadResponse {
+            account_id: request.account_id,
+            new_state: old_state.clone(),
+            old_state,
+            thread_id: None,
+            not_found: vec![],
+            state_change: None,
+        };
+
+        if request.thread_ids.len() > self.core.jmap.set_max_objects {
+            return Err(MethodError::RequestTooLarge);
+        }
+
+        // Obtain the messages in each thread
+        let mut threads = Vec::with_capacity(request.thread_ids.len());
+        for id in request.thread_ids {
+            let thread_id = id.document_id();
+            if threads.iter().any(|(id, _)| *id == thread_id) {
+                continue;
+            }
+            match self
+                .get_tag(account_id, Collection::Email, Property::ThreadId, thread_id)
+                .await?
+            {
+                Some(document_ids) if !document_ids.is_empty() => {
+                    threads.push((thread_id, document_ids));
+                }
+                _ => {
+                    response.not_found.push(id);
+                }
+            }
+        }
+
+        // Messages are moved to the first thread
+        let thread_id = if let Some((thread_id, _)) = threads.first() {
+            *thread_id
+        } else {
+            return Ok(response);
+        };
+        response.thread_id = Some(Id::from(thread_id));
+        if threads.len() == 1 {
+            return Ok(response);
+        }
+
+        let change_id = self.assign_change_id(account_id).await?;
+        let mut changes = ChangeLogBuilder::with_change_id(change_id);
+        let mut batch = BatchBuilder::new();
+        batch.with_account_id(account_id);
+        for (old_thread_id, document_ids) in threads.into_iter().skip(1) {
+            batch
+                .with_collection(Collection::Thread)
+                .delete_document(old_thread_id);
+            changes.log_delete(Collection::Thread, old_thread_id);
+
+            batch.with_collection(Collection::Email);
+            for document_id in document_ids {
+                batch
+                    .update_document(document_id)
+                    .assert_value(Property::ThreadId, old_thread_id)
+                    .value(Property::ThreadId, old_thread_id, F_BITMAP | F_CLEAR)
+                    .value(Property::ThreadId, thread_id, F_VALUE | F_BITMAP);
+                changes.log_move(
+                    Collection::Email,
+                    Id::from_parts(old_thread_id, document_id),
+                    Id::from_parts(thread_id, document_id),
+                );
+            }
+        }
+        changes.log_child_update(Collection::Thread, thread_id);
+        batch.custom(changes);
+        self.write_batch(batch).await?;
+
+        response.new_state = change_id.into();
+        response.state_change = StateChange::new(account_id)
+            .with_change(DataType::Email, change_id)
+            .with_change(DataType::Thread, change_id)
+            .into();
+
+        Ok(response)
+    }
+}
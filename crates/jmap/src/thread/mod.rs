@@ -4,4 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod export;
 pub mod get;
+pub mod merge;
+pub mod split;
@@ -0,0 +1,184 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    error::{method::MethodError, set::SetError},
+    method::thread::{SplitThreadRequest, SplitThreadResponse},
+    object::Object,
+    types::{
+        collection::Collection, id::Id, property::Property, state::StateChange,
+        type_state::DataType,
+    },
+};
+use store::{
+    ahash::AHashSet,
+    write::{
+        log::Changes, AssignedIds, BatchBuilder, MaybeDynamicId, MaybeDynamicValue,
+        SerializeWithId, TagValue, F_BITMAP, F_CLEAR,
+    },
+    Serialize,
+};
+use utils::map::vec_map::VecMap;
+
+use crate::JMAP;
+
+impl JMAP {
+    pub async fn thread_split(
+        &self,
+        request: SplitThreadRequest,
+    ) -> Result<SplitThreadResponse, MethodError> {
+        let account_id = request.account_id.document_id();
+        let old_state = self.get_state(account_id, Collection::Thread).await?;
+        let mut response = SplitThreadResponse {
+            account_id: request.account_id,
+            new_state: old_state.clone(),
+            old_state,
+            split: VecMap::new(),
+            not_split: VecMap::new(),
+            state_change: None,
+        };
+
+        if request.email_ids.len() > self.core.jmap.set_max_objects {
+            return Err(MethodError::RequestTooLarge);
+        }
+
+        let mut thread_sizes = VecMap::<u32, u64>::new();
+        let mut seen_ids = AHashSet::with_capacity(request.email_ids.len());
+        let mut moves = Vec::new();
+        let mut batch = BatchBuilder::new();
+        batch.with_account_id(account_id);
+
+        for id in request.email_ids {
+            let document_id = id.document_id();
+            if !seen_ids.insert(document_id) {
+                continue;
+            }
+            let thread_id = if let Some(thread_id) = self
+                .get_property::<u32>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::ThreadId,
+                )
+                .await?
+            {
+                thread_id
+            } else {
+                response.not_split.append(id, SetError::not_found());
+                continue;
+            };
+
+            // Messages that are alone in their thread are left untouched
+            if !thread_sizes.contains_key(&thread_id) {
+                let size = self
+                    .get_tag(account_id, Collection::Email, Property::ThreadId, thread_id)
+                    .await?
+                    .map_or(0, |document_ids| document_ids.len());
+                thread_sizes.append(thread_id, size);
+            }
+            let thread_size = thread_sizes.get_mut(&thread_id).unwrap();
+            if *thread_size <= 1 {
+                response.split.append(
+                    id,
+                    Object::with_capacity(2)
+                        .with_property(Property::Id, Id::from_parts(thread_id, document_id))
+                        .with_property(Property::ThreadId, Id::from(thread_id)),
+                );
+                continue;
+            }
+            *thread_size -= 1;
+
+            // Move the message to a new thread, created in the same batch
+            let new_thread_id = MaybeDynamicId::Dynamic(moves.len());
+            batch
+                .with_collection(Collection::Thread)
+                .create_document()
+                .with_collection(Collection::Email)
+                .update_document(document_id)
+                .assert_value(Property::ThreadId, thread_id)
+                .value(Property::ThreadId, thread_id, F_BITMAP | F_CLEAR)
+                .set(Property::ThreadId, new_thread_id)
+                .tag(Property::ThreadId, TagValue::Id(new_thread_id), 0);
+            moves.push((id, thread_id, document_id));
+        }
+
+        if !moves.is_empty() {
+            let change_id = self.assign_change_id(account_id).await?;
+            let thread_moves = moves
+                .iter()
+                .map(|(_, thread_id, document_id)| (*thread_id, *document_id))
+                .collect::<Vec<_>>();
+            batch
+                .with_change_id(change_id)
+                .with_collection(Collection::Thread)
+                .log(LogThreadSplit {
+                    collection: Collection::Thread,
+                    moves: thread_moves.clone(),
+                })
+                .with_collection(Collection::Email)
+                .log(LogThreadSplit {
+                    collection: Collection::Email,
+                    moves: thread_moves,
+                });
+            let ids = self.write_batch(batch).await?;
+
+            for (idx, (id, _, document_id)) in moves.into_iter().enumerate() {
+                let new_thread_id = ids
+                    .get_document_id(idx)
+                    .map_err(|_| MethodError::ServerPartialFail)?;
+                response.split.append(
+                    id,
+                    Object::with_capacity(2)
+                        .with_property(Property::Id, Id::from_parts(new_thread_id, document_id))
+                        .with_property(Property::ThreadId, Id::from(new_thread_id)),
+                );
+            }
+
+            response.new_state = change_id.into();
+            response.state_change = StateChange::new(account_id)
+                .with_change(DataType::Email, change_id)
+                .with_change(DataType::Thread, change_id)
+                .into();
+        }
+
+        Ok(response)
+    }
+}
+
+// Logs the threads created by a split and the emails moved into them,
+// the new thread ids are only known once the batch is written.
+struct LogThreadSplit {
+    collection: Collection,
+    moves: Vec<(u32, u32)>,
+}
+
+impl SerializeWithId for LogThreadSplit {
+    fn serialize_with_id(&self, ids: &AssignedIds) -> store::Result<Vec<u8>> {
+        let mut changes = Changes::default();
+        for (idx, (thread_id, document_id)) in self.moves.iter().enumerate() {
+            let new_thread_id = ids.get_document_id(idx)?;
+            if self.collection == Collection::Thread {
+                changes.inserts.insert(new_thread_id as u64);
+                changes.child_updates.insert(*thread_id as u64);
+            } else {
+                changes
+                    .deletes
+                    .insert(Id::from_parts(*thread_id, *document_id).into());
+                changes
+                    .inserts
+                    .insert(Id::from_parts(new_thread_id, *document_id).into());
+            }
+        }
+
+        Ok(changes.serialize())
+    }
+}
+
+impl From<LogThreadSplit> for MaybeDynamicValue {
+    fn from(log: LogThreadSplit) -> Self {
+        MaybeDynamicValue::Dynamic(Box::new(log))
+    }
+}
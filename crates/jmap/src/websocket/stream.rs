@@ -13,7 +13,8 @@ use hyper_util::rt::TokioIo;
 use jmap_proto::{
     error::request::RequestError,
     request::websocket::{
-        WebSocketMessage, WebSocketRequestError, WebSocketResponse, WebSocketStateChange,
+        WebSocketMessage, WebSocketPing, WebSocketRequestError, WebSocketResponse,
+        WebSocketStateChange,
     },
     types::type_state::DataType,
 };
@@ -21,7 +22,7 @@ use tokio_tungstenite::WebSocketStream;
 use tungstenite::Message;
 use utils::map::bitmap::Bitmap;
 
-use crate::{auth::AccessToken, JMAP};
+use crate::{api::event_source::ping_interval, auth::AccessToken, JMAP};
 
 impl JMAP {
     pub async fn handle_websocket_stream(
@@ -60,6 +61,9 @@ impl JMAP {
         };
         let mut changes = WebSocketStateChange::new(None);
         let mut change_types: Bitmap<DataType> = Bitmap::new();
+        let mut close_after_state = false;
+        let mut ping = None;
+        let mut last_ping = Instant::now();
 
         loop {
             tokio::select! {
@@ -98,10 +102,15 @@ impl JMAP {
                                             } else {
                                                 Bitmap::all()
                                             };
+                                            close_after_state = push_enable.close_after_state;
+                                            ping = ping_interval(push_enable.ping);
+                                            last_ping = Instant::now();
                                             continue;
                                         }
                                         Ok(WebSocketMessage::PushDisable) => {
                                             change_types = Bitmap::new();
+                                            changes.changed.clear();
+                                            ping = None;
                                             continue;
                                         }
                                         Err(err) => err.to_json(),
@@ -145,18 +154,14 @@ impl JMAP {
                 }
                 state_change = change_rx.recv() => {
                     if let Some(state_change) = state_change {
-                        if !change_types.is_empty() && state_change
-                            .types
-                            .iter()
-                            .any(|(t, _)| change_types.contains(*t))
-                            {
-                                for (type_state, change_id) in state_change.types {
-                                    changes
-                                        .changed
-                                        .get_mut_or_insert(state_change.account_id.into())
-                                        .set(type_state, change_id.into());
-                                }
+                        for (type_state, change_id) in state_change.types {
+                            if change_types.contains(type_state) {
+                                changes
+                                    .changed
+                                    .get_mut_or_insert(state_change.account_id.into())
+                                    .set(type_state, change_id.into());
                             }
+                        }
                     } else {
                         tracing::debug!(
                             parent: &span,
@@ -178,10 +183,31 @@ impl JMAP {
                     changes.changed.clear();
                     last_changes_sent = Instant::now();
                     last_heartbeat = Instant::now();
+                    last_ping = Instant::now();
                     next_event = heartbeat;
+
+                    if close_after_state {
+                        change_types = Bitmap::new();
+                        ping = None;
+                    }
                 } else {
                     next_event = throttle - elapsed;
                 }
+            } else if let Some(interval) = ping.filter(|interval| last_ping.elapsed() >= *interval)
+            {
+                // Send a ping message to the client
+                if let Err(err) = stream
+                    .send(Message::Text(
+                        WebSocketPing::new(interval.as_millis() as u64).to_json(),
+                    ))
+                    .await
+                {
+                    tracing::debug!(parent: &span, error = ?err, "Failed to send ping message");
+                    break;
+                }
+                last_ping = Instant::now();
+                last_heartbeat = Instant::now();
+                next_event = std::cmp::min(interval, heartbeat);
             } else if last_heartbeat.elapsed() > heartbeat {
                 if let Err(err) = stream.send(Message::Ping(vec![])).await {
                     tracing::debug!(parent: &span, error = ?err, "Failed to send ping message");
@@ -190,6 +216,12 @@ impl JMAP {
                 last_heartbeat = Instant::now();
                 next_event = heartbeat;
             }
+
+            // Wake up in time for the next ping
+            if let Some(interval) = ping.filter(|_| changes.changed.is_empty()) {
+                next_event =
+                    std::cmp::min(next_event, interval.saturating_sub(last_ping.elapsed()));
+            }
         }
     }
 }
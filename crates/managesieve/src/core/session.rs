@@ -31,6 +31,7 @@ impl SessionManager for ManageSieveSessionManager {
                 state: State::NotAuthenticated { auth_failures: 0 },
                 span: session.span,
                 stream: session.stream,
+                sasl: None,
                 in_flight: session.in_flight,
                 remote_addr: session.remote_ip,
             };
@@ -132,6 +133,7 @@ impl<T: SessionStream> Session<T> {
         let span = self.span;
         Ok(Session {
             stream: self.instance.tls_accept(self.stream, &span).await?,
+            sasl: None,
             state: self.state,
             instance: self.instance,
             in_flight: self.in_flight,
@@ -5,6 +5,7 @@
  */
 
 use common::{
+    auth::sasl::{SaslStep, ScramMechanism, ScramSession},
     config::server::ServerProtocol,
     listener::{limiter::ConcurrencyLimiter, SessionStream},
     AuthFailureReason, AuthResult,
@@ -14,12 +15,12 @@ use imap_proto::{
     protocol::authenticate::Mechanism,
     receiver::{self, Request},
 };
-use jmap::auth::rate_limit::ConcurrencyLimiters;
+use jmap::auth::{rate_limit::ConcurrencyLimiters, AccessToken};
 use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
 use std::sync::Arc;
 
-use crate::core::{Command, Session, State, StatusResponse};
+use crate::core::{Command, ResponseCode, Session, State, StatusResponse};
 
 impl<T: SessionStream> Session<T> {
     pub async fn handle_authenticate(&mut self, request: Request<Command>) -> crate::op::OpResult {
@@ -55,6 +56,12 @@ impl<T: SessionStream> Session<T> {
                     return Ok(b"{0}\r\n".to_vec());
                 }
             }
+            Mechanism::ScramSha1
+            | Mechanism::ScramSha1Plus
+            | Mechanism::ScramSha256
+            | Mechanism::ScramSha256Plus => {
+                return self.handle_scram(mechanism, params).await;
+            }
             _ => {
                 return Err(StatusResponse::no(
                     "Authentication mechanism not supported.",
@@ -63,20 +70,7 @@ impl<T: SessionStream> Session<T> {
         };
 
         // Throttle authentication requests
-        if self
-            .jmap
-            .is_auth_allowed_soft(&self.remote_addr)
-            .await
-            .is_err()
-        {
-            tracing::debug!(parent: &self.span,
-                event = "disconnect",
-                "Too many authentication attempts, disconnecting.",
-            );
-            return Err(StatusResponse::bye(
-                "Too many authentication requests from this IP address.",
-            ));
-        }
+        self.is_auth_allowed().await?;
 
         // Authenticate
         let mut is_totp_error = false;
@@ -100,6 +94,14 @@ impl<T: SessionStream> Session<T> {
                         is_totp_error = true;
                         None
                     }
+                    AuthResult::Failure(AuthFailureReason::PasswordExpired) => {
+                        return Err(StatusResponse::no("Password expired."));
+                    }
+                    AuthResult::Failure(AuthFailureReason::AccountLocked) => {
+                        return Err(StatusResponse::no(
+                            "Account temporarily locked, try again later.",
+                        ));
+                    }
                     AuthResult::Failure(AuthFailureReason::Banned) => {
                         return Err(StatusResponse::bye(
                             "Too many authentication requests from this IP address.",
@@ -110,23 +112,144 @@ impl<T: SessionStream> Session<T> {
             Credentials::OAuthBearer { token } => {
                 match self
                     .jmap
-                    .validate_access_token("access_token", &token)
+                    .authenticate_bearer(&token, self.remote_addr, ServerProtocol::ManageSieve)
                     .await
                 {
-                    Ok((account_id, _, _)) => self.jmap.get_access_token(account_id).await,
-                    Err(err) => {
-                        tracing::debug!(
-                            parent: &self.span,
-                            context = "authenticate",
-                            err = err,
-                            "Failed to validate access token."
-                        );
-                        None
+                    AuthResult::Success(token) => Some(token),
+                    AuthResult::Failure(
+                        AuthFailureReason::InvalidCredentials
+                        | AuthFailureReason::MissingTotp
+                        | AuthFailureReason::PasswordExpired
+                        | AuthFailureReason::AccountLocked
+                        | AuthFailureReason::InternalError(_),
+                    ) => None,
+                    AuthResult::Failure(AuthFailureReason::Banned) => {
+                        return Err(StatusResponse::bye(
+                            "Too many authentication requests from this IP address.",
+                        ))
                     }
                 }
             }
         };
 
+        self.complete_authentication(access_token, is_totp_error, None)
+    }
+
+    async fn handle_scram(
+        &mut self,
+        mechanism: Mechanism,
+        mut params: Vec<String>,
+    ) -> crate::op::OpResult {
+        let mut sasl = if let Some(sasl) = self.sasl.take() {
+            sasl
+        } else {
+            let channel_binding = self.jmap.core.tls_channel_binding(&self.stream);
+            let scram_mechanism = ScramMechanism::parse(
+                std::str::from_utf8(&mechanism.clone().into_bytes()).unwrap_or_default(),
+            )
+            .filter(|m| !m.channel_binding || channel_binding.is_available())
+            .ok_or_else(|| StatusResponse::no("Channel binding is not available."))?;
+            self.is_auth_allowed().await?;
+
+            let sasl = ScramSession::new(scram_mechanism, channel_binding, self.stream.is_tls());
+            if params.is_empty() {
+                // Wait for the client-first-message
+                self.sasl = Some(sasl);
+                return Ok(self.sasl_challenge(mechanism, ""));
+            }
+            sasl
+        };
+
+        let message = match params.pop() {
+            Some(param) => base64_decode(param.as_bytes())
+                .ok_or_else(|| StatusResponse::no("Failed to decode challenge."))?,
+            None => vec![],
+        };
+
+        let mut step = self
+            .jmap
+            .authenticate_scram(
+                &mut sasl,
+                &message,
+                self.remote_addr,
+                ServerProtocol::ManageSieve,
+            )
+            .await;
+        let mut server_final = None;
+        if let SaslStep::Challenge(challenge) = &step {
+            if sasl.is_server_final() {
+                // Send the server-final-message with the OK response
+                server_final = Some(challenge.clone());
+                step = self
+                    .jmap
+                    .authenticate_scram(
+                        &mut sasl,
+                        b"",
+                        self.remote_addr,
+                        ServerProtocol::ManageSieve,
+                    )
+                    .await;
+            }
+        }
+
+        match step {
+            SaslStep::Challenge(challenge) => {
+                self.sasl = Some(sasl);
+                Ok(self.sasl_challenge(mechanism, &challenge))
+            }
+            SaslStep::Success(access_token) => {
+                self.complete_authentication(Some(access_token), false, server_final)
+            }
+            SaslStep::Failure(AuthFailureReason::Banned) => Err(StatusResponse::bye(
+                "Too many authentication requests from this IP address.",
+            )),
+            SaslStep::Failure(AuthFailureReason::PasswordExpired) => {
+                Err(StatusResponse::no("Password expired."))
+            }
+            SaslStep::Failure(AuthFailureReason::AccountLocked) => Err(StatusResponse::no(
+                "Account temporarily locked, try again later.",
+            )),
+            SaslStep::Failure(_) => self.complete_authentication(None, false, None),
+        }
+    }
+
+    fn sasl_challenge(&mut self, mechanism: Mechanism, challenge: &str) -> Vec<u8> {
+        self.receiver.request = receiver::Request {
+            tag: String::new(),
+            command: Command::Authenticate,
+            tokens: vec![receiver::Token::Argument(mechanism.into_bytes())],
+        };
+        self.receiver.state = receiver::State::Argument { last_ch: b' ' };
+
+        format!("\"{challenge}\"\r\n").into_bytes()
+    }
+
+    async fn is_auth_allowed(&self) -> Result<(), StatusResponse> {
+        // Throttle authentication requests
+        if self
+            .jmap
+            .is_auth_allowed_soft(&self.remote_addr)
+            .await
+            .is_err()
+        {
+            tracing::debug!(parent: &self.span,
+                event = "disconnect",
+                "Too many authentication attempts, disconnecting.",
+            );
+            return Err(StatusResponse::bye(
+                "Too many authentication requests from this IP address.",
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn complete_authentication(
+        &mut self,
+        access_token: Option<AccessToken>,
+        is_totp_error: bool,
+        server_final: Option<String>,
+    ) -> crate::op::OpResult {
         if let Some(access_token) = access_token {
             // Enforce concurrency limits
             let in_flight = match self
@@ -154,7 +277,13 @@ impl<T: SessionStream> Session<T> {
                 in_flight,
             };
 
-            Ok(StatusResponse::ok("Authentication successful").into_bytes())
+            let response = StatusResponse::ok("Authentication successful");
+            Ok(if let Some(server_final) = server_final {
+                response.with_code(ResponseCode::Sasl(server_final))
+            } else {
+                response
+            }
+            .into_bytes())
         } else {
             match &self.state {
                 State::NotAuthenticated { auth_failures }
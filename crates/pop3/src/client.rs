@@ -100,12 +100,17 @@ impl<T: SessionStream> Session<T> {
                             self.handle_rset().await?;
                         }
                         Command::Capa => {
-                            let mechanisms =
-                                if self.stream.is_tls() || self.jmap.core.imap.allow_plain_auth {
-                                    vec![Mechanism::Plain, Mechanism::OAuthBearer]
-                                } else {
-                                    vec![Mechanism::OAuthBearer]
-                                };
+                            let mut mechanisms = if self.stream.is_tls() {
+                                vec![Mechanism::ScramSha256Plus, Mechanism::ScramSha1Plus]
+                            } else {
+                                vec![]
+                            };
+                            mechanisms.extend([Mechanism::ScramSha256, Mechanism::ScramSha1]);
+                            if self.stream.is_tls() || self.jmap.core.imap.allow_plain_auth {
+                                mechanisms.extend([Mechanism::Plain, Mechanism::OAuthBearer]);
+                            } else {
+                                mechanisms.push(Mechanism::OAuthBearer);
+                            }
 
                             self.write_bytes(
                                 Response::Capability::<u32> {
@@ -6,7 +6,10 @@
 
 use std::{net::IpAddr, sync::Arc};
 
-use common::listener::{limiter::InFlight, ServerInstance, SessionStream};
+use common::{
+    auth::sasl::ScramSession,
+    listener::{limiter::InFlight, ServerInstance, SessionStream},
+};
 use imap::core::{ImapInstance, Inner};
 use jmap::JMAP;
 use mailbox::Mailbox;
@@ -38,6 +41,7 @@ pub struct Session<T: SessionStream> {
     pub receiver: Parser,
     pub state: State,
     pub stream: T,
+    pub sasl: Option<ScramSession>,
     pub in_flight: InFlight,
     pub remote_addr: IpAddr,
     pub span: tracing::Span,
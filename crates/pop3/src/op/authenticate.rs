@@ -5,12 +5,13 @@
  */
 
 use common::{
+    auth::sasl::{SaslStep, ScramMechanism, ScramSession},
     config::server::ServerProtocol,
     listener::{limiter::ConcurrencyLimiter, SessionStream},
     AuthFailureReason, AuthResult,
 };
 use imap::op::authenticate::{decode_challenge_oauth, decode_challenge_plain};
-use jmap::auth::rate_limit::ConcurrencyLimiters;
+use jmap::auth::{rate_limit::ConcurrencyLimiters, AccessToken};
 use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
 use std::sync::Arc;
@@ -57,6 +58,10 @@ impl<T: SessionStream> Session<T> {
                     self.write_bytes("+\r\n").await
                 }
             }
+            Mechanism::ScramSha1
+            | Mechanism::ScramSha1Plus
+            | Mechanism::ScramSha256
+            | Mechanism::ScramSha256Plus => self.handle_scram(mechanism, params).await,
             _ => {
                 self.write_err("Authentication mechanism not supported.")
                     .await
@@ -64,7 +69,84 @@ impl<T: SessionStream> Session<T> {
         }
     }
 
-    pub async fn handle_auth(&mut self, credentials: Credentials<String>) -> Result<(), ()> {
+    async fn handle_scram(
+        &mut self,
+        mechanism: Mechanism,
+        mut params: Vec<String>,
+    ) -> Result<(), ()> {
+        let mut sasl = if let Some(sasl) = self.sasl.take() {
+            sasl
+        } else {
+            let channel_binding = self.jmap.core.tls_channel_binding(&self.stream);
+            let scram_mechanism = ScramMechanism::parse(mechanism.as_str())
+                .filter(|m| !m.channel_binding || channel_binding.is_available());
+            let scram_mechanism = if let Some(scram_mechanism) = scram_mechanism {
+                scram_mechanism
+            } else {
+                return self.write_err("Channel binding is not available.").await;
+            };
+            self.is_auth_allowed().await?;
+
+            let sasl = ScramSession::new(scram_mechanism, channel_binding, self.stream.is_tls());
+            if params.is_empty() {
+                // Wait for the client-first-message
+                self.sasl = Some(sasl);
+                return self.sasl_challenge(mechanism, "").await;
+            }
+            sasl
+        };
+
+        let message = match params.pop() {
+            Some(param) => match base64_decode(param.as_bytes()) {
+                Some(message) => message,
+                None => return self.write_err("Failed to decode challenge.").await,
+            },
+            None => vec![],
+        };
+
+        match self
+            .jmap
+            .authenticate_scram(&mut sasl, &message, self.remote_addr, ServerProtocol::Pop3)
+            .await
+        {
+            SaslStep::Challenge(challenge) => {
+                self.sasl = Some(sasl);
+                self.sasl_challenge(mechanism, &challenge).await
+            }
+            SaslStep::Success(access_token) => {
+                self.complete_authentication(Some(access_token), false)
+                    .await
+            }
+            SaslStep::Failure(AuthFailureReason::Banned) => {
+                self.write_err("Too many authentication requests from this IP address.")
+                    .await?;
+                Err(())
+            }
+            SaslStep::Failure(AuthFailureReason::PasswordExpired) => {
+                self.write_err("[AUTH] Password expired.").await
+            }
+            SaslStep::Failure(AuthFailureReason::AccountLocked) => {
+                self.write_err("[AUTH] Account temporarily locked, try again later.")
+                    .await
+            }
+            SaslStep::Failure(_) => self.complete_authentication(None, false).await,
+        }
+    }
+
+    async fn sasl_challenge(&mut self, mechanism: Mechanism, challenge: &str) -> Result<(), ()> {
+        self.receiver.state = request::State::Argument {
+            request: Command::Auth {
+                mechanism: mechanism.as_str().as_bytes().to_vec(),
+                params: vec![],
+            },
+            num: 1,
+            last_is_space: true,
+        };
+
+        self.write_bytes(format!("+ {challenge}\r\n")).await
+    }
+
+    async fn is_auth_allowed(&mut self) -> Result<(), ()> {
         // Throttle authentication requests
         if self
             .jmap
@@ -82,6 +164,13 @@ impl<T: SessionStream> Session<T> {
             return Err(());
         }
 
+        Ok(())
+    }
+
+    pub async fn handle_auth(&mut self, credentials: Credentials<String>) -> Result<(), ()> {
+        // Throttle authentication requests
+        self.is_auth_allowed().await?;
+
         // Authenticate
         let mut is_totp_error = false;
         let access_token = match credentials {
@@ -99,6 +188,14 @@ impl<T: SessionStream> Session<T> {
                         is_totp_error = true;
                         None
                     }
+                    AuthResult::Failure(AuthFailureReason::PasswordExpired) => {
+                        return self.write_err("[AUTH] Password expired.").await;
+                    }
+                    AuthResult::Failure(AuthFailureReason::AccountLocked) => {
+                        return self
+                            .write_err("[AUTH] Account temporarily locked, try again later.")
+                            .await;
+                    }
                     AuthResult::Failure(AuthFailureReason::Banned) => {
                         self.write_err("Too many authentication requests from this IP address.")
                             .await?;
@@ -109,23 +206,35 @@ impl<T: SessionStream> Session<T> {
             Credentials::OAuthBearer { token } => {
                 match self
                     .jmap
-                    .validate_access_token("access_token", &token)
+                    .authenticate_bearer(&token, self.remote_addr, ServerProtocol::Pop3)
                     .await
                 {
-                    Ok((account_id, _, _)) => self.jmap.get_access_token(account_id).await,
-                    Err(err) => {
-                        tracing::debug!(
-                            parent: &self.span,
-                            context = "authenticate",
-                            err = err,
-                            "Failed to validate access token."
-                        );
-                        None
+                    AuthResult::Success(token) => Some(token),
+                    AuthResult::Failure(
+                        AuthFailureReason::InvalidCredentials
+                        | AuthFailureReason::MissingTotp
+                        | AuthFailureReason::PasswordExpired
+                        | AuthFailureReason::AccountLocked
+                        | AuthFailureReason::InternalError(_),
+                    ) => None,
+                    AuthResult::Failure(AuthFailureReason::Banned) => {
+                        self.write_err("Too many authentication requests from this IP address.")
+                            .await?;
+                        return Err(());
                     }
                 }
             }
         };
 
+        self.complete_authentication(access_token, is_totp_error)
+            .await
+    }
+
+    async fn complete_authentication(
+        &mut self,
+        access_token: Option<AccessToken>,
+        is_totp_error: bool,
+    ) -> Result<(), ()> {
         if let Some(access_token) = access_token {
             // Enforce concurrency limits
             let in_flight = match self
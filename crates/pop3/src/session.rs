@@ -34,6 +34,7 @@ impl SessionManager for Pop3SessionManager {
                     username: None,
                 },
                 stream: session.stream,
+                sasl: None,
                 in_flight: session.in_flight,
                 remote_addr: session.remote_ip,
                 span: session.span,
@@ -116,6 +117,7 @@ impl<T: SessionStream> Session<T> {
     pub async fn into_tls(self) -> Result<Session<TlsStream<T>>, ()> {
         Ok(Session {
             stream: self.instance.tls_accept(self.stream, &self.span).await?,
+            sasl: None,
             jmap: self.jmap,
             imap: self.imap,
             instance: self.instance,
@@ -4,42 +4,63 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::{listener::SessionStream, AuthFailureReason, AuthResult};
+use common::{
+    auth::sasl::{SaslStep, ScramMechanism, ScramSession},
+    listener::SessionStream,
+    AuthFailureReason, AuthResult,
+};
+use directory::{core::policy::PasswordStatus, Principal};
 use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
-use smtp_proto::{IntoString, AUTH_LOGIN, AUTH_OAUTHBEARER, AUTH_PLAIN, AUTH_XOAUTH2};
+use smtp_proto::{
+    IntoString, AUTH_LOGIN, AUTH_OAUTHBEARER, AUTH_PLAIN, AUTH_SCRAM_SHA_1, AUTH_SCRAM_SHA_1_PLUS,
+    AUTH_SCRAM_SHA_256, AUTH_SCRAM_SHA_256_PLUS, AUTH_XOAUTH2,
+};
 
 use crate::core::Session;
 
 pub struct SaslToken {
     mechanism: u64,
+    offered: u64,
     credentials: Credentials<String>,
+    scram: Option<ScramSession>,
 }
 
 impl SaslToken {
-    pub fn from_mechanism(mechanism: u64) -> Option<SaslToken> {
-        match mechanism {
-            AUTH_PLAIN | AUTH_LOGIN => SaslToken {
+    pub fn from_mechanism(mechanism: u64, offered: u64) -> Option<SaslToken> {
+        match mechanism & offered {
+            AUTH_PLAIN
+            | AUTH_LOGIN
+            | AUTH_SCRAM_SHA_1
+            | AUTH_SCRAM_SHA_1_PLUS
+            | AUTH_SCRAM_SHA_256
+            | AUTH_SCRAM_SHA_256_PLUS => SaslToken {
                 mechanism,
+                offered,
                 credentials: Credentials::Plain {
                     username: String::new(),
                     secret: String::new(),
                 },
+                scram: None,
             }
             .into(),
             AUTH_OAUTHBEARER => SaslToken {
                 mechanism,
+                offered,
                 credentials: Credentials::OAuthBearer {
                     token: String::new(),
                 },
+                scram: None,
             }
             .into(),
             AUTH_XOAUTH2 => SaslToken {
                 mechanism,
+                offered,
                 credentials: Credentials::XOauth2 {
                     username: String::new(),
                     secret: String::new(),
                 },
+                scram: None,
             }
             .into(),
             _ => None,
@@ -53,6 +74,13 @@ impl<T: SessionStream> Session<T> {
         token: &mut SaslToken,
         response: &[u8],
     ) -> Result<bool, ()> {
+        if matches!(
+            token.mechanism,
+            AUTH_SCRAM_SHA_1 | AUTH_SCRAM_SHA_1_PLUS | AUTH_SCRAM_SHA_256 | AUTH_SCRAM_SHA_256_PLUS
+        ) {
+            return self.handle_scram_response(token, response).await;
+        }
+
         if response.is_empty() {
             match (token.mechanism, &token.credentials) {
                 (AUTH_PLAIN | AUTH_XOAUTH2 | AUTH_OAUTHBEARER, _) => {
@@ -156,6 +184,174 @@ impl<T: SessionStream> Session<T> {
         self.auth_error(b"500 5.5.6 Invalid challenge.\r\n").await
     }
 
+    async fn handle_scram_response(
+        &mut self,
+        token: &mut SaslToken,
+        response: &[u8],
+    ) -> Result<bool, ()> {
+        let sasl = match &mut token.scram {
+            Some(sasl) => sasl,
+            None => {
+                let channel_binding = self.core.core.tls_channel_binding(&self.stream);
+                let plus_offered = self.stream.is_tls()
+                    && token.offered & (AUTH_SCRAM_SHA_256_PLUS | AUTH_SCRAM_SHA_1_PLUS) != 0;
+                let mechanism = ScramMechanism::parse(match token.mechanism {
+                    AUTH_SCRAM_SHA_1 => "SCRAM-SHA-1",
+                    AUTH_SCRAM_SHA_1_PLUS => "SCRAM-SHA-1-PLUS",
+                    AUTH_SCRAM_SHA_256 => "SCRAM-SHA-256",
+                    _ => "SCRAM-SHA-256-PLUS",
+                })
+                .filter(|m| !m.channel_binding || channel_binding.is_available());
+                let sasl = if let Some(mechanism) = mechanism {
+                    token
+                        .scram
+                        .insert(ScramSession::new(mechanism, channel_binding, plus_offered))
+                } else {
+                    self.write(b"554 5.7.8 Channel binding is not available.\r\n")
+                        .await?;
+                    return Ok(false);
+                };
+                if response.is_empty() {
+                    // Wait for the client-first-message
+                    self.write(b"334 \r\n").await?;
+                    return Ok(true);
+                }
+                sasl
+            }
+        };
+        let message = if !response.is_empty() {
+            if let Some(message) = base64_decode(response) {
+                message
+            } else {
+                return self.auth_error(b"500 5.5.6 Invalid challenge.\r\n").await;
+            }
+        } else {
+            vec![]
+        };
+
+        let directory = if let Some(directory) = &self.params.auth_directory {
+            directory
+        } else {
+            tracing::warn!(
+                parent: &self.span,
+                context = "auth",
+                event = "error",
+                "No lookup list configured for authentication."
+            );
+            self.write(b"454 4.7.0 Temporary authentication failure\r\n")
+                .await?;
+            return Ok(false);
+        };
+        let username = sasl.username().unwrap_or_default().to_string();
+        match self
+            .core
+            .core
+            .authenticate_scram(
+                directory,
+                &self.core.inner.ipc,
+                sasl,
+                &message,
+                self.data.remote_ip,
+                self.instance.protocol,
+                false,
+            )
+            .await
+        {
+            SaslStep::Challenge(challenge) => {
+                self.write(format!("334 {challenge}\r\n").as_bytes())
+                    .await?;
+                Ok(true)
+            }
+            SaslStep::Success(principal) => self.auth_success(username, principal).await,
+            SaslStep::Failure(
+                AuthFailureReason::InvalidCredentials | AuthFailureReason::MissingTotp,
+            ) => {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "auth",
+                    event = "authenticate",
+                    result = "failed"
+                );
+
+                self.auth_error(b"535 5.7.8 Authentication credentials invalid.\r\n")
+                    .await
+            }
+            SaslStep::Failure(AuthFailureReason::PasswordExpired) => {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "auth",
+                    event = "authenticate",
+                    result = "password-expired"
+                );
+
+                self.auth_error(b"535 5.7.8 Password expired.\r\n").await
+            }
+            SaslStep::Failure(AuthFailureReason::AccountLocked) => {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "auth",
+                    event = "authenticate",
+                    result = "account-locked"
+                );
+
+                self.auth_error(b"535 5.7.8 Account temporarily locked, try again later.\r\n")
+                    .await
+            }
+            SaslStep::Failure(AuthFailureReason::Banned) => {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "auth",
+                    event = "authenticate",
+                    result = "banned"
+                );
+
+                Err(())
+            }
+            SaslStep::Failure(AuthFailureReason::InternalError(_)) => {
+                self.write(b"454 4.7.0 Temporary authentication failure\r\n")
+                    .await?;
+                Ok(false)
+            }
+        }
+    }
+
+    async fn auth_success(
+        &mut self,
+        authenticated_as: String,
+        principal: Principal<u32>,
+    ) -> Result<bool, ()> {
+        tracing::debug!(
+            parent: &self.span,
+            context = "auth",
+            event = "authenticate",
+            result = "success"
+        );
+
+        let password_status = self
+            .core
+            .core
+            .jmap
+            .password_policy
+            .status(principal.password_changed_at());
+        self.data.authenticated_as = authenticated_as.to_lowercase();
+        self.data.authenticated_emails = principal
+            .emails
+            .into_iter()
+            .map(|e| e.trim().to_lowercase())
+            .collect();
+        self.eval_post_auth_params().await;
+        if let PasswordStatus::Grace { .. } = password_status {
+            self.write(
+                b"235 2.7.0 Authentication succeeded, password expired and must be changed.\r\n",
+            )
+            .await?;
+        } else {
+            self.write(b"235 2.7.0 Authentication succeeded.\r\n")
+                .await?;
+        }
+        Ok(false)
+    }
+
     pub async fn authenticate(&mut self, credentials: Credentials<String>) -> Result<bool, ()> {
         if let Some(directory) = &self.params.auth_directory {
             let authenticated_as = match &credentials {
@@ -177,23 +373,7 @@ impl<T: SessionStream> Session<T> {
                 .await
             {
                 Ok(AuthResult::Success(principal)) => {
-                    tracing::debug!(
-                        parent: &self.span,
-                        context = "auth",
-                        event = "authenticate",
-                        result = "success"
-                    );
-
-                    self.data.authenticated_as = authenticated_as.to_lowercase();
-                    self.data.authenticated_emails = principal
-                        .emails
-                        .into_iter()
-                        .map(|e| e.trim().to_lowercase())
-                        .collect();
-                    self.eval_post_auth_params().await;
-                    self.write(b"235 2.7.0 Authentication succeeded.\r\n")
-                        .await?;
-                    return Ok(false);
+                    return self.auth_success(authenticated_as, principal).await;
                 }
                 Ok(AuthResult::Failure(AuthFailureReason::InvalidCredentials)) => {
                     tracing::debug!(
@@ -217,6 +397,28 @@ impl<T: SessionStream> Session<T> {
 
                     return Err(());
                 }
+                Ok(AuthResult::Failure(AuthFailureReason::PasswordExpired)) => {
+                    tracing::debug!(
+                        parent: &self.span,
+                        context = "auth",
+                        event = "authenticate",
+                        result = "password-expired"
+                    );
+
+                    return self.auth_error(b"535 5.7.8 Password expired.\r\n").await;
+                }
+                Ok(AuthResult::Failure(AuthFailureReason::AccountLocked)) => {
+                    tracing::debug!(
+                        parent: &self.span,
+                        context = "auth",
+                        event = "authenticate",
+                        result = "account-locked"
+                    );
+
+                    return self
+                        .auth_error(b"535 5.7.8 Account temporarily locked, try again later.\r\n")
+                        .await;
+                }
                 Ok(AuthResult::Failure(AuthFailureReason::MissingTotp)) => {
                     tracing::debug!(
                         parent: &self.span,
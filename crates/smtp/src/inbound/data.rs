@@ -11,6 +11,7 @@ use std::{
     time::{Duration, SystemTime},
 };
 
+use ahash::AHashSet;
 use chrono::{TimeZone, Utc};
 use common::{
     config::smtp::{auth::VerifyStrategy, session::Stage},
@@ -20,7 +21,8 @@ use common::{
 };
 use mail_auth::{
     common::{headers::HeaderWriter, verify::VerifySignature},
-    dmarc, AuthenticatedMessage, AuthenticationResults, DkimResult, DmarcResult, ReceivedSpf,
+    dmarc, ArcOutput, AuthenticatedMessage, AuthenticationResults, DkimResult, DmarcResult,
+    ReceivedSpf,
 };
 use mail_builder::headers::{date::Date, message_id::generate_message_id_header};
 use sieve::runtime::Variable;
@@ -34,7 +36,11 @@ use utils::config::Rate;
 use crate::{
     core::{Session, SessionAddress, State},
     inbound::milter::Modification,
-    queue::{self, Message, QueueEnvelope, Schedule},
+    queue::{
+        self,
+        quarantine::{QuarantineReason, QuarantineSource, QuarantinedMessage},
+        Message, QueueEnvelope, Schedule, MAIL_SENDER_VERIFIED,
+    },
     scripts::ScriptResult,
 };
 
@@ -240,6 +246,7 @@ impl<T: SessionStream> Session<T> {
 
         // Verify DMARC
         let is_report = self.is_report();
+        let mut trusted_sealer = None;
         let (dmarc_result, dmarc_policy) = match &self.data.spf_mail_from {
             Some(spf_output) if dmarc.verify() => {
                 let dmarc_output = self
@@ -260,10 +267,19 @@ impl<T: SessionStream> Session<T> {
                     )
                     .await;
 
+                // Trust the ARC results of known forwarders when DMARC fails
+                let dmarc_pass = matches!(dmarc_output.spf_result(), DmarcResult::Pass)
+                    || matches!(dmarc_output.dkim_result(), DmarcResult::Pass);
+                trusted_sealer = match &arc_output {
+                    Some(arc_output) if !dmarc_pass && arc.verify() => {
+                        trusted_arc_sealer(arc_output, &auth_message, &ac.arc.trusted_sealers)
+                    }
+                    _ => None,
+                };
                 let rejected = dmarc.is_strict()
                     && dmarc_output.policy() == dmarc::Policy::Reject
-                    && !(matches!(dmarc_output.spf_result(), DmarcResult::Pass)
-                        || matches!(dmarc_output.dkim_result(), DmarcResult::Pass));
+                    && !dmarc_pass
+                    && trusted_sealer.is_none();
                 let is_temp_fail = rejected
                     && matches!(dmarc_output.spf_result(), DmarcResult::TempError(_))
                     || matches!(dmarc_output.dkim_result(), DmarcResult::TempError(_));
@@ -283,7 +299,17 @@ impl<T: SessionStream> Session<T> {
                 };
                 let dmarc_policy = dmarc_output.policy();
 
-                if !rejected {
+                if let Some(sealer) = &trusted_sealer {
+                    tracing::info!(parent: &self.span,
+                    context = "dmarc",
+                    event = "override",
+                    return_path = mail_from.address,
+                    from = auth_message.from(),
+                    arc_sealer = sealer,
+                    dkim_result = %dmarc_output.dkim_result(),
+                    spf_result = %dmarc_output.spf_result(),
+                    "DMARC policy overridden by trusted ARC sealer.");
+                } else if !rejected {
                     tracing::debug!(parent: &self.span,
                     context = "dmarc",
                     event = "verify",
@@ -310,6 +336,7 @@ impl<T: SessionStream> Session<T> {
                         dmarc_output,
                         &dkim_output,
                         &arc_output,
+                        trusted_sealer.as_deref(),
                     )
                     .await;
                 }
@@ -360,7 +387,11 @@ impl<T: SessionStream> Session<T> {
             .await
             .unwrap_or(true)
         {
-            auth_results.write_header(&mut headers);
+            if let Some(sealer) = &trusted_sealer {
+                write_dmarc_override(&auth_results, sealer, &mut headers);
+            } else {
+                auth_results.write_header(&mut headers);
+            }
         }
 
         // Add Received-SPF header
@@ -404,9 +435,17 @@ impl<T: SessionStream> Session<T> {
 
         // Run Milter filters
         let mut modifications = Vec::new();
+        let mut quarantine = Vec::new();
         match self.run_milters(Stage::Data, (&auth_message).into()).await {
             Ok(modifications_) => {
                 if !modifications_.is_empty() {
+                    quarantine.extend(modifications_.iter().filter_map(|m| match m {
+                        Modification::Quarantine { reason } => Some(QuarantineReason::new(
+                            QuarantineSource::Milter,
+                            reason.as_str(),
+                        )),
+                        _ => None,
+                    }));
                     tracing::debug!(
                     parent: &self.span,
                     context = "milter",
@@ -444,6 +483,13 @@ impl<T: SessionStream> Session<T> {
                             event = "accept",
                             "MTAHook filter(s) accepted message.");
 
+                    quarantine.extend(modifications_.iter().filter_map(|m| match m {
+                        Modification::Quarantine { reason } => Some(QuarantineReason::new(
+                            QuarantineSource::MtaHook,
+                            reason.as_str(),
+                        )),
+                        _ => None,
+                    }));
                     modifications.retain(|m| !matches!(m, Modification::ReplaceBody { .. }));
                     modifications.extend(modifications_);
                 }
@@ -666,6 +712,9 @@ impl<T: SessionStream> Session<T> {
                     ScriptModification::SetEnvelope { name, value } => {
                         self.data.apply_envelope_modification(name, value);
                     }
+                    ScriptModification::Quarantine { reason } => {
+                        quarantine.push(QuarantineReason::new(QuarantineSource::Sieve, reason));
+                    }
                 }
             }
         }
@@ -675,6 +724,24 @@ impl<T: SessionStream> Session<T> {
         let rcpt_to = std::mem::take(&mut self.data.rcpt_to);
         let mut message = self.build_message(mail_from, rcpt_to, message_id).await;
 
+        // Flag senders that authenticated or whose envelope matches a DMARC aligned From header
+        let sender_verified = if !self.data.authenticated_as.is_empty() {
+            self.data.authenticated_as == message.return_path_lcase
+                || self
+                    .data
+                    .authenticated_emails
+                    .iter()
+                    .any(|e| e == &message.return_path_lcase)
+        } else {
+            matches!(dmarc_result, Some(DmarcResult::Pass))
+                && auth_message
+                    .from()
+                    .eq_ignore_ascii_case(&message.return_path_lcase)
+        };
+        if sender_verified {
+            message.flags |= MAIL_SENDER_VERIFIED;
+        }
+
         // Add Return-Path
         if self
             .core
@@ -744,6 +811,41 @@ impl<T: SessionStream> Session<T> {
         // Update size
         message.size = raw_message.len() + headers.len();
 
+        // Quarantine message
+        if !quarantine.is_empty() {
+            let expires = self
+                .core
+                .core
+                .eval_if(&self.core.core.smtp.quarantine.expire, self)
+                .await
+                .unwrap_or_else(|| Duration::from_secs(30 * 86400));
+            let quarantined = QuarantinedMessage {
+                message,
+                expires: now() + expires.as_secs(),
+                from: String::new(),
+                subject: String::new(),
+                remote_ip: self.data.remote_ip,
+                authenticated_as: self.data.authenticated_as.clone(),
+                reasons: quarantine,
+                digest_sent: false,
+            };
+
+            return if quarantined
+                .store(Some(&headers), raw_message, &self.core, &self.span)
+                .await
+            {
+                self.send_failure_webhook(WebhookMessageFailure::Quarantined)
+                    .await;
+                self.data.messages_sent += 1;
+                (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into()
+            } else {
+                self.send_failure_webhook(WebhookMessageFailure::ServerFailure)
+                    .await;
+
+                (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into()
+            };
+        }
+
         // Verify queue quota
         if self.core.has_quota(&mut message).await {
             // Prepare webhook event
@@ -1060,3 +1162,75 @@ impl<T: SessionStream> Session<T> {
         }
     }
 }
+
+fn trusted_arc_sealer(
+    arc_output: &ArcOutput<'_>,
+    message: &AuthenticatedMessage<'_>,
+    trusted_sealers: &AHashSet<String>,
+) -> Option<String> {
+    // Use the most recent validated ARC set
+    if !matches!(arc_output.result(), DkimResult::Pass) {
+        return None;
+    }
+    let instance = arc_output.sets().len() as u32;
+    let domain = message
+        .as_headers
+        .iter()
+        .find(|header| arc_instance(header.value) == Some(instance))
+        .and_then(|header| header.header.as_ref().ok())?
+        .domain()
+        .to_lowercase();
+    if !trusted_sealers.contains(&domain) {
+        return None;
+    }
+
+    // Accept the message only if the sealer recorded a DMARC pass
+    let results = message
+        .aar_headers
+        .iter()
+        .find(|header| arc_instance(header.value) == Some(instance))?;
+    std::str::from_utf8(results.value)
+        .ok()?
+        .split(';')
+        .skip(1)
+        .any(|result| {
+            result
+                .trim()
+                .get(..10)
+                .map_or(false, |r| r.eq_ignore_ascii_case("dmarc=pass"))
+        })
+        .then_some(domain)
+}
+
+fn arc_instance(value: &[u8]) -> Option<u32> {
+    std::str::from_utf8(value)
+        .ok()?
+        .split(';')
+        .filter_map(|tag| tag.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("i"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+}
+
+fn write_dmarc_override(
+    auth_results: &AuthenticationResults<'_>,
+    sealer: &str,
+    headers: &mut Vec<u8>,
+) {
+    // Record the override as a comment on the DMARC result
+    let header = auth_results.to_header();
+    let comment = format!(" (overridden by local_policy: trusted_forwarder arc.sealer={sealer})");
+    let dmarc_end = header.match_indices("dmarc=").find_map(|(start, _)| {
+        header[..start].trim_end().ends_with(';').then(|| {
+            header[start..]
+                .find(';')
+                .map_or(header.trim_end().len(), |end| start + end)
+        })
+    });
+    if let Some(end) = dmarc_end {
+        headers.extend_from_slice(header[..end].as_bytes());
+        headers.extend_from_slice(comment.as_bytes());
+        headers.extend_from_slice(header[end..].as_bytes());
+    } else {
+        headers.extend_from_slice(header.as_bytes());
+    }
+}
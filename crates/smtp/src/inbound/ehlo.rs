@@ -213,6 +213,10 @@ impl<T: SessionStream> Session<T> {
                 .await
                 .unwrap_or_default()
                 .into();
+            if !self.stream.is_tls() {
+                // Channel binding requires TLS
+                response.auth_mechanisms &= !(AUTH_SCRAM_SHA_256_PLUS | AUTH_SCRAM_SHA_1_PLUS);
+            }
             if response.auth_mechanisms != 0 {
                 response.capabilities |= EXT_AUTH;
             }
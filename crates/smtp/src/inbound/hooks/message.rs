@@ -113,11 +113,13 @@ impl<T: SessionStream> Session<T> {
                         Action::Discard => FilterResponse::accept(),
                         Action::Reject => FilterResponse::reject(),
                         Action::Quarantine => {
-                            modifications.push(Modification::AddHeader {
-                                name: "X-Quarantine".to_string(),
-                                value: "true".to_string(),
+                            modifications.push(Modification::Quarantine {
+                                reason: response
+                                    .response
+                                    .and_then(|r| r.message)
+                                    .unwrap_or_else(|| "Quarantined by MTA hook".to_string()),
                             });
-                            FilterResponse::accept()
+                            continue;
                         }
                     };
 
@@ -358,8 +358,8 @@ impl SessionData {
                         header_changes.push((0, name, value, false));
                     }
                 }
-                Modification::Quarantine { reason } => {
-                    header_changes.push((0, "X-Quarantine".to_string(), reason, false));
+                Modification::Quarantine { .. } => {
+                    // Quarantine requests are handled by the caller
                 }
             }
         }
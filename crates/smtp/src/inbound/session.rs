@@ -92,7 +92,7 @@ impl<T: SessionStream> Session<T> {
                                 } else if !self.data.authenticated_as.is_empty() {
                                     self.write(b"503 5.5.1 Already authenticated.\r\n").await?;
                                 } else if let Some(mut token) =
-                                    SaslToken::from_mechanism(mechanism & auth)
+                                    SaslToken::from_mechanism(mechanism, auth)
                                 {
                                     if self
                                         .handle_sasl_response(
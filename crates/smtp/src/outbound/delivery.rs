@@ -190,7 +190,7 @@ impl DeliveryAttempt {
                         let delivery_result = message
                             .deliver_local(
                                 recipients.iter_mut().filter(|r| r.domain_idx == domain_idx),
-                                &core.inner.ipc.delivery_tx,
+                                &core,
                                 &span,
                             )
                             .await;
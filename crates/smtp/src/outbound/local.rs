@@ -6,17 +6,21 @@
 
 use common::{DeliveryEvent, DeliveryResult, IngestMessage};
 use smtp_proto::Response;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::oneshot;
 
-use crate::queue::{
-    Error, ErrorDetails, HostResponse, Message, Recipient, Status, RCPT_STATUS_CHANGED,
+use crate::{
+    core::SMTP,
+    queue::{
+        Error, ErrorDetails, HostResponse, Message, Recipient, Status, MAIL_SENDER_VERIFIED,
+        RCPT_STATUS_CHANGED,
+    },
 };
 
 impl Message {
     pub async fn deliver_local(
         &self,
         recipients: impl Iterator<Item = &mut Recipient>,
-        delivery_tx: &mpsc::Sender<DeliveryEvent>,
+        core: &SMTP,
         span: &tracing::Span,
     ) -> Status<(), Error> {
         // Prepare recipients list
@@ -24,6 +28,7 @@ impl Message {
         let mut total_completed = 0;
         let mut pending_recipients = Vec::new();
         let mut recipient_addresses = Vec::new();
+        let mut list_recipients = Vec::new();
         for rcpt in recipients {
             total_rcpt += 1;
             if matches!(
@@ -33,54 +38,97 @@ impl Message {
                 total_completed += 1;
                 continue;
             }
+            match core.core.mailing_list(&rcpt.address_lcase).await {
+                Ok(Some(list)) => {
+                    list_recipients.push((rcpt, Some(list)));
+                    continue;
+                }
+                Ok(None) => (),
+                Err(err) => {
+                    tracing::error!(
+                        parent: span,
+                        context = "deliver_local",
+                        event = "error",
+                        rcpt = rcpt.address,
+                        "Failed to look up mailing list: {}",
+                        err
+                    );
+                    list_recipients.push((rcpt, None));
+                    continue;
+                }
+            }
             recipient_addresses.push(rcpt.address_lcase.clone());
             pending_recipients.push(rcpt);
         }
 
-        // Create oneshot channel
-        let (result_tx, result_rx) = oneshot::channel();
-
         // Deliver message to JMAP server
-        let delivery_result = match delivery_tx
-            .send(DeliveryEvent::Ingest {
-                message: IngestMessage {
-                    sender_address: self.return_path_lcase.clone(),
-                    recipients: recipient_addresses,
-                    message_blob: self.blob_hash.clone(),
-                    message_size: self.size,
-                },
-                result_tx,
-            })
-            .await
-        {
-            Ok(_) => {
-                // Wait for result
-                match result_rx.await {
-                    Ok(delivery_result) => delivery_result,
-                    Err(_) => {
-                        tracing::warn!(
-                            parent: span,
-                            context = "deliver_local",
-                            event = "error",
-                            reason = "result channel closed",
-                        );
-                        return Status::local_error();
+        let delivery_results = if !recipient_addresses.is_empty() {
+            // Create oneshot channel
+            let (result_tx, result_rx) = oneshot::channel();
+
+            match core
+                .inner
+                .ipc
+                .delivery_tx
+                .send(DeliveryEvent::Ingest {
+                    message: IngestMessage {
+                        sender_address: self.return_path_lcase.clone(),
+                        sender_verified: (self.flags & MAIL_SENDER_VERIFIED) != 0,
+                        recipients: recipient_addresses,
+                        message_blob: self.blob_hash.clone(),
+                        message_size: self.size,
+                    },
+                    result_tx,
+                })
+                .await
+            {
+                Ok(_) => {
+                    // Wait for result
+                    match result_rx.await {
+                        Ok(delivery_result) => delivery_result,
+                        Err(_) => {
+                            tracing::warn!(
+                                parent: span,
+                                context = "deliver_local",
+                                event = "error",
+                                reason = "result channel closed",
+                            );
+                            return Status::local_error();
+                        }
                     }
                 }
+                Err(_) => {
+                    tracing::warn!(
+                        parent: span,
+                        context = "deliver_local",
+                        event = "error",
+                        reason = "tx channel closed",
+                    );
+                    return Status::local_error();
+                }
             }
-            Err(_) => {
-                tracing::warn!(
-                    parent: span,
-                    context = "deliver_local",
-                    event = "error",
-                    reason = "tx channel closed",
-                );
-                return Status::local_error();
-            }
+        } else {
+            Vec::new()
         };
 
+        // Hand mailing list traffic to the list manager
+        let mut results = pending_recipients
+            .into_iter()
+            .zip(delivery_results)
+            .collect::<Vec<_>>();
+        for (rcpt, list) in list_recipients {
+            let result = if let Some((list, command)) = list {
+                core.deliver_list(self, list, command, span).await
+            } else {
+                DeliveryResult::TemporaryFailure {
+                    reason: "Transient server failure.".into(),
+                }
+            };
+            results.push((rcpt, result));
+        }
+
         // Process delivery results
-        for (rcpt, result) in pending_recipients.into_iter().zip(delivery_result) {
+        for (rcpt, result) in results {
             rcpt.flags |= RCPT_STATUS_CHANGED;
             match result {
                 DeliveryResult::Success => {
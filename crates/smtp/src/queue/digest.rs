@@ -0,0 +1,267 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::AHashMap;
+use common::config::smtp::queue::DsnDigest;
+use serde::{Deserialize, Serialize};
+use smtp_proto::{RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER};
+use store::{
+    write::{key::DeserializeBigEndian, now, BatchBuilder, Bincode, ReportClass, ValueClass},
+    Deserialize as _, IterateParams, Serialize as _, ValueKey, U64_LEN,
+};
+use utils::BlobHash;
+
+use crate::core::SMTP;
+
+use super::{Domain, Message, QueueId, Recipient, Status, RCPT_DSN_SENT, RCPT_STATUS_CHANGED};
+
+// Pending digest entries are discarded if they were not delivered within a week
+const DIGEST_ENTRY_EXPIRY: u64 = 7 * 86400;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DsnDigestEntry {
+    pub queue_id: QueueId,
+    pub created: u64,
+    pub return_path: String,
+    pub return_path_lcase: String,
+    pub return_path_domain: String,
+    pub recipient: Recipient,
+    pub domain: Domain,
+}
+
+impl Message {
+    pub(super) async fn add_to_dsn_digest(
+        &mut self,
+        core: &SMTP,
+        digest: &DsnDigest,
+        span: &tracing::Span,
+    ) {
+        let digest_delay = core
+            .core
+            .eval_if(&digest.delay, self)
+            .await
+            .unwrap_or(false);
+        let digest_failure = core
+            .core
+            .eval_if(&digest.failure, self)
+            .await
+            .unwrap_or(false);
+        if !digest_delay && !digest_failure {
+            return;
+        }
+
+        // Obtain the recipients to be included in the digest
+        let now = now();
+        let mut failed = Vec::new();
+        let mut has_delay = false;
+        let mut batch = BatchBuilder::new();
+        for (rcpt_idx, rcpt) in self.recipients.iter().enumerate() {
+            if rcpt.has_flag(RCPT_DSN_SENT | RCPT_NOTIFY_NEVER) {
+                continue;
+            }
+            let domain = &self.domains[rcpt.domain_idx];
+            let is_failure = match &rcpt.status {
+                Status::PermanentFailure(_) => true,
+                Status::TemporaryFailure(_) => false,
+                Status::Scheduled => match &domain.status {
+                    Status::PermanentFailure(_) => true,
+                    Status::TemporaryFailure(_) | Status::Scheduled => false,
+                    Status::Completed(_) => continue,
+                },
+                Status::Completed(_) => continue,
+            };
+
+            if is_failure {
+                if !digest_failure || !rcpt.has_flag(RCPT_NOTIFY_FAILURE) {
+                    continue;
+                }
+                failed.push(rcpt_idx);
+            } else if digest_delay && domain.notify.due <= now && rcpt.has_flag(RCPT_NOTIFY_DELAY) {
+                has_delay = true;
+            } else {
+                continue;
+            }
+
+            batch.set(
+                ValueClass::Report(ReportClass::DsnDigest {
+                    id: core
+                        .inner
+                        .snowflake_id
+                        .generate()
+                        .unwrap_or_else(|| now + rcpt_idx as u64),
+                    expires: now + DIGEST_ENTRY_EXPIRY,
+                }),
+                Bincode::new(DsnDigestEntry {
+                    queue_id: self.id,
+                    created: self.created,
+                    return_path: self.return_path.clone(),
+                    return_path_lcase: self.return_path_lcase.clone(),
+                    return_path_domain: self.return_path_domain.clone(),
+                    recipient: rcpt.clone(),
+                    domain: domain.clone(),
+                })
+                .serialize(),
+            );
+        }
+
+        if batch.is_empty() {
+            return;
+        }
+
+        // Individual notifications are sent when the digest cannot be stored
+        if let Err(err) = core.core.storage.data.write(batch.build()).await {
+            tracing::error!(
+                parent: span,
+                context = "dsn-digest",
+                event = "error",
+                "Failed to write to data store: {}",
+                err
+            );
+            return;
+        }
+
+        tracing::debug!(
+            parent: span,
+            context = "dsn-digest",
+            event = "add",
+            id = self.id,
+            failed = failed.len(),
+            delayed = has_delay,
+            "Added delivery status notifications to sender digest."
+        );
+
+        for rcpt_idx in failed {
+            self.recipients[rcpt_idx].flags |= RCPT_DSN_SENT | RCPT_STATUS_CHANGED;
+        }
+        if has_delay {
+            self.schedule_next_notify(core, now).await;
+        }
+    }
+}
+
+impl SMTP {
+    pub async fn send_dsn_digests(&self) {
+        // Group pending notifications by sender
+        let from_key = ValueKey::from(ValueClass::Report(ReportClass::DsnDigest {
+            id: 0,
+            expires: now(),
+        }));
+        let to_key = ValueKey::from(ValueClass::Report(ReportClass::DsnDigest {
+            id: u64::MAX,
+            expires: u64::MAX,
+        }));
+        let mut digests: AHashMap<String, Vec<(ReportClass, DsnDigestEntry)>> = AHashMap::new();
+        let mut superseded = Vec::new();
+        let mut last_id = 0;
+        if let Err(err) = self
+            .core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(from_key, to_key).ascending(),
+                |key, value| {
+                    // Skip chunked records
+                    let id = key.deserialize_be_u64(U64_LEN + 1)?;
+                    if id == last_id {
+                        return Ok(true);
+                    }
+                    last_id = id;
+
+                    let entry = Bincode::<DsnDigestEntry>::deserialize(value)?.inner;
+                    let report_id = ReportClass::DsnDigest {
+                        id,
+                        expires: key.deserialize_be_u64(1)?,
+                    };
+                    let entries = digests.entry(entry.return_path_lcase.clone()).or_default();
+
+                    // List each recipient once, with its most recent status
+                    if let Some(existing) = entries.iter_mut().find(|(_, existing)| {
+                        existing.queue_id == entry.queue_id
+                            && existing.recipient.address_lcase == entry.recipient.address_lcase
+                    }) {
+                        superseded.push(std::mem::replace(existing, (report_id, entry)).0);
+                    } else {
+                        entries.push((report_id, entry));
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+        {
+            tracing::error!(
+                context = "dsn-digest",
+                event = "error",
+                "Failed to read pending notifications: {}",
+                err
+            );
+            return;
+        }
+
+        // Send one report per sender
+        let span = tracing::info_span!("dsn-digest");
+        if !superseded.is_empty() {
+            let mut batch = BatchBuilder::new();
+            for id in superseded {
+                batch.clear(ValueClass::Report(id));
+            }
+            if let Err(err) = self.core.storage.data.write(batch.build()).await {
+                tracing::error!(
+                    parent: &span,
+                    context = "dsn-digest",
+                    event = "error",
+                    "Failed to remove pending notifications: {}",
+                    err
+                );
+            }
+        }
+        for (_, entries) in digests {
+            let first = &entries[0].1;
+            let mut message = Message {
+                id: 0,
+                created: entries
+                    .iter()
+                    .map(|(_, entry)| entry.created)
+                    .min()
+                    .unwrap_or_default(),
+                blob_hash: BlobHash::default(),
+                return_path: first.return_path.clone(),
+                return_path_lcase: first.return_path_lcase.clone(),
+                return_path_domain: first.return_path_domain.clone(),
+                recipients: Vec::with_capacity(entries.len()),
+                domains: Vec::with_capacity(entries.len()),
+                flags: 0,
+                env_id: None,
+                priority: 0,
+                size: 0,
+                quota_keys: vec![],
+            };
+            let mut batch = BatchBuilder::new();
+            for (domain_idx, (id, entry)) in entries.into_iter().enumerate() {
+                let mut recipient = entry.recipient;
+                recipient.domain_idx = domain_idx;
+                recipient.flags &= !(RCPT_DSN_SENT | RCPT_STATUS_CHANGED);
+                message.recipients.push(recipient);
+                message.domains.push(entry.domain);
+                batch.clear(ValueClass::Report(id));
+            }
+
+            if let Some(dsn) = message.build_dsn_report(self, false, &span).await {
+                self.queue_dsn(&mut message, dsn, &span).await;
+            }
+
+            if let Err(err) = self.core.storage.data.write(batch.build()).await {
+                tracing::error!(
+                    parent: &span,
+                    context = "dsn-digest",
+                    event = "error",
+                    "Failed to remove pending notifications: {}",
+                    err
+                );
+            }
+        }
+    }
+}
@@ -4,13 +4,15 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use ahash::AHashMap;
 use chrono::{TimeZone, Utc};
+use common::config::smtp::queue::{DsnTemplate, DsnTemplateText};
 use common::webhooks::{WebhookDSN, WebhookDSNType, WebhookPayload, WebhookType};
 use mail_builder::headers::content_type::ContentType;
 use mail_builder::headers::HeaderType;
 use mail_builder::mime::{make_boundary, BodyPart, MimePart};
 use mail_builder::MessageBuilder;
-use mail_parser::DateTime;
+use mail_parser::{DateTime, MessageParser};
 use smtp_proto::{
     Response, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS,
 };
@@ -31,27 +33,14 @@ impl SMTP {
         self.send_dsn_webhook(message).await;
 
         if !message.return_path.is_empty() {
+            // Add delay and failure notifications to the sender's digest
+            if let Some(digest) = &self.core.smtp.queue.dsn.digest {
+                message.add_to_dsn_digest(self, digest, span).await;
+            }
+
             // Build DSN
             if let Some(dsn) = message.build_dsn(self, span).await {
-                let mut dsn_message = self.new_message("", "", "");
-                dsn_message
-                    .add_recipient_parts(
-                        &message.return_path,
-                        &message.return_path_lcase,
-                        &message.return_path_domain,
-                        self,
-                    )
-                    .await;
-
-                // Sign message
-                let signature = self
-                    .sign_message(message, &self.core.smtp.queue.dsn.sign, &dsn, span)
-                    .await;
-
-                // Queue DSN
-                dsn_message
-                    .queue(signature.as_deref(), &dsn, self, span)
-                    .await;
+                self.queue_dsn(message, dsn, span).await;
             }
         } else {
             // Handle double bounce
@@ -59,6 +48,33 @@ impl SMTP {
         }
     }
 
+    pub(super) async fn queue_dsn(
+        &self,
+        message: &mut Message,
+        dsn: Vec<u8>,
+        span: &tracing::Span,
+    ) {
+        let mut dsn_message = self.new_message("", "", "");
+        dsn_message
+            .add_recipient_parts(
+                &message.return_path,
+                &message.return_path_lcase,
+                &message.return_path_domain,
+                self,
+            )
+            .await;
+
+        // Sign message
+        let signature = self
+            .sign_message(message, &self.core.smtp.queue.dsn.sign, &dsn, span)
+            .await;
+
+        // Queue DSN
+        dsn_message
+            .queue(signature.as_deref(), &dsn, self, span)
+            .await;
+    }
+
     async fn send_dsn_webhook(&self, message: &Message) {
         let typ = if !message.return_path.is_empty() {
             WebhookType::DSN
@@ -178,13 +194,23 @@ impl SMTP {
 
 impl Message {
     pub async fn build_dsn(&mut self, core: &SMTP, span: &tracing::Span) -> Option<Vec<u8>> {
+        self.build_dsn_report(core, true, span).await
+    }
+
+    pub(super) async fn build_dsn_report(
+        &mut self,
+        core: &SMTP,
+        include_headers: bool,
+        span: &tracing::Span,
+    ) -> Option<Vec<u8>> {
         let config = &core.core.smtp.queue;
         let now = now();
 
-        let mut txt_success = String::new();
-        let mut txt_delay = String::new();
-        let mut txt_failed = String::new();
+        let mut txt_success = Vec::new();
+        let mut txt_delay = Vec::new();
+        let mut txt_failed = Vec::new();
         let mut dsn = String::new();
+        let mut retry_until = 0;
 
         for rcpt in &mut self.recipients {
             if rcpt.has_flag(RCPT_DSN_SENT | RCPT_NOTIFY_NEVER) {
@@ -199,7 +225,7 @@ impl Message {
                     }
                     rcpt.write_dsn(&mut dsn);
                     rcpt.status.write_dsn(&mut dsn);
-                    response.write_dsn_text(&rcpt.address, &mut txt_success);
+                    txt_success.push(response.dsn_text(&rcpt.address));
                 }
                 Status::TemporaryFailure(response)
                     if domain.notify.due <= now && rcpt.has_flag(RCPT_NOTIFY_DELAY) =>
@@ -207,7 +233,8 @@ impl Message {
                     rcpt.write_dsn(&mut dsn);
                     rcpt.status.write_dsn(&mut dsn);
                     domain.write_dsn_will_retry_until(&mut dsn);
-                    response.write_dsn_text(&rcpt.address, &mut txt_delay);
+                    retry_until = retry_until.max(domain.expires);
+                    txt_delay.push(response.dsn_text(&rcpt.address));
                 }
                 Status::PermanentFailure(response) => {
                     rcpt.flags |= RCPT_DSN_SENT | RCPT_STATUS_CHANGED;
@@ -216,7 +243,7 @@ impl Message {
                     }
                     rcpt.write_dsn(&mut dsn);
                     rcpt.status.write_dsn(&mut dsn);
-                    response.write_dsn_text(&rcpt.address, &mut txt_failed);
+                    txt_failed.push(response.dsn_text(&rcpt.address));
                 }
                 Status::Scheduled => {
                     // There is no status for this address, use the domain's status.
@@ -228,7 +255,7 @@ impl Message {
                             }
                             rcpt.write_dsn(&mut dsn);
                             domain.status.write_dsn(&mut dsn);
-                            err.write_dsn_text(&rcpt.address, &domain.domain, &mut txt_failed);
+                            txt_failed.push(err.dsn_text(&rcpt.address, &domain.domain));
                         }
                         Status::TemporaryFailure(err)
                             if domain.notify.due <= now && rcpt.has_flag(RCPT_NOTIFY_DELAY) =>
@@ -236,7 +263,8 @@ impl Message {
                             rcpt.write_dsn(&mut dsn);
                             domain.status.write_dsn(&mut dsn);
                             domain.write_dsn_will_retry_until(&mut dsn);
-                            err.write_dsn_text(&rcpt.address, &domain.domain, &mut txt_delay);
+                            retry_until = retry_until.max(domain.expires);
+                            txt_delay.push(err.dsn_text(&rcpt.address, &domain.domain));
                         }
                         Status::Scheduled
                             if domain.notify.due <= now && rcpt.has_flag(RCPT_NOTIFY_DELAY) =>
@@ -245,10 +273,9 @@ impl Message {
                             rcpt.write_dsn(&mut dsn);
                             domain.status.write_dsn(&mut dsn);
                             domain.write_dsn_will_retry_until(&mut dsn);
-                            Error::ConcurrencyLimited.write_dsn_text(
-                                &rcpt.address,
-                                &domain.domain,
-                                &mut txt_delay,
+                            retry_until = retry_until.max(domain.expires);
+                            txt_delay.push(
+                                Error::ConcurrencyLimited.dsn_text(&rcpt.address, &domain.domain),
                             );
                         }
                         Status::Completed(_) => {
@@ -264,101 +291,29 @@ impl Message {
             dsn.push_str("\r\n");
         }
 
-        // Build text response
-        let txt_len = txt_success.len() + txt_delay.len() + txt_failed.len();
-        if txt_len == 0 {
+        // Determine the type of report
+        if txt_success.is_empty() && txt_delay.is_empty() && txt_failed.is_empty() {
             return None;
         }
 
         let has_success = !txt_success.is_empty();
         let has_delay = !txt_delay.is_empty();
         let has_failure = !txt_failed.is_empty();
-
-        let mut txt = String::with_capacity(txt_len + 128);
-        let (subject, is_mixed) = if has_success && !has_delay && !has_failure {
-            txt.push_str(
-                "Your message has been successfully delivered to the following recipients:\r\n\r\n",
-            );
-            ("Successfully delivered message", false)
+        let kind = if has_success && !has_delay && !has_failure {
+            DsnKind::Success
         } else if has_delay && !has_success && !has_failure {
-            txt.push_str("There was a temporary problem delivering your message to the following recipients:\r\n\r\n");
-            ("Warning: Delay in message delivery", false)
+            DsnKind::Delay
         } else if has_failure && !has_success && !has_delay {
-            txt.push_str(
-                "Your message could not be delivered to the following recipients:\r\n\r\n",
-            );
-            ("Failed to deliver message", false)
+            DsnKind::Failure
         } else if has_success {
-            txt.push_str("Your message has been partially delivered:\r\n\r\n");
-            ("Partially delivered message", true)
+            DsnKind::Partial
         } else {
-            txt.push_str("Your message could not be delivered to some recipients:\r\n\r\n");
-            (
-                "Warning: Temporary and permanent failures during message delivery",
-                true,
-            )
+            DsnKind::Mixed
         };
 
-        if has_success {
-            if is_mixed {
-                txt.push_str(
-                    "    ----- Delivery to the following addresses was successful -----\r\n",
-                );
-            }
-
-            txt.push_str(&txt_success);
-            txt.push_str("\r\n");
-        }
-
-        if has_delay {
-            if is_mixed {
-                txt.push_str(
-                    "    ----- There was a temporary problem delivering to these addresses -----\r\n",
-                );
-            }
-            txt.push_str(&txt_delay);
-            txt.push_str("\r\n");
-        }
-
-        if has_failure {
-            if is_mixed {
-                txt.push_str("    ----- Delivery to the following addresses failed -----\r\n");
-            }
-            txt.push_str(&txt_failed);
-            txt.push_str("\r\n");
-        }
-
         // Update next delay notification time
         if has_delay {
-            let mut changes = Vec::new();
-            for (domain_idx, domain) in self.domains.iter().enumerate() {
-                if matches!(
-                    &domain.status,
-                    Status::TemporaryFailure(_) | Status::Scheduled
-                ) && domain.notify.due <= now
-                {
-                    let envelope = QueueEnvelope::new(self, domain_idx);
-
-                    if let Some(next_notify) = core
-                        .core
-                        .eval_if::<Vec<Duration>, _>(&config.notify, &envelope)
-                        .await
-                        .and_then(|notify| {
-                            notify.into_iter().nth((domain.notify.inner + 1) as usize)
-                        })
-                    {
-                        changes.push((domain_idx, 1, now + next_notify.as_secs()));
-                    } else {
-                        changes.push((domain_idx, 0, domain.expires + 10));
-                    }
-                }
-            }
-
-            for (domain_idx, inner, due) in changes {
-                let domain = &mut self.domains[domain_idx];
-                domain.notify.inner += inner;
-                domain.notify.due = due;
-            }
+            self.schedule_next_notify(core, now).await;
         }
 
         // Obtain hostname and sender addresses
@@ -384,62 +339,166 @@ impl Message {
         let dsn = dsn_header + dsn.as_str();
 
         // Fetch up to 1024 bytes of message headers
-        let headers = match core
-            .core
-            .storage
-            .blob
-            .get_blob(self.blob_hash.as_slice(), 0..1024)
-            .await
-        {
-            Ok(Some(mut buf)) => {
-                let mut prev_ch = 0;
-                let mut last_lf = buf.len();
-                for (pos, &ch) in buf.iter().enumerate() {
-                    match ch {
-                        b'\n' => {
-                            last_lf = pos + 1;
-                            if prev_ch != b'\n' {
+        let headers = if !include_headers {
+            String::new()
+        } else {
+            match core
+                .core
+                .storage
+                .blob
+                .get_blob(self.blob_hash.as_slice(), 0..1024)
+                .await
+            {
+                Ok(Some(mut buf)) => {
+                    let mut prev_ch = 0;
+                    let mut last_lf = buf.len();
+                    for (pos, &ch) in buf.iter().enumerate() {
+                        match ch {
+                            b'\n' => {
+                                last_lf = pos + 1;
+                                if prev_ch != b'\n' {
+                                    prev_ch = ch;
+                                } else {
+                                    break;
+                                }
+                            }
+                            b'\r' => (),
+                            0 => break,
+                            _ => {
                                 prev_ch = ch;
-                            } else {
-                                break;
                             }
                         }
-                        b'\r' => (),
-                        0 => break,
-                        _ => {
-                            prev_ch = ch;
-                        }
                     }
+                    if last_lf < 1024 {
+                        buf.truncate(last_lf);
+                    }
+                    String::from_utf8(buf).unwrap_or_default()
+                }
+                Ok(None) => {
+                    tracing::error!(
+                        parent: span,
+                        context = "queue",
+                        event = "error",
+                        "Failed to open blob {:?}: not found",
+                        self.blob_hash
+                    );
+                    String::new()
                 }
-                if last_lf < 1024 {
-                    buf.truncate(last_lf);
+                Err(err) => {
+                    tracing::error!(
+                        parent: span,
+                        context = "queue",
+                        event = "error",
+                        "Failed to open blob {:?}: {}",
+                        self.blob_hash,
+                        err
+                    );
+                    String::new()
                 }
-                String::from_utf8(buf).unwrap_or_default()
             }
-            Ok(None) => {
-                tracing::error!(
-                    parent: span,
-                    context = "queue",
-                    event = "error",
-                    "Failed to open blob {:?}: not found",
-                    self.blob_hash
-                );
-                String::new()
+        };
+
+        // Build text response
+        let parsed_headers = MessageParser::new().parse_headers(headers.as_bytes());
+        let template = self.dsn_template(core, parsed_headers.as_ref()).await;
+        let recipients = txt_success
+            .iter()
+            .chain(txt_delay.iter())
+            .chain(txt_failed.iter())
+            .map(|rcpt| rcpt.address.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let retry_until = if retry_until > now {
+            DateTime::from_timestamp(retry_until as i64).to_rfc822()
+        } else {
+            String::new()
+        };
+        let arrival_date = DateTime::from_timestamp(self.created as i64).to_rfc822();
+        let vars = [
+            ("recipients", recipients.as_str()),
+            ("return_path", self.return_path.as_str()),
+            ("reporting_mta", reporting_mta.as_str()),
+            ("retry_until", retry_until.as_str()),
+            ("arrival_date", arrival_date.as_str()),
+            (
+                "subject",
+                parsed_headers
+                    .as_ref()
+                    .and_then(|headers| headers.subject())
+                    .unwrap_or_default(),
+            ),
+        ];
+
+        let subject = template
+            .and_then(|template| kind.text(&template.subject))
+            .map(|subject| render_dsn_template(subject, &vars))
+            .unwrap_or_else(|| kind.subject().to_string());
+        let mut txt = String::with_capacity(128);
+        if let Some(intro) = template.and_then(|template| kind.text(&template.intro)) {
+            txt.push_str(&render_dsn_template(intro, &vars));
+        } else {
+            txt.push_str(kind.intro());
+        }
+        txt.push_str("\r\n\r\n");
+
+        let is_mixed = matches!(kind, DsnKind::Partial | DsnKind::Mixed);
+        for (section, rcpts) in [
+            (DsnKind::Success, &txt_success),
+            (DsnKind::Delay, &txt_delay),
+            (DsnKind::Failure, &txt_failed),
+        ] {
+            if rcpts.is_empty() {
+                continue;
             }
-            Err(err) => {
-                tracing::error!(
-                    parent: span,
-                    context = "queue",
-                    event = "error",
-                    "Failed to open blob {:?}: {}",
-                    self.blob_hash,
-                    err
-                );
-                String::new()
+
+            if is_mixed {
+                if let Some(heading) = template.and_then(|template| section.text(&template.heading))
+                {
+                    txt.push_str(&render_dsn_template(heading, &vars));
+                } else {
+                    txt.push_str(section.heading());
+                }
+                txt.push_str("\r\n");
             }
-        };
+
+            for rcpt in rcpts {
+                if let Some(line) = template.and_then(|template| section.text(&template.recipient))
+                {
+                    txt.push_str(&render_dsn_template(
+                        line,
+                        &[
+                            ("address", rcpt.address.as_str()),
+                            ("remote_mta", rcpt.remote_mta.as_str()),
+                            ("response", rcpt.response.as_str()),
+                        ],
+                    ));
+                    txt.push_str("\r\n");
+                } else {
+                    txt.push_str(&rcpt.text);
+                }
+            }
+            txt.push_str("\r\n");
+        }
+
+        if let Some(footer) = template.and_then(|template| template.footer.as_deref()) {
+            txt.push_str(&render_dsn_template(footer, &vars));
+            txt.push_str("\r\n");
+        }
 
         // Build message
+        let mut parts = vec![
+            MimePart::new(ContentType::new("text/plain"), BodyPart::Text(txt.into())),
+            MimePart::new(
+                ContentType::new("message/delivery-status"),
+                BodyPart::Text(dsn.into()),
+            ),
+        ];
+        if include_headers {
+            parts.push(MimePart::new(
+                ContentType::new("message/rfc822"),
+                BodyPart::Text(headers.into()),
+            ));
+        }
         MessageBuilder::new()
             .from((from_name.as_str(), from_addr.as_str()))
             .header("To", HeaderType::Text(self.return_path.as_str().into()))
@@ -448,23 +507,44 @@ impl Message {
             .subject(subject)
             .body(MimePart::new(
                 ContentType::new("multipart/report").attribute("report-type", "delivery-status"),
-                BodyPart::Multipart(vec![
-                    MimePart::new(ContentType::new("text/plain"), BodyPart::Text(txt.into())),
-                    MimePart::new(
-                        ContentType::new("message/delivery-status"),
-                        BodyPart::Text(dsn.into()),
-                    ),
-                    MimePart::new(
-                        ContentType::new("message/rfc822"),
-                        BodyPart::Text(headers.into()),
-                    ),
-                ]),
+                BodyPart::Multipart(parts),
             ))
             .write_to_vec()
             .unwrap_or_default()
             .into()
     }
 
+    pub(super) async fn schedule_next_notify(&mut self, core: &SMTP, now: u64) {
+        let config = &core.core.smtp.queue;
+        let mut changes = Vec::new();
+        for (domain_idx, domain) in self.domains.iter().enumerate() {
+            if matches!(
+                &domain.status,
+                Status::TemporaryFailure(_) | Status::Scheduled
+            ) && domain.notify.due <= now
+            {
+                let envelope = QueueEnvelope::new(self, domain_idx);
+
+                if let Some(next_notify) = core
+                    .core
+                    .eval_if::<Vec<Duration>, _>(&config.notify, &envelope)
+                    .await
+                    .and_then(|notify| notify.into_iter().nth((domain.notify.inner + 1) as usize))
+                {
+                    changes.push((domain_idx, 1, now + next_notify.as_secs()));
+                } else {
+                    changes.push((domain_idx, 0, domain.expires + 10));
+                }
+            }
+        }
+
+        for (domain_idx, inner, due) in changes {
+            let domain = &mut self.domains[domain_idx];
+            domain.notify.inner += inner;
+            domain.notify.due = due;
+        }
+    }
+
     fn handle_double_bounce(&mut self, span: &tracing::Span) {
         let mut is_double_bounce = Vec::with_capacity(0);
 
@@ -512,6 +592,17 @@ impl Message {
 }
 
 impl HostResponse<String> {
+    fn dsn_text(&self, addr: &str) -> DsnRecipientText {
+        let mut text = String::new();
+        self.write_dsn_text(addr, &mut text);
+        DsnRecipientText {
+            address: addr.to_string(),
+            remote_mta: self.hostname.clone(),
+            response: dsn_response(&self.response),
+            text,
+        }
+    }
+
     fn write_dsn_text(&self, addr: &str, dsn: &mut String) {
         let _ = write!(
             dsn,
@@ -529,6 +620,17 @@ impl HostResponse<String> {
 }
 
 impl HostResponse<ErrorDetails> {
+    fn dsn_text(&self, addr: &str) -> DsnRecipientText {
+        let mut text = String::new();
+        self.write_dsn_text(addr, &mut text);
+        DsnRecipientText {
+            address: addr.to_string(),
+            remote_mta: self.hostname.entity.clone(),
+            response: dsn_response(&self.response),
+            text,
+        }
+    }
+
     fn write_dsn_text(&self, addr: &str, dsn: &mut String) {
         let _ = write!(dsn, "<{}> (host '{}' rejected ", addr, self.hostname.entity);
 
@@ -549,6 +651,26 @@ impl HostResponse<ErrorDetails> {
 }
 
 impl Error {
+    fn dsn_text(&self, addr: &str, domain: &str) -> DsnRecipientText {
+        if let Error::UnexpectedResponse(response) = self {
+            return response.dsn_text(addr);
+        }
+
+        let mut text = String::new();
+        self.write_dsn_text(addr, domain, &mut text);
+        DsnRecipientText {
+            address: addr.to_string(),
+            remote_mta: match self {
+                Error::ConnectionError(details)
+                | Error::TlsError(details)
+                | Error::DaneError(details) => details.entity.clone(),
+                _ => domain.to_string(),
+            },
+            response: self.to_string(),
+            text,
+        }
+    }
+
     fn write_dsn_text(&self, addr: &str, domain: &str, dsn: &mut String) {
         match self {
             Error::UnexpectedResponse(response) => {
@@ -601,6 +723,51 @@ impl Error {
 }
 
 impl Message {
+    async fn dsn_template<'x>(
+        &self,
+        core: &'x SMTP,
+        headers: Option<&mail_parser::Message<'_>>,
+    ) -> Option<&'x DsnTemplate> {
+        let config = &core.core.smtp.queue.dsn;
+        if config.templates.is_empty() {
+            return None;
+        }
+        let domain_template = config
+            .domain_templates
+            .get(&self.return_path_domain.to_ascii_lowercase())
+            .map(|id| id.as_str());
+
+        // Use the language requested in the original message, if any
+        if let Some(value) = config
+            .language_header
+            .as_ref()
+            .zip(headers)
+            .and_then(|(name, headers)| headers.header_raw(name.as_str()))
+        {
+            for language in value.split(',') {
+                let language = language
+                    .split_once(';')
+                    .map_or(language, |(language, _)| language)
+                    .trim()
+                    .to_ascii_lowercase();
+                if let Some(template) =
+                    find_dsn_template(&config.templates, domain_template, &language)
+                {
+                    return Some(template);
+                }
+            }
+        }
+
+        let language = core
+            .core
+            .eval_if::<String, _>(&config.language, self)
+            .await
+            .map(|language| language.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        find_dsn_template(&config.templates, domain_template, &language)
+            .or_else(|| domain_template.and_then(|id| config.templates.get(id)))
+    }
+
     fn write_dsn_headers(&self, dsn: &mut String, reporting_mta: &str) {
         let _ = write!(dsn, "Reporting-MTA: dns;{reporting_mta}\r\n");
         dsn.push_str("Arrival-Date: ");
@@ -796,3 +963,135 @@ trait WriteDsn {
     fn write_dsn_diagnostic(&self, dsn: &mut String);
     fn write_response(&self, dsn: &mut String);
 }
+
+struct DsnRecipientText {
+    address: String,
+    remote_mta: String,
+    response: String,
+    text: String,
+}
+
+#[derive(Clone, Copy)]
+enum DsnKind {
+    Success,
+    Delay,
+    Failure,
+    Partial,
+    Mixed,
+}
+
+impl DsnKind {
+    fn text<'x>(&self, text: &'x DsnTemplateText) -> Option<&'x str> {
+        match self {
+            DsnKind::Success => text.success.as_deref(),
+            DsnKind::Delay => text.delay.as_deref(),
+            DsnKind::Failure => text.failure.as_deref(),
+            DsnKind::Partial => text.partial.as_deref(),
+            DsnKind::Mixed => text.mixed.as_deref(),
+        }
+    }
+
+    fn subject(&self) -> &'static str {
+        match self {
+            DsnKind::Success => "Successfully delivered message",
+            DsnKind::Delay => "Warning: Delay in message delivery",
+            DsnKind::Failure => "Failed to deliver message",
+            DsnKind::Partial => "Partially delivered message",
+            DsnKind::Mixed => "Warning: Temporary and permanent failures during message delivery",
+        }
+    }
+
+    fn intro(&self) -> &'static str {
+        match self {
+            DsnKind::Success => {
+                "Your message has been successfully delivered to the following recipients:"
+            }
+            DsnKind::Delay => {
+                "There was a temporary problem delivering your message to the following recipients:"
+            }
+            DsnKind::Failure => "Your message could not be delivered to the following recipients:",
+            DsnKind::Partial => "Your message has been partially delivered:",
+            DsnKind::Mixed => "Your message could not be delivered to some recipients:",
+        }
+    }
+
+    fn heading(&self) -> &'static str {
+        match self {
+            DsnKind::Success => {
+                "    ----- Delivery to the following addresses was successful -----"
+            }
+            DsnKind::Delay => {
+                "    ----- There was a temporary problem delivering to these addresses -----"
+            }
+            DsnKind::Failure | DsnKind::Partial | DsnKind::Mixed => {
+                "    ----- Delivery to the following addresses failed -----"
+            }
+        }
+    }
+}
+
+// Domain templates are named "<id>-<language>" and take precedence over
+// the global "<language>" templates.
+fn find_dsn_template<'x>(
+    templates: &'x AHashMap<String, DsnTemplate>,
+    domain_template: Option<&str>,
+    language: &str,
+) -> Option<&'x DsnTemplate> {
+    if language.is_empty() {
+        return None;
+    }
+    let base_language = language.split_once('-').map(|(language, _)| language);
+
+    domain_template
+        .and_then(|id| {
+            templates.get(&format!("{id}-{language}")).or_else(|| {
+                base_language.and_then(|language| templates.get(&format!("{id}-{language}")))
+            })
+        })
+        .or_else(|| templates.get(language))
+        .or_else(|| base_language.and_then(|language| templates.get(language)))
+}
+
+fn dsn_response(response: &Response<String>) -> String {
+    let mut text = format!(
+        "{} {}.{}.{} ",
+        response.code, response.esc[0], response.esc[1], response.esc[2]
+    );
+    response.write_response(&mut text);
+    text
+}
+
+fn render_dsn_template(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut result = String::with_capacity(template.len() + 64);
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        push_crlf(&mut result, &rest[..start]);
+        rest = &rest[start..];
+        if let Some((end, value)) = rest.find('}').and_then(|end| {
+            vars.iter()
+                .find(|(name, _)| *name == &rest[1..end])
+                .map(|(_, value)| (end, value))
+        }) {
+            result.push_str(value);
+            rest = &rest[end + 1..];
+        } else {
+            result.push('{');
+            rest = &rest[1..];
+        }
+    }
+    push_crlf(&mut result, rest);
+
+    result
+}
+
+fn push_crlf(result: &mut String, text: &str) {
+    let mut last_ch = result.chars().last().unwrap_or_default();
+    for ch in text.chars() {
+        if ch == '\n' && last_ch != '\r' {
+            result.push('\r');
+        }
+        result.push(ch);
+        last_ch = ch;
+    }
+}
@@ -0,0 +1,1240 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::borrow::Cow;
+
+use ahash::AHashSet;
+use common::{
+    config::smtp::list::{FromRewrite, ListCommand, ListPolicy, ListSettings, MailingList},
+    DeliveryResult,
+};
+use directory::backend::internal::lookup::DirectoryStore;
+use mail_auth::dmarc::{Dmarc, Policy};
+use mail_builder::{
+    headers::{address::Address, content_type::ContentType, text::Text, Header, HeaderType},
+    mime::{make_boundary, BodyPart, MimePart},
+    MessageBuilder,
+};
+use mail_parser::{DateTime, HeaderName, MessageParser, MimeHeaders};
+use serde::{Deserialize, Serialize};
+use store::{
+    write::{
+        key::DeserializeBigEndian, now, BatchBuilder, Bincode, BlobOp, DirectoryClass, ReportClass,
+        ValueClass,
+    },
+    Deserialize as _, IterateParams, Serialize as _, ValueKey, U32_LEN, U64_LEN,
+};
+use utils::BlobHash;
+
+use crate::core::SMTP;
+
+use super::{DomainPart, Message, RecipientDomain, MAIL_SENDER_VERIFIED};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeldMessage {
+    pub id: u64,
+    pub list_id: u32,
+    pub return_path: String,
+    pub from: String,
+    pub subject: String,
+    pub blob_hash: BlobHash,
+    pub size: usize,
+    pub created: u64,
+    pub expires: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListSubscription {
+    pub status: SubscriptionStatus,
+    pub bounces: u32,
+    pub updated: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SubscriptionStatus {
+    #[serde(rename = "active")]
+    Active,
+    #[serde(rename = "unsubscribed")]
+    Unsubscribed,
+    #[serde(rename = "disabled")]
+    Disabled,
+}
+
+impl HeldMessage {
+    pub fn id(&self) -> ReportClass {
+        ReportClass::ListModeration {
+            id: self.id,
+            expires: self.expires,
+        }
+    }
+}
+
+impl ListSubscription {
+    pub fn new(status: SubscriptionStatus) -> Self {
+        ListSubscription {
+            status,
+            bounces: 0,
+            updated: now(),
+        }
+    }
+}
+
+impl SMTP {
+    pub async fn deliver_list(
+        &self,
+        message: &Message,
+        list: MailingList,
+        command: ListCommand,
+        span: &tracing::Span,
+    ) -> DeliveryResult {
+        // Fetch message
+        let raw_message = match self
+            .core
+            .storage
+            .blob
+            .get_blob(message.blob_hash.as_slice(), 0..usize::MAX)
+            .await
+        {
+            Ok(Some(raw_message)) => raw_message,
+            result => {
+                tracing::error!(
+                    parent: span,
+                    context = "list",
+                    event = "error",
+                    list = list.id,
+                    error = ?result,
+                    "Failed to fetch message blob."
+                );
+                return temporary_failure();
+            }
+        };
+
+        // Commands sent from the null sender are ignored to avoid loops
+        let sender = message.return_path_lcase.as_str();
+        let is_approve = matches!(command, ListCommand::Approve { .. });
+        match command {
+            ListCommand::Post => self.list_post(&list, message, &raw_message, span).await,
+            ListCommand::Bounces => {
+                self.list_process_bounces(&list, &raw_message, span).await;
+                DeliveryResult::Success
+            }
+            _ if sender.is_empty() => DeliveryResult::Success,
+            ListCommand::Subscribe => {
+                if list.allow_subscribe {
+                    self.list_send_confirmation(&list, sender, span).await;
+                    DeliveryResult::Success
+                } else {
+                    DeliveryResult::PermanentFailure {
+                        code: [5, 7, 1],
+                        reason: "This list does not accept subscription requests.".into(),
+                    }
+                }
+            }
+            ListCommand::Confirm { token } => {
+                if list.allow_subscribe && token == self.list_token(&list, &["subscribe", sender]) {
+                    tracing::info!(
+                        parent: span,
+                        context = "list",
+                        event = "subscribe",
+                        list = list.id,
+                        address = sender,
+                    );
+
+                    self.set_list_subscription(
+                        &list,
+                        sender,
+                        ListSubscription::new(SubscriptionStatus::Active).into(),
+                    )
+                    .await
+                    .map_or_else(|_| temporary_failure(), |_| DeliveryResult::Success)
+                } else {
+                    invalid_token()
+                }
+            }
+            ListCommand::Unsubscribe => match self.unsubscribe_list(&list, sender, span).await {
+                Ok(_) => DeliveryResult::Success,
+                Err(_) => temporary_failure(),
+            },
+            ListCommand::Approve { id, token } | ListCommand::Reject { id, token } => {
+                if token != self.list_token(&list, &["moderate", &id.to_string()]) {
+                    return invalid_token();
+                }
+
+                let mut held = None;
+                if self
+                    .read_held_messages(|message| {
+                        if message.id == id && message.list_id == list.id {
+                            held = message.into();
+                            false
+                        } else {
+                            true
+                        }
+                    })
+                    .await
+                    .is_err()
+                {
+                    return temporary_failure();
+                }
+
+                match held {
+                    Some(held) if is_approve => {
+                        if self.approve_held_message(held).await {
+                            DeliveryResult::Success
+                        } else {
+                            temporary_failure()
+                        }
+                    }
+                    Some(held) => {
+                        if self.reject_held_message(held).await {
+                            DeliveryResult::Success
+                        } else {
+                            temporary_failure()
+                        }
+                    }
+                    None => {
+                        tracing::debug!(
+                            parent: span,
+                            context = "list",
+                            event = "not-found",
+                            list = list.id,
+                            id = id,
+                            "Held message not found, it might have been moderated already."
+                        );
+                        DeliveryResult::Success
+                    }
+                }
+            }
+        }
+    }
+
+    async fn list_post(
+        &self,
+        list: &MailingList,
+        message: &Message,
+        raw_message: &[u8],
+        span: &tracing::Span,
+    ) -> DeliveryResult {
+        let parsed = if let Some(parsed) = MessageParser::new().parse_headers(raw_message) {
+            parsed
+        } else {
+            return DeliveryResult::PermanentFailure {
+                code: [5, 6, 0],
+                reason: "Failed to parse message.".into(),
+            };
+        };
+
+        // Discard messages that have already been distributed by this list
+        let list_id = format!("<{}>", list.list_id());
+        if parsed.headers().iter().any(|header| {
+            matches!(header.name, HeaderName::ListId)
+                && raw_message
+                    .get(header.offset_start..header.offset_end)
+                    .map_or(false, |value| {
+                        String::from_utf8_lossy(value)
+                            .to_lowercase()
+                            .contains(&list_id)
+                    })
+        }) {
+            tracing::info!(
+                parent: span,
+                context = "list",
+                event = "loop-detected",
+                list = list.id,
+                "Discarding message already distributed by this list."
+            );
+            return DeliveryResult::Success;
+        }
+
+        // Enforce posting policy, only trusting senders that were authenticated
+        // or whose envelope address matched a DMARC aligned From header
+        let from = parsed
+            .from()
+            .and_then(|f| f.first())
+            .and_then(|a| a.address())
+            .map(|a| a.to_lowercase())
+            .unwrap_or_else(|| message.return_path_lcase.clone());
+        let sender = if (message.flags & MAIL_SENDER_VERIFIED) != 0 {
+            message.return_path_lcase.as_str()
+        } else {
+            ""
+        };
+        let recipients = if let Some(recipients) = self.list_recipients(list).await {
+            recipients
+        } else {
+            return temporary_failure();
+        };
+        let is_moderator = !sender.is_empty() && list.is_moderator(sender);
+        let is_member =
+            is_moderator || (!sender.is_empty() && recipients.iter().any(|r| r == sender));
+        match list.policy {
+            ListPolicy::MembersOnly if !is_member => {
+                return DeliveryResult::PermanentFailure {
+                    code: [5, 7, 1],
+                    reason: "Only list members may post to this list.".into(),
+                };
+            }
+            ListPolicy::AnnounceOnly if !is_moderator => {
+                return DeliveryResult::PermanentFailure {
+                    code: [5, 7, 1],
+                    reason: "Only moderators may post to this list.".into(),
+                };
+            }
+            ListPolicy::Moderated if !is_moderator => {
+                let held = HeldMessage {
+                    id: self.inner.snowflake_id.generate().unwrap_or_else(now),
+                    list_id: list.id,
+                    return_path: message.return_path.clone(),
+                    from,
+                    subject: parsed.subject().unwrap_or_default().to_string(),
+                    blob_hash: message.blob_hash.clone(),
+                    size: raw_message.len(),
+                    created: now(),
+                    expires: now() + self.core.smtp.list.moderation_expire.as_secs(),
+                };
+
+                return if self.hold_list_message(list, held, raw_message, span).await {
+                    DeliveryResult::Success
+                } else {
+                    temporary_failure()
+                };
+            }
+            _ => (),
+        }
+
+        if self
+            .distribute_list_message(list, raw_message, recipients, span)
+            .await
+        {
+            DeliveryResult::Success
+        } else {
+            temporary_failure()
+        }
+    }
+
+    pub async fn distribute_list_message(
+        &self,
+        list: &MailingList,
+        raw_message: &[u8],
+        mut recipients: Vec<String>,
+        span: &tracing::Span,
+    ) -> bool {
+        let parsed = if let Some(parsed) = MessageParser::new().parse_headers(raw_message) {
+            parsed
+        } else {
+            return false;
+        };
+        if let Some(archive) = &list.archive {
+            if !recipients.contains(archive) {
+                recipients.push(archive.clone());
+            }
+        }
+        if recipients.is_empty() {
+            return true;
+        }
+
+        // Rewrite From when the author's domain would not pass DMARC after distribution
+        let (from_name, from_address) = parsed
+            .from()
+            .and_then(|f| f.first())
+            .map(|a| {
+                (
+                    a.name().map(|n| n.to_string()),
+                    a.address().unwrap_or_default().to_lowercase(),
+                )
+            })
+            .unwrap_or_default();
+        let rewrite_from = !from_address.is_empty()
+            && match list.rewrite_from {
+                FromRewrite::Never => false,
+                FromRewrite::Always => true,
+                FromRewrite::Dmarc => self.has_strict_dmarc(from_address.domain_part()).await,
+            };
+        let subject = parsed.subject().unwrap_or_default();
+        let subject_prefix = list
+            .subject_prefix
+            .as_deref()
+            .filter(|prefix| !subject.contains(prefix));
+
+        // Copy the original headers, replacing the ones managed by the list
+        let mut headers = Vec::with_capacity(1024);
+        let mut body_offset = 0;
+        for header in parsed.headers() {
+            body_offset = header.offset_end;
+            let skip = match &header.name {
+                HeaderName::ReturnPath
+                | HeaderName::ListArchive
+                | HeaderName::ListHelp
+                | HeaderName::ListId
+                | HeaderName::ListOwner
+                | HeaderName::ListPost
+                | HeaderName::ListSubscribe
+                | HeaderName::ListUnsubscribe => true,
+                HeaderName::From => rewrite_from,
+                HeaderName::Subject => subject_prefix.is_some(),
+                HeaderName::Other(name) => {
+                    name.eq_ignore_ascii_case("List-Unsubscribe-Post")
+                        || name.eq_ignore_ascii_case("Precedence")
+                }
+                _ => false,
+            };
+            if !skip {
+                headers.extend_from_slice(&raw_message[header.offset_field..header.offset_end]);
+            }
+        }
+        if rewrite_from {
+            let display_name = format!(
+                "{} via {}",
+                from_name.as_deref().unwrap_or(from_address.as_str()),
+                list.name
+            );
+            write_header(
+                &mut headers,
+                "From",
+                Address::new_address(display_name.into(), list.address.as_str()),
+            );
+            if parsed.reply_to().is_none() {
+                write_header(
+                    &mut headers,
+                    "Reply-To",
+                    Address::new_address(from_name.as_deref(), from_address.as_str()),
+                );
+            }
+            write_header(
+                &mut headers,
+                "X-Original-From",
+                Address::new_address(from_name.as_deref(), from_address.as_str()),
+            );
+        }
+        if let Some(prefix) = subject_prefix {
+            write_header(
+                &mut headers,
+                "Subject",
+                Text::new(format!("{prefix} {subject}")),
+            );
+        }
+        write_header(
+            &mut headers,
+            "List-Id",
+            Address::new_address(list.name.as_str().into(), list.list_id()),
+        );
+        if list.policy != ListPolicy::AnnounceOnly {
+            headers
+                .extend_from_slice(format!("List-Post: <mailto:{}>\r\n", list.address).as_bytes());
+        } else {
+            headers.extend_from_slice(b"List-Post: NO\r\n");
+        }
+        if list.allow_subscribe {
+            headers.extend_from_slice(
+                format!(
+                    "List-Subscribe: <mailto:{}>\r\n",
+                    list.command_address("subscribe")
+                )
+                .as_bytes(),
+            );
+        }
+        headers.extend_from_slice(b"Precedence: list\r\n");
+        let body = raw_message.get(body_offset..).unwrap_or_default();
+        let return_path = list.command_address("bounces");
+        let unsubscribe = list.command_address("unsubscribe");
+
+        tracing::info!(
+            parent: span,
+            context = "list",
+            event = "distribute",
+            list = list.id,
+            from = from_address,
+            rewrite_from = rewrite_from,
+            nrcpts = recipients.len(),
+        );
+
+        if !list.one_click {
+            headers.extend_from_slice(
+                format!("List-Unsubscribe: <mailto:{unsubscribe}>\r\n").as_bytes(),
+            );
+            headers.extend_from_slice(body);
+            return self
+                .queue_list_message(&return_path, recipients.iter(), headers, span)
+                .await;
+        }
+
+        // One-click unsubscribe links are unique for each recipient (RFC 8058)
+        let base_url = self
+            .core
+            .eval_if::<String, _>(
+                &self.core.smtp.list.unsubscribe_url,
+                &RecipientDomain::new(list.address.domain_part()),
+            )
+            .await
+            .unwrap_or_default();
+        let mut total_queued = 0;
+        for rcpt in &recipients {
+            let mut message = Vec::with_capacity(headers.len() + body.len() + 256);
+            message.extend_from_slice(
+                format!(
+                    "List-Unsubscribe: <{}>,\r\n\t<mailto:{unsubscribe}>\r\n",
+                    self.list_unsubscribe_url(list, &base_url, rcpt)
+                )
+                .as_bytes(),
+            );
+            message.extend_from_slice(b"List-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n");
+            message.extend_from_slice(&headers);
+            message.extend_from_slice(body);
+
+            if self
+                .queue_list_message(&return_path, [rcpt].into_iter(), message, span)
+                .await
+            {
+                total_queued += 1;
+            }
+        }
+
+        total_queued > 0
+    }
+
+    async fn queue_list_message(
+        &self,
+        return_path: &str,
+        recipients: impl Iterator<Item = impl AsRef<str>>,
+        raw_message: Vec<u8>,
+        span: &tracing::Span,
+    ) -> bool {
+        let mut message = self.new_message(return_path, return_path, return_path.domain_part());
+        for rcpt in recipients {
+            message.add_recipient(rcpt.as_ref(), self).await;
+        }
+        let signature = self
+            .sign_message(&mut message, &self.core.smtp.list.sign, &raw_message, span)
+            .await;
+
+        message
+            .queue(signature.as_deref(), &raw_message, self, span)
+            .await
+    }
+
+    async fn hold_list_message(
+        &self,
+        list: &MailingList,
+        held: HeldMessage,
+        raw_message: &[u8],
+        span: &tracing::Span,
+    ) -> bool {
+        // Reserve blob until the moderation request expires
+        let mut batch = BatchBuilder::new();
+        batch
+            .set(
+                BlobOp::Reserve {
+                    hash: held.blob_hash.clone(),
+                    until: held.expires,
+                },
+                0u32.serialize(),
+            )
+            .set(
+                ValueClass::Report(held.id()),
+                Bincode::new(held.clone()).serialize(),
+            );
+        if let Err(err) = self.core.storage.data.write(batch.build()).await {
+            tracing::error!(
+                parent: span,
+                context = "list",
+                event = "error",
+                "Failed to write to data store: {}",
+                err
+            );
+            return false;
+        }
+
+        tracing::info!(
+            parent: span,
+            context = "list",
+            event = "held",
+            list = list.id,
+            id = held.id,
+            from = held.from,
+            "Message held for moderation."
+        );
+
+        // Notify moderators
+        if !list.moderators.is_empty() {
+            let token = self.list_token(list, &["moderate", &held.id.to_string()]);
+            let approve = list.command_address(&format!("approve-{}-{token}", held.id));
+            let reject = list.command_address(&format!("reject-{}-{token}", held.id));
+            let txt = format!(
+                concat!(
+                    "A message sent to the {} mailing list <{}> requires moderator approval.\r\n\r\n",
+                    "From: {}\r\nSubject: {}\r\n\r\n",
+                    "To approve it, reply to this message or send an empty message to:\r\n\r\n",
+                    "    {}\r\n\r\n",
+                    "To reject it, send an empty message to:\r\n\r\n",
+                    "    {}\r\n\r\n",
+                    "Unless approved, the message will be discarded on {}.\r\n"
+                ),
+                list.name,
+                list.address,
+                held.from,
+                held.subject,
+                approve,
+                reject,
+                DateTime::from_timestamp(held.expires as i64).to_rfc822(),
+            );
+            let from = list.command_address("bounces");
+            let notification = MessageBuilder::new()
+                .from((list.name.as_str(), from.as_str()))
+                .header("To", HeaderType::Text(list.moderators.join(", ").into()))
+                .reply_to(approve.as_str())
+                .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+                .message_id(format!("<{}@{}>", make_boundary("."), from.domain_part()))
+                .subject(format!("{}: message held for moderation", list.name))
+                .body(MimePart::new(
+                    ContentType::new("multipart/mixed"),
+                    BodyPart::Multipart(vec![
+                        MimePart::new(ContentType::new("text/plain"), BodyPart::Text(txt.into())),
+                        MimePart::new(
+                            ContentType::new("message/rfc822"),
+                            BodyPart::Binary(raw_message.into()),
+                        ),
+                    ]),
+                ))
+                .write_to_vec()
+                .unwrap_or_default();
+
+            self.send_report(
+                &from,
+                list.moderators.iter(),
+                notification,
+                &self.core.smtp.list.sign,
+                span,
+                true,
+            )
+            .await;
+        }
+
+        true
+    }
+
+    async fn list_send_confirmation(
+        &self,
+        list: &MailingList,
+        address: &str,
+        span: &tracing::Span,
+    ) {
+        let confirm = list.command_address(&format!(
+            "confirm-{}",
+            self.list_token(list, &["subscribe", address])
+        ));
+        let txt = format!(
+            concat!(
+                "We have received a request to subscribe <{}> to the {} mailing list <{}>.\r\n\r\n",
+                "To confirm, reply to this message or send an empty message to:\r\n\r\n",
+                "    {}\r\n\r\n",
+                "If you did not request this subscription, please ignore this message.\r\n"
+            ),
+            address, list.name, list.address, confirm
+        );
+        let from = list.command_address("bounces");
+        let message = MessageBuilder::new()
+            .from((list.name.as_str(), from.as_str()))
+            .header("To", HeaderType::Text(address.into()))
+            .reply_to(confirm.as_str())
+            .header("Auto-Submitted", HeaderType::Text("auto-replied".into()))
+            .message_id(format!("<{}@{}>", make_boundary("."), from.domain_part()))
+            .subject(format!("Confirm your subscription to {}", list.name))
+            .text_body(txt)
+            .write_to_vec()
+            .unwrap_or_default();
+
+        tracing::info!(
+            parent: span,
+            context = "list",
+            event = "subscribe-request",
+            list = list.id,
+            address = address,
+        );
+
+        self.send_report(
+            &from,
+            [address].into_iter(),
+            message,
+            &self.core.smtp.list.sign,
+            span,
+            true,
+        )
+        .await;
+    }
+
+    pub async fn unsubscribe_list(
+        &self,
+        list: &MailingList,
+        address: &str,
+        span: &tracing::Span,
+    ) -> store::Result<bool> {
+        // Only record the request for current members
+        let is_member = match self.list_recipients(list).await {
+            Some(recipients) => recipients.iter().any(|r| r == address),
+            None => {
+                return Err(store::Error::InternalError(
+                    "Failed to obtain list members".into(),
+                ))
+            }
+        };
+        if is_member {
+            tracing::info!(
+                parent: span,
+                context = "list",
+                event = "unsubscribe",
+                list = list.id,
+                address = address,
+            );
+
+            self.set_list_subscription(
+                list,
+                address,
+                ListSubscription::new(SubscriptionStatus::Unsubscribed).into(),
+            )
+            .await?;
+        }
+
+        Ok(is_member)
+    }
+
+    async fn list_process_bounces(
+        &self,
+        list: &MailingList,
+        raw_message: &[u8],
+        span: &tracing::Span,
+    ) {
+        let failures = parse_dsn_failures(raw_message);
+        if failures.is_empty() {
+            return;
+        }
+        let recipients = if let Some(recipients) = self.list_recipients(list).await {
+            recipients
+        } else {
+            return;
+        };
+
+        for address in failures {
+            if !recipients.contains(&address) {
+                continue;
+            }
+
+            let mut subscription = match self.get_list_subscription(list, &address).await {
+                Ok(subscription) => subscription
+                    .unwrap_or_else(|| ListSubscription::new(SubscriptionStatus::Active)),
+                Err(_) => continue,
+            };
+            subscription.bounces += 1;
+            subscription.updated = now();
+            if subscription.bounces >= self.core.smtp.list.bounce_threshold {
+                subscription.status = SubscriptionStatus::Disabled;
+
+                tracing::info!(
+                    parent: span,
+                    context = "list",
+                    event = "disabled",
+                    list = list.id,
+                    address = address,
+                    bounces = subscription.bounces,
+                    "Subscriber disabled after too many bounces."
+                );
+            } else {
+                tracing::debug!(
+                    parent: span,
+                    context = "list",
+                    event = "bounce",
+                    list = list.id,
+                    address = address,
+                    bounces = subscription.bounces,
+                );
+            }
+
+            let _ = self
+                .set_list_subscription(list, &address, subscription.into())
+                .await;
+        }
+    }
+
+    pub async fn list_recipients(&self, list: &MailingList) -> Option<Vec<String>> {
+        let members = match self.core.storage.data.expn(&list.address).await {
+            Ok(members) => members,
+            Err(err) => {
+                tracing::error!(
+                    context = "list",
+                    event = "error",
+                    list = list.id,
+                    "Failed to expand list members: {}",
+                    err
+                );
+                return None;
+            }
+        };
+        let subscriptions = match self.read_list_subscriptions(list).await {
+            Ok(subscriptions) => subscriptions,
+            Err(err) => {
+                tracing::error!(
+                    context = "list",
+                    event = "error",
+                    list = list.id,
+                    "Failed to read list subscriptions: {}",
+                    err
+                );
+                return None;
+            }
+        };
+
+        // Subscription records override directory membership
+        let mut excluded = AHashSet::new();
+        let mut recipients = Vec::with_capacity(members.len() + subscriptions.len());
+        for (address, subscription) in subscriptions {
+            if subscription.status == SubscriptionStatus::Active {
+                recipients.push(address);
+            } else {
+                excluded.insert(address);
+            }
+        }
+        for member in members {
+            let member = member.to_lowercase();
+            if !excluded.contains(&member) && !recipients.contains(&member) {
+                recipients.push(member);
+            }
+        }
+
+        Some(recipients)
+    }
+
+    pub async fn read_list_subscriptions(
+        &self,
+        list: &MailingList,
+    ) -> store::Result<Vec<(String, ListSubscription)>> {
+        let from_key = ValueKey::from(ValueClass::Directory(DirectoryClass::ListSubscriber {
+            list_id: list.id,
+            address: vec![],
+        }));
+        let to_key = ValueKey::from(ValueClass::Directory(DirectoryClass::ListSubscriber {
+            list_id: list.id,
+            address: vec![u8::MAX; 10],
+        }));
+        let prefix_len = U32_LEN + 1;
+        let mut subscriptions = Vec::new();
+
+        self.core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(from_key, to_key).ascending(),
+                |key, value| {
+                    let address = key
+                        .get(prefix_len..)
+                        .and_then(|address| std::str::from_utf8(address).ok())
+                        .unwrap_or_default()
+                        .to_string();
+                    subscriptions.push((
+                        address,
+                        Bincode::<ListSubscription>::deserialize(value)?.inner,
+                    ));
+
+                    Ok(true)
+                },
+            )
+            .await
+            .map(|_| subscriptions)
+    }
+
+    pub async fn get_list_subscription(
+        &self,
+        list: &MailingList,
+        address: &str,
+    ) -> store::Result<Option<ListSubscription>> {
+        self.core
+            .storage
+            .data
+            .get_value::<Bincode<ListSubscription>>(ValueKey::from(ValueClass::Directory(
+                DirectoryClass::ListSubscriber {
+                    list_id: list.id,
+                    address: address.as_bytes().to_vec(),
+                },
+            )))
+            .await
+            .map(|subscription| subscription.map(|s| s.inner))
+    }
+
+    pub async fn set_list_subscription(
+        &self,
+        list: &MailingList,
+        address: &str,
+        subscription: Option<ListSubscription>,
+    ) -> store::Result<()> {
+        let key = ValueClass::Directory(DirectoryClass::ListSubscriber {
+            list_id: list.id,
+            address: address.as_bytes().to_vec(),
+        });
+        let mut batch = BatchBuilder::new();
+        if let Some(subscription) = subscription {
+            batch.set(key, Bincode::new(subscription).serialize());
+        } else {
+            batch.clear(key);
+        }
+
+        self.core
+            .storage
+            .data
+            .write(batch.build())
+            .await
+            .map(|_| ())
+            .map_err(|err| {
+                tracing::error!(
+                    context = "list",
+                    event = "error",
+                    list = list.id,
+                    "Failed to update list subscription: {}",
+                    err
+                );
+                err
+            })
+    }
+
+    pub async fn read_list_ids(&self) -> store::Result<Vec<u32>> {
+        let mut list_ids = Vec::new();
+
+        self.core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::Directory(DirectoryClass::ListSettings(0))),
+                    ValueKey::from(ValueClass::Directory(DirectoryClass::ListSettings(
+                        u32::MAX,
+                    ))),
+                )
+                .no_values(),
+                |key, _| {
+                    list_ids.push(key.deserialize_be_u32(1)?);
+
+                    Ok(true)
+                },
+            )
+            .await
+            .map(|_| list_ids)
+    }
+
+    pub async fn set_list_settings(
+        &self,
+        list_id: u32,
+        settings: Option<ListSettings>,
+    ) -> store::Result<()> {
+        let key = ValueClass::Directory(DirectoryClass::ListSettings(list_id));
+        let mut batch = BatchBuilder::new();
+        if let Some(settings) = settings {
+            batch.set(key, Bincode::new(settings.normalize()).serialize());
+        } else {
+            batch.clear(key);
+        }
+
+        self.core
+            .storage
+            .data
+            .write(batch.build())
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn read_held_message(&self, id: ReportClass) -> Option<HeldMessage> {
+        match self
+            .core
+            .storage
+            .data
+            .get_value::<Bincode<HeldMessage>>(ValueKey::from(ValueClass::Report(id)))
+            .await
+        {
+            Ok(Some(message)) => Some(message.inner),
+            Ok(None) => None,
+            Err(err) => {
+                tracing::error!(
+                    context = "list",
+                    event = "error",
+                    "Failed to read held message from store: {}",
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    pub async fn read_held_messages(
+        &self,
+        mut cb: impl FnMut(HeldMessage) -> bool + Sync + Send,
+    ) -> store::Result<()> {
+        let from_key = ValueKey::from(ValueClass::Report(ReportClass::ListModeration {
+            id: 0,
+            expires: now(),
+        }));
+        let to_key = ValueKey::from(ValueClass::Report(ReportClass::ListModeration {
+            id: u64::MAX,
+            expires: u64::MAX,
+        }));
+        let mut last_id = 0;
+
+        self.core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(from_key, to_key).descending(),
+                |key, value| {
+                    // Skip chunked records
+                    let id = key.deserialize_be_u64(U64_LEN + 1)?;
+                    if id == last_id {
+                        return Ok(true);
+                    }
+                    last_id = id;
+
+                    Ok(cb(Bincode::<HeldMessage>::deserialize(value)?.inner))
+                },
+            )
+            .await
+    }
+
+    pub async fn approve_held_message(&self, held: HeldMessage) -> bool {
+        let span = tracing::info_span!("list-approve", id = held.id, list = held.list_id);
+        let list = match self.core.mailing_list_by_id(held.list_id).await {
+            Ok(Some(list)) => list,
+            Ok(None) => {
+                tracing::warn!(
+                    parent: &span,
+                    context = "list",
+                    event = "error",
+                    "List no longer exists, discarding held message."
+                );
+                return self.delete_held_message(&held).await;
+            }
+            Err(err) => {
+                tracing::error!(
+                    parent: &span,
+                    context = "list",
+                    event = "error",
+                    "Failed to obtain list: {}",
+                    err
+                );
+                return false;
+            }
+        };
+
+        let raw_message = match self
+            .core
+            .storage
+            .blob
+            .get_blob(held.blob_hash.as_slice(), 0..usize::MAX)
+            .await
+        {
+            Ok(Some(raw_message)) => raw_message,
+            result => {
+                tracing::error!(
+                    parent: &span,
+                    context = "list",
+                    event = "error",
+                    error = ?result,
+                    "Failed to fetch held message blob."
+                );
+                return false;
+            }
+        };
+
+        if let Some(recipients) = self.list_recipients(&list).await {
+            self.distribute_list_message(&list, &raw_message, recipients, &span)
+                .await
+                && self.delete_held_message(&held).await
+        } else {
+            false
+        }
+    }
+
+    pub async fn reject_held_message(&self, held: HeldMessage) -> bool {
+        if !self.delete_held_message(&held).await {
+            return false;
+        }
+
+        // Notify the author
+        if let Some(list) = self
+            .core
+            .mailing_list_by_id(held.list_id)
+            .await
+            .ok()
+            .flatten()
+            .filter(|_| !held.return_path.is_empty())
+        {
+            let span = tracing::info_span!("list-reject", id = held.id, list = held.list_id);
+            let from = list.command_address("bounces");
+            let message = MessageBuilder::new()
+                .from((list.name.as_str(), from.as_str()))
+                .header("To", HeaderType::Text(held.return_path.as_str().into()))
+                .header("Auto-Submitted", HeaderType::Text("auto-replied".into()))
+                .message_id(format!("<{}@{}>", make_boundary("."), from.domain_part()))
+                .subject(format!("{}: message rejected", list.name))
+                .text_body(format!(
+                    concat!(
+                        "Your message to the {} mailing list <{}> with subject {:?} ",
+                        "was rejected by a moderator.\r\n"
+                    ),
+                    list.name, list.address, held.subject
+                ))
+                .write_to_vec()
+                .unwrap_or_default();
+
+            self.send_report(
+                &from,
+                [held.return_path.as_str()].into_iter(),
+                message,
+                &self.core.smtp.list.sign,
+                &span,
+                true,
+            )
+            .await;
+        }
+
+        true
+    }
+
+    pub async fn delete_held_message(&self, held: &HeldMessage) -> bool {
+        let mut batch = BatchBuilder::new();
+        batch
+            .clear(BlobOp::Reserve {
+                hash: held.blob_hash.clone(),
+                until: held.expires,
+            })
+            .clear(ValueClass::Report(held.id()));
+
+        if let Err(err) = self.core.storage.data.write(batch.build()).await {
+            tracing::error!(
+                context = "list",
+                event = "error",
+                "Failed to delete held message: {}",
+                err
+            );
+            false
+        } else {
+            true
+        }
+    }
+
+    pub fn list_token(&self, list: &MailingList, params: &[&str]) -> String {
+        let key = blake3::hash(self.core.smtp.list.secret.as_bytes());
+        let mut hasher = blake3::Hasher::new_keyed(key.as_bytes());
+        hasher.update(&list.id.to_be_bytes());
+        for param in params {
+            hasher.update(&[0]);
+            hasher.update(param.as_bytes());
+        }
+        hasher.finalize().to_hex()[..32].to_string()
+    }
+
+    fn list_unsubscribe_url(&self, list: &MailingList, base_url: &str, address: &str) -> String {
+        format!(
+            "{}/list/unsubscribe?{}",
+            base_url.trim_end_matches('/'),
+            form_urlencoded::Serializer::new(String::new())
+                .append_pair("l", &list.id.to_string())
+                .append_pair("a", address)
+                .append_pair("t", &self.list_token(list, &["unsubscribe", address]))
+                .finish()
+        )
+    }
+
+    async fn has_strict_dmarc(&self, domain: &str) -> bool {
+        let mut domain = domain;
+        loop {
+            match self
+                .core
+                .smtp
+                .resolvers
+                .dns
+                .txt_lookup::<Dmarc>(format!("_dmarc.{domain}."))
+                .await
+            {
+                Ok(dmarc) => return matches!(dmarc.p, Policy::Reject | Policy::Quarantine),
+                Err(
+                    mail_auth::Error::DnsRecordNotFound(_) | mail_auth::Error::InvalidRecordType,
+                ) => {}
+                // Rewrite when in doubt
+                Err(_) => return true,
+            }
+
+            // Fall back to the parent domain
+            match domain.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => domain = parent,
+                _ => return false,
+            }
+        }
+    }
+}
+
+fn write_header(headers: &mut Vec<u8>, name: &str, value: impl Header) {
+    headers.extend_from_slice(name.as_bytes());
+    headers.extend_from_slice(b": ");
+    let _ = value.write_header(&mut *headers, name.len() + 2);
+}
+
+fn temporary_failure() -> DeliveryResult {
+    DeliveryResult::TemporaryFailure {
+        reason: "Transient server failure.".into(),
+    }
+}
+
+fn invalid_token() -> DeliveryResult {
+    DeliveryResult::PermanentFailure {
+        code: [5, 7, 1],
+        reason: "Invalid or expired list command.".into(),
+    }
+}
+
+pub fn parse_dsn_failures(raw_message: &[u8]) -> Vec<String> {
+    let mut failures = Vec::new();
+    let message = if let Some(message) = MessageParser::new().parse(raw_message) {
+        message
+    } else {
+        return failures;
+    };
+
+    for part in &message.parts {
+        if !part.content_type().map_or(false, |ct| {
+            ct.ctype().eq_ignore_ascii_case("message")
+                && ct
+                    .subtype()
+                    .map_or(false, |st| st.eq_ignore_ascii_case("delivery-status"))
+        }) {
+            continue;
+        }
+
+        // Per-recipient fields are separated by blank lines
+        let status = String::from_utf8_lossy(part.contents()).replace("\r\n", "\n");
+        for fields in status.split("\n\n") {
+            let mut recipient: Option<Cow<str>> = None;
+            let mut is_failure = false;
+            for line in fields.lines() {
+                if let Some((name, value)) = line.split_once(':') {
+                    let name = name.trim();
+                    if name.eq_ignore_ascii_case("Final-Recipient") {
+                        recipient = value
+                            .split_once(';')
+                            .map(|(_, address)| {
+                                address
+                                    .trim()
+                                    .trim_start_matches('<')
+                                    .trim_end_matches('>')
+                                    .to_lowercase()
+                                    .into()
+                            })
+                            .filter(|address: &Cow<str>| address.contains('@'));
+                    } else if name.eq_ignore_ascii_case("Action") {
+                        is_failure = value.trim().eq_ignore_ascii_case("failed");
+                    }
+                }
+            }
+            if let (Some(recipient), true) = (recipient, is_failure) {
+                failures.push(recipient.into_owned());
+            }
+        }
+    }
+
+    failures
+}
@@ -21,8 +21,11 @@ use utils::BlobHash;
 
 use self::spool::QueueEventLock;
 
+pub mod digest;
 pub mod dsn;
+pub mod list;
 pub mod manager;
+pub mod quarantine;
 pub mod quota;
 pub mod spool;
 pub mod throttle;
@@ -97,6 +100,8 @@ pub struct Recipient {
 pub const RCPT_DSN_SENT: u64 = 1 << 32;
 pub const RCPT_STATUS_CHANGED: u64 = 2 << 32;
 
+pub const MAIL_SENDER_VERIFIED: u64 = 1 << 32;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Status<T, E> {
     #[serde(rename = "scheduled")]
@@ -0,0 +1,600 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{borrow::Cow, fmt::Write, net::IpAddr};
+
+use ahash::{AHashMap, AHashSet};
+use mail_builder::{
+    headers::{content_type::ContentType, HeaderType},
+    mime::{make_boundary, BodyPart, MimePart},
+    MessageBuilder,
+};
+use mail_parser::{DateTime, MessageParser};
+use serde::{Deserialize, Serialize};
+use store::{
+    write::{
+        key::DeserializeBigEndian, now, BatchBuilder, Bincode, BlobOp, ReportClass, ValueClass,
+    },
+    Deserialize as _, IterateParams, Serialize as _, ValueKey, U64_LEN,
+};
+
+use crate::core::SMTP;
+
+use super::{DomainPart, Message, Recipient, RecipientDomain};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantinedMessage {
+    pub message: Message,
+    pub expires: u64,
+    pub from: String,
+    pub subject: String,
+    pub remote_ip: IpAddr,
+    pub authenticated_as: String,
+    pub reasons: Vec<QuarantineReason>,
+    pub digest_sent: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantineReason {
+    pub source: QuarantineSource,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuarantineSource {
+    #[serde(rename = "sieve")]
+    Sieve,
+    #[serde(rename = "milter")]
+    Milter,
+    #[serde(rename = "mta_hook")]
+    MtaHook,
+}
+
+impl QuarantinedMessage {
+    pub fn id(&self) -> ReportClass {
+        ReportClass::Quarantine {
+            id: self.message.id,
+            expires: self.expires,
+        }
+    }
+
+    pub fn has_recipient(&self, addresses: &[String]) -> bool {
+        self.message
+            .recipients
+            .iter()
+            .any(|r| addresses.contains(&r.address_lcase))
+    }
+
+    pub fn contains(&self, text: &str) -> bool {
+        self.from.contains(text)
+            || self.subject.contains(text)
+            || self.message.return_path_lcase.contains(text)
+            || self
+                .message
+                .recipients
+                .iter()
+                .any(|r| r.address_lcase.contains(text))
+            || self.reasons.iter().any(|r| r.reason.contains(text))
+    }
+
+    pub async fn store(
+        mut self,
+        raw_headers: Option<&[u8]>,
+        raw_message: &[u8],
+        core: &SMTP,
+        span: &tracing::Span,
+    ) -> bool {
+        let message = if let Some(raw_headers) = raw_headers {
+            let mut message = Vec::with_capacity(raw_headers.len() + raw_message.len());
+            message.extend_from_slice(raw_headers);
+            message.extend_from_slice(raw_message);
+            Cow::Owned(message)
+        } else {
+            raw_message.into()
+        };
+        self.message.blob_hash = utils::BlobHash::from(message.as_ref());
+        self.message.size = message.len();
+
+        // Obtain summary headers
+        if let Some(parsed) = MessageParser::new().parse_headers(message.as_ref()) {
+            self.from = parsed
+                .from()
+                .and_then(|f| f.first())
+                .and_then(|a| a.address())
+                .unwrap_or_default()
+                .to_string();
+            self.subject = parsed.subject().unwrap_or_default().to_string();
+        }
+
+        // Reserve blob until the quarantine expires
+        let mut batch = BatchBuilder::new();
+        batch.set(
+            BlobOp::Reserve {
+                hash: self.message.blob_hash.clone(),
+                until: self.expires,
+            },
+            0u32.serialize(),
+        );
+        if let Err(err) = core.core.storage.data.write(batch.build()).await {
+            tracing::error!(
+                parent: span,
+                context = "quarantine",
+                event = "error",
+                "Failed to write to data store: {}",
+                err
+            );
+            return false;
+        }
+        if let Err(err) = core
+            .core
+            .storage
+            .blob
+            .put_blob(self.message.blob_hash.as_slice(), message.as_ref())
+            .await
+        {
+            tracing::error!(
+                parent: span,
+                context = "quarantine",
+                event = "error",
+                "Failed to write to blob store: {}",
+                err
+            );
+            return false;
+        }
+
+        tracing::info!(
+            parent: span,
+            context = "quarantine",
+            event = "stored",
+            id = self.message.id,
+            from = if !self.message.return_path.is_empty() {
+                self.message.return_path.as_str()
+            } else {
+                "<>"
+            },
+            nrcpts = self.message.recipients.len(),
+            size = self.message.size,
+            reasons = self.reasons.iter().fold(String::new(), |mut s, r| {
+                if !s.is_empty() {
+                    s.push_str(", ");
+                }
+                s.push_str(&r.reason);
+                s
+            }),
+            "Message quarantined."
+        );
+
+        // Write quarantine record
+        let mut batch = BatchBuilder::new();
+        batch
+            .set(
+                BlobOp::Commit {
+                    hash: self.message.blob_hash.clone(),
+                },
+                vec![],
+            )
+            .set(
+                ValueClass::Report(self.id()),
+                Bincode::new(self).serialize(),
+            );
+
+        if let Err(err) = core.core.storage.data.write(batch.build()).await {
+            tracing::error!(
+                parent: span,
+                context = "quarantine",
+                event = "error",
+                "Failed to write to store: {}",
+                err
+            );
+            false
+        } else {
+            true
+        }
+    }
+}
+
+impl SMTP {
+    pub async fn read_quarantined_message(&self, id: ReportClass) -> Option<QuarantinedMessage> {
+        match self
+            .core
+            .storage
+            .data
+            .get_value::<Bincode<QuarantinedMessage>>(ValueKey::from(ValueClass::Report(id)))
+            .await
+        {
+            Ok(Some(message)) => Some(message.inner),
+            Ok(None) => None,
+            Err(err) => {
+                tracing::error!(
+                    context = "quarantine",
+                    event = "error",
+                    "Failed to read quarantined message from store: {}",
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    pub async fn read_quarantined_messages(
+        &self,
+        mut cb: impl FnMut(QuarantinedMessage) -> bool + Sync + Send,
+    ) -> store::Result<()> {
+        let from_key = ValueKey::from(ValueClass::Report(ReportClass::Quarantine {
+            id: 0,
+            expires: now(),
+        }));
+        let to_key = ValueKey::from(ValueClass::Report(ReportClass::Quarantine {
+            id: u64::MAX,
+            expires: u64::MAX,
+        }));
+        let mut last_id = 0;
+
+        self.core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(from_key, to_key).descending(),
+                |key, value| {
+                    // Skip chunked records
+                    let id = key.deserialize_be_u64(U64_LEN + 1)?;
+                    if id == last_id {
+                        return Ok(true);
+                    }
+                    last_id = id;
+
+                    Ok(cb(Bincode::<QuarantinedMessage>::deserialize(value)?.inner))
+                },
+            )
+            .await
+    }
+
+    pub async fn delete_quarantined_message(
+        &self,
+        mut quarantined: QuarantinedMessage,
+        addresses: Option<&[String]>,
+    ) -> bool {
+        // Only remove the requested recipients when others remain
+        if let Some(addresses) = addresses {
+            if quarantined
+                .message
+                .recipients
+                .iter()
+                .any(|r| !addresses.contains(&r.address_lcase))
+            {
+                quarantined.message = self
+                    .copy_message(&quarantined.message, |r| {
+                        !addresses.contains(&r.address_lcase)
+                    })
+                    .await;
+                return self.update_quarantined_message(quarantined).await;
+            }
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .clear(BlobOp::Reserve {
+                hash: quarantined.message.blob_hash.clone(),
+                until: quarantined.expires,
+            })
+            .clear(ValueClass::Report(quarantined.id()));
+
+        if let Err(err) = self.core.storage.data.write(batch.build()).await {
+            tracing::error!(
+                context = "quarantine",
+                event = "error",
+                "Failed to delete quarantined message: {}",
+                err
+            );
+            false
+        } else {
+            true
+        }
+    }
+
+    async fn update_quarantined_message(&self, quarantined: QuarantinedMessage) -> bool {
+        let mut batch = BatchBuilder::new();
+        batch.set(
+            ValueClass::Report(quarantined.id()),
+            Bincode::new(quarantined).serialize(),
+        );
+
+        if let Err(err) = self.core.storage.data.write(batch.build()).await {
+            tracing::error!(
+                context = "quarantine",
+                event = "error",
+                "Failed to update quarantined message: {}",
+                err
+            );
+            false
+        } else {
+            true
+        }
+    }
+
+    pub async fn release_quarantined_message(
+        &self,
+        quarantined: QuarantinedMessage,
+        addresses: Option<&[String]>,
+    ) -> bool {
+        let span = tracing::info_span!(
+            "quarantine-release",
+            id = quarantined.message.id,
+            return_path = quarantined.message.return_path.as_str()
+        );
+
+        // Obtain the recipients to release and reset their delivery schedule
+        let mut message = self
+            .copy_message(&quarantined.message, |r| {
+                addresses.map_or(true, |addresses| addresses.contains(&r.address_lcase))
+            })
+            .await;
+        if message.recipients.is_empty() {
+            return false;
+        }
+        message.id = self
+            .inner
+            .snowflake_id
+            .generate()
+            .unwrap_or(message.created);
+
+        // Fetch message
+        let raw_message = match self
+            .core
+            .storage
+            .blob
+            .get_blob(message.blob_hash.as_slice(), 0..usize::MAX)
+            .await
+        {
+            Ok(Some(raw_message)) => raw_message,
+            Ok(None) => {
+                tracing::error!(
+                    parent: &span,
+                    context = "quarantine",
+                    event = "error",
+                    "Failed to release message: blob {:?} not found",
+                    message.blob_hash
+                );
+                return false;
+            }
+            Err(err) => {
+                tracing::error!(
+                    parent: &span,
+                    context = "quarantine",
+                    event = "error",
+                    "Failed to release message: {}",
+                    err
+                );
+                return false;
+            }
+        };
+
+        // Verify queue quota and queue message
+        if !self.has_quota(&mut message).await {
+            tracing::warn!(
+                parent: &span,
+                context = "quarantine",
+                event = "quota-exceeded",
+                "Queue quota exceeded, unable to release message."
+            );
+            return false;
+        }
+        if !message.queue(None, &raw_message, self, &span).await {
+            return false;
+        }
+
+        // Update or remove the quarantine record
+        self.delete_quarantined_message(quarantined, addresses)
+            .await
+    }
+
+    async fn copy_message(
+        &self,
+        message: &Message,
+        filter: impl Fn(&Recipient) -> bool,
+    ) -> Message {
+        let mut new_message = Message {
+            recipients: Vec::new(),
+            domains: Vec::new(),
+            quota_keys: Vec::new(),
+            ..message.clone()
+        };
+        for rcpt in message.recipients.iter().filter(|r| filter(r)) {
+            new_message
+                .add_recipient_parts(
+                    rcpt.address.as_str(),
+                    rcpt.address_lcase.as_str(),
+                    rcpt.address_lcase.domain_part(),
+                    self,
+                )
+                .await;
+            if let Some(new_rcpt) = new_message.recipients.last_mut() {
+                new_rcpt.flags = rcpt.flags;
+                new_rcpt.orcpt = rcpt.orcpt.clone();
+            }
+        }
+        new_message
+    }
+
+    pub async fn send_quarantine_digest(&self) {
+        let config = if let Some(config) = &self.core.smtp.quarantine.digest {
+            config
+        } else {
+            return;
+        };
+
+        // Group pending messages by local recipient
+        let mut pending = Vec::new();
+        let mut digests: AHashMap<String, Vec<usize>> = AHashMap::new();
+        if let Err(err) = self
+            .read_quarantined_messages(|message| {
+                if !message.digest_sent {
+                    pending.push(message);
+                }
+                true
+            })
+            .await
+        {
+            tracing::error!(
+                context = "quarantine",
+                event = "error",
+                "Failed to read quarantined messages: {}",
+                err
+            );
+            return;
+        }
+        for (idx, message) in pending.iter().enumerate() {
+            for rcpt in &message.message.recipients {
+                if self
+                    .core
+                    .storage
+                    .directory
+                    .rcpt(&rcpt.address_lcase)
+                    .await
+                    .unwrap_or(false)
+                {
+                    digests
+                        .entry(rcpt.address_lcase.clone())
+                        .or_default()
+                        .push(idx);
+                }
+            }
+        }
+
+        // Send one summary per recipient
+        let mut failed = AHashSet::new();
+        let span = tracing::info_span!("quarantine-digest");
+        for (rcpt, ids) in digests {
+            let domain = RecipientDomain::new(rcpt.domain_part());
+            let from_name = self
+                .core
+                .eval_if(&config.name, &domain)
+                .await
+                .unwrap_or_else(|| String::from("Quarantine Notification"));
+            let from_addr = self
+                .core
+                .eval_if(&config.address, &domain)
+                .await
+                .unwrap_or_else(|| String::from("MAILER-DAEMON@localhost"));
+            let subject = self
+                .core
+                .eval_if(&config.subject, &domain)
+                .await
+                .unwrap_or_else(|| String::from("Quarantined messages summary"));
+
+            let mut txt = format!(
+                "The following {} message(s) addressed to <{}> have been quarantined.\r\n\r\n",
+                ids.len(),
+                rcpt
+            );
+            for &idx in &ids {
+                let message = &pending[idx];
+                let _ = write!(
+                    txt,
+                    "From: {}\r\nSubject: {}\r\nDate: {}\r\nExpires: {}\r\nReason: {}\r\n\r\n",
+                    if !message.from.is_empty() {
+                        message.from.as_str()
+                    } else {
+                        message.message.return_path.as_str()
+                    },
+                    message.subject,
+                    DateTime::from_timestamp(message.message.created as i64).to_rfc822(),
+                    DateTime::from_timestamp(message.expires as i64).to_rfc822(),
+                    message
+                        .reasons
+                        .iter()
+                        .map(|r| r.reason.as_str())
+                        .collect::<Vec<_>>()
+                        .join("; "),
+                );
+            }
+            txt.push_str(concat!(
+                "You can review and release these messages from the web interface ",
+                "or ask your administrator to release them.\r\n"
+            ));
+
+            let report = MessageBuilder::new()
+                .from((from_name.as_str(), from_addr.as_str()))
+                .header("To", HeaderType::Text(rcpt.as_str().into()))
+                .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+                .message_id(format!(
+                    "<{}@{}>",
+                    make_boundary("."),
+                    from_addr.domain_part()
+                ))
+                .subject(subject)
+                .body(MimePart::new(
+                    ContentType::new("text/plain"),
+                    BodyPart::Text(txt.into()),
+                ))
+                .write_to_vec()
+                .unwrap_or_default();
+
+            if !self
+                .send_report(
+                    &from_addr,
+                    [rcpt.as_str()].into_iter(),
+                    report,
+                    &config.sign,
+                    &span,
+                    true,
+                )
+                .await
+            {
+                // Retry on the next digest run
+                failed.extend(ids);
+            }
+        }
+
+        // Mark messages as notified
+        let mut batch = BatchBuilder::new();
+        for (idx, mut message) in pending.into_iter().enumerate() {
+            if failed.contains(&idx) {
+                continue;
+            }
+            message.digest_sent = true;
+            batch.set(
+                ValueClass::Report(message.id()),
+                Bincode::new(message).serialize(),
+            );
+            if batch.ops.len() >= 1000 {
+                if let Err(err) = self
+                    .core
+                    .storage
+                    .data
+                    .write(std::mem::take(&mut batch).build())
+                    .await
+                {
+                    tracing::error!(
+                        context = "quarantine",
+                        event = "error",
+                        "Failed to update quarantined messages: {}",
+                        err
+                    );
+                }
+            }
+        }
+        if !batch.is_empty() {
+            if let Err(err) = self.core.storage.data.write(batch.build()).await {
+                tracing::error!(
+                    context = "quarantine",
+                    event = "error",
+                    "Failed to update quarantined messages: {}",
+                    err
+                );
+            }
+        }
+    }
+}
+
+impl QuarantineReason {
+    pub fn new(source: QuarantineSource, reason: impl Into<String>) -> Self {
+        Self {
+            source,
+            reason: reason.into(),
+        }
+    }
+}
@@ -11,7 +11,10 @@ use common::{config::smtp::report::AggregateFrequency, listener::SessionStream};
 use mail_auth::{
     common::verify::VerifySignature,
     dmarc::{self, URI},
-    report::{AuthFailureType, IdentityAlignment, PolicyPublished, Record, Report, SPFDomainScope},
+    report::{
+        ActionDisposition, AuthFailureType, IdentityAlignment, PolicyOverride,
+        PolicyOverrideReason, PolicyPublished, Record, Report, SPFDomainScope,
+    },
     ArcOutput, AuthenticatedMessage, AuthenticationResults, DkimOutput, DkimResult, DmarcOutput,
     SpfResult,
 };
@@ -45,6 +48,7 @@ impl<T: SessionStream> Session<T> {
         dmarc_output: DmarcOutput,
         dkim_output: &[DkimOutput<'_>],
         arc_output: &Option<ArcOutput<'_>>,
+        trusted_sealer: Option<&str>,
     ) {
         let dmarc_record = dmarc_output.dmarc_record_cloned().unwrap();
         let config = &self.core.core.smtp.report.dmarc;
@@ -274,6 +278,14 @@ impl<T: SessionStream> Session<T> {
         if let Some(arc_output) = arc_output {
             report_record = report_record.with_arc_output(arc_output);
         }
+        if let Some(sealer) = trusted_sealer {
+            report_record = report_record
+                .with_action_disposition(ActionDisposition::None)
+                .with_policy_override_reason(
+                    PolicyOverrideReason::new(PolicyOverride::TrustedForwarder)
+                        .with_comment(format!("arc=pass as.d={sealer}")),
+                );
+        }
 
         // Submit DMARC report event
         self.core
@@ -121,7 +121,7 @@ impl SMTP {
         sign_config: &IfBlock,
         span: &tracing::Span,
         deliver_now: bool,
-    ) {
+    ) -> bool {
         // Build message
         let from_addr_lcase = from_addr.to_lowercase();
         let from_addr_domain = from_addr_lcase.domain_part().to_string();
@@ -191,7 +191,7 @@ impl SMTP {
         // Queue message
         message
             .queue(signature.as_deref(), &report, self, span)
-            .await;
+            .await
     }
 
     pub async fn schedule_report(&self, report: impl Into<Event>) {
@@ -389,6 +389,12 @@ impl SMTP {
             } else {
                 ScriptResult::Accept { modifications }
             }
+        } else if modifications
+            .iter()
+            .any(|m| matches!(m, ScriptModification::Quarantine { .. }))
+        {
+            // Quarantined messages are kept rather than discarded
+            ScriptResult::Accept { modifications }
         } else {
             ScriptResult::Discard
         }
@@ -25,6 +25,8 @@ struct Document<'x> {
     body: Vec<Cow<'x, str>>,
     attachments: Vec<Cow<'x, str>>,
     keywords: Vec<Cow<'x, str>>,
+    attachment_name: Vec<Cow<'x, str>>,
+    attachment_type: Vec<Cow<'x, str>>,
     header: Vec<Header<'x>>,
 }
 
@@ -141,6 +143,8 @@ impl<'x, T: Into<u8> + Display + Clone + std::fmt::Debug> From<FtsDocument<'x, T
                 Field::Body => document.body.push(part.text),
                 Field::Attachment => document.attachments.push(part.text),
                 Field::Keyword => document.keywords.push(part.text),
+                Field::AttachmentName => document.attachment_name.push(part.text),
+                Field::AttachmentType => document.attachment_type.push(part.text),
             }
         }
 
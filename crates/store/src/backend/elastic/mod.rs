@@ -147,6 +147,13 @@ impl ElasticSearchStore {
                       },
                       "keyword": {
                         "type": "keyword"
+                      },
+                      "attachment_name": {
+                        "analyzer": "default_analyzer",
+                        "type": "text"
+                      },
+                      "attachment_type": {
+                        "type": "keyword"
                       }
                     }
                   },
@@ -125,6 +125,8 @@ impl<T: Into<u8> + Display + Clone + std::fmt::Debug> Field<T> {
             Field::Body => "body".into(),
             Field::Attachment => "attachment".into(),
             Field::Keyword => "keyword".into(),
+            Field::AttachmentName => "attachment_name".into(),
+            Field::AttachmentType => "attachment_type".into(),
         }
     }
 }
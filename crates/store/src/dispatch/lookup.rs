@@ -263,6 +263,14 @@ impl LookupStore {
         }
     }
 
+    pub async fn reset_rate(&self, key: &[u8], rate: &Rate) -> crate::Result<()> {
+        let mut bucket = Vec::with_capacity(key.len() + U64_LEN);
+        bucket.extend_from_slice(key);
+        bucket.extend_from_slice((now() / rate.period.as_secs()).to_be_bytes().as_slice());
+
+        self.counter_delete(bucket).await
+    }
+
     pub async fn purge_lookup_store(&self) -> crate::Result<()> {
         match self {
             LookupStore::Store(store) => {
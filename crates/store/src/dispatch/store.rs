@@ -256,6 +256,39 @@ impl Store {
             })),
         )
         .await?;
+        self.delete_range(
+            ValueKey::from(ValueClass::Report(ReportClass::Quarantine {
+                id: 0,
+                expires: 0,
+            })),
+            ValueKey::from(ValueClass::Report(ReportClass::Quarantine {
+                id: u64::MAX,
+                expires: now,
+            })),
+        )
+        .await?;
+        self.delete_range(
+            ValueKey::from(ValueClass::Report(ReportClass::DsnDigest {
+                id: 0,
+                expires: 0,
+            })),
+            ValueKey::from(ValueClass::Report(ReportClass::DsnDigest {
+                id: u64::MAX,
+                expires: now,
+            })),
+        )
+        .await?;
+        self.delete_range(
+            ValueKey::from(ValueClass::Report(ReportClass::ListModeration {
+                id: 0,
+                expires: 0,
+            })),
+            ValueKey::from(ValueClass::Report(ReportClass::ListModeration {
+                id: u64::MAX,
+                expires: now,
+            })),
+        )
+        .await?;
 
         match self {
             #[cfg(feature = "sqlite")]
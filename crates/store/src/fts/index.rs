@@ -110,6 +110,10 @@ impl<T: Into<u8> + Display + Clone + std::fmt::Debug> From<Field<T>> for u8 {
             Field::Attachment => 1,
             Field::Keyword => 2,
             Field::Header(value) => 3 + value.into(),
+            // Placed below the stemmed flag and the postings terminator (0xFF),
+            // high enough to avoid clashing with header ids
+            Field::AttachmentName => 126,
+            Field::AttachmentType => 127,
         }
     }
 }
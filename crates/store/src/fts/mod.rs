@@ -18,6 +18,8 @@ pub enum Field<T: Into<u8> + Display + Clone + std::fmt::Debug> {
     Body,
     Attachment,
     Keyword,
+    AttachmentName,
+    AttachmentType,
 }
 
 #[derive(Debug, PartialEq, Eq)]
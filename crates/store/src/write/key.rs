@@ -319,6 +319,11 @@ impl<T: ResolveId> ValueClass<T> {
                     .write(6u8)
                     .write(principal_id.resolve_id(assigned_ids))
                     .write(has_member.resolve_id(assigned_ids)),
+                DirectoryClass::ListSubscriber { list_id, address } => serializer
+                    .write(7u8)
+                    .write(*list_id)
+                    .write(address.as_slice()),
+                DirectoryClass::ListSettings(list_id) => serializer.write(8u8).write(*list_id),
             },
             ValueClass::Queue(queue) => match queue {
                 QueueClass::Message(queue_id) => serializer.write(*queue_id),
@@ -364,6 +369,18 @@ impl<T: ResolveId> ValueClass<T> {
                 ReportClass::Arf { id, expires } => {
                     serializer.write(2u8).write(*expires).write(*id)
                 }
+                ReportClass::Quarantine { id, expires } => {
+                    serializer.write(3u8).write(*expires).write(*id)
+                }
+                ReportClass::DsnDigest { id, expires } => {
+                    serializer.write(4u8).write(*expires).write(*id)
+                }
+                ReportClass::ListModeration { id, expires } => {
+                    serializer.write(5u8).write(*expires).write(*id)
+                }
+                ReportClass::Snooze { id, expires } => {
+                    serializer.write(6u8).write(*expires).write(*id)
+                }
             },
             ValueClass::Any(any) => serializer.write(any.key.as_slice()),
         }
@@ -521,8 +538,11 @@ impl<T> ValueClass<T> {
                 DirectoryClass::NameToId(v)
                 | DirectoryClass::EmailToId(v)
                 | DirectoryClass::Domain(v) => v.len(),
-                DirectoryClass::Principal(_) | DirectoryClass::UsedQuota(_) => U32_LEN,
+                DirectoryClass::Principal(_)
+                | DirectoryClass::UsedQuota(_)
+                | DirectoryClass::ListSettings(_) => U32_LEN,
                 DirectoryClass::Members { .. } | DirectoryClass::MemberOf { .. } => U32_LEN * 2,
+                DirectoryClass::ListSubscriber { address, .. } => U32_LEN + address.len(),
             },
             ValueClass::Blob(op) => match op {
                 BlobOp::Reserve { .. } => BLOB_HASH_LEN + U64_LEN + U32_LEN + 1,
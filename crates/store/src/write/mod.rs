@@ -183,6 +183,8 @@ pub enum DirectoryClass<T> {
     Domain(Vec<u8>),
     Principal(T),
     UsedQuota(u32),
+    ListSubscriber { list_id: u32, address: Vec<u8> },
+    ListSettings(u32),
 }
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
@@ -202,6 +204,10 @@ pub enum ReportClass {
     Tls { id: u64, expires: u64 },
     Dmarc { id: u64, expires: u64 },
     Arf { id: u64, expires: u64 },
+    Quarantine { id: u64, expires: u64 },
+    DsnDigest { id: u64, expires: u64 },
+    ListModeration { id: u64, expires: u64 },
+    Snooze { id: u64, expires: u64 },
 }
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
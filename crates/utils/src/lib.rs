@@ -125,6 +125,10 @@ pub fn failed(message: &str) -> ! {
     std::process::exit(1);
 }
 
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
 pub async fn wait_for_shutdown(message: &str) {
     #[cfg(not(target_env = "msvc"))]
     {
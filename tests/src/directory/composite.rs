@@ -0,0 +1,214 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use directory::{Directories, Directory, QueryBy};
+use mail_send::Credentials;
+use store::Stores;
+use utils::config::Config;
+
+use crate::{store::TempDir, AssertConfig};
+
+const CONFIG: &str = r#"
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/composite.db"
+
+[directory."primary"]
+type = "memory"
+
+[[directory."primary".principals]]
+name = "john"
+class = "individual"
+description = "John Doe"
+secret = "12345"
+email = "john@example.org"
+
+[[directory."primary".principals]]
+name = "sales"
+class = "group"
+description = "Sales Team"
+email = "sales@example.org"
+
+[directory."legacy"]
+type = "memory"
+
+[[directory."legacy".principals]]
+name = "john"
+class = "individual"
+description = "John Doe (Legacy)"
+secret = "legacy-pass"
+quota = 1000
+email = ["john.doe@example.org", "john@example.org"]
+email-list = ["sales@example.org"]
+member-of = ["sales"]
+
+[[directory."legacy".principals]]
+name = "jane"
+class = "individual"
+description = "Jane Doe"
+secret = "abcde"
+email = "jane@example.org"
+email-list = ["sales@example.org"]
+
+[directory."first"]
+type = "composite"
+directories = ["primary", "legacy"]
+
+[directory."merged"]
+type = "composite"
+directories = ["primary", "legacy"]
+authentication = ["primary"]
+attributes.description = "legacy"
+merge.lookup = "merge"
+merge.vrfy = "merge"
+merge.expn = "merge"
+
+[directory."invalid"]
+type = "composite"
+directories = ["primary", "first"]
+
+[directory."a-invalid"]
+type = "composite"
+directories = ["legacy", "merged"]
+"#;
+
+#[tokio::test]
+async fn composite_directory() {
+    let temp_dir = TempDir::new("composite_tests", true);
+    let mut config =
+        Config::new(CONFIG.replace("{TMP}", &temp_dir.path.to_string_lossy())).unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let directories = Directories::parse(
+        &mut config,
+        &stores,
+        stores.stores.get("sqlite").unwrap().clone(),
+    )
+    .await;
+
+    // Composite directories cannot be nested, regardless of their order
+    for id in ["invalid", "a-invalid"] {
+        let key = format!("directory.{id}.directories");
+        assert!(!directories.directories.contains_key(id));
+        assert!(config.errors.contains_key(key.as_str()));
+        config.errors.remove(key.as_str());
+    }
+    config.assert_no_errors();
+
+    let first = directories.directories.get("first").unwrap();
+    let merged = directories.directories.get("merged").unwrap();
+
+    // Lookups return the principal from the first directory that has it
+    let john = first
+        .query(QueryBy::Name("john"), true)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(john.description(), Some("John Doe"));
+    assert_eq!(john.quota, 0);
+    assert_eq!(john.emails, vec!["john@example.org".to_string()]);
+    assert!(john.member_of.is_empty());
+    let jane = first
+        .query(QueryBy::Name("jane"), true)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(jane.description(), Some("Jane Doe"));
+    assert_eq!(
+        first
+            .query(QueryBy::Id(john.id), true)
+            .await
+            .unwrap()
+            .unwrap()
+            .name,
+        "john"
+    );
+    assert!(first
+        .query(QueryBy::Name("bill"), true)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Merged lookups fill in missing attributes and apply authoritative directories
+    let john = merged
+        .query(QueryBy::Name("john"), true)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(john.description(), Some("John Doe (Legacy)"));
+    assert_eq!(john.quota, 1000);
+    assert_eq!(
+        john.emails,
+        vec![
+            "john@example.org".to_string(),
+            "john.doe@example.org".to_string()
+        ]
+    );
+    assert_eq!(john.member_of.len(), 1);
+
+    // Authentication falls back to the next directory unless restricted
+    for (directory, user, secret, expect) in [
+        (first, "john", "12345", true),
+        (first, "john", "legacy-pass", true),
+        (first, "jane", "abcde", true),
+        (first, "jane", "wrong", false),
+        (merged, "john", "12345", true),
+        (merged, "john", "legacy-pass", false),
+        (merged, "jane", "abcde", false),
+    ] {
+        assert_eq!(
+            authenticate(directory, user, secret).await,
+            expect,
+            "{user}:{secret}"
+        );
+    }
+
+    // Recipient lookups
+    for directory in [first, merged] {
+        assert!(directory.rcpt("jane@example.org").await.unwrap());
+        assert!(directory.rcpt("john.doe@example.org").await.unwrap());
+        assert!(!directory.rcpt("bill@example.org").await.unwrap());
+    }
+    assert_eq!(
+        sorted(first.email_to_ids("john@example.org").await.unwrap()),
+        vec![john.id]
+    );
+
+    // VRFY and EXPN follow their merge rules
+    assert_eq!(
+        sorted(first.vrfy("john").await.unwrap()),
+        vec!["john@example.org".to_string()]
+    );
+    assert_eq!(
+        sorted(merged.vrfy("john").await.unwrap()),
+        vec![
+            "john.doe@example.org".to_string(),
+            "john@example.org".to_string()
+        ]
+    );
+    assert_eq!(
+        sorted(merged.expn("sales@example.org").await.unwrap()),
+        vec![
+            "jane@example.org".to_string(),
+            "john.doe@example.org".to_string()
+        ]
+    );
+}
+
+async fn authenticate(directory: &Directory, user: &str, secret: &str) -> bool {
+    directory
+        .query(
+            QueryBy::Credentials(&Credentials::new(user.to_string(), secret.to_string())),
+            false,
+        )
+        .await
+        .unwrap()
+        .is_some()
+}
+
+fn sorted<T: Ord>(mut items: Vec<T>) -> Vec<T> {
+    items.sort_unstable();
+    items
+}
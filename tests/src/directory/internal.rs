@@ -7,8 +7,12 @@
 use ahash::AHashSet;
 use directory::{
     backend::internal::{
-        lookup::DirectoryStore, manage::ManageDirectory, PrincipalField, PrincipalUpdate,
-        PrincipalValue,
+        lookup::DirectoryStore, manage::ManageDirectory, PrincipalAction, PrincipalField,
+        PrincipalUpdate, PrincipalValue, SpecialSecrets,
+    },
+    core::{
+        policy::{PasswordPolicy, PasswordStatus, PasswordViolation},
+        secret::ScramAlgorithm,
     },
     DirectoryError, ManagementError, Principal, QueryBy, Type,
 };
@@ -17,10 +21,27 @@ use mail_send::Credentials;
 use store::{
     roaring::RoaringBitmap,
     write::{BatchBuilder, BitmapClass, ValueClass},
-    BitmapKey, ValueKey,
+    BitmapKey, Stores, ValueKey,
 };
+use utils::config::Config;
+
+use crate::{directory::DirectoryTest, store::TempDir, AssertConfig};
+
+const POLICY_CONFIG: &str = r#"
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/policy.db"
 
-use crate::directory::DirectoryTest;
+[authentication.password]
+min-length = 10
+require.uppercase = true
+require.number = true
+require.special = true
+breached-list = "{TMP}/breached.txt"
+history = 3
+expiry = "90d"
+grace-period = "7d"
+"#;
 
 #[tokio::test]
 async fn internal_directory() {
@@ -408,6 +429,14 @@ async fn internal_directory() {
                             PrincipalValue::StringList(vec!["12345".to_string()])
                         ),
                         PrincipalUpdate::set(PrincipalField::Quota, PrincipalValue::Integer(1024)),
+                        PrincipalUpdate::set(
+                            PrincipalField::TimeZone,
+                            PrincipalValue::String("Europe/Madrid".to_string())
+                        ),
+                        PrincipalUpdate::set(
+                            PrincipalField::UndeleteRetention,
+                            PrincipalValue::Integer(86400)
+                        ),
                         PrincipalUpdate::set(
                             PrincipalField::Type,
                             PrincipalValue::String("superuser".to_string())
@@ -446,6 +475,8 @@ async fn internal_directory() {
                 quota: 1024,
                 typ: Type::Superuser,
                 member_of: vec!["list".to_string(), "sales".to_string()],
+                time_zone: Some("Europe/Madrid".to_string()),
+                undelete_retention: 86400,
             }
         );
         assert_eq!(store.get_account_id("john").await.unwrap(), None);
@@ -677,3 +708,203 @@ async fn internal_directory() {
         );
     }
 }
+
+#[tokio::test]
+async fn internal_directory_password_policy() {
+    let temp_dir = TempDir::new("password_policy_tests", true);
+    std::fs::write(
+        temp_dir.path.join("breached.txt"),
+        concat!(
+            "Summer2024!xyz\n",
+            // SHA-1 of 'Winter2024!xyz' in the 'HASH:count' format
+            "A64110F807F21099746B5A611F20E4854CA49A4A:3\n"
+        ),
+    )
+    .unwrap();
+    let mut config =
+        Config::new(POLICY_CONFIG.replace("{TMP}", &temp_dir.path.to_string_lossy())).unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let policy = PasswordPolicy::parse(&mut config);
+    config.assert_no_errors();
+    let store = stores.stores.get("sqlite").unwrap().clone();
+    store.destroy().await;
+
+    // Complexity rules are enforced on plain text passwords
+    for (password, violation) in [
+        ("Short1!", PasswordViolation::TooShort(10)),
+        ("lowercase-only-1", PasswordViolation::MissingUppercase),
+        ("No-Numbers-Here", PasswordViolation::MissingDigit),
+        ("NoSpecialChars123", PasswordViolation::MissingSpecial),
+        ("Summer2024!xyz", PasswordViolation::Breached),
+    ] {
+        assert_eq!(
+            store
+                .create_account_with_policy(
+                    Principal {
+                        name: "john".to_string(),
+                        secrets: vec![password.to_string()],
+                        ..Default::default()
+                    },
+                    vec![],
+                    Some(&policy),
+                )
+                .await,
+            Err(violation.into_error()),
+            "{password}"
+        );
+    }
+    assert_eq!(
+        policy.validate("Winter2024!xyz"),
+        Err(PasswordViolation::Breached)
+    );
+
+    // Only administrators can set hashed passwords while a policy is enabled
+    for (password, expected) in [
+        ("$6$rounds=5000$salt$hash", Err(PasswordViolation::Hashed)),
+        ("{SSHA}aGFzaA==", Err(PasswordViolation::Hashed)),
+        ("{PLAIN}Password-0001", Ok(())),
+        ("Password-0001", Ok(())),
+    ] {
+        assert_eq!(policy.validate_plain_text(password), expected, "{password}");
+    }
+    assert_eq!(
+        PasswordPolicy::default().validate_plain_text("$6$rounds=5000$salt$hash"),
+        Ok(())
+    );
+
+    // Valid passwords are accepted and the time of the change is recorded
+    let john_id = store
+        .create_account_with_policy(
+            Principal {
+                name: "john".to_string(),
+                secrets: vec!["Password-0001".to_string()],
+                ..Default::default()
+            },
+            vec![],
+            Some(&policy),
+        )
+        .await
+        .unwrap();
+    let john = store
+        .query(QueryBy::Id(john_id), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(john.password_changed_at().is_some());
+    assert_eq!(
+        policy.status(john.password_changed_at()),
+        PasswordStatus::Valid
+    );
+    assert!(john.verify_secret("Password-0001").await.unwrap());
+    assert!(!john.secrets.iter().any(|secret| secret == "Password-0001"));
+    assert!(john.secrets.iter().any(|secret| secret.starts_with("$6$")));
+
+    // SCRAM credentials are stored when the password is set
+    for algorithm in [ScramAlgorithm::Sha1, ScramAlgorithm::Sha256] {
+        let credentials = john.scram_credentials(algorithm, &[0u8; 16]).unwrap();
+        assert_ne!(credentials.salt, vec![0u8; 16]);
+        assert!(credentials.verify("Password-0001"));
+    }
+
+    // Passwords cannot be reused until they leave the history
+    for (password, expected) in [
+        ("Password-0002", Ok(())),
+        ("Password-0003", Ok(())),
+        ("Password-0001", Err(PasswordViolation::Reused.into_error())),
+        ("Password-0002", Err(PasswordViolation::Reused.into_error())),
+        ("Password-0004", Ok(())),
+        ("Password-0001", Ok(())),
+    ] {
+        assert_eq!(
+            store
+                .update_account_with_policy(
+                    QueryBy::Id(john_id),
+                    vec![
+                        PrincipalUpdate::remove_item(
+                            PrincipalField::Secrets,
+                            PrincipalValue::String(String::new()),
+                        ),
+                        PrincipalUpdate::add_item(
+                            PrincipalField::Secrets,
+                            PrincipalValue::String(password.to_string()),
+                        ),
+                    ],
+                    Some(&policy),
+                )
+                .await,
+            expected,
+            "{password}"
+        );
+    }
+    let john = store
+        .query(QueryBy::Id(john_id), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(john.verify_secret("Password-0001").await.unwrap());
+    assert!(!john.verify_secret("Password-0004").await.unwrap());
+    assert!(john
+        .scram_credentials(ScramAlgorithm::Sha256, &[])
+        .unwrap()
+        .verify("Password-0001"));
+    assert_eq!(
+        john.secrets
+            .iter()
+            .filter(|secret| secret.is_password_history())
+            .count(),
+        2
+    );
+    assert!(john
+        .secrets
+        .iter()
+        .filter(|secret| secret.is_password_history())
+        .all(|secret| secret.starts_with("$history$$6$")));
+
+    // Clients cannot tamper with the password metadata
+    store
+        .update_account_with_policy(
+            QueryBy::Id(john_id),
+            vec![PrincipalUpdate {
+                action: PrincipalAction::Set,
+                field: PrincipalField::Secrets,
+                value: PrincipalValue::StringList(vec![
+                    "Password-0001".to_string(),
+                    "$changed$0".to_string(),
+                ]),
+            }],
+            Some(&policy),
+        )
+        .await
+        .unwrap();
+    let john = store
+        .query(QueryBy::Id(john_id), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_ne!(john.password_changed_at(), Some(0));
+    assert_eq!(
+        john.secrets
+            .iter()
+            .filter(|secret| secret.is_password_history())
+            .count(),
+        2
+    );
+
+    // Expired passwords have a grace period
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    assert_eq!(policy.status(None), PasswordStatus::Valid);
+    assert_eq!(policy.status(Some(now - 89 * 86400)), PasswordStatus::Valid);
+    assert!(matches!(
+        policy.status(Some(now - 92 * 86400)),
+        PasswordStatus::Grace { expires_at } if expires_at > now
+    ));
+    assert_eq!(
+        policy.status(Some(now - 98 * 86400)),
+        PasswordStatus::Expired
+    );
+
+    temp_dir.delete();
+}
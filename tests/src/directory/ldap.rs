@@ -6,7 +6,11 @@
 
 use std::fmt::Debug;
 
-use directory::{backend::internal::manage::ManageDirectory, Principal, QueryBy, Type};
+use directory::{
+    backend::internal::{manage::ManageDirectory, PrincipalField, PrincipalUpdate, PrincipalValue},
+    core::policy::{PasswordPolicy, PasswordViolation},
+    DirectoryError, DirectoryInner, ManagementError, Principal, QueryBy, Type,
+};
 use mail_send::Credentials;
 
 use crate::directory::{map_account_ids, DirectoryTest};
@@ -222,6 +226,239 @@ async fn ldap_directory() {
     );
 }
 
+#[tokio::test]
+async fn ldap_directory_manage() {
+    // Obtain directory handle
+    let mut config = DirectoryTest::new("sqlite".into()).await;
+    let handle = config.directories.directories.remove("ldap").unwrap();
+    let base_store = config.stores.stores.get("sqlite").unwrap();
+    let ldap = match &handle.store {
+        DirectoryInner::Ldap(ldap) if ldap.is_writable() => ldap,
+        _ => panic!("Expected a writable LDAP directory"),
+    };
+
+    // Create a group and an individual
+    let group_id = ldap
+        .create_account(
+            Principal {
+                name: "ldap-managers".to_string(),
+                description: "LDAP Managers".to_string().into(),
+                typ: Type::Group,
+                ..Default::default()
+            },
+            vec![],
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        base_store.get_account_id("ldap-managers").await.unwrap(),
+        Some(group_id)
+    );
+    let account_id = ldap
+        .create_account(
+            Principal {
+                name: "ldap-manager".to_string(),
+                description: "LDAP Manager".to_string().into(),
+                typ: Type::Individual,
+                secrets: vec!["initial-secret".to_string()],
+                emails: vec!["ldap-manager@example.org".to_string()],
+                member_of: vec!["ldap-managers".to_string()],
+                ..Default::default()
+            },
+            vec![],
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        base_store.get_account_id("ldap-manager").await.unwrap(),
+        Some(account_id)
+    );
+    assert!(matches!(
+        ldap.create_account(
+            Principal {
+                name: "ldap-manager".to_string(),
+                ..Default::default()
+            },
+            vec![],
+            None,
+        )
+        .await,
+        Err(DirectoryError::Management(
+            ManagementError::AlreadyExists { .. }
+        ))
+    ));
+    let principal = handle
+        .query(QueryBy::Name("ldap-manager"), true)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(principal.description.as_deref(), Some("LDAP Manager"));
+    assert_eq!(
+        principal.emails,
+        vec!["ldap-manager@example.org".to_string()]
+    );
+    assert_eq!(
+        ldap.get_members("ldap-managers").await.unwrap(),
+        vec!["ldap-manager".to_string()]
+    );
+
+    // Listing
+    assert_eq!(
+        ldap.list_accounts("ldap-manage".into(), None)
+            .await
+            .unwrap(),
+        vec!["ldap-manager".to_string(), "ldap-managers".to_string()]
+    );
+    assert_eq!(
+        ldap.list_accounts("ldap-manage".into(), Type::Group.into())
+            .await
+            .unwrap(),
+        vec!["ldap-managers".to_string()]
+    );
+
+    // Update
+    ldap.update_account(
+        QueryBy::Id(account_id),
+        vec![
+            PrincipalUpdate::set(
+                PrincipalField::Description,
+                PrincipalValue::String("Updated Manager".to_string()),
+            ),
+            PrincipalUpdate::add_item(
+                PrincipalField::Emails,
+                PrincipalValue::String("ldap-alias@example.org".to_string()),
+            ),
+        ],
+        None,
+    )
+    .await
+    .unwrap();
+    let principal = handle
+        .query(QueryBy::Name("ldap-manager"), true)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(principal.description.as_deref(), Some("Updated Manager"));
+    assert!(principal
+        .emails
+        .contains(&"ldap-alias@example.org".to_string()));
+
+    // Group membership
+    ldap.update_account(
+        QueryBy::Name("ldap-manager"),
+        vec![PrincipalUpdate::remove_item(
+            PrincipalField::MemberOf,
+            PrincipalValue::String("ldap-managers".to_string()),
+        )],
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(ldap.get_members("ldap-managers").await.unwrap().is_empty());
+    ldap.update_account(
+        QueryBy::Name("ldap-managers"),
+        vec![PrincipalUpdate::add_item(
+            PrincipalField::Members,
+            PrincipalValue::String("ldap-manager".to_string()),
+        )],
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        ldap.get_members("ldap-managers").await.unwrap(),
+        vec!["ldap-manager".to_string()]
+    );
+
+    // Password policy
+    let policy = PasswordPolicy {
+        min_length: 10,
+        ..Default::default()
+    };
+    assert!(matches!(
+        ldap.update_account(
+            QueryBy::Id(account_id),
+            vec![PrincipalUpdate::add_item(
+                PrincipalField::Secrets,
+                PrincipalValue::String("short".to_string()),
+            )],
+            Some(&policy),
+        )
+        .await,
+        Err(DirectoryError::Management(ManagementError::PasswordPolicy(
+            PasswordViolation::TooShort(10)
+        )))
+    ));
+
+    // Password change
+    for (secret, expect) in [("initial-secret", true), ("changed-secret", false)] {
+        assert_eq!(
+            handle
+                .query(
+                    QueryBy::Credentials(&Credentials::Plain {
+                        username: "ldap-manager".to_string(),
+                        secret: secret.to_string()
+                    }),
+                    false
+                )
+                .await
+                .unwrap()
+                .is_some(),
+            expect
+        );
+    }
+    ldap.update_account(
+        QueryBy::Id(account_id),
+        vec![PrincipalUpdate::add_item(
+            PrincipalField::Secrets,
+            PrincipalValue::String("changed-secret".to_string()),
+        )],
+        Some(&policy),
+    )
+    .await
+    .unwrap();
+    for (secret, expect) in [("initial-secret", false), ("changed-secret", true)] {
+        assert_eq!(
+            handle
+                .query(
+                    QueryBy::Credentials(&Credentials::Plain {
+                        username: "ldap-manager".to_string(),
+                        secret: secret.to_string()
+                    }),
+                    false
+                )
+                .await
+                .unwrap()
+                .is_some(),
+            expect
+        );
+    }
+
+    // Delete
+    ldap.delete_account(QueryBy::Name("ldap-manager"))
+        .await
+        .unwrap();
+    ldap.delete_account(QueryBy::Name("ldap-managers"))
+        .await
+        .unwrap();
+    assert!(handle
+        .query(QueryBy::Name("ldap-manager"), false)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(ldap
+        .list_accounts("ldap-manage".into(), None)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(matches!(
+        ldap.delete_account(QueryBy::Name("ldap-manager")).await,
+        Err(DirectoryError::Management(ManagementError::NotFound(_)))
+    ));
+}
+
 fn compare_sorted<T: Eq + Debug>(v1: Vec<T>, v2: Vec<T>) {
     for val in v1.iter() {
         assert!(v2.contains(val), "{v1:?} != {v2:?}");
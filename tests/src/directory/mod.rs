@@ -4,9 +4,12 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod composite;
 pub mod imap;
 pub mod internal;
 pub mod ldap;
+pub mod oidc;
+pub mod security;
 pub mod smtp;
 pub mod sql;
 
@@ -163,6 +166,11 @@ email-alias = "givenName"
 quota = "diskQuota"
 class = "objectClass"
 
+[directory."ldap".write]
+enable = true
+base-dn = "ou=users,dc=example,dc=org"
+password-modify = true
+
 ##############################################################################
 
 [directory."imap"]
@@ -0,0 +1,487 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    time::SystemTime,
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use common::{
+    auth::oidc::OidcValidator, config::server::ServerProtocol, AuthResult, Core, Ipc,
+    IPC_CHANNEL_BUFFER,
+};
+use directory::{backend::internal::manage::ManageDirectory, Principal, QueryBy};
+use mail_send::Credentials;
+use ring::{
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+};
+use serde_json::{json, Value};
+use store::Stores;
+use tokio::sync::mpsc;
+use utils::config::Config;
+
+use crate::store::TempDir;
+
+const CONFIG: &str = r#"
+[storage]
+data = "sqlite"
+lookup = "sqlite"
+blob = "sqlite"
+fts = "sqlite"
+directory = "internal"
+
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/oidc.db"
+
+[directory."internal"]
+type = "internal"
+store = "sqlite"
+
+[authentication.oidc]
+jwks.path = "{TMP}/jwks.json"
+issuer = "https://idp.example.org/realms/mail"
+audience = ["stalwart", "mail"]
+auto-provision = true
+"#;
+
+const ISSUER: &str = "https://idp.example.org/realms/mail";
+
+enum SigningKey {
+    Ecdsa(EcdsaKeyPair),
+    Ed25519(Ed25519KeyPair),
+}
+
+#[tokio::test]
+async fn oidc_directory() {
+    let temp_dir = TempDir::new("oidc_tests", true);
+    let rng = SystemRandom::new();
+
+    // Generate signing keys and publish them as a JWKS file
+    let ec_key = EcdsaKeyPair::from_pkcs8(
+        &ECDSA_P256_SHA256_FIXED_SIGNING,
+        EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+            .unwrap()
+            .as_ref(),
+        &rng,
+    )
+    .unwrap();
+    let ed_key =
+        Ed25519KeyPair::from_pkcs8(Ed25519KeyPair::generate_pkcs8(&rng).unwrap().as_ref()).unwrap();
+    let ec_point = ec_key.public_key().as_ref();
+    std::fs::write(
+        temp_dir.path.join("jwks.json"),
+        json!({
+            "keys": [
+                {
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "use": "sig",
+                    "kid": "ec-key",
+                    "x": URL_SAFE_NO_PAD.encode(&ec_point[1..33]),
+                    "y": URL_SAFE_NO_PAD.encode(&ec_point[33..]),
+                },
+                {
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "kid": "ed-key",
+                    "x": URL_SAFE_NO_PAD.encode(ed_key.public_key().as_ref()),
+                },
+                {
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "use": "enc",
+                    "kid": "enc-key",
+                    "x": URL_SAFE_NO_PAD.encode(&ec_point[1..33]),
+                    "y": URL_SAFE_NO_PAD.encode(&ec_point[33..]),
+                }
+            ]
+        })
+        .to_string(),
+    )
+    .unwrap();
+    let ec_key = SigningKey::Ecdsa(ec_key);
+    let ed_key = SigningKey::Ed25519(ed_key);
+
+    // Build core
+    let mut config =
+        Config::new(CONFIG.replace("{TMP}", &temp_dir.path.to_string_lossy())).unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    assert!(core.jmap.oidc.is_some());
+    let (delivery_tx, _delivery_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
+    let (webhook_tx, _webhook_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
+    let ipc = Ipc {
+        delivery_tx,
+        webhook_tx,
+    };
+    let store = core.storage.data.clone();
+    store.create_domain("example.org").await.unwrap();
+    store
+        .create_account(
+            Principal {
+                name: "john".to_string(),
+                emails: vec!["john@example.org".to_string()],
+                ..Default::default()
+            },
+            vec![],
+        )
+        .await
+        .unwrap();
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let john = json!({
+        "iss": ISSUER,
+        "aud": ["account", "stalwart"],
+        "exp": now + 300,
+        "nbf": now - 10,
+        "sub": "John",
+        "preferred_username": "jdoe",
+        "email": "john@example.org",
+        "email_verified": true,
+    });
+    let john_token = sign(&ec_key, "ES256", Some("ec-key"), &john);
+
+    // Valid tokens are accepted in every supported credential format
+    for (idx, credentials) in [
+        Credentials::OAuthBearer {
+            token: john_token.clone(),
+        },
+        Credentials::OAuthBearer {
+            token: format!(
+                "n,a=john@example.org,\x01host=localhost\x01auth=Bearer {john_token}\x01\x01"
+            ),
+        },
+        Credentials::OAuthBearer {
+            token: format!("n,,\x01auth=Bearer {john_token}\x01\x01"),
+        },
+        Credentials::XOauth2 {
+            username: "john".to_string(),
+            secret: format!("Bearer {john_token}"),
+        },
+        Credentials::OAuthBearer {
+            token: sign(&ed_key, "EdDSA", Some("ed-key"), &john),
+        },
+        Credentials::OAuthBearer {
+            token: sign(&ed_key, "EdDSA", None, &john),
+        },
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        assert_eq!(
+            authenticate(&core, &ipc, &credentials).await,
+            Some("john".to_string()),
+            "credentials #{idx}"
+        );
+    }
+
+    // Invalid tokens are rejected
+    for (test, token) in [
+        (
+            "authorization identity mismatch",
+            format!("n,a=jane@example.org,\x01auth=Bearer {john_token}\x01\x01"),
+        ),
+        (
+            "expired",
+            sign(
+                &ec_key,
+                "ES256",
+                Some("ec-key"),
+                &with_claim(&john, "exp", json!(now - 3600)),
+            ),
+        ),
+        (
+            "not yet valid",
+            sign(
+                &ec_key,
+                "ES256",
+                Some("ec-key"),
+                &with_claim(&john, "nbf", json!(now + 3600)),
+            ),
+        ),
+        (
+            "missing expiration",
+            sign(
+                &ec_key,
+                "ES256",
+                Some("ec-key"),
+                &with_claim(&john, "exp", Value::Null),
+            ),
+        ),
+        (
+            "untrusted issuer",
+            sign(
+                &ec_key,
+                "ES256",
+                Some("ec-key"),
+                &with_claim(&john, "iss", json!("https://evil.example.net")),
+            ),
+        ),
+        (
+            "wrong audience",
+            sign(
+                &ec_key,
+                "ES256",
+                Some("ec-key"),
+                &with_claim(&john, "aud", json!("other-service")),
+            ),
+        ),
+        ("wrong key", sign(&ed_key, "EdDSA", Some("ec-key"), &john)),
+        (
+            "encryption key",
+            sign(&ec_key, "ES256", Some("enc-key"), &john),
+        ),
+        (
+            "symmetric algorithm",
+            sign(&ec_key, "HS256", Some("ec-key"), &john),
+        ),
+        (
+            "unsigned",
+            format!(
+                "{}.{}.",
+                URL_SAFE_NO_PAD.encode(json!({"alg": "none"}).to_string()),
+                URL_SAFE_NO_PAD.encode(john.to_string())
+            ),
+        ),
+        ("tampered", {
+            let (message, signature) = john_token.rsplit_once('.').unwrap();
+            let (header, _) = message.split_once('.').unwrap();
+            format!(
+                "{header}.{}.{signature}",
+                URL_SAFE_NO_PAD.encode(with_claim(&john, "sub", json!("admin")).to_string())
+            )
+        }),
+        ("garbage", "not-a-token".to_string()),
+    ] {
+        assert_eq!(
+            authenticate(&core, &ipc, &Credentials::OAuthBearer { token }).await,
+            None,
+            "{test}"
+        );
+    }
+    assert_eq!(
+        authenticate(
+            &core,
+            &ipc,
+            &Credentials::XOauth2 {
+                username: "jane".to_string(),
+                secret: format!("Bearer {john_token}"),
+            }
+        )
+        .await,
+        None
+    );
+
+    // Principals are never matched by e-mail address
+    assert_eq!(
+        authenticate(
+            &core,
+            &ipc,
+            &Credentials::OAuthBearer {
+                token: sign(
+                    &ec_key,
+                    "ES256",
+                    Some("ec-key"),
+                    &with_claim(&john, "sub", json!("jdoe")),
+                ),
+            },
+        )
+        .await,
+        None
+    );
+
+    // Unverified addresses are not added to new accounts
+    assert_eq!(
+        authenticate(
+            &core,
+            &ipc,
+            &Credentials::OAuthBearer {
+                token: sign(
+                    &ec_key,
+                    "ES256",
+                    Some("ec-key"),
+                    &with_claim(
+                        &with_claim(&john, "sub", json!("mallory")),
+                        "email_verified",
+                        json!(false)
+                    ),
+                ),
+            },
+        )
+        .await,
+        Some("mallory".to_string())
+    );
+    assert!(core
+        .storage
+        .directory
+        .query(QueryBy::Name("mallory"), false)
+        .await
+        .unwrap()
+        .unwrap()
+        .emails
+        .is_empty());
+
+    // New users are provisioned on first login
+    let alice = json!({
+        "iss": ISSUER,
+        "aud": "mail",
+        "exp": now + 300,
+        "sub": "alice",
+        "email": "Alice@example.org",
+        "email_verified": "true",
+        "name": "Alice Smith",
+    });
+    assert_eq!(
+        authenticate(
+            &core,
+            &ipc,
+            &Credentials::OAuthBearer {
+                token: sign(&ec_key, "ES256", Some("ec-key"), &alice),
+            },
+        )
+        .await,
+        Some("alice".to_string())
+    );
+    let principal = core
+        .storage
+        .directory
+        .query(QueryBy::Name("alice"), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(principal.description.as_deref(), Some("Alice Smith"));
+    assert_eq!(principal.emails, vec!["alice@example.org".to_string()]);
+
+    // Addresses outside local domains are not added to provisioned accounts
+    assert_eq!(
+        authenticate(
+            &core,
+            &ipc,
+            &Credentials::OAuthBearer {
+                token: sign(
+                    &ec_key,
+                    "ES256",
+                    Some("ec-key"),
+                    &with_claim(
+                        &with_claim(&alice, "sub", json!("bob")),
+                        "email",
+                        json!("bob@example.net")
+                    ),
+                ),
+            },
+        )
+        .await,
+        Some("bob".to_string())
+    );
+    assert!(core
+        .storage
+        .directory
+        .query(QueryBy::Name("bob"), false)
+        .await
+        .unwrap()
+        .unwrap()
+        .emails
+        .is_empty());
+
+    // Passwords keep working
+    store
+        .create_account(
+            Principal {
+                name: "jane".to_string(),
+                secrets: vec!["secret".to_string()],
+                ..Default::default()
+            },
+            vec![],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        authenticate(
+            &core,
+            &ipc,
+            &Credentials::Plain {
+                username: "jane".to_string(),
+                secret: "secret".to_string(),
+            }
+        )
+        .await,
+        Some("jane".to_string())
+    );
+
+    // JWKS validation requires an issuer and an audience
+    for (key, config) in [
+        (
+            "authentication.oidc.issuer",
+            "[authentication.oidc]\njwks.path = \"jwks.json\"\naudience = \"mail\"\n",
+        ),
+        (
+            "authentication.oidc.audience",
+            "[authentication.oidc]\njwks.path = \"jwks.json\"\nissuer = \"https://idp.example.org\"\n",
+        ),
+    ] {
+        let mut config = Config::new(config).unwrap();
+        assert!(OidcValidator::parse(&mut config).is_none(), "{key}");
+        assert!(config.errors.contains_key(key), "{key}");
+    }
+
+    temp_dir.delete();
+}
+
+async fn authenticate(core: &Core, ipc: &Ipc, credentials: &Credentials<String>) -> Option<String> {
+    match core
+        .authenticate(
+            &core.storage.directory,
+            ipc,
+            credentials,
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            ServerProtocol::Imap,
+            false,
+        )
+        .await
+        .unwrap()
+    {
+        AuthResult::Success(principal) => Some(principal.name),
+        AuthResult::Failure(_) => None,
+    }
+}
+
+fn sign(key: &SigningKey, alg: &str, kid: Option<&str>, claims: &Value) -> String {
+    let mut header = json!({"alg": alg, "typ": "JWT"});
+    if let Some(kid) = kid {
+        header["kid"] = json!(kid);
+    }
+    let message = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(header.to_string()),
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    let signature = match key {
+        SigningKey::Ecdsa(key) => key
+            .sign(&SystemRandom::new(), message.as_bytes())
+            .unwrap()
+            .as_ref()
+            .to_vec(),
+        SigningKey::Ed25519(key) => key.sign(message.as_bytes()).as_ref().to_vec(),
+    };
+
+    format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature))
+}
+
+fn with_claim(claims: &Value, name: &str, value: Value) -> Value {
+    let mut claims = claims.clone();
+    if value.is_null() {
+        claims.as_object_mut().unwrap().remove(name);
+    } else {
+        claims[name] = value;
+    }
+    claims
+}
@@ -0,0 +1,548 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{net::IpAddr, time::Duration};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use common::{
+    auth::{
+        security::{AccountSecurity, GeoIpLocation},
+        webauthn::{
+            AssertionCredential, AssertionResponse, RegistrationCredential, RegistrationResponse,
+            WebAuthnConfig, WebAuthnCredential,
+        },
+    },
+    config::server::ServerProtocol,
+    AuthFailureReason, AuthResult, Core, DeliveryEvent, Ipc, IPC_CHANNEL_BUFFER,
+};
+use directory::{backend::internal::manage::ManageDirectory, Principal};
+use mail_send::Credentials;
+use ring::{
+    digest::{digest, SHA256},
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING},
+};
+use serde_json::json;
+use store::Stores;
+use tokio::sync::mpsc;
+use utils::config::Config;
+
+use crate::{store::TempDir, AssertConfig};
+
+const CONFIG: &str = r#"
+[authentication.lockout]
+rate = "5/1m"
+duration = "30m"
+
+[authentication.login-history]
+size = 10
+
+[authentication.geoip]
+country = "{TMP}/country.csv"
+asn = "{TMP}/asn.csv"
+"#;
+
+const CORE_CONFIG: &str = r#"
+[storage]
+data = "sqlite"
+lookup = "sqlite"
+blob = "sqlite"
+fts = "sqlite"
+directory = "internal"
+
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/security.db"
+
+[directory."internal"]
+type = "internal"
+store = "sqlite"
+
+[authentication.lockout]
+rate = "3/1m"
+duration = "2s"
+
+[authentication.login-history]
+size = 3
+
+[authentication.geoip]
+country = "{TMP}/country.csv"
+asn = "{TMP}/asn.csv"
+"#;
+
+const COUNTRY_DB: &str = r#"ip_range_start,ip_range_end,country_code
+1.0.0.0,1.0.0.255,AU
+8.8.8.0,8.8.8.255,us
+192.0.2.0/24,FR
+2001:db8::/32,DE
+"#;
+
+const ASN_DB: &str = r#"network,autonomous_system_number,autonomous_system_organization
+8.8.8.0/24,15169,"Google LLC"
+192.0.2.0,192.0.2.127,AS64500,Example Networks
+192.0.2.128/25,64501,
+"#;
+
+#[test]
+fn account_security_config() {
+    let temp_dir = TempDir::new("account_security_tests", true);
+    std::fs::write(temp_dir.path.join("country.csv"), COUNTRY_DB).unwrap();
+    std::fs::write(temp_dir.path.join("asn.csv"), ASN_DB).unwrap();
+    let mut config =
+        Config::new(CONFIG.replace("{TMP}", &temp_dir.path.to_string_lossy())).unwrap();
+    let security = AccountSecurity::parse(&mut config);
+    config.assert_no_errors();
+
+    assert_eq!(security.lockout_rate.as_ref().unwrap().requests, 5);
+    assert_eq!(security.lockout_duration, Duration::from_secs(30 * 60));
+    assert_eq!(security.history_size, 10);
+    assert!(security.notify_lockout);
+    assert!(security.notify_new_location);
+    assert_eq!(security.from_address, "postmaster@localhost");
+
+    for (ip, country, asn, org) in [
+        ("1.0.0.1", Some("AU"), None, None),
+        ("1.0.1.1", None, None, None),
+        ("8.8.8.8", Some("US"), Some(15169), Some("Google LLC")),
+        (
+            "192.0.2.1",
+            Some("FR"),
+            Some(64500),
+            Some("Example Networks"),
+        ),
+        ("192.0.2.200", Some("FR"), Some(64501), None),
+        ("2001:db8::1", Some("DE"), None, None),
+        ("2001:db9::1", None, None, None),
+        (
+            "::ffff:8.8.8.8",
+            Some("US"),
+            Some(15169),
+            Some("Google LLC"),
+        ),
+    ] {
+        assert_eq!(
+            security.geoip.lookup(&ip.parse::<IpAddr>().unwrap()),
+            GeoIpLocation {
+                country: country.map(|c| c.to_string()),
+                asn,
+                as_org: org.map(|o| o.to_string()),
+            },
+            "{ip}"
+        );
+    }
+
+    // Missing databases are reported
+    let mut config = Config::new(CONFIG.replace("{TMP}", "/missing")).unwrap();
+    let security = AccountSecurity::parse(&mut config);
+    assert!(security.geoip.is_empty());
+    assert!(config.errors.contains_key("authentication.geoip.country"));
+
+    temp_dir.delete();
+}
+
+#[tokio::test]
+async fn account_lockout_and_login_history() {
+    let temp_dir = TempDir::new("account_lockout_tests", true);
+    std::fs::write(temp_dir.path.join("country.csv"), COUNTRY_DB).unwrap();
+    std::fs::write(temp_dir.path.join("asn.csv"), ASN_DB).unwrap();
+    let mut config =
+        Config::new(CORE_CONFIG.replace("{TMP}", &temp_dir.path.to_string_lossy())).unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    config.assert_no_errors();
+    let (delivery_tx, delivery_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
+    let (webhook_tx, _webhook_rx) = mpsc::channel(IPC_CHANNEL_BUFFER);
+    let mut test = LoginTest {
+        core,
+        ipc: Ipc {
+            delivery_tx,
+            webhook_tx,
+        },
+        delivery_rx,
+    };
+    let store = test.core.storage.data.clone();
+    store.create_domain("example.org").await.unwrap();
+    let mut account_ids = Vec::new();
+    for name in ["john", "jane"] {
+        account_ids.push(
+            store
+                .create_account(
+                    Principal {
+                        name: name.to_string(),
+                        secrets: vec!["secret".to_string()],
+                        emails: vec![format!("{name}@example.org")],
+                        ..Default::default()
+                    },
+                    vec![],
+                )
+                .await
+                .unwrap(),
+        );
+    }
+    let jane_id = account_ids[1];
+
+    // Successful logins reset the failed attempts counter
+    for _ in 0..3 {
+        assert!(matches!(
+            test.login("john", "wrong", "192.0.2.1", ServerProtocol::Imap)
+                .await,
+            Err(AuthFailureReason::InvalidCredentials)
+        ));
+    }
+    test.assert_login("john", "192.0.2.1", ServerProtocol::Imap)
+        .await;
+    for _ in 0..3 {
+        assert!(matches!(
+            test.login("john", "wrong", "192.0.2.1", ServerProtocol::Imap)
+                .await,
+            Err(AuthFailureReason::InvalidCredentials)
+        ));
+    }
+    assert_eq!(test.notices(), Vec::<String>::new());
+
+    // Accounts are locked after exceeding the rate, even with the right password
+    assert!(matches!(
+        test.login("john", "wrong", "192.0.2.1", ServerProtocol::Imap)
+            .await,
+        Err(AuthFailureReason::AccountLocked)
+    ));
+    assert!(matches!(
+        test.login("JOHN", "secret", "8.8.8.8", ServerProtocol::Pop3)
+            .await,
+        Err(AuthFailureReason::AccountLocked)
+    ));
+    assert_eq!(test.notices(), vec!["john@example.org".to_string()]);
+
+    // Accounts are unlocked once the lockout duration elapses
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    test.assert_login("john", "192.0.2.1", ServerProtocol::Imap)
+        .await;
+
+    // Logins are recorded at most once per address and protocol within an hour,
+    // the first login is never considered suspicious
+    for _ in 0..2 {
+        test.assert_login("jane", "192.0.2.1", ServerProtocol::Imap)
+            .await;
+    }
+    assert_eq!(test.core.login_history(jane_id).await.unwrap().len(), 1);
+    assert_eq!(test.notices(), Vec::<String>::new());
+
+    // Logins from a new network or country are reported
+    for ip in ["192.0.2.200", "8.8.8.8"] {
+        test.assert_login("jane", ip, ServerProtocol::Imap).await;
+        assert_eq!(test.notices(), vec!["jane@example.org".to_string()], "{ip}");
+    }
+
+    // Known locations are not reported and the history is truncated
+    test.assert_login("jane", "192.0.2.1", ServerProtocol::Pop3)
+        .await;
+    assert_eq!(test.notices(), Vec::<String>::new());
+    assert_eq!(
+        test.core
+            .login_history(jane_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| (
+                entry.remote_ip.to_string(),
+                entry.protocol,
+                entry.location.country,
+                entry.location.asn
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "192.0.2.1".to_string(),
+                ServerProtocol::Pop3,
+                Some("FR".to_string()),
+                Some(64500)
+            ),
+            (
+                "8.8.8.8".to_string(),
+                ServerProtocol::Imap,
+                Some("US".to_string()),
+                Some(15169)
+            ),
+            (
+                "192.0.2.200".to_string(),
+                ServerProtocol::Imap,
+                Some("FR".to_string()),
+                Some(64501)
+            ),
+        ]
+    );
+
+    temp_dir.delete();
+}
+
+struct LoginTest {
+    core: Core,
+    ipc: Ipc,
+    delivery_rx: mpsc::Receiver<DeliveryEvent>,
+}
+
+impl LoginTest {
+    async fn login(
+        &self,
+        username: &str,
+        secret: &str,
+        remote_ip: &str,
+        protocol: ServerProtocol,
+    ) -> Result<String, AuthFailureReason> {
+        match self
+            .core
+            .authenticate(
+                &self.core.storage.directory,
+                &self.ipc,
+                &Credentials::Plain {
+                    username: username.to_string(),
+                    secret: secret.to_string(),
+                },
+                remote_ip.parse().unwrap(),
+                protocol,
+                false,
+            )
+            .await
+            .unwrap()
+        {
+            AuthResult::Success(principal) => Ok(principal.name),
+            AuthResult::Failure(reason) => Err(reason),
+        }
+    }
+
+    async fn assert_login(&self, username: &str, remote_ip: &str, protocol: ServerProtocol) {
+        assert_eq!(
+            self.login(username, "secret", remote_ip, protocol)
+                .await
+                .ok()
+                .as_deref(),
+            Some(username),
+            "{username} from {remote_ip}"
+        );
+    }
+
+    // Returns the recipients of the security notices sent so far
+    fn notices(&mut self) -> Vec<String> {
+        let mut recipients = Vec::new();
+        while let Ok(DeliveryEvent::Ingest { message, .. }) = self.delivery_rx.try_recv() {
+            recipients.extend(message.recipients);
+        }
+        recipients
+    }
+}
+
+#[test]
+fn webauthn_ceremonies() {
+    let mut config = Config::new(
+        r#"
+[authentication.webauthn]
+rp-id = "example.org"
+"#,
+    )
+    .unwrap();
+    let webauthn = WebAuthnConfig::parse(&mut config);
+    config.assert_no_errors();
+
+    // Create a P-256 authenticator
+    let rng = SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+    let key_pair =
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+    let point = key_pair.public_key().as_ref();
+    let mut cose_key = vec![0xa5, 0x01, 0x02, 0x03, 0x26, 0x20, 0x01, 0x21, 0x58, 0x20];
+    cose_key.extend_from_slice(&point[1..33]);
+    cose_key.extend_from_slice(&[0x22, 0x58, 0x20]);
+    cose_key.extend_from_slice(&point[33..65]);
+    let credential_id = b"test-credential-id".to_vec();
+    let rp_id_hash = digest(&SHA256, b"example.org");
+
+    // Build attestation object using the 'none' format
+    let mut auth_data = rp_id_hash.as_ref().to_vec();
+    auth_data.push(0x41);
+    auth_data.extend_from_slice(&0u32.to_be_bytes());
+    auth_data.extend_from_slice(&[0u8; 16]);
+    auth_data.extend_from_slice(&(credential_id.len() as u16).to_be_bytes());
+    auth_data.extend_from_slice(&credential_id);
+    auth_data.extend_from_slice(&cose_key);
+    let mut attestation = vec![0xa3, 0x63];
+    attestation.extend_from_slice(b"fmt");
+    attestation.push(0x64);
+    attestation.extend_from_slice(b"none");
+    attestation.push(0x67);
+    attestation.extend_from_slice(b"attStmt");
+    attestation.extend_from_slice(&[0xa0, 0x68]);
+    attestation.extend_from_slice(b"authData");
+    attestation.push(0x59);
+    attestation.extend_from_slice(&(auth_data.len() as u16).to_be_bytes());
+    attestation.extend_from_slice(&auth_data);
+
+    // Registration
+    let principal = Principal {
+        id: 1,
+        name: "jdoe".to_string(),
+        ..Default::default()
+    };
+    let challenge = b"registration-challenge".to_vec();
+    let options = webauthn.creation_options(&challenge, &principal, &[]);
+    assert_eq!(options["publicKey"]["rp"]["id"], "example.org");
+    assert_eq!(
+        options["publicKey"]["challenge"],
+        URL_SAFE_NO_PAD.encode(&challenge)
+    );
+    let registration = |typ: &str, origin: &str| RegistrationCredential {
+        id: URL_SAFE_NO_PAD.encode(&credential_id),
+        response: RegistrationResponse {
+            client_data_json: client_data(typ, &challenge, origin),
+            attestation_object: URL_SAFE_NO_PAD.encode(&attestation),
+        },
+    };
+    for (typ, origin, name, expected) in [
+        (
+            "webauthn.get",
+            "https://mail.example.org",
+            "key",
+            "Invalid ceremony type",
+        ),
+        (
+            "webauthn.create",
+            "https://example.org.evil.com",
+            "key",
+            "Invalid origin",
+        ),
+        (
+            "webauthn.create",
+            "http://mail.example.org",
+            "key",
+            "Invalid origin",
+        ),
+        (
+            "webauthn.create",
+            "https://mail.example.org",
+            "my$key",
+            "Invalid credential name",
+        ),
+    ] {
+        assert_eq!(
+            webauthn.verify_registration(
+                &challenge,
+                name.to_string(),
+                &registration(typ, origin),
+                &[]
+            ),
+            Err(expected),
+            "{typ} {origin} {name}"
+        );
+    }
+    assert_eq!(
+        webauthn.verify_registration(
+            b"other-challenge",
+            "key".to_string(),
+            &registration("webauthn.create", "https://mail.example.org"),
+            &[]
+        ),
+        Err("Invalid challenge")
+    );
+    let credential = webauthn
+        .verify_registration(
+            &challenge,
+            "key".to_string(),
+            &registration("webauthn.create", "https://mail.example.org:8080"),
+            &[],
+        )
+        .unwrap();
+    assert_eq!(credential.id, credential_id);
+    assert_eq!(credential.public_key, cose_key);
+    assert_eq!(credential.sign_count, 0);
+    assert_eq!(
+        webauthn.verify_registration(
+            &challenge,
+            "other".to_string(),
+            &registration("webauthn.create", "https://mail.example.org"),
+            &[credential.clone()]
+        ),
+        Err("Credential is already registered")
+    );
+
+    // Credentials are stored as principal secrets
+    let principal = Principal {
+        secrets: vec!["secret".to_string(), credential.to_secret()],
+        ..principal
+    };
+    assert_eq!(
+        WebAuthnCredential::from_principal(&principal),
+        vec![credential.clone()]
+    );
+
+    // Assertion
+    let challenge = b"assertion-challenge".to_vec();
+    let options = webauthn.request_options(&challenge, &[credential.clone()]);
+    assert_eq!(
+        options["publicKey"]["allowCredentials"][0]["id"],
+        URL_SAFE_NO_PAD.encode(&credential_id)
+    );
+    let assertion = |counter: u32, tamper: bool| {
+        let client_data_json = client_data("webauthn.get", &challenge, "https://example.org");
+        let mut auth_data = rp_id_hash.as_ref().to_vec();
+        auth_data.push(0x01);
+        auth_data.extend_from_slice(&counter.to_be_bytes());
+        let mut message = auth_data.clone();
+        message.extend_from_slice(
+            digest(&SHA256, &URL_SAFE_NO_PAD.decode(&client_data_json).unwrap()).as_ref(),
+        );
+        let signature = key_pair.sign(&rng, &message).unwrap().as_ref().to_vec();
+        if tamper {
+            auth_data[33] ^= 0xff;
+        }
+        AssertionCredential {
+            id: URL_SAFE_NO_PAD.encode(&credential_id),
+            response: AssertionResponse {
+                client_data_json,
+                authenticator_data: URL_SAFE_NO_PAD.encode(&auth_data),
+                signature: URL_SAFE_NO_PAD.encode(signature),
+            },
+        }
+    };
+    assert_eq!(
+        webauthn.verify_assertion(&challenge, &assertion(1, true), &[credential.clone()]),
+        Err("Invalid signature")
+    );
+    assert_eq!(
+        webauthn.verify_assertion(
+            b"other-challenge",
+            &assertion(1, false),
+            &[credential.clone()]
+        ),
+        Err("Invalid challenge")
+    );
+    let updated = webauthn
+        .verify_assertion(&challenge, &assertion(5, false), &[credential.clone()])
+        .unwrap();
+    assert_eq!(updated.sign_count, 5);
+
+    // Signature counters must increase
+    assert_eq!(
+        webauthn.verify_assertion(&challenge, &assertion(5, false), &[updated.clone()]),
+        Err("Invalid signature counter")
+    );
+    assert_eq!(
+        webauthn
+            .verify_assertion(&challenge, &assertion(6, false), &[updated])
+            .unwrap()
+            .sign_count,
+        6
+    );
+}
+
+fn client_data(typ: &str, challenge: &[u8], origin: &str) -> String {
+    URL_SAFE_NO_PAD.encode(
+        json!({
+            "type": typ,
+            "challenge": URL_SAFE_NO_PAD.encode(challenge),
+            "origin": origin,
+        })
+        .to_string(),
+    )
+}
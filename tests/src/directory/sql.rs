@@ -302,7 +302,8 @@ impl DirectoryStore {
             concat!(
                 "CREATE TABLE accounts (name TEXT PRIMARY KEY, secret TEXT, description TEXT,",
                 " type TEXT NOT NULL, quota INTEGER ",
-                "DEFAULT 0, active BOOLEAN DEFAULT TRUE)"
+                "DEFAULT 0, time_zone TEXT, undelete_retention INTEGER DEFAULT 0, ",
+                "active BOOLEAN DEFAULT TRUE)"
             ),
             concat!(
                 "CREATE TABLE group_members (name TEXT NOT NULL, member_of ",
@@ -427,6 +428,34 @@ impl DirectoryStore {
             .unwrap();
     }
 
+    pub async fn set_test_time_zone(&self, login: &str, time_zone: &str) {
+        self.store
+            .query::<usize>(
+                if self.is_postgresql() {
+                    "UPDATE accounts SET time_zone = $1 where name = $2"
+                } else {
+                    "UPDATE accounts SET time_zone = ? where name = ?"
+                },
+                vec![time_zone.into(), login.into()],
+            )
+            .await
+            .unwrap();
+    }
+
+    pub async fn set_test_undelete_retention(&self, login: &str, retention: u32) {
+        self.store
+            .query::<usize>(
+                if self.is_postgresql() {
+                    "UPDATE accounts SET undelete_retention = $1 where name = $2"
+                } else {
+                    "UPDATE accounts SET undelete_retention = ? where name = ?"
+                },
+                vec![retention.into(), login.into()],
+            )
+            .await
+            .unwrap();
+    }
+
     pub async fn add_to_group(&self, login: &str, group: &str) {
         self.store
             .query::<usize>(
@@ -18,6 +18,7 @@ pub mod pop;
 pub mod search;
 pub mod store;
 pub mod thread;
+pub mod undelete;
 
 use std::{
     path::PathBuf,
@@ -237,7 +238,7 @@ type = "sqlite"
 path = "{TMP}/auth.db"
 
 [store."auth".query]
-name = "SELECT name, type, secret, description, quota FROM accounts WHERE name = ? AND active = true"
+name = "SELECT name, type, secret, description, quota, undelete_retention FROM accounts WHERE name = ? AND active = true"
 members = "SELECT member_of FROM group_members WHERE name = ?"
 recipients = "SELECT name FROM emails WHERE address = ?"
 emails = "SELECT address FROM emails WHERE name = ? AND type != 'list' ORDER BY type DESC, address ASC"
@@ -256,6 +257,7 @@ secret = "secret"
 email = "address"
 quota = "quota"
 class = "type"
+undelete-retention = "undelete_retention"
 
 [oauth]
 key = "parerga_und_paralipomena"
@@ -273,6 +275,7 @@ refresh-token-renew = "2s"
 pub struct IMAPTest {
     jmap: Arc<JMAP>,
     imap: Arc<Inner>,
+    directory: DirectoryStore,
     temp_dir: TempDir,
     shutdown_tx: watch::Sender<bool>,
 }
@@ -406,6 +409,7 @@ async fn init_imap_tests(store_id: &str, delete_if_exists: bool) -> IMAPTest {
     IMAPTest {
         jmap: JMAP::from(jmap.clone()).into(),
         imap: imap.imap_inner,
+        directory: lookup,
         temp_dir,
         shutdown_tx,
     }
@@ -471,6 +475,7 @@ pub async fn imap_tests() {
     idle::test(&mut imap, &mut imap_check).await;
     condstore::test(&mut imap, &mut imap_check).await;
     acl::test(&mut imap, &mut imap_check).await;
+    undelete::test(&handle).await;
 
     // Logout
     for imap in [&mut imap, &mut imap_check] {
@@ -49,7 +49,7 @@ pub async fn test() {
     pop3.send("CAPA").await;
     pop3.assert_read(ResponseType::Multiline)
         .await
-        .assert_contains("SASL PLAIN")
+        .assert_contains("SASL SCRAM-SHA-256-PLUS SCRAM-SHA-1-PLUS SCRAM-SHA-256 SCRAM-SHA-1 PLAIN")
         .assert_contains("IMPLEMENTATION");
 
     // Noop
@@ -0,0 +1,115 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use imap_proto::ResponseType;
+
+use super::{AssertResult, IMAPTest, ImapConnection, Type};
+
+pub async fn test(handle: &IMAPTest) {
+    println!("Running Recently Deleted tests...");
+
+    // Hold deleted messages for a day
+    handle
+        .directory
+        .set_test_undelete_retention("jdoe@example.com", 86400)
+        .await;
+    let mut imap = ImapConnection::connect(b"_z ").await;
+    imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+    imap.send("AUTHENTICATE PLAIN {32+}\r\nAGpkb2VAZXhhbXBsZS5jb20Ac2VjcmV0")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // Append a message and expunge it
+    imap.send("CREATE Leftovers").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    let message = "From: test@domain.com\nSubject: Leftover pizza\n\nStill good\n";
+    imap.send(&format!(
+        "APPEND Leftovers (\\Flagged) {{{}}}",
+        message.len()
+    ))
+    .await;
+    imap.assert_read(Type::Continuation, ResponseType::Ok).await;
+    imap.send_untagged(message).await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("SELECT Leftovers").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("STORE 1 +FLAGS (\\Deleted)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("EXPUNGE").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // The message is listed in the Recently Deleted mailbox
+    imap.send("LIST \"\" \"Recently Deleted\" RETURN (STATUS (MESSAGES UIDNEXT UIDVALIDITY))")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("\"Recently Deleted\"")
+        .assert_contains("MESSAGES 1")
+        .assert_contains("UIDNEXT 2")
+        .assert_contains("UIDVALIDITY 1");
+    imap.send("SELECT \"Recently Deleted\"").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("READ-ONLY")
+        .assert_contains("* 1 EXISTS");
+    imap.send("FETCH 1 (UID FLAGS RFC822.SIZE BODY[HEADER.FIELDS (SUBJECT)])")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("UID 1")
+        .assert_contains("\\Flagged")
+        .assert_contains(&format!("RFC822.SIZE {}", message.len()))
+        .assert_contains("Subject: Leftover pizza");
+
+    // The mailbox is read-only
+    imap.send("STORE 1 +FLAGS (\\Seen)").await;
+    imap.assert_read(Type::Tagged, ResponseType::No).await;
+    imap.send("APPEND \"Recently Deleted\" {1+}\r\na").await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_response_code("CANNOT");
+    imap.send("DELETE \"Recently Deleted\"").await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_response_code("CANNOT");
+    imap.send("SEARCH ALL").await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_response_code("CANNOT");
+
+    // Moving the message out of the mailbox restores it
+    imap.send("UID MOVE 1 Leftovers").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("COPYUID")
+        .assert_contains("* 1 EXPUNGE");
+    imap.send("STATUS \"Recently Deleted\" (MESSAGES)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("MESSAGES 0");
+    imap.send("SELECT Leftovers").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 EXISTS");
+    imap.send("FETCH 1 (FLAGS BODY.PEEK[HEADER.FIELDS (SUBJECT)])")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("\\Flagged")
+        .assert_contains("Subject: Leftover pizza");
+
+    // Clean up
+    handle
+        .directory
+        .set_test_undelete_retention("jdoe@example.com", 0)
+        .await;
+    imap.send("UNSELECT").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("DELETE Leftovers").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("LOGOUT").await;
+    imap.assert_read(Type::Untagged, ResponseType::Bye).await;
+}
@@ -19,7 +19,9 @@ use jmap_proto::types::id::Id;
 use std::fmt::Debug;
 use store::ahash::AHashMap;
 
-use crate::jmap::{assert_is_empty, mailbox::destroy_all_mailboxes, test_account_login};
+use crate::jmap::{
+    assert_is_empty, jmap_json_request, mailbox::destroy_all_mailboxes, test_account_login,
+};
 
 use super::JMAPTest;
 
@@ -39,6 +41,10 @@ pub async fn test(params: &mut JMAPTest) {
         .directory
         .create_test_user_with_email("jane.smith@example.com", "abcde", "Jane Smith")
         .await;
+    params
+        .directory
+        .set_test_time_zone("jane.smith@example.com", "Europe/Paris")
+        .await;
     params
         .directory
         .create_test_user_with_email("bill@example.com", "098765", "Bill Foobar")
@@ -180,6 +186,143 @@ pub async fn test(params: &mut JMAPTest) {
         .await
         .unwrap();
 
+    // John should have received a share notification
+    let response = jmap_json_request(
+        format!(
+            r#"[["ShareNotification/get", {{"accountId": "{john_id}"}}, "0"],
+                ["ShareNotification/query", {{"accountId": "{john_id}",
+                  "filter": {{"objectType": "Mailbox", "objectAccountId": "{jane_id}"}}}}, "1"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let notification = response
+        .pointer("/methodResponses/0/1/list/0")
+        .unwrap_or_else(|| panic!("Missing share notification: {response}"));
+    assert_eq!(
+        notification.pointer("/objectType").and_then(|v| v.as_str()),
+        Some("Mailbox")
+    );
+    assert_eq!(
+        notification
+            .pointer("/objectAccountId")
+            .and_then(|v| v.as_str()),
+        Some(jane_id.to_string().as_str())
+    );
+    assert_eq!(
+        notification.pointer("/objectId").and_then(|v| v.as_str()),
+        Some(inbox_id.as_str())
+    );
+    assert_eq!(
+        notification
+            .pointer("/changedBy/principalId")
+            .and_then(|v| v.as_str()),
+        Some(jane_id.to_string().as_str())
+    );
+    assert_eq!(
+        notification
+            .pointer("/oldRights/mayReadItems")
+            .and_then(|v| v.as_bool()),
+        Some(false)
+    );
+    assert_eq!(
+        notification
+            .pointer("/newRights/mayReadItems")
+            .and_then(|v| v.as_bool()),
+        Some(true)
+    );
+    let notification_id = notification.pointer("/id").unwrap().as_str().unwrap();
+    assert_eq!(
+        response
+            .pointer("/methodResponses/1/1/ids/0")
+            .and_then(|v| v.as_str()),
+        Some(notification_id)
+    );
+
+    // Jane's inbox should be listed in John's principal accounts
+    john_client.refresh_session().await.unwrap();
+    let response = jmap_json_request(
+        format!(
+            r#"[["Principal/get", {{"accountId": "{john_id}", "ids": ["{jane_id}"],
+                 "properties": ["name", "accounts"]}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert!(
+        response
+            .pointer(&format!(
+                "/methodResponses/0/1/list/0/accounts/{jane_id}/accountCapabilities"
+            ))
+            .and_then(|v| v.as_object())
+            .is_some_and(|caps| caps.contains_key("urn:ietf:params:jmap:principals:owner")),
+        "{response}"
+    );
+
+    // Principals include their time zone and the capabilities of their accounts,
+    // only disclosing account ids that are accessible
+    let response = jmap_json_request(
+        format!(
+            r#"[["Principal/get", {{"accountId": "{john_id}",
+                 "ids": ["{jane_id}", "{bill_id}"]}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let jane = response.pointer("/methodResponses/0/1/list/0").unwrap();
+    let bill = response.pointer("/methodResponses/0/1/list/1").unwrap();
+    assert_eq!(
+        jane.pointer("/timeZone").and_then(|v| v.as_str()),
+        Some("Europe/Paris"),
+        "{response}"
+    );
+    assert!(bill.pointer("/timeZone").unwrap().is_null(), "{response}");
+    for capability in [
+        "urn:ietf:params:jmap:mail",
+        "urn:ietf:params:jmap:submission",
+        "urn:ietf:params:jmap:calendars",
+    ] {
+        assert_eq!(
+            jane.pointer(&format!("/capabilities/{capability}/accountId"))
+                .and_then(|v| v.as_str()),
+            Some(jane_id.to_string().as_str()),
+            "{response}"
+        );
+        assert!(
+            bill.pointer(&format!("/capabilities/{capability}/accountId"))
+                .unwrap()
+                .is_null(),
+            "{response}"
+        );
+    }
+    assert_eq!(
+        jane.pointer("/capabilities/urn:ietf:params:jmap:calendars/calendarAddress")
+            .and_then(|v| v.as_str()),
+        Some("mailto:jane.smith@example.com"),
+        "{response}"
+    );
+
+    // Dismiss the notification
+    let response = jmap_json_request(
+        format!(
+            r#"[["ShareNotification/set", {{"accountId": "{john_id}",
+                 "destroy": ["{notification_id}"]}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/destroyed/0")
+            .and_then(|v| v.as_str()),
+        Some(notification_id),
+        "{response}"
+    );
+
     // John should have ReadItems access to Inbox
     assert_eq!(
         john_client
@@ -778,6 +921,29 @@ pub async fn test(params: &mut JMAPTest) {
     );
 
     // Destroy test account data
+    for (id, login, secret) in [
+        (john_id, "jdoe@example.com", "12345"),
+        (jane_id, "jane.smith@example.com", "abcde"),
+        (bill_id, "bill@example.com", "098765"),
+    ] {
+        let response = jmap_json_request(
+            format!(
+                r##"[["ShareNotification/query", {{"accountId": "{id}"}}, "0"],
+                    ["ShareNotification/set", {{"accountId": "{id}",
+                      "#destroy": {{"resultOf": "0", "name": "ShareNotification/query", "path": "/ids"}}}}, "1"]]"##
+            ),
+            login,
+            secret,
+        )
+        .await;
+        assert!(
+            response
+                .pointer("/methodResponses/1/1/notDestroyed")
+                .is_none(),
+            "{response}"
+        );
+    }
+    server.inner.sessions.clear();
     for id in [john_id, bill_id, jane_id, sales_id] {
         params.client.set_default_account_id(&id.to_string());
         destroy_all_mailboxes(params).await;
@@ -7,7 +7,7 @@
 use std::{
     net::{IpAddr, Ipv4Addr},
     sync::Arc,
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use common::listener::blocked::BLOCKED_IP_KEY;
@@ -19,7 +19,6 @@ use jmap_client::{
     mailbox::{self},
 };
 use jmap_proto::types::id::Id;
-use store::write::now;
 
 use crate::{
     imap::{ImapConnection, Type},
@@ -65,12 +64,14 @@ pub async fn test(params: &mut JMAPTest) {
             .await,
         Err(jmap_client::Error::Problem(err)) if err.status() == Some(401)));
 
-    // Wait until the beginning of the 5 seconds bucket
-    const LIMIT: u64 = 5;
-    let now = now();
-    let range_start = now / LIMIT;
-    let range_end = (range_start * LIMIT) + LIMIT;
-    tokio::time::sleep(Duration::from_secs(range_end - now)).await;
+    // Wait until the beginning of a 10 seconds bucket, so that the 2 seconds
+    // rate limiter and the 5 seconds fail2ban windows start at the same time
+    const LIMIT: u128 = 10_000;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    tokio::time::sleep(Duration::from_millis((LIMIT - now % LIMIT) as u64)).await;
 
     // Invalid authentication requests should be rate limited
     let mut n_401 = 0;
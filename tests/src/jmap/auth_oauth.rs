@@ -4,18 +4,21 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use bytes::Bytes;
 use directory::backend::internal::manage::ManageDirectory;
 use jmap::auth::oauth::{
-    DeviceAuthResponse, ErrorType, OAuthCodeRequest, OAuthMetadata, TokenResponse,
+    openid::OpenIdMetadata, DeviceAuthResponse, ErrorType, OAuthCodeRequest, OAuthMetadata,
+    TokenResponse,
 };
 use jmap_client::{
     client::{Client, Credentials},
     mailbox::query::Filter,
 };
 use jmap_proto::types::id::Id;
+use ring::signature::{RsaPublicKeyComponents, RSA_PKCS1_2048_8192_SHA256};
 use serde::de::DeserializeOwned;
 use store::ahash::AHashMap;
 
@@ -70,6 +73,9 @@ pub async fn test(params: &mut JMAPTest) {
             &OAuthCodeRequest::Code {
                 client_id: "OAuthyMcOAuthFace".to_string(),
                 redirect_uri: "https://localhost".to_string().into(),
+                scope: None,
+                nonce: None,
+                webauthn: None,
             },
         )
         .await
@@ -101,7 +107,8 @@ pub async fn test(params: &mut JMAPTest) {
         }
     );
 
-    // Obtain token
+    // Obtain token, it is also used to test the OpenID endpoints below
+    wait_for_next_second().await;
     token_params.insert("redirect_uri".to_string(), "https://localhost".to_string());
     let (token, _, _) = unwrap_token_response(post(&metadata.token_endpoint, &token_params).await);
 
@@ -120,6 +127,150 @@ pub async fn test(params: &mut JMAPTest) {
         .ids()
         .is_empty());
 
+    // ------------------------
+    // OpenID Connect
+    // ------------------------
+
+    // Obtain OpenID metadata and signing keys
+    let oidc_metadata: OpenIdMetadata =
+        get("https://127.0.0.1:8899/.well-known/openid-configuration").await;
+    assert_eq!(oidc_metadata.issuer, metadata.issuer);
+    assert_eq!(
+        oidc_metadata.id_token_signing_alg_values_supported,
+        vec!["RS256".to_string()]
+    );
+    let jwks: serde_json::Value = get(&oidc_metadata.jwks_uri).await;
+    let jwk = &jwks["keys"][0];
+    assert_eq!(jwk["kty"], "RSA");
+    assert_eq!(jwk["alg"], "RS256");
+
+    // Registered clients must use a registered redirect URI
+    for redirect_uri in [None, Some("https://localhost")] {
+        api.post::<OAuthCodeResponse>(
+            "/api/oauth",
+            &OAuthCodeRequest::Code {
+                client_id: "webapp".to_string(),
+                redirect_uri: redirect_uri.map(|uri| uri.to_string()),
+                scope: None,
+                nonce: None,
+                webauthn: None,
+            },
+        )
+        .await
+        .unwrap()
+        .unwrap_error();
+    }
+    let response = api
+        .post::<OAuthCodeResponse>(
+            "/api/oauth",
+            &OAuthCodeRequest::Code {
+                client_id: "webapp".to_string(),
+                redirect_uri: "https://webapp.example.org/callback".to_string().into(),
+                scope: "openid profile email".to_string().into(),
+                nonce: "n-0S6_WzA2Mj".to_string().into(),
+                webauthn: None,
+            },
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+
+    // Confidential clients have to authenticate
+    let mut token_params = AHashMap::from_iter([
+        ("client_id".to_string(), "webapp".to_string()),
+        (
+            "redirect_uri".to_string(),
+            "https://webapp.example.org/callback".to_string(),
+        ),
+        ("grant_type".to_string(), "authorization_code".to_string()),
+        ("code".to_string(), response.code),
+        ("client_secret".to_string(), "wrong-secret".to_string()),
+    ]);
+    assert_eq!(
+        post::<TokenResponse>(&metadata.token_endpoint, &token_params).await,
+        TokenResponse::Error {
+            error: ErrorType::InvalidClient
+        }
+    );
+    token_params.insert("client_secret".to_string(), "webapp-secret".to_string());
+    let granted = match post::<TokenResponse>(&metadata.token_endpoint, &token_params).await {
+        TokenResponse::Granted(granted) => granted,
+        TokenResponse::Error { error } => panic!("Expected granted, got {:?}", error),
+    };
+    assert_eq!(granted.scope.as_deref(), Some("openid profile email"));
+
+    // Validate the ID token
+    let id_token = granted.id_token.expect("Missing ID token");
+    let (message, signature) = id_token.rsplit_once('.').unwrap();
+    RsaPublicKeyComponents {
+        n: URL_SAFE_NO_PAD.decode(jwk["n"].as_str().unwrap()).unwrap(),
+        e: URL_SAFE_NO_PAD.decode(jwk["e"].as_str().unwrap()).unwrap(),
+    }
+    .verify(
+        &RSA_PKCS1_2048_8192_SHA256,
+        message.as_bytes(),
+        &URL_SAFE_NO_PAD.decode(signature).unwrap(),
+    )
+    .expect("Invalid ID token signature");
+    let (header, claims) = message.split_once('.').unwrap();
+    let header: serde_json::Value =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).unwrap()).unwrap();
+    let claims: serde_json::Value =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims).unwrap()).unwrap();
+    assert_eq!(header["kid"], jwk["kid"]);
+    assert_eq!(claims["iss"], oidc_metadata.issuer.as_str());
+    assert_eq!(claims["aud"], "webapp");
+    assert_eq!(claims["nonce"], "n-0S6_WzA2Mj");
+    assert_eq!(claims["preferred_username"], "jdoe@example.com");
+    assert_eq!(claims["name"], "John Doe");
+    assert_eq!(claims["email"], "jdoe@example.com");
+
+    // Obtain user information using the access token
+    let (status, user) = userinfo(&oidc_metadata.userinfo_endpoint, &granted.access_token).await;
+    assert_eq!(status, 200);
+    assert_eq!(user["sub"], claims["sub"]);
+    assert_eq!(user["preferred_username"], "jdoe@example.com");
+    assert_eq!(user["email"], "jdoe@example.com");
+
+    // Tokens issued without the openid scope cannot access user information
+    let (status, _) = userinfo(&oidc_metadata.userinfo_endpoint, &token).await;
+    assert_eq!(status, 403);
+
+    // Only the claims of the granted scope are returned, also after refreshing the token
+    let response = api
+        .post::<OAuthCodeResponse>(
+            "/api/oauth",
+            &OAuthCodeRequest::Code {
+                client_id: "webapp".to_string(),
+                redirect_uri: "https://webapp.example.org/callback".to_string().into(),
+                scope: "openid profile".to_string().into(),
+                nonce: None,
+                webauthn: None,
+            },
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    token_params.insert("code".to_string(), response.code);
+    wait_for_next_second().await;
+    let (access_token, refresh_token, _) =
+        unwrap_token_response(post(&metadata.token_endpoint, &token_params).await);
+    let refresh_params = AHashMap::from_iter([
+        ("client_id".to_string(), "webapp".to_string()),
+        ("client_secret".to_string(), "webapp-secret".to_string()),
+        ("grant_type".to_string(), "refresh_token".to_string()),
+        ("refresh_token".to_string(), refresh_token.unwrap()),
+    ]);
+    let (refreshed_token, _, _) =
+        unwrap_token_response(post(&metadata.token_endpoint, &refresh_params).await);
+    for access_token in [access_token, refreshed_token] {
+        let (status, user) = userinfo(&oidc_metadata.userinfo_endpoint, &access_token).await;
+        assert_eq!(status, 200);
+        assert_eq!(user["sub"], claims["sub"]);
+        assert_eq!(user["name"], "John Doe");
+        assert!(user.get("email").is_none(), "{user}");
+    }
+
     // ------------------------
     // Device code flow
     // ------------------------
@@ -156,6 +307,7 @@ pub async fn test(params: &mut JMAPTest) {
             "/api/oauth",
             &OAuthCodeRequest::Device {
                 code: device_response.user_code.clone(),
+                webauthn: None,
             },
         )
         .await
@@ -182,6 +334,7 @@ pub async fn test(params: &mut JMAPTest) {
             "/api/oauth",
             &OAuthCodeRequest::Device {
                 code: device_response.user_code.clone(),
+                webauthn: None,
             },
         )
         .await
@@ -191,6 +344,7 @@ pub async fn test(params: &mut JMAPTest) {
     );
 
     // Obtain token
+    wait_for_next_second().await;
     let time_first_token = Instant::now();
     let (token, refresh_token, _) =
         unwrap_token_response(post(&metadata.token_endpoint, &token_params).await);
@@ -328,6 +482,26 @@ async fn get<T: DeserializeOwned>(url: &str) -> T {
     serde_json::from_slice(&get_bytes(url).await).unwrap()
 }
 
+async fn userinfo(url: &str, token: &str) -> (u16, serde_json::Value) {
+    let response = reqwest::Client::builder()
+        .timeout(Duration::from_millis(500))
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap_or_default()
+        .get(url)
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let body = response.bytes().await.unwrap();
+
+    (
+        status,
+        serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null),
+    )
+}
+
 async fn assert_unauthorized(base_url: &str, token: &str) {
     match Client::new()
         .credentials(Credentials::bearer(token))
@@ -343,6 +517,16 @@ async fn assert_unauthorized(base_url: &str, token: &str) {
     }
 }
 
+// Token expiration times have a resolution of one second, waiting for the next
+// second to start keeps short-lived tokens valid for the requests that follow
+async fn wait_for_next_second() {
+    let elapsed = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_millis();
+    tokio::time::sleep(Duration::from_millis(1000 - elapsed as u64)).await;
+}
+
 fn unwrap_token_response(response: TokenResponse) -> (String, Option<String>, u64) {
     match response {
         TokenResponse::Granted(granted) => {
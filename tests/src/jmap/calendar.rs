@@ -0,0 +1,519 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use directory::backend::internal::manage::ManageDirectory;
+use jmap_proto::types::id::Id;
+use mail_auth::{common::parse::TxtRecordParser, dmarc::Dmarc, spf::Spf};
+
+use crate::jmap::{
+    assert_is_empty,
+    delivery::SmtpConnection,
+    email_submission::{expect_message_delivery, spawn_mock_smtp_server},
+    jmap_json_request,
+    mailbox::destroy_all_mailboxes,
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Calendar tests...");
+
+    // Create test account
+    let server = params.server.clone();
+    params
+        .directory
+        .create_test_user_with_email("jdoe@example.com", "12345", "John Doe")
+        .await;
+    let account_id = Id::from(
+        server
+            .core
+            .storage
+            .data
+            .get_or_create_account_id("jdoe@example.com")
+            .await
+            .unwrap(),
+    )
+    .to_string();
+
+    // Start mock SMTP server
+    let (mut smtp_rx, smtp_settings) = spawn_mock_smtp_server();
+    server.core.smtp.resolvers.dns.ipv4_add(
+        "localhost",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + std::time::Duration::from_secs(10),
+    );
+
+    // Create a calendar and a weekly recurring event
+    let response = jmap_json_request(
+        format!(
+            r##"[["Calendar/set", {{"accountId": "{account_id}",
+                  "create": {{"c1": {{"name": "Work", "color": "blue"}}}}}}, "0"],
+                ["CalendarEvent/set", {{"accountId": "{account_id}",
+                  "create": {{"e1": {{
+                    "@type": "Event",
+                    "calendarIds": {{"#c1": true}},
+                    "uid": "weekly-sync@example.com",
+                    "title": "Weekly sync",
+                    "start": "2024-01-01T10:00:00",
+                    "duration": "PT1H",
+                    "recurrenceRules": [{{"@type": "RecurrenceRule", "frequency": "weekly", "count": 10}}]
+                  }}}}}}, "1"]]"##
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let calendar_id = response
+        .pointer("/methodResponses/0/1/created/c1/id")
+        .and_then(|v| v.as_str())
+        .unwrap_or_else(|| panic!("Missing calendar: {response}"))
+        .to_string();
+    assert!(
+        response
+            .pointer("/methodResponses/1/1/created/e1/id")
+            .is_some(),
+        "Missing event: {response}"
+    );
+
+    // Occurrences within the range should match the event
+    for (after, before, expected) in [
+        ("2024-02-05T00:00:00Z", "2024-02-06T00:00:00Z", 1),
+        ("2024-01-02T00:00:00Z", "2024-01-08T00:00:00Z", 0),
+        ("2024-06-01T00:00:00Z", "2024-07-01T00:00:00Z", 0),
+    ] {
+        let response = jmap_json_request(
+            format!(
+                r#"[["CalendarEvent/query", {{"accountId": "{account_id}",
+                  "filter": {{"operator": "AND", "conditions": [{{"inCalendar": "{calendar_id}"}},
+                  {{"after": "{after}"}}, {{"before": "{before}"}}]}}}}, "0"]]"#
+            ),
+            "jdoe@example.com",
+            "12345",
+        )
+        .await;
+        assert_eq!(
+            response
+                .pointer("/methodResponses/0/1/ids")
+                .and_then(|v| v.as_array())
+                .map(|ids| ids.len()),
+            Some(expected),
+            "Range {after}..{before}: {response}"
+        );
+    }
+
+    // Invitations from senders that fail DMARC are delivered as plain messages
+    let invitation = concat!(
+        "From: bill@remote.org\r\n",
+        "To: jdoe@example.com\r\n",
+        "Subject: Invitation: TPS reports\r\n",
+        "Content-Type: text/calendar; method=REQUEST; charset=utf-8\r\n",
+        "\r\n",
+        "BEGIN:VCALENDAR\r\n",
+        "VERSION:2.0\r\n",
+        "METHOD:REQUEST\r\n",
+        "BEGIN:VEVENT\r\n",
+        "UID:tps-reports@remote.org\r\n",
+        "SEQUENCE:0\r\n",
+        "DTSTART:20240115T150000Z\r\n",
+        "DTEND:20240115T160000Z\r\n",
+        "SUMMARY:TPS reports\r\n",
+        "ORGANIZER;CN=Bill:mailto:bill@remote.org\r\n",
+        "ATTENDEE;PARTSTAT=NEEDS-ACTION;RSVP=TRUE:mailto:jdoe@example.com\r\n",
+        "END:VEVENT\r\n",
+        "END:VCALENDAR\r\n",
+    );
+    let dns = &server.core.smtp.resolvers.dns;
+    let valid_until = Instant::now() + Duration::from_secs(60);
+    dns.txt_add(
+        "_dmarc.remote.org",
+        Dmarc::parse(b"v=DMARC1; p=reject").unwrap(),
+        valid_until,
+    );
+    dns.txt_add(
+        "remote.org",
+        Spf::parse(b"v=spf1 -all").unwrap(),
+        valid_until,
+    );
+    let mut lmtp = SmtpConnection::connect().await;
+    lmtp.ingest("bill@remote.org", &["jdoe@example.com"], invitation)
+        .await;
+    let response = jmap_json_request(
+        format!(
+            r#"[["CalendarEvent/query", {{"accountId": "{account_id}",
+                  "filter": {{"uid": "tps-reports@remote.org"}}}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/ids")
+            .and_then(|v| v.as_array())
+            .map(|ids| ids.len()),
+        Some(0),
+        "{response}"
+    );
+
+    // Deliver an iMIP invitation from a DMARC aligned sender
+    dns.txt_add(
+        "remote.org",
+        Spf::parse(b"v=spf1 ip4:127.0.0.1 -all").unwrap(),
+        valid_until,
+    );
+    lmtp.ingest("bill@remote.org", &["jdoe@example.com"], invitation)
+        .await;
+
+    let response = jmap_json_request(
+        format!(
+            r##"[["CalendarEvent/query", {{"accountId": "{account_id}",
+                  "filter": {{"uid": "tps-reports@remote.org"}}}}, "0"],
+                ["CalendarEvent/get", {{"accountId": "{account_id}",
+                  "#ids": {{"resultOf": "0", "name": "CalendarEvent/query", "path": "/ids"}}}}, "1"],
+                ["CalendarEventNotification/get", {{"accountId": "{account_id}"}}, "2"]]"##
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let invitation = response
+        .pointer("/methodResponses/1/1/list/0")
+        .unwrap_or_else(|| panic!("Missing invitation: {response}"));
+    let invitation_id = invitation
+        .pointer("/id")
+        .and_then(|v| v.as_str())
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        invitation.pointer("/title").and_then(|v| v.as_str()),
+        Some("TPS reports")
+    );
+    assert_eq!(
+        invitation.pointer("/start").and_then(|v| v.as_str()),
+        Some("2024-01-15T15:00:00")
+    );
+    assert_eq!(
+        invitation.pointer("/duration").and_then(|v| v.as_str()),
+        Some("PT1H")
+    );
+    assert_eq!(
+        response
+            .pointer("/methodResponses/2/1/list/0/type")
+            .and_then(|v| v.as_str()),
+        Some("created"),
+        "{response}"
+    );
+    let notification_id = response
+        .pointer("/methodResponses/2/1/list/0/id")
+        .and_then(|v| v.as_str())
+        .unwrap()
+        .to_string();
+
+    // Accepting the invitation should send a REPLY to the organizer
+    let attendee_key = invitation
+        .pointer("/participants")
+        .and_then(|v| v.as_object())
+        .and_then(|participants| {
+            participants.iter().find_map(|(key, participant)| {
+                (participant.pointer("/email").and_then(|v| v.as_str()) == Some("jdoe@example.com"))
+                    .then(|| key.clone())
+            })
+        })
+        .unwrap_or_else(|| panic!("Missing attendee: {invitation}"));
+    let mut participants = invitation.pointer("/participants").unwrap().clone();
+    participants[&attendee_key]["participationStatus"] = "accepted".into();
+    smtp_settings.lock().do_stop = true;
+    let response = jmap_json_request(
+        format!(
+            r#"[["CalendarEvent/set", {{"accountId": "{account_id}",
+                  "sendSchedulingMessages": true,
+                  "update": {{"{invitation_id}": {{"participants": {participants}}}}}}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert!(
+        response
+            .pointer(&format!("/methodResponses/0/1/updated/{invitation_id}"))
+            .is_some(),
+        "{response}"
+    );
+    let reply = expect_message_delivery(&mut smtp_rx).await;
+    assert_eq!(reply.mail_from, "<jdoe@example.com>");
+    assert_eq!(reply.rcpt_to, vec!["<bill@remote.org>".to_string()]);
+    for needle in [
+        "METHOD:REPLY",
+        "UID:tps-reports@remote.org",
+        "PARTSTAT=ACCEPTED",
+    ] {
+        assert!(
+            reply.message.contains(needle),
+            "{needle:?} not in {}",
+            reply.message
+        );
+    }
+
+    // Updates from the organizer keep the settings and participation status of the attendee
+    let response = jmap_json_request(
+        format!(
+            r#"[["CalendarEvent/set", {{"accountId": "{account_id}",
+                  "update": {{"{invitation_id}": {{"color": "red"}}}}}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert!(
+        response
+            .pointer(&format!("/methodResponses/0/1/updated/{invitation_id}"))
+            .is_some(),
+        "{response}"
+    );
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Updated invitation: TPS reports\r\n",
+            "Content-Type: text/calendar; method=REQUEST; charset=utf-8\r\n",
+            "\r\n",
+            "BEGIN:VCALENDAR\r\n",
+            "VERSION:2.0\r\n",
+            "METHOD:REQUEST\r\n",
+            "BEGIN:VEVENT\r\n",
+            "UID:tps-reports@remote.org\r\n",
+            "SEQUENCE:1\r\n",
+            "DTSTART:20240116T150000Z\r\n",
+            "DTEND:20240116T160000Z\r\n",
+            "SUMMARY:TPS reports (moved)\r\n",
+            "ORGANIZER;CN=Bill:mailto:bill@remote.org\r\n",
+            "ATTENDEE;PARTSTAT=NEEDS-ACTION;RSVP=TRUE:mailto:jdoe@example.com\r\n",
+            "END:VEVENT\r\n",
+            "END:VCALENDAR\r\n",
+        ),
+    )
+    .await;
+    let response = jmap_json_request(
+        format!(
+            r#"[["CalendarEvent/get", {{"accountId": "{account_id}",
+                  "ids": ["{invitation_id}"]}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let event = response
+        .pointer("/methodResponses/0/1/list/0")
+        .unwrap_or_else(|| panic!("Missing invitation: {response}"));
+    assert_eq!(
+        event.pointer("/title").and_then(|v| v.as_str()),
+        Some("TPS reports (moved)"),
+        "{event}"
+    );
+    assert_eq!(
+        event.pointer("/start").and_then(|v| v.as_str()),
+        Some("2024-01-16T15:00:00"),
+        "{event}"
+    );
+    assert_eq!(
+        event.pointer("/color").and_then(|v| v.as_str()),
+        Some("red"),
+        "{event}"
+    );
+    assert_eq!(
+        event.pointer("/calendarIds"),
+        invitation.pointer("/calendarIds"),
+        "{event}"
+    );
+    assert!(
+        event
+            .pointer("/participants")
+            .and_then(|v| v.as_object())
+            .unwrap()
+            .values()
+            .any(|participant| {
+                participant.pointer("/email").and_then(|v| v.as_str()) == Some("jdoe@example.com")
+                    && participant
+                        .pointer("/participationStatus")
+                        .and_then(|v| v.as_str())
+                        == Some("accepted")
+            }),
+        "{event}"
+    );
+
+    // Only the organizer of an existing event can modify it
+    for (method, uid) in [
+        ("CANCEL", "tps-reports@remote.org"),
+        ("REQUEST", "weekly-sync@example.com"),
+    ] {
+        lmtp.ingest(
+            "mallory@remote.org",
+            &["jdoe@example.com"],
+            &format!(
+                concat!(
+                    "From: mallory@remote.org\r\n",
+                    "To: jdoe@example.com\r\n",
+                    "Subject: Updated event\r\n",
+                    "Content-Type: text/calendar; method={method}; charset=utf-8\r\n",
+                    "\r\n",
+                    "BEGIN:VCALENDAR\r\n",
+                    "VERSION:2.0\r\n",
+                    "METHOD:{method}\r\n",
+                    "BEGIN:VEVENT\r\n",
+                    "UID:{uid}\r\n",
+                    "SEQUENCE:5\r\n",
+                    "DTSTART:20240115T150000Z\r\n",
+                    "SUMMARY:Hijacked\r\n",
+                    "STATUS:CANCELLED\r\n",
+                    "ORGANIZER:mailto:mallory@remote.org\r\n",
+                    "ATTENDEE:mailto:jdoe@example.com\r\n",
+                    "END:VEVENT\r\n",
+                    "END:VCALENDAR\r\n",
+                ),
+                method = method,
+                uid = uid
+            ),
+        )
+        .await;
+    }
+    let response = jmap_json_request(
+        format!(
+            r##"[["CalendarEvent/query", {{"accountId": "{account_id}"}}, "0"],
+                ["CalendarEvent/get", {{"accountId": "{account_id}",
+                  "#ids": {{"resultOf": "0", "name": "CalendarEvent/query", "path": "/ids"}},
+                  "properties": ["uid", "title", "status"]}}, "1"]]"##
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let events = response
+        .pointer("/methodResponses/1/1/list")
+        .and_then(|v| v.as_array())
+        .unwrap_or_else(|| panic!("Missing events: {response}"));
+    assert_eq!(events.len(), 2, "{response}");
+    for event in events {
+        assert_ne!(
+            event.pointer("/title").and_then(|v| v.as_str()),
+            Some("Hijacked"),
+            "{response}"
+        );
+        assert_ne!(
+            event.pointer("/status").and_then(|v| v.as_str()),
+            Some("cancelled"),
+            "{response}"
+        );
+    }
+
+    // A cancellation from the organizer should update the event
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Cancelled: TPS reports\r\n",
+            "Content-Type: text/calendar; method=CANCEL; charset=utf-8\r\n",
+            "\r\n",
+            "BEGIN:VCALENDAR\r\n",
+            "VERSION:2.0\r\n",
+            "METHOD:CANCEL\r\n",
+            "BEGIN:VEVENT\r\n",
+            "UID:tps-reports@remote.org\r\n",
+            "SEQUENCE:1\r\n",
+            "DTSTART:20240115T150000Z\r\n",
+            "ORGANIZER:mailto:bill@remote.org\r\n",
+            "ATTENDEE:mailto:jdoe@example.com\r\n",
+            "END:VEVENT\r\n",
+            "END:VCALENDAR\r\n",
+        ),
+    )
+    .await;
+    lmtp.quit().await;
+
+    let response = jmap_json_request(
+        format!(
+            r#"[["CalendarEvent/get", {{"accountId": "{account_id}",
+                  "ids": ["{invitation_id}"], "properties": ["status", "sequence"]}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/list/0/status")
+            .and_then(|v| v.as_str()),
+        Some("cancelled"),
+        "{response}"
+    );
+
+    // Calendars with events can only be destroyed when requested
+    let response = jmap_json_request(
+        format!(
+            r#"[["Calendar/set", {{"accountId": "{account_id}",
+                  "destroy": ["{calendar_id}"]}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer(&format!(
+                "/methodResponses/0/1/notDestroyed/{calendar_id}/type"
+            ))
+            .and_then(|v| v.as_str()),
+        Some("calendarHasEvent"),
+        "{response}"
+    );
+
+    // Remove test data
+    let response = jmap_json_request(
+        format!(
+            r##"[["Calendar/get", {{"accountId": "{account_id}", "properties": ["id"]}}, "0"],
+                ["Calendar/set", {{"accountId": "{account_id}",
+                  "#destroy": {{"resultOf": "0", "name": "Calendar/get", "path": "/list/*/id"}},
+                  "onDestroyRemoveEvents": true}}, "1"],
+                ["CalendarEventNotification/set", {{"accountId": "{account_id}",
+                  "destroy": ["{notification_id}"]}}, "2"]]"##
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/1/1/destroyed")
+            .and_then(|v| v.as_array())
+            .map(|ids| ids.len()),
+        Some(1),
+        "{response}"
+    );
+    let response = jmap_json_request(
+        format!(
+            r##"[["CalendarEventNotification/query", {{"accountId": "{account_id}"}}, "0"],
+                ["CalendarEventNotification/set", {{"accountId": "{account_id}",
+                  "#destroy": {{"resultOf": "0", "name": "CalendarEventNotification/query", "path": "/ids"}}}}, "1"]]"##
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert!(
+        response
+            .pointer("/methodResponses/1/1/notDestroyed")
+            .is_none(),
+        "{response}"
+    );
+    params.client.set_default_account_id(&account_id);
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
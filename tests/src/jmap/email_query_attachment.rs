@@ -0,0 +1,180 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap::mailbox::INBOX_ID;
+use jmap_proto::types::id::Id;
+
+use crate::jmap::{
+    assert_is_empty, jmap_json_request, mailbox::destroy_all_mailboxes, wait_for_index,
+};
+
+use super::JMAPTest;
+
+const MESSAGE_ATTACHMENT: &str = concat!(
+    "From: bill@example.com\r\n",
+    "To: jdoe@example.com\r\n",
+    "Subject: Your invoice\r\n",
+    "Content-Type: multipart/mixed; boundary=\"boundary\"\r\n",
+    "\r\n",
+    "--boundary\r\n",
+    "Content-Type: text/plain\r\n",
+    "\r\n",
+    "Please find your invoice attached.\r\n",
+    "--boundary\r\n",
+    "Content-Type: application/pdf\r\n",
+    "Content-Disposition: attachment; filename=\"Invoice 2024.pdf\"\r\n",
+    "Content-Transfer-Encoding: base64\r\n",
+    "\r\n",
+    "JVBERi0xLjQK\r\n",
+    "--boundary--\r\n",
+);
+
+const MESSAGE_SIGNED: &str = concat!(
+    "From: jane@example.com\r\n",
+    "To: jdoe@example.com\r\n",
+    "Subject: Signed message\r\n",
+    "Content-Type: multipart/signed; protocol=\"application/pgp-signature\";\r\n",
+    "\tmicalg=pgp-sha256; boundary=\"boundary\"\r\n",
+    "\r\n",
+    "--boundary\r\n",
+    "Content-Type: text/plain\r\n",
+    "\r\n",
+    "This message is signed.\r\n",
+    "--boundary\r\n",
+    "Content-Type: application/pgp-signature; name=\"signature.asc\"\r\n",
+    "\r\n",
+    "-----BEGIN PGP SIGNATURE-----\r\n",
+    "-----END PGP SIGNATURE-----\r\n",
+    "--boundary--\r\n",
+);
+
+const MESSAGE_ENCRYPTED: &str = concat!(
+    "From: jane@example.com\r\n",
+    "To: jdoe@example.com\r\n",
+    "Subject: Encrypted message\r\n",
+    "Content-Type: application/pkcs7-mime; smime-type=enveloped-data; name=\"smime.p7m\"\r\n",
+    "Content-Disposition: attachment; filename=\"smime.p7m\"\r\n",
+    "Content-Transfer-Encoding: base64\r\n",
+    "\r\n",
+    "MIAGCSqGSIb3DQEHA6CAMIACAQAxggFOMIIBSgIBADAyMCYx\r\n",
+);
+
+const MESSAGE_NESTED_SIGNED: &str = concat!(
+    "From: jane@example.com\r\n",
+    "To: jdoe@example.com\r\n",
+    "Subject: Holiday pictures\r\n",
+    "Content-Type: multipart/mixed; boundary=\"outer\"\r\n",
+    "\r\n",
+    "--outer\r\n",
+    "Content-Type: multipart/signed; protocol=\"application/pkcs7-signature\";\r\n",
+    "\tmicalg=sha-256; boundary=\"inner\"\r\n",
+    "\r\n",
+    "--inner\r\n",
+    "Content-Type: text/plain\r\n",
+    "\r\n",
+    "Pictures from the trip.\r\n",
+    "--inner\r\n",
+    "Content-Type: application/pkcs7-signature; name=\"smime.p7s\"\r\n",
+    "Content-Transfer-Encoding: base64\r\n",
+    "\r\n",
+    "MIAGCSqGSIb3DQEHAqCAMIACAQExDzANBglghkgBZQMEAgEF\r\n",
+    "--inner--\r\n",
+    "--outer\r\n",
+    "Content-Type: image/png\r\n",
+    "Content-Disposition: attachment; filename=\"beach.png\"\r\n",
+    "Content-Transfer-Encoding: base64\r\n",
+    "\r\n",
+    "iVBORw0KGgo=\r\n",
+    "--outer--\r\n",
+);
+
+const MESSAGE_PLAIN: &str = concat!(
+    "From: jane@example.com\r\n",
+    "To: jdoe@example.com\r\n",
+    "Subject: Lunch\r\n",
+    "Content-Type: text/plain\r\n",
+    "\r\n",
+    "See you at noon.\r\n",
+);
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Email/query attachment filter tests...");
+
+    let server = params.server.clone();
+    let account_id = Id::from(1u64).to_string();
+    let inbox_id = Id::from(INBOX_ID).to_string();
+    params.client.set_default_account_id(&account_id);
+
+    // Import test messages
+    let mut email_ids = Vec::new();
+    for message in [
+        MESSAGE_ATTACHMENT,
+        MESSAGE_SIGNED,
+        MESSAGE_ENCRYPTED,
+        MESSAGE_PLAIN,
+        MESSAGE_NESTED_SIGNED,
+    ] {
+        email_ids.push(
+            params
+                .client
+                .email_import(
+                    message.as_bytes().to_vec(),
+                    [&inbox_id],
+                    None::<Vec<&str>>,
+                    None,
+                )
+                .await
+                .unwrap()
+                .take_id(),
+        );
+    }
+    wait_for_index(&server).await;
+
+    // Run queries
+    for (filter, expected) in [
+        (r#"{"attachmentName": "invoice"}"#, vec![0]),
+        (r#"{"attachmentName": "receipt"}"#, vec![]),
+        (r#"{"attachmentType": "application/pdf"}"#, vec![0]),
+        (r#"{"attachmentType": "Application/PDF"}"#, vec![0]),
+        (r#"{"attachmentType": "image"}"#, vec![4]),
+        (r#"{"attachmentName": "beach"}"#, vec![4]),
+        (r#"{"header": ["Content-Type", "application/pdf"]}"#, vec![]),
+        (r#"{"isEncrypted": true}"#, vec![2]),
+        (r#"{"isSigned": true}"#, vec![1, 4]),
+        (
+            r#"{"operator": "AND", "conditions": [{"isEncrypted": false}, {"isSigned": false}]}"#,
+            vec![0, 3],
+        ),
+    ] {
+        let response = jmap_json_request(
+            format!(
+                r#"[["Email/query", {{"accountId": "{account_id}",
+                      "filter": {filter}}}, "0"]]"#
+            ),
+            "admin",
+            "secret",
+        )
+        .await;
+        let mut ids = response
+            .pointer("/methodResponses/0/1/ids")
+            .and_then(|v| v.as_array())
+            .unwrap_or_else(|| panic!("Missing ids for {filter}: {response}"))
+            .iter()
+            .map(|id| id.as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        let mut expected = expected
+            .into_iter()
+            .map(|pos: usize| email_ids[pos].clone())
+            .collect::<Vec<_>>();
+        expected.sort_unstable();
+        assert_eq!(ids, expected, "filter: {filter}");
+    }
+
+    // Remove test data
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
@@ -0,0 +1,160 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use directory::backend::internal::manage::ManageDirectory;
+use jmap::mailbox::INBOX_ID;
+use jmap_proto::types::id::Id;
+
+use crate::jmap::{assert_is_empty, jmap_json_request, mailbox::destroy_all_mailboxes};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Email snooze tests...");
+
+    // Create test account
+    let server = params.server.clone();
+    params
+        .directory
+        .create_test_user_with_email("jdoe@example.com", "12345", "John Doe")
+        .await;
+    let document_id = server
+        .core
+        .storage
+        .data
+        .get_or_create_account_id("jdoe@example.com")
+        .await
+        .unwrap();
+    let account_id = Id::from(document_id).to_string();
+    let inbox_id = Id::from(INBOX_ID).to_string();
+
+    // Create a snoozed mailbox and a read message in the inbox
+    let response = jmap_json_request(
+        format!(
+            r#"[["Mailbox/set", {{"accountId": "{account_id}",
+                  "create": {{"m1": {{"name": "Snoozed"}}}}}}, "0"],
+                ["Email/set", {{"accountId": "{account_id}",
+                  "create": {{"e1": {{
+                    "mailboxIds": {{"{inbox_id}": true}},
+                    "keywords": {{"$seen": true}},
+                    "subject": "Remind me later",
+                    "bodyValues": {{"1": {{"value": "Hello"}}}},
+                    "textBody": [{{"partId": "1", "type": "text/plain"}}]
+                  }}}}}}, "1"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let snoozed_mailbox_id = response
+        .pointer("/methodResponses/0/1/created/m1/id")
+        .and_then(|v| v.as_str())
+        .unwrap_or_else(|| panic!("Missing mailbox: {response}"))
+        .to_string();
+    let email_id = response
+        .pointer("/methodResponses/1/1/created/e1/id")
+        .and_then(|v| v.as_str())
+        .unwrap_or_else(|| panic!("Missing email: {response}"))
+        .to_string();
+
+    // Snoozing to a mailbox that does not exist should fail
+    let missing_id = Id::from(1000u64);
+    let response = jmap_json_request(
+        format!(
+            r#"[["Email/set", {{"accountId": "{account_id}",
+                  "update": {{"{email_id}": {{
+                    "snoozed": {{"until": "2000-01-01T00:00:00Z", "moveToMailboxId": "{missing_id}"}}
+                  }}}}}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer(&format!("/methodResponses/0/1/notUpdated/{email_id}/type"))
+            .and_then(|v| v.as_str()),
+        Some("invalidProperties"),
+        "{response}"
+    );
+
+    // Snooze the message
+    let response = jmap_json_request(
+        format!(
+            r#"[["Email/set", {{"accountId": "{account_id}",
+                  "update": {{"{email_id}": {{
+                    "mailboxIds": {{"{snoozed_mailbox_id}": true}},
+                    "snoozed": {{"until": "2000-01-01T00:00:00Z", "moveToMailboxId": "{inbox_id}"}}
+                  }}}}}}, "0"],
+                ["Email/get", {{"accountId": "{account_id}", "ids": ["{email_id}"],
+                  "properties": ["mailboxIds", "keywords", "snoozed"]}}, "1"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let email = response
+        .pointer("/methodResponses/1/1/list/0")
+        .unwrap_or_else(|| panic!("Missing email: {response}"));
+    assert_eq!(
+        email.pointer("/snoozed/until").and_then(|v| v.as_str()),
+        Some("2000-01-01T00:00:00Z"),
+        "{response}"
+    );
+    assert_eq!(
+        email
+            .pointer("/keywords/$snoozed")
+            .and_then(|v| v.as_bool()),
+        Some(true),
+        "{response}"
+    );
+    assert!(
+        email
+            .pointer(&format!("/mailboxIds/{snoozed_mailbox_id}"))
+            .is_some(),
+        "{response}"
+    );
+
+    // Waking the message should move it back to the inbox as unread
+    server.emails_wake_snoozed().await;
+    let response = jmap_json_request(
+        format!(
+            r#"[["Email/get", {{"accountId": "{account_id}", "ids": ["{email_id}"],
+                  "properties": ["mailboxIds", "keywords", "snoozed"]}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let email = response
+        .pointer("/methodResponses/0/1/list/0")
+        .unwrap_or_else(|| panic!("Missing email: {response}"));
+    assert_eq!(
+        email
+            .pointer("/mailboxIds")
+            .and_then(|v| v.as_object())
+            .map(|ids| ids.keys().cloned().collect::<Vec<_>>()),
+        Some(vec![inbox_id.clone()]),
+        "{response}"
+    );
+    assert_eq!(
+        email
+            .pointer("/keywords")
+            .and_then(|v| v.as_object())
+            .map(|keywords| keywords.len()),
+        Some(0),
+        "{response}"
+    );
+    assert!(
+        email.pointer("/snoozed").map_or(true, |v| v.is_null()),
+        "{response}"
+    );
+
+    // Remove test data
+    params.client.set_default_account_id(&account_id);
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
@@ -27,7 +27,8 @@ use tokio::{
 };
 
 use crate::jmap::{
-    assert_is_empty, email_set::assert_email_properties, mailbox::destroy_all_mailboxes,
+    assert_is_empty, email_set::assert_email_properties, jmap_json_request,
+    mailbox::destroy_all_mailboxes,
 };
 
 use super::JMAPTest;
@@ -434,6 +435,48 @@ pub async fn test(params: &mut JMAPTest) {
         ),])
     );
 
+    // Scheduled submissions using sendAt remain pending and can be cancelled
+    let response = jmap_json_request(
+        format!(
+            r#"[["EmailSubmission/set", {{"accountId": "{account_id}",
+                  "create": {{"s1": {{"emailId": "{email_id}", "identityId": "{identity_id}",
+                    "sendAt": "2079-11-20T05:00:00Z"}}}}}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let email_submission_id = response
+        .pointer("/methodResponses/0/1/created/s1/id")
+        .and_then(|v| v.as_str())
+        .unwrap_or_else(|| panic!("Missing submission: {response}"))
+        .to_string();
+    expect_nothing(&mut smtp_rx).await;
+    let email_submission = client
+        .email_submission_get(&email_submission_id, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(email_submission.send_at().unwrap(), hold_until);
+    assert_eq!(
+        email_submission.undo_status().unwrap(),
+        &UndoStatus::Pending
+    );
+    client
+        .email_submission_change_status(&email_submission_id, UndoStatus::Canceled)
+        .await
+        .unwrap();
+    assert_eq!(
+        client
+            .email_submission_get(&email_submission_id, None)
+            .await
+            .unwrap()
+            .unwrap()
+            .undo_status()
+            .unwrap(),
+        &UndoStatus::Canceled
+    );
+
     // Verify onSuccessUpdateEmail action
     let mut request = client.build();
     let set_request = request.set_email_submission();
@@ -452,6 +495,7 @@ pub async fn test(params: &mut JMAPTest) {
     request.send().await.unwrap().unwrap_method_responses();
 
     assert_email_properties(client, &email_id, &[&mailbox_id_2], &["$draft"]).await;
+    expect_message_delivery(&mut smtp_rx).await;
 
     // Verify onSuccessDestroyEmail action
     let mut request = client.build();
@@ -470,6 +514,155 @@ pub async fn test(params: &mut JMAPTest) {
         .await
         .unwrap()
         .is_none());
+    expect_message_delivery(&mut smtp_rx).await;
+
+    // Send an MDN for a message requesting a disposition notification
+    let email_id = client
+        .email_import(
+            concat!(
+                "From: Jane Smith <jane_smith@remote.org>\r\n",
+                "To: jdoe@example.com\r\n",
+                "Subject: Please confirm\r\n",
+                "Message-ID: <mdn-test@remote.org>\r\n",
+                "Disposition-Notification-To: Jane Smith <jane_smith@remote.org>\r\n",
+                "\r\n",
+                "Did you read this?"
+            )
+            .as_bytes()
+            .to_vec(),
+            [&mailbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    let mdn_request = r##"[[
+            "MDN/send",
+            {
+             "accountId": "$$",
+             "identityId": "%%",
+             "send": {
+              "k1": {
+               "forEmailId": "&&",
+               "subject": "Read receipt",
+               "textBody": "Your message was displayed.",
+               "disposition": {
+                "actionMode": "manual-action",
+                "sendingMode": "mdn-sent-manually",
+                "type": "displayed"
+               }
+              }
+             },
+             "onSuccessUpdateEmail": {
+              "#k1": {
+               "keywords/$seen": true
+              }
+             }
+            },
+            "R1"
+           ]]"##
+        .replace("$$", &account_id)
+        .replace("%%", &identity_id)
+        .replace("&&", &email_id);
+    let response = jmap_json_request(&mdn_request, "jdoe@example.com", "12345").await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/sent/k1/finalRecipient")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default(),
+        "rfc822; jdoe@example.com",
+        "Response: {:?}",
+        response
+    );
+    assert_eq!(
+        response
+            .pointer("/methodResponses/1/0")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default(),
+        "Email/set",
+        "Response: {:?}",
+        response
+    );
+    let mdn = expect_message_delivery(&mut smtp_rx).await;
+    assert_eq!(mdn.mail_from, "<>");
+    assert_eq!(mdn.rcpt_to, vec!["<jane_smith@remote.org>".to_string()]);
+    for needle in [
+        "report-type=\"disposition-notification\"",
+        "Final-Recipient: rfc822; jdoe@example.com",
+        "Original-Message-ID: <mdn-test@remote.org>",
+        "Disposition: manual-action/mdn-sent-manually; displayed",
+        "Your message was displayed.",
+    ] {
+        assert!(
+            mdn.message.contains(needle),
+            "{needle:?} not in {}",
+            mdn.message
+        );
+    }
+    assert_email_properties(client, &email_id, &[&mailbox_id], &["$mdnsent", "$seen"]).await;
+
+    // Sending a second MDN for the same message should fail
+    let response = jmap_json_request(&mdn_request, "jdoe@example.com", "12345").await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/notSent/k1/type")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default(),
+        "mdnAlreadySent",
+        "Response: {:?}",
+        response
+    );
+    expect_nothing(&mut smtp_rx).await;
+
+    // Parse the MDN that was sent
+    let blob_id = client
+        .email_import(
+            mdn.message.into_bytes(),
+            [&mailbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .blob_id()
+        .unwrap()
+        .to_string();
+    let response = jmap_json_request(
+        r#"[[
+            "MDN/parse",
+            {
+             "accountId": "$$",
+             "blobIds": ["%%"]
+            },
+            "R1"
+           ]]"#
+        .replace("$$", &account_id)
+        .replace("%%", &blob_id),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    for (pointer, expected) in [
+        ("forEmailId", email_id.as_str()),
+        ("subject", "Read receipt"),
+        ("finalRecipient", "rfc822; jdoe@example.com"),
+        ("originalMessageId", "<mdn-test@remote.org>"),
+        ("disposition/actionMode", "manual-action"),
+        ("disposition/sendingMode", "mdn-sent-manually"),
+        ("disposition/type", "displayed"),
+    ] {
+        assert_eq!(
+            response
+                .pointer(&format!("/methodResponses/0/1/parsed/{blob_id}/{pointer}"))
+                .and_then(|v| v.as_str())
+                .unwrap_or_default(),
+            expected,
+            "Response: {:?}",
+            response
+        );
+    }
+
     smtp_settings.lock().do_stop = true;
 
     // Destroy the created mailbox, identity and all submissions
@@ -0,0 +1,373 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use directory::backend::internal::manage::ManageDirectory;
+use jmap::api::management::list::html_escape;
+use jmap_proto::types::id::Id;
+
+use crate::jmap::{assert_is_empty, jmap_json_request, mailbox::destroy_all_mailboxes};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Email template tests...");
+
+    // Create test account
+    let server = params.server.clone();
+    params
+        .directory
+        .create_test_user_with_email("jdoe@example.com", "12345", "John Doe")
+        .await;
+    let account_id = Id::from(
+        server
+            .core
+            .storage
+            .data
+            .get_or_create_account_id("jdoe@example.com")
+            .await
+            .unwrap(),
+    )
+    .to_string();
+    let admin_account_id = Id::from(
+        server
+            .core
+            .storage
+            .data
+            .get_or_create_account_id("admin")
+            .await
+            .unwrap(),
+    )
+    .to_string();
+
+    // Create an account template
+    let response = jmap_json_request(
+        format!(
+            r#"[["Template/set", {{"accountId": "{account_id}",
+                  "create": {{"t1": {{
+                    "name": "Follow up",
+                    "subject": "Following up from {{{{name}}}}",
+                    "textBody": "Just checking in, {{{{name}}}} <{{{{email}}}}>"
+                  }}}}}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let template_id = response
+        .pointer("/methodResponses/0/1/created/t1/id")
+        .and_then(|v| v.as_str())
+        .unwrap_or_else(|| panic!("Missing template: {response}"))
+        .to_string();
+    let account_state = response
+        .pointer("/methodResponses/0/1/newState")
+        .and_then(|v| v.as_str())
+        .unwrap_or_else(|| panic!("Missing state: {response}"))
+        .to_string();
+
+    // Only administrators can create domain templates
+    let domain_template = r#""create": {"d1": {
+                    "name": "Company welcome",
+                    "scope": "domain",
+                    "domain": "example.com",
+                    "subject": "Welcome",
+                    "htmlBody": "<p>Welcome aboard, {{name}}!</p>"
+                  }}"#;
+    let response = jmap_json_request(
+        format!(r#"[["Template/set", {{"accountId": "{account_id}", {domain_template}}}, "0"]]"#),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/notCreated/d1/type")
+            .and_then(|v| v.as_str()),
+        Some("forbidden"),
+        "{response}"
+    );
+    let response = jmap_json_request(
+        format!(
+            r#"[["Template/set", {{"accountId": "{admin_account_id}", {domain_template}}}, "0"]]"#
+        ),
+        "admin",
+        "secret",
+    )
+    .await;
+    let domain_template_id = response
+        .pointer("/methodResponses/0/1/created/d1/id")
+        .and_then(|v| v.as_str())
+        .unwrap_or_else(|| panic!("Missing domain template: {response}"))
+        .to_string();
+
+    // Domain templates on unknown domains are rejected
+    let response = jmap_json_request(
+        format!(
+            r#"[["Template/set", {{"accountId": "{admin_account_id}",
+                  "create": {{"d2": {{"name": "Other", "scope": "domain",
+                                      "domain": "unknown-domain.org"}}}}}}, "0"]]"#
+        ),
+        "admin",
+        "secret",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/notCreated/d2/properties/0")
+            .and_then(|v| v.as_str()),
+        Some("domain"),
+        "{response}"
+    );
+
+    // Both templates are visible to the account
+    let response = jmap_json_request(
+        format!(
+            r#"[["Template/query", {{"accountId": "{account_id}"}}, "0"],
+                ["Template/query", {{"accountId": "{account_id}",
+                  "filter": {{"scope": "domain"}}}}, "1"],
+                ["Template/get", {{"accountId": "{account_id}",
+                  "ids": ["{domain_template_id}"],
+                  "properties": ["name", "scope", "domain"]}}, "2"],
+                ["Template/changes", {{"accountId": "{account_id}",
+                  "sinceState": "{account_state}"}}, "3"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/ids")
+            .and_then(|v| v.as_array())
+            .map(|ids| ids.iter().filter_map(|id| id.as_str()).collect::<Vec<_>>()),
+        Some(vec![domain_template_id.as_str(), template_id.as_str()]),
+        "{response}"
+    );
+    assert_eq!(
+        response
+            .pointer("/methodResponses/1/1/ids")
+            .and_then(|v| v.as_array())
+            .map(|ids| ids.iter().filter_map(|id| id.as_str()).collect::<Vec<_>>()),
+        Some(vec![domain_template_id.as_str()]),
+        "{response}"
+    );
+    assert_eq!(
+        response
+            .pointer("/methodResponses/2/1/list/0/domain")
+            .and_then(|v| v.as_str()),
+        Some("example.com"),
+        "{response}"
+    );
+
+    // Domain template changes advance the Template state of the account
+    let state = response
+        .pointer("/methodResponses/2/1/state")
+        .and_then(|v| v.as_str())
+        .unwrap_or_else(|| panic!("Missing state: {response}"))
+        .to_string();
+    assert_ne!(state, account_state, "{response}");
+    assert_eq!(
+        response
+            .pointer("/methodResponses/3/1/type")
+            .and_then(|v| v.as_str()),
+        Some("cannotCalculateChanges"),
+        "{response}"
+    );
+    let response = jmap_json_request(
+        format!(
+            r#"[["Template/changes", {{"accountId": "{account_id}",
+                  "sinceState": "{state}"}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/newState")
+            .and_then(|v| v.as_str()),
+        Some(state.as_str()),
+        "{response}"
+    );
+
+    // Obtain the identity names used to replace the {{name}} variable,
+    // drafts without a sender use the first identity
+    let response = jmap_json_request(
+        format!(r#"[["Identity/get", {{"accountId": "{account_id}"}}, "0"]]"#),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let identities = response
+        .pointer("/methodResponses/0/1/list")
+        .and_then(|v| v.as_array())
+        .unwrap_or_else(|| panic!("Missing identities: {response}"));
+    let identity_name = |identity: &serde_json::Value| {
+        identity
+            .pointer("/name")
+            .and_then(|v| v.as_str())
+            .unwrap_or_else(|| panic!("Missing identity name: {response}"))
+            .to_string()
+    };
+    let sender_name = identities
+        .iter()
+        .find(|identity| {
+            identity.pointer("/email").and_then(|v| v.as_str()) == Some("jdoe@example.com")
+        })
+        .map(identity_name)
+        .unwrap_or_else(|| panic!("Missing sender identity: {response}"));
+    let default_name = identities
+        .first()
+        .map(identity_name)
+        .unwrap_or_else(|| panic!("Missing default identity: {response}"));
+
+    // Create drafts from both templates
+    let missing_id = Id::from(9999u32);
+    let response = jmap_json_request(
+        format!(
+            r##"[["Mailbox/set", {{"accountId": "{account_id}",
+                  "create": {{"m1": {{"name": "Templates"}}}}}}, "0"],
+                ["Email/set", {{"accountId": "{account_id}",
+                  "create": {{"e1": {{
+                    "mailboxIds": {{"#m1": true}},
+                    "from": [{{"email": "jdoe@example.com"}}],
+                    "to": [{{"email": "jane@example.org"}}],
+                    "fromTemplate": "{template_id}"
+                  }}, "e2": {{
+                    "mailboxIds": {{"#m1": true}},
+                    "subject": "Hello there",
+                    "fromTemplate": "{domain_template_id}"
+                  }}, "e3": {{
+                    "mailboxIds": {{"#m1": true}},
+                    "fromTemplate": "{missing_id}"
+                  }}}}}}, "1"],
+                ["Email/get", {{"accountId": "{account_id}", "ids": ["#e1", "#e2"],
+                  "properties": ["subject", "bodyValues", "textBody", "htmlBody"],
+                  "fetchAllBodyValues": true}}, "2"]]"##
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/1/1/notCreated/e3/properties/0")
+            .and_then(|v| v.as_str()),
+        Some("fromTemplate"),
+        "{response}"
+    );
+    let draft = response
+        .pointer("/methodResponses/2/1/list/0")
+        .unwrap_or_else(|| panic!("Missing draft: {response}"));
+    assert_eq!(
+        draft.pointer("/subject").and_then(|v| v.as_str()),
+        Some(format!("Following up from {sender_name}").as_str()),
+        "{response}"
+    );
+    assert!(
+        has_body_value(
+            draft,
+            &format!("Just checking in, {sender_name} <jdoe@example.com>")
+        ),
+        "{response}"
+    );
+    let draft = response
+        .pointer("/methodResponses/2/1/list/1")
+        .unwrap_or_else(|| panic!("Missing draft: {response}"));
+    assert_eq!(
+        draft.pointer("/subject").and_then(|v| v.as_str()),
+        Some("Hello there"),
+        "{response}"
+    );
+    assert!(
+        has_body_value(
+            draft,
+            &format!("<p>Welcome aboard, {}!</p>", html_escape(&default_name))
+        ),
+        "{response}"
+    );
+
+    // Only administrators can delete domain templates
+    let response = jmap_json_request(
+        format!(
+            r#"[["Template/set", {{"accountId": "{account_id}",
+                  "destroy": ["{template_id}", "{domain_template_id}"]}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/destroyed/0")
+            .and_then(|v| v.as_str()),
+        Some(template_id.as_str()),
+        "{response}"
+    );
+    assert_eq!(
+        response
+            .pointer(&format!(
+                "/methodResponses/0/1/notDestroyed/{domain_template_id}/type"
+            ))
+            .and_then(|v| v.as_str()),
+        Some("forbidden"),
+        "{response}"
+    );
+    let response = jmap_json_request(
+        format!(
+            r#"[["Template/set", {{"accountId": "{admin_account_id}",
+                  "destroy": ["{domain_template_id}"]}}, "0"]]"#
+        ),
+        "admin",
+        "secret",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/destroyed/0")
+            .and_then(|v| v.as_str()),
+        Some(domain_template_id.as_str()),
+        "{response}"
+    );
+
+    // Remove test data
+    let response = jmap_json_request(
+        format!(r#"[["Identity/get", {{"accountId": "{account_id}"}}, "0"]]"#),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    for identity_id in response
+        .pointer("/methodResponses/0/1/list")
+        .and_then(|v| v.as_array())
+        .unwrap()
+        .iter()
+        .filter_map(|identity| identity.pointer("/id").and_then(|v| v.as_str()))
+    {
+        jmap_json_request(
+            format!(
+                r#"[["Identity/set", {{"accountId": "{account_id}",
+                      "destroy": ["{identity_id}"]}}, "0"]]"#
+            ),
+            "jdoe@example.com",
+            "12345",
+        )
+        .await;
+    }
+    params.client.set_default_account_id(&account_id);
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+fn has_body_value(email: &serde_json::Value, expected: &str) -> bool {
+    email
+        .pointer("/bodyValues")
+        .and_then(|v| v.as_object())
+        .map_or(false, |values| {
+            values
+                .values()
+                .any(|value| value.pointer("/value").and_then(|v| v.as_str()) == Some(expected))
+        })
+}
@@ -0,0 +1,232 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use directory::backend::internal::manage::ManageDirectory;
+use jmap_proto::types::id::Id;
+use mail_parser::DateTime;
+
+use crate::jmap::{
+    assert_is_empty, emails_purge_tombstoned, jmap_json_request, mailbox::destroy_all_mailboxes,
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Email undelete tests...");
+
+    // Create test account
+    let server = params.server.clone();
+    params
+        .directory
+        .create_test_user_with_email("jdoe@example.com", "12345", "John Doe")
+        .await;
+    params
+        .directory
+        .set_test_undelete_retention("jdoe@example.com", 7 * 86400)
+        .await;
+    let document_id = server
+        .core
+        .storage
+        .data
+        .get_or_create_account_id("jdoe@example.com")
+        .await
+        .unwrap();
+    let account_id = Id::from(document_id).to_string();
+
+    // Create a mailbox and a flagged message in it
+    let response = jmap_json_request(
+        format!(
+            r##"[["Mailbox/set", {{"accountId": "{account_id}",
+                  "create": {{"m1": {{"name": "Projects"}}}}}}, "0"],
+                ["Email/set", {{"accountId": "{account_id}",
+                  "create": {{"e1": {{
+                    "mailboxIds": {{"#m1": true}},
+                    "keywords": {{"$flagged": true}},
+                    "subject": "Quarterly report",
+                    "receivedAt": "2024-01-01T00:00:00Z",
+                    "bodyValues": {{"1": {{"value": "Numbers are up"}}}},
+                    "textBody": [{{"partId": "1", "type": "text/plain"}}]
+                  }}}}}}, "1"]]"##
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let mailbox_id = response
+        .pointer("/methodResponses/0/1/created/m1/id")
+        .and_then(|v| v.as_str())
+        .unwrap_or_else(|| panic!("Missing mailbox: {response}"))
+        .to_string();
+    let email_id = response
+        .pointer("/methodResponses/1/1/created/e1/id")
+        .and_then(|v| v.as_str())
+        .unwrap_or_else(|| panic!("Missing email: {response}"))
+        .to_string();
+    let used_quota = server.get_used_quota(document_id).await.unwrap();
+    assert!(used_quota > 0);
+
+    // Destroy the message and purge it
+    let response = jmap_json_request(
+        format!(
+            r#"[["Email/set", {{"accountId": "{account_id}",
+                  "destroy": ["{email_id}"]}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/destroyed/0")
+            .and_then(|v| v.as_str()),
+        Some(email_id.as_str()),
+        "{response}"
+    );
+    emails_purge_tombstoned(&server).await;
+    assert_eq!(server.get_used_quota(document_id).await.unwrap(), 0);
+
+    // The deleted message should be listed with its original metadata
+    let response = jmap_json_request(
+        format!(r#"[["Email/listDeleted", {{"accountId": "{account_id}"}}, "0"]]"#),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let deleted = response
+        .pointer("/methodResponses/0/1/list/0")
+        .unwrap_or_else(|| panic!("Missing deleted email: {response}"));
+    assert_eq!(
+        deleted.pointer("/subject").and_then(|v| v.as_str()),
+        Some("Quarterly report"),
+        "{response}"
+    );
+    assert_eq!(
+        deleted
+            .pointer(&format!("/mailboxIds/{mailbox_id}"))
+            .and_then(|v| v.as_bool()),
+        Some(true),
+        "{response}"
+    );
+    assert_eq!(
+        deleted
+            .pointer("/keywords/$flagged")
+            .and_then(|v| v.as_bool()),
+        Some(true),
+        "{response}"
+    );
+
+    // The retention window is taken from the account rather than the server default
+    let deleted_at = deleted
+        .pointer("/deletedAt")
+        .and_then(|v| v.as_str())
+        .and_then(DateTime::parse_rfc3339)
+        .unwrap();
+    let expires_at = deleted
+        .pointer("/expiresAt")
+        .and_then(|v| v.as_str())
+        .and_then(DateTime::parse_rfc3339)
+        .unwrap();
+    assert_eq!(
+        expires_at.to_timestamp() - deleted_at.to_timestamp(),
+        7 * 86400,
+        "{response}"
+    );
+    let blob_id = deleted
+        .pointer("/blobId")
+        .and_then(|v| v.as_str())
+        .unwrap()
+        .to_string();
+
+    // Restore the message
+    let response = jmap_json_request(
+        format!(
+            r#"[["Email/undelete", {{"accountId": "{account_id}",
+                  "blobIds": ["{blob_id}"]}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let email_id = response
+        .pointer(&format!("/methodResponses/0/1/created/{blob_id}/id"))
+        .and_then(|v| v.as_str())
+        .unwrap_or_else(|| panic!("Missing restored email: {response}"))
+        .to_string();
+    let response = jmap_json_request(
+        format!(
+            r#"[["Email/get", {{"accountId": "{account_id}", "ids": ["{email_id}"],
+                  "properties": ["mailboxIds", "keywords", "subject", "receivedAt"]}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let email = response
+        .pointer("/methodResponses/0/1/list/0")
+        .unwrap_or_else(|| panic!("Missing restored email: {response}"));
+    assert_eq!(
+        email.pointer("/subject").and_then(|v| v.as_str()),
+        Some("Quarterly report"),
+        "{response}"
+    );
+    assert_eq!(
+        email.pointer("/receivedAt").and_then(|v| v.as_str()),
+        Some("2024-01-01T00:00:00Z"),
+        "{response}"
+    );
+    assert_eq!(
+        email
+            .pointer(&format!("/mailboxIds/{mailbox_id}"))
+            .and_then(|v| v.as_bool()),
+        Some(true),
+        "{response}"
+    );
+    assert_eq!(
+        email
+            .pointer("/keywords/$flagged")
+            .and_then(|v| v.as_bool()),
+        Some(true),
+        "{response}"
+    );
+
+    // Restored messages count towards the quota again
+    assert_eq!(
+        server.get_used_quota(document_id).await.unwrap(),
+        used_quota
+    );
+
+    // The message can only be restored once
+    let response = jmap_json_request(
+        format!(
+            r#"[["Email/undelete", {{"accountId": "{account_id}",
+                  "blobIds": ["{blob_id}"]}}, "0"],
+                ["Email/listDeleted", {{"accountId": "{account_id}"}}, "1"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer(&format!("/methodResponses/0/1/notCreated/{blob_id}/type"))
+            .and_then(|v| v.as_str()),
+        Some("blobNotFound"),
+        "{response}"
+    );
+    assert_eq!(
+        response
+            .pointer("/methodResponses/1/1/list")
+            .and_then(|v| v.as_array())
+            .map(|list| list.len()),
+        Some(0),
+        "{response}"
+    );
+
+    // Remove test data
+    params.client.set_default_account_id(&account_id);
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
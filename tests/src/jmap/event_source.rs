@@ -7,14 +7,16 @@
 use std::time::Duration;
 
 use crate::jmap::{
-    assert_is_empty, delivery::SmtpConnection, mailbox::destroy_all_mailboxes, test_account_login,
+    assert_is_empty, delivery::SmtpConnection, jmap_json_request, mailbox::destroy_all_mailboxes,
+    test_account_login,
 };
 use directory::backend::internal::manage::ManageDirectory;
 use futures::StreamExt;
 use jmap::mailbox::INBOX_ID;
-use jmap_client::{event_source::Changes, mailbox::Role, TypeState};
-use jmap_proto::types::id::Id;
+use jmap_client::{event_source::Changes, mailbox::Role, principal::ACL, TypeState};
+use jmap_proto::types::{id::Id, state::StateChange, type_state::DataType};
 use store::ahash::AHashSet;
+use utils::map::bitmap::Bitmap;
 
 use tokio::sync::mpsc;
 
@@ -29,16 +31,14 @@ pub async fn test(params: &mut JMAPTest) {
         .directory
         .create_test_user_with_email("jdoe@example.com", "12345", "John Doe")
         .await;
-    let account_id = Id::from(
-        server
-            .core
-            .storage
-            .data
-            .get_or_create_account_id("jdoe@example.com")
-            .await
-            .unwrap(),
-    )
-    .to_string();
+    let john_doc_id = server
+        .core
+        .storage
+        .data
+        .get_or_create_account_id("jdoe@example.com")
+        .await
+        .unwrap();
+    let account_id = Id::from(john_doc_id).to_string();
     let client = test_account_login("jdoe@example.com", "12345").await;
 
     let mut changes = client
@@ -48,7 +48,7 @@ pub async fn test(params: &mut JMAPTest) {
 
     let (event_tx, mut event_rx) = mpsc::channel::<Changes>(100);
 
-    tokio::spawn(async move {
+    let event_task = tokio::spawn(async move {
         while let Some(change) = changes.next().await {
             if let Err(_err) = event_tx.send(change.unwrap()).await {
                 //println!("Error sending event: {}", _err);
@@ -128,7 +128,78 @@ pub async fn test(params: &mut JMAPTest) {
     .await;
     assert_ping(&mut event_rx).await;
     assert_ping(&mut event_rx).await;
+    event_task.abort();
 
+    // Changes to shared accounts should only include the types allowed by the ACL
+    params
+        .directory
+        .create_test_user_with_email("jane.smith@example.com", "abcde", "Jane Smith")
+        .await;
+    let jane_doc_id = server
+        .core
+        .storage
+        .data
+        .get_or_create_account_id("jane.smith@example.com")
+        .await
+        .unwrap();
+    let jane_id = Id::from(jane_doc_id).to_string();
+    let jane_client = test_account_login("jane.smith@example.com", "abcde").await;
+    let mut change_rx = server
+        .subscribe_state_manager(
+            john_doc_id,
+            Bitmap::from_iter([DataType::Mailbox, DataType::Email]),
+        )
+        .await
+        .unwrap();
+    let shared_id = jane_client
+        .mailbox_create("Shared", None::<String>, Role::None)
+        .await
+        .unwrap()
+        .take_id();
+    expect_no_state_change(&mut change_rx, jane_doc_id).await;
+    jane_client
+        .mailbox_update_acl(&shared_id, "jdoe@example.com", [ACL::Read])
+        .await
+        .unwrap();
+    assert_state_change(&mut change_rx, jane_doc_id, &[DataType::Mailbox]).await;
+    jane_client
+        .mailbox_update_sort_order(&shared_id, 1)
+        .await
+        .unwrap();
+    assert_state_change(&mut change_rx, jane_doc_id, &[DataType::Mailbox]).await;
+
+    // Revoking access should stop the changes
+    jane_client
+        .mailbox_update_acl(&shared_id, "jdoe@example.com", [])
+        .await
+        .unwrap();
+    jane_client
+        .mailbox_update_sort_order(&shared_id, 2)
+        .await
+        .unwrap();
+    expect_no_state_change(&mut change_rx, jane_doc_id).await;
+    drop(change_rx);
+
+    // Remove test data
+    let response = jmap_json_request(
+        format!(
+            r##"[["ShareNotification/query", {{"accountId": "{account_id}"}}, "0"],
+                ["ShareNotification/set", {{"accountId": "{account_id}",
+                  "#destroy": {{"resultOf": "0", "name": "ShareNotification/query", "path": "/ids"}}}}, "1"]]"##
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert!(
+        response
+            .pointer("/methodResponses/1/1/notDestroyed")
+            .is_none(),
+        "{response}"
+    );
+    params.client.set_default_account_id(&jane_id);
+    destroy_all_mailboxes(params).await;
+    params.client.set_default_account_id(&account_id);
     destroy_all_mailboxes(params).await;
     assert_is_empty(server).await;
 }
@@ -155,6 +226,47 @@ async fn assert_state(
     }
 }
 
+async fn assert_state_change(
+    change_rx: &mut mpsc::Receiver<StateChange>,
+    account_id: u32,
+    state: &[DataType],
+) {
+    loop {
+        match tokio::time::timeout(Duration::from_millis(700), change_rx.recv()).await {
+            Ok(Some(changes)) if changes.account_id == account_id => {
+                assert_eq!(
+                    changes
+                        .types
+                        .iter()
+                        .map(|(data_type, _)| *data_type)
+                        .collect::<AHashSet<_>>(),
+                    state.iter().copied().collect::<AHashSet<_>>()
+                );
+                break;
+            }
+            Ok(Some(_)) => {}
+            result => {
+                panic!("Timeout waiting for state change {:?}: {:?}", state, result);
+            }
+        }
+    }
+}
+
+async fn expect_no_state_change(change_rx: &mut mpsc::Receiver<StateChange>, account_id: u32) {
+    loop {
+        match tokio::time::timeout(Duration::from_millis(700), change_rx.recv()).await {
+            Ok(Some(changes)) if changes.account_id == account_id => {
+                panic!(
+                    "Received a state change when expecting nothing: {:?}",
+                    changes
+                );
+            }
+            Ok(Some(_)) => {}
+            _ => break,
+        }
+    }
+}
+
 async fn assert_ping(event_rx: &mut mpsc::Receiver<Changes>) {
     match tokio::time::timeout(Duration::from_millis(1100), event_rx.recv()).await {
         Ok(Some(changes)) => {
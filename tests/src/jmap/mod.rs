@@ -18,9 +18,14 @@ use common::{
 };
 use hyper::{header::AUTHORIZATION, Method};
 use imap::core::{ImapSessionManager, IMAP};
-use jmap::{api::JmapSessionManager, services::housekeeper::Event, JMAP};
+use jmap::{
+    api::JmapSessionManager, mailbox::RECENTLY_DELETED_ID, services::housekeeper::Event, JMAP,
+};
 use jmap_client::client::{Client, Credentials};
-use jmap_proto::{error::request::RequestError, types::id::Id};
+use jmap_proto::{
+    error::request::RequestError,
+    types::{collection::Collection, id::Id, property::Property},
+};
 use managesieve::core::ManageSieveSessionManager;
 use pop3::Pop3SessionManager;
 use reqwest::header;
@@ -29,8 +34,8 @@ use smtp::core::{SmtpSessionManager, SMTP};
 
 use store::{
     roaring::RoaringBitmap,
-    write::{key::DeserializeBigEndian, AnyKey},
-    IterateParams, Stores, SUBSPACE_PROPERTY,
+    write::{key::DeserializeBigEndian, AnyKey, BatchBuilder, F_CLEAR, F_VALUE},
+    IterateParams, Stores, SUBSPACE_COUNTER, SUBSPACE_PROPERTY,
 };
 use tokio::sync::{mpsc, watch};
 use utils::config::Config;
@@ -42,6 +47,7 @@ pub mod auth_acl;
 pub mod auth_limits;
 pub mod auth_oauth;
 pub mod blob;
+pub mod calendar;
 pub mod crypto;
 pub mod delivery;
 pub mod email_changes;
@@ -49,10 +55,14 @@ pub mod email_copy;
 pub mod email_get;
 pub mod email_parse;
 pub mod email_query;
+pub mod email_query_attachment;
 pub mod email_query_changes;
 pub mod email_search_snippet;
 pub mod email_set;
+pub mod email_snooze;
 pub mod email_submission;
+pub mod email_template;
+pub mod email_undelete;
 pub mod event_source;
 pub mod mailbox;
 pub mod purge;
@@ -224,6 +234,7 @@ attempts.interval = "500ms"
 
 [jmap.email]
 auto-expunge = "1s"
+undelete.retention = "1d"
 
 [jmap.protocol.changes]
 max-history = "1s"
@@ -233,7 +244,7 @@ type = "sqlite"
 path = "{TMP}/auth.db"
 
 [store."auth".query]
-name = "SELECT name, type, secret, description, quota FROM accounts WHERE name = ? AND active = true"
+name = "SELECT name, type, secret, description, quota, time_zone, undelete_retention FROM accounts WHERE name = ? AND active = true"
 members = "SELECT member_of FROM group_members WHERE name = ?"
 recipients = "SELECT name FROM emails WHERE address = ?"
 emails = "SELECT address FROM emails WHERE name = ? AND type != 'list' ORDER BY type DESC, address ASC"
@@ -252,6 +263,8 @@ secret = "secret"
 email = "address"
 quota = "quota"
 class = "type"
+time-zone = "time_zone"
+undelete-retention = "undelete_retention"
 
 [oauth]
 key = "parerga_und_paralipomena"
@@ -259,6 +272,15 @@ key = "parerga_und_paralipomena"
 [oauth.auth]
 max-attempts = 1
 
+[oauth.oidc]
+signature-key = "%{file:{PK}}%"
+signature-algorithm = "RS256"
+
+[oauth.client."webapp"]
+name = "Web App"
+redirect-uri = ["https://webapp.example.org/callback"]
+secret = "webapp-secret"
+
 [oauth.expiry]
 user-code = "1s"
 token = "1s"
@@ -311,6 +333,7 @@ pub async fn jmap_tests() {
     email_set::test(&mut params).await;
     email_parse::test(&mut params).await;
     email_search_snippet::test(&mut params).await;
+    email_query_attachment::test(&mut params).await;
     email_changes::test(&mut params).await;
     email_query_changes::test(&mut params).await;
     email_copy::test(&mut params).await;
@@ -325,7 +348,11 @@ pub async fn jmap_tests() {
     push_subscription::test(&mut params).await;
     sieve_script::test(&mut params).await;
     vacation_response::test(&mut params).await;
+    calendar::test(&mut params).await;
     email_submission::test(&mut params).await;
+    email_snooze::test(&mut params).await;
+    email_undelete::test(&mut params).await;
+    email_template::test(&mut params).await;
     websocket::test(&mut params).await;
     quota::test(&mut params).await;
     crypto::test(&mut params).await;
@@ -398,6 +425,7 @@ pub async fn assert_is_empty(server: Arc<JMAP>) {
 
     // Purge accounts
     emails_purge_tombstoned(&server).await;
+    clear_recently_deleted_uids(&server).await;
 
     // Assert is empty
     server
@@ -440,6 +468,51 @@ pub async fn emails_purge_tombstoned(server: &JMAP) {
     }
 }
 
+// The UID counter of the Recently Deleted mailbox outlives the account's mailboxes
+pub async fn clear_recently_deleted_uids(server: &JMAP) {
+    let mut account_ids = RoaringBitmap::new();
+    server
+        .core
+        .storage
+        .data
+        .iterate(
+            IterateParams::new(
+                AnyKey {
+                    subspace: SUBSPACE_COUNTER,
+                    key: vec![0u8],
+                },
+                AnyKey {
+                    subspace: SUBSPACE_COUNTER,
+                    key: vec![u8::MAX; 10],
+                },
+            )
+            .no_values(),
+            |key, _| {
+                if key.len() == 10
+                    && key[4] == u8::from(Collection::Mailbox)
+                    && key[5] == u8::from(Property::EmailIds)
+                    && key[6..] == RECENTLY_DELETED_ID.to_be_bytes()
+                {
+                    account_ids.insert(key.deserialize_be_u32(0).unwrap());
+                }
+
+                Ok(true)
+            },
+        )
+        .await
+        .unwrap();
+
+    for account_id in account_ids {
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Mailbox)
+            .update_document(RECENTLY_DELETED_ID)
+            .value(Property::EmailIds, (), F_VALUE | F_CLEAR);
+        server.core.storage.data.write(batch.build()).await.unwrap();
+    }
+}
+
 async fn init_jmap_tests(store_id: &str, delete_if_exists: bool) -> JMAPTest {
     // Load and parse config
     let temp_dir = TempDir::new("jmap_tests", delete_if_exists);
@@ -7,7 +7,7 @@
 use std::{io::Cursor, time::Duration};
 
 use crate::{
-    jmap::{assert_is_empty, mailbox::destroy_all_mailboxes},
+    jmap::{assert_is_empty, jmap_json_request, mailbox::destroy_all_mailboxes},
     store::deflate_test_resource,
 };
 use jmap::{
@@ -27,6 +27,7 @@ use super::JMAPTest;
 pub async fn test(params: &mut JMAPTest) {
     test_single_thread(params).await;
     test_multi_thread(params).await;
+    test_split_merge(params).await;
 }
 
 async fn test_single_thread(params: &mut JMAPTest) {
@@ -296,6 +297,174 @@ async fn test_multi_thread(params: &mut JMAPTest) {
     assert_is_empty(params.server.clone()).await;
 }
 
+async fn test_split_merge(params: &mut JMAPTest) {
+    println!("Running Thread split/merge tests...");
+    let server = params.server.clone();
+    let account_id = Id::new(0u64).to_string();
+
+    // Create two messages in the same thread and an unrelated one
+    let response = jmap_json_request(
+        format!(
+            r##"[["Mailbox/set", {{"accountId": "{account_id}",
+                  "create": {{"m1": {{"name": "Threads"}}}}}}, "0"],
+                ["Email/set", {{"accountId": "{account_id}",
+                  "create": {{
+                    "a": {{"mailboxIds": {{"#m1": true}},
+                           "messageId": ["a@example.com"], "subject": "Invoice",
+                           "bodyValues": {{"1": {{"value": "Invoice attached"}}}},
+                           "textBody": [{{"partId": "1", "type": "text/plain"}}]}},
+                    "b": {{"mailboxIds": {{"#m1": true}},
+                           "messageId": ["b@example.com"], "references": ["a@example.com"],
+                           "subject": "Re: Invoice",
+                           "bodyValues": {{"1": {{"value": "Thanks"}}}},
+                           "textBody": [{{"partId": "1", "type": "text/plain"}}]}},
+                    "c": {{"mailboxIds": {{"#m1": true}},
+                           "messageId": ["c@example.com"], "subject": "Lunch",
+                           "bodyValues": {{"1": {{"value": "Pizza?"}}}},
+                           "textBody": [{{"partId": "1", "type": "text/plain"}}]}}
+                  }}}}, "1"],
+                ["Thread/get", {{"accountId": "{account_id}", "ids": []}}, "2"]]"##
+        ),
+        "admin",
+        "secret",
+    )
+    .await;
+    let created = |name: &str, property: &str| {
+        response
+            .pointer(&format!("/methodResponses/1/1/created/{name}/{property}"))
+            .and_then(|v| v.as_str())
+            .unwrap_or_else(|| panic!("Missing {name}/{property}: {response}"))
+            .to_string()
+    };
+    let (email_b, thread_a, thread_b, thread_c) = (
+        created("b", "id"),
+        created("a", "threadId"),
+        created("b", "threadId"),
+        created("c", "threadId"),
+    );
+    assert_eq!(thread_a, thread_b);
+    assert_ne!(thread_a, thread_c);
+    let state = response
+        .pointer("/methodResponses/2/1/state")
+        .and_then(|v| v.as_str())
+        .unwrap()
+        .to_string();
+
+    // Split the reply into its own thread, duplicate ids are only moved once
+    let response = jmap_json_request(
+        format!(
+            r#"[["Thread/split", {{"accountId": "{account_id}",
+                  "emailIds": ["{email_b}", "{email_b}"]}}, "0"],
+                ["Thread/changes", {{"accountId": "{account_id}", "sinceState": "{state}"}}, "1"],
+                ["Thread/get", {{"accountId": "{account_id}", "ids": ["{thread_a}"]}}, "2"]]"#
+        ),
+        "admin",
+        "secret",
+    )
+    .await;
+    let split_thread = response
+        .pointer(&format!("/methodResponses/0/1/split/{email_b}/threadId"))
+        .and_then(|v| v.as_str())
+        .unwrap_or_else(|| panic!("Missing split: {response}"))
+        .to_string();
+    assert_ne!(split_thread, thread_a, "{response}");
+    assert_eq!(
+        response.pointer("/methodResponses/1/1/created"),
+        Some(&serde_json::Value::from(vec![split_thread.as_str()])),
+        "{response}"
+    );
+    assert_eq!(
+        response.pointer("/methodResponses/1/1/updated/0"),
+        Some(&serde_json::Value::from(thread_a.as_str())),
+        "{response}"
+    );
+    assert_eq!(
+        response
+            .pointer("/methodResponses/2/1/list/0/emailIds")
+            .and_then(|v| v.as_array())
+            .map(|ids| ids.len()),
+        Some(1),
+        "{response}"
+    );
+
+    // Merge the unrelated thread into the original one
+    let response = jmap_json_request(
+        format!(
+            r#"[["Thread/merge", {{"accountId": "{account_id}",
+                  "threadIds": ["{thread_a}", "{thread_c}"]}}, "0"],
+                ["Thread/get", {{"accountId": "{account_id}",
+                  "ids": ["{thread_a}", "{thread_c}"]}}, "1"]]"#
+        ),
+        "admin",
+        "secret",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/threadId")
+            .and_then(|v| v.as_str()),
+        Some(thread_a.as_str()),
+        "{response}"
+    );
+    assert_eq!(
+        response
+            .pointer("/methodResponses/1/1/list/0/emailIds")
+            .and_then(|v| v.as_array())
+            .map(|ids| ids.len()),
+        Some(2),
+        "{response}"
+    );
+    assert_eq!(
+        response.pointer("/methodResponses/1/1/notFound/0"),
+        Some(&serde_json::Value::from(thread_c.as_str())),
+        "{response}"
+    );
+
+    // Export the merged thread
+    params.client.set_default_account_id(&account_id);
+    for (format, content_type) in [("mbox", "application/mbox"), ("zip", "application/zip")] {
+        let response = jmap_json_request(
+            format!(
+                r#"[["Thread/export", {{"accountId": "{account_id}",
+                      "threadIds": ["{thread_a}"], "format": "{format}"}}, "0"]]"#
+            ),
+            "admin",
+            "secret",
+        )
+        .await;
+        let exported = response
+            .pointer(&format!("/methodResponses/0/1/exported/{thread_a}"))
+            .unwrap_or_else(|| panic!("Missing export: {response}"));
+        assert_eq!(
+            exported.pointer("/type").and_then(|v| v.as_str()),
+            Some(content_type),
+            "{response}"
+        );
+        let bundle = params
+            .client
+            .download(
+                exported
+                    .pointer("/blobId")
+                    .and_then(|v| v.as_str())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            Some(bundle.len() as u64),
+            exported.pointer("/size").and_then(|v| v.as_u64()),
+        );
+        if format == "mbox" {
+            assert_eq!(MessageIterator::new(Cursor::new(bundle)).count(), 2);
+        } else {
+            assert!(bundle.starts_with(b"PK"));
+        }
+    }
+
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
 fn build_message(message: usize, in_reply_to: Option<usize>, thread_num: usize) -> String {
     if let Some(in_reply_to) = in_reply_to {
         format!(
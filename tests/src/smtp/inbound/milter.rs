@@ -20,7 +20,7 @@ use mail_auth::AuthenticatedMessage;
 use mail_parser::MessageParser;
 use serde::Deserialize;
 use smtp::{
-    core::{Inner, Session, SessionData},
+    core::{Inner, Session, SessionData, SMTP},
     inbound::{
         hooks::{self, Request, SmtpResponse},
         milter::{
@@ -28,6 +28,7 @@ use smtp::{
             Action, Command, Macros, MilterClient, Modification, Options, Response,
         },
     },
+    queue::quarantine::{QuarantineReason, QuarantineSource},
 };
 use store::Stores;
 use tokio::{
@@ -118,7 +119,8 @@ async fn milter_session() {
     let mut qr = inner.init_test_queue(&core);
 
     // Build session
-    let mut session = Session::test(build_smtp(core, inner));
+    let core = build_smtp(core, inner);
+    let mut session = Session::test(core.clone());
     session.data.remote_ip_str = "10.0.0.1".to_string();
     session.eval_session_params().await;
     session.ehlo("mx.doe.org").await;
@@ -227,6 +229,18 @@ async fn milter_session() {
         .await
         .assert_contains("X-Spam: Yes")
         .assert_contains("123456");
+
+    // Test quarantine
+    session
+        .send_message(
+            "quarantine@doe.org",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "250 2.0.0",
+        )
+        .await;
+    qr.assert_no_events();
+    assert_quarantined(&core, QuarantineSource::Milter, "Suspicious attachment").await;
 }
 
 #[tokio::test]
@@ -251,7 +265,8 @@ async fn mta_hook_session() {
     let mut qr = inner.init_test_queue(&core);
 
     // Build session
-    let mut session = Session::test(build_smtp(core, inner));
+    let core = build_smtp(core, inner);
+    let mut session = Session::test(core.clone());
     session.data.remote_ip_str = "10.0.0.1".to_string();
     session.eval_session_params().await;
     session.ehlo("mx.doe.org").await;
@@ -360,6 +375,36 @@ async fn mta_hook_session() {
         .await
         .assert_contains("X-Spam: Yes")
         .assert_contains("123456");
+
+    // Test quarantine
+    session
+        .send_message(
+            "quarantine@doe.org",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "250 2.0.0",
+        )
+        .await;
+    qr.assert_no_events();
+    assert_quarantined(&core, QuarantineSource::MtaHook, "Suspicious link").await;
+}
+
+async fn assert_quarantined(core: &SMTP, source: QuarantineSource, reason: &str) {
+    let mut quarantined = Vec::new();
+    core.read_quarantined_messages(|message| {
+        quarantined.push(message);
+        true
+    })
+    .await
+    .unwrap();
+    assert_eq!(quarantined.len(), 1);
+    let message = quarantined.pop().unwrap();
+    assert_eq!(message.reasons, vec![QuarantineReason::new(source, reason)]);
+    assert_eq!(
+        message.message.recipients.first().unwrap().address,
+        "bill@foobar.org"
+    );
+    assert_eq!(message.subject, "Is dinner ready?");
 }
 
 #[test]
@@ -727,6 +772,13 @@ async fn accept_milter(
                                     code: [b'3', b'2', b'1'],
                                     text: "test".to_string(),
                                 },
+                                "quarantine" => {
+                                    modifications = vec![Modification::Quarantine {
+                                        reason: "Suspicious attachment".to_string(),
+                                    }]
+                                    .into();
+                                    Action::Accept
+                                }
                                 test_num => {
                                     modifications = tests[test_num.parse::<usize>().unwrap()]
                                         .modifications
@@ -906,6 +958,15 @@ fn handle_mta_hook(request: Request, tests: Arc<Vec<HeaderTest>>) -> hooks::Resp
             .into(),
             modifications: vec![],
         },
+        "quarantine" => hooks::Response {
+            action: hooks::Action::Quarantine,
+            response: SmtpResponse {
+                message: "Suspicious link".to_string().into(),
+                ..Default::default()
+            }
+            .into(),
+            modifications: vec![],
+        },
         test_num => hooks::Response {
             action: hooks::Action::Accept,
             response: None,
@@ -17,6 +17,7 @@ use common::Core;
 
 use smtp::{
     core::{Inner, Session},
+    queue::quarantine::{QuarantineReason, QuarantineSource},
     scripts::ScriptResult,
 };
 use store::Stores;
@@ -380,6 +381,50 @@ async fn sieve_scripts() {
         .assert_contains("Authentication-Results: ");
     qr.assert_no_events();
 
+    // Expect the message to be quarantined rather than discarded
+    session
+        .send_message(
+            "test@example.net",
+            &["mallory@foobar.gov"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    qr.assert_no_events();
+    let mut quarantined = Vec::new();
+    core.read_quarantined_messages(|message| {
+        quarantined.push(message);
+        true
+    })
+    .await
+    .unwrap();
+    assert_eq!(quarantined.len(), 1);
+    let message = quarantined.pop().unwrap();
+    assert_eq!(
+        message.reasons,
+        vec![QuarantineReason::new(
+            QuarantineSource::Sieve,
+            "Suspicious recipient"
+        )]
+    );
+    assert_eq!(
+        message.message.recipients.first().unwrap().address,
+        "mallory@foobar.gov"
+    );
+    assert_eq!(message.subject, "Is dinner ready?");
+
+    // Releasing the message should queue it for delivery
+    assert!(core.release_quarantined_message(message, None).await);
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("Subject: Is dinner ready?");
+    qr.assert_no_events();
+    core.read_quarantined_messages(|_| panic!("Quarantine should be empty"))
+        .await
+        .unwrap();
+
     // Test pipes
     session.data.remote_ip_str = "10.0.0.123".parse().unwrap();
     session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
@@ -0,0 +1,102 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use common::{config::smtp::auth::DkimRotation, manager::config::ConfigManager};
+use jmap::api::management::dkim::rotate_dkim_signatures;
+use utils::config::{cron::SimpleCron, utils::ParseValue};
+
+use crate::smtp::outbound::TestServer;
+
+const DAY: u64 = 86400;
+
+#[tokio::test]
+async fn dkim_rotation() {
+    let local = TestServer::new("smtp_dkim_rotation_test", "", true).await;
+    let core = local.build_smtp();
+    let config = ConfigManager {
+        cfg_store: core.core.storage.data.clone(),
+        ..Default::default()
+    };
+    let rotation = DkimRotation {
+        frequency: SimpleCron::parse_value("0 3 *").unwrap(),
+        interval: Duration::from_secs(90 * DAY),
+        grace_period: Duration::from_secs(7 * DAY),
+        retire_after: Duration::from_secs(7 * DAY),
+    };
+    let id = "ed25519-example.org";
+    let get = |key: &str| {
+        let key = format!("signature.{id}.{key}");
+        let config = &config;
+        async move { config.get(&key).await.unwrap() }
+    };
+    config
+        .set([
+            (format!("signature.{id}.private-key"), "old-key".to_string()),
+            (format!("signature.{id}.selector"), "old".to_string()),
+            (
+                format!("signature.{id}.algorithm"),
+                "ed25519-sha256".to_string(),
+            ),
+            (format!("signature.{id}.domain"), "example.org".to_string()),
+        ])
+        .await
+        .unwrap();
+
+    // The first run only schedules the rotation
+    let mut now = 1_000_000;
+    assert!(!rotate_dkim_signatures(&config, &rotation, now).await);
+    assert_eq!(
+        get("rotation.due").await,
+        Some((now + 90 * DAY).to_string())
+    );
+    assert_eq!(get("rotation.state").await, None);
+
+    // Nothing happens before the rotation is due
+    assert!(!rotate_dkim_signatures(&config, &rotation, now + DAY).await);
+    assert_eq!(get("rotation.state").await, None);
+
+    // A new key is staged while signing with the old one
+    now += 90 * DAY;
+    assert!(rotate_dkim_signatures(&config, &rotation, now).await);
+    assert_eq!(get("rotation.state").await.as_deref(), Some("staged"));
+    assert_eq!(get("rotation.due").await, Some((now + 7 * DAY).to_string()));
+    assert_eq!(get("private-key").await.as_deref(), Some("old-key"));
+    assert_eq!(get("selector").await.as_deref(), Some("old"));
+    let new_pk = get("rotation.private-key").await.unwrap();
+    let new_selector = get("rotation.selector").await.unwrap();
+    assert_ne!(new_pk, "old-key");
+    assert_ne!(new_selector, "old");
+    assert!(!rotate_dkim_signatures(&config, &rotation, now + DAY).await);
+
+    // After the grace period signing switches to the new key
+    now += 7 * DAY;
+    assert!(rotate_dkim_signatures(&config, &rotation, now).await);
+    assert_eq!(get("rotation.state").await.as_deref(), Some("switched"));
+    assert_eq!(get("rotation.due").await, Some((now + 7 * DAY).to_string()));
+    assert_eq!(get("private-key").await, Some(new_pk.clone()));
+    assert_eq!(get("selector").await, Some(new_selector.clone()));
+    assert_eq!(
+        get("rotation.private-key").await.as_deref(),
+        Some("old-key")
+    );
+    assert_eq!(get("rotation.selector").await.as_deref(), Some("old"));
+    assert!(!rotate_dkim_signatures(&config, &rotation, now + DAY).await);
+
+    // The old selector is retired and the next rotation scheduled
+    now += 7 * DAY;
+    assert!(rotate_dkim_signatures(&config, &rotation, now).await);
+    assert_eq!(get("rotation.state").await, None);
+    assert_eq!(get("rotation.private-key").await, None);
+    assert_eq!(get("rotation.selector").await, None);
+    assert_eq!(
+        get("rotation.due").await,
+        Some((now + 90 * DAY).to_string())
+    );
+    assert_eq!(get("private-key").await, Some(new_pk));
+    assert_eq!(get("selector").await, Some(new_selector));
+}
@@ -4,5 +4,9 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod dkim;
+pub mod password;
+pub mod quarantine;
 pub mod queue;
 pub mod report;
+pub mod scim;
@@ -0,0 +1,236 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use common::config::server::ServerProtocol;
+use directory::{
+    backend::internal::{
+        lookup::DirectoryStore, manage::ManageDirectory, PrincipalField, PrincipalUpdate,
+        PrincipalValue, SpecialSecrets,
+    },
+    core::policy::PasswordStatus,
+    Principal, QueryBy,
+};
+use reqwest::{header::AUTHORIZATION, Method, StatusCode};
+use serde_json::{json, Value};
+use smtp::core::SMTP;
+
+use crate::smtp::outbound::TestServer;
+
+const CONFIG: &str = r#"
+[storage]
+directory = "internal"
+
+[directory."internal"]
+type = "internal"
+store = "sqlite"
+
+[scim]
+token = "scim-secret"
+
+[authentication.password]
+min-length = 10
+expiry = "90d"
+grace-period = "1d"
+"#;
+
+const HASHED_SECRET: &str = "$6$rounds=5000$salt$hash";
+
+#[tokio::test]
+#[serial_test::serial]
+async fn password_self_service() {
+    let local = TestServer::new("smtp_password_test", CONFIG, true).await;
+    let core = local.build_smtp();
+    let store = core.core.storage.data.clone();
+    store.create_domain("example.org").await.unwrap();
+    let account_id = store
+        .create_account(
+            Principal {
+                name: "jane".to_string(),
+                secrets: vec!["Legacy-secret".to_string()],
+                emails: vec!["jane@example.org".to_string()],
+                ..Default::default()
+            },
+            vec![],
+        )
+        .await
+        .unwrap();
+    let _rx = local.start(&[ServerProtocol::Http]).await;
+
+    // Passwords set before expiry was enabled start ageing on their first login,
+    // logins differ in case so that cached sessions are not reused once it expires
+    assert_eq!(password_changed_at(&core, account_id).await, None);
+    let (status, _) = account_auth(Method::GET, "Jane", "Legacy-secret", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let changed_at = password_changed_at(&core, account_id).await;
+    assert!(changed_at.is_some());
+    assert_eq!(
+        core.core.jmap.password_policy.status(changed_at),
+        PasswordStatus::Valid
+    );
+
+    // Hashed passwords cannot be checked against the policy
+    let (_, response) = account_auth(
+        Method::POST,
+        "Jane",
+        "Legacy-secret",
+        json!([{"type": "setPassword", "password": HASHED_SECRET}]).into(),
+    )
+    .await;
+    assert_eq!(response["error"], "PasswordPolicy", "{response}");
+    let (_, user) = scim(
+        Method::POST,
+        "/Users",
+        json!({"userName": "john", "password": HASHED_SECRET}),
+    )
+    .await;
+    assert_eq!(user["scimType"], "invalidValue", "{user}");
+    let (status, _) = scim(
+        Method::POST,
+        "/Users",
+        json!({"userName": "john", "password": "Plain-secret"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // Expire the password
+    let secrets = store
+        .query(QueryBy::Id(account_id), false)
+        .await
+        .unwrap()
+        .unwrap()
+        .secrets
+        .into_iter()
+        .map(|secret| {
+            if secret.is_password_timestamp() {
+                "$changed$0".to_string()
+            } else {
+                secret
+            }
+        })
+        .collect::<Vec<_>>();
+    store
+        .update_account(
+            QueryBy::Id(account_id),
+            vec![PrincipalUpdate::set(
+                PrincipalField::Secrets,
+                PrincipalValue::StringList(secrets),
+            )],
+        )
+        .await
+        .unwrap();
+
+    // Expired passwords can only be used to set a new password
+    let (status, _) = account_auth(Method::GET, "jane", "Legacy-secret", None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (_, response) = account_auth(
+        Method::POST,
+        "jane",
+        "Legacy-secret",
+        json!([{"type": "addAppPassword", "name": "app", "password": "App-secret"}]).into(),
+    )
+    .await;
+    assert_eq!(response["error"], "Other", "{response}");
+    let (_, response) = account_auth(
+        Method::POST,
+        "jane",
+        "Legacy-secret",
+        json!([{"type": "setPassword", "password": "short"}]).into(),
+    )
+    .await;
+    assert_eq!(response["error"], "PasswordPolicy", "{response}");
+    let (status, response) = account_auth(
+        Method::POST,
+        "jane",
+        "Legacy-secret",
+        json!([{"type": "setPassword", "password": "Renewed-secret"}]).into(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(response.get("error").is_none(), "{response}");
+
+    // The new password is valid and the old one no longer works
+    let (status, _) = account_auth(Method::GET, "jane", "Renewed-secret", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = account_auth(Method::GET, "jane", "Legacy-secret", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        core.core
+            .jmap
+            .password_policy
+            .status(password_changed_at(&core, account_id).await),
+        PasswordStatus::Valid
+    );
+}
+
+async fn password_changed_at(core: &SMTP, account_id: u32) -> Option<u64> {
+    core.core
+        .storage
+        .data
+        .query(QueryBy::Id(account_id), false)
+        .await
+        .unwrap()
+        .unwrap()
+        .password_changed_at()
+}
+
+async fn account_auth(
+    method: Method,
+    login: &str,
+    secret: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    request(
+        method,
+        "/api/account/auth",
+        body,
+        &format!("Basic {}", STANDARD.encode(format!("{login}:{secret}"))),
+    )
+    .await
+}
+
+async fn scim(method: Method, path: &str, body: Value) -> (StatusCode, Value) {
+    request(
+        method,
+        &format!("/scim/v2{path}"),
+        body.into(),
+        "Bearer scim-secret",
+    )
+    .await
+}
+
+async fn request(
+    method: Method,
+    path: &str,
+    body: Option<Value>,
+    authorization: &str,
+) -> (StatusCode, Value) {
+    let mut request = reqwest::Client::builder()
+        .timeout(Duration::from_millis(500))
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap()
+        .request(method, format!("https://127.0.0.1:9980{path}"))
+        .header(AUTHORIZATION, authorization);
+    if let Some(body) = body {
+        request = request.body(body.to_string());
+    }
+
+    let response = request.send().await.unwrap();
+    let status = response.status();
+    let body = response.bytes().await.unwrap();
+
+    (
+        status,
+        if !body.is_empty() {
+            serde_json::from_slice(&body).unwrap_or(Value::Null)
+        } else {
+            Value::Null
+        },
+    )
+}
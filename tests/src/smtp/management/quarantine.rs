@@ -0,0 +1,211 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::{AHashMap, AHashSet};
+use common::config::server::ServerProtocol;
+use reqwest::Method;
+use store::write::now;
+
+use crate::{
+    jmap::ManagementApi,
+    smtp::{management::queue::List, outbound::TestServer, queue::quarantine::quarantine_message},
+};
+
+const CONFIG: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "admin"
+type = "admin"
+description = "Superuser"
+secret = "secret"
+class = "admin"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "12345"
+email = "john@foobar.org"
+
+[[directory."local".principals]]
+name = "jane"
+description = "Jane Doe"
+secret = "abcde"
+email = "jane@foobar.org"
+
+[session.rcpt]
+relay = true
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn manage_quarantine() {
+    /*tracing::subscriber::set_global_default(
+        tracing_subscriber::FmtSubscriber::builder()
+            .with_max_level(tracing::Level::TRACE)
+            .finish(),
+    )
+    .unwrap();*/
+
+    // Start local management interface
+    let local = TestServer::new("smtp_manage_quarantine", CONFIG, true).await;
+    let _rx = local.start(&[ServerProtocol::Http]).await;
+    let core = local.build_smtp();
+
+    // Quarantine test messages
+    let expires = now() + 86400;
+    for (name, rcpts) in [
+        ("john", vec!["john@foobar.org"]),
+        ("jane", vec!["jane@foobar.org"]),
+        ("both", vec!["john@foobar.org", "jane@foobar.org"]),
+    ] {
+        quarantine_message(&core, &rcpts, name, expires).await;
+    }
+    let admin = ManagementApi::default();
+    let john = ManagementApi::new(9980, "john", "12345");
+    let jane = ManagementApi::new(9980, "jane", "abcde");
+
+    // Administrators can see all messages
+    let all_ids = list_ids(&admin, "").await;
+    assert_eq!(all_ids.len(), 3);
+    let mut subject_to_id = AHashMap::new();
+    for id in &all_ids {
+        let message = admin
+            .request::<serde_json::Value>(Method::GET, &format!("/api/quarantine/{id}"))
+            .await
+            .unwrap()
+            .unwrap_data();
+        subject_to_id.insert(message["subject"].as_str().unwrap().to_string(), id.clone());
+    }
+    assert_eq!(subject_to_id.len(), 3);
+    let john_id = subject_to_id.get("john").unwrap();
+    let jane_id = subject_to_id.get("jane").unwrap();
+    let both_id = subject_to_id.get("both").unwrap();
+
+    // Users only see messages addressed to them
+    assert_eq!(
+        list_ids(&john, "").await,
+        AHashSet::from_iter([john_id.clone(), both_id.clone()])
+    );
+    assert_eq!(
+        list_ids(&jane, "").await,
+        AHashSet::from_iter([jane_id.clone(), both_id.clone()])
+    );
+    assert_eq!(
+        list_ids(&john, "?text=jane@foobar.org").await,
+        AHashSet::from_iter([both_id.clone()])
+    );
+
+    // Other recipients are hidden from users
+    let items = john
+        .request::<List<serde_json::Value>>(Method::GET, "/api/quarantine?values=true")
+        .await
+        .unwrap()
+        .unwrap_data()
+        .items;
+    assert_eq!(items.len(), 2);
+    for item in items {
+        assert_eq!(
+            item["recipients"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|rcpt| rcpt["address"].as_str().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["john@foobar.org"],
+            "{item:?}"
+        );
+    }
+
+    // Messages addressed to other users can't be read, previewed or deleted
+    for (method, path) in [
+        (Method::GET, format!("/api/quarantine/{jane_id}")),
+        (Method::GET, format!("/api/quarantine/{jane_id}/preview")),
+        (Method::DELETE, format!("/api/quarantine/{jane_id}")),
+        (Method::PATCH, format!("/api/quarantine/{jane_id}")),
+    ] {
+        assert!(
+            john.request::<serde_json::Value>(method, &path)
+                .await
+                .unwrap()
+                .try_unwrap_data()
+                .is_none(),
+            "{path}"
+        );
+    }
+
+    // Preview own message
+    let preview = john
+        .request::<serde_json::Value>(Method::GET, &format!("/api/quarantine/{john_id}/preview"))
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(
+        preview["headers"]
+            .as_str()
+            .unwrap()
+            .contains("Subject: john"),
+        "{preview:?}"
+    );
+    assert_eq!(
+        preview["preview"].as_str().unwrap().trim(),
+        "This message has been quarantined."
+    );
+
+    // Deleting a shared message only removes the user's copy
+    assert!(john
+        .request::<bool>(Method::DELETE, &format!("/api/quarantine/{both_id}"))
+        .await
+        .unwrap()
+        .unwrap_data());
+    assert_eq!(
+        list_ids(&john, "").await,
+        AHashSet::from_iter([john_id.clone()])
+    );
+    assert_eq!(
+        list_ids(&jane, "").await,
+        AHashSet::from_iter([jane_id.clone(), both_id.clone()])
+    );
+    let message = admin
+        .request::<serde_json::Value>(Method::GET, &format!("/api/quarantine/{both_id}"))
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(
+        message["recipients"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|rcpt| rcpt["address"].as_str().unwrap())
+            .collect::<Vec<_>>(),
+        vec!["jane@foobar.org"]
+    );
+
+    // Deleting the last recipient removes the message
+    assert!(jane
+        .request::<bool>(Method::DELETE, &format!("/api/quarantine/{both_id}"))
+        .await
+        .unwrap()
+        .unwrap_data());
+    assert_eq!(
+        list_ids(&admin, "").await,
+        AHashSet::from_iter([john_id.clone(), jane_id.clone()])
+    );
+}
+
+async fn list_ids(api: &ManagementApi, query: &str) -> AHashSet<String> {
+    api.request::<List<String>>(Method::GET, &format!("/api/quarantine{query}"))
+        .await
+        .unwrap()
+        .unwrap_data()
+        .items
+        .into_iter()
+        .collect()
+}
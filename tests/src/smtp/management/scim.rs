@@ -0,0 +1,317 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use common::config::server::ServerProtocol;
+use directory::{
+    backend::internal::{lookup::DirectoryStore, manage::ManageDirectory},
+    QueryBy,
+};
+use reqwest::{
+    header::{HeaderMap, AUTHORIZATION, ETAG, IF_MATCH, IF_NONE_MATCH, LOCATION},
+    Method, StatusCode,
+};
+use serde_json::{json, Value};
+use smtp::core::SMTP;
+
+use crate::smtp::outbound::TestServer;
+
+const CONFIG: &str = r#"
+[storage]
+directory = "internal"
+
+[directory."internal"]
+type = "internal"
+store = "sqlite"
+
+[scim]
+token = "scim-secret"
+"#;
+
+const EXTENSION: &str = "urn:ietf:params:scim:schemas:extension:stalwart:2.0:User";
+
+#[tokio::test]
+#[serial_test::serial]
+async fn scim_provisioning() {
+    let local = TestServer::new("smtp_scim_test", CONFIG, true).await;
+    let core = local.build_smtp();
+    core.core
+        .storage
+        .data
+        .create_domain("example.org")
+        .await
+        .unwrap();
+    let _rx = local.start(&[ServerProtocol::Http]).await;
+
+    // Bearer tokens are required
+    let (status, _, _) = request(Method::GET, "/Users", None, &[]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _, _) = request(
+        Method::GET,
+        "/ServiceProviderConfig",
+        None,
+        &[(AUTHORIZATION.as_str(), "Bearer wrong-secret")],
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _, config) = scim(Method::GET, "/ServiceProviderConfig", None, &[]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(config["patch"]["supported"], true);
+    assert_eq!(config["etag"]["supported"], true);
+
+    // Create a user
+    let (status, headers, user) = scim(
+        Method::POST,
+        "/Users",
+        Some(json!({
+            "schemas": ["urn:ietf:params:scim:schemas:core:2.0:User", EXTENSION],
+            "userName": "jdoe",
+            "displayName": "John Doe",
+            "password": "secret",
+            "emails": [
+                {"value": "john.doe@example.org"},
+                {"value": "jdoe@example.org", "primary": true},
+            ],
+            EXTENSION: {"quota": 1024},
+        })),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{user}");
+    let user_id = user["id"].as_str().unwrap().to_string();
+    assert_eq!(user["userName"], "jdoe");
+    assert_eq!(user["displayName"], "John Doe");
+    assert_eq!(user["active"], true);
+    assert_eq!(user["emails"][0]["value"], "jdoe@example.org");
+    assert_eq!(user["emails"][0]["primary"], true);
+    assert_eq!(user[EXTENSION]["quota"], 1024);
+    assert!(headers[LOCATION]
+        .to_str()
+        .unwrap()
+        .ends_with(&format!("/scim/v2/Users/{user_id}")));
+    let etag = headers[ETAG].to_str().unwrap().to_string();
+    assert_eq!(user["meta"]["version"], etag.as_str());
+    assert_password(&core, &user_id, "secret").await;
+
+    // Duplicate user names are rejected
+    let (status, _, error) = scim(
+        Method::POST,
+        "/Users",
+        Some(json!({"userName": "jdoe"})),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(error["scimType"], "uniqueness");
+
+    // Conditional requests
+    let (status, _, _) = scim(
+        Method::GET,
+        &format!("/Users/{user_id}"),
+        None,
+        &[(IF_NONE_MATCH.as_str(), &etag)],
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+    // Create a group with the user as a member
+    let (status, _, group) = scim(
+        Method::POST,
+        "/Groups",
+        Some(json!({
+            "displayName": "Sales",
+            "members": [{"value": user_id}],
+        })),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{group}");
+    let group_id = group["id"].as_str().unwrap().to_string();
+    assert_eq!(group["members"][0]["value"], user_id.as_str());
+    let (_, _, user) = scim(Method::GET, &format!("/Users/{user_id}"), None, &[]).await;
+    assert_eq!(user["groups"][0]["value"], group_id.as_str());
+    assert_eq!(user["groups"][0]["display"], "Sales");
+
+    // Patch the user
+    let (status, _, user) = scim(
+        Method::PATCH,
+        &format!("/Users/{user_id}"),
+        Some(json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+            "Operations": [
+                {"op": "replace", "value": {"active": "False", "displayName": "Johnny"}},
+                {"op": "remove", "path": "emails[value eq \"john.doe@example.org\"]"},
+                {"op": "add", "path": "emails", "value": [{"value": "johnny@example.org"}]},
+                {"op": "replace", "path": format!("{EXTENSION}:quota"), "value": 2048},
+                {"op": "replace", "path": "password", "value": "new-secret"},
+            ],
+        })),
+        &[(IF_MATCH.as_str(), &etag)],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{user}");
+    assert_eq!(user["active"], false);
+    assert_eq!(user["displayName"], "Johnny");
+    assert_eq!(user[EXTENSION]["quota"], 2048);
+    assert_eq!(
+        user["emails"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["value"].as_str().unwrap())
+            .collect::<Vec<_>>(),
+        vec!["jdoe@example.org", "johnny@example.org"]
+    );
+    assert_password(&core, &user_id, "new-secret").await;
+
+    // Stale versions are rejected
+    let (status, _, _) = scim(
+        Method::PATCH,
+        &format!("/Users/{user_id}"),
+        Some(json!({
+            "Operations": [{"op": "replace", "path": "active", "value": true}],
+        })),
+        &[(IF_MATCH.as_str(), &etag)],
+    )
+    .await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+
+    // Group memberships are updated through the group
+    let (status, _, group) = scim(
+        Method::PATCH,
+        &format!("/Groups/{group_id}"),
+        Some(json!({
+            "Operations": [{"op": "remove", "path": format!("members[value eq \"{user_id}\"]")}],
+        })),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{group}");
+    assert_eq!(group["members"].as_array().unwrap().len(), 0);
+    let (status, _, _) = scim(
+        Method::PATCH,
+        &format!("/Users/{user_id}"),
+        Some(json!({
+            "Operations": [{"op": "add", "path": "groups", "value": [{"value": group_id}]}],
+        })),
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Filtering and pagination
+    for name in ["alice", "bob", "carol"] {
+        let (status, _, _) = scim(
+            Method::POST,
+            "/Users",
+            Some(json!({
+                "userName": name,
+                "emails": [{"value": format!("{name}@example.org")}],
+            })),
+            &[],
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+    let (status, _, list) = scim(
+        Method::GET,
+        "/Users?filter=userName%20eq%20%22JDOE%22",
+        None,
+        &[],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list["totalResults"], 1);
+    assert_eq!(list["Resources"][0]["id"], user_id.as_str());
+    let (_, _, list) = scim(
+        Method::GET,
+        "/Users?filter=emails.value%20ew%20%22%40example.org%22%20and%20not%20(active%20eq%20false)",
+        None,
+        &[],
+    )
+    .await;
+    assert_eq!(list["totalResults"], 3, "{list}");
+    let (_, _, list) = scim(Method::GET, "/Users?startIndex=2&count=2", None, &[]).await;
+    assert_eq!(list["totalResults"], 4);
+    assert_eq!(list["startIndex"], 2);
+    assert_eq!(list["itemsPerPage"], 2);
+    let (status, _, error) = scim(Method::GET, "/Users?filter=userName%20xx", None, &[]).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error["scimType"], "invalidFilter");
+
+    // Delete the user
+    let (status, _, _) = scim(Method::DELETE, &format!("/Users/{user_id}"), None, &[]).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _, _) = scim(Method::GET, &format!("/Users/{user_id}"), None, &[]).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _, _) = scim(Method::GET, &format!("/Users/{group_id}"), None, &[]).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+// Passwords received in plain text are stored hashed
+async fn assert_password(core: &SMTP, user_id: &str, password: &str) {
+    let principal = core
+        .core
+        .storage
+        .data
+        .query(QueryBy::Id(user_id.parse().unwrap()), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!principal.secrets.iter().any(|secret| secret == password));
+    assert!(principal
+        .secrets
+        .iter()
+        .any(|secret| secret.starts_with("$6$")));
+    assert!(principal.verify_secret(password).await.unwrap());
+}
+
+async fn scim(
+    method: Method,
+    path: &str,
+    body: Option<Value>,
+    headers: &[(&str, &str)],
+) -> (StatusCode, HeaderMap, Value) {
+    let mut headers = headers.to_vec();
+    headers.push((AUTHORIZATION.as_str(), "Bearer scim-secret"));
+    request(method, path, body, &headers).await
+}
+
+async fn request(
+    method: Method,
+    path: &str,
+    body: Option<Value>,
+    headers: &[(&str, &str)],
+) -> (StatusCode, HeaderMap, Value) {
+    let mut request = reqwest::Client::builder()
+        .timeout(Duration::from_millis(500))
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap()
+        .request(method, format!("https://127.0.0.1:9980/scim/v2{path}"));
+    if let Some(body) = body {
+        request = request.body(body.to_string());
+    }
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+
+    let response = request.send().await.unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.bytes().await.unwrap();
+
+    (
+        status,
+        headers,
+        if !body.is_empty() {
+            serde_json::from_slice(&body).unwrap()
+        } else {
+            Value::Null
+        },
+    )
+}
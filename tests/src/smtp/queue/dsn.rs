@@ -12,7 +12,7 @@ use utils::BlobHash;
 
 use crate::smtp::{inbound::sign::SIGNATURES, outbound::TestServer, QueueReceiver};
 use smtp::queue::{
-    Domain, Error, ErrorDetails, HostResponse, Message, Recipient, Schedule, Status,
+    Domain, Error, ErrorDetails, HostResponse, Message, Recipient, Schedule, Status, RCPT_DSN_SENT,
 };
 
 const CONFIG: &str = r#"
@@ -157,6 +157,241 @@ async fn generate_dsn() {
     assert_eq!(queue.len(), 4);
 }
 
+const CONFIG_TEMPLATE: &str = r#"
+[report.dsn.template.es]
+subject.failure = "No se pudo entregar: {subject}"
+intro.failure = "Su mensaje no pudo ser entregado a: {recipients}"
+recipient.failure = "<{address}> ({remote_mta}: {response})"
+footer = "Enviado por {reporting_mta}"
+
+[report.dsn.template.acme-es]
+subject.failure = "Acme: no se pudo entregar: {subject}"
+footer = "Soporte de Acme"
+
+[report.dsn.domain."acme.org"]
+template = "acme"
+"#;
+
+#[tokio::test]
+async fn generate_localized_dsn() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("resources");
+    path.push("smtp");
+    path.push("dsn");
+    path.push("original.txt");
+    let dsn_original = fs::read_to_string(&path).unwrap();
+
+    let mut message = failed_message("sender@foobar.org", "foobar@example.org");
+    message.size = dsn_original.len();
+    message.blob_hash = BlobHash::from(dsn_original.as_bytes());
+    let span = tracing::span!(tracing::Level::INFO, "hi");
+
+    // Load config
+    let mut local = TestServer::new(
+        "smtp_dsn_template_test",
+        CONFIG.replace("[report.dsn]\n", "[report.dsn]\nlanguage = \"'es-ES'\"\n")
+            + CONFIG_TEMPLATE
+            + SIGNATURES,
+        true,
+    )
+    .await;
+    let core = local.build_smtp();
+    let qr = &mut local.qr;
+    qr.blob_store
+        .put_blob(message.blob_hash.as_slice(), dsn_original.as_bytes())
+        .await
+        .unwrap();
+
+    // Localized failure DSN
+    core.send_dsn(&mut message, &span).await;
+    let dsn_message = qr.expect_message().await;
+    let dsn = String::from_utf8(
+        qr.blob_store
+            .get_blob(dsn_message.blob_hash.as_slice(), 0..usize::MAX)
+            .await
+            .unwrap()
+            .unwrap(),
+    )
+    .unwrap();
+    for expected in [
+        "Subject: No se pudo entregar: Status of: Re: Battery current sense\r\n",
+        "Su mensaje no pudo ser entregado a: foobar@example.org\r\n\r\n",
+        "<foobar@example.org> (mx.example.org: 550 5.1.2 User does not exist)\r\n",
+        "Enviado por mx.example.org\r\n",
+        "Status: 5.1.2\r\n",
+    ] {
+        assert!(dsn.contains(expected), "{expected:?} not found in {dsn}");
+    }
+
+    // Senders from domains with their own templates receive those instead
+    let mut message = failed_message("sender@acme.org", "foobar@example.org");
+    message.size = dsn_original.len();
+    message.blob_hash = BlobHash::from(dsn_original.as_bytes());
+    core.send_dsn(&mut message, &span).await;
+    let dsn_message = qr.expect_message().await;
+    let dsn = String::from_utf8(
+        qr.blob_store
+            .get_blob(dsn_message.blob_hash.as_slice(), 0..usize::MAX)
+            .await
+            .unwrap()
+            .unwrap(),
+    )
+    .unwrap();
+    for expected in [
+        "Subject: Acme: no se pudo entregar: Status of: Re: Battery current sense\r\n",
+        "Soporte de Acme\r\n",
+    ] {
+        assert!(dsn.contains(expected), "{expected:?} not found in {dsn}");
+    }
+    assert!(!dsn.contains("Enviado por"), "{dsn}");
+}
+
+const CONFIG_DIGEST: &str = r#"
+[report.dsn.digest]
+enable = true
+failure = [{if = "sender_domain = 'individual.org'", then = false},
+           {else = true}]
+"#;
+
+#[tokio::test]
+async fn generate_dsn_digest() {
+    let span = tracing::span!(tracing::Level::INFO, "hi");
+    let mut local = TestServer::new(
+        "smtp_dsn_digest_test",
+        CONFIG.to_string() + CONFIG_DIGEST + SIGNATURES,
+        true,
+    )
+    .await;
+    let core = local.build_smtp();
+    let qr = &mut local.qr;
+
+    // Failures for senders with digests enabled are not sent individually
+    for rcpt in ["jane@example.org", "john@example.org"] {
+        let mut message = failed_message("sender@foobar.org", rcpt);
+        core.send_dsn(&mut message, &span).await;
+        assert!(message.recipients[0].has_flag(RCPT_DSN_SENT));
+    }
+    qr.assert_no_events();
+
+    // Other senders receive individual notifications
+    let mut message = failed_message("sender@individual.org", "bill@example.org");
+    core.send_dsn(&mut message, &span).await;
+    let dsn_message = qr.expect_message().await;
+    assert_eq!(dsn_message.recipients[0].address, "sender@individual.org");
+
+    // Send digest
+    core.send_dsn_digests().await;
+    let dsn_message = qr.expect_message().await;
+    assert_eq!(dsn_message.recipients[0].address, "sender@foobar.org");
+    let dsn = String::from_utf8(
+        qr.blob_store
+            .get_blob(dsn_message.blob_hash.as_slice(), 0..usize::MAX)
+            .await
+            .unwrap()
+            .unwrap(),
+    )
+    .unwrap();
+    for expected in [
+        "Final-Recipient: rfc822;jane@example.org\r\n",
+        "Final-Recipient: rfc822;john@example.org\r\n",
+    ] {
+        assert!(dsn.contains(expected), "{expected:?} not found in {dsn}");
+    }
+    assert!(!dsn.contains("bill@example.org"));
+    assert!(!dsn.contains("message/rfc822"));
+
+    // Delayed recipients are listed once, regardless of the number of notify cycles
+    let mut message = delayed_message("sender@foobar.org", "delayed@example.org");
+    for _ in 0..2 {
+        message.domains[0].notify = Schedule::now();
+        core.send_dsn(&mut message, &span).await;
+    }
+    qr.assert_no_events();
+    core.send_dsn_digests().await;
+    let dsn_message = qr.expect_message().await;
+    assert_eq!(dsn_message.recipients[0].address, "sender@foobar.org");
+    let dsn = String::from_utf8(
+        qr.blob_store
+            .get_blob(dsn_message.blob_hash.as_slice(), 0..usize::MAX)
+            .await
+            .unwrap()
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        dsn.matches("Final-Recipient: rfc822;delayed@example.org\r\n")
+            .count(),
+        1,
+        "{dsn}"
+    );
+
+    // Digest entries are removed once sent
+    core.send_dsn_digests().await;
+    qr.assert_no_events();
+}
+
+fn delayed_message(return_path: &str, rcpt: &str) -> Message {
+    let mut message = failed_message(return_path, rcpt);
+    message.recipients[0].status = Status::TemporaryFailure(HostResponse {
+        hostname: ErrorDetails {
+            entity: "mx.example.org".to_string(),
+            details: format!("RCPT TO:<{rcpt}>"),
+        },
+        response: Response {
+            code: 450,
+            esc: [4, 2, 2],
+            message: "Mailbox full".to_string(),
+        },
+    });
+    message.recipients[0].flags = RCPT_NOTIFY_DELAY;
+    message.domains[0].status = Status::TemporaryFailure(Error::ConnectionError(ErrorDetails {
+        entity: "mx.example.org".to_string(),
+        details: "Connection timeout".to_string(),
+    }));
+    message
+}
+
+fn failed_message(return_path: &str, rcpt: &str) -> Message {
+    Message {
+        size: 0,
+        id: 0,
+        created: now(),
+        return_path: return_path.to_string(),
+        return_path_lcase: return_path.to_string(),
+        return_path_domain: return_path.split_once('@').unwrap().1.to_string(),
+        recipients: vec![Recipient {
+            domain_idx: 0,
+            address: rcpt.to_string(),
+            address_lcase: rcpt.to_string(),
+            status: Status::PermanentFailure(HostResponse {
+                hostname: ErrorDetails {
+                    entity: "mx.example.org".to_string(),
+                    details: format!("RCPT TO:<{rcpt}>"),
+                },
+                response: Response {
+                    code: 550,
+                    esc: [5, 1, 2],
+                    message: "User does not exist".to_string(),
+                },
+            }),
+            flags: RCPT_NOTIFY_FAILURE,
+            orcpt: None,
+        }],
+        domains: vec![Domain {
+            domain: "example.org".to_string(),
+            retry: Schedule::now(),
+            notify: Schedule::now(),
+            expires: now() + 10,
+            status: Status::Scheduled,
+        }],
+        flags: 0,
+        env_id: None,
+        priority: 0,
+        blob_hash: BlobHash::from(rcpt.as_bytes()),
+        quota_keys: vec![],
+    }
+}
+
 impl QueueReceiver {
     async fn compare_dsn(&self, message: Message, test: &str) {
         let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
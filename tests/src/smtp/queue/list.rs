@@ -0,0 +1,479 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{
+    config::smtp::list::{FromRewrite, ListCommand, ListPolicy, ListSettings},
+    DeliveryResult,
+};
+use directory::{backend::internal::manage::ManageDirectory, Principal, QueryBy, Type};
+use utils::BlobHash;
+
+use crate::smtp::{inbound::TestMessage, outbound::TestServer, session::VerifyResponse};
+use smtp::{
+    core::SMTP,
+    queue::{
+        list::{parse_dsn_failures, ListSubscription, SubscriptionStatus},
+        Message, MAIL_SENDER_VERIFIED,
+    },
+};
+
+const CONFIG: &str = r#"
+[list]
+secret = "list-secret"
+bounce.threshold = 2
+"#;
+
+const MESSAGE: &str = concat!(
+    "From: John Doe <john@example.net>\r\n",
+    "To: team@foobar.org\r\n",
+    "Subject: Hello\r\n",
+    "List-Id: Other <other.example.net>\r\n",
+    "\r\n",
+    "Hi everyone!\r\n"
+);
+
+#[tokio::test]
+async fn mailing_list() {
+    let mut local = TestServer::new("smtp_list_test", CONFIG, true).await;
+    let core = local.build_smtp();
+    let qr = &mut local.qr;
+    let span = tracing::info_span!("list_test");
+
+    // Lists are backed by directory principals
+    let store = &core.core.storage.data;
+    store
+        .create_account(
+            Principal {
+                typ: Type::Individual,
+                name: "sam".to_string(),
+                emails: vec!["sam@foobar.org".to_string()],
+                ..Default::default()
+            },
+            vec![],
+        )
+        .await
+        .unwrap();
+    let mut list_ids = Vec::new();
+    for (name, description, settings) in [
+        (
+            "team",
+            "Team",
+            ListSettings {
+                policy: ListPolicy::Moderated,
+                moderators: vec!["Jane@foobar.org".to_string()],
+                subject_prefix: "[team]".to_string().into(),
+                rewrite_from: FromRewrite::Always,
+                archive: "archive@foobar.org".to_string().into(),
+                ..Default::default()
+            },
+        ),
+        (
+            "news",
+            "News",
+            ListSettings {
+                policy: ListPolicy::AnnounceOnly,
+                moderators: vec!["jane@foobar.org".to_string()],
+                one_click: false,
+                ..Default::default()
+            },
+        ),
+        (
+            "dev",
+            "Developers",
+            ListSettings {
+                policy: ListPolicy::MembersOnly,
+                one_click: false,
+                ..Default::default()
+            },
+        ),
+    ] {
+        let list_id = store
+            .create_account(
+                Principal {
+                    typ: Type::List,
+                    name: name.to_string(),
+                    description: description.to_string().into(),
+                    emails: vec![format!("{name}@foobar.org")],
+                    ..Default::default()
+                },
+                vec!["sam".to_string()],
+            )
+            .await
+            .unwrap();
+        core.set_list_settings(list_id, settings.into())
+            .await
+            .unwrap();
+        list_ids.push(list_id);
+    }
+    store
+        .create_account(
+            Principal {
+                typ: Type::List,
+                name: "plain".to_string(),
+                emails: vec!["plain@foobar.org".to_string()],
+                ..Default::default()
+            },
+            vec!["sam".to_string()],
+        )
+        .await
+        .unwrap();
+    let mut stored_ids = core.read_list_ids().await.unwrap();
+    stored_ids.sort_unstable();
+    assert_eq!(stored_ids, list_ids);
+
+    // Parse list addresses
+    let lists = &core.core;
+    for (address, command) in [
+        ("team@foobar.org", ListCommand::Post),
+        ("team+subscribe@foobar.org", ListCommand::Subscribe),
+        (
+            "team+approve-123-abc@foobar.org",
+            ListCommand::Approve {
+                id: 123,
+                token: "abc".to_string(),
+            },
+        ),
+    ] {
+        assert_eq!(
+            lists.mailing_list(address).await.unwrap().unwrap().1,
+            command
+        );
+    }
+    for address in [
+        "team+unknown@foobar.org",
+        "other@foobar.org",
+        "sam@foobar.org",
+        "plain@foobar.org",
+    ] {
+        assert!(lists.mailing_list(address).await.unwrap().is_none());
+    }
+    let team = lists
+        .mailing_list("team@foobar.org")
+        .await
+        .unwrap()
+        .unwrap()
+        .0;
+    let news = lists
+        .mailing_list("news@foobar.org")
+        .await
+        .unwrap()
+        .unwrap()
+        .0;
+    assert_eq!(team.name, "Team");
+    assert_eq!(team.moderators, vec!["jane@foobar.org"]);
+
+    // Add subscribers
+    for (address, status) in [
+        ("bill@example.net", SubscriptionStatus::Active),
+        ("mike@example.net", SubscriptionStatus::Active),
+        ("tom@example.net", SubscriptionStatus::Disabled),
+    ] {
+        core.set_list_subscription(&team, address, ListSubscription::new(status).into())
+            .await
+            .unwrap();
+    }
+    let mut recipients = core.list_recipients(&team).await.unwrap();
+    recipients.sort_unstable();
+    assert_eq!(
+        recipients,
+        vec!["bill@example.net", "mike@example.net", "sam@foobar.org"]
+    );
+
+    // Posts from non-moderators to announce-only lists are rejected
+    let message = store_message(&core, "john@example.net", MESSAGE).await;
+    assert!(matches!(
+        deliver(&core, &message, "news@foobar.org", &span).await,
+        DeliveryResult::PermanentFailure { .. }
+    ));
+
+    // The From header is not trusted to identify moderators or members
+    for (rcpt, from) in [
+        ("news@foobar.org", "jane@foobar.org"),
+        ("dev@foobar.org", "sam@foobar.org"),
+    ] {
+        let message = store_message(
+            &core,
+            from,
+            &format!("From: {from}\r\nSubject: Spoofed\r\n\r\nSpoofed\r\n"),
+        )
+        .await;
+        assert!(matches!(
+            deliver(&core, &message, rcpt, &span).await,
+            DeliveryResult::PermanentFailure { .. }
+        ));
+    }
+
+    // Verified members can post to members-only lists
+    let mut message = store_message(
+        &core,
+        "sam@foobar.org",
+        "From: sam@foobar.org\r\nSubject: Dev\r\n\r\nDev\r\n",
+    )
+    .await;
+    message.flags |= MAIL_SENDER_VERIFIED;
+    assert!(matches!(
+        deliver(&core, &message, "dev@foobar.org", &span).await,
+        DeliveryResult::Success
+    ));
+    let message = qr.expect_message().await;
+    assert_eq!(message.recipients[0].address, "sam@foobar.org");
+    assert_eq!(message.return_path, "dev+bounces@foobar.org");
+
+    // Posts to moderated lists are held and the moderators notified
+    let message = store_message(&core, "john@example.net", MESSAGE).await;
+    assert!(matches!(
+        deliver(&core, &message, "team@foobar.org", &span).await,
+        DeliveryResult::Success
+    ));
+    let notification = qr.expect_message().await;
+    assert_eq!(notification.recipients[0].address, "jane@foobar.org");
+    assert_eq!(notification.return_path, "team+bounces@foobar.org");
+    let mut held = Vec::new();
+    core.read_held_messages(|message| {
+        held.push(message);
+        true
+    })
+    .await
+    .unwrap();
+    assert_eq!(held.len(), 1);
+    let held = held.pop().unwrap();
+    notification
+        .read_lines(qr)
+        .await
+        .assert_contains(&format!("approve-{}-", held.id));
+
+    // Invalid moderation tokens are rejected
+    let approve = store_message(&core, "jane@foobar.org", "Subject: ok\r\n\r\nok\r\n").await;
+    assert!(matches!(
+        deliver(
+            &core,
+            &approve,
+            &format!("team+approve-{}-0123456789abcdef@foobar.org", held.id),
+            &span
+        )
+        .await,
+        DeliveryResult::PermanentFailure { .. }
+    ));
+
+    // Approve the message
+    let token = core.list_token(&team, &["moderate", &held.id.to_string()]);
+    assert!(matches!(
+        deliver(
+            &core,
+            &approve,
+            &format!("team+approve-{}-{}@foobar.org", held.id, token),
+            &span
+        )
+        .await,
+        DeliveryResult::Success
+    ));
+    let mut rcpts = Vec::new();
+    for _ in 0..4 {
+        let message = qr.expect_message().await;
+        assert_eq!(message.return_path, "team+bounces@foobar.org");
+        let contents = message.read_message(qr).await;
+        assert!(contents.contains("Subject: [team] Hello\r\n"), "{contents}");
+        assert!(
+            contents.contains("List-Id: \"Team\" <team.foobar.org>\r\n"),
+            "{contents}"
+        );
+        assert!(contents.contains("List-Post: <mailto:team@foobar.org>\r\n"));
+        assert!(contents.contains("List-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n"));
+        assert!(contents.contains("From: \"John Doe via Team\" <team@foobar.org>\r\n"));
+        assert!(contents.contains("Reply-To: \"John Doe\" <john@example.net>\r\n"));
+        assert!(!contents.contains("other.example.net"));
+        rcpts.push(message.recipients[0].address.clone());
+    }
+    rcpts.sort_unstable();
+    assert_eq!(
+        rcpts,
+        vec![
+            "archive@foobar.org",
+            "bill@example.net",
+            "mike@example.net",
+            "sam@foobar.org"
+        ]
+    );
+    let mut held = Vec::new();
+    core.read_held_messages(|message| {
+        held.push(message);
+        true
+    })
+    .await
+    .unwrap();
+    assert!(held.is_empty());
+
+    // Messages distributed by the list are not redistributed
+    let looped = store_message(
+        &core,
+        "bill@example.net",
+        "From: bill@example.net\r\nList-Id: Team <team.foobar.org>\r\n\r\nloop\r\n",
+    )
+    .await;
+    assert!(matches!(
+        deliver(&core, &looped, "team@foobar.org", &span).await,
+        DeliveryResult::Success
+    ));
+    qr.assert_no_events();
+
+    // Subscription requests require confirmation
+    let request = store_message(&core, "jim@example.net", "Subject: subscribe\r\n\r\n").await;
+    assert!(matches!(
+        deliver(&core, &request, "team+subscribe@foobar.org", &span).await,
+        DeliveryResult::Success
+    ));
+    let confirmation = qr.expect_message().await;
+    assert_eq!(confirmation.recipients[0].address, "jim@example.net");
+    let token = core.list_token(&team, &["subscribe", "jim@example.net"]);
+    confirmation
+        .read_lines(qr)
+        .await
+        .assert_contains(&format!("team+confirm-{token}@foobar.org"));
+    assert!(matches!(
+        deliver(
+            &core,
+            &request,
+            &format!("team+confirm-{token}@foobar.org"),
+            &span
+        )
+        .await,
+        DeliveryResult::Success
+    ));
+    assert!(core
+        .list_recipients(&team)
+        .await
+        .unwrap()
+        .contains(&"jim@example.net".to_string()));
+
+    // Unsubscribe
+    assert!(matches!(
+        deliver(&core, &request, "team+unsubscribe@foobar.org", &span).await,
+        DeliveryResult::Success
+    ));
+    assert!(!core
+        .list_recipients(&team)
+        .await
+        .unwrap()
+        .contains(&"jim@example.net".to_string()));
+
+    // Bounces disable subscribers after reaching the threshold
+    let dsn = concat!(
+        "From: MAILER-DAEMON@example.net\r\n",
+        "Subject: Delivery Status Notification\r\n",
+        "MIME-Version: 1.0\r\n",
+        "Content-Type: multipart/report; report-type=delivery-status; boundary=\"b\"\r\n",
+        "\r\n",
+        "--b\r\n",
+        "Content-Type: text/plain\r\n",
+        "\r\n",
+        "Delivery failed.\r\n",
+        "--b\r\n",
+        "Content-Type: message/delivery-status\r\n",
+        "\r\n",
+        "Reporting-MTA: dns;mx.example.net\r\n",
+        "\r\n",
+        "Final-Recipient: rfc822;Bill@Example.net\r\n",
+        "Action: failed\r\n",
+        "Status: 5.1.1\r\n",
+        "\r\n",
+        "Final-Recipient: rfc822;<mike@example.net>\r\n",
+        "Action: delayed\r\n",
+        "Status: 4.4.7\r\n",
+        "\r\n",
+        "Final-Recipient: rfc822;<unknown@example.net>\r\n",
+        "Action: failed\r\n",
+        "Status: 5.2.2\r\n",
+        "--b--\r\n",
+    );
+    assert_eq!(
+        parse_dsn_failures(dsn.as_bytes()),
+        vec!["bill@example.net", "unknown@example.net"]
+    );
+    let bounce = store_message(&core, "", dsn).await;
+    for _ in 0..2 {
+        assert!(matches!(
+            deliver(&core, &bounce, "team+bounces@foobar.org", &span).await,
+            DeliveryResult::Success
+        ));
+    }
+    assert_eq!(
+        core.get_list_subscription(&team, "bill@example.net")
+            .await
+            .unwrap()
+            .unwrap()
+            .status,
+        SubscriptionStatus::Disabled
+    );
+    assert!(core
+        .get_list_subscription(&team, "unknown@example.net")
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        core.list_recipients(&team).await.unwrap(),
+        vec!["mike@example.net", "sam@foobar.org"]
+    );
+
+    // Moderators can post to announce-only lists
+    core.set_list_subscription(
+        &news,
+        "bill@example.net",
+        ListSubscription::new(SubscriptionStatus::Active).into(),
+    )
+    .await
+    .unwrap();
+    let mut message = store_message(
+        &core,
+        "jane@foobar.org",
+        "From: jane@foobar.org\r\nSubject: News\r\n\r\nNews\r\n",
+    )
+    .await;
+    message.flags |= MAIL_SENDER_VERIFIED;
+    assert!(matches!(
+        deliver(&core, &message, "news@foobar.org", &span).await,
+        DeliveryResult::Success
+    ));
+    let contents = qr.expect_message().await.read_message(qr).await;
+    assert!(contents.contains("List-Post: NO\r\n"));
+    assert!(contents.contains("List-Unsubscribe: <mailto:news+unsubscribe@foobar.org>\r\n"));
+    assert!(!contents.contains("List-Unsubscribe-Post"));
+
+    // Deleting the list principal removes its settings and subscribers
+    store.delete_account(QueryBy::Name("news")).await.unwrap();
+    assert!(core
+        .core
+        .mailing_list("news@foobar.org")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(core
+        .read_list_subscriptions(&news)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+async fn deliver(
+    core: &SMTP,
+    message: &Message,
+    rcpt: &str,
+    span: &tracing::Span,
+) -> DeliveryResult {
+    let (list, command) = core.core.mailing_list(rcpt).await.unwrap().unwrap();
+    core.deliver_list(message, list, command, span).await
+}
+
+async fn store_message(core: &SMTP, sender: &str, contents: &str) -> Message {
+    let mut message = core.new_message(sender, sender, "");
+    message.blob_hash = BlobHash::from(contents.as_bytes());
+    core.core
+        .storage
+        .blob
+        .put_blob(message.blob_hash.as_slice(), contents.as_bytes())
+        .await
+        .unwrap();
+    message
+}
@@ -6,5 +6,7 @@
 
 pub mod concurrent;
 pub mod dsn;
+pub mod list;
 pub mod manager;
+pub mod quarantine;
 pub mod retry;
@@ -0,0 +1,207 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use ahash::AHashMap;
+use smtp::{
+    core::SMTP,
+    queue::quarantine::{QuarantineReason, QuarantineSource, QuarantinedMessage},
+};
+use store::write::{now, ReportClass};
+
+use crate::smtp::{
+    inbound::{sign::SIGNATURES, TestMessage, TestQueueEvent},
+    outbound::TestServer,
+};
+
+const CONFIG: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = "john@foobar.org"
+
+[[directory."local".principals]]
+name = "jane"
+description = "Jane Doe"
+secret = "secret"
+email = "jane@foobar.org"
+
+[session.rcpt]
+relay = true
+
+[quarantine.digest]
+enable = true
+from-name = "'Quarantine'"
+from-address = "'quarantine@foobar.org'"
+subject = "'Your quarantined messages'"
+sign = "['rsa']"
+"#;
+
+#[tokio::test]
+async fn quarantine_digest() {
+    /*tracing::subscriber::set_global_default(
+        tracing_subscriber::FmtSubscriber::builder()
+            .with_max_level(tracing::Level::TRACE)
+            .finish(),
+    )
+    .unwrap();*/
+
+    let mut local = TestServer::new(
+        "smtp_quarantine_digest",
+        CONFIG.to_string() + SIGNATURES,
+        true,
+    )
+    .await;
+    let core = local.build_smtp();
+
+    // Quarantine messages for local and remote recipients
+    let expires = now() + 86400;
+    quarantine_message(&core, &["john@foobar.org"], "Invoice overdue", expires).await;
+    quarantine_message(
+        &core,
+        &["john@foobar.org", "jane@foobar.org"],
+        "You won a prize",
+        expires,
+    )
+    .await;
+    quarantine_message(&core, &["bill@example.org"], "Remote recipient", expires).await;
+
+    // Expect one digest per local recipient
+    core.send_quarantine_digest().await;
+    for _ in 0..2 {
+        local.qr.read_event().await.assert_reload();
+    }
+    local.qr.assert_no_events();
+    let mut digests = AHashMap::new();
+    for message in local.qr.read_queued_messages().await {
+        assert_eq!(message.return_path, "quarantine@foobar.org");
+        assert_eq!(message.recipients.len(), 1);
+        digests.insert(
+            message.recipients[0].address.clone(),
+            message.read_message(&local.qr).await,
+        );
+    }
+    assert_eq!(digests.len(), 2);
+
+    let digest = digests.get("john@foobar.org").unwrap();
+    assert!(
+        digest.contains("Subject: Your quarantined messages"),
+        "{digest}"
+    );
+    assert!(digest.contains("2 message(s)"), "{digest}");
+    assert!(digest.contains("Subject: Invoice overdue"), "{digest}");
+    assert!(digest.contains("Subject: You won a prize"), "{digest}");
+    assert!(!digest.contains("Remote recipient"), "{digest}");
+    let digest = digests.get("jane@foobar.org").unwrap();
+    assert!(digest.contains("1 message(s)"), "{digest}");
+    assert!(digest.contains("Subject: You won a prize"), "{digest}");
+    assert!(!digest.contains("Invoice overdue"), "{digest}");
+
+    // Messages are only included in one digest
+    let mut total = 0;
+    core.read_quarantined_messages(|message| {
+        assert!(message.digest_sent, "{message:?}");
+        total += 1;
+        true
+    })
+    .await
+    .unwrap();
+    assert_eq!(total, 3);
+    core.send_quarantine_digest().await;
+    local.qr.assert_no_events();
+
+    // Expired messages are no longer listed and are removed on purge
+    let id = quarantine_message(&core, &["john@foobar.org"], "Expiring soon", now() + 1).await;
+    let blob_hash = core
+        .read_quarantined_message(id.clone())
+        .await
+        .unwrap()
+        .message
+        .blob_hash;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    core.read_quarantined_messages(|message| {
+        assert_ne!(message.subject, "Expiring soon");
+        true
+    })
+    .await
+    .unwrap();
+    core.send_quarantine_digest().await;
+    local.qr.assert_no_events();
+    core.core.storage.data.purge_store().await.unwrap();
+    core.core
+        .storage
+        .data
+        .purge_blobs(core.core.storage.blob.clone())
+        .await
+        .unwrap();
+    assert!(core.read_quarantined_message(id).await.is_none());
+    assert!(core
+        .core
+        .storage
+        .blob
+        .get_blob(blob_hash.as_slice(), 0..usize::MAX)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+pub async fn quarantine_message(
+    core: &SMTP,
+    rcpts: &[&str],
+    subject: &str,
+    expires: u64,
+) -> ReportClass {
+    let mut message = core.new_message("sender@example.org", "sender@example.org", "example.org");
+    for rcpt in rcpts {
+        message.add_recipient(*rcpt, core).await;
+    }
+    let quarantined = QuarantinedMessage {
+        message,
+        expires,
+        from: String::new(),
+        subject: String::new(),
+        remote_ip: "10.0.0.1".parse().unwrap(),
+        authenticated_as: String::new(),
+        reasons: vec![QuarantineReason::new(
+            QuarantineSource::Sieve,
+            "Suspicious content",
+        )],
+        digest_sent: false,
+    };
+    let id = quarantined.id();
+    let raw_message = format!(
+        concat!(
+            "From: Sender <sender@example.org>\r\n",
+            "To: {}\r\n",
+            "Subject: {}\r\n",
+            "\r\n",
+            "This message has been quarantined.\r\n"
+        ),
+        rcpts.join(", "),
+        subject
+    );
+
+    assert!(
+        quarantined
+            .store(
+                None,
+                raw_message.as_bytes(),
+                core,
+                &tracing::info_span!("quarantine-test")
+            )
+            .await
+    );
+
+    id
+}